@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::coordinate::Coordinate;
+use crate::grammar::Grammar;
+
+// Cells refer to an asset by writing `asset:<name>` in their text
+pub const ASSET_PREFIX: &str = "asset:";
+
+// Asset is an image, driver file or other binary blob that cells of a session refer to.
+// `data` is the base64 content: saved in the session file in web builds, and to a sidecar
+// folder next to the session file in Electron (see `sidecar_dir`), in which case the
+// session file only lists the asset.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Asset {
+    pub name: String,
+    pub mime: String,
+    #[serde(default)]
+    pub data: String,
+}
+
+impl Asset {
+    pub fn new(name: String, mime: String, content: &[u8]) -> Asset {
+        Asset {
+            name,
+            mime,
+            data: base64::encode(content),
+        }
+    }
+
+    pub fn bytes(&self) -> Option<Vec<u8>> {
+        base64::decode(&self.data).ok()
+    }
+
+    pub fn is_image(&self) -> bool {
+        self.mime.starts_with("image/")
+    }
+
+    pub fn data_url(&self) -> String {
+        format! {"data:{};base64,{}", self.mime, self.data}
+    }
+}
+
+// The names of the assets referenced in a piece of text
+pub fn references_in(text: &str) -> Vec<&str> {
+    let mut names = vec![];
+    let mut rest = text;
+    while let Some(start) = rest.find(ASSET_PREFIX) {
+        let after = &rest[start + ASSET_PREFIX.len()..];
+        let end = after
+            .find(|c: char| !(c.is_alphanumeric() || c == '.' || c == '-' || c == '_'))
+            .unwrap_or(after.len());
+        // a trailing '.' ends a sentence rather than the name
+        let name = after[..end].trim_end_matches('.');
+        if !name.is_empty() {
+            names.push(name);
+        }
+        rest = &after[end..];
+    }
+    names
+}
+
+// How many times each asset is referenced by the cells of a session
+pub fn reference_counts(grammars: &HashMap<Coordinate, Grammar>) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for grammar in grammars.values() {
+        for name in references_in(&grammar.text_value()) {
+            *counts.entry(name.to_string()).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+// Drops the assets no cell refers to anymore, returning their names
+pub fn collect_garbage(assets: &mut Vec<Asset>, grammars: &HashMap<Coordinate, Grammar>) -> Vec<String> {
+    let counts = reference_counts(grammars);
+    let (kept, dropped): (Vec<Asset>, Vec<Asset>) = assets.drain(..).partition(|a| counts.contains_key(&a.name));
+    *assets = kept;
+    dropped.into_iter().map(|a| a.name).collect()
+}
+
+// A name for a new asset that doesn't clash with existing ones: `logo.png`, `logo-2.png`, ...
+// Characters that can't be part of a reference are replaced
+pub fn unique_name(assets: &[Asset], file_name: &str) -> String {
+    let clean: String = file_name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '.' || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    let (stem, extension) = match clean.rfind('.') {
+        Some(i) if i > 0 => (&clean[..i], &clean[i..]),
+        _ => (clean.as_str(), ""),
+    };
+    let mut name = clean.clone();
+    let mut n = 2;
+    while assets.iter().any(|a| a.name == name) {
+        name = format! {"{}-{}{}", stem, n, extension};
+        n += 1;
+    }
+    name
+}
+
+// Folder the assets of a session file are saved in: `budget.json` -> `budget.assets`
+pub fn sidecar_dir(session_file: &str) -> String {
+    let stem = if session_file.to_lowercase().ends_with(".json") {
+        &session_file[..session_file.len() - ".json".len()]
+    } else {
+        session_file
+    };
+    format! {"{}.assets", stem}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+
+    fn asset(name: &str) -> Asset {
+        Asset::new(name.to_string(), "image/png".to_string(), &[137, 80, 78, 71])
+    }
+
+    #[test]
+    fn test_references_in() {
+        assert_eq!(references_in("see asset:logo.png."), vec!["logo.png"]);
+        assert_eq!(references_in("asset:a-1, asset:b_2 asset:"), vec!["a-1", "b_2"]);
+        assert!(references_in("no assets").is_empty());
+    }
+
+    #[test]
+    fn test_collect_garbage() {
+        let grammars = hashmap! {
+            coord!("root-A1") => Grammar::input("", "asset:logo.png"),
+            coord!("root-A2") => Grammar::text("", "asset:logo.png and asset:chart.svg"),
+        };
+        let counts = reference_counts(&grammars);
+        assert_eq!(counts.get("logo.png"), Some(&2));
+        assert_eq!(counts.get("chart.svg"), Some(&1));
+
+        let mut assets = vec![asset("logo.png"), asset("unused.png"), asset("chart.svg")];
+        assert_eq!(collect_garbage(&mut assets, &grammars), vec!["unused.png".to_string()]);
+        assert_eq!(assets.len(), 2);
+    }
+
+    #[test]
+    fn test_names() {
+        let assets = vec![asset("logo.png"), asset("logo-2.png")];
+        assert_eq!(unique_name(&assets, "logo.png"), "logo-3.png");
+        assert_eq!(unique_name(&assets, "my chart.svg"), "my_chart.svg");
+        assert_eq!(sidecar_dir("budget.json"), "budget.assets");
+        assert_eq!(sidecar_dir("notes"), "notes.assets");
+        assert_eq!(asset("logo.png").bytes(), Some(vec![137, 80, 78, 71]));
+    }
+}
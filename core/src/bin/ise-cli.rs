@@ -0,0 +1,73 @@
+// ise-cli: validates, migrates, diffs and exports session files from the command line,
+// without the app (see `cli::CliCommand`)
+use std::collections::HashMap;
+use std::fs;
+use std::process;
+
+use ise_core::cli::{diff, export, migrate, parse_args, CliCommand};
+use ise_core::integrity::check;
+use ise_core::session::{parse_session, Session};
+
+fn read(path: &str) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format! {"couldn't read {}: {}", path, e})
+}
+
+fn read_session(path: &str) -> Result<Session, String> {
+    parse_session(&read(path)?).map_err(|e| format! {"{}: {}", path, e})
+}
+
+// runs a command, printing its output. `Ok(false)` means the command ran but found problems
+// (or differences), which is reported through the exit code
+fn run(command: CliCommand) -> Result<bool, String> {
+    match command {
+        CliCommand::Validate { path } => {
+            // row heights & column widths aren't needed without the app, so only the structure
+            // of the grids is checked
+            let problems: Vec<_> = check(
+                &read_session(&path)?.grammars,
+                &HashMap::new(),
+                &HashMap::new(),
+            )
+            .into_iter()
+            .filter(|problem| problem.is_structural())
+            .collect();
+            for problem in problems.iter() {
+                println!("{}", problem.describe());
+            }
+            Ok(problems.is_empty())
+        }
+        CliCommand::Migrate { path, out } => {
+            let migrated = migrate(&read(&path)?)?;
+            let out = out.unwrap_or(path);
+            fs::write(&out, migrated).map_err(|e| format! {"couldn't write {}: {}", out, e})?;
+            Ok(true)
+        }
+        CliCommand::Diff { old, new } => {
+            let lines = diff(&read_session(&old)?.grammars, &read_session(&new)?.grammars);
+            for line in lines.iter() {
+                println!("{}", line);
+            }
+            Ok(lines.is_empty())
+        }
+        CliCommand::Export { format, grid, path } => {
+            print!(
+                "{}",
+                export(&read_session(&path)?.grammars, format, grid.as_ref())?
+            );
+            Ok(true)
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = parse_args(&args).and_then(run);
+    match result {
+        Ok(true) => (),
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::calc::{evaluate_formula, reads, uses, FormulaValue, Values, Variables};
+use crate::coordinate::Coordinate;
+use crate::gauge::source_formula;
+use crate::grammar::{parse_color, Grammar};
+use crate::style::{ComputedStyle, StyleBindings};
+
+// Style attributes bound to cell values (`Style::bindings`): each bound attribute is a formula,
+// or a cell it reads from (like the source of gauges), evaluated by the formula engine whenever
+// a cell it reads from changes. What they evaluate to is kept in `Style::computed` and applied
+// over the rest of the cell's style by `get_style`. Values that aren't a color (or a width) are
+// ignored, leaving the attribute as it's set otherwise.
+
+// The style bindings of every cell that has any
+pub fn bindings(grammars: &HashMap<Coordinate, Grammar>) -> HashMap<Coordinate, StyleBindings> {
+    grammars
+        .iter()
+        .filter(|(_, g)| !g.style.bindings.is_empty())
+        .map(|(coord, g)| (coord.clone(), g.style.bindings.clone()))
+        .collect()
+}
+
+fn formulas(bindings: &StyleBindings) -> Vec<String> {
+    vec![&bindings.background, &bindings.font_color, &bindings.width]
+        .into_iter()
+        .flatten()
+        .map(|source| source_formula(source))
+        .collect()
+}
+
+// The cells whose bindings read from any of `changed`
+pub fn readers(
+    bindings: &HashMap<Coordinate, StyleBindings>,
+    changed: &HashSet<Coordinate>,
+) -> HashSet<Coordinate> {
+    bindings
+        .iter()
+        .filter(|(_, b)| {
+            formulas(b)
+                .iter()
+                .any(|formula| reads(formula).iter().any(|c| changed.contains(c)))
+        })
+        .map(|(coord, _)| coord.clone())
+        .collect()
+}
+
+// The cells whose bindings read any of the variables `changed`
+pub fn variable_readers(
+    bindings: &HashMap<Coordinate, StyleBindings>,
+    changed: &HashSet<String>,
+) -> HashSet<Coordinate> {
+    bindings
+        .iter()
+        .filter(|(_, b)| {
+            formulas(b)
+                .iter()
+                .any(|formula| uses(formula).iter().any(|name| changed.contains(name)))
+        })
+        .map(|(coord, _)| coord.clone())
+        .collect()
+}
+
+// A `#rgb`/`#rrggbb` or named CSS color
+pub fn css_color(text: &str) -> Option<String> {
+    let text = text.trim();
+    match parse_color(text) {
+        Some(color) => Some(color),
+        None if !text.is_empty() && text.chars().all(|c| c.is_ascii_alphabetic()) => {
+            Some(text.to_lowercase())
+        }
+        None => None,
+    }
+}
+
+// What the bindings evaluate to, reading formula cells from `values`
+pub fn compute(
+    grammars: &HashMap<Coordinate, Grammar>,
+    variables: &Variables,
+    values: &mut Values,
+    bindings: &StyleBindings,
+) -> ComputedStyle {
+    let mut evaluate = |source: &Option<String>| {
+        source
+            .as_ref()
+            .map(|source| evaluate_formula(grammars, variables, values, &source_formula(source)))
+    };
+    let color = |value: Option<FormulaValue>| value.and_then(|v| css_color(&v.to_string()));
+    ComputedStyle {
+        background: color(evaluate(&bindings.background)),
+        font_color: color(evaluate(&bindings.font_color)),
+        width: match evaluate(&bindings.width) {
+            Some(FormulaValue::Number(n)) if n > 0.0 => Some(n),
+            _ => None,
+        },
+    }
+}
+
+// Re-evaluates the bindings of each of `cells` (cells without any lose their computed style),
+// returning the ones whose computed style changed
+pub fn apply_bindings(
+    grammars: &mut HashMap<Coordinate, Grammar>,
+    variables: &Variables,
+    values: &mut Values,
+    cells: &[Coordinate],
+) -> Vec<Coordinate> {
+    let mut changed = vec![];
+    for cell in cells {
+        let computed = match grammars.get(cell) {
+            Some(grammar) => compute(grammars, variables, values, &grammar.style.bindings.clone()),
+            None => continue,
+        };
+        if let Some(grammar) = grammars.get_mut(cell) {
+            if grammar.style.computed != computed {
+                grammar.style.computed = computed;
+                changed.push(cell.clone());
+            }
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use crate::coordinate::{Col, Row};
+    use crate::grammar::{Interactive, Kind};
+    use crate::style::get_style;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn test_apply_bindings() {
+        let mut total = Grammar::input("", "120");
+        total.style.bindings = StyleBindings {
+            background: Some("root-A1".to_string()),
+            font_color: Some("=IF(root-A2 > 100, \"red\", \"green\")".to_string()),
+            width: Some("=root-A2".to_string()),
+        };
+        let mut grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => Grammar {
+                kind: Kind::Interactive("".to_string(), Interactive::ColorPicker("#FFcc00".to_string())),
+                ..Grammar::default()
+            },
+            coord!("root-A2") => Grammar::input("", "150"),
+            coord!("root-B2") => total,
+        };
+        let changed = vec![coord!("root-A1")].into_iter().collect();
+        assert_eq!(
+            readers(&bindings(&grammars), &changed),
+            vec![coord!("root-B2")].into_iter().collect()
+        );
+
+        let mut values = Values::new();
+        let cells = vec![coord!("root-B2")];
+        assert_eq!(apply_bindings(&mut grammars, &Variables::new(), &mut values, &cells), cells);
+        let grammar = &grammars[&coord!("root-B2")];
+        assert_eq!(
+            grammar.style.computed,
+            ComputedStyle {
+                background: Some("#ffcc00".to_string()),
+                font_color: Some("red".to_string()),
+                width: Some(150.0),
+            }
+        );
+        let style = get_style(
+            grammar,
+            &HashMap::<Col, f64>::new(),
+            &HashMap::<Row, f64>::new(),
+            &coord!("root-B2"),
+        );
+        assert!(style.contains("width: 150px;"));
+        assert!(style.ends_with("background: #ffcc00;\ncolor: red;\n"));
+
+        // values that aren't colors are left out
+        assert_eq!(css_color("red; display: none"), None);
+        assert_eq!(apply_bindings(&mut grammars, &Variables::new(), &mut values, &cells), vec![]);
+    }
+}
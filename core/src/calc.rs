@@ -0,0 +1,825 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::num::NonZeroU32;
+
+use serde::{Deserialize, Serialize};
+
+use crate::coordinate::Coordinate;
+use crate::formula::{is_formula, parse, references, variables, Expr, Reference};
+use crate::gauge::source_formula;
+use crate::grammar::{Grammar, Interactive, Kind};
+use crate::value::Value;
+
+// number of formula cells evaluated per step of a recalculation, so large recalculations
+// don't block the UI (see `Action::CalcStep`)
+pub const CALC_BATCH: usize = 200;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormulaValue {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+    Empty,
+    // "#NAME?", "#DIV/0!", "#CYCLE!", ...
+    Error(String),
+    // the result of a range-valued formula, row by row, which spills into the cells to the
+    // right of and below its formula (see `spill`)
+    Array(Vec<Vec<FormulaValue>>),
+}
+
+impl fmt::Display for FormulaValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FormulaValue::Number(n) => write!(f, "{}", n),
+            FormulaValue::Text(t) | FormulaValue::Error(t) => f.write_str(t),
+            FormulaValue::Bool(b) => f.write_str(if *b { "TRUE" } else { "FALSE" }),
+            FormulaValue::Empty => Ok(()),
+            // the formula cell itself shows the top-left value
+            FormulaValue::Array(rows) => match rows.first().and_then(|row| row.first()) {
+                Some(v) => write!(f, "{}", v),
+                None => Ok(()),
+            },
+        }
+    }
+}
+
+impl FormulaValue {
+    fn as_number(&self) -> Result<f64, FormulaValue> {
+        match self {
+            FormulaValue::Number(n) => Ok(*n),
+            FormulaValue::Bool(b) => Ok(if *b { 1.0 } else { 0.0 }),
+            FormulaValue::Empty => Ok(0.0),
+            FormulaValue::Text(t) => t
+                .trim()
+                .parse()
+                .map_err(|_| FormulaValue::Error("#VALUE!".to_string())),
+            FormulaValue::Error(_) => Err(self.clone()),
+            FormulaValue::Array(_) => Err(FormulaValue::Error("#VALUE!".to_string())),
+        }
+    }
+
+    // scalars are 1x1 arrays
+    fn as_rows(&self) -> Vec<Vec<FormulaValue>> {
+        match self {
+            FormulaValue::Array(rows) => rows.clone(),
+            v => vec![vec![v.clone()]],
+        }
+    }
+
+    pub fn as_bool(&self) -> Result<bool, FormulaValue> {
+        match self {
+            FormulaValue::Bool(b) => Ok(*b),
+            FormulaValue::Error(_) => Err(self.clone()),
+            other => other.as_number().map(|n| n != 0.0),
+        }
+    }
+}
+
+// computed values of the formula cells of a session
+pub type Values = HashMap<Coordinate, FormulaValue>;
+
+// values of the variables of a session, by name (see `variables::values`)
+pub type Variables = HashMap<String, FormulaValue>;
+
+// In automatic mode formulas are recalculated after every edit, in manual mode the formulas an
+// edit affects are only marked stale until they're recalculated with F9 ("Calculate now")
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum CalcMode {
+    Automatic,
+    Manual,
+}
+
+// Iterative calculation lets formulas refer to themselves (through other formulas), e.g. for
+// convergence calculations: formulas in a cycle are evaluated over and over, each time from the
+// values of the previous pass, until no value changes by more than `epsilon` anymore.
+// Set per session, since it's a property of the model being built.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct IterationSettings {
+    pub enabled: bool,
+    pub max_iterations: u32,
+    pub epsilon: f64,
+}
+
+impl Default for IterationSettings {
+    fn default() -> Self {
+        IterationSettings {
+            enabled: false,
+            max_iterations: 100,
+            epsilon: 0.001,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IterationOutcome {
+    // after how many passes
+    Converged(u32),
+    // the formulas whose values didn't settle (or blew up)
+    Diverged(Vec<Coordinate>),
+}
+
+// number of passes in a row in which the values move further apart before the iteration is
+// considered to be diverging
+const DIVERGING_PASSES: u32 = 5;
+
+// Evaluates the `cyclic` formulas over and over until they settle, or until `settings.max_iterations`
+// passes have been made. Values start out empty (0) on the first pass.
+pub fn iterate(
+    grammars: &HashMap<Coordinate, Grammar>,
+    variables: &Variables,
+    values: &mut Values,
+    cyclic: &HashSet<Coordinate>,
+    settings: &IterationSettings,
+) -> IterationOutcome {
+    let mut cells: Vec<Coordinate> = cyclic.iter().cloned().collect();
+    cells.sort_by(|a, b| a.row_cols.cmp(&b.row_cols));
+    for cell in cells.iter() {
+        values.insert(cell.clone(), FormulaValue::Empty);
+    }
+    let mut last_delta = f64::INFINITY;
+    let mut diverging_passes = 0;
+    for pass in 1..=settings.max_iterations.max(1) {
+        let mut delta: f64 = 0.0;
+        let mut unsettled = vec![];
+        for cell in cells.iter() {
+            let expr = match grammars.get(cell).and_then(|g| formula_text(&g.kind)) {
+                Some(text) => match parse(&text) {
+                    Ok(expr) => bind(&expr, variables),
+                    Err(_) => continue,
+                },
+                _ => continue,
+            };
+            let new_value = {
+                let mut done = HashSet::new();
+                let mut visiting = HashSet::new();
+                let no_pending = HashSet::new();
+                let mut read = |c: &Coordinate| {
+                    if cyclic.contains(c) {
+                        values.get(c).cloned().unwrap_or(FormulaValue::Empty)
+                    } else {
+                        evaluate_cell(c, grammars, variables, values, &mut done, &mut visiting, &no_pending)
+                    }
+                };
+                evaluate(&expr, &mut read)
+            };
+            let old_value = values.get(cell).cloned().unwrap_or(FormulaValue::Empty);
+            let change = match (old_value.as_number(), new_value.as_number()) {
+                (Ok(a), Ok(b)) if b.is_finite() => (a - b).abs(),
+                _ if old_value == new_value => 0.0,
+                _ => f64::INFINITY,
+            };
+            if change > settings.epsilon {
+                unsettled.push(cell.clone());
+            }
+            delta = delta.max(change);
+            values.insert(cell.clone(), new_value);
+        }
+        if delta <= settings.epsilon {
+            return IterationOutcome::Converged(pass);
+        }
+        diverging_passes = if delta > last_delta { diverging_passes + 1 } else { 0 };
+        if delta.is_infinite() || diverging_passes >= DIVERGING_PASSES {
+            return IterationOutcome::Diverged(unsettled);
+        }
+        last_delta = delta;
+        if pass == settings.max_iterations.max(1) {
+            return IterationOutcome::Diverged(unsettled);
+        }
+    }
+    IterationOutcome::Converged(0)
+}
+
+// The text of every formula cell, by coordinate
+pub fn formula_cells(grammars: &HashMap<Coordinate, Grammar>) -> HashMap<Coordinate, String> {
+    grammars
+        .iter()
+        .filter_map(|(coord, g)| match formula_text(&g.kind) {
+            Some(text) if is_formula(&text) => Some((coord.clone(), text)),
+            _ => None,
+        })
+        .collect()
+}
+
+// The text a cell is calculated from: what's typed into inputs, the source of gauges, whether
+// toggles are checked and the color of color pickers
+pub fn formula_text(kind: &Kind) -> Option<String> {
+    match kind {
+        Kind::Input(text) => Some(text.clone()),
+        Kind::Interactive(_, Interactive::Gauge(source, _, _)) => Some(source_formula(source)),
+        Kind::Interactive(_, Interactive::Toggle(on)) => {
+            Some(if *on { "TRUE" } else { "FALSE" }.to_string())
+        }
+        Kind::Interactive(_, Interactive::ColorPicker(color)) => Some(color.clone()),
+        _ => None,
+    }
+}
+
+// the cells of a range, row by row
+pub fn range_cells(start: &Coordinate, end: &Coordinate) -> Vec<Coordinate> {
+    range_rows(start, end).into_iter().flatten().collect()
+}
+
+pub fn range_rows(start: &Coordinate, end: &Coordinate) -> Vec<Vec<Coordinate>> {
+    let parent = match (start.parent(), end.parent()) {
+        (Some(a), Some(b)) if a == b => a,
+        _ => return vec![],
+    };
+    let (r1, c1) = start.row_col();
+    let (r2, c2) = end.row_col();
+    (r1.get().min(r2.get())..=r1.get().max(r2.get()))
+        .map(|row| {
+            (c1.get().min(c2.get())..=c1.get().max(c2.get()))
+                .map(|col| {
+                    Coordinate::child_of(
+                        &parent,
+                        (NonZeroU32::new(row).unwrap(), NonZeroU32::new(col).unwrap()),
+                    )
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// The variables a formula reads
+pub fn uses(text: &str) -> Vec<String> {
+    match parse(text) {
+        Ok(expr) => variables(&expr),
+        Err(_) => vec![],
+    }
+}
+
+// The cells a formula reads from
+pub fn reads(text: &str) -> Vec<Coordinate> {
+    match parse(text) {
+        Ok(expr) => references(&expr)
+            .into_iter()
+            .flat_map(|r| match r {
+                Reference::Cell(c) => vec![c],
+                Reference::Range(start, end) => range_cells(&start, &end),
+            })
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+// Evaluates the formula cells `cells`, storing the results in `values`. Formulas they read from
+// that are `pending` (waiting to be recalculated) are evaluated first, other formulas keep their
+// last value. Formulas that (indirectly) refer to themselves are "#CYCLE!".
+pub fn recalculate(
+    grammars: &HashMap<Coordinate, Grammar>,
+    variables: &Variables,
+    values: &mut Values,
+    cells: &[Coordinate],
+    pending: &HashSet<Coordinate>,
+) {
+    let mut done: HashSet<Coordinate> = HashSet::new();
+    for cell in cells {
+        let mut visiting = HashSet::new();
+        evaluate_cell(cell, grammars, variables, values, &mut done, &mut visiting, pending);
+    }
+}
+
+// Evaluates a formula that isn't in any cell, reading formula cells from `values` (or
+// evaluating the ones that aren't in there yet)
+pub fn evaluate_formula(
+    grammars: &HashMap<Coordinate, Grammar>,
+    variables: &Variables,
+    values: &mut Values,
+    text: &str,
+) -> FormulaValue {
+    match parse(text) {
+        Ok(expr) => {
+            let (mut done, mut visiting, pending) = (HashSet::new(), HashSet::new(), HashSet::new());
+            let mut read = |c: &Coordinate| {
+                evaluate_cell(c, grammars, variables, values, &mut done, &mut visiting, &pending)
+            };
+            evaluate(&bind(&expr, variables), &mut read)
+        }
+        Err(_) => FormulaValue::Error("#PARSE!".to_string()),
+    }
+}
+
+fn evaluate_cell(
+    coord: &Coordinate,
+    grammars: &HashMap<Coordinate, Grammar>,
+    variables: &Variables,
+    values: &mut Values,
+    done: &mut HashSet<Coordinate>,
+    visiting: &mut HashSet<Coordinate>,
+    pending: &HashSet<Coordinate>,
+) -> FormulaValue {
+    let text = match grammars.get(coord).map(|g| &g.kind) {
+        Some(Kind::Lookup(text, _)) | Some(Kind::Text(text)) => return FormulaValue::Text(text.clone()),
+        Some(Kind::Interactive(_, Interactive::Toggle(on))) => return FormulaValue::Bool(*on),
+        Some(Kind::Interactive(_, Interactive::ColorPicker(color))) => {
+            return FormulaValue::Text(color.clone())
+        }
+        Some(kind) => match formula_text(kind) {
+            Some(text) => text,
+            None => return FormulaValue::Empty,
+        },
+        None => return FormulaValue::Empty,
+    };
+    if !is_formula(&text) {
+        return match Value::parse(&text) {
+            // empty cells may have an array spilled into them
+            _ if text.trim().is_empty() => values.get(coord).cloned().unwrap_or(FormulaValue::Empty),
+            Value::Number(n) => FormulaValue::Number(n),
+            _ => FormulaValue::Text(text),
+        };
+    }
+    if done.contains(coord) || (!pending.contains(coord) && values.contains_key(coord)) {
+        return values.get(coord).cloned().unwrap_or(FormulaValue::Empty);
+    }
+    if !visiting.insert(coord.clone()) {
+        return FormulaValue::Error("#CYCLE!".to_string());
+    }
+    let value = match parse(&text) {
+        Ok(expr) => {
+            let mut read =
+                |c: &Coordinate| evaluate_cell(c, grammars, variables, values, done, visiting, pending);
+            evaluate(&bind(&expr, variables), &mut read)
+        }
+        Err(_) => FormulaValue::Error("#PARSE!".to_string()),
+    };
+    visiting.remove(coord);
+    done.insert(coord.clone());
+    values.insert(coord.clone(), value.clone());
+    value
+}
+
+// the cells each array formula spills into, by formula cell
+pub type Spills = HashMap<Coordinate, Vec<Coordinate>>;
+
+// Spreads the values of array formulas over the cells to the right of and below them, replacing
+// whatever was spilled before. A formula whose block would run out of its grid, or over a cell
+// that isn't empty (or that another array spills into), is "#SPILL!" instead.
+pub fn spill(grammars: &HashMap<Coordinate, Grammar>, values: &mut Values, spills: &mut Spills) {
+    for cell in spills.values().flatten() {
+        values.remove(cell);
+    }
+    spills.clear();
+    let mut anchors: Vec<Coordinate> = values
+        .iter()
+        .filter(|(_, v)| matches!(v, FormulaValue::Array(_)))
+        .map(|(c, _)| c.clone())
+        .collect();
+    anchors.sort_by(|a, b| a.row_cols.cmp(&b.row_cols));
+    let mut claimed: HashSet<Coordinate> = HashSet::new();
+    for anchor in anchors {
+        let rows = values[&anchor].as_rows();
+        let parent = match anchor.parent() {
+            Some(p) => p,
+            None => continue,
+        };
+        let (row, col) = anchor.row_col();
+        let mut block = vec![];
+        let mut fits = true;
+        for (i, values_row) in rows.iter().enumerate() {
+            for (j, value) in values_row.iter().enumerate() {
+                if i == 0 && j == 0 {
+                    continue;
+                }
+                let target = Coordinate::child_of(
+                    &parent,
+                    (
+                        NonZeroU32::new(row.get() + i as u32).unwrap(),
+                        NonZeroU32::new(col.get() + j as u32).unwrap(),
+                    ),
+                );
+                let is_empty = match grammars.get(&target).map(|g| &g.kind) {
+                    Some(Kind::Input(text)) => text.trim().is_empty(),
+                    _ => false,
+                };
+                if !is_empty || claimed.contains(&target) {
+                    fits = false;
+                }
+                block.push((target, value.clone()));
+            }
+        }
+        if !fits {
+            values.insert(anchor, FormulaValue::Error("#SPILL!".to_string()));
+            continue;
+        }
+        for (target, value) in block.iter() {
+            claimed.insert(target.clone());
+            values.insert(target.clone(), value.clone());
+        }
+        spills.insert(anchor, block.into_iter().map(|(c, _)| c).collect());
+    }
+}
+
+// The expression with the variables it reads replaced by their values. Variables are constants,
+// so they're bound before evaluating, and the ones that don't exist are left to be "#NAME?".
+pub fn bind(expr: &Expr, variables: &Variables) -> Expr {
+    match expr {
+        Expr::Var(name) => match variables.get(name) {
+            Some(FormulaValue::Number(n)) => Expr::Number(*n),
+            Some(FormulaValue::Bool(b)) => Expr::Bool(*b),
+            Some(value) => Expr::Text(value.to_string()),
+            None => expr.clone(),
+        },
+        Expr::Call(name, args) => Expr::Call(
+            name.clone(),
+            args.iter().map(|a| bind(a, variables)).collect(),
+        ),
+        Expr::Unary(op, e) => Expr::Unary(op.clone(), Box::new(bind(e, variables))),
+        Expr::Binary(op, lhs, rhs) => Expr::Binary(
+            op.clone(),
+            Box::new(bind(lhs, variables)),
+            Box::new(bind(rhs, variables)),
+        ),
+        _ => expr.clone(),
+    }
+}
+
+// Evaluates an expression, reading the value of referenced cells with `read`
+pub fn evaluate<F>(expr: &Expr, read: &mut F) -> FormulaValue
+where
+    F: FnMut(&Coordinate) -> FormulaValue,
+{
+    match eval(expr, read) {
+        Ok(v) => v,
+        Err(e) => e,
+    }
+}
+
+fn eval<F>(expr: &Expr, read: &mut F) -> Result<FormulaValue, FormulaValue>
+where
+    F: FnMut(&Coordinate) -> FormulaValue,
+{
+    match expr {
+        Expr::Number(n) => Ok(FormulaValue::Number(*n)),
+        Expr::Text(t) => Ok(FormulaValue::Text(t.clone())),
+        Expr::Bool(b) => Ok(FormulaValue::Bool(*b)),
+        // a variable that isn't bound (see `bind`)
+        Expr::Var(_) => Err(FormulaValue::Error("#NAME?".to_string())),
+        Expr::Ref(Reference::Cell(c)) => match read(c) {
+            FormulaValue::Error(e) => Err(FormulaValue::Error(e)),
+            v => Ok(v),
+        },
+        Expr::Ref(Reference::Range(start, end)) => {
+            let mut rows = vec![];
+            for row in range_rows(start, end) {
+                rows.push(row.iter().map(&mut *read).collect());
+            }
+            Ok(FormulaValue::Array(rows))
+        }
+        Expr::Unary(op, e) => {
+            let op = op.clone();
+            element_wise(eval(e, read)?, FormulaValue::Empty, &|a, _| {
+                let n = a.as_number()?;
+                Ok(FormulaValue::Number(if op == "-" { -n } else { n }))
+            })
+        }
+        Expr::Binary(op, lhs, rhs) => {
+            let (a, b) = (eval(lhs, read)?, eval(rhs, read)?);
+            element_wise(a, b, &|a, b| binary(op, a, b))
+        }
+        Expr::Call(name, args) => call(name, args, read),
+    }
+}
+
+// Applies `f` to scalars, or to each pair of elements of arrays of the same size (a scalar
+// goes with every element of an array). Errors of single elements stay in their element.
+fn element_wise<F>(a: FormulaValue, b: FormulaValue, f: &F) -> Result<FormulaValue, FormulaValue>
+where
+    F: Fn(FormulaValue, FormulaValue) -> Result<FormulaValue, FormulaValue>,
+{
+    let (a_rows, b_rows) = match (&a, &b) {
+        (FormulaValue::Array(_), _) | (_, FormulaValue::Array(_)) => (a.as_rows(), b.as_rows()),
+        _ => return f(a, b),
+    };
+    let size = |rows: &Vec<Vec<FormulaValue>>| (rows.len(), rows.first().map_or(0, |r| r.len()));
+    let (a_size, b_size) = (size(&a_rows), size(&b_rows));
+    let out_size = match (a_size, b_size) {
+        ((1, 1), s) | (s, (1, 1)) => s,
+        (s, t) if s == t => s,
+        _ => return Err(FormulaValue::Error("#VALUE!".to_string())),
+    };
+    let at = |rows: &Vec<Vec<FormulaValue>>, (r, c): (usize, usize), s: (usize, usize)| {
+        if s == (1, 1) {
+            rows[0][0].clone()
+        } else {
+            rows[r].get(c).cloned().unwrap_or(FormulaValue::Empty)
+        }
+    };
+    let rows = (0..out_size.0)
+        .map(|r| {
+            (0..out_size.1)
+                .map(|c| {
+                    match f(at(&a_rows, (r, c), a_size), at(&b_rows, (r, c), b_size)) {
+                        Ok(v) => v,
+                        Err(e) => e,
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    Ok(FormulaValue::Array(rows))
+}
+
+// the numbers of an array (or a scalar), row by row; anything that isn't a number is an error
+fn number_rows(value: &FormulaValue) -> Result<Vec<Vec<f64>>, FormulaValue> {
+    value
+        .as_rows()
+        .iter()
+        .map(|row| row.iter().map(|v| v.as_number()).collect())
+        .collect()
+}
+
+fn binary(op: &str, a: FormulaValue, b: FormulaValue) -> Result<FormulaValue, FormulaValue> {
+    if op == "&" {
+        return Ok(FormulaValue::Text(a.to_string() + &b.to_string()));
+    }
+    if ["=", "<>", "<", "<=", ">", ">="].contains(&op) {
+        let ordering = match (a.as_number(), b.as_number()) {
+            (Ok(x), Ok(y)) => x.partial_cmp(&y),
+            _ => Some(a.to_string().to_lowercase().cmp(&b.to_string().to_lowercase())),
+        };
+        let result = match (op, ordering) {
+            ("=", Some(o)) => o == std::cmp::Ordering::Equal,
+            ("<>", Some(o)) => o != std::cmp::Ordering::Equal,
+            ("<", Some(o)) => o == std::cmp::Ordering::Less,
+            ("<=", Some(o)) => o != std::cmp::Ordering::Greater,
+            (">", Some(o)) => o == std::cmp::Ordering::Greater,
+            (">=", Some(o)) => o != std::cmp::Ordering::Less,
+            _ => false,
+        };
+        return Ok(FormulaValue::Bool(result));
+    }
+    let (x, y) = (a.as_number()?, b.as_number()?);
+    let n = match op {
+        "+" => x + y,
+        "-" => x - y,
+        "*" => x * y,
+        "/" if y == 0.0 => return Err(FormulaValue::Error("#DIV/0!".to_string())),
+        "/" => x / y,
+        "^" => x.powf(y),
+        _ => return Err(FormulaValue::Error("#NAME?".to_string())),
+    };
+    Ok(FormulaValue::Number(n))
+}
+
+// the values of function arguments, with ranges spread into the values of their cells
+fn spread<F>(args: &[Expr], read: &mut F) -> Result<Vec<FormulaValue>, FormulaValue>
+where
+    F: FnMut(&Coordinate) -> FormulaValue,
+{
+    let mut values = vec![];
+    for arg in args {
+        match eval(arg, read)? {
+            FormulaValue::Array(rows) => {
+                for v in rows.into_iter().flatten() {
+                    match v {
+                        FormulaValue::Error(e) => return Err(FormulaValue::Error(e)),
+                        v => values.push(v),
+                    }
+                }
+            }
+            v => values.push(v),
+        }
+    }
+    Ok(values)
+}
+
+// numbers among the values; text and empty cells are skipped, like in other spreadsheets
+fn numbers(values: &[FormulaValue]) -> Vec<f64> {
+    values
+        .iter()
+        .filter_map(|v| match v {
+            FormulaValue::Number(n) => Some(*n),
+            FormulaValue::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+            _ => None,
+        })
+        .collect()
+}
+
+fn call<F>(name: &str, args: &[Expr], read: &mut F) -> Result<FormulaValue, FormulaValue>
+where
+    F: FnMut(&Coordinate) -> FormulaValue,
+{
+    let arity_error = || FormulaValue::Error("#N/A".to_string());
+    match name {
+        "IF" => {
+            if args.len() < 2 || args.len() > 3 {
+                return Err(arity_error());
+            }
+            if eval(&args[0], read)?.as_bool()? {
+                eval(&args[1], read)
+            } else {
+                args.get(2).map_or(Ok(FormulaValue::Bool(false)), |e| eval(e, read))
+            }
+        }
+        "SUM" | "AVERAGE" | "MIN" | "MAX" | "COUNT" => {
+            let ns = numbers(&spread(args, read)?);
+            let n = match name {
+                "SUM" => ns.iter().sum(),
+                "COUNT" => ns.len() as f64,
+                _ if ns.is_empty() => 0.0,
+                "AVERAGE" => ns.iter().sum::<f64>() / ns.len() as f64,
+                "MIN" => ns.iter().cloned().fold(f64::INFINITY, f64::min),
+                _ => ns.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+            };
+            Ok(FormulaValue::Number(n))
+        }
+        "AND" | "OR" => {
+            let mut result = name == "AND";
+            for v in spread(args, read)? {
+                let b = v.as_bool()?;
+                result = if name == "AND" { result && b } else { result || b };
+            }
+            Ok(FormulaValue::Bool(result))
+        }
+        "CONCAT" => Ok(FormulaValue::Text(
+            spread(args, read)?.iter().map(|v| v.to_string()).collect(),
+        )),
+        "NOT" | "ABS" | "LEN" => {
+            if args.len() != 1 {
+                return Err(arity_error());
+            }
+            let v = eval(&args[0], read)?;
+            Ok(match name {
+                "NOT" => FormulaValue::Bool(!v.as_bool()?),
+                "ABS" => FormulaValue::Number(v.as_number()?.abs()),
+                _ => FormulaValue::Number(v.to_string().chars().count() as f64),
+            })
+        }
+        "TRANSPOSE" => {
+            if args.len() != 1 {
+                return Err(arity_error());
+            }
+            let rows = eval(&args[0], read)?.as_rows();
+            let cols = rows.first().map_or(0, |r| r.len());
+            Ok(FormulaValue::Array(
+                (0..cols)
+                    .map(|c| rows.iter().map(|row| row[c].clone()).collect())
+                    .collect(),
+            ))
+        }
+        "MMULT" => {
+            if args.len() != 2 {
+                return Err(arity_error());
+            }
+            let a = number_rows(&eval(&args[0], read)?)?;
+            let b = number_rows(&eval(&args[1], read)?)?;
+            let inner = a.first().map_or(0, |r| r.len());
+            if inner == 0 || inner != b.len() {
+                return Err(FormulaValue::Error("#VALUE!".to_string()));
+            }
+            let cols = b[0].len();
+            Ok(FormulaValue::Array(
+                a.iter()
+                    .map(|row| {
+                        (0..cols)
+                            .map(|c| {
+                                FormulaValue::Number((0..inner).map(|k| row[k] * b[k][c]).sum())
+                            })
+                            .collect()
+                    })
+                    .collect(),
+            ))
+        }
+        "ROUND" => {
+            if args.is_empty() || args.len() > 2 {
+                return Err(arity_error());
+            }
+            let n = eval(&args[0], read)?.as_number()?;
+            let digits = match args.get(1) {
+                Some(e) => eval(e, read)?.as_number()?,
+                None => 0.0,
+            };
+            let factor = 10f64.powi(digits as i32);
+            Ok(FormulaValue::Number((n * factor).round() / factor))
+        }
+        _ => Err(FormulaValue::Error("#NAME?".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use crate::deps::DepGraph;
+    use std::num::NonZeroU32;
+
+    fn graph(grammars: &HashMap<Coordinate, Grammar>) -> DepGraph {
+        let mut graph = DepGraph::default();
+        graph.update(&formula_cells(grammars));
+        graph
+    }
+
+    #[test]
+    fn test_recalculate() {
+        let grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(3).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => Grammar::input("", "1"),
+            coord!("root-A2") => Grammar::input("", "2"),
+            coord!("root-A3") => Grammar::input("", "=SUM(root-A1:root-A2) * 10"),
+            coord!("root-B1") => Grammar::input("", "=root-A3 / 0"),
+            coord!("root-B2") => Grammar::input("", "=IF(root-A3 > 20, \"big\", \"small\")"),
+            coord!("root-B3") => Grammar::input("", "=root-B3 + 1"),
+        };
+        let mut values = Values::new();
+        let cells: Vec<Coordinate> = formula_cells(&grammars).keys().cloned().collect();
+        let pending = cells.iter().cloned().collect();
+        recalculate(&grammars, &Variables::new(), &mut values, &cells, &pending);
+        assert_eq!(values[&coord!("root-A3")], FormulaValue::Number(30.0));
+        assert_eq!(values[&coord!("root-B1")], FormulaValue::Error("#DIV/0!".to_string()));
+        assert_eq!(values[&coord!("root-B2")], FormulaValue::Text("big".to_string()));
+        assert_eq!(values[&coord!("root-B3")], FormulaValue::Error("#CYCLE!".to_string()));
+    }
+
+    #[test]
+    fn test_iterate() {
+        // x = (x + 2 / x) / 2 converges to sqrt(2), starting from x = 1
+        let grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(1).unwrap()),
+            coord!("root-A1") => Grammar::input("", "=IF(root-A1 = 0, 1, (root-A1 + 2 / root-A1) / 2)"),
+            coord!("root-A2") => Grammar::input("", "=root-A2 * 2 + 1"),
+        };
+        assert_eq!(graph(&grammars).cyclic().len(), 2);
+        let mut values = Values::new();
+        let settings = IterationSettings {
+            enabled: true,
+            ..IterationSettings::default()
+        };
+        let converging = vec![coord!("root-A1")].into_iter().collect();
+        match iterate(&grammars, &Variables::new(), &mut values, &converging, &settings) {
+            IterationOutcome::Converged(_) => (),
+            outcome => panic!("expected convergence, got {:?}", outcome),
+        }
+        match values[&coord!("root-A1")] {
+            FormulaValue::Number(n) => assert!((n - 2f64.sqrt()).abs() < 0.001),
+            ref v => panic!("expected a number, got {:?}", v),
+        }
+        let diverging = vec![coord!("root-A2")].into_iter().collect();
+        assert_eq!(
+            iterate(&grammars, &Variables::new(), &mut values, &diverging, &settings),
+            IterationOutcome::Diverged(vec![coord!("root-A2")])
+        );
+    }
+
+    #[test]
+    fn test_arrays() {
+        let grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(3).unwrap(), NonZeroU32::new(3).unwrap()),
+            coord!("root-A1") => Grammar::input("", "1"),
+            coord!("root-A2") => Grammar::input("", "2"),
+            coord!("root-B1") => Grammar::input("", "=TRANSPOSE(root-A1:root-A2 * 10)"),
+            coord!("root-B2") => Grammar::input("", ""),
+            coord!("root-C1") => Grammar::input("", ""),
+            coord!("root-C2") => Grammar::input("", "=MMULT(TRANSPOSE(root-A1:root-A2), root-A1:root-A2)"),
+            coord!("root-A3") => Grammar::input("", "=root-A1:root-A2 + 1"),
+            coord!("root-B3") => Grammar::input("", ""),
+            coord!("root-C3") => Grammar::input("", ""),
+        };
+        let mut values = Values::new();
+        let cells: Vec<Coordinate> = formula_cells(&grammars).keys().cloned().collect();
+        let pending = cells.iter().cloned().collect();
+        recalculate(&grammars, &Variables::new(), &mut values, &cells, &pending);
+        assert_eq!(values[&coord!("root-C2")], FormulaValue::Array(vec![vec![FormulaValue::Number(5.0)]]));
+        let mut spills = Spills::new();
+        spill(&grammars, &mut values, &mut spills);
+        // [10, 20] spills into C1
+        assert_eq!(values[&coord!("root-C1")], FormulaValue::Number(20.0));
+        assert_eq!(spills[&coord!("root-B1")], vec![coord!("root-C1")]);
+        // [2; 3] would spill below the grid
+        assert_eq!(values[&coord!("root-A3")], FormulaValue::Error("#SPILL!".to_string()));
+    }
+
+    #[test]
+    fn test_dependents_of() {
+        let grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(3).unwrap(), NonZeroU32::new(1).unwrap()),
+            coord!("root-A1") => Grammar::input("", "1"),
+            coord!("root-A2") => Grammar::input("", "=root-A1"),
+            coord!("root-A3") => Grammar::input("", "=root-A2"),
+        };
+        let changed = vec![coord!("root-A1")].into_iter().collect();
+        let dependents = graph(&grammars).dependents(&changed);
+        assert_eq!(dependents.len(), 2);
+        assert!(dependents.contains(&coord!("root-A3")));
+    }
+
+    #[test]
+    fn test_gauge_source() {
+        let grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(1).unwrap()),
+            coord!("root-A1") => Grammar::input("", "40"),
+            coord!("root-A2") => Grammar {
+                kind: Kind::Interactive("".to_string(), Interactive::Gauge("root-A1".to_string(), 0.0, 80.0)),
+                ..Grammar::default()
+            },
+        };
+        let cells: Vec<Coordinate> = formula_cells(&grammars).keys().cloned().collect();
+        assert_eq!(cells, vec![coord!("root-A2")]);
+        let mut values = Values::new();
+        let pending = cells.iter().cloned().collect();
+        recalculate(&grammars, &Variables::new(), &mut values, &cells, &pending);
+        assert_eq!(values[&coord!("root-A2")], FormulaValue::Number(40.0));
+        let changed = vec![coord!("root-A1")].into_iter().collect();
+        assert!(graph(&grammars).dependents(&changed).contains(&coord!("root-A2")));
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::coordinate::Coordinate;
+use crate::export::dot::{to_dot, DotOptions};
+use crate::export::markdown::to_markdown;
+use crate::export::{cell_text, Region};
+use crate::external::{grid_table, to_csv};
+use crate::grammar::Grammar;
+use crate::session::parse_session;
+
+pub const USAGE: &str = "usage:
+  ise-cli validate <session>
+  ise-cli migrate <session> [<output>]
+  ise-cli diff <old session> <new session>
+  ise-cli export (--csv | --markdown | --dot) [--grid <coordinate>] <session>";
+
+// What `ise-cli export` writes a grid (or the whole session, for DOT) as
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Markdown,
+    Dot,
+}
+
+// CliCommand is what ise-cli (src/bin/ise-cli.rs) was asked to do with session files,
+// without the app running
+#[derive(Debug, Clone, PartialEq)]
+pub enum CliCommand {
+    // lists what's inconsistent in the grids of a session (see `integrity::check`)
+    Validate { path: String },
+    // rewrites a session in the current file format, in place without `out`
+    Migrate { path: String, out: Option<String> },
+    // lists the cells added, removed or changed between two sessions
+    Diff { old: String, new: String },
+    // prints a grid (the root grid without `grid`) in an export format
+    Export {
+        format: ExportFormat,
+        grid: Option<Coordinate>,
+        path: String,
+    },
+}
+
+// Parses the arguments after the program name
+pub fn parse_args(args: &[String]) -> Result<CliCommand, String> {
+    let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+    match args.as_slice() {
+        ["validate", path] => Ok(CliCommand::Validate { path: path.to_string() }),
+        ["migrate", path] => Ok(CliCommand::Migrate {
+            path: path.to_string(),
+            out: None,
+        }),
+        ["migrate", path, out] => Ok(CliCommand::Migrate {
+            path: path.to_string(),
+            out: Some(out.to_string()),
+        }),
+        ["diff", old, new] => Ok(CliCommand::Diff {
+            old: old.to_string(),
+            new: new.to_string(),
+        }),
+        ["export", options @ ..] => {
+            let mut format = None;
+            let mut grid = None;
+            let mut path = None;
+            let mut options = options.iter();
+            while let Some(option) = options.next() {
+                match *option {
+                    "--csv" => format = Some(ExportFormat::Csv),
+                    "--markdown" => format = Some(ExportFormat::Markdown),
+                    "--dot" => format = Some(ExportFormat::Dot),
+                    "--grid" => {
+                        let text = options.next().ok_or("--grid needs a coordinate")?;
+                        grid = Some(Coordinate::parse(text).ok_or_else(|| format! {"{} isn't a coordinate", text})?);
+                    }
+                    other if other.starts_with("--") => return Err(format! {"unknown option {}", other}),
+                    other => path = Some(other.to_string()),
+                }
+            }
+            Ok(CliCommand::Export {
+                format: format.ok_or("pick an export format")?,
+                grid,
+                path: path.ok_or("missing the session to export")?,
+            })
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+// Reads a session and writes it back as the app would save it now, filling in what older
+// versions of the file format didn't have
+pub fn migrate(content: &str) -> Result<String, String> {
+    let session = parse_session(content)?;
+    serde_json::to_string(&session).map_err(|e| e.to_string())
+}
+
+// One line per cell that differs between two sessions, by coordinate: `+` for added cells,
+// `-` for removed ones and `~` for ones whose name or contents changed (with their text before
+// and after). Styles aren't compared.
+pub fn diff(old: &HashMap<Coordinate, Grammar>, new: &HashMap<Coordinate, Grammar>) -> Vec<String> {
+    let by_coord = |grammars: &HashMap<Coordinate, Grammar>| -> BTreeMap<String, (Coordinate, Grammar)> {
+        grammars
+            .iter()
+            .map(|(c, g)| (c.to_string(), (c.clone(), g.clone())))
+            .collect()
+    };
+    let (old_cells, new_cells) = (by_coord(old), by_coord(new));
+    let mut lines = vec![];
+    for (name, (coord, grammar)) in old_cells.iter() {
+        match new_cells.get(name) {
+            None => lines.push(format! {"- {}: {}", name, cell_text(old, coord)}),
+            Some((_, new_grammar)) if new_grammar.kind != grammar.kind || new_grammar.name != grammar.name => {
+                lines.push(format! {"~ {}: {} -> {}", name, cell_text(old, coord), cell_text(new, coord)})
+            }
+            _ => (),
+        }
+    }
+    for (name, (coord, _)) in new_cells.iter() {
+        if !old_cells.contains_key(name) {
+            lines.push(format! {"+ {}: {}", name, cell_text(new, coord)});
+        }
+    }
+    lines.sort_by(|a, b| a[2..].cmp(&b[2..]));
+    lines
+}
+
+pub fn export(
+    grammars: &HashMap<Coordinate, Grammar>,
+    format: ExportFormat,
+    grid: Option<&Coordinate>,
+) -> Result<String, String> {
+    let root = Coordinate::parse("root").unwrap();
+    let grid = grid.unwrap_or(&root);
+    let not_a_grid = || format! {"{} isn't a grid", grid.to_string()};
+    match format {
+        ExportFormat::Csv => grid_table(grammars, grid).map(|t| to_csv(&t)).ok_or_else(not_a_grid),
+        ExportFormat::Markdown => Region::of_grid(grammars, grid)
+            .map(|region| to_markdown(grammars, &region))
+            .ok_or_else(not_a_grid),
+        ExportFormat::Dot => Ok(to_dot(
+            grammars,
+            &DotOptions {
+                subtree: if grid == &root { None } else { Some(grid.clone()) },
+                hierarchy: false,
+            },
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use std::num::NonZeroU32;
+
+    fn args(text: &str) -> Vec<String> {
+        text.split_whitespace().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(
+            parse_args(&args("export --csv --grid root-A1 file.json")),
+            Ok(CliCommand::Export {
+                format: ExportFormat::Csv,
+                grid: Coordinate::parse("root-A1"),
+                path: "file.json".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_args(&args("migrate old.ise")),
+            Ok(CliCommand::Migrate {
+                path: "old.ise".to_string(),
+                out: None,
+            })
+        );
+        assert!(parse_args(&args("export file.json")).is_err());
+        assert!(parse_args(&args("export --xml file.json")).is_err());
+        assert_eq!(parse_args(&args("frobnicate")), Err(USAGE.to_string()));
+    }
+
+    #[test]
+    fn test_export_and_diff() {
+        let old = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => Grammar::input("", "1"),
+            coord!("root-B1") => Grammar::input("", "2"),
+        };
+        assert_eq!(export(&old, ExportFormat::Csv, None), Ok("1,2\n".to_string()));
+        assert!(export(&old, ExportFormat::Markdown, Some(&coord!("root-A1"))).is_err());
+
+        let new = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => Grammar::input("", "3"),
+            coord!("root-A2") => Grammar::input("", "stray"),
+        };
+        assert_eq!(
+            diff(&old, &new),
+            vec!["~ root-A1: 1 -> 3", "+ root-A2: stray", "- root-B1: 2"]
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+
+use crate::coordinate::Coordinate;
+use crate::export::{cell_text, Region};
+use crate::external::write_table;
+use crate::grammar::Grammar;
+
+// Copying cells to the system clipboard and pasting them from it. Cells are copied both as
+// tab-separated values and as an HTML table (with the spans of merged cells), so rich-paste
+// targets like spreadsheets and word processors keep the merges. Pasted HTML tables (from
+// browsers, Excel, ...) are laid out the way browsers do it, colspans & rowspans included.
+
+// HtmlCell is a `<td>`/`<th>` of a pasted table, as the app reads it out of the clipboard
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct HtmlCell {
+    pub text: String,
+    #[serde(default = "one")]
+    pub rowspan: u32,
+    #[serde(default = "one")]
+    pub colspan: u32,
+}
+
+fn one() -> u32 {
+    1
+}
+
+// PastedTable is a pasted table laid out on a grid: the text of every cell (empty where cells
+// are covered by a merged one), and the (sub_coord, (rows, cols)) spans of merged cells
+#[derive(Debug, Clone, PartialEq)]
+pub struct PastedTable {
+    pub cells: Vec<Vec<String>>,
+    pub spans: Vec<((NonZeroU32, NonZeroU32), (NonZeroU32, NonZeroU32))>,
+}
+
+// The region as tab-separated values, one line per row
+pub fn to_tsv(grammars: &HashMap<Coordinate, Grammar>, region: &Region) -> String {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(b'\t')
+        .from_writer(vec![]);
+    for row in region.rows.0..=region.rows.1 {
+        let cells: Vec<String> = (region.cols.0..=region.cols.1)
+            .map(|col| cell_text(grammars, &region.cell(row, col)))
+            .collect();
+        let _ = writer.write_record(&cells);
+    }
+    let tsv = String::from_utf8(writer.into_inner().unwrap_or_default()).unwrap_or_default();
+    tsv.trim_end_matches('\n').to_string()
+}
+
+pub fn parse_tsv(text: &str) -> Vec<Vec<String>> {
+    csv::ReaderBuilder::new()
+        .delimiter(b'\t')
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes())
+        .records()
+        .filter_map(|record| record.ok())
+        .map(|record| record.iter().map(|cell| cell.to_string()).collect())
+        .collect()
+}
+
+// The region as an HTML table. Cells spanning over others get a rowspan & colspan (cut down
+// to the region), and the cells they cover are left out.
+pub fn to_html(grammars: &HashMap<Coordinate, Grammar>, region: &Region) -> String {
+    let grid = grammars.get(&region.parent);
+    let in_region = |(row, col): (NonZeroU32, NonZeroU32)| {
+        region.rows.0 <= row.get()
+            && row.get() <= region.rows.1
+            && region.cols.0 <= col.get()
+            && col.get() <= region.cols.1
+    };
+    let mut html = String::from("<table>");
+    for row in region.rows.0..=region.rows.1 {
+        html.push_str("<tr>");
+        for col in region.cols.0..=region.cols.1 {
+            let sub_coord = (NonZeroU32::new(row).unwrap(), NonZeroU32::new(col).unwrap());
+            if grid
+                .and_then(|g| g.spanned_by(sub_coord))
+                .is_some_and(&in_region)
+            {
+                continue;
+            }
+            let span = grid.and_then(|g| {
+                g.style
+                    .spans
+                    .iter()
+                    .find(|(anchor, _)| *anchor == sub_coord)
+                    .map(|(_, span)| *span)
+            });
+            let mut attributes = String::new();
+            if let Some((rows, cols)) = span {
+                let rows = rows.get().min(region.rows.1 - row + 1);
+                let cols = cols.get().min(region.cols.1 - col + 1);
+                if rows > 1 {
+                    attributes.push_str(&format! {" rowspan=\"{}\"", rows});
+                }
+                if cols > 1 {
+                    attributes.push_str(&format! {" colspan=\"{}\"", cols});
+                }
+            }
+            let text = escape(&cell_text(grammars, &region.cell(row, col)));
+            html.push_str(&format! {"<td{}>{}</td>", attributes, text});
+        }
+        html.push_str("</tr>");
+    }
+    html.push_str("</table>");
+    html
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\n', "<br>")
+}
+
+// Lays the rows of a pasted HTML table out on a grid: each cell goes in the first slot of its
+// row that no cell above spans into, and rowspans stop at the last row
+pub fn lay_out(rows: &[Vec<HtmlCell>]) -> PastedTable {
+    let height = rows.len();
+    let mut cells: Vec<Vec<String>> = vec![vec![]; height];
+    let mut taken: Vec<Vec<bool>> = vec![vec![]; height];
+    let mut spans = vec![];
+    for (row, html_cells) in rows.iter().enumerate() {
+        let mut col = 0;
+        for cell in html_cells {
+            while taken[row].get(col).cloned().unwrap_or(false) {
+                col += 1;
+            }
+            let rowspan = (cell.rowspan.max(1) as usize).min(height - row);
+            let colspan = cell.colspan.max(1) as usize;
+            for r in row..row + rowspan {
+                if taken[r].len() < col + colspan {
+                    taken[r].resize(col + colspan, false);
+                    cells[r].resize(col + colspan, String::new());
+                }
+                for slot in taken[r][col..col + colspan].iter_mut() {
+                    *slot = true;
+                }
+            }
+            cells[row][col] = cell.text.clone();
+            if rowspan > 1 || colspan > 1 {
+                spans.push((
+                    (
+                        NonZeroU32::new(row as u32 + 1).unwrap(),
+                        NonZeroU32::new(col as u32 + 1).unwrap(),
+                    ),
+                    (
+                        NonZeroU32::new(rowspan as u32).unwrap(),
+                        NonZeroU32::new(colspan as u32).unwrap(),
+                    ),
+                ));
+            }
+            col += colspan;
+        }
+    }
+    let width = cells.iter().map(|row| row.len()).max().unwrap_or(0);
+    for row in cells.iter_mut() {
+        row.resize(width, String::new());
+    }
+    PastedTable { cells, spans }
+}
+
+// Writes a pasted table into `grid` (see `write_table`), merging the cells it merges
+pub fn paste_table(
+    grammars: &mut HashMap<Coordinate, Grammar>,
+    grid: &Coordinate,
+    table: &PastedTable,
+) {
+    write_table(grammars, grid, &table.cells);
+    if let Some(grammar) = grammars.get_mut(grid) {
+        grammar.style.spans = table.spans.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use crate::util::non_zero_u32_tuple;
+
+    fn cell(text: &str, rowspan: u32, colspan: u32) -> HtmlCell {
+        HtmlCell {
+            text: text.to_string(),
+            rowspan,
+            colspan,
+        }
+    }
+
+    #[test]
+    fn test_to_html() {
+        let mut grid = Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(2).unwrap());
+        grid.style.spans = vec![(non_zero_u32_tuple((1, 1)), non_zero_u32_tuple((1, 2)))];
+        let grammars = hashmap! {
+            coord!("root") => grid,
+            coord!("root-A1") => Grammar::input("", "fruit & veg"),
+            coord!("root-B1") => Grammar::input("", ""),
+            coord!("root-A2") => Grammar::input("", "apples"),
+            coord!("root-B2") => Grammar::input("", "3\t<kg>"),
+        };
+        let region = Region::of_grid(&grammars, &coord!("root")).unwrap();
+        assert_eq!(
+            to_html(&grammars, &region),
+            "<table><tr><td colspan=\"2\">fruit &amp; veg</td></tr>\
+             <tr><td>apples</td><td>3\t&lt;kg&gt;</td></tr></table>"
+        );
+        let tsv = to_tsv(&grammars, &region);
+        assert_eq!(tsv, "fruit & veg\t\napples\t\"3\t<kg>\"");
+        assert_eq!(
+            parse_tsv(&tsv),
+            vec![
+                vec!["fruit & veg".to_string(), "".to_string()],
+                vec!["apples".to_string(), "3\t<kg>".to_string()]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lay_out() {
+        let table = lay_out(&[
+            vec![cell("region", 2, 1), cell("q1", 1, 1), cell("q2", 1, 1)],
+            vec![cell("10", 1, 2)],
+            vec![cell("total", 5, 1), cell("20", 1, 1)],
+        ]);
+        let rows: Vec<String> = table.cells.iter().map(|row| row.join("|")).collect();
+        assert_eq!(rows, vec!["region|q1|q2", "|10|", "total|20|"]);
+        assert_eq!(
+            table.spans,
+            vec![
+                (non_zero_u32_tuple((1, 1)), non_zero_u32_tuple((2, 1))),
+                (non_zero_u32_tuple((2, 2)), non_zero_u32_tuple((1, 2))),
+            ]
+        );
+
+        let mut grammars = HashMap::new();
+        paste_table(&mut grammars, &coord!("root-A1"), &table);
+        let grid = grammars.get(&coord!("root-A1")).unwrap();
+        assert_eq!(
+            grid.spanned_by(non_zero_u32_tuple((2, 1))),
+            Some(non_zero_u32_tuple((1, 1)))
+        );
+        assert_eq!(
+            grammars.get(&coord!("root-A1-B3")).unwrap().text_value(),
+            "20"
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+
+use crate::coordinate::{Col, Coordinate, Row};
+use crate::export::{cell_text, Region};
+use crate::fragment::{extract, graft, CellSize, Fragment};
+use crate::grammar::Grammar;
+
+// how many copies the clipboard history keeps, dropping the oldest ones first
+pub const CLIPBOARD_HISTORY_LIMIT: usize = 10;
+
+// ClipEntry is a copy of cells kept in the clipboard history, besides what went to the system
+// clipboard: every cell of the copied region with everything nested in it, so copies of nested
+// grids, definitions, lookups, ... can be pasted again later as they were, from any tab
+#[derive(Debug, Clone)]
+pub struct ClipEntry {
+    // the first and last cells of the copied region, e.g. `root-A1:root-B3`
+    pub label: String,
+    pub copied_at: String,
+    pub rows: u32,
+    pub cols: u32,
+    // the (row, col) of each cell within the region, starting at (0, 0), and its fragment
+    pub cells: Vec<((u32, u32), Fragment)>,
+    // the text of the copied cells, like they were exported (see `cell_text`)
+    pub preview: Vec<String>,
+}
+
+impl ClipEntry {
+    // The entry for copying `region`, whose cells are taken along with their row heights and
+    // column widths
+    pub fn of_region(
+        copied_at: &str,
+        grammars: &HashMap<Coordinate, Grammar>,
+        row_heights: &HashMap<Row, f64>,
+        col_widths: &HashMap<Col, f64>,
+        region: &Region,
+    ) -> Option<ClipEntry> {
+        let mut cells = vec![];
+        let mut preview = vec![];
+        for row in region.rows.0..=region.rows.1 {
+            for col in region.cols.0..=region.cols.1 {
+                let coord = region.cell(row, col);
+                if let Some(fragment) = extract(grammars, row_heights, col_widths, &coord) {
+                    cells.push(((row - region.rows.0, col - region.cols.0), fragment));
+                    preview.push(cell_text(grammars, &coord));
+                }
+            }
+        }
+        if cells.is_empty() {
+            return None;
+        }
+        let first = region.cell(region.rows.0, region.cols.0);
+        let last = region.cell(region.rows.1, region.cols.1);
+        Some(ClipEntry {
+            label: format! {"{}:{}", first.to_string(), last.to_string()},
+            copied_at: copied_at.to_string(),
+            rows: region.rows.1 - region.rows.0 + 1,
+            cols: region.cols.1 - region.cols.0 + 1,
+            cells,
+            preview,
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        let shown: Vec<&str> = self
+            .preview
+            .iter()
+            .map(|text| text.trim())
+            .filter(|text| !text.is_empty())
+            .take(3)
+            .collect();
+        format! {
+            "{}x{} cells{}{}",
+            self.rows,
+            self.cols,
+            if shown.is_empty() { "" } else { ": " },
+            shown.join(", "),
+        }
+    }
+}
+
+// Adds a copy to the clipboard history, newest first
+pub fn push_entry(history: &mut Vec<ClipEntry>, entry: ClipEntry) {
+    history.insert(0, entry);
+    history.truncate(CLIPBOARD_HISTORY_LIMIT);
+}
+
+// the sizes of pasted cells, by coordinate
+type Sizes = Vec<(Coordinate, CellSize)>;
+
+// Pastes the cells of `entry` over the cells of the grid of `dest`, with the first copied cell
+// at `dest`, replacing them and everything nested in them. Nothing is pasted if the copy doesn't
+// fit in the grid. Returns the sizes of the pasted cells to apply, like `graft`.
+pub fn paste_entry(
+    grammars: &mut HashMap<Coordinate, Grammar>,
+    entry: &ClipEntry,
+    dest: &Coordinate,
+) -> Result<Sizes, String> {
+    let grid = match dest.parent() {
+        Some(grid) => grid,
+        None => return Err("can't paste over the root grid".to_string()),
+    };
+    let region = Region::of_grid(grammars, &grid).ok_or_else(|| format! {"{} isn't a grid", grid.to_string()})?;
+    let (row, col) = dest.row_col();
+    if row.get() + entry.rows - 1 > region.rows.1 || col.get() + entry.cols - 1 > region.cols.1 {
+        return Err(format! {
+            "the {}x{} cells don't fit in {} from {}",
+            entry.rows, entry.cols, grid.to_string(), dest.to_string()
+        });
+    }
+    let mut sizes = vec![];
+    for ((row_offset, col_offset), fragment) in entry.cells.iter() {
+        let target = Coordinate::child_of(
+            &grid,
+            (
+                NonZeroU32::new(row.get() + row_offset).unwrap(),
+                NonZeroU32::new(col.get() + col_offset).unwrap(),
+            ),
+        );
+        sizes.extend(graft(fragment, grammars, &target));
+    }
+    Ok(sizes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+
+    #[test]
+    fn test_clipboard_history() {
+        let mut grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(3).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => Grammar::input("", "apples"),
+            coord!("root-B1") => Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(1).unwrap()),
+            coord!("root-B1-A1") => Grammar::input("", "3"),
+            coord!("root-A2") => Grammar::input("", ""),
+            coord!("root-B2") => Grammar::input("", ""),
+            coord!("root-A3") => Grammar::input("", ""),
+            coord!("root-B3") => Grammar::input("", "replaced"),
+        };
+        let col_widths = hashmap! { coord!("root-B1-A1").full_col() => 120.0 };
+        let region = Region::from_selection(&coord!("root-A1"), &coord!("root-B1")).unwrap();
+        let entry =
+            ClipEntry::of_region("2020-05-01T10:00:00", &grammars, &HashMap::new(), &col_widths, &region).unwrap();
+        assert_eq!(entry.label, "root-A1:root-B1");
+        assert_eq!(entry.describe(), "1x2 cells: apples, [1x1 grid: 3...]");
+
+        let sizes = paste_entry(&mut grammars, &entry, &coord!("root-A3")).unwrap();
+        // the column width comes along without a row height
+        assert_eq!(sizes, vec![(coord!("root-B3-A1"), (None, Some(120.0)))]);
+        assert_eq!(grammars[&coord!("root-A3")].text_value(), "apples");
+        assert_eq!(grammars[&coord!("root-B3-A1")].text_value(), "3");
+        // the copy is kept as it was copied
+        grammars.insert(coord!("root-B1-A1"), Grammar::input("", "4"));
+        paste_entry(&mut grammars, &entry, &coord!("root-A2")).unwrap();
+        assert_eq!(grammars[&coord!("root-B2-A1")].text_value(), "3");
+
+        assert!(paste_entry(&mut grammars, &entry, &coord!("root-B2")).is_err());
+        assert!(paste_entry(&mut grammars, &entry, &coord!("root")).is_err());
+
+        let mut history = vec![];
+        for _ in 0..CLIPBOARD_HISTORY_LIMIT + 1 {
+            push_entry(&mut history, entry.clone());
+        }
+        assert_eq!(history.len(), CLIPBOARD_HISTORY_LIMIT);
+    }
+}
@@ -8,7 +8,7 @@ use std::panic;
 
 use crate::coord;
 use crate::coordinate;
-use crate::util::{coord_show, non_zero_u32_tuple};
+use crate::util::{coord_show, non_zero_u32_tuple, special_row_col};
 
 #[derive(Parser)]
 #[grammar = "coordinate.pest"]
@@ -19,23 +19,66 @@ pub struct CoordinateParser;
 pub struct Coordinate {
     pub row_cols: Vec<(NonZeroU32, NonZeroU32)>, // TEST: should never be empty list
 }
+#[cfg(feature = "stdweb")]
 js_serializable!(Coordinate);
+#[cfg(feature = "stdweb")]
 js_deserializable!(Coordinate);
 
 impl Coordinate {
     pub fn child_of(parent: &Self, child_coord: (NonZeroU32, NonZeroU32)) -> Coordinate {
         let mut new_row_col = parent.clone().row_cols;
         new_row_col.push(child_coord);
-<<<<<<< HEAD
-=======
         // info!("pareb = {:?}, child_coord = {:?}", parent, child_coord);
->>>>>>> hieule/fix_bug
 
         Coordinate {
             row_cols: new_row_col,
         }
     }
 
+    // parses an absolute coordinate (starting at root, meta or a sheet), such as one typed in by
+    // the user; `coord!` is the panicking shorthand for it
+    pub fn parse(s: &str) -> Option<Coordinate> {
+        let pairs = CoordinateParser::parse(Rule::whole_coordinate, s).ok()?;
+        let mut fragments: Vec<(NonZeroU32, NonZeroU32)> = Vec::new();
+        for pair in pairs {
+            // only absolute coordinates (starting at root or meta) can be resolved
+            if fragments.is_empty() && pair.as_rule() != Rule::special {
+                return None;
+            }
+            match pair.as_rule() {
+                Rule::special => {
+                    let (row, col) = special_row_col(pair.as_str())?;
+                    fragments.push((NonZeroU32::new(row)?, NonZeroU32::new(col)?));
+                }
+                Rule::fragment => {
+                    let mut fragment: (u32, u32) = (0, 0);
+                    for inner_pair in pair.into_inner() {
+                        match inner_pair.as_rule() {
+                            Rule::alpha => {
+                                for ch in inner_pair.as_str().chars() {
+                                    fragment.1 += (ch as u32) - 64;
+                                }
+                            }
+                            Rule::digit => {
+                                fragment.0 = inner_pair.as_str().parse::<u32>().ok()?;
+                            }
+                            _ => return None,
+                        };
+                    }
+                    fragments.push((NonZeroU32::new(fragment.0)?, NonZeroU32::new(fragment.1)?));
+                }
+                Rule::EOI => (),
+                _ => return None,
+            }
+        }
+        if fragments.is_empty() {
+            return None;
+        }
+        Some(Coordinate {
+            row_cols: fragments,
+        })
+    }
+
     pub fn parent(&self) -> Option<Coordinate> {
         if self.row_cols.len() == 1 {
             return None;
@@ -259,52 +302,12 @@ impl PartialEq for Col {
 
 impl Eq for Col {}
 
-// macro for easily defining a coordinate
-// either absolutely or relative to it's parent coordinate
-// TODO: this code is messy, can be optimized more later
+// macro for easily defining a coordinate, panicking if it isn't one (see `Coordinate::parse`)
 #[macro_export]
 macro_rules! coord {
     ( $coord_str:tt ) => {{
-        let mut fragments: Vec<(NonZeroU32, NonZeroU32)> = Vec::new();
-        let pairs = CoordinateParser::parse(Rule::coordinate, $coord_str)
-            .unwrap_or_else(|e| panic!("{}", e));
-
-        for pair in pairs {
-            match pair.as_rule() {
-                Rule::special if pair.as_str() == "root" => {
-                    fragments.push(non_zero_u32_tuple((1, 1)));
-                }
-                Rule::special if pair.as_str() == "meta" => {
-                    fragments.push(non_zero_u32_tuple((1, 2)));
-                }
-                Rule::fragment => {
-                    let mut fragment: (u32, u32) = (0, 0);
-                    for inner_pair in pair.into_inner() {
-                        match inner_pair.as_rule() {
-                            // COLUMN
-                            Rule::alpha => {
-                                let mut val: u32 = 0;
-                                for ch in inner_pair.as_str().to_string().chars() {
-                                    val += (ch as u32) - 64;
-                                }
-                                fragment.1 = val;
-                            }
-                            // ROW
-                            Rule::digit => {
-                                fragment.0 = inner_pair.as_str().parse::<u32>().unwrap();
-                            }
-                            _ => unreachable!(),
-                        };
-                    }
-                    fragments.push(non_zero_u32_tuple(fragment));
-                }
-                _ => unreachable!(),
-            }
-        }
-
-        Coordinate {
-            row_cols: fragments,
-        }
+        $crate::coordinate::Coordinate::parse($coord_str)
+            .unwrap_or_else(|| panic!("not a coordinate: {}", $coord_str))
     }};
 }
 
@@ -373,6 +376,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse() {
+        assert_eq!(Coordinate::parse("root-A1-B2"), Some(coord!("root-A1-B2")));
+        assert_eq!(Coordinate::parse("meta"), Some(coord!("meta")));
+        assert_eq!(Coordinate::parse("sheet2-B1").unwrap().row_cols[0], non_zero_u32_tuple((1, 4)));
+        assert_eq!(Coordinate::parse("A1"), None);
+        assert_eq!(Coordinate::parse(""), None);
+        assert_eq!(Coordinate::parse("root-A10").unwrap().row_cols[1], non_zero_u32_tuple((10, 1)));
+        assert_eq!(Coordinate::parse("root-A1xyz"), None);
+        assert_eq!(Coordinate::parse("root-A1-"), None);
+        assert_eq!(Coordinate::parse("root-a1"), None);
+    }
+
     #[test]
     fn test_parent() {
         assert_eq!(coord!("root").parent(), None);
@@ -0,0 +1,110 @@
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroU32;
+
+use crate::coordinate::Coordinate;
+use crate::export::{cell_text, Region};
+use crate::grammar::{Grammar, Kind};
+
+// Rows of `grid` (by row number) that repeat an earlier row, comparing the text of the cells in
+// `key_cols` (all columns if empty). Comparison ignores case and surrounding whitespace.
+// The first occurrence of each row is kept, so it isn't in the result.
+pub fn duplicate_rows(
+    grammars: &HashMap<Coordinate, Grammar>,
+    grid: &Coordinate,
+    key_cols: &[u32],
+) -> Vec<u32> {
+    let region = match Region::of_grid(grammars, grid) {
+        Some(region) => region,
+        None => return vec![],
+    };
+    let cols: Vec<u32> = if key_cols.is_empty() {
+        (region.cols.0..=region.cols.1).collect()
+    } else {
+        key_cols.to_vec()
+    };
+    let mut seen: HashSet<Vec<String>> = HashSet::new();
+    let mut duplicates = vec![];
+    for row in region.rows.0..=region.rows.1 {
+        let key: Vec<String> = cols
+            .iter()
+            .map(|col| cell_text(grammars, &region.cell(row, *col)).trim().to_lowercase())
+            .collect();
+        if !seen.insert(key) {
+            duplicates.push(row);
+        }
+    }
+    duplicates
+}
+
+// Removes `rows` of `grid` (and everything nested in them), moving the rows below up
+pub fn remove_rows(grammars: &mut HashMap<Coordinate, Grammar>, grid: &Coordinate, rows: &[u32]) {
+    let depth = grid.row_cols.len();
+    let removed: HashSet<u32> = rows.iter().cloned().collect();
+    // how many removed rows are above a row
+    let shift = |row: u32| removed.iter().filter(|r| **r < row).count() as u32;
+    let cells: Vec<(Coordinate, Grammar)> = grammars
+        .iter()
+        .filter(|(c, _)| c.row_cols.len() > depth && c.row_cols[..depth] == grid.row_cols[..])
+        .map(|(c, g)| (c.clone(), g.clone()))
+        .collect();
+    for (coord, _) in cells.iter() {
+        grammars.remove(coord);
+    }
+    for (coord, grammar) in cells {
+        let row = coord.row_cols[depth].0.get();
+        if removed.contains(&row) {
+            continue;
+        }
+        let mut moved = coord.clone();
+        moved.row_cols[depth].0 = NonZeroU32::new(row - shift(row)).unwrap();
+        grammars.insert(moved, grammar);
+    }
+    if let Some(Grammar {
+        kind: Kind::Grid(sub_coords),
+        ..
+    }) = grammars.get_mut(grid)
+    {
+        *sub_coords = sub_coords
+            .iter()
+            .filter(|(r, _)| !removed.contains(&r.get()))
+            .map(|(r, c)| (NonZeroU32::new(r.get() - shift(r.get())).unwrap(), *c))
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+
+    fn table() -> HashMap<Coordinate, Grammar> {
+        hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(4).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => Grammar::input("", "apple"),
+            coord!("root-B1") => Grammar::input("", "1"),
+            coord!("root-A2") => Grammar::input("", "pear"),
+            coord!("root-B2") => Grammar::input("", "2"),
+            coord!("root-A3") => Grammar::input("", "Apple "),
+            coord!("root-B3") => Grammar::input("", "3"),
+            coord!("root-A4") => Grammar::input("", "pear"),
+            coord!("root-B4") => Grammar::input("", "2"),
+        }
+    }
+
+    #[test]
+    fn test_duplicate_rows() {
+        let grammars = table();
+        assert_eq!(duplicate_rows(&grammars, &coord!("root"), &[]), vec![4]);
+        assert_eq!(duplicate_rows(&grammars, &coord!("root"), &[1]), vec![3, 4]);
+    }
+
+    #[test]
+    fn test_remove_rows() {
+        let mut grammars = table();
+        remove_rows(&mut grammars, &coord!("root"), &[1, 3]);
+        assert_eq!(grammars.get(&coord!("root-A1")).unwrap().text_value(), "pear");
+        assert_eq!(grammars.get(&coord!("root-B2")).unwrap().text_value(), "2");
+        assert!(!grammars.contains_key(&coord!("root-A3")));
+        assert_eq!(Region::of_grid(&grammars, &coord!("root")).unwrap().rows, (1, 2));
+    }
+}
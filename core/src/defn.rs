@@ -0,0 +1,821 @@
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+
+use crate::coordinate::Coordinate;
+use crate::formula::{reference_spans, Reference};
+use crate::grammar::{Grammar, Kind};
+use crate::meta_import::import_grammar;
+use crate::suggestion::SuggestionCategory;
+use crate::util::non_zero_u32_tuple;
+use crate::value::Value;
+
+// `name`, unless it's `taken`, then with the first free number appended (`shape` becomes `shape_2`)
+pub fn unique_name(name: &str, taken: &[String]) -> String {
+    let mut unique = name.to_string();
+    let mut n = 2;
+    while taken.contains(&unique) {
+        unique = format! {"{}_{}", name, n};
+        n += 1;
+    }
+    unique
+}
+
+// The sub-rule names given by header cells with text `headers`: the text with spaces replaced by
+// `_`, `rule_<n>` for empty headers, and a number appended to repeated ones
+pub fn rule_names(headers: &[String]) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    for (i, header) in headers.iter().enumerate() {
+        let words: Vec<&str> = header.split_whitespace().collect();
+        let name = if words.is_empty() {
+            format! {"rule_{}", i + 1}
+        } else {
+            words.join("_")
+        };
+        names.push(unique_name(&name, &names));
+    }
+    names
+}
+
+// Wraps the cells of `grid` in `rows` and `cols` (first & last, inclusive) into a definition named
+// `name` at `defn_coord`. The cells are a header row above a row of cells or, when they're 2
+// columns wide but not 2 rows high, a header column beside a column of cells. Each header names a
+// sub-rule, which is the cell (and everything nested in it) next to the header.
+// A definition lays out its sub-rules as rows of (name, grammar) cells.
+pub fn definition_from_region(
+    grammars: &mut HashMap<Coordinate, Grammar>,
+    grid: &Coordinate,
+    rows: (u32, u32),
+    cols: (u32, u32),
+    name: &str,
+    defn_coord: &Coordinate,
+) -> Result<usize, String> {
+    let cell = |row: u32, col: u32| Coordinate::child_of(grid, non_zero_u32_tuple((row, col)));
+    let pairs: Vec<(Coordinate, Coordinate)> = if rows.1 - rows.0 == 1 {
+        (cols.0..=cols.1)
+            .map(|col| (cell(rows.0, col), cell(rows.1, col)))
+            .collect()
+    } else if cols.1 - cols.0 == 1 {
+        (rows.0..=rows.1)
+            .map(|row| (cell(row, cols.0), cell(row, cols.1)))
+            .collect()
+    } else {
+        return Err(
+            "select a row of headers above a row of cells, or a column of headers beside a column of cells"
+                .to_string(),
+        );
+    };
+    let headers: Vec<String> = pairs
+        .iter()
+        .map(|(header, _)| {
+            grammars
+                .get(header)
+                .map(|g| g.text_value())
+                .unwrap_or_default()
+        })
+        .collect();
+    let names = rule_names(&headers);
+    let source = grammars.clone();
+    let mut rules = vec![];
+    for (i, (name_of_rule, (_, rule_cell))) in names.iter().zip(pairs.iter()).enumerate() {
+        let row = NonZeroU32::new(i as u32 + 1).unwrap();
+        let name_coord = Coordinate::child_of(defn_coord, (row, NonZeroU32::new(1).unwrap()));
+        let rule_coord = Coordinate::child_of(defn_coord, (row, NonZeroU32::new(2).unwrap()));
+        grammars.insert(
+            name_coord,
+            Grammar::text("".to_string(), name_of_rule.clone()),
+        );
+        if !import_grammar(&source, rule_cell, &rule_coord, &HashMap::new(), grammars) {
+            grammars.insert(rule_coord.clone(), Grammar::default());
+        }
+        if let Some(g) = grammars.get_mut(&rule_coord) {
+            g.name = name_of_rule.clone();
+        }
+        rules.push((name_of_rule.clone(), rule_coord));
+    }
+    let count = rules.len();
+    let mut defn = Grammar::text(name.to_string(), "".to_string());
+    defn.kind = Kind::Defn(name.to_string(), defn_coord.clone(), rules);
+    defn.style.category = Some(SuggestionCategory::Definitions);
+    grammars.insert(defn_coord.clone(), defn);
+    Ok(count)
+}
+
+// The cells a definition at `defn_coord` holds, the way a grid holds its sub_coords: the name and
+// the grammar cell of each of its sub-rules (see `definition_from_region`)
+pub fn rule_cells(defn_coord: &Coordinate, rules: &[(String, Coordinate)]) -> Vec<Coordinate> {
+    let mut cells = vec![];
+    for (i, (_, rule_coord)) in rules.iter().enumerate() {
+        cells.push(Coordinate::child_of(
+            defn_coord,
+            non_zero_u32_tuple((i as u32 + 1, 1)),
+        ));
+        cells.push(rule_coord.clone());
+    }
+    cells
+}
+
+// Stamps the definition at `defn_coord` (with its sub-rules) into `dest`, replacing whatever
+// was there. The instance is a definition of its own, whose sub-rules are its own cells.
+pub fn instantiate(
+    grammars: &mut HashMap<Coordinate, Grammar>,
+    defn_coord: &Coordinate,
+    dest: &Coordinate,
+) -> bool {
+    match grammars.get(defn_coord) {
+        Some(Grammar {
+            kind: Kind::Defn(_, _, _),
+            ..
+        }) => (),
+        _ => return false,
+    }
+    let source = grammars.clone();
+    import_grammar(&source, defn_coord, dest, &HashMap::new(), grammars)
+}
+
+// The type of argument a parameter of a definition takes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamType {
+    Text,
+    Number,
+    Date,
+}
+
+impl ParamType {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ParamType::Text => "text",
+            ParamType::Number => "number",
+            ParamType::Date => "date",
+        }
+    }
+
+    fn parse(text: &str) -> Option<ParamType> {
+        match text.trim() {
+            "" | "text" => Some(ParamType::Text),
+            "number" => Some(ParamType::Number),
+            "date" => Some(ParamType::Date),
+            _ => None,
+        }
+    }
+
+    fn accepts(&self, argument: &str) -> bool {
+        matches!(
+            (self, Value::parse(argument)),
+            (ParamType::Text, _)
+                | (ParamType::Number, Value::Number(_))
+                | (ParamType::Date, Value::Date(_))
+        )
+    }
+}
+
+// Parameter is a placeholder written as `{{name}}` or `{{name: type}}` (text, number or date) in
+// the cells of a definition, substituted by an argument when the definition is instantiated
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    pub param_type: ParamType,
+}
+
+// DefnArguments is the state of the argument prompt shown when a definition with parameters is
+// instantiated: the arguments typed in (by parameter), or a range of cells to read them from
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefnArguments {
+    pub defn: Coordinate,
+    pub dest: Coordinate,
+    pub parameters: Vec<Parameter>,
+    pub values: Vec<String>,
+    pub range: String,
+    pub error: Option<String>,
+}
+
+impl DefnArguments {
+    pub fn new(defn: Coordinate, dest: Coordinate, parameters: Vec<Parameter>) -> DefnArguments {
+        let values = vec![String::new(); parameters.len()];
+        DefnArguments {
+            defn,
+            dest,
+            parameters,
+            values,
+            range: String::new(),
+            error: None,
+        }
+    }
+}
+
+// The placeholders in `text`: (byte span, parameter). Placeholders of an unknown type are
+// left alone.
+fn placeholders(text: &str) -> Vec<((usize, usize), Parameter)> {
+    let mut found = vec![];
+    let mut position = 0;
+    while let Some(start) = text[position..].find("{{").map(|i| i + position) {
+        let end = match text[start..].find("}}") {
+            Some(i) => start + i + 2,
+            None => break,
+        };
+        let inner = &text[start + 2..end - 2];
+        let (name, param_type) = match inner.find(':') {
+            Some(i) => (inner[..i].trim(), ParamType::parse(&inner[i + 1..])),
+            None => (inner.trim(), Some(ParamType::Text)),
+        };
+        if let (false, Some(param_type)) = (name.is_empty(), param_type) {
+            found.push((
+                (start, end),
+                Parameter {
+                    name: name.to_string(),
+                    param_type,
+                },
+            ));
+        }
+        position = end;
+    }
+    found
+}
+
+// The cells nested in `coord` (itself included) whose text can hold placeholders, in order
+fn text_cells(grammars: &HashMap<Coordinate, Grammar>, coord: &Coordinate) -> Vec<Coordinate> {
+    let depth = coord.row_cols.len();
+    let mut cells: Vec<Coordinate> = grammars
+        .iter()
+        .filter(|(c, g)| {
+            c.row_cols.len() >= depth
+                && c.row_cols[..depth] == coord.row_cols[..]
+                && matches!(g.kind, Kind::Text(_) | Kind::Input(_))
+        })
+        .map(|(c, _)| c.clone())
+        .collect();
+    cells.sort_by(|a, b| a.row_cols.cmp(&b.row_cols));
+    cells
+}
+
+// The parameters of the definition at `defn_coord`, in the order they first appear in its cells.
+// A parameter used more than once takes the type it's first declared with.
+pub fn parameters(
+    grammars: &HashMap<Coordinate, Grammar>,
+    defn_coord: &Coordinate,
+) -> Vec<Parameter> {
+    let mut found: Vec<Parameter> = vec![];
+    for coord in text_cells(grammars, defn_coord) {
+        let text = grammars[&coord].text_value();
+        for (_, parameter) in placeholders(&text) {
+            if !found.iter().any(|p| p.name == parameter.name) {
+                found.push(parameter);
+            }
+        }
+    }
+    found
+}
+
+// The texts of the cells `range` refers to (a cell, or a range of cells read row by row), to be
+// used as arguments
+pub fn range_values(
+    grammars: &HashMap<Coordinate, Grammar>,
+    range: &str,
+) -> Result<Vec<String>, String> {
+    let text = |c: &Coordinate| grammars.get(c).map(|g| g.text_value()).unwrap_or_default();
+    match reference_spans(range.trim()).first() {
+        Some((_, Reference::Cell(coord))) => Ok(vec![text(coord)]),
+        Some((_, Reference::Range(first, last))) if first.parent() == last.parent() => {
+            let grid = first.parent().unwrap_or_else(|| first.clone());
+            let (r0, r1) = (
+                first.row().get().min(last.row().get()),
+                first.row().get().max(last.row().get()),
+            );
+            let (c0, c1) = (
+                first.col().get().min(last.col().get()),
+                first.col().get().max(last.col().get()),
+            );
+            let mut values = vec![];
+            for row in r0..=r1 {
+                for col in c0..=c1 {
+                    values.push(text(&Coordinate::child_of(
+                        &grid,
+                        non_zero_u32_tuple((row, col)),
+                    )));
+                }
+            }
+            Ok(values)
+        }
+        _ => Err(format! {"{} isn't a cell or a range of cells", range}),
+    }
+}
+
+// Binds `values` to `parameters` in order, checking each has the type of its parameter
+pub fn bind_arguments(
+    parameters: &[Parameter],
+    values: &[String],
+) -> Result<HashMap<String, String>, String> {
+    if values.len() < parameters.len() {
+        return Err(format! {"expected {} arguments, got {}", parameters.len(), values.len()});
+    }
+    let mut arguments = HashMap::new();
+    for (parameter, value) in parameters.iter().zip(values.iter()) {
+        if !parameter.param_type.accepts(value) {
+            return Err(
+                format! {"{} expects a {}, not {:?}", parameter.name, parameter.param_type.label(), value},
+            );
+        }
+        arguments.insert(parameter.name.clone(), value.trim().to_string());
+    }
+    Ok(arguments)
+}
+
+// Replaces the placeholders in the cells of the instance at `instance` with their `arguments`
+pub fn substitute(
+    grammars: &mut HashMap<Coordinate, Grammar>,
+    instance: &Coordinate,
+    arguments: &HashMap<String, String>,
+) {
+    for coord in text_cells(grammars, instance) {
+        let grammar = match grammars.get_mut(&coord) {
+            Some(grammar) => grammar,
+            None => continue,
+        };
+        let text = match &mut grammar.kind {
+            Kind::Text(text) | Kind::Input(text) => text,
+            _ => continue,
+        };
+        let mut substituted = String::new();
+        let mut position = 0;
+        for ((start, end), parameter) in placeholders(text) {
+            if let Some(argument) = arguments.get(&parameter.name) {
+                substituted.push_str(&text[position..start]);
+                substituted.push_str(argument);
+                position = end;
+            }
+        }
+        substituted.push_str(&text[position..]);
+        *text = substituted;
+    }
+}
+
+// The instances of the definition at `defn_coord`: definitions bound to its name elsewhere in
+// `grammars`, in order
+pub fn instances(
+    grammars: &HashMap<Coordinate, Grammar>,
+    defn_coord: &Coordinate,
+) -> Vec<Coordinate> {
+    let binding = match grammars.get(defn_coord) {
+        Some(Grammar {
+            kind: Kind::Defn(binding, _, _),
+            ..
+        }) => binding,
+        _ => return vec![],
+    };
+    let depth = defn_coord.row_cols.len();
+    let mut found: Vec<Coordinate> = grammars
+        .iter()
+        .filter(|(c, _)| {
+            !(c.row_cols.len() >= depth && c.row_cols[..depth] == defn_coord.row_cols[..])
+        })
+        .filter(|(_, g)| match &g.kind {
+            Kind::Defn(b, _, _) => b == binding,
+            _ => false,
+        })
+        .map(|(c, _)| c.clone())
+        .collect();
+    found.sort_by(|a, b| a.row_cols.cmp(&b.row_cols));
+    found
+}
+
+// The cells of `instance` (and everything nested in it) once it's stamped again from the
+// definition at `defn_coord`. The sub-rules the instance has a rule of the same name for keep
+// what's in the instance, so values entered into it aren't lost.
+pub fn restamp(
+    grammars: &HashMap<Coordinate, Grammar>,
+    defn_coord: &Coordinate,
+    instance: &Coordinate,
+) -> HashMap<Coordinate, Grammar> {
+    let mut stamped = grammars.clone();
+    if !instantiate(&mut stamped, defn_coord, instance) {
+        return subtree(grammars, instance);
+    }
+    let kept = match grammars.get(instance).map(|g| &g.kind) {
+        Some(Kind::Defn(_, _, rules)) => rules.clone(),
+        _ => vec![],
+    };
+    let restamped = match stamped.get(instance).map(|g| &g.kind) {
+        Some(Kind::Defn(_, _, rules)) => rules.clone(),
+        _ => vec![],
+    };
+    for (rule, rule_coord) in restamped.iter() {
+        if let Some((_, kept_coord)) = kept.iter().find(|(name, _)| name == rule) {
+            import_grammar(
+                grammars,
+                kept_coord,
+                rule_coord,
+                &HashMap::new(),
+                &mut stamped,
+            );
+        }
+    }
+    subtree(&stamped, instance)
+}
+
+fn subtree(
+    grammars: &HashMap<Coordinate, Grammar>,
+    coord: &Coordinate,
+) -> HashMap<Coordinate, Grammar> {
+    let depth = coord.row_cols.len();
+    grammars
+        .iter()
+        .filter(|(c, _)| c.row_cols.len() >= depth && c.row_cols[..depth] == coord.row_cols[..])
+        .map(|(c, g)| (c.clone(), g.clone()))
+        .collect()
+}
+
+// CellChange is a cell of an instance that changes when changes to its definition are propagated
+// to it, described as it is and as it will be (`None` where there's no cell)
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellChange {
+    pub coord: Coordinate,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+// what a cell is shown as in the preview of a propagation
+fn summary(grammar: &Grammar) -> String {
+    let text = grammar.text_value();
+    if !text.is_empty() {
+        return text;
+    }
+    match grammar.kind_tag() {
+        Some(tag) => format! {"({})", tag.label()},
+        None => "(empty)".to_string(),
+    }
+}
+
+// How the cells of `instance` change when it's stamped again from the definition at
+// `defn_coord` (see `restamp`), in order. No changes means the instance is up to date.
+pub fn propagation_diff(
+    grammars: &HashMap<Coordinate, Grammar>,
+    defn_coord: &Coordinate,
+    instance: &Coordinate,
+) -> Vec<CellChange> {
+    let before = subtree(grammars, instance);
+    let after = restamp(grammars, defn_coord, instance);
+    let mut coords: Vec<&Coordinate> = before
+        .keys()
+        .chain(after.keys().filter(|c| !before.contains_key(c)))
+        .collect();
+    coords.sort_by(|a, b| a.row_cols.cmp(&b.row_cols));
+    coords
+        .into_iter()
+        .filter(|c| match (before.get(c), after.get(c)) {
+            (Some(b), Some(a)) => b.kind != a.kind || b.name != a.name,
+            _ => true,
+        })
+        .map(|c| CellChange {
+            coord: c.clone(),
+            before: before.get(c).map(summary),
+            after: after.get(c).map(summary),
+        })
+        .collect()
+}
+
+// Stamps `instance` again from the definition at `defn_coord` (see `restamp`)
+pub fn propagate(
+    grammars: &mut HashMap<Coordinate, Grammar>,
+    defn_coord: &Coordinate,
+    instance: &Coordinate,
+) {
+    let restamped = restamp(grammars, defn_coord, instance);
+    let depth = instance.row_cols.len();
+    grammars.retain(|c, _| {
+        !(c.row_cols.len() >= depth && c.row_cols[..depth] == instance.row_cols[..])
+    });
+    grammars.extend(restamped);
+}
+
+// Propagation is the state of the "Propagate changes" dialog of the definition at `defn`: its
+// instances with how each would change, and the ones opted out of the propagation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Propagation {
+    pub defn: Coordinate,
+    pub instances: Vec<(Coordinate, Vec<CellChange>)>,
+    pub excluded: Vec<Coordinate>,
+}
+
+impl Propagation {
+    pub fn new(grammars: &HashMap<Coordinate, Grammar>, defn: Coordinate) -> Propagation {
+        let instances = instances(grammars, &defn)
+            .into_iter()
+            .map(|instance| {
+                let changes = propagation_diff(grammars, &defn, &instance);
+                (instance, changes)
+            })
+            .collect();
+        Propagation {
+            defn,
+            instances,
+            excluded: vec![],
+        }
+    }
+
+    // the instances that are out of date and not opted out
+    pub fn included(&self) -> Vec<Coordinate> {
+        self.instances
+            .iter()
+            .filter(|(c, changes)| !changes.is_empty() && !self.excluded.contains(c))
+            .map(|(c, _)| c.clone())
+            .collect()
+    }
+
+    pub fn toggle(&mut self, instance: &Coordinate) {
+        match self.excluded.iter().position(|c| c == instance) {
+            Some(index) => {
+                self.excluded.remove(index);
+            }
+            None => self.excluded.push(instance.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+
+    fn table() -> HashMap<Coordinate, Grammar> {
+        hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => Grammar::input("", "first name"),
+            coord!("root-B1") => Grammar::input("", ""),
+            coord!("root-A2") => Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(1).unwrap()),
+            coord!("root-A2-A1") => Grammar::input("", "Ada"),
+            coord!("root-B2") => Grammar::input("", "36"),
+        }
+    }
+
+    #[test]
+    fn test_rule_names() {
+        let headers: Vec<String> = ["first name", "", "age", "age"]
+            .iter()
+            .map(|h| h.to_string())
+            .collect();
+        assert_eq!(
+            rule_names(&headers),
+            vec!["first_name", "rule_2", "age", "age_2"]
+        );
+        assert_eq!(unique_name("shape", &["shape".to_string()]), "shape_2");
+    }
+
+    #[test]
+    fn test_definition_from_region() {
+        let mut grammars = table();
+        let defn_coord = coord!("meta-A1");
+        assert_eq!(
+            definition_from_region(
+                &mut grammars,
+                &coord!("root"),
+                (1, 2),
+                (1, 2),
+                "person",
+                &defn_coord
+            ),
+            Ok(2)
+        );
+        assert_eq!(
+            grammars.get(&defn_coord).unwrap().kind,
+            Kind::Defn(
+                "person".to_string(),
+                defn_coord.clone(),
+                vec![
+                    ("first_name".to_string(), coord!("meta-A1-B1")),
+                    ("rule_2".to_string(), coord!("meta-A1-B2")),
+                ]
+            )
+        );
+        assert_eq!(
+            grammars.get(&coord!("meta-A1-A2")).unwrap().text_value(),
+            "rule_2"
+        );
+        if let Some(Kind::Defn(_, _, rules)) = grammars.get(&defn_coord).map(|g| &g.kind) {
+            assert_eq!(
+                rule_cells(&defn_coord, rules),
+                vec![
+                    coord!("meta-A1-A1"),
+                    coord!("meta-A1-B1"),
+                    coord!("meta-A1-A2"),
+                    coord!("meta-A1-B2")
+                ]
+            );
+        }
+        // nested grammars come along with their rule
+        assert_eq!(
+            grammars.get(&coord!("meta-A1-B1-A1")).unwrap().text_value(),
+            "Ada"
+        );
+        assert_eq!(
+            grammars.get(&coord!("meta-A1-B2")).unwrap().text_value(),
+            "36"
+        );
+        // a header column beside a column of cells
+        let mut grammars = table();
+        assert_eq!(
+            definition_from_region(
+                &mut grammars,
+                &coord!("root"),
+                (1, 1),
+                (1, 2),
+                "person",
+                &defn_coord
+            ),
+            Ok(1)
+        );
+        assert!(definition_from_region(
+            &mut grammars,
+            &coord!("root"),
+            (1, 1),
+            (1, 1),
+            "person",
+            &defn_coord
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_instantiate() {
+        let mut grammars = table();
+        definition_from_region(
+            &mut grammars,
+            &coord!("root"),
+            (1, 2),
+            (1, 2),
+            "person",
+            &coord!("meta-A1"),
+        )
+        .unwrap();
+        assert!(instantiate(
+            &mut grammars,
+            &coord!("meta-A1"),
+            &coord!("root-A1")
+        ));
+        assert_eq!(
+            grammars.get(&coord!("root-A1")).unwrap().kind,
+            Kind::Defn(
+                "person".to_string(),
+                coord!("root-A1"),
+                vec![
+                    ("first_name".to_string(), coord!("root-A1-B1")),
+                    ("rule_2".to_string(), coord!("root-A1-B2")),
+                ]
+            )
+        );
+        assert_eq!(
+            grammars.get(&coord!("root-A1-B1-A1")).unwrap().text_value(),
+            "Ada"
+        );
+        assert!(!instantiate(
+            &mut grammars,
+            &coord!("root-B2"),
+            &coord!("root-B1")
+        ));
+    }
+
+    fn greeting() -> HashMap<Coordinate, Grammar> {
+        hashmap! {
+            coord!("meta-A1") => Grammar {
+                kind: Kind::Defn(
+                    "greeting".to_string(),
+                    coord!("meta-A1"),
+                    vec![("to".to_string(), coord!("meta-A1-B1")), ("age".to_string(), coord!("meta-A1-B2"))],
+                ),
+                ..Grammar::text("greeting", "")
+            },
+            coord!("meta-A1-A1") => Grammar::text("", "to"),
+            coord!("meta-A1-B1") => Grammar::input("", "Hello {{who}}, see you on {{when: date}}"),
+            coord!("meta-A1-A2") => Grammar::text("", "age"),
+            coord!("meta-A1-B2") => Grammar::input("", "{{age: number}} ({{who}}) {{x: color}}"),
+            coord!("root-A1") => Grammar::input("", "Ada"),
+            coord!("root-B1") => Grammar::input("", "2020-05-01"),
+            coord!("root-A2") => Grammar::input("", "36"),
+            coord!("root-B2") => Grammar::input("", ""),
+        }
+    }
+
+    #[test]
+    fn test_parameters() {
+        let param = |name: &str, param_type| Parameter {
+            name: name.to_string(),
+            param_type,
+        };
+        // unknown types aren't parameters
+        assert_eq!(
+            parameters(&greeting(), &coord!("meta-A1")),
+            vec![
+                param("who", ParamType::Text),
+                param("when", ParamType::Date),
+                param("age", ParamType::Number),
+            ]
+        );
+        assert!(parameters(&greeting(), &coord!("root-A1")).is_empty());
+    }
+
+    #[test]
+    fn test_bind_arguments() {
+        let params = parameters(&greeting(), &coord!("meta-A1"));
+        let values = |v: &[&str]| -> Vec<String> { v.iter().map(|s| s.to_string()).collect() };
+        let bound = bind_arguments(&params, &values(&["Ada", "2020-05-01", " 36 "])).unwrap();
+        assert_eq!(bound.get("age"), Some(&"36".to_string()));
+        assert!(bind_arguments(&params, &values(&["Ada", "tomorrow", "36"])).is_err());
+        assert!(bind_arguments(&params, &values(&["Ada"])).is_err());
+        assert_eq!(
+            range_values(&greeting(), "root-A1:root-B2"),
+            Ok(values(&["Ada", "2020-05-01", "36", ""]))
+        );
+        assert_eq!(range_values(&greeting(), "root-A2"), Ok(values(&["36"])));
+        assert!(range_values(&greeting(), "nowhere").is_err());
+    }
+
+    #[test]
+    fn test_substitute() {
+        let mut grammars = greeting();
+        assert!(instantiate(
+            &mut grammars,
+            &coord!("meta-A1"),
+            &coord!("root-C1")
+        ));
+        let arguments = hashmap! {
+            "who".to_string() => "Ada".to_string(),
+            "when".to_string() => "2020-05-01".to_string(),
+            "age".to_string() => "36".to_string(),
+        };
+        substitute(&mut grammars, &coord!("root-C1"), &arguments);
+        assert_eq!(
+            grammars.get(&coord!("root-C1-B1")).unwrap().text_value(),
+            "Hello Ada, see you on 2020-05-01"
+        );
+        assert_eq!(
+            grammars.get(&coord!("root-C1-B2")).unwrap().text_value(),
+            "36 (Ada) {{x: color}}"
+        );
+        // the definition keeps its placeholders
+        assert_eq!(
+            grammars.get(&coord!("meta-A1-B1")).unwrap().text_value(),
+            "Hello {{who}}, see you on {{when: date}}"
+        );
+    }
+
+    #[test]
+    fn test_propagate() {
+        let mut grammars = table();
+        let defn = coord!("meta-A1");
+        definition_from_region(
+            &mut grammars,
+            &coord!("root"),
+            (1, 2),
+            (1, 2),
+            "person",
+            &defn,
+        )
+        .unwrap();
+        assert!(instantiate(&mut grammars, &defn, &coord!("root-A1")));
+        assert!(instantiate(&mut grammars, &defn, &coord!("root-B1")));
+        assert_eq!(
+            instances(&grammars, &defn),
+            vec![coord!("root-A1"), coord!("root-B1")]
+        );
+        // a value is entered into an instance, then the definition gets a new sub-rule
+        grammars.insert(coord!("root-A1-B2"), Grammar::input("rule_2", "41"));
+        grammars.insert(coord!("meta-A1-A3"), Grammar::text("", "email"));
+        grammars.insert(coord!("meta-A1-B3"), Grammar::input("email", ""));
+        if let Some(Grammar {
+            kind: Kind::Defn(_, _, rules),
+            ..
+        }) = grammars.get_mut(&defn)
+        {
+            rules.push(("email".to_string(), coord!("meta-A1-B3")));
+        }
+        let changes = propagation_diff(&grammars, &defn, &coord!("root-A1"));
+        assert_eq!(
+            changes
+                .iter()
+                .map(|c| c.coord.clone())
+                .collect::<Vec<Coordinate>>(),
+            vec![
+                coord!("root-A1"),
+                coord!("root-A1-A3"),
+                coord!("root-A1-B3")
+            ]
+        );
+        assert_eq!(changes[1].before, None);
+        assert_eq!(changes[1].after, Some("email".to_string()));
+
+        let mut propagation = Propagation::new(&grammars, defn.clone());
+        propagation.toggle(&coord!("root-B1"));
+        assert_eq!(propagation.included(), vec![coord!("root-A1")]);
+
+        propagate(&mut grammars, &defn, &coord!("root-A1"));
+        // the value entered is kept
+        assert_eq!(
+            grammars.get(&coord!("root-A1-B2")).unwrap().text_value(),
+            "41"
+        );
+        assert_eq!(
+            grammars.get(&coord!("root-A1-A3")).unwrap().text_value(),
+            "email"
+        );
+        assert!(propagation_diff(&grammars, &defn, &coord!("root-A1")).is_empty());
+        assert!(!propagation_diff(&grammars, &defn, &coord!("root-B1")).is_empty());
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::calc::reads;
+use crate::coordinate::Coordinate;
+
+// The dependency graph of the formula cells of a session: the cells each formula reads from
+// and, the other way around, the formulas reading from each cell. It's kept from one update to
+// the next, re-reading only the formulas whose text changed, so working out what an edit
+// affects doesn't parse every formula of the session again. Formulas reading from themselves
+// (through other formulas) are the cycles of the graph, see `cycles`.
+#[derive(Debug, Clone, Default)]
+pub struct DepGraph {
+    // the text of every formula, as of the last update
+    formulas: HashMap<Coordinate, String>,
+    reads: HashMap<Coordinate, Vec<Coordinate>>,
+    readers: HashMap<Coordinate, HashSet<Coordinate>>,
+}
+
+impl DepGraph {
+    // Brings the graph up to date with the formula cells of a session (see `formula_cells`),
+    // returning the formulas that were added, changed or removed
+    pub fn update(&mut self, formulas: &HashMap<Coordinate, String>) -> HashSet<Coordinate> {
+        let mut changed: HashSet<Coordinate> = self
+            .formulas
+            .keys()
+            .filter(|c| !formulas.contains_key(c))
+            .cloned()
+            .collect();
+        changed.extend(
+            formulas
+                .iter()
+                .filter(|(c, text)| self.formulas.get(c) != Some(text))
+                .map(|(c, _)| c.clone()),
+        );
+        for formula in changed.iter() {
+            self.unlink(formula);
+            if let Some(text) = formulas.get(formula) {
+                let cells = reads(text);
+                for cell in cells.iter() {
+                    self.readers.entry(cell.clone()).or_default().insert(formula.clone());
+                }
+                self.reads.insert(formula.clone(), cells);
+                self.formulas.insert(formula.clone(), text.clone());
+            }
+        }
+        changed
+    }
+
+    fn unlink(&mut self, formula: &Coordinate) {
+        self.formulas.remove(formula);
+        for cell in self.reads.remove(formula).unwrap_or_default() {
+            if let Some(readers) = self.readers.get_mut(&cell) {
+                readers.remove(formula);
+                if readers.is_empty() {
+                    self.readers.remove(&cell);
+                }
+            }
+        }
+    }
+
+    // The cells a formula reads from
+    pub fn reads(&self, formula: &Coordinate) -> &[Coordinate] {
+        self.reads.get(formula).map_or(&[], |cells| cells.as_slice())
+    }
+
+    // The formula cells that (directly or through other formulas) read from any of `changed`
+    pub fn dependents(&self, changed: &HashSet<Coordinate>) -> HashSet<Coordinate> {
+        let mut dependents: HashSet<Coordinate> = HashSet::new();
+        let mut frontier: Vec<&Coordinate> = changed.iter().collect();
+        while let Some(cell) = frontier.pop() {
+            for formula in self.readers.get(cell).into_iter().flatten() {
+                if dependents.insert(formula.clone()) {
+                    frontier.push(formula);
+                }
+            }
+        }
+        dependents
+    }
+
+    // The formulas that read from themselves, grouped by the cycle they're in (the formulas of a
+    // group all read from each other, through the group), in coordinate order
+    pub fn cycles(&self) -> Vec<Vec<Coordinate>> {
+        // Tarjan's strongly connected components, without recursion so long chains of formulas
+        // don't run out of stack
+        let mut index: HashMap<&Coordinate, usize> = HashMap::new();
+        let mut low: HashMap<&Coordinate, usize> = HashMap::new();
+        let mut stack: Vec<&Coordinate> = vec![];
+        let mut on_stack: HashSet<&Coordinate> = HashSet::new();
+        let mut cycles = vec![];
+        let mut formulas: Vec<&Coordinate> = self.reads.keys().collect();
+        formulas.sort_by(|a, b| a.row_cols.cmp(&b.row_cols));
+        for root in formulas {
+            if index.contains_key(root) {
+                continue;
+            }
+            // the formulas being visited, with how many of the cells they read were visited
+            let mut visiting: Vec<(&Coordinate, usize)> = vec![(root, 0)];
+            while let Some((formula, next)) = visiting.pop() {
+                if next == 0 {
+                    index.insert(formula, index.len());
+                    low.insert(formula, index[formula]);
+                    stack.push(formula);
+                    on_stack.insert(formula);
+                }
+                let cells = self.reads(formula);
+                if let Some(cell) = cells.get(next) {
+                    visiting.push((formula, next + 1));
+                    // cells that aren't formulas aren't in any cycle
+                    if self.reads.contains_key(cell) {
+                        if !index.contains_key(cell) {
+                            visiting.push((cell, 0));
+                        } else if on_stack.contains(cell) {
+                            low.insert(formula, low[formula].min(index[cell]));
+                        }
+                    }
+                    continue;
+                }
+                if let Some((parent, _)) = visiting.last() {
+                    low.insert(parent, low[parent].min(low[formula]));
+                }
+                if low[formula] == index[formula] {
+                    let mut component = vec![];
+                    while let Some(member) = stack.pop() {
+                        on_stack.remove(member);
+                        component.push(member.clone());
+                        if member == formula {
+                            break;
+                        }
+                    }
+                    if component.len() > 1 || cells.contains(formula) {
+                        component.sort_by(|a, b| a.row_cols.cmp(&b.row_cols));
+                        cycles.push(component);
+                    }
+                }
+            }
+        }
+        cycles.sort_by(|a, b| a[0].row_cols.cmp(&b[0].row_cols));
+        cycles
+    }
+
+    // The formulas in any cycle
+    pub fn cyclic(&self) -> HashSet<Coordinate> {
+        self.cycles().into_iter().flatten().collect()
+    }
+
+    // The shortest way from a formula back to itself through the cells it reads from, starting
+    // and ending with it, if it's in a cycle
+    pub fn cycle_through(&self, formula: &Coordinate) -> Option<Vec<Coordinate>> {
+        let mut came_from: HashMap<&Coordinate, &Coordinate> = HashMap::new();
+        let mut queue: VecDeque<&Coordinate> = VecDeque::new();
+        queue.push_back(formula);
+        while let Some(cell) = queue.pop_front() {
+            for next in self.reads(cell) {
+                if next == formula {
+                    let mut path = vec![formula.clone()];
+                    let mut at = cell;
+                    while at != formula {
+                        path.push(at.clone());
+                        at = came_from[at];
+                    }
+                    path.push(formula.clone());
+                    path.reverse();
+                    return Some(path);
+                }
+                if self.reads.contains_key(next) && !came_from.contains_key(next) {
+                    came_from.insert(next, cell);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+
+    fn set(coords: &[Coordinate]) -> HashSet<Coordinate> {
+        coords.iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_dependents() {
+        let mut formulas = hashmap! {
+            coord!("root-B1") => "=root-A1 * 2".to_string(),
+            coord!("root-C1") => "=SUM(root-A1:root-B1)".to_string(),
+            coord!("root-D1") => "=root-C1".to_string(),
+            coord!("root-E1") => "=root-A2".to_string(),
+        };
+        let mut deps = DepGraph::default();
+        assert_eq!(deps.update(&formulas).len(), 4);
+        assert_eq!(
+            deps.dependents(&set(&[coord!("root-A1")])),
+            set(&[coord!("root-B1"), coord!("root-C1"), coord!("root-D1")])
+        );
+
+        // only the formulas that changed are read again
+        formulas.insert(coord!("root-D1"), "=root-A2".to_string());
+        formulas.remove(&coord!("root-E1"));
+        assert_eq!(deps.update(&formulas), set(&[coord!("root-D1"), coord!("root-E1")]));
+        assert!(deps.update(&formulas).is_empty());
+        assert_eq!(
+            deps.dependents(&set(&[coord!("root-A1")])),
+            set(&[coord!("root-B1"), coord!("root-C1")])
+        );
+        assert_eq!(deps.dependents(&set(&[coord!("root-A2")])), set(&[coord!("root-D1")]));
+        assert!(deps.reads(&coord!("root-E1")).is_empty());
+        assert!(deps.cycles().is_empty());
+    }
+
+    #[test]
+    fn test_cycles() {
+        let formulas = hashmap! {
+            coord!("root-A1") => "=root-C1 + 1".to_string(),
+            coord!("root-B1") => "=root-A1".to_string(),
+            coord!("root-C1") => "=root-B1".to_string(),
+            coord!("root-D1") => "=root-A1".to_string(),
+            coord!("root-E1") => "=root-E1".to_string(),
+            coord!("root-F1") => "=root-D1 + root-G1".to_string(),
+        };
+        let mut deps = DepGraph::default();
+        deps.update(&formulas);
+        assert_eq!(
+            deps.cycles(),
+            vec![
+                vec![coord!("root-A1"), coord!("root-B1"), coord!("root-C1")],
+                vec![coord!("root-E1")],
+            ]
+        );
+        assert_eq!(
+            deps.cycle_through(&coord!("root-B1")),
+            Some(vec![
+                coord!("root-B1"),
+                coord!("root-A1"),
+                coord!("root-C1"),
+                coord!("root-B1")
+            ])
+        );
+        assert_eq!(
+            deps.cycle_through(&coord!("root-E1")),
+            Some(vec![coord!("root-E1"), coord!("root-E1")])
+        );
+        // reading from a cycle isn't being in one
+        assert_eq!(deps.cycle_through(&coord!("root-D1")), None);
+        assert!(!deps.cyclic().contains(&coord!("root-F1")));
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::{BTreeMap, HashMap};
+use std::mem::size_of;
+use std::num::NonZeroU32;
+
+use crate::coordinate::Coordinate;
+use crate::grammar::Grammar;
+use crate::history::{History, Snapshot};
+
+// Diagnostics is what the diagnostics panel reports about a session, to help tell why it feels
+// slow: how many grammars it has, how deeply they're nested, and roughly how much memory the
+// grammar map holds on to. What the undo history holds is estimated apart (see `history_bytes`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostics {
+    pub grammar_count: usize,
+    // number of grammars at each coordinate depth (root-A1 is at depth 1)
+    pub depths: BTreeMap<usize, usize>,
+    pub grammar_map_bytes: usize,
+    pub undo_steps: usize,
+    pub redo_steps: usize,
+}
+
+pub fn diagnose(
+    grammars: &HashMap<Coordinate, Grammar>,
+    history: &History<Snapshot>,
+) -> Diagnostics {
+    let mut depths = BTreeMap::new();
+    for coord in grammars.keys() {
+        *depths.entry(coord.row_cols.len()).or_insert(0) += 1;
+    }
+    Diagnostics {
+        grammar_count: grammars.len(),
+        depths,
+        grammar_map_bytes: estimate_bytes(grammars),
+        undo_steps: history.undo_len(),
+        redo_steps: history.redo_len(),
+    }
+}
+
+// A rough estimate of the memory the undo history takes, which goes through every grammar of
+// every state it holds, so it's worth caching until the history changes
+pub fn history_bytes(history: &History<Snapshot>) -> usize {
+    history
+        .states()
+        .map(|s| estimate_bytes(&s.session.grammars))
+        .sum()
+}
+
+// A rough estimate of the memory a grammar map takes: the entries themselves, the coordinates'
+// row/col lists, and the size of each grammar's contents when saved, which stands in for the
+// strings and lists they own
+pub fn estimate_bytes(grammars: &HashMap<Coordinate, Grammar>) -> usize {
+    let entry_size = size_of::<(Coordinate, Grammar)>();
+    let row_col_size = size_of::<(NonZeroU32, NonZeroU32)>();
+    grammars
+        .iter()
+        .map(|(coord, grammar)| {
+            let contents = serde_json::to_string(grammar)
+                .map(|s| s.len())
+                .unwrap_or_default();
+            entry_size + coord.row_cols.len() * row_col_size + contents
+        })
+        .sum()
+}
+
+// Formats a byte count with the largest unit that keeps it at or above 1
+pub fn format_bytes(bytes: usize) -> String {
+    match bytes {
+        b if b >= 1 << 20 => format! {"{:.1} MB", b as f64 / (1 << 20) as f64},
+        b if b >= 1 << 10 => format! {"{:.1} KB", b as f64 / (1 << 10) as f64},
+        b => format! {"{} B", b},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+
+    #[test]
+    fn test_diagnose() {
+        let grammars = hashmap! {
+            coord!("root-A1") => Grammar::text("a".to_string(), "hello".to_string()),
+            coord!("root-A2") => Grammar::default(),
+            coord!("root-A2-A1") => Grammar::default(),
+        };
+        let diagnostics = diagnose(&grammars, &History::new());
+        assert_eq!(diagnostics.grammar_count, 3);
+        assert_eq!(diagnostics.depths, btreemap! { 2 => 2, 3 => 1 });
+        assert_eq!((diagnostics.undo_steps, diagnostics.redo_steps), (0, 0));
+        assert_eq!(history_bytes(&History::new()), 0);
+        assert!(estimate_bytes(&grammars) > 3 * size_of::<(Coordinate, Grammar)>());
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KB");
+        assert_eq!(format_bytes(3 << 20), "3.0 MB");
+    }
+}
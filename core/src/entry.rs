@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+
+use crate::coordinate::Coordinate;
+use crate::grammar::Grammar;
+
+// What pressing Enter in a cell does: start a new line in it, or move to the next cell
+// down or to the right (Shift+Enter moves the other way)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum EnterBehavior {
+    Newline,
+    #[default]
+    Down,
+    Right,
+}
+
+// The cell Enter moves to from `current`. When `range` is a data entry range (a selection in
+// the grid of `current` that contains it), Enter goes through its cells in order, wrapping at
+// its edges to the next column (moving down) or row (moving right) and from its last cell back
+// to its first. Outside of one, Enter moves to the neighbor, if there is one.
+pub fn next_cell(
+    grammars: &HashMap<Coordinate, Grammar>,
+    current: &Coordinate,
+    range: Option<(&Coordinate, &Coordinate)>,
+    behavior: EnterBehavior,
+    backwards: bool,
+) -> Option<Coordinate> {
+    if behavior == EnterBehavior::Newline {
+        return None;
+    }
+    let down = behavior == EnterBehavior::Down;
+    let parent = current.parent()?;
+    let (row, col) = (current.row().get(), current.col().get());
+    let cell = |r: u32, c: u32| {
+        Some(Coordinate::child_of(
+            &parent,
+            (NonZeroU32::new(r)?, NonZeroU32::new(c)?),
+        ))
+    };
+    let region = range
+        .filter(|(first, last)| {
+            first.parent().as_ref() == Some(&parent) && last.parent().as_ref() == Some(&parent)
+        })
+        .map(|(first, last)| {
+            let (r0, r1) = (
+                first.row().get().min(last.row().get()),
+                first.row().get().max(last.row().get()),
+            );
+            let (c0, c1) = (
+                first.col().get().min(last.col().get()),
+                first.col().get().max(last.col().get()),
+            );
+            ((r0, c0), (r1, c1))
+        })
+        .filter(|((r0, c0), (r1, c1))| (*r0..=*r1).contains(&row) && (*c0..=*c1).contains(&col));
+    let ((r0, c0), (r1, c1)) = match region {
+        Some(region) => region,
+        None => {
+            let (r, c) = match (down, backwards) {
+                (true, false) => (row + 1, col),
+                (true, true) => (row - 1, col),
+                (false, false) => (row, col + 1),
+                (false, true) => (row, col - 1),
+            };
+            return cell(r, c).filter(|c| grammars.contains_key(c));
+        }
+    };
+    let (rows, cols) = (r1 - r0 + 1, c1 - c0 + 1);
+    let total = rows * cols;
+    // position of a cell in the order the range is filled in
+    let index_of = |r: u32, c: u32| {
+        if down {
+            (c - c0) * rows + (r - r0)
+        } else {
+            (r - r0) * cols + (c - c0)
+        }
+    };
+    let at = |index: u32| {
+        if down {
+            (r0 + index % rows, c0 + index / rows)
+        } else {
+            (r0 + index / cols, c0 + index % cols)
+        }
+    };
+    let mut index = index_of(row, col);
+    // cells hidden by a merge are skipped
+    for _ in 1..total {
+        index = if backwards {
+            (index + total - 1) % total
+        } else {
+            (index + 1) % total
+        };
+        let (r, c) = at(index);
+        if let Some(next) = cell(r, c) {
+            if grammars.get(&next).is_some_and(|g| g.style.display) {
+                return Some(next);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use crate::util::non_zero_u32_tuple;
+
+    fn grid() -> HashMap<Coordinate, Grammar> {
+        let mut grammars = HashMap::new();
+        for row in 1..=3 {
+            for col in 1..=3 {
+                grammars.insert(
+                    Coordinate::child_of(&coord!("root"), non_zero_u32_tuple((row, col))),
+                    Grammar::default(),
+                );
+            }
+        }
+        grammars
+    }
+
+    #[test]
+    fn test_next_cell_without_range() {
+        let grammars = grid();
+        let down = |c: &str, back| {
+            next_cell(
+                &grammars,
+                &Coordinate::parse(c).unwrap(),
+                None,
+                EnterBehavior::Down,
+                back,
+            )
+        };
+        assert_eq!(down("root-A1", false), Some(coord!("root-A2")));
+        assert_eq!(down("root-A2", true), Some(coord!("root-A1")));
+        assert_eq!(down("root-A3", false), None);
+        assert_eq!(
+            next_cell(
+                &grammars,
+                &coord!("root-A1"),
+                None,
+                EnterBehavior::Right,
+                false
+            ),
+            Some(coord!("root-B1"))
+        );
+        assert_eq!(
+            next_cell(
+                &grammars,
+                &coord!("root-A1"),
+                None,
+                EnterBehavior::Newline,
+                false
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_next_cell_in_range() {
+        let mut grammars = grid();
+        let (first, last) = (coord!("root-A1"), coord!("root-B2"));
+        let range = Some((&first, &last));
+        let next = |grammars: &HashMap<Coordinate, Grammar>, c: Coordinate, behavior, back| {
+            next_cell(grammars, &c, range, behavior, back)
+        };
+        // moving right wraps to the first column of the next row, and back to the start
+        assert_eq!(
+            next(&grammars, coord!("root-B1"), EnterBehavior::Right, false),
+            Some(coord!("root-A2"))
+        );
+        assert_eq!(
+            next(&grammars, coord!("root-B2"), EnterBehavior::Right, false),
+            Some(coord!("root-A1"))
+        );
+        assert_eq!(
+            next(&grammars, coord!("root-A2"), EnterBehavior::Right, true),
+            Some(coord!("root-B1"))
+        );
+        // moving down wraps to the first row of the next column
+        assert_eq!(
+            next(&grammars, coord!("root-A2"), EnterBehavior::Down, false),
+            Some(coord!("root-B1"))
+        );
+        // cells hidden by a merge are skipped
+        grammars.get_mut(&coord!("root-A2")).unwrap().style.display = false;
+        assert_eq!(
+            next(&grammars, coord!("root-B1"), EnterBehavior::Right, false),
+            Some(coord!("root-B2"))
+        );
+        // outside of the range, it's ignored
+        assert_eq!(
+            next(&grammars, coord!("root-C3"), EnterBehavior::Down, true),
+            Some(coord!("root-C2"))
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+use crate::coordinate::Coordinate;
+use crate::export::{cell_text, Region};
+use crate::external::grid_table;
+use crate::grammar::{Grammar, Kind};
+
+// Exports a grid as CSV or TSV. Nested grids are either summarized in their cell (see
+// `cell_text`), or flattened: the first row of the grid is taken as its header, and a column
+// holding nested grids is spread over one column per cell of them, headed by the path to the
+// cell, e.g. "address/B1" for the B1 cell of the grids in the "address" column.
+
+// The rows the grid is exported as, or `None` if `grid` isn't a grid
+pub fn flatten(
+    grammars: &HashMap<Coordinate, Grammar>,
+    grid: &Coordinate,
+    flatten_nested: bool,
+) -> Option<Vec<Vec<String>>> {
+    if !flatten_nested {
+        return grid_table(grammars, grid);
+    }
+    let region = Region::of_grid(grammars, grid)?;
+    let mut header = vec![];
+    let mut body: Vec<Vec<String>> = vec![vec![]; (region.rows.1 - region.rows.0) as usize];
+    for col in region.cols.0..=region.cols.1 {
+        let name = cell_text(grammars, &region.cell(region.rows.0, col))
+            .trim()
+            .to_string();
+        let name = if name.is_empty() {
+            format! {"column_{}", col}
+        } else {
+            name
+        };
+        // the cells of every row under this column, by path
+        let mut paths: Vec<String> = vec![];
+        let mut rows: Vec<HashMap<String, String>> = vec![];
+        for row in region.rows.0 + 1..=region.rows.1 {
+            let mut cells = vec![];
+            leaves(grammars, &region.cell(row, col), name.clone(), &mut cells);
+            for (path, _) in cells.iter() {
+                if !paths.contains(path) {
+                    paths.push(path.clone());
+                }
+            }
+            rows.push(cells.into_iter().collect());
+        }
+        if paths.is_empty() {
+            paths.push(name);
+        }
+        for (cells, row) in rows.iter().zip(body.iter_mut()) {
+            row.extend(
+                paths
+                    .iter()
+                    .map(|p| cells.get(p).cloned().unwrap_or_default()),
+            );
+        }
+        header.extend(paths);
+    }
+    let mut table = vec![header];
+    table.extend(body);
+    Some(table)
+}
+
+// the (path, text) of the cells a cell is exported as: itself, or the cells of the grid nested
+// in it (and so on), with their coordinate within it appended to `path`
+fn leaves(
+    grammars: &HashMap<Coordinate, Grammar>,
+    coord: &Coordinate,
+    path: String,
+    cells: &mut Vec<(String, String)>,
+) {
+    match grammars.get(coord) {
+        Some(Grammar {
+            kind: Kind::Grid(_),
+            style,
+            ..
+        }) if style.display => {
+            let region = Region::of_grid(grammars, coord).unwrap();
+            let prefix = coord.to_string().len() + 1;
+            for row in region.rows.0..=region.rows.1 {
+                for col in region.cols.0..=region.cols.1 {
+                    let cell = region.cell(row, col);
+                    let fragment = cell.to_string()[prefix..].to_string();
+                    leaves(grammars, &cell, format! {"{}/{}", path, fragment}, cells);
+                }
+            }
+        }
+        _ => cells.push((path, cell_text(grammars, coord))),
+    }
+}
+
+// Writes rows separated by `delimiter` (b',' for CSV, b'\t' for TSV), quoting the cells that
+// need it
+pub fn to_delimited(table: &[Vec<String>], delimiter: u8) -> String {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_writer(vec![]);
+    for row in table {
+        // writing to a Vec can't fail
+        writer.write_record(row).unwrap();
+    }
+    String::from_utf8(writer.into_inner().unwrap()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn test_flatten() {
+        let grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(3).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => Grammar::input("", "name"),
+            coord!("root-B1") => Grammar::input("", "address"),
+            coord!("root-A2") => Grammar::input("", "Ada"),
+            coord!("root-B2") => Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-B2-A1") => Grammar::input("", "12 Main St"),
+            coord!("root-B2-B1") => Grammar::input("", "Springfield, IL"),
+            coord!("root-A3") => Grammar::input("", "Grace"),
+            coord!("root-B3") => Grammar::input("", "unknown"),
+        };
+        let summarized = flatten(&grammars, &coord!("root"), false).unwrap();
+        assert_eq!(summarized[1][1], "[1x2 grid: 12 Main St...]");
+
+        let table = flatten(&grammars, &coord!("root"), true).unwrap();
+        assert_eq!(
+            to_delimited(&table, b','),
+            "name,address/A1,address/B1,address\n\
+             Ada,12 Main St,\"Springfield, IL\",\n\
+             Grace,,,unknown\n"
+        );
+        assert_eq!(
+            to_delimited(&table[..1], b'\t'),
+            "name\taddress/A1\taddress/B1\taddress\n"
+        );
+        assert_eq!(flatten(&grammars, &coord!("root-A1"), true), None);
+    }
+}
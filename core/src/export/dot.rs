@@ -0,0 +1,145 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::coordinate::Coordinate;
+use crate::formula::{is_formula, parse, references, Reference};
+use crate::grammar::{Grammar, Kind, Lookup};
+
+// longest cell text shown in a node's label
+const LABEL_TEXT_LIMIT: usize = 24;
+
+// What the DOT graph covers: only the cells under `subtree` (all of them without one), and
+// optionally how grids nest, on top of the formula and lookup dependencies
+#[derive(Debug, Clone, PartialEq)]
+pub struct DotOptions {
+    pub subtree: Option<Coordinate>,
+    pub hierarchy: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum EdgeKind {
+    Formula,
+    Lookup,
+    Nesting,
+}
+
+// Serializes the dependency graph of a session in Graphviz's DOT format. Formulas point to the
+// cells they read (solid), lookups to what they look up (dashed) and, with `hierarchy`, grids
+// to their cells (dotted). Ranges, rows and columns are nodes of their own.
+pub fn to_dot(grammars: &HashMap<Coordinate, Grammar>, options: &DotOptions) -> String {
+    let in_subtree = |c: &Coordinate| match &options.subtree {
+        Some(root) => c.row_cols.starts_with(&root.row_cols),
+        None => true,
+    };
+    let mut edges: BTreeSet<(String, String, EdgeKind)> = BTreeSet::new();
+    for (coord, grammar) in grammars.iter().filter(|(c, _)| in_subtree(c)) {
+        let from = coord.to_string();
+        match &grammar.kind {
+            Kind::Input(text) if is_formula(text) => {
+                if let Ok(expr) = parse(text) {
+                    for reference in references(&expr) {
+                        let to = match reference {
+                            Reference::Cell(c) => c.to_string(),
+                            Reference::Range(start, end) => format! {"{}:{}", start.to_string(), end.to_string()},
+                        };
+                        edges.insert((from.clone(), to, EdgeKind::Formula));
+                    }
+                }
+            }
+            Kind::Lookup(_, Some(lookup)) => {
+                let to = match lookup {
+                    Lookup::Cell(c) => c.to_string(),
+                    Lookup::Range { parent, start, end } => format! {
+                        "{}:{}",
+                        Coordinate::child_of(parent, *start).to_string(),
+                        Coordinate::child_of(parent, *end).to_string()
+                    },
+                    Lookup::Row(row) => format! {"{} row {}", row.0.to_string(), row.1},
+                    Lookup::Col(col) => format! {"{} col {}", col.0.to_string(), col.1},
+                };
+                edges.insert((from.clone(), to, EdgeKind::Lookup));
+            }
+            Kind::Grid(sub_coords) if options.hierarchy => {
+                for sub_coord in sub_coords {
+                    let child = Coordinate::child_of(coord, *sub_coord);
+                    if grammars.contains_key(&child) {
+                        edges.insert((from.clone(), child.to_string(), EdgeKind::Nesting));
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let mut nodes: BTreeSet<&str> = BTreeSet::new();
+    for (from, to, _) in edges.iter() {
+        nodes.insert(from);
+        nodes.insert(to);
+    }
+    let mut lines = vec!["digraph dependencies {".to_string(), "  node [shape=box, fontsize=10];".to_string()];
+    for node in nodes {
+        // ranges, rows and columns aren't cells, though they start with the coordinate of one
+        let cell = Coordinate::parse(node).filter(|c| c.to_string() == node);
+        let label = match cell.as_ref().and_then(|c| grammars.get(c)) {
+            Some(grammar) => {
+                let text: String = grammar.text_value().chars().take(LABEL_TEXT_LIMIT).collect();
+                if text.is_empty() {
+                    node.to_string()
+                } else {
+                    format! {"{}\\n{}", node, escape(&text)}
+                }
+            }
+            None => node.to_string(),
+        };
+        let shape = if cell.is_some() { "" } else { ", shape=folder" };
+        lines.push(format! {"  \"{}\" [label=\"{}\"{}];", escape(node), label, shape});
+    }
+    for (from, to, kind) in edges.iter() {
+        let style = match kind {
+            EdgeKind::Formula => "",
+            EdgeKind::Lookup => " [style=dashed]",
+            EdgeKind::Nesting => " [style=dotted, arrowhead=none]",
+        };
+        lines.push(format! {"  \"{}\" -> \"{}\"{};", escape(from), escape(to), style});
+    }
+    lines.push("}".to_string());
+    lines.join("\n")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn test_to_dot() {
+        let grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => Grammar::input("", "1"),
+            coord!("root-A2") => Grammar::input("", "=root-A1 * 2"),
+            coord!("root-B1") => Grammar::input("", "=SUM(root-A1:root-A2)"),
+            coord!("root-B2") => Grammar {
+                kind: Kind::Lookup("".to_string(), Some(Lookup::Cell(coord!("root-B1")))),
+                ..Grammar::default()
+            },
+        };
+        let dot = to_dot(&grammars, &DotOptions { subtree: None, hierarchy: false });
+        assert!(dot.starts_with("digraph dependencies {"));
+        assert!(dot.contains("  \"root-A2\" -> \"root-A1\";"));
+        assert!(dot.contains("  \"root-B1\" -> \"root-A1:root-A2\";"));
+        assert!(dot.contains("  \"root-B2\" -> \"root-B1\" [style=dashed];"));
+        assert!(dot.contains("  \"root-A1:root-A2\" [label=\"root-A1:root-A2\", shape=folder];"));
+        assert!(dot.contains("  \"root-A2\" [label=\"root-A2\\n=root-A1 * 2\"];"));
+        assert!(!dot.contains("arrowhead"));
+
+        let with_hierarchy = to_dot(&grammars, &DotOptions { subtree: None, hierarchy: true });
+        assert!(with_hierarchy.contains("  \"root\" -> \"root-A1\" [style=dotted, arrowhead=none];"));
+
+        let subtree = to_dot(&grammars, &DotOptions { subtree: Some(coord!("root-B2")), hierarchy: false });
+        assert_eq!(subtree.matches(" -> ").count(), 1);
+    }
+}
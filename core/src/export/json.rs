@@ -0,0 +1,214 @@
+use serde::ser::{SerializeMap, SerializeSeq, Serializer};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::coordinate::Coordinate;
+use crate::export::{cell_text, Region};
+use crate::grammar::{Grammar, Kind};
+use crate::value::Value;
+
+// Exports a table as JSON records: the first row of the region is its header, whose cells are
+// the keys of the objects every other row becomes. A nested grid becomes an array: of the values
+// of its cells if it's a single row or column, or of records headed by its own first row.
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct JsonExport {
+    // indented over several lines, rather than all on one
+    pub pretty: bool,
+    // cells are typed by what they hold (see `Value`): numbers as numbers and empty cells as
+    // null, rather than every cell as a string
+    pub coerce: bool,
+}
+
+impl Default for JsonExport {
+    fn default() -> Self {
+        JsonExport {
+            pretty: true,
+            coerce: true,
+        }
+    }
+}
+
+// Json is what's exported, keeping the keys of records in the order of their columns
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Number(f64),
+    Text(String),
+    Array(Vec<Json>),
+    Record(Vec<(String, Json)>),
+}
+
+impl Serialize for Json {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Json::Null => serializer.serialize_unit(),
+            // whole numbers are written without a fraction, like they're typed
+            Json::Number(n) if n.fract() == 0.0 && n.abs() < 9007199254740992.0 => serializer.serialize_i64(*n as i64),
+            Json::Number(n) => serializer.serialize_f64(*n),
+            Json::Text(text) => serializer.serialize_str(text),
+            Json::Array(values) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for value in values {
+                    seq.serialize_element(value)?;
+                }
+                seq.end()
+            }
+            Json::Record(fields) => {
+                let mut map = serializer.serialize_map(Some(fields.len()))?;
+                for (key, value) in fields {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+// The rows of the region under its first one as records, leaving out the rows with no content
+pub fn records(grammars: &HashMap<Coordinate, Grammar>, region: &Region, coerce: bool) -> Json {
+    let mut keys: Vec<String> = vec![];
+    for col in region.cols.0..=region.cols.1 {
+        let key = cell_text(grammars, &region.cell(region.rows.0, col)).trim().to_string();
+        let key = if key.is_empty() {
+            format! {"column_{}", col}
+        } else {
+            key
+        };
+        // repeated headers get numbered, so no column is lost
+        let mut unique = key.clone();
+        let mut n = 2;
+        while keys.contains(&unique) {
+            unique = format! {"{}_{}", key, n};
+            n += 1;
+        }
+        keys.push(unique);
+    }
+    let rows = (region.rows.0 + 1..=region.rows.1).filter_map(|row| {
+        let cells: Vec<Coordinate> = (region.cols.0..=region.cols.1)
+            .map(|col| region.cell(row, col))
+            .collect();
+        if cells.iter().all(|c| cell_text(grammars, c).trim().is_empty()) {
+            return None;
+        }
+        Some(Json::Record(
+            keys.iter()
+                .cloned()
+                .zip(cells.iter().map(|c| value(grammars, c, coerce)))
+                .collect(),
+        ))
+    });
+    Json::Array(rows.collect())
+}
+
+fn value(grammars: &HashMap<Coordinate, Grammar>, coord: &Coordinate, coerce: bool) -> Json {
+    match grammars.get(coord) {
+        Some(Grammar {
+            kind: Kind::Grid(_),
+            style,
+            ..
+        }) if style.display => {
+            let region = Region::of_grid(grammars, coord).unwrap();
+            if region.rows.0 == region.rows.1 || region.cols.0 == region.cols.1 {
+                let mut values = vec![];
+                for row in region.rows.0..=region.rows.1 {
+                    for col in region.cols.0..=region.cols.1 {
+                        values.push(value(grammars, &region.cell(row, col), coerce));
+                    }
+                }
+                Json::Array(values)
+            } else {
+                records(grammars, &region, coerce)
+            }
+        }
+        _ => scalar(&cell_text(grammars, coord), coerce),
+    }
+}
+
+fn scalar(text: &str, coerce: bool) -> Json {
+    if !coerce {
+        return Json::Text(text.to_string());
+    }
+    if text.trim().is_empty() {
+        return Json::Null;
+    }
+    match Value::parse(text) {
+        Value::Number(n) => Json::Number(n),
+        // dates are written the one way they're parsed, YYYY-MM-DD
+        date @ Value::Date(_) => Json::Text(date.to_string()),
+        Value::Text(text) => Json::Text(text),
+    }
+}
+
+pub fn to_json(grammars: &HashMap<Coordinate, Grammar>, region: &Region, options: JsonExport) -> String {
+    let records = records(grammars, region, options.coerce);
+    let json = if options.pretty {
+        serde_json::to_string_pretty(&records)
+    } else {
+        serde_json::to_string(&records)
+    };
+    // records only hold strings, numbers that are finite & nulls, which always serialize
+    json.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn test_to_json() {
+        let grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(4).unwrap(), NonZeroU32::new(3).unwrap()),
+            coord!("root-A1") => Grammar::input("", "name"),
+            coord!("root-B1") => Grammar::input("", "price"),
+            coord!("root-C1") => Grammar::input("", "price"),
+            coord!("root-A2") => Grammar::input("", "apple"),
+            coord!("root-B2") => Grammar::input("", "3.50"),
+            coord!("root-C2") => Grammar::input("", "2020-02-29"),
+            coord!("root-A3") => Grammar::input("", ""),
+            coord!("root-B3") => Grammar::input("", ""),
+            coord!("root-C3") => Grammar::input("", ""),
+            coord!("root-A4") => Grammar::input("", "pear"),
+            coord!("root-B4") => Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-B4-A1") => Grammar::input("", "1"),
+            coord!("root-B4-B1") => Grammar::input("", "2"),
+            coord!("root-C4") => Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-C4-A1") => Grammar::input("", "size"),
+            coord!("root-C4-B1") => Grammar::input("", "ripe"),
+            coord!("root-C4-A2") => Grammar::input("", "L"),
+            coord!("root-C4-B2") => Grammar::input("", ""),
+        };
+        let region = Region::of_grid(&grammars, &coord!("root")).unwrap();
+        assert_eq!(
+            to_json(
+                &grammars,
+                &region,
+                JsonExport {
+                    pretty: false,
+                    coerce: true
+                }
+            ),
+            r#"[{"name":"apple","price":3.5,"price_2":"2020-02-29"},{"name":"pear","price":[1,2],"price_2":[{"size":"L","ripe":null}]}]"#
+        );
+        assert_eq!(
+            to_json(
+                &grammars,
+                &region,
+                JsonExport {
+                    pretty: false,
+                    coerce: false
+                }
+            ),
+            r#"[{"name":"apple","price":"3.50","price_2":"2020-02-29"},{"name":"pear","price":["1","2"],"price_2":[{"size":"L","ripe":""}]}]"#
+        );
+
+        let region = Region::from_selection(&coord!("root-A1"), &coord!("root-B2")).unwrap();
+        assert_eq!(
+            to_json(&grammars, &region, JsonExport::default()),
+            "[\n  {\n    \"name\": \"apple\",\n    \"price\": 3.5\n  }\n]"
+        );
+    }
+}
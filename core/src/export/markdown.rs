@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+
+use crate::coordinate::Coordinate;
+use crate::export::{cell_text, Region};
+use crate::grammar::Grammar;
+
+// Serializes a region into a pipe-delimited Markdown table. Markdown tables need a header, so
+// the first row of the region is used as one. Rows & columns of collapsed outline groups are
+// included, as in every export (see `outline::is_hidden`).
+pub fn to_markdown(grammars: &HashMap<Coordinate, Grammar>, region: &Region) -> String {
+    let mut lines = vec![];
+    for row in region.rows.0..=region.rows.1 {
+        let cells: Vec<String> = (region.cols.0..=region.cols.1)
+            .map(|col| escape(&cell_text(grammars, &region.cell(row, col))))
+            .collect();
+        lines.push(format! {"| {} |", cells.join(" | ")});
+        if row == region.rows.0 {
+            let separator: Vec<&str> = cells.iter().map(|_| "---").collect();
+            lines.push(format! {"| {} |", separator.join(" | ")});
+        }
+    }
+    lines.join("\n")
+}
+
+// pipes would end a table cell early, and newlines the whole row
+fn escape(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', "<br>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn test_to_markdown() {
+        let grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => Grammar::input("", "name"),
+            coord!("root-B1") => Grammar::input("", "a|b"),
+            coord!("root-A2") => Grammar::input("", "x"),
+            coord!("root-B2") => Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(3).unwrap()),
+            coord!("root-B2-A1") => Grammar::input("", "y"),
+        };
+        let region = Region::of_grid(&grammars, &coord!("root")).unwrap();
+        assert_eq!(
+            to_markdown(&grammars, &region),
+            "| name | a\\|b |\n| --- | --- |\n| x | [2x3 grid: y...] |"
+        );
+        let selection = Region::from_selection(&coord!("root-B2"), &coord!("root-A2")).unwrap();
+        assert_eq!(to_markdown(&grammars, &selection), "| x | [2x3 grid: y...] |\n| --- | --- |");
+    }
+}
@@ -0,0 +1,88 @@
+// Exporters that turn (parts of) a session into formats used outside of the app
+pub mod delimited;
+pub mod dot;
+pub mod json;
+pub mod markdown;
+pub mod schema;
+
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+
+use crate::coordinate::Coordinate;
+use crate::grammar::{Grammar, Kind};
+
+// Region is a rectangular block of cells of a grid, with inclusive row/col bounds
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    pub parent: Coordinate,
+    pub rows: (u32, u32),
+    pub cols: (u32, u32),
+}
+
+impl Region {
+    // the region covered by a selection, whose first and last cells need to share a grid
+    pub fn from_selection(first: &Coordinate, last: &Coordinate) -> Option<Region> {
+        let parent = first.parent()?;
+        if last.parent() != Some(parent.clone()) {
+            return None;
+        }
+        let (first_row, first_col) = first.row_col();
+        let (last_row, last_col) = last.row_col();
+        Some(Region {
+            parent,
+            rows: (
+                first_row.get().min(last_row.get()),
+                first_row.get().max(last_row.get()),
+            ),
+            cols: (
+                first_col.get().min(last_col.get()),
+                first_col.get().max(last_col.get()),
+            ),
+        })
+    }
+
+    // the whole of a grid
+    pub fn of_grid(grammars: &HashMap<Coordinate, Grammar>, grid: &Coordinate) -> Option<Region> {
+        if let Some(Grammar {
+            kind: Kind::Grid(sub_coords),
+            ..
+        }) = grammars.get(grid)
+        {
+            let (rows, cols) = sub_coords.iter().fold((1, 1), |(rows, cols), (r, c)| {
+                (rows.max(r.get()), cols.max(c.get()))
+            });
+            return Some(Region {
+                parent: grid.clone(),
+                rows: (1, rows),
+                cols: (1, cols),
+            });
+        }
+        None
+    }
+
+    pub fn cell(&self, row: u32, col: u32) -> Coordinate {
+        Coordinate::child_of(
+            &self.parent,
+            (NonZeroU32::new(row).unwrap(), NonZeroU32::new(col).unwrap()),
+        )
+    }
+}
+
+// Plain text of a cell as shown in exports. Nested grids are summarized inline by their size
+// and the text of their first cell.
+pub fn cell_text(grammars: &HashMap<Coordinate, Grammar>, coord: &Coordinate) -> String {
+    match grammars.get(coord) {
+        Some(Grammar { style, .. }) if !style.display => String::new(),
+        Some(Grammar {
+            kind: Kind::Grid(_),
+            ..
+        }) => {
+            let region = Region::of_grid(grammars, coord).unwrap();
+            let first = cell_text(grammars, &region.cell(1, 1));
+            format! {"[{}x{} grid{}]", region.rows.1, region.cols.1,
+                if first.is_empty() { "".to_string() } else { format!{": {}...", first} }}
+        }
+        Some(g) => g.text_value(),
+        None => String::new(),
+    }
+}
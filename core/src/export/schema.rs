@@ -0,0 +1,281 @@
+use serde_json::{json, Map, Value as JsonValue};
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+
+use crate::coordinate::Coordinate;
+use crate::defn::unique_name;
+use crate::export::{cell_text, Region};
+use crate::external::write_table;
+use crate::grammar::{Grammar, Interactive, Kind};
+use crate::shape::Shape;
+use crate::value::Value;
+
+const DRAFT: &str = "http://json-schema.org/draft-07/schema#";
+
+// Derives a JSON Schema from a table grid: an array of objects with a property for each column,
+// named by the header row. The type of a property is the shape declared on the cells of its
+// column (see `Style::shape`), or else inferred from the values entered so far, and columns that
+// are filled in on every row are required. Nested grids are described as tables of their own.
+pub fn to_json_schema(
+    grammars: &HashMap<Coordinate, Grammar>,
+    grid: &Coordinate,
+) -> Option<JsonValue> {
+    let mut schema = table_schema(grammars, grid)?;
+    schema["$schema"] = json!(DRAFT);
+    let name = grammars.get(grid).map_or("", |g| g.name.as_str());
+    schema["title"] = json!(if name.is_empty() {
+        grid.to_string()
+    } else {
+        name.to_string()
+    });
+    Some(schema)
+}
+
+fn table_schema(grammars: &HashMap<Coordinate, Grammar>, grid: &Coordinate) -> Option<JsonValue> {
+    let region = Region::of_grid(grammars, grid)?;
+    let mut properties = Map::new();
+    let mut required = vec![];
+    let mut taken = vec![];
+    for col in region.cols.0..=region.cols.1 {
+        let header = cell_text(grammars, &region.cell(1, col)).trim().to_string();
+        let name = unique_name(
+            &if header.is_empty() {
+                format! {"column_{}", col}
+            } else {
+                header
+            },
+            &taken,
+        );
+        taken.push(name.clone());
+        let cells: Vec<Coordinate> = (2..=region.rows.1)
+            .map(|row| region.cell(row, col))
+            .collect();
+        if !cells.is_empty() && cells.iter().all(|c| !is_blank(grammars, c)) {
+            required.push(name.clone());
+        }
+        let mut property = column_schema(grammars, &cells);
+        // objects don't keep the order of their properties, so the column is kept alongside
+        property["propertyOrder"] = json!(col);
+        properties.insert(name, property);
+    }
+    Some(json!({
+        "type": "array",
+        "items": {
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        },
+    }))
+}
+
+fn column_schema(grammars: &HashMap<Coordinate, Grammar>, cells: &[Coordinate]) -> JsonValue {
+    let declared = cells
+        .iter()
+        .filter_map(|c| grammars.get(c)?.style.shape.clone())
+        .find_map(|declared| Shape::parse(&declared).ok());
+    match declared {
+        Some(Shape::Number) => return json!({ "type": "number" }),
+        Some(Shape::Date) => return json!({ "type": "string", "format": "date" }),
+        Some(Shape::Text) | Some(Shape::Any) => return json!({ "type": "string" }),
+        _ => (),
+    }
+    let filled: Vec<&Grammar> = cells
+        .iter()
+        .filter(|c| !is_blank(grammars, c))
+        .filter_map(|c| grammars.get(c))
+        .collect();
+    if let Some(nested) = cells
+        .iter()
+        .find(|c| matches!(grammars.get(c).map(|g| &g.kind), Some(Kind::Grid(_))))
+    {
+        return table_schema(grammars, nested).unwrap();
+    }
+    let all = |f: &dyn Fn(&Grammar) -> bool| !filled.is_empty() && filled.iter().all(|g| f(g));
+    if all(&|g| matches!(g.kind, Kind::Interactive(_, Interactive::Toggle(_)))) {
+        json!({ "type": "boolean" })
+    } else if all(&|g| matches!(Value::parse(&g.text_value()), Value::Number(n) if n.fract() == 0.0)) {
+        json!({ "type": "integer" })
+    } else if all(&|g| matches!(Value::parse(&g.text_value()), Value::Number(_))) {
+        json!({ "type": "number" })
+    } else if all(&|g| matches!(Value::parse(&g.text_value()), Value::Date(_))) {
+        json!({ "type": "string", "format": "date" })
+    } else {
+        json!({ "type": "string" })
+    }
+}
+
+fn is_blank(grammars: &HashMap<Coordinate, Grammar>, coord: &Coordinate) -> bool {
+    match grammars.get(coord) {
+        Some(Grammar {
+            kind: Kind::Grid(_),
+            ..
+        }) => false,
+        Some(grammar) => grammar.text_value().trim().is_empty(),
+        None => true,
+    }
+}
+
+// Scaffolds `grid` from a JSON Schema: a header row with the properties of the objects it
+// describes (the items of an array, or the object itself) and an empty row below, whose cells
+// expect the shape of their property. Properties that are objects (or arrays of objects) get a
+// nested grid scaffolded the same way.
+pub fn scaffold(
+    grammars: &mut HashMap<Coordinate, Grammar>,
+    grid: &Coordinate,
+    schema: &JsonValue,
+) -> Result<(), String> {
+    let properties = object_properties(schema)
+        .ok_or_else(|| "the schema doesn't describe objects with properties".to_string())?;
+    let mut properties: Vec<(&String, &JsonValue)> = properties.iter().collect();
+    properties.sort_by_key(|(name, property)| {
+        let order = property.get("propertyOrder").and_then(|o| o.as_u64());
+        (order.unwrap_or(u64::MAX), name.to_string())
+    });
+    let header: Vec<String> = properties
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .collect();
+    let blank = vec![String::new(); header.len()];
+    write_table(grammars, grid, &[header, blank]);
+    for (col, (_, property)) in properties.iter().enumerate() {
+        let cell = Coordinate::child_of(
+            grid,
+            (
+                NonZeroU32::new(2).unwrap(),
+                NonZeroU32::new(col as u32 + 1).unwrap(),
+            ),
+        );
+        let shape = if object_properties(property).is_some() {
+            scaffold(grammars, &cell, property)?;
+            Some("grid")
+        } else {
+            match (
+                schema_type(property),
+                property.get("format").and_then(|f| f.as_str()),
+            ) {
+                (Some("number"), _) | (Some("integer"), _) => Some("number"),
+                (Some("string"), Some("date")) => Some("date"),
+                (Some("string"), _) => Some("text"),
+                _ => None,
+            }
+        };
+        if let Some(g) = grammars.get_mut(&cell) {
+            g.style.shape = shape.map(|s| s.to_string());
+        }
+    }
+    Ok(())
+}
+
+fn object_properties(schema: &JsonValue) -> Option<&Map<String, JsonValue>> {
+    match schema_type(schema) {
+        Some("array") => object_properties(schema.get("items")?),
+        _ => schema
+            .get("properties")?
+            .as_object()
+            .filter(|p| !p.is_empty()),
+    }
+}
+
+// the type of a schema, the first one that isn't null if it allows several
+fn schema_type(schema: &JsonValue) -> Option<&str> {
+    match schema.get("type")? {
+        JsonValue::String(t) => Some(t.as_str()),
+        JsonValue::Array(types) => types
+            .iter()
+            .filter_map(|t| t.as_str())
+            .find(|t| *t != "null"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+
+    fn orders() -> HashMap<Coordinate, Grammar> {
+        hashmap! {
+            coord!("root-A1") => Grammar::as_grid(NonZeroU32::new(3).unwrap(), NonZeroU32::new(4).unwrap()),
+            coord!("root-A1-A1") => Grammar::input("", "item"),
+            coord!("root-A1-B1") => Grammar::input("", "qty"),
+            coord!("root-A1-C1") => Grammar::input("", "due"),
+            coord!("root-A1-D1") => Grammar::input("", ""),
+            coord!("root-A1-A2") => Grammar::input("", "apples"),
+            coord!("root-A1-B2") => Grammar::input("", "3"),
+            coord!("root-A1-C2") => Grammar::input("", "2020-05-01"),
+            coord!("root-A1-D2") => Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(1).unwrap()),
+            coord!("root-A1-D2-A1") => Grammar::input("", "price"),
+            coord!("root-A1-D2-A2") => Grammar::input("", "1.5"),
+            coord!("root-A1-A3") => Grammar::input("", "pears"),
+            coord!("root-A1-B3") => Grammar::input("", "12"),
+            coord!("root-A1-C3") => Grammar::input("", ""),
+            coord!("root-A1-D3") => Grammar::input("", ""),
+        }
+    }
+
+    #[test]
+    fn test_to_json_schema() {
+        let mut grammars = orders();
+        let schema = to_json_schema(&grammars, &coord!("root-A1")).unwrap();
+        assert_eq!(schema["title"], json!("root-A1"));
+        let items = &schema["items"];
+        assert_eq!(items["required"], json!(["item", "qty"]));
+        assert_eq!(
+            items["properties"]["qty"],
+            json!({ "type": "integer", "propertyOrder": 2 })
+        );
+        assert_eq!(items["properties"]["due"]["format"], json!("date"));
+        assert_eq!(
+            items["properties"]["column_4"]["items"]["properties"]["price"]["type"],
+            json!("number")
+        );
+        // a declared shape wins over the values
+        grammars.get_mut(&coord!("root-A1-A3")).unwrap().style.shape = Some("number".to_string());
+        let schema = to_json_schema(&grammars, &coord!("root-A1")).unwrap();
+        assert_eq!(
+            schema["items"]["properties"]["item"]["type"],
+            json!("number")
+        );
+        assert_eq!(to_json_schema(&grammars, &coord!("root-A1-A1")), None);
+    }
+
+    #[test]
+    fn test_scaffold() {
+        let schema = to_json_schema(&orders(), &coord!("root-A1")).unwrap();
+        let mut grammars = HashMap::new();
+        assert_eq!(scaffold(&mut grammars, &coord!("root-B1"), &schema), Ok(()));
+        let text = |c: &str| {
+            grammars
+                .get(&Coordinate::parse(c).unwrap())
+                .unwrap()
+                .text_value()
+        };
+        assert_eq!(
+            vec![
+                text("root-B1-A1"),
+                text("root-B1-B1"),
+                text("root-B1-C1"),
+                text("root-B1-D1")
+            ],
+            vec!["item", "qty", "due", "column_4"]
+        );
+        let shape = |c: &str| {
+            grammars
+                .get(&Coordinate::parse(c).unwrap())
+                .unwrap()
+                .style
+                .shape
+                .clone()
+        };
+        assert_eq!(shape("root-B1-B2"), Some("number".to_string()));
+        assert_eq!(shape("root-B1-C2"), Some("date".to_string()));
+        assert_eq!(shape("root-B1-D2"), Some("grid".to_string()));
+        assert_eq!(text("root-B1-D2-A1"), "price");
+        assert!(scaffold(
+            &mut grammars,
+            &coord!("root-C1"),
+            &json!({ "type": "string" })
+        )
+        .is_err());
+    }
+}
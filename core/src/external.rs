@@ -0,0 +1,263 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+
+use crate::coordinate::Coordinate;
+use crate::export::{cell_text, Region};
+use crate::grammar::{Grammar, Kind};
+use crate::packages::is_url;
+
+// ExternalSource binds a grid to a CSV or JSON file on disk, which is reimported into the grid
+// whenever it changes, or to a remote CSV (a URL), which is reimported when it's refreshed. `snapshot` is the table as it was last imported, so edits made to the
+// grid by hand since then aren't overwritten without asking.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExternalSource {
+    pub grid: Coordinate,
+    pub path: String,
+    #[serde(default)]
+    pub snapshot: Vec<Vec<String>>,
+}
+
+impl ExternalSource {
+    pub fn new(grid: Coordinate, path: String) -> ExternalSource {
+        ExternalSource {
+            grid,
+            path,
+            snapshot: vec![],
+        }
+    }
+
+    // whether the source is a URL, fetched through the main process rather than watched on disk
+    pub fn is_remote(&self) -> bool {
+        is_url(&self.path)
+    }
+
+    // whether the grid was edited since it was last imported
+    pub fn is_hand_edited(&self, grammars: &HashMap<Coordinate, Grammar>) -> bool {
+        !self.snapshot.is_empty() && grid_table(grammars, &self.grid).is_some_and(|t| t != self.snapshot)
+    }
+}
+
+// Parses the content of a source file by its extension: CSV rows as they are, JSON as an array
+// of arrays, or an array of objects with their keys as the header row
+pub fn parse_table(path: &str, content: &str) -> Result<Vec<Vec<String>>, String> {
+    if path.to_lowercase().ends_with(".json") {
+        parse_json(content)
+    } else {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(content.as_bytes());
+        reader
+            .records()
+            .map(|record| {
+                record
+                    .map(|r| r.iter().map(|cell| cell.to_string()).collect())
+                    .map_err(|e| e.to_string())
+            })
+            .collect()
+    }
+}
+
+fn parse_json(content: &str) -> Result<Vec<Vec<String>>, String> {
+    use serde_json::Value;
+    let text = |v: &Value| match v {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        v => v.to_string(),
+    };
+    let rows = match serde_json::from_str::<Value>(content).map_err(|e| e.to_string())? {
+        Value::Array(rows) => rows,
+        _ => return Err("expected a JSON array of rows".to_string()),
+    };
+    let mut keys: Vec<String> = vec![];
+    for row in rows.iter() {
+        if let Value::Object(object) = row {
+            for key in object.keys() {
+                if !keys.contains(key) {
+                    keys.push(key.clone());
+                }
+            }
+        }
+    }
+    let mut table = vec![];
+    if !keys.is_empty() {
+        table.push(keys.clone());
+    }
+    for row in rows.iter() {
+        table.push(match row {
+            Value::Array(cells) => cells.iter().map(text).collect(),
+            Value::Object(object) => keys.iter().map(|k| object.get(k).map_or(String::new(), text)).collect(),
+            v => vec![text(v)],
+        });
+    }
+    Ok(table)
+}
+
+// The URL a remote CSV is fetched from. Links to a Google Sheet (as copied from the address bar,
+// or published to the web) point to the CSV export of its sheet; other URLs are fetched as they
+// are. Only links that don't need signing in can be imported: sheets shared with anyone who has
+// the link, or published.
+pub fn csv_url(url: &str) -> Result<String, String> {
+    let url = url.trim();
+    if !is_url(url) {
+        return Err(format! {"{} isn't an http(s) URL", url});
+    }
+    let prefix = "https://docs.google.com/spreadsheets/d/";
+    if !url.starts_with(prefix) {
+        return Ok(url.to_string());
+    }
+    let rest = &url[prefix.len()..];
+    let published = rest.starts_with("e/");
+    let id = rest
+        .trim_start_matches("e/")
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or("");
+    if id.is_empty() {
+        return Err(format! {"{} doesn't link to a sheet", url});
+    }
+    // the sheet (tab) is `gid=` in the query or the fragment, the first one if there's none
+    let gid = url
+        .split(['?', '#', '&'])
+        .find(|part| part.starts_with("gid="))
+        .map(|part| &part["gid=".len()..]);
+    Ok(match (published, gid) {
+        (true, Some(gid)) => format! {"{}e/{}/pub?output=csv&gid={}", prefix, id, gid},
+        (true, None) => format! {"{}e/{}/pub?output=csv", prefix, id},
+        (false, Some(gid)) => format! {"{}{}/export?format=csv&gid={}", prefix, id, gid},
+        (false, None) => format! {"{}{}/export?format=csv", prefix, id},
+    })
+}
+
+// Text of the cells of a grid, row by row, or `None` if `grid` isn't a grid
+pub fn grid_table(grammars: &HashMap<Coordinate, Grammar>, grid: &Coordinate) -> Option<Vec<Vec<String>>> {
+    let region = Region::of_grid(grammars, grid)?;
+    Some(
+        (region.rows.0..=region.rows.1)
+            .map(|row| {
+                (region.cols.0..=region.cols.1)
+                    .map(|col| cell_text(grammars, &region.cell(row, col)))
+                    .collect()
+            })
+            .collect(),
+    )
+}
+
+// Writes a table as CSV, quoting the cells that need it
+pub fn to_csv(table: &[Vec<String>]) -> String {
+    let mut writer = csv::WriterBuilder::new().flexible(true).from_writer(vec![]);
+    for row in table {
+        // writing to a Vec can't fail
+        writer.write_record(row).unwrap();
+    }
+    String::from_utf8(writer.into_inner().unwrap()).unwrap()
+}
+
+// Replaces the contents of `grid` (turning it into a grid if it isn't one) with input cells
+// holding `table`. Short rows are padded with empty cells.
+pub fn write_table(grammars: &mut HashMap<Coordinate, Grammar>, grid: &Coordinate, table: &[Vec<String>]) {
+    let depth = grid.row_cols.len();
+    grammars.retain(|c, _| !(c.row_cols.len() > depth && c.row_cols[..depth] == grid.row_cols[..]));
+    let rows = table.len().max(1);
+    let cols = table.iter().map(|row| row.len()).max().unwrap_or(0).max(1);
+    let mut sub_coords = vec![];
+    for row in 1..=rows {
+        for col in 1..=cols {
+            let sub_coord = (
+                NonZeroU32::new(row as u32).unwrap(),
+                NonZeroU32::new(col as u32).unwrap(),
+            );
+            let text = table
+                .get(row - 1)
+                .and_then(|r| r.get(col - 1))
+                .map_or("", |s| s.as_str());
+            grammars.insert(Coordinate::child_of(grid, sub_coord), Grammar::input("", text));
+            sub_coords.push(sub_coord);
+        }
+    }
+    let grammar = grammars.entry(grid.clone()).or_default();
+    grammar.kind = Kind::Grid(sub_coords);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+
+    fn table(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter().map(|r| r.iter().map(|s| s.to_string()).collect()).collect()
+    }
+
+    #[test]
+    fn test_parse_table() {
+        assert_eq!(
+            parse_table("prices.csv", "fruit,price\napple,3\n\"pear, green\"\n"),
+            Ok(table(&[&["fruit", "price"], &["apple", "3"], &["pear, green"]]))
+        );
+        assert_eq!(
+            parse_table("prices.JSON", r#"[{"fruit": "apple", "price": 3}, {"fruit": "pear", "ripe": true}]"#),
+            Ok(table(&[
+                &["fruit", "price", "ripe"],
+                &["apple", "3", ""],
+                &["pear", "", "true"]
+            ]))
+        );
+        assert_eq!(parse_table("a.json", r#"[[1, null], ["x"]]"#), Ok(table(&[&["1", ""], &["x"]])));
+        assert!(parse_table("a.json", r#"{"fruit": "apple"}"#).is_err());
+
+        let rows = table(&[&["fruit", "price"], &["pear, green", "say \"hi\""]]);
+        assert_eq!(to_csv(&rows), "fruit,price\n\"pear, green\",\"say \"\"hi\"\"\"\n");
+        assert_eq!(parse_table("a.csv", &to_csv(&rows)), Ok(rows));
+    }
+
+    #[test]
+    fn test_write_table() {
+        let mut grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(1).unwrap()),
+            coord!("root-A1") => Grammar::as_grid(NonZeroU32::new(3).unwrap(), NonZeroU32::new(3).unwrap()),
+            coord!("root-A1-C3") => Grammar::input("", "old"),
+        };
+        let imported = table(&[&["fruit", "price"], &["apple"]]);
+        write_table(&mut grammars, &coord!("root-A1"), &imported);
+        assert!(!grammars.contains_key(&coord!("root-A1-C3")));
+        assert_eq!(
+            grid_table(&grammars, &coord!("root-A1")),
+            Some(table(&[&["fruit", "price"], &["apple", ""]]))
+        );
+
+        let mut source = ExternalSource::new(coord!("root-A1"), "prices.csv".to_string());
+        assert!(!source.is_hand_edited(&grammars));
+        source.snapshot = grid_table(&grammars, &coord!("root-A1")).unwrap();
+        assert!(!source.is_hand_edited(&grammars));
+        grammars.insert(coord!("root-A1-B2"), Grammar::input("", "4"));
+        assert!(source.is_hand_edited(&grammars));
+        assert!(!source.is_remote());
+    }
+
+    #[test]
+    fn test_csv_url() {
+        let sheet = "https://docs.google.com/spreadsheets/d/1AbC_d";
+        assert_eq!(
+            csv_url(&format! {" {}/edit#gid=42 ", sheet}),
+            Ok(format! {"{}/export?format=csv&gid=42", sheet})
+        );
+        assert_eq!(
+            csv_url(&format! {"{}/edit?usp=sharing", sheet}),
+            Ok(format! {"{}/export?format=csv", sheet})
+        );
+        assert_eq!(
+            csv_url("https://docs.google.com/spreadsheets/d/e/2PACX-1v/pubhtml?gid=7&single=true"),
+            Ok("https://docs.google.com/spreadsheets/d/e/2PACX-1v/pub?output=csv&gid=7".to_string())
+        );
+        assert_eq!(
+            csv_url("https://example.com/prices.csv"),
+            Ok("https://example.com/prices.csv".to_string())
+        );
+        assert!(csv_url("https://docs.google.com/spreadsheets/d/").is_err());
+        assert!(csv_url("file:///home/me/prices.csv").is_err());
+
+        let source = ExternalSource::new(coord!("root-A1"), "https://example.com/prices.csv".to_string());
+        assert!(source.is_remote());
+    }
+}
@@ -0,0 +1,537 @@
+use crate::coordinate::Coordinate;
+
+// Formulas are Input cells whose text starts with "=", e.g. "=SUM(root-A1:root-A3) * 2".
+// They're made of numbers, "strings", TRUE/FALSE, references to cells (root-A1) or
+// ranges (root-A1:root-B2), variables of the session ($tax_rate), function calls (SUM(...))
+// and the operators + - * / ^ & and comparisons (= <> < <= > >=).
+//
+// Spans are (start, end) char offsets into the whole text of the cell, "=" included,
+// so they can be used to highlight the text as it's shown.
+pub type Span = (usize, usize);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenKind {
+    Number,
+    Text,
+    Bool,
+    Reference,
+    Variable,
+    Function,
+    Operator,
+    Paren,
+    Comma,
+    Colon,
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reference {
+    Cell(Coordinate),
+    Range(Coordinate, Coordinate),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+    Ref(Reference),
+    // the name of a session variable, without its "$"
+    Var(String),
+    Call(String, Vec<Expr>),
+    Unary(String, Box<Expr>),
+    Binary(String, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Span,
+}
+
+pub fn is_formula(text: &str) -> bool {
+    text.starts_with('=')
+}
+
+// colors given to the references of a formula, in order of appearance, both in the
+// highlighted formula text and on the cells they point to
+const REFERENCE_COLORS: [&str; 6] = [
+    "#4a90d9", "#d9534f", "#5cb85c", "#9b59b6", "#f0ad4e", "#1abc9c",
+];
+
+pub fn reference_color(index: usize) -> &'static str {
+    REFERENCE_COLORS[index % REFERENCE_COLORS.len()]
+}
+
+// Splits the text of a formula (after its "=") into tokens. Text that isn't part of any
+// token becomes an Unknown token, so every character of the formula is covered by the
+// tokens (whitespace aside).
+pub fn tokenize(text: &str) -> Vec<Token> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = vec![];
+    let mut i = if is_formula(text) { 1 } else { 0 };
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        let kind = if c.is_whitespace() {
+            i += 1;
+            continue;
+        } else if c.is_ascii_digit() || (c == '.' && chars.get(i + 1).is_some_and(|d| d.is_ascii_digit())) {
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            TokenKind::Number
+        } else if c == '"' {
+            i += 1;
+            while i < chars.len() && chars[i] != '"' {
+                i += 1;
+            }
+            if i < chars.len() {
+                i += 1;
+                TokenKind::Text
+            } else {
+                // unterminated string
+                TokenKind::Unknown
+            }
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            // coordinates continue with "-<fragment>"s, e.g. root-A1-B2
+            while i + 1 < chars.len() && chars[i] == '-' && chars[i + 1].is_ascii_uppercase() {
+                let mut j = i + 1;
+                while j < chars.len() && chars[j].is_ascii_uppercase() {
+                    j += 1;
+                }
+                let digits_start = j;
+                while j < chars.len() && chars[j].is_ascii_digit() {
+                    j += 1;
+                }
+                if j == digits_start {
+                    break;
+                }
+                i = j;
+            }
+            let word: String = chars[start..i].iter().collect();
+            if chars.get(i) == Some(&'(') {
+                TokenKind::Function
+            } else if word.eq_ignore_ascii_case("true") || word.eq_ignore_ascii_case("false") {
+                TokenKind::Bool
+            } else if parse_reference(&word).is_some() {
+                TokenKind::Reference
+            } else {
+                TokenKind::Unknown
+            }
+        } else if c == '$' {
+            i += 1;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            if i - start > 1 {
+                TokenKind::Variable
+            } else {
+                TokenKind::Unknown
+            }
+        } else if c == '(' || c == ')' {
+            i += 1;
+            TokenKind::Paren
+        } else if c == ',' {
+            i += 1;
+            TokenKind::Comma
+        } else if c == ':' {
+            i += 1;
+            TokenKind::Colon
+        } else if "+-*/^&=<>".contains(c) {
+            i += 1;
+            let next = chars.get(i).cloned();
+            if (c == '<' && (next == Some('=') || next == Some('>'))) || (c == '>' && next == Some('=')) {
+                i += 1;
+            }
+            TokenKind::Operator
+        } else {
+            i += 1;
+            TokenKind::Unknown
+        };
+        tokens.push(Token {
+            kind,
+            text: chars[start..i].iter().collect(),
+            span: (start, i),
+        });
+    }
+    tokens
+}
+
+// a coordinate, written out in full (Coordinate::parse accepts a valid prefix)
+fn parse_reference(word: &str) -> Option<Coordinate> {
+    Coordinate::parse(word).filter(|c| c.to_string() == word)
+}
+
+pub fn parse(text: &str) -> Result<Expr, ParseError> {
+    let tokens = tokenize(text);
+    let end = text.chars().count();
+    let mut parser = Parser {
+        tokens: &tokens,
+        pos: 0,
+        end,
+    };
+    if tokens.is_empty() {
+        return Err(ParseError {
+            message: "empty formula".to_string(),
+            span: (0, end),
+        });
+    }
+    let expr = parser.comparison()?;
+    if let Some(token) = parser.peek() {
+        return Err(ParseError {
+            message: format! {"unexpected {}", token.text},
+            span: token.span,
+        });
+    }
+    Ok(expr)
+}
+
+// Recursive descent, from the loosest binding operators to the tightest:
+// comparison < concatenation (&) < additive < multiplicative < power (^) < unary < primary
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    end: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+
+    fn peek_operator(&self, operators: &[&str]) -> Option<String> {
+        match self.peek() {
+            Some(t) if t.kind == TokenKind::Operator && operators.contains(&t.text.as_str()) => {
+                Some(t.text.clone())
+            }
+            _ => None,
+        }
+    }
+
+    fn error_here(&self, message: &str) -> ParseError {
+        ParseError {
+            message: message.to_string(),
+            span: self.peek().map_or((self.end, self.end), |t| t.span),
+        }
+    }
+
+    fn binary<F>(&mut self, operators: &[&str], operand: F) -> Result<Expr, ParseError>
+    where
+        F: Fn(&mut Self) -> Result<Expr, ParseError>,
+    {
+        let mut lhs = operand(self)?;
+        while let Some(op) = self.peek_operator(operators) {
+            self.pos += 1;
+            let rhs = operand(self)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        self.binary(&["=", "<>", "<", "<=", ">", ">="], Self::concatenation)
+    }
+
+    fn concatenation(&mut self) -> Result<Expr, ParseError> {
+        self.binary(&["&"], Self::additive)
+    }
+
+    fn additive(&mut self) -> Result<Expr, ParseError> {
+        self.binary(&["+", "-"], Self::multiplicative)
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, ParseError> {
+        self.binary(&["*", "/"], Self::power)
+    }
+
+    fn power(&mut self) -> Result<Expr, ParseError> {
+        self.binary(&["^"], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if let Some(op) = self.peek_operator(&["-", "+"]) {
+            self.pos += 1;
+            let operand = self.unary()?;
+            return Ok(Expr::Unary(op, Box::new(operand)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let token = match self.peek() {
+            Some(t) => t,
+            None => return Err(self.error_here("expected a value")),
+        };
+        match token.kind {
+            TokenKind::Number => {
+                self.pos += 1;
+                token.text.parse().map(Expr::Number).map_err(|_| ParseError {
+                    message: format! {"invalid number {}", token.text},
+                    span: token.span,
+                })
+            }
+            TokenKind::Text => {
+                self.pos += 1;
+                Ok(Expr::Text(token.text[1..token.text.len() - 1].to_string()))
+            }
+            TokenKind::Bool => {
+                self.pos += 1;
+                Ok(Expr::Bool(token.text.eq_ignore_ascii_case("true")))
+            }
+            TokenKind::Reference => {
+                self.pos += 1;
+                let start = parse_reference(&token.text).unwrap();
+                match self.peek() {
+                    Some(t) if t.kind == TokenKind::Colon => {
+                        self.pos += 1;
+                        match self.next() {
+                            Some(end) if end.kind == TokenKind::Reference => Ok(Expr::Ref(
+                                Reference::Range(start, parse_reference(&end.text).unwrap()),
+                            )),
+                            _ => {
+                                self.pos -= 1;
+                                Err(self.error_here("expected the end of the range"))
+                            }
+                        }
+                    }
+                    _ => Ok(Expr::Ref(Reference::Cell(start))),
+                }
+            }
+            TokenKind::Variable => {
+                self.pos += 1;
+                Ok(Expr::Var(token.text[1..].to_string()))
+            }
+            TokenKind::Function => {
+                self.pos += 2; // name and "("
+                let name = token.text.to_uppercase();
+                let mut args = vec![];
+                match self.peek() {
+                    Some(t) if t.text == ")" => {
+                        self.pos += 1;
+                        return Ok(Expr::Call(name, args));
+                    }
+                    _ => (),
+                }
+                loop {
+                    args.push(self.comparison()?);
+                    match self.next() {
+                        Some(t) if t.kind == TokenKind::Comma => continue,
+                        Some(t) if t.text == ")" => return Ok(Expr::Call(name, args)),
+                        _ => {
+                            self.pos -= 1;
+                            return Err(self.error_here(&format! {"expected , or ) in {}", name}));
+                        }
+                    }
+                }
+            }
+            TokenKind::Paren if token.text == "(" => {
+                self.pos += 1;
+                let expr = self.comparison()?;
+                match self.next() {
+                    Some(t) if t.text == ")" => Ok(expr),
+                    _ => {
+                        self.pos -= 1;
+                        Err(ParseError {
+                            message: "unclosed (".to_string(),
+                            span: token.span,
+                        })
+                    }
+                }
+            }
+            TokenKind::Unknown if token.text.starts_with('"') => Err(ParseError {
+                message: "unterminated string".to_string(),
+                span: token.span,
+            }),
+            TokenKind::Unknown => Err(ParseError {
+                message: format! {"unknown name {}", token.text},
+                span: token.span,
+            }),
+            _ => Err(self.error_here(&format! {"unexpected {}", token.text})),
+        }
+    }
+}
+
+// The references written in a formula, whether or not it parses, in order of appearance:
+// (span, reference), with the span of a range covering both of its corners
+pub fn reference_spans(text: &str) -> Vec<(Span, Reference)> {
+    let tokens = tokenize(text);
+    let mut spans = vec![];
+    let mut i = 0;
+    while i < tokens.len() {
+        if tokens[i].kind == TokenKind::Reference {
+            let start = parse_reference(&tokens[i].text).unwrap();
+            match (tokens.get(i + 1), tokens.get(i + 2)) {
+                (Some(colon), Some(end))
+                    if colon.kind == TokenKind::Colon && end.kind == TokenKind::Reference =>
+                {
+                    let end_coord = parse_reference(&end.text).unwrap();
+                    spans.push((
+                        (tokens[i].span.0, end.span.1),
+                        Reference::Range(start, end_coord),
+                    ));
+                    i += 3;
+                    continue;
+                }
+                _ => spans.push((tokens[i].span, Reference::Cell(start))),
+            }
+        }
+        i += 1;
+    }
+    spans
+}
+
+// The distinct references of a formula with the color each one is highlighted in
+pub fn colored_references(text: &str) -> Vec<(Reference, &'static str)> {
+    let mut colored: Vec<(Reference, &'static str)> = vec![];
+    for (_, reference) in reference_spans(text) {
+        if !colored.iter().any(|(r, _)| *r == reference) {
+            let color = reference_color(colored.len());
+            colored.push((reference, color));
+        }
+    }
+    colored
+}
+
+// every reference of a formula, in order of appearance
+pub fn references(expr: &Expr) -> Vec<Reference> {
+    let mut refs = vec![];
+    collect_references(expr, &mut refs);
+    refs
+}
+
+fn collect_references(expr: &Expr, refs: &mut Vec<Reference>) {
+    match expr {
+        Expr::Ref(r) => refs.push(r.clone()),
+        Expr::Call(_, args) => args.iter().for_each(|a| collect_references(a, refs)),
+        Expr::Unary(_, e) => collect_references(e, refs),
+        Expr::Binary(_, lhs, rhs) => {
+            collect_references(lhs, refs);
+            collect_references(rhs, refs);
+        }
+        _ => (),
+    }
+}
+
+// the distinct variables a formula reads, in order of appearance
+pub fn variables(expr: &Expr) -> Vec<String> {
+    let mut names = vec![];
+    collect_variables(expr, &mut names);
+    names
+}
+
+fn collect_variables(expr: &Expr, names: &mut Vec<String>) {
+    match expr {
+        Expr::Var(name) if !names.contains(name) => names.push(name.clone()),
+        Expr::Call(_, args) => args.iter().for_each(|a| collect_variables(a, names)),
+        Expr::Unary(_, e) => collect_variables(e, names),
+        Expr::Binary(_, lhs, rhs) => {
+            collect_variables(lhs, names);
+            collect_variables(rhs, names);
+        }
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+
+    #[test]
+    fn test_tokenize() {
+        let kinds: Vec<TokenKind> = tokenize("=SUM(root-A1:root-B2) * 2 - \"x\"")
+            .iter()
+            .map(|t| t.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Function,
+                TokenKind::Paren,
+                TokenKind::Reference,
+                TokenKind::Colon,
+                TokenKind::Reference,
+                TokenKind::Paren,
+                TokenKind::Operator,
+                TokenKind::Number,
+                TokenKind::Operator,
+                TokenKind::Text,
+            ]
+        );
+        let tokens = tokenize("=root-A1-B2+1");
+        assert_eq!(tokens[0].text, "root-A1-B2");
+        assert_eq!(tokens[0].span, (1, 11));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("=1 + 2 * root-A1"),
+            Ok(Expr::Binary(
+                "+".to_string(),
+                Box::new(Expr::Number(1.0)),
+                Box::new(Expr::Binary(
+                    "*".to_string(),
+                    Box::new(Expr::Number(2.0)),
+                    Box::new(Expr::Ref(Reference::Cell(coord!("root-A1")))),
+                )),
+            ))
+        );
+        let expr = parse("=SUM(root-A1:root-A3, -root-B1)").unwrap();
+        assert_eq!(
+            references(&expr),
+            vec![
+                Reference::Range(coord!("root-A1"), coord!("root-A3")),
+                Reference::Cell(coord!("root-B1")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_colored_references() {
+        let colored = colored_references("=root-A1 + SUM(root-B1:root-B3) + root-A1 +");
+        assert_eq!(
+            colored,
+            vec![
+                (Reference::Cell(coord!("root-A1")), reference_color(0)),
+                (Reference::Range(coord!("root-B1"), coord!("root-B3")), reference_color(1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse("=1 +").unwrap_err().span, (4, 4));
+        assert_eq!(parse("=foo + 1").unwrap_err().span, (1, 4));
+        assert_eq!(parse("=(1 + 2").unwrap_err().message, "unclosed (");
+        assert_eq!(parse("=\"abc").unwrap_err().message, "unterminated string");
+        assert_eq!(parse("=SUM(1 2)").unwrap_err().span, (7, 8));
+        assert_eq!(parse("=$ * 2").unwrap_err().message, "unknown name $");
+    }
+
+    #[test]
+    fn test_variables() {
+        let tokens = tokenize("=root-A1 * (1 + $tax_rate)");
+        assert_eq!(tokens[5].kind, TokenKind::Variable);
+        assert_eq!(tokens[5].span, (16, 25));
+        let expr = parse("=IF($net, root-A1 * (1 + $tax_rate), $net + $tax_rate)").unwrap();
+        assert_eq!(variables(&expr), vec!["net".to_string(), "tax_rate".to_string()]);
+    }
+}
@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+
+use crate::coordinate::{Col, Coordinate, Row};
+use crate::grammar::Grammar;
+
+pub const FRAGMENT_FORMAT: &str = "ise-fragment";
+pub const FRAGMENT_VERSION: u32 = 2;
+
+// Fragment is a grammar and everything nested in it, saved on its own so it can be shared
+// and grafted into other sessions. Cells are stored relative to the fragment's root (whose
+// path is empty), along with their row height and column width.
+// Version 2 keeps the row height and column width apart, version 1 only kept both or neither.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Fragment {
+    pub format: String,
+    pub version: u32,
+    pub cells: Vec<FragmentCell>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FragmentCell {
+    pub path: Vec<(NonZeroU32, NonZeroU32)>,
+    pub grammar: Grammar,
+    // the row height and the column width, each if it was set
+    #[serde(default)]
+    pub row_height: Option<f64>,
+    #[serde(default)]
+    pub col_width: Option<f64>,
+    // (row height, column width) in version 1, moved to the fields above by `parse`
+    #[serde(default, skip_serializing)]
+    size: Option<(f64, f64)>,
+}
+
+// the row height and column width of a grafted cell, each if the fragment had it
+pub type CellSize = (Option<f64>, Option<f64>);
+
+// The fragment of the grammar at `root`, or `None` if there's no grammar there
+pub fn extract(
+    grammars: &HashMap<Coordinate, Grammar>,
+    row_heights: &HashMap<Row, f64>,
+    col_widths: &HashMap<Col, f64>,
+    root: &Coordinate,
+) -> Option<Fragment> {
+    grammars.get(root)?;
+    let depth = root.row_cols.len();
+    let mut cells: Vec<FragmentCell> = grammars
+        .iter()
+        .filter(|(c, _)| c.row_cols.len() >= depth && c.row_cols[..depth] == root.row_cols[..])
+        .map(|(c, g)| {
+            // the root's size belongs to the row & column it's in, not to the fragment
+            let nested = c.row_cols.len() > depth;
+            FragmentCell {
+                path: c.row_cols[depth..].to_vec(),
+                grammar: g.clone(),
+                row_height: row_heights.get(&c.full_row()).filter(|_| nested).cloned(),
+                col_width: col_widths.get(&c.full_col()).filter(|_| nested).cloned(),
+                size: None,
+            }
+        })
+        .collect();
+    cells.sort_by(|a, b| a.path.cmp(&b.path));
+    Some(Fragment {
+        format: FRAGMENT_FORMAT.to_string(),
+        version: FRAGMENT_VERSION,
+        cells,
+    })
+}
+
+pub fn parse(content: &str) -> Result<Fragment, String> {
+    let mut fragment: Fragment = serde_json::from_str(content).map_err(|e| format! {"not a cell fragment: {}", e})?;
+    if fragment.format != FRAGMENT_FORMAT || fragment.version > FRAGMENT_VERSION {
+        return Err(format! {"unsupported fragment format {} v{}", fragment.format, fragment.version});
+    }
+    if !fragment.cells.iter().any(|c| c.path.is_empty()) {
+        return Err("the fragment has no root cell".to_string());
+    }
+    for cell in fragment.cells.iter_mut() {
+        if let Some((height, width)) = cell.size.take() {
+            cell.row_height = Some(height);
+            cell.col_width = Some(width);
+        }
+    }
+    Ok(fragment)
+}
+
+// Replaces the grammar at `dest` (and everything nested in it) with the fragment, returning the
+// sizes of the grafted cells to apply
+pub fn graft(
+    fragment: &Fragment,
+    grammars: &mut HashMap<Coordinate, Grammar>,
+    dest: &Coordinate,
+) -> Vec<(Coordinate, CellSize)> {
+    let depth = dest.row_cols.len();
+    grammars.retain(|c, _| !(c.row_cols.len() > depth && c.row_cols[..depth] == dest.row_cols[..]));
+    let mut sizes = vec![];
+    for cell in fragment.cells.iter() {
+        let mut coord = dest.clone();
+        coord.row_cols.extend(cell.path.iter().cloned());
+        if cell.row_height.is_some() || cell.col_width.is_some() {
+            sizes.push((coord.clone(), (cell.row_height, cell.col_width)));
+        }
+        grammars.insert(coord, cell.grammar.clone());
+    }
+    sizes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+
+    #[test]
+    fn test_extract_and_graft() {
+        let grammars = hashmap! {
+            coord!("root-A1") => Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1-A1") => Grammar::input("", "apple"),
+            coord!("root-A1-B1") => Grammar::input("", "3"),
+            coord!("root-A2") => Grammar::input("", "outside"),
+        };
+        let row_heights = hashmap! { coord!("root-A1-A1").full_row() => 40.0 };
+        let col_widths = hashmap! { coord!("root-A1-A1").full_col() => 120.0 };
+        let fragment = extract(&grammars, &row_heights, &col_widths, &coord!("root-A1")).unwrap();
+        assert_eq!(fragment.cells.len(), 3);
+        assert!(fragment.cells[0].path.is_empty());
+        assert!(extract(&grammars, &row_heights, &col_widths, &coord!("root-C9")).is_none());
+
+        let json = serde_json::to_string(&fragment).unwrap();
+        let fragment = parse(&json).unwrap();
+        let mut target = hashmap! {
+            coord!("root-B2") => Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-B2-B2") => Grammar::input("", "replaced"),
+        };
+        let sizes = graft(&fragment, &mut target, &coord!("root-B2"));
+        assert_eq!(
+            sizes,
+            vec![
+                (coord!("root-B2-A1"), (Some(40.0), Some(120.0))),
+                (coord!("root-B2-B1"), (Some(40.0), None)),
+            ]
+        );
+        assert_eq!(target.get(&coord!("root-B2-B1")).unwrap().text_value(), "3");
+        assert!(!target.contains_key(&coord!("root-B2-B2")));
+
+        // version 1 kept both sizes together
+        let v1 = r#"{"format": "ise-fragment", "version": 1, "cells": [
+            {"path": [], "grammar": GRAMMAR, "size": null},
+            {"path": [[1, 1]], "grammar": GRAMMAR, "size": [40.0, 120.0]}
+        ]}"#
+        .replace("GRAMMAR", &serde_json::to_string(&Grammar::default()).unwrap());
+        let fragment = parse(&v1).unwrap();
+        assert_eq!(fragment.cells[1].row_height, Some(40.0));
+        assert_eq!(fragment.cells[1].col_width, Some(120.0));
+
+        assert!(parse(r#"{"format": "ise-fragment", "version": 1, "cells": []}"#).is_err());
+        assert!(parse("{}").is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use crate::calc::FormulaValue;
+use crate::formula::is_formula;
+
+// fractions of the range above which a gauge turns from low to mid and from mid to high
+pub const MID_THRESHOLD: f64 = 1.0 / 3.0;
+pub const HIGH_THRESHOLD: f64 = 2.0 / 3.0;
+
+// How full a gauge is, its bar is colored after it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GaugeLevel {
+    Low,
+    Mid,
+    High,
+}
+
+impl GaugeLevel {
+    pub fn of(fraction: f64) -> GaugeLevel {
+        if fraction >= HIGH_THRESHOLD {
+            GaugeLevel::High
+        } else if fraction >= MID_THRESHOLD {
+            GaugeLevel::Mid
+        } else {
+            GaugeLevel::Low
+        }
+    }
+
+    pub fn class(&self) -> &'static str {
+        match self {
+            GaugeLevel::Low => "gauge-low",
+            GaugeLevel::Mid => "gauge-mid",
+            GaugeLevel::High => "gauge-high",
+        }
+    }
+}
+
+// The source of a gauge is a formula, or a cell it reads from which is turned into one,
+// so gauges are recalculated along with the formulas when what they read changes
+pub fn source_formula(source: &str) -> String {
+    let source = source.trim();
+    if source.is_empty() || is_formula(source) {
+        source.to_string()
+    } else {
+        format! {"={}", source}
+    }
+}
+
+// The number a gauge shows, if its source evaluated to one
+pub fn gauge_value(value: Option<&FormulaValue>) -> Option<f64> {
+    match value {
+        Some(FormulaValue::Number(n)) => Some(*n),
+        Some(FormulaValue::Text(t)) => t.trim().parse().ok(),
+        Some(FormulaValue::Bool(b)) => Some(if *b { 1.0 } else { 0.0 }),
+        Some(array @ FormulaValue::Array(_)) => array.to_string().parse().ok(),
+        _ => None,
+    }
+}
+
+// How far `value` is between `min` and `max`, between 0 and 1
+pub fn fraction(value: f64, min: f64, max: f64) -> f64 {
+    if max <= min {
+        return if value >= max { 1.0 } else { 0.0 };
+    }
+    ((value - min) / (max - min)).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gauge() {
+        assert_eq!(source_formula("root-A1-B2"), "=root-A1-B2");
+        assert_eq!(source_formula(" =root-A1 / 2"), "=root-A1 / 2");
+        assert_eq!(source_formula(""), "");
+
+        assert_eq!(fraction(50.0, 0.0, 200.0), 0.25);
+        assert_eq!(fraction(-5.0, 0.0, 10.0), 0.0);
+        assert_eq!(fraction(15.0, 0.0, 10.0), 1.0);
+        assert_eq!(fraction(3.0, 5.0, 5.0), 0.0);
+
+        assert_eq!(GaugeLevel::of(0.1), GaugeLevel::Low);
+        assert_eq!(GaugeLevel::of(0.5), GaugeLevel::Mid);
+        assert_eq!(GaugeLevel::of(1.0), GaugeLevel::High);
+
+        assert_eq!(gauge_value(Some(&FormulaValue::Number(4.0))), Some(4.0));
+        assert_eq!(gauge_value(Some(&FormulaValue::Text(" 7 ".to_string()))), Some(7.0));
+        assert_eq!(gauge_value(Some(&FormulaValue::Error("#DIV/0!".to_string()))), None);
+        assert_eq!(gauge_value(None), None);
+    }
+}
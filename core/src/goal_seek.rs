@@ -0,0 +1,166 @@
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+
+use crate::calc::{formula_cells, recalculate, FormulaValue, Values, Variables};
+use crate::coordinate::Coordinate;
+use crate::grammar::{Grammar, Kind};
+use crate::value::Value;
+
+// max number of times the target formula is evaluated while seeking
+pub const GOAL_SEEK_MAX_ITERATIONS: u32 = 100;
+// how close the target needs to get to the desired value
+pub const GOAL_SEEK_TOLERANCE: f64 = 1e-7;
+
+// GoalSeek is the state of the goal seek dialog: the formula cell to bring to a desired
+// value by changing an input cell, as typed in, and the result of the last search
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GoalSeek {
+    pub target: String,
+    pub desired: String,
+    pub variable: String,
+    pub preview: Option<Result<Solution, String>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    // the value for the input cell
+    pub input: f64,
+    // the value the target formula takes with it
+    pub achieved: f64,
+    pub iterations: u32,
+}
+
+// Searches for a value of the input cell `variable` for which the formula in `target` evaluates
+// to `desired`: Newton's method (with a numeric derivative) starting from the input's current
+// value, falling back to bisection over a bracket found around it.
+pub fn goal_seek(
+    grammars: &HashMap<Coordinate, Grammar>,
+    variables: &Variables,
+    target: &Coordinate,
+    desired: f64,
+    variable: &Coordinate,
+) -> Result<Solution, String> {
+    let start = match grammars.get(variable).map(|g| &g.kind) {
+        Some(Kind::Input(text)) => match Value::parse(text) {
+            Value::Number(n) => n,
+            _ if text.trim().is_empty() => 0.0,
+            _ => return Err(format! {"{} doesn't hold a number", variable.to_string()}),
+        },
+        _ => return Err(format! {"{} isn't an input cell", variable.to_string()}),
+    };
+    match grammars.get(target).map(|g| &g.kind) {
+        Some(Kind::Input(text)) if text.starts_with('=') => (),
+        _ => return Err(format! {"{} isn't a formula", target.to_string()}),
+    }
+    let iterations = Cell::new(0);
+    let f = |x: f64| -> Result<f64, String> {
+        iterations.set(iterations.get() + 1);
+        if iterations.get() > GOAL_SEEK_MAX_ITERATIONS {
+            return Err("no solution found within the iteration limit".to_string());
+        }
+        target_value(grammars, variables, target, variable, x).map(|v| v - desired)
+    };
+
+    // Newton's method
+    let mut x = start;
+    for _ in 0..20 {
+        let fx = f(x)?;
+        if fx.abs() < GOAL_SEEK_TOLERANCE {
+            return solution(grammars, variables, target, variable, x, iterations.get());
+        }
+        let h = (x.abs() * 1e-6).max(1e-6);
+        let slope = (f(x + h)? - fx) / h;
+        if slope == 0.0 || !slope.is_finite() {
+            break;
+        }
+        let next = x - fx / slope;
+        if !next.is_finite() {
+            break;
+        }
+        x = next;
+    }
+
+    // bisection, over a bracket grown around the start until the sign changes
+    let (mut lo, mut hi) = (start - 1.0, start + 1.0);
+    let (mut f_lo, mut f_hi) = (f(lo)?, f(hi)?);
+    while f_lo.signum() == f_hi.signum() {
+        let width = hi - lo;
+        lo -= width;
+        hi += width;
+        f_lo = f(lo)?;
+        f_hi = f(hi)?;
+    }
+    loop {
+        let mid = (lo + hi) / 2.0;
+        let f_mid = f(mid)?;
+        if f_mid.abs() < GOAL_SEEK_TOLERANCE || (hi - lo) / 2.0 < GOAL_SEEK_TOLERANCE {
+            return solution(grammars, variables, target, variable, mid, iterations.get());
+        }
+        if f_mid.signum() == f_lo.signum() {
+            lo = mid;
+            f_lo = f_mid;
+        } else {
+            hi = mid;
+        }
+    }
+}
+
+fn solution(
+    grammars: &HashMap<Coordinate, Grammar>,
+    variables: &Variables,
+    target: &Coordinate,
+    variable: &Coordinate,
+    input: f64,
+    iterations: u32,
+) -> Result<Solution, String> {
+    Ok(Solution {
+        input,
+        achieved: target_value(grammars, variables, target, variable, input)?,
+        iterations,
+    })
+}
+
+// the value of the target formula with `x` typed into the input cell
+fn target_value(
+    grammars: &HashMap<Coordinate, Grammar>,
+    variables: &Variables,
+    target: &Coordinate,
+    variable: &Coordinate,
+    x: f64,
+) -> Result<f64, String> {
+    let mut grammars = grammars.clone();
+    if let Some(g) = grammars.get_mut(variable) {
+        g.kind = Kind::Input(x.to_string());
+    }
+    let cells: Vec<Coordinate> = formula_cells(&grammars).into_keys().collect();
+    let pending: HashSet<Coordinate> = cells.iter().cloned().collect();
+    let mut values = Values::new();
+    recalculate(&grammars, variables, &mut values, &cells, &pending);
+    match values.get(target) {
+        Some(FormulaValue::Number(n)) if n.is_finite() => Ok(*n),
+        Some(v) => Err(format! {"{} evaluates to {}", target.to_string(), v}),
+        None => Err(format! {"{} can't be evaluated", target.to_string()}),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn test_goal_seek() {
+        let grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(1).unwrap()),
+            coord!("root-A1") => Grammar::input("", "1"),
+            coord!("root-A2") => Grammar::input("", "=root-A1 * root-A1 + 3"),
+        };
+        let solution = goal_seek(&grammars, &Variables::new(), &coord!("root-A2"), 12.0, &coord!("root-A1")).unwrap();
+        assert!((solution.input - 3.0).abs() < 1e-4);
+        assert!((solution.achieved - 12.0).abs() < 1e-4);
+        // x * x + 3 never gets below 3
+        assert!(goal_seek(&grammars, &Variables::new(), &coord!("root-A2"), 0.0, &coord!("root-A1")).is_err());
+        assert!(goal_seek(&grammars, &Variables::new(), &coord!("root-A1"), 0.0, &coord!("root-A2")).is_err());
+    }
+}
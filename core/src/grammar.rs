@@ -0,0 +1,738 @@
+use pest::Parser;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::default::Default;
+use std::num::NonZeroU32;
+use std::ops::Deref;
+use std::option::Option;
+use std::string::String;
+use std::vec::Vec;
+
+use crate::coordinate::*;
+use crate::coordinate::{Col, Coordinate, Row};
+use crate::grammar;
+use crate::style::Style;
+use crate::util::non_zero_u32_tuple;
+use crate::{coord, coord_col, coord_row, row_col_vec};
+
+#[derive(Parser)]
+#[grammar = "coordinate.pest"]
+pub struct CoordinateParser;
+
+// Grammar is the main data-type representing
+// the contents of a cell
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Grammar {
+    pub name: String,
+    pub style: Style,
+    pub kind: Kind,
+}
+#[cfg(feature = "stdweb")]
+js_serializable!(Grammar);
+#[cfg(feature = "stdweb")]
+js_deserializable!(Grammar);
+
+// Kinds of grammars in the system.
+// Since this is an Enum, a Grammar's kind field
+// can only be set to one these variants at a time
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub enum Kind {
+    // Read-only text grammar
+    Text(String),
+
+    // Readable and writable text grammar
+    Input(String),
+
+    // Structural grammar that nests a grid of grammars
+    Grid(Vec<(NonZeroU32, NonZeroU32)>),
+
+    // Interactive Grammars
+    Interactive(String, Interactive),
+
+    // Lookup grammar
+    // in the context of definitions, these bind to cell bindings
+    Lookup(String, Option<Lookup>),
+
+    // Definition grammar
+    // sort of like a mirror to the meta-table that creates new grammars and
+    // specifies valid completions
+    Defn(
+        /* binding name */ String,
+        /* definition coord */ Coordinate,
+        /* rule names and coordinates */ Vec<(String, Coordinate)>,
+    ),
+
+    Editor(/* content */ String),
+
+    // Read-only view of a grid in another session file, kept in sync with the file on disk
+    Transclude(/* session file path */ String, /* grid coord in that session */ Coordinate),
+}
+#[cfg(feature = "stdweb")]
+js_serializable!(Kind);
+#[cfg(feature = "stdweb")]
+js_deserializable!(Kind);
+
+// Kinds of lookup grammars
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Lookup {
+    Cell(Coordinate),
+    Range {
+        parent: Coordinate,
+        start: (NonZeroU32, NonZeroU32),
+        end: (NonZeroU32, NonZeroU32),
+    },
+    Row(Row),
+    Col(Col),
+}
+
+// Kinds of interactive grammars
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub enum Interactive {
+    Button(),
+    Slider(/*value*/ f64, /*min*/ f64, /*max*/ f64),
+    Toggle(bool),
+    // runs a shell command in the Electron main process, its output goes to the next cell
+    // on the right (see `shell::write_output`)
+    Shell(/* command */ String, ShellOutput),
+    // a color chosen with the system color picker, as `#rrggbb` (see `parse_color`)
+    ColorPicker(/* color */ String),
+    // progress bar showing the value of a formula (or of the cell it names) between min and max,
+    // recalculated along with the other formulas
+    Gauge(/* source */ String, /* min */ f64, /* max */ f64),
+    // 0 to `max` stars
+    Rating(/* stars */ u32, /* max */ u32),
+    // number changed by `step` with its -/+ buttons or the arrow keys, kept between min and max
+    Stepper(/* value */ f64, /* step */ f64, /* min */ f64, /* max */ f64),
+    // stopwatch, the time it ran for is written into it while it runs (see `Action::TimerTick`)
+    Timer(/* elapsed seconds */ f64),
+    // fires a desktop notification with its text when its trigger comes (see `reminders`),
+    // once until it's re-armed
+    Reminder(ReminderTrigger, /* fired */ bool),
+}
+
+impl Interactive {
+    // the rating with `stars` stars, clicking the current rating clears it
+    pub fn rated(&self, stars: u32) -> Option<Interactive> {
+        match self {
+            Interactive::Rating(current, max) => {
+                let stars = if stars == *current { 0 } else { stars.min(*max) };
+                Some(Interactive::Rating(stars, *max))
+            }
+            _ => None,
+        }
+    }
+
+    // the stepper moved `count` steps up (or down, when negative)
+    pub fn stepped(&self, count: i32) -> Option<Interactive> {
+        match self {
+            Interactive::Stepper(value, step, min, max) => {
+                let next = value + step * f64::from(count);
+                // rounded so repeated steps of 0.1 don't drift
+                let next = ((next * 1e9).round() / 1e9).max(*min).min(*max);
+                Some(Interactive::Stepper(next, *step, *min, *max))
+            }
+            _ => None,
+        }
+    }
+}
+
+// Normalizes a `#rgb` or `#rrggbb` color to lowercase `#rrggbb`, the form `<input type="color">` uses
+pub fn parse_color(text: &str) -> Option<String> {
+    let text = text.trim();
+    if !text.starts_with('#') {
+        return None;
+    }
+    let hex = &text[1..];
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    match hex.len() {
+        3 => Some(hex.chars().fold("#".to_string(), |mut color, c| {
+            color.push(c);
+            color.push(c);
+            color
+        }))
+        .map(|color| color.to_lowercase()),
+        6 => Some(format! {"#{}", hex.to_lowercase()}),
+        _ => None,
+    }
+}
+
+// When a reminder cell fires
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum ReminderTrigger {
+    // at a local date & time, `YYYY-MM-DDTHH:MM`
+    At(String),
+    // when a formula (or the cell it names) becomes true
+    When(String),
+}
+
+// How the output of a shell command cell is written
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ShellOutput {
+    // as the text of the output cell
+    Text,
+    // parsed as CSV into a grid in the output cell
+    Csv,
+}
+
+// Kinds a cell can be converted into with the kind switcher
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum KindTag {
+    Text,
+    Input,
+    Lookup,
+    Button,
+    Slider,
+    Toggle,
+    Color,
+    Rating,
+    Stepper,
+    Grid,
+}
+
+impl KindTag {
+    pub const ALL: [KindTag; 10] = [
+        KindTag::Text,
+        KindTag::Input,
+        KindTag::Lookup,
+        KindTag::Button,
+        KindTag::Slider,
+        KindTag::Toggle,
+        KindTag::Color,
+        KindTag::Rating,
+        KindTag::Stepper,
+        KindTag::Grid,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            KindTag::Text => "Text",
+            KindTag::Input => "Input",
+            KindTag::Lookup => "Lookup",
+            KindTag::Button => "Button",
+            KindTag::Slider => "Slider",
+            KindTag::Toggle => "Toggle",
+            KindTag::Color => "Color",
+            KindTag::Rating => "Rating",
+            KindTag::Stepper => "Stepper",
+            KindTag::Grid => "Grid",
+        }
+    }
+
+    // builds a (non-grid) kind for this tag out of the text carried over from the previous kind
+    pub fn kind_from_text(&self, text: String) -> Option<Kind> {
+        match self {
+            KindTag::Text => Some(Kind::Text(text)),
+            KindTag::Input => Some(Kind::Input(text)),
+            KindTag::Lookup => Some(Kind::Lookup(text, None)),
+            KindTag::Button => Some(Kind::Interactive(text, Interactive::Button())),
+            KindTag::Slider => {
+                let value = text.trim().parse::<f64>().unwrap_or(0.0);
+                Some(Kind::Interactive(
+                    text,
+                    Interactive::Slider(value, value.min(0.0), value.max(100.0)),
+                ))
+            }
+            KindTag::Toggle => {
+                let on = text.trim() == "true";
+                Some(Kind::Interactive(text, Interactive::Toggle(on)))
+            }
+            KindTag::Color => {
+                let color = parse_color(&text).unwrap_or_else(|| "#000000".to_string());
+                Some(Kind::Interactive(String::new(), Interactive::ColorPicker(color)))
+            }
+            KindTag::Rating => {
+                let stars = text.trim().parse::<u32>().unwrap_or(0);
+                Some(Kind::Interactive(String::new(), Interactive::Rating(stars, stars.max(5))))
+            }
+            KindTag::Stepper => {
+                let value = text.trim().parse::<f64>().unwrap_or(0.0);
+                Some(Kind::Interactive(
+                    String::new(),
+                    Interactive::Stepper(value, 1.0, value.min(0.0), value.max(100.0)),
+                ))
+            }
+            KindTag::Grid => None,
+        }
+    }
+}
+
+impl Default for Grammar {
+    fn default() -> Self {
+        Self {
+            name: "".to_string(),
+            style: Style::default(),
+            kind: Kind::Input("".to_string()),
+        }
+    }
+}
+
+impl Grammar {
+    pub fn style(&self, coord: &Coordinate) -> String {
+        match &self.kind {
+            Kind::Grid(sub_coords) => {
+                let mut grid_area_str = "\"".to_string();
+                let mut prev_row = 1;
+                let mut sub_coords = sub_coords.clone();
+                sub_coords.sort_by(|(a_row, a_col), (b_row, b_col)| {
+                    if a_row < b_row {
+                        Ordering::Less
+                    } else if a_row == b_row {
+                        if a_col < b_col {
+                            Ordering::Less
+                        } else {
+                            Ordering::Greater
+                        }
+                    } else {
+                        Ordering::Greater
+                    }
+                });
+                for (row, col) in sub_coords {
+                    if row.get() > prev_row {
+                        grid_area_str.pop();
+                        grid_area_str += "\"\n\"";
+                    }
+                    // slots covered by a spanning child take on that child's grid area
+                    let area = self.spanned_by((row, col)).unwrap_or((row, col));
+                    let sub_coord = Coordinate::child_of(coord, area);
+                    // info!("sub_coord {:?}", sub_coord.clone());
+                    grid_area_str += format! {"cell-{} ", sub_coord.to_string()}.deref();
+                    prev_row = row.get();
+                }
+                grid_area_str.pop();
+                grid_area_str += "\"";
+                // info!("grid_area_str {:?}", grid_area_str.clone());
+                format! {
+                    "display: grid;\ngrid-area: cell-{};\nheight: fit-content;\nwidth: fit-content !important;\ngrid-template-areas: \n{};\n",
+                    coord.to_string(),
+                    grid_area_str,
+                }
+            }
+            Kind::Lookup(_, _) => format! {
+                "{}display: inline-flex; grid-area: cell-{}; background: white;\n", self.style.to_string(), coord.to_string()
+            },
+            _ => format! {"{}grid-area: cell-{};\n", self.style.to_string(), coord.to_string()},
+        }
+    }
+
+    pub fn kind_tag(&self) -> Option<KindTag> {
+        match &self.kind {
+            Kind::Text(_) => Some(KindTag::Text),
+            Kind::Input(_) => Some(KindTag::Input),
+            Kind::Lookup(_, _) => Some(KindTag::Lookup),
+            Kind::Interactive(_, Interactive::Button()) => Some(KindTag::Button),
+            Kind::Interactive(_, Interactive::Slider(_, _, _)) => Some(KindTag::Slider),
+            Kind::Interactive(_, Interactive::Toggle(_)) => Some(KindTag::Toggle),
+            Kind::Interactive(_, Interactive::ColorPicker(_)) => Some(KindTag::Color),
+            Kind::Interactive(_, Interactive::Rating(_, _)) => Some(KindTag::Rating),
+            Kind::Interactive(_, Interactive::Stepper(_, _, _, _)) => Some(KindTag::Stepper),
+            Kind::Interactive(_, Interactive::Shell(_, _))
+            | Kind::Interactive(_, Interactive::Gauge(_, _, _))
+            | Kind::Interactive(_, Interactive::Timer(_))
+            | Kind::Interactive(_, Interactive::Reminder(_, _)) => None,
+            Kind::Grid(_) => Some(KindTag::Grid),
+            Kind::Defn(_, _, _) | Kind::Editor(_) | Kind::Transclude(_, _) => None,
+        }
+    }
+
+    // the text of this grammar that's carried over when converting it into another kind
+    // (grids don't have text of their own). Color pickers have their color as their text, so
+    // references and drivers read it, and so do ratings, steppers and timers (in seconds) with
+    // their value.
+    pub fn text_value(&self) -> String {
+        match &self.kind {
+            Kind::Interactive(_, Interactive::ColorPicker(color)) => color.clone(),
+            Kind::Interactive(_, Interactive::Rating(stars, _)) => stars.to_string(),
+            Kind::Interactive(_, Interactive::Stepper(value, _, _, _)) => value.to_string(),
+            Kind::Interactive(_, Interactive::Timer(elapsed)) => elapsed.floor().to_string(),
+            Kind::Text(s) | Kind::Input(s) | Kind::Editor(s) => s.clone(),
+            Kind::Lookup(s, _) | Kind::Interactive(s, _) => s.clone(),
+            Kind::Defn(name, _, _) => name.clone(),
+            Kind::Grid(_) | Kind::Transclude(_, _) => String::new(),
+        }
+    }
+
+    // Row & column span of a child of this grid, (1, 1) for children that don't span
+    pub fn span_of(&self, sub_coord: (NonZeroU32, NonZeroU32)) -> (NonZeroU32, NonZeroU32) {
+        self.style
+            .spans
+            .iter()
+            .find(|(c, _)| *c == sub_coord)
+            .map(|(_, span)| *span)
+            .unwrap_or(non_zero_u32_tuple((1, 1)))
+    }
+
+    // If the slot at `sub_coord` is covered by another child of this grid that spans over it,
+    // returns the sub_coord of that spanning child
+    pub fn spanned_by(
+        &self,
+        sub_coord: (NonZeroU32, NonZeroU32),
+    ) -> Option<(NonZeroU32, NonZeroU32)> {
+        let (row, col) = (sub_coord.0.get(), sub_coord.1.get());
+        self.style
+            .spans
+            .iter()
+            .find(|((anchor_row, anchor_col), (rows, cols))| {
+                let (anchor_row, anchor_col) = (anchor_row.get(), anchor_col.get());
+                (anchor_row, anchor_col) != (row, col)
+                    && anchor_row <= row
+                    && row < anchor_row + rows.get()
+                    && anchor_col <= col
+                    && col < anchor_col + cols.get()
+            })
+            .map(|(anchor, _)| *anchor)
+    }
+
+    // NOTE: more info on this pattern here: https://hermanradtke.com/2015/05/06/creating-a-rust-function-that-accepts-string-or-str.html
+    pub fn text<S>(name: S, value: S) -> Grammar
+    where
+        S: Into<String>,
+    {
+        Grammar {
+            name: name.into(),
+            style: Style::default(),
+            kind: Kind::Text(value.into()),
+        }
+    }
+
+    pub fn input<S>(name: S, value: S) -> Grammar
+    where
+        S: Into<String>,
+    {
+        Grammar {
+            name: name.into(),
+            style: Style::default(),
+            kind: Kind::Input(value.into()),
+        }
+    }
+
+    pub fn default_button() -> Grammar {
+        Grammar {
+            name: "button".to_string(),
+            style: Style::default(),
+            kind: Kind::Interactive("".to_string(), Interactive::Button()),
+        }
+    }
+
+    pub fn default_slider() -> Grammar {
+        Grammar {
+            name: "slider".to_string(),
+            style: Style::default(),
+            kind: Kind::Interactive("".to_string(), Interactive::Slider(0.0, 0.0, 100.0)),
+        }
+    }
+
+    pub fn default_toggle() -> Grammar {
+        Grammar {
+            name: "toggle".to_string(),
+            style: Style::default(),
+            kind: Kind::Interactive("".to_string(), Interactive::Toggle(false)),
+        }
+    }
+
+    pub fn default_color_picker() -> Grammar {
+        Grammar {
+            name: "color".to_string(),
+            style: Style::default(),
+            kind: Kind::Interactive("".to_string(), Interactive::ColorPicker("#000000".to_string())),
+        }
+    }
+
+    pub fn default_gauge() -> Grammar {
+        Grammar {
+            name: "gauge".to_string(),
+            style: Style::default(),
+            kind: Kind::Interactive("".to_string(), Interactive::Gauge("".to_string(), 0.0, 100.0)),
+        }
+    }
+
+    pub fn default_rating() -> Grammar {
+        Grammar {
+            name: "rating".to_string(),
+            style: Style::default(),
+            kind: Kind::Interactive("".to_string(), Interactive::Rating(0, 5)),
+        }
+    }
+
+    pub fn default_stepper() -> Grammar {
+        Grammar {
+            name: "stepper".to_string(),
+            style: Style::default(),
+            kind: Kind::Interactive("".to_string(), Interactive::Stepper(0.0, 1.0, 0.0, 100.0)),
+        }
+    }
+
+    pub fn default_timer() -> Grammar {
+        Grammar {
+            name: "timer".to_string(),
+            style: Style::default(),
+            kind: Kind::Interactive("".to_string(), Interactive::Timer(0.0)),
+        }
+    }
+
+    pub fn default_reminder() -> Grammar {
+        Grammar {
+            name: "reminder".to_string(),
+            style: Style::default(),
+            kind: Kind::Interactive("".to_string(), Interactive::Reminder(ReminderTrigger::At("".to_string()), false)),
+        }
+    }
+
+    pub fn default_shell() -> Grammar {
+        Grammar {
+            name: "shell".to_string(),
+            style: Style::default(),
+            kind: Kind::Interactive("Run".to_string(), Interactive::Shell("".to_string(), ShellOutput::Text)),
+        }
+    }
+
+    pub fn as_grid(rows: NonZeroU32, cols: NonZeroU32) -> Grammar {
+        let mut grid: Vec<(NonZeroU32, NonZeroU32)> = Vec::new();
+        for i in 1..(rows.get() + 1) {
+            for j in 1..(cols.get() + 1) {
+                grid.push((NonZeroU32::new(i).unwrap(), NonZeroU32::new(j).unwrap()));
+            }
+        }
+
+        Grammar {
+            name: "".to_string(),
+            style: Style::default(),
+            kind: Kind::Grid(grid),
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! grammar_table {
+	($([$($content:tt)*]), *) => (
+		HashMap::<Coordinate, Grammar>::from_iter(vec![$(vec![$($content)*]), *].into_iter().flatten().collect())
+	);
+
+    /*
+    (@step $_idx:expr,) => {};
+
+    (@step $idx:expr, $head:ident, $($tail:ident,)*) => {
+        impl A {
+            fn $head(&self) -> i32 {
+                self.data[$idx]
+            }
+        }
+
+        grammar_table!(@step $idx + 1usize, $($tail,)*);
+    };
+
+    ($($n:ident),*) => {
+        grammar_table!(@step 0usize, $($n,)*);
+    }
+    */
+}
+
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    #[test]
+    fn test_kind_from_text() {
+        let toggle = KindTag::Toggle.kind_from_text("true".to_string()).unwrap();
+        assert_eq!(
+            toggle,
+            Kind::Interactive("true".to_string(), Interactive::Toggle(true))
+        );
+        let slider = KindTag::Slider.kind_from_text("42".to_string()).unwrap();
+        assert_eq!(
+            slider,
+            Kind::Interactive("42".to_string(), Interactive::Slider(42.0, 0.0, 100.0))
+        );
+        assert_eq!(KindTag::Grid.kind_from_text("".to_string()), None);
+        let color = Grammar {
+            kind: KindTag::Color.kind_from_text("#F80".to_string()).unwrap(),
+            ..Grammar::default()
+        };
+        assert_eq!(color.kind_tag(), Some(KindTag::Color));
+        assert_eq!(color.text_value(), "#ff8800".to_string());
+        // converting back and forth keeps the text
+        let input = Grammar::input("", "hello");
+        let mut text = input.clone();
+        text.kind = KindTag::Text.kind_from_text(input.text_value()).unwrap();
+        assert_eq!(text.kind_tag(), Some(KindTag::Text));
+        assert_eq!(text.text_value(), "hello".to_string());
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#A0b1C2"), Some("#a0b1c2".to_string()));
+        assert_eq!(parse_color(" #fff "), Some("#ffffff".to_string()));
+        assert_eq!(parse_color("red"), None);
+        assert_eq!(parse_color("#12345"), None);
+        assert_eq!(parse_color("#ggg"), None);
+    }
+
+    #[test]
+    fn test_rating_and_stepper() {
+        let rating = Interactive::Rating(2, 5);
+        assert_eq!(rating.rated(4), Some(Interactive::Rating(4, 5)));
+        assert_eq!(rating.rated(2), Some(Interactive::Rating(0, 5)));
+        assert_eq!(rating.rated(9), Some(Interactive::Rating(5, 5)));
+        let stepper = Interactive::Stepper(0.2, 0.1, 0.0, 1.0);
+        assert_eq!(stepper.stepped(1), Some(Interactive::Stepper(0.3, 0.1, 0.0, 1.0)));
+        assert_eq!(stepper.stepped(-5), Some(Interactive::Stepper(0.0, 0.1, 0.0, 1.0)));
+        assert_eq!(stepper.stepped(20), Some(Interactive::Stepper(1.0, 0.1, 0.0, 1.0)));
+        assert_eq!(Interactive::Toggle(true).stepped(1), None);
+        let converted = Grammar {
+            kind: KindTag::Rating.kind_from_text("3".to_string()).unwrap(),
+            ..Grammar::default()
+        };
+        assert_eq!(converted.text_value(), "3".to_string());
+    }
+
+    #[test]
+    fn test_default_grammar() {
+        assert_eq!(Grammar::default().kind, Kind::Input("".to_string()));
+        assert_ne!(Grammar::default().kind, Kind::Text("".to_string()));
+        assert_eq!(Grammar::default().name, "".to_string());
+        assert_ne!(Grammar::default().name, " ");
+        assert_eq!(
+            Grammar::default().style.to_string(),
+            Style::default().to_string()
+        );
+    }
+
+    #[test]
+    fn test_grammar_style() {
+        assert_eq!(
+            Grammar::default().style(&coord!("root-A1")),
+            format! {"/* border: 1px; NOTE: ignoring Style::border_* for now */\nborder-collapse: inherit;\nfont-weight: 400;\ncolor: black;\n\ngrid-area: cell-root-A1;\n"}
+        );
+        assert_ne!(
+            Grammar::default().style(&coord!("root-A1")),
+            format! {"display: grid;\ngrid-area: cell-root-A1;\nheight: fit-content;\nwidth: fit-content !important;\ngrid-template-areas: \n\"cell-root-A1-A1 cell-root-A1-B1\";\n"}
+        );
+        // Type Grid
+        assert_eq!(
+            Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(2).unwrap())
+                .style(&coord!("root-A1")),
+            format! {"display: grid;\ngrid-area: cell-root-A1;\nheight: fit-content;\nwidth: fit-content !important;\ngrid-template-areas: \n\"cell-root-A1-A1 cell-root-A1-B1\";\n"}
+        );
+        assert_ne!(
+            Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(2).unwrap())
+                .style(&coord!("root-A1")),
+            format! {"/* border: 1px; NOTE: ignoring Style::border_* for now */\nborder-collapse: inherit;\nfont-weight: 400;\ncolor: black;\n\ngrid-area: cell-root-A1;\n"}
+        );
+    }
+
+    #[test]
+    fn test_grid_spans() {
+        let mut grid = Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(2).unwrap());
+        grid.style.spans = vec![(non_zero_u32_tuple((1, 1)), non_zero_u32_tuple((1, 2)))];
+        assert_eq!(grid.span_of(non_zero_u32_tuple((1, 1))), non_zero_u32_tuple((1, 2)));
+        assert_eq!(grid.span_of(non_zero_u32_tuple((2, 2))), non_zero_u32_tuple((1, 1)));
+        assert_eq!(
+            grid.spanned_by(non_zero_u32_tuple((1, 2))),
+            Some(non_zero_u32_tuple((1, 1)))
+        );
+        assert_eq!(grid.spanned_by(non_zero_u32_tuple((1, 1))), None);
+        assert_eq!(grid.spanned_by(non_zero_u32_tuple((2, 2))), None);
+        assert_eq!(
+            grid.style(&coord!("root-A1")),
+            format! {"display: grid;\ngrid-area: cell-root-A1;\nheight: fit-content;\nwidth: fit-content !important;\ngrid-template-areas: \n\"cell-root-A1-A1 cell-root-A1-A1\"\n\"cell-root-A1-A2 cell-root-A1-B2\";\n"}
+        );
+    }
+
+    #[test]
+    fn test_grammar_text() {
+        assert_eq!(
+            Grammar::text("testing", "testing").name,
+            "testing".to_string(),
+        );
+
+        assert_eq!(
+            Grammar::text("testing", "testing").style.to_string(),
+            Style::default().to_string()
+        );
+    }
+
+    #[test]
+    fn test_grammar_input() {
+        assert_eq!(
+            Grammar::input("testing", "testing").name,
+            "testing".to_string(),
+        );
+
+        assert_eq!(
+            Grammar::input("testing", "testing").style.to_string(),
+            Style::default().to_string()
+        );
+        assert_ne!(
+            Grammar::input("testing", "testing").kind,
+            Kind::Input("testing ".to_string())
+        );
+    }
+
+    #[test]
+    fn test_default_button() {
+        assert_eq!(Grammar::default_button().name, "button".to_string());
+
+        assert_eq!(
+            Grammar::default_button().style.to_string(),
+            Style::default().to_string()
+        );
+
+        assert_ne!(
+            Grammar::default_button().kind,
+            Kind::Interactive(" ".to_string(), Interactive::Button())
+        );
+    }
+
+    #[test]
+    fn test_default_slider() {
+        assert_eq!(Grammar::default_slider().name, "slider".to_string());
+
+        assert_eq!(
+            Grammar::default_slider().style.to_string(),
+            Style::default().to_string()
+        );
+
+        assert_ne!(
+            Grammar::default_slider().kind,
+            Kind::Interactive(" ".to_string(), Interactive::Slider(0.0, 0.0, 100.0))
+        );
+    }
+
+    #[test]
+    fn test_default_toggle() {
+        assert_eq!(Grammar::default_toggle().name, "toggle".to_string());
+
+        assert_eq!(
+            Grammar::default_toggle().style.to_string(),
+            Style::default().to_string()
+        );
+
+        assert_ne!(
+            Grammar::default_toggle().kind,
+            Kind::Interactive(" ".to_string(), Interactive::Toggle(false))
+        );
+    }
+
+    #[test]
+    fn test_as_grid() {
+        assert_eq!(
+            Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(2).unwrap()).name,
+            "".to_string()
+        );
+
+        assert_eq!(
+            Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(2).unwrap())
+                .style
+                .to_string(),
+            Style::default().to_string()
+        );
+
+        assert_eq!(
+            Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(2).unwrap()).kind,
+            Kind::Grid(vec![non_zero_u32_tuple((1, 1)), non_zero_u32_tuple((1, 2))])
+        );
+    }
+}
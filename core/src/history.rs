@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+
+use crate::coordinate::{Col, Row};
+use crate::session::Session;
+
+// max number of steps that can be undone
+const HISTORY_LIMIT: usize = 100;
+
+// What an undoable action changed: the contents of the session, or only how it's laid out
+// (row & column sizes, spans and styles)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EditKind {
+    Content,
+    Layout,
+}
+
+// Snapshot is the state restored by an undo: the session (by its index in `Model::sessions`)
+// as it was right before an undoable action, along with the row & column sizes, which the
+// model keeps outside of the session
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub session_index: usize,
+    pub session: Session,
+    pub kind: EditKind,
+    pub row_heights: HashMap<Row, f64>,
+    pub col_widths: HashMap<Col, f64>,
+}
+
+// History is a snapshot-based undo stack: undoable actions record the state from before they
+// were applied, and undoing swaps the current state with the last recorded one.
+#[derive(Debug, Clone)]
+pub struct History<T: Clone> {
+    undo_stack: Vec<T>,
+    redo_stack: Vec<T>,
+    // how many states have been recorded so far, see `records`
+    records: usize,
+}
+
+impl<T: Clone> Default for History<T> {
+    fn default() -> History<T> {
+        History::new()
+    }
+}
+
+impl<T: Clone> History<T> {
+    pub fn new() -> History<T> {
+        History {
+            undo_stack: vec![],
+            redo_stack: vec![],
+            records: 0,
+        }
+    }
+
+    // records the state from before an undoable action, which invalidates anything
+    // that could have been redone
+    pub fn record(&mut self, before: T) {
+        self.undo_stack.push(before);
+        if self.undo_stack.len() > HISTORY_LIMIT {
+            self.undo_stack.remove(0);
+        }
+        self.redo_stack.clear();
+        self.records += 1;
+    }
+
+    // how many states have been recorded so far (which keeps counting once the oldest ones
+    // are dropped), to tell whether an action recorded one
+    pub fn records(&self) -> usize {
+        self.records
+    }
+
+    // returns the state to restore, if there's anything to undo
+    pub fn undo(&mut self, current: T) -> Option<T> {
+        let previous = self.undo_stack.pop()?;
+        self.redo_stack.push(current);
+        Some(previous)
+    }
+
+    // returns the state to restore, if there's anything to redo
+    pub fn redo(&mut self, current: T) -> Option<T> {
+        let next = self.redo_stack.pop()?;
+        self.undo_stack.push(current);
+        Some(next)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    // the state the next undo restores
+    pub fn last(&self) -> Option<&T> {
+        self.undo_stack.last()
+    }
+
+    // the state the next redo restores
+    pub fn next(&self) -> Option<&T> {
+        self.redo_stack.last()
+    }
+
+    pub fn undo_len(&self) -> usize {
+        self.undo_stack.len()
+    }
+
+    pub fn redo_len(&self) -> usize {
+        self.redo_stack.len()
+    }
+
+    // every state held on to, to be undone or redone
+    pub fn states(&self) -> impl Iterator<Item = &T> {
+        self.undo_stack.iter().chain(self.redo_stack.iter())
+    }
+
+    // keeps only the states `keep` returns true for, letting it update them as it goes
+    // (e.g. when the tab a snapshot belongs to moves or is closed)
+    pub fn retain_mut<F: FnMut(&mut T) -> bool>(&mut self, mut keep: F) {
+        for stack in [&mut self.undo_stack, &mut self.redo_stack].iter_mut() {
+            let states = std::mem::take(&mut **stack);
+            for mut state in states {
+                if keep(&mut state) {
+                    stack.push(state);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_undo_redo() {
+        let mut history = History::new();
+        assert_eq!(history.undo(0), None);
+        history.record(0);
+        history.record(1);
+        assert_eq!(history.undo(2), Some(1));
+        assert_eq!(history.undo(1), Some(0));
+        assert!(!history.can_undo());
+        assert_eq!(history.last(), None);
+        assert_eq!(history.next(), Some(&1));
+        assert_eq!(history.redo(0), Some(1));
+        assert_eq!(history.last(), Some(&0));
+        assert_eq!(history.redo(1), Some(2));
+        assert!(!history.can_redo());
+        assert_eq!((history.undo_len(), history.redo_len()), (2, 0));
+        assert_eq!(history.states().collect::<Vec<_>>(), vec![&0, &1]);
+        // undoing & redoing doesn't record anything
+        assert_eq!(history.records(), 2);
+    }
+
+    #[test]
+    fn test_record_clears_redo() {
+        let mut history = History::new();
+        history.record("a");
+        assert_eq!(history.undo("b"), Some("a"));
+        history.record("a");
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn test_history_limit() {
+        let mut history = History::new();
+        for i in 0..(HISTORY_LIMIT + 10) {
+            history.record(i);
+        }
+        let mut undone = 0;
+        while history.undo(0).is_some() {
+            undone += 1;
+        }
+        assert_eq!(undone, HISTORY_LIMIT);
+    }
+
+    #[test]
+    fn test_retain_mut() {
+        let mut history = History::new();
+        history.record(1);
+        history.record(2);
+        history.record(3);
+        assert_eq!(history.undo(4), Some(3));
+        history.retain_mut(|s| {
+            *s *= 10;
+            *s != 20
+        });
+        assert_eq!(history.states().collect::<Vec<_>>(), vec![&10, &40]);
+        assert_eq!(history.undo(0), Some(10));
+    }
+}
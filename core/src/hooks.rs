@@ -0,0 +1,284 @@
+use serde::{Deserialize, Serialize};
+
+use crate::grammar::{Kind, Lookup};
+use crate::session::Session;
+
+// Stage is when hooks transform a session: right before it's written to a file, or right after
+// it's read from one
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Stage {
+    PreSave,
+    PostLoad,
+}
+
+impl Stage {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Stage::PreSave => "before saving",
+            Stage::PostLoad => "after loading",
+        }
+    }
+}
+
+// Builtin is a transformation that ships with the app
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Builtin {
+    // clears the values lookups keep of the cells they refer to
+    StripComputedValues,
+    // sorts the cells of grids and drops the spans of cells they don't have anymore
+    NormalizeStyles,
+    // fills lookups in with the current text of the cells they refer to
+    RefreshLookups,
+    // recalculates every formula (done by the app, see `Builtin::is_pure`)
+    Recalculate,
+    // reimports the files grids are bound to (done by the app, see `Builtin::is_pure`)
+    RefreshExternalSources,
+}
+
+impl Builtin {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Builtin::StripComputedValues => "Strip computed values",
+            Builtin::NormalizeStyles => "Normalize styles",
+            Builtin::RefreshLookups => "Refresh lookups",
+            Builtin::Recalculate => "Recalculate formulas",
+            Builtin::RefreshExternalSources => "Refresh external sources",
+        }
+    }
+
+    // whether the hook only transforms the session, rather than having the app do something
+    // with it once it's loaded
+    pub fn is_pure(&self) -> bool {
+        !matches!(self, Builtin::Recalculate | Builtin::RefreshExternalSources)
+    }
+
+    // applies a pure hook to `session`
+    pub fn apply(&self, session: &mut Session) -> Result<(), String> {
+        match self {
+            Builtin::StripComputedValues => strip_computed_values(session),
+            Builtin::NormalizeStyles => normalize_styles(session),
+            Builtin::RefreshLookups => refresh_lookups(session),
+            Builtin::Recalculate | Builtin::RefreshExternalSources => (),
+        }
+        Ok(())
+    }
+}
+
+// HookSource is what a hook runs: a builtin transformation, or a function a driver registered
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookSource {
+    Builtin(Builtin),
+    Driver(/* driver name */ String),
+}
+
+// Hook is a transformation of the session at one of the stages. Hooks of a stage run from the
+// lowest order up (by name when they're tied), each one getting what the previous ones made of
+// the session.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Hook {
+    pub name: String,
+    pub stage: Stage,
+    pub order: i32,
+    pub source: HookSource,
+    pub enabled: bool,
+}
+
+impl Hook {
+    pub fn builtin(builtin: Builtin, stage: Stage, order: i32, enabled: bool) -> Hook {
+        Hook {
+            name: builtin.label().to_string(),
+            stage,
+            order,
+            source: HookSource::Builtin(builtin),
+            enabled,
+        }
+    }
+}
+
+// The builtin hooks the preferences start with. Stripping computed values is paired with
+// refreshing lookups, so it's off until the user turns both on.
+pub fn default_hooks() -> Vec<Hook> {
+    vec![
+        Hook::builtin(Builtin::StripComputedValues, Stage::PreSave, 10, false),
+        Hook::builtin(Builtin::NormalizeStyles, Stage::PreSave, 20, true),
+        Hook::builtin(Builtin::RefreshLookups, Stage::PostLoad, 10, false),
+        Hook::builtin(Builtin::Recalculate, Stage::PostLoad, 20, true),
+        Hook::builtin(Builtin::RefreshExternalSources, Stage::PostLoad, 30, false),
+    ]
+}
+
+// The enabled hooks of `stage`, in the order they run
+pub fn ordered(hooks: &[Hook], stage: Stage) -> Vec<&Hook> {
+    let mut ordered: Vec<&Hook> = hooks
+        .iter()
+        .filter(|h| h.enabled && h.stage == stage)
+        .collect();
+    ordered.sort_by(|a, b| (a.order, &a.name).cmp(&(b.order, &b.name)));
+    ordered
+}
+
+// Runs the enabled hooks of `stage` over `session` in order, `run` applying each one. A hook
+// that fails leaves the session as it was before it ran and the others still run; the failures
+// are returned as (hook name, error).
+pub fn run_hooks<F>(
+    hooks: &[Hook],
+    stage: Stage,
+    session: &mut Session,
+    mut run: F,
+) -> Vec<(String, String)>
+where
+    F: FnMut(&Hook, &mut Session) -> Result<(), String>,
+{
+    let mut failures = vec![];
+    for hook in ordered(hooks, stage) {
+        let mut transformed = session.clone();
+        match run(hook, &mut transformed) {
+            Ok(()) => *session = transformed,
+            Err(e) => failures.push((hook.name.clone(), e)),
+        }
+    }
+    failures
+}
+
+fn strip_computed_values(session: &mut Session) {
+    for grammar in session.grammars.values_mut() {
+        if let Kind::Lookup(value, Some(_)) = &mut grammar.kind {
+            value.clear();
+        }
+    }
+}
+
+fn normalize_styles(session: &mut Session) {
+    for grammar in session.grammars.values_mut() {
+        if let Kind::Grid(sub_coords) = &mut grammar.kind {
+            sub_coords.sort();
+            sub_coords.dedup();
+            let sub_coords = sub_coords.clone();
+            grammar
+                .style
+                .spans
+                .retain(|(sub_coord, _)| sub_coords.contains(sub_coord));
+        }
+    }
+}
+
+fn refresh_lookups(session: &mut Session) {
+    let targets: Vec<_> = session
+        .grammars
+        .iter()
+        .filter_map(|(coord, grammar)| match &grammar.kind {
+            Kind::Lookup(_, Some(Lookup::Cell(target))) => session
+                .grammars
+                .get(target)
+                .map(|g| (coord.clone(), g.text_value())),
+            _ => None,
+        })
+        .collect();
+    for (coord, text) in targets {
+        if let Some(Kind::Lookup(value, _)) = session.grammars.get_mut(&coord).map(|g| &mut g.kind)
+        {
+            *value = text;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::IterationSettings;
+    use crate::coord;
+    use crate::grammar::Grammar;
+    use crate::session::{SessionLayout, SessionMetadata, TabView};
+    use std::num::NonZeroU32;
+
+    fn session() -> Session {
+        Session {
+            title: "hooks".to_string(),
+            root: Grammar::default(),
+            meta: Grammar::default(),
+            grammars: hashmap! {
+                coord!("root") => Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(2).unwrap()),
+                coord!("root-A1") => Grammar::input("", "apples"),
+                coord!("root-B1") => Grammar {
+                    kind: Kind::Lookup("pears".to_string(), Some(Lookup::Cell(coord!("root-A1")))),
+                    ..Grammar::default()
+                },
+            },
+            outline_groups: vec![],
+            sheets: vec![],
+            metadata: SessionMetadata::default(),
+            bookmarks: vec![],
+            iteration: IterationSettings::default(),
+            external_sources: vec![],
+            assets: vec![],
+            packages: vec![],
+            macros: vec![],
+            mute_reminders: false,
+            variables: vec![],
+            layout: SessionLayout::default(),
+            view: TabView::default(),
+        }
+    }
+
+    fn lookup_value(session: &Session) -> String {
+        session
+            .grammars
+            .get(&coord!("root-B1"))
+            .unwrap()
+            .text_value()
+    }
+
+    #[test]
+    fn test_ordered() {
+        let mut hooks = default_hooks();
+        hooks.push(Hook {
+            name: "stamp".to_string(),
+            stage: Stage::PreSave,
+            order: 0,
+            source: HookSource::Driver("stamper".to_string()),
+            enabled: true,
+        });
+        let names: Vec<&str> = ordered(&hooks, Stage::PreSave)
+            .iter()
+            .map(|h| h.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["stamp", "Normalize styles"]);
+        assert_eq!(ordered(&hooks, Stage::PostLoad).len(), 1);
+    }
+
+    #[test]
+    fn test_run_hooks() {
+        let hooks = vec![
+            Hook::builtin(Builtin::StripComputedValues, Stage::PreSave, 1, true),
+            Hook {
+                name: "broken".to_string(),
+                stage: Stage::PreSave,
+                order: 2,
+                source: HookSource::Driver("broken".to_string()),
+                enabled: true,
+            },
+            Hook::builtin(Builtin::RefreshLookups, Stage::PostLoad, 1, true),
+        ];
+        let run = |hook: &Hook, session: &mut Session| match &hook.source {
+            HookSource::Builtin(builtin) => builtin.apply(session),
+            HookSource::Driver(_) => {
+                // what a failing hook did to the session is thrown away
+                session.title = "broken".to_string();
+                Err("not a function".to_string())
+            }
+        };
+        let mut saved = session();
+        let failures = run_hooks(&hooks, Stage::PreSave, &mut saved, run);
+        assert_eq!(
+            failures,
+            vec![("broken".to_string(), "not a function".to_string())]
+        );
+        assert_eq!(saved.title, "hooks");
+        assert_eq!(lookup_value(&saved), "");
+        assert!(run_hooks(&hooks, Stage::PostLoad, &mut saved, run).is_empty());
+        assert_eq!(lookup_value(&saved), "apples");
+    }
+}
@@ -0,0 +1,458 @@
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroU32;
+
+use crate::coordinate::{Col, Coordinate, Row};
+use crate::defn::rule_cells;
+use crate::grammar::{Grammar, Kind};
+
+// The coordinates reachable from the top-level grids (root, meta and the sheets) by following
+// the cells every grid and definition on the way holds
+pub fn reachable(grammars: &HashMap<Coordinate, Grammar>) -> HashSet<Coordinate> {
+    let mut seen = HashSet::new();
+    let mut stack: Vec<Coordinate> = grammars
+        .keys()
+        .filter(|c| c.row_cols.len() == 1)
+        .cloned()
+        .collect();
+    while let Some(coord) = stack.pop() {
+        if let Some(grammar) = grammars.get(&coord) {
+            for child in held_cells(&coord, grammar) {
+                if grammars.contains_key(&child) && !seen.contains(&child) {
+                    stack.push(child);
+                }
+            }
+        }
+        seen.insert(coord);
+    }
+    seen
+}
+
+// The cells the grammar at `coord` holds: the sub_coords of a grid, or the sub-rules of a
+// definition (see `defn::rule_cells`)
+fn held_cells(coord: &Coordinate, grammar: &Grammar) -> Vec<Coordinate> {
+    match &grammar.kind {
+        Kind::Grid(sub_coords) => sub_coords
+            .iter()
+            .map(|sub_coord| Coordinate::child_of(coord, *sub_coord))
+            .collect(),
+        Kind::Defn(_, _, rules) => rule_cells(coord, rules),
+        _ => vec![],
+    }
+}
+
+// Orphans are grammars no grid refers to anymore, which structural edits can leave behind.
+// They're never shown, but are still saved with the session. Sorted by coordinate.
+pub fn orphans(grammars: &HashMap<Coordinate, Grammar>) -> Vec<Coordinate> {
+    let reached = reachable(grammars);
+    let mut orphaned: Vec<Coordinate> = grammars
+        .keys()
+        .filter(|c| !reached.contains(c))
+        .cloned()
+        .collect();
+    orphaned.sort_by(|a, b| a.row_cols.cmp(&b.row_cols));
+    orphaned
+}
+
+// Removes the orphaned grammars, returning the coordinates removed
+pub fn prune_orphans(grammars: &mut HashMap<Coordinate, Grammar>) -> Vec<Coordinate> {
+    let orphaned = orphans(grammars);
+    for coord in orphaned.iter() {
+        grammars.remove(coord);
+    }
+    orphaned
+}
+
+// a merged area of a grid, as (row span, col span)
+type Area = ((u32, u32), (u32, u32));
+
+// Inconsistency is a way the grammar map and the grids in it (or the row & column sizes kept
+// for them) disagree, as found by `check`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inconsistency {
+    // a grid lists a sub_coord that has no grammar
+    MissingGrammar(Coordinate),
+    // a grammar its parent doesn't hold: it isn't among the sub_coords of a grid, or the cells of a
+    // definition's sub-rules
+    UnlistedGrammar(Coordinate),
+    MissingRowHeight(Row),
+    MissingColWidth(Col),
+    // two merged areas of the same grid overlap, by a cell of each
+    OverlappingMerge(Coordinate, Coordinate),
+}
+
+impl Inconsistency {
+    // the cell the inconsistency is found at, to jump to
+    pub fn coord(&self) -> Coordinate {
+        match self {
+            Inconsistency::MissingGrammar(coord)
+            | Inconsistency::UnlistedGrammar(coord)
+            | Inconsistency::OverlappingMerge(_, coord) => coord.clone(),
+            Inconsistency::MissingRowHeight(Row(grid, _))
+            | Inconsistency::MissingColWidth(Col(grid, _)) => grid.clone(),
+        }
+    }
+
+    // missing row heights & column widths are all that's wrong with a session right after it's
+    // loaded, as the sizes aren't saved with it
+    pub fn is_structural(&self) -> bool {
+        !matches!(
+            self,
+            Inconsistency::MissingRowHeight(_) | Inconsistency::MissingColWidth(_)
+        )
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Inconsistency::MissingGrammar(coord) => {
+                format! {"{} is in its grid, but has no grammar", coord.to_string()}
+            }
+            Inconsistency::UnlistedGrammar(coord) => {
+                format! {"{} has a grammar, but isn't in its grid", coord.to_string()}
+            }
+            Inconsistency::MissingRowHeight(Row(grid, row)) => {
+                format! {"row {} of {} has no height", row, grid.to_string()}
+            }
+            Inconsistency::MissingColWidth(Col(grid, col)) => {
+                format! {"column {} of {} has no width", col, grid.to_string()}
+            }
+            Inconsistency::OverlappingMerge(first, second) => {
+                format! {"merged cells at {} overlap those at {}", second.to_string(), first.to_string()}
+            }
+        }
+    }
+}
+
+// Checks that:
+// - every sub_coord of a grid has a grammar, and every nested grammar is held by its parent (see
+//   `held_cells`)
+// - every row & column of a grid has a height & width
+// - the merged areas of a grid don't overlap
+pub fn check(
+    grammars: &HashMap<Coordinate, Grammar>,
+    row_heights: &HashMap<Row, f64>,
+    col_widths: &HashMap<Col, f64>,
+) -> Vec<Inconsistency> {
+    let mut found = vec![];
+    let mut grids: Vec<(&Coordinate, &Vec<(NonZeroU32, NonZeroU32)>)> = grammars
+        .iter()
+        .filter_map(|(coord, grammar)| match &grammar.kind {
+            Kind::Grid(sub_coords) => Some((coord, sub_coords)),
+            _ => None,
+        })
+        .collect();
+    grids.sort_by(|a, b| a.0.row_cols.cmp(&b.0.row_cols));
+    for (grid, sub_coords) in grids.iter() {
+        let mut rows: Vec<NonZeroU32> = sub_coords.iter().map(|(row, _)| *row).collect();
+        let mut cols: Vec<NonZeroU32> = sub_coords.iter().map(|(_, col)| *col).collect();
+        rows.sort();
+        rows.dedup();
+        cols.sort();
+        cols.dedup();
+        for sub_coord in sub_coords.iter() {
+            let child = Coordinate::child_of(grid, *sub_coord);
+            if !grammars.contains_key(&child) {
+                found.push(Inconsistency::MissingGrammar(child));
+            }
+        }
+        for row in rows {
+            let row = Row((*grid).clone(), row);
+            if !row_heights.contains_key(&row) {
+                found.push(Inconsistency::MissingRowHeight(row));
+            }
+        }
+        for col in cols {
+            let col = Col((*grid).clone(), col);
+            if !col_widths.contains_key(&col) {
+                found.push(Inconsistency::MissingColWidth(col));
+            }
+        }
+        let merges = merged_areas(grammars, grid);
+        for (i, (first, first_cell)) in merges.iter().enumerate() {
+            for (second, second_cell) in merges[i + 1..].iter() {
+                if overlaps(first, second) {
+                    found.push(Inconsistency::OverlappingMerge(
+                        first_cell.clone(),
+                        second_cell.clone(),
+                    ));
+                }
+            }
+        }
+    }
+    let mut unlisted: Vec<Coordinate> = grammars
+        .keys()
+        .filter(|coord| match coord.parent() {
+            None => false,
+            Some(parent) => match grammars.get(&parent) {
+                Some(grammar) => !held_cells(&parent, grammar).contains(coord),
+                None => true,
+            },
+        })
+        .cloned()
+        .collect();
+    unlisted.sort_by(|a, b| a.row_cols.cmp(&b.row_cols));
+    found.extend(unlisted.into_iter().map(Inconsistency::UnlistedGrammar));
+    found
+}
+
+// Repairs what `check` finds, returning what was repaired:
+// - missing grammars are added as empty inputs
+// - unlisted grammars are added to their parent grid, or removed (with everything nested in them)
+//   if their parent isn't one
+// - missing row heights & column widths are set to the given defaults
+// - of two overlapping merged areas, the second one is unmerged
+// Listing a grammar can give its grid a new row or column, so repairs are checked again until
+// there's nothing left to repair. As this can remove grammars, it's only done when asked for.
+pub fn repair(
+    grammars: &mut HashMap<Coordinate, Grammar>,
+    row_heights: &mut HashMap<Row, f64>,
+    col_widths: &mut HashMap<Col, f64>,
+    defaults: (f64, f64),
+) -> Vec<Inconsistency> {
+    let mut repaired = vec![];
+    loop {
+        let found = check(grammars, row_heights, col_widths);
+        if found.is_empty() {
+            return repaired;
+        }
+        repair_once(grammars, row_heights, col_widths, defaults, &found);
+        repaired.extend(found);
+    }
+}
+
+// Sets the row heights & column widths `check` finds missing to the given defaults, which is all
+// that's repaired without asking, returning the (structural) inconsistencies left for `repair`
+pub fn repair_sizes(
+    grammars: &HashMap<Coordinate, Grammar>,
+    row_heights: &mut HashMap<Row, f64>,
+    col_widths: &mut HashMap<Col, f64>,
+    (default_row_height, default_col_width): (f64, f64),
+) -> Vec<Inconsistency> {
+    let mut left = vec![];
+    for inconsistency in check(grammars, row_heights, col_widths) {
+        match inconsistency {
+            Inconsistency::MissingRowHeight(row) => {
+                row_heights.insert(row, default_row_height);
+            }
+            Inconsistency::MissingColWidth(col) => {
+                col_widths.insert(col, default_col_width);
+            }
+            _ => left.push(inconsistency),
+        }
+    }
+    left
+}
+
+fn repair_once(
+    grammars: &mut HashMap<Coordinate, Grammar>,
+    row_heights: &mut HashMap<Row, f64>,
+    col_widths: &mut HashMap<Col, f64>,
+    (default_row_height, default_col_width): (f64, f64),
+    found: &[Inconsistency],
+) {
+    for inconsistency in found.iter() {
+        match inconsistency {
+            Inconsistency::MissingGrammar(coord) => {
+                grammars.insert(coord.clone(), Grammar::default());
+            }
+            Inconsistency::UnlistedGrammar(coord) => {
+                let parent = coord.parent().expect("unlisted grammars are nested");
+                match grammars.get_mut(&parent) {
+                    Some(Grammar {
+                        kind: Kind::Grid(sub_coords),
+                        ..
+                    }) => sub_coords.push(coord.row_col()),
+                    // grammars nested under one of these are removed along with them
+                    _ => {
+                        grammars.retain(|c, _| !c.row_cols.starts_with(&coord.row_cols));
+                    }
+                }
+            }
+            Inconsistency::MissingRowHeight(row) => {
+                row_heights.insert(row.clone(), default_row_height);
+            }
+            Inconsistency::MissingColWidth(col) => {
+                col_widths.insert(col.clone(), default_col_width);
+            }
+            Inconsistency::OverlappingMerge(_, second) => {
+                let grid = second.parent().expect("merged cells are nested");
+                let area = grammars
+                    .get(second)
+                    .map(|g| (g.style.row_span, g.style.col_span));
+                for (coord, grammar) in grammars.iter_mut() {
+                    if coord.parent().as_ref() == Some(&grid)
+                        && Some((grammar.style.row_span, grammar.style.col_span)) == area
+                    {
+                        grammar.style.row_span = (0, 0);
+                        grammar.style.col_span = (0, 0);
+                        grammar.style.display = true;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// the distinct merged areas of a grid's cells, each with the first of the cells merged into it
+fn merged_areas(
+    grammars: &HashMap<Coordinate, Grammar>,
+    grid: &Coordinate,
+) -> Vec<(Area, Coordinate)> {
+    let mut cells: Vec<(Area, Coordinate)> = grammars
+        .iter()
+        .filter(|(coord, grammar)| {
+            coord.parent().as_ref() == Some(grid)
+                && (grammar.style.row_span.0 != 0 || grammar.style.col_span.0 != 0)
+        })
+        .map(|(coord, grammar)| {
+            (
+                (grammar.style.row_span, grammar.style.col_span),
+                coord.clone(),
+            )
+        })
+        .collect();
+    cells.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.row_cols.cmp(&b.1.row_cols)));
+    cells.dedup_by(|a, b| a.0 == b.0);
+    cells
+}
+
+fn overlaps((rows_a, cols_a): &Area, (rows_b, cols_b): &Area) -> bool {
+    rows_a.0 <= rows_b.1 && rows_b.0 <= rows_a.1 && cols_a.0 <= cols_b.1 && cols_b.0 <= cols_a.1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use crate::defn::definition_from_region;
+
+    #[test]
+    fn test_orphans() {
+        let mut grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => Grammar::default(),
+            coord!("root-B1") => Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(1).unwrap()),
+            coord!("root-B1-A1") => Grammar::default(),
+            // left behind by deleting a row
+            coord!("root-A2") => Grammar::default(),
+            // under a cell that's no longer a grid
+            coord!("root-A1-A1") => Grammar::default(),
+            coord!("meta") => Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(1).unwrap()),
+            coord!("meta-A1") => Grammar::default(),
+        };
+        assert_eq!(
+            orphans(&grammars),
+            vec![coord!("root-A1-A1"), coord!("root-A2")]
+        );
+        assert_eq!(prune_orphans(&mut grammars).len(), 2);
+        assert_eq!(grammars.len(), 6);
+        assert!(orphans(&grammars).is_empty());
+
+        // the sub-rules of a definition, and what's nested in them, are held by the definition
+        definition_from_region(
+            &mut grammars,
+            &coord!("root"),
+            (1, 1),
+            (1, 2),
+            "pair",
+            &coord!("meta-A1"),
+        )
+        .unwrap();
+        assert!(orphans(&grammars).is_empty());
+    }
+
+    fn merged(row_span: (u32, u32), col_span: (u32, u32)) -> Grammar {
+        let mut grammar = Grammar::default();
+        grammar.style.row_span = row_span;
+        grammar.style.col_span = col_span;
+        grammar
+    }
+
+    #[test]
+    fn test_check_and_repair() {
+        let mut grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => merged((1, 1), (1, 2)),
+            coord!("root-B1") => merged((1, 1), (1, 2)),
+            // left over from an earlier merge of B1:B2
+            coord!("root-B2") => merged((1, 2), (2, 2)),
+            // no grammar for root-A2
+            coord!("root-C1") => Grammar::default(),
+            coord!("root-A1-A1") => Grammar::default(),
+        };
+        let mut row_heights = hashmap! { Row(coord!("root"), NonZeroU32::new(1).unwrap()) => 30.0 };
+        let mut col_widths = hashmap! {
+            Col(coord!("root"), NonZeroU32::new(1).unwrap()) => 90.0,
+            Col(coord!("root"), NonZeroU32::new(2).unwrap()) => 90.0,
+        };
+        let found = check(&grammars, &row_heights, &col_widths);
+        assert_eq!(
+            found,
+            vec![
+                Inconsistency::MissingGrammar(coord!("root-A2")),
+                Inconsistency::MissingRowHeight(Row(coord!("root"), NonZeroU32::new(2).unwrap())),
+                Inconsistency::OverlappingMerge(coord!("root-A1"), coord!("root-B2")),
+                Inconsistency::UnlistedGrammar(coord!("root-A1-A1")),
+                Inconsistency::UnlistedGrammar(coord!("root-C1")),
+            ]
+        );
+        assert_eq!(
+            found[0].describe(),
+            "root-A2 is in its grid, but has no grammar"
+        );
+
+        let repaired = repair(
+            &mut grammars,
+            &mut row_heights,
+            &mut col_widths,
+            (30.0, 90.0),
+        );
+        // listing root-C1 adds a third column to root
+        assert_eq!(repaired[..found.len()], found[..]);
+        assert_eq!(
+            repaired[found.len()..],
+            [Inconsistency::MissingColWidth(Col(
+                coord!("root"),
+                NonZeroU32::new(3).unwrap()
+            ))]
+        );
+        assert!(check(&grammars, &row_heights, &col_widths).is_empty());
+        assert!(grammars.contains_key(&coord!("root-A2")));
+        assert!(!grammars.contains_key(&coord!("root-A1-A1")));
+        assert_eq!(grammars[&coord!("root-B2")].style.row_span, (0, 0));
+        assert_eq!(grammars[&coord!("root-B1")].style.row_span, (1, 1));
+    }
+
+    #[test]
+    fn test_check_definitions() {
+        let mut grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => Grammar::input("", "name"),
+            coord!("root-B1") => Grammar::input("", "age"),
+            coord!("root-A2") => Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(1).unwrap()),
+            coord!("root-A2-A1") => Grammar::input("", "Ada"),
+            coord!("root-B2") => Grammar::input("", "36"),
+            coord!("meta") => Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(1).unwrap()),
+            coord!("meta-A1") => Grammar::default(),
+        };
+        definition_from_region(
+            &mut grammars,
+            &coord!("root"),
+            (1, 2),
+            (1, 2),
+            "person",
+            &coord!("meta-A1"),
+        )
+        .unwrap();
+        let (mut row_heights, mut col_widths) = (HashMap::new(), HashMap::new());
+        // a session with a definition has nothing to repair once it's loaded
+        assert!(repair_sizes(&grammars, &mut row_heights, &mut col_widths, (30.0, 90.0)).is_empty());
+        assert!(check(&grammars, &row_heights, &col_widths).is_empty());
+
+        // a cell under a definition that isn't one of its sub-rules isn't held by it
+        grammars.insert(coord!("meta-A1-C1"), Grammar::default());
+        assert_eq!(
+            check(&grammars, &row_heights, &col_widths),
+            vec![Inconsistency::UnlistedGrammar(coord!("meta-A1-C1"))]
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::coordinate::Coordinate;
+use crate::grammar::Grammar;
+
+// Cells are keyed so the UI can tell where a cell went once rows & columns have been inserted,
+// deleted, moved or merged: a grammar keeps its key (`Style::key`) wherever it's moved to, and
+// only new grammars (or copies of others) get a new one.
+
+// Gives the grammars without a key a new one, counting up from `next_key`. When copies share a
+// key, the first one (in coordinate order) keeps it.
+pub fn assign_keys(grammars: &mut HashMap<Coordinate, Grammar>, next_key: &mut u64) {
+    let mut coords: Vec<Coordinate> = grammars.keys().cloned().collect();
+    coords.sort_by(|a, b| a.row_cols.cmp(&b.row_cols));
+    let mut seen = HashSet::new();
+    for coord in coords {
+        if let Some(grammar) = grammars.get_mut(&coord) {
+            if grammar.style.key == 0 || !seen.insert(grammar.style.key) {
+                *next_key += 1;
+                grammar.style.key = *next_key;
+                seen.insert(*next_key);
+            }
+        }
+    }
+}
+
+// The element id of the cell each grammar is rendered in, by key
+pub fn cell_ids(grammars: &HashMap<Coordinate, Grammar>) -> HashMap<String, String> {
+    grammars
+        .iter()
+        .filter(|(_, g)| g.style.key != 0)
+        .map(|(c, g)| (g.style.key.to_string(), format! {"cell-{}", c.to_string()}))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+
+    #[test]
+    fn test_assign_keys() {
+        let mut grammars = hashmap! {
+            coord!("root-A1") => Grammar::input("", "apples"),
+            coord!("root-A2") => Grammar::input("", "pears"),
+        };
+        let mut next_key = 0;
+        assign_keys(&mut grammars, &mut next_key);
+        assert_eq!(next_key, 2);
+        assert_eq!(
+            cell_ids(&grammars).get("1"),
+            Some(&"cell-root-A1".to_string())
+        );
+
+        // of two cells sharing a key, the copy further down gets a new one
+        let copy = grammars.get(&coord!("root-A2")).unwrap().clone();
+        grammars.insert(coord!("root-A3"), copy);
+        assign_keys(&mut grammars, &mut next_key);
+        let ids = cell_ids(&grammars);
+        assert_eq!(ids.get("2"), Some(&"cell-root-A2".to_string()));
+        assert_eq!(ids.get("3"), Some(&"cell-root-A3".to_string()));
+        assert_eq!(ids.len(), 3);
+    }
+}
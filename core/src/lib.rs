@@ -0,0 +1,55 @@
+extern crate pest;
+#[cfg(test)]
+#[macro_use]
+extern crate maplit;
+#[macro_use]
+extern crate pest_derive;
+#[cfg(feature = "stdweb")]
+#[macro_use]
+extern crate stdweb;
+
+pub mod assets;
+pub mod bindings;
+pub mod calc;
+pub mod cli;
+pub mod clipboard;
+pub mod clipboard_history;
+pub mod coordinate;
+pub mod dedup;
+pub mod defn;
+pub mod deps;
+pub mod diagnostics;
+pub mod entry;
+pub mod export;
+pub mod external;
+pub mod formula;
+pub mod fragment;
+pub mod gauge;
+pub mod goal_seek;
+pub mod grammar;
+pub mod grammar_map;
+pub mod history;
+pub mod hooks;
+pub mod integrity;
+pub mod keys;
+pub mod lines;
+pub mod locale;
+pub mod macros;
+pub mod meta_import;
+pub mod outline;
+pub mod outline_import;
+pub mod packages;
+pub mod reminders;
+pub mod rename;
+pub mod selection;
+pub mod session;
+pub mod session_merge;
+pub mod shape;
+pub mod style;
+pub mod suggestion;
+pub mod trash;
+pub mod util;
+pub mod value;
+pub mod variables;
+pub mod visibility;
+pub mod zoom;
@@ -0,0 +1,1026 @@
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+
+use crate::coordinate::{Col, Coordinate, Row};
+use crate::formula::{is_formula, reference_spans, Reference};
+use crate::grammar::{Grammar, Interactive, Kind, Lookup, ReminderTrigger};
+use crate::outline::{OutlineAxis, OutlineGroup};
+
+// Where line `n` of a grid (a row or column number) ends up once line `from` has been moved to
+// `to`, the lines in between moving over by one to make room
+pub fn moved_line(n: u32, from: u32, to: u32) -> u32 {
+    if n == from {
+        to
+    } else if from < n && n <= to {
+        n - 1
+    } else if to <= n && n < from {
+        n + 1
+    } else {
+        n
+    }
+}
+
+// Moves row or column `from` of `grid` (with everything nested in it) to `to`. Formulas
+// referring to cells of the grid are updated to keep referring to the same cells, and merged
+// areas move along with their lines.
+// Returns false if `grid` isn't a grid or doesn't have both lines, or if the move would take a
+// line out of merged cells or into them.
+pub fn move_line(
+    grammars: &mut HashMap<Coordinate, Grammar>,
+    grid: &Coordinate,
+    axis: OutlineAxis,
+    from: u32,
+    to: u32,
+) -> bool {
+    let last = match last_line(grammars, grid, axis) {
+        Some(last) => last,
+        None => return false,
+    };
+    if from == to || from.max(to) > last || from.min(to) == 0 {
+        return false;
+    }
+    let remap = |n| moved_line(n, from, to);
+    let splits = merged_lines(grammars, grid, axis)
+        .into_iter()
+        .any(|(first, last)| {
+            let moved: Vec<u32> = (first..=last).map(remap).collect();
+            moved.iter().max().unwrap() - moved.iter().min().unwrap() != last - first
+        });
+    if splits {
+        return false;
+    }
+    remap_lines(grammars, grid, axis, &remap);
+    true
+}
+
+// Inserts a copy of row or column `line` of `grid` (with everything nested in it) right after
+// it, moving the lines after it over by one. Formulas are updated like in `move_line`, and
+// references inside the copied line to cells of that line point at the copy instead. Merged
+// areas the line is in grow over the copy, and those only in that line are copied with it.
+// Returns false if `grid` isn't a grid or doesn't have that line.
+pub fn duplicate_line(
+    grammars: &mut HashMap<Coordinate, Grammar>,
+    grid: &Coordinate,
+    axis: OutlineAxis,
+    line: u32,
+) -> bool {
+    match last_line(grammars, grid, axis) {
+        Some(last) if line >= 1 && line <= last => (),
+        _ => return false,
+    }
+    remap_lines(grammars, grid, axis, &|n| if n > line { n + 1 } else { n });
+    let depth = grid.row_cols.len();
+    let copied: Vec<(Coordinate, Grammar)> = grammars
+        .iter()
+        .filter(|(c, _)| in_grid(c, grid) && line_of(c, depth, axis) == line)
+        .map(|(c, g)| {
+            let copy = with_line(c, depth, axis, line + 1);
+            let mut grammar = g.clone();
+            if copy.row_cols.len() == depth + 1 {
+                let merged = merge_span(&mut grammar, axis);
+                if *merged == (line, line) {
+                    *merged = (line + 1, line + 1);
+                }
+            }
+            retarget_grammar(
+                &mut grammar,
+                &|r| {
+                    if in_grid(r, grid) && line_of(r, depth, axis) == line {
+                        Some(with_line(r, depth, axis, line + 1))
+                    } else {
+                        None
+                    }
+                },
+                &|_, _, _| None,
+            );
+            (copy, grammar)
+        })
+        .collect();
+    let mut added = vec![];
+    for (coord, grammar) in copied {
+        if coord.row_cols.len() == depth + 1 {
+            added.push(coord.row_col());
+        }
+        grammars.insert(coord, grammar);
+    }
+    if let Some(Grammar {
+        kind: Kind::Grid(sub_coords),
+        style,
+        ..
+    }) = grammars.get_mut(grid)
+    {
+        sub_coords.extend(added);
+        let copied_spans: Vec<_> = style
+            .spans
+            .iter()
+            .filter(|(anchor, extent)| {
+                along(anchor, axis).get() == line && along(extent, axis).get() == 1
+            })
+            .map(|(anchor, extent)| {
+                let mut copy = *anchor;
+                *along_mut(&mut copy, axis) = NonZeroU32::new(line + 1).unwrap();
+                (copy, *extent)
+            })
+            .collect();
+        style.spans.extend(copied_spans);
+    }
+    true
+}
+
+// Makes room for a row or column at `line` of `grid`, moving it and the lines after it over by
+// one. Formulas are updated like in `move_line`, and the new line has no cells yet.
+// Returns false if `grid` isn't a grid.
+pub fn insert_line(
+    grammars: &mut HashMap<Coordinate, Grammar>,
+    grid: &Coordinate,
+    axis: OutlineAxis,
+    line: u32,
+) -> bool {
+    if last_line(grammars, grid, axis).is_none() {
+        return false;
+    }
+    remap_lines(grammars, grid, axis, &|n| if n >= line { n + 1 } else { n });
+    true
+}
+
+// Moves the outline groups along with the lines of `grid` `remap` gives the new number of, like
+// it did for `move_line`, `duplicate_line` or `insert_line`: the groups of `grid` along `axis`
+// stretch between the new places of their first & last lines, and the groups of the grids nested
+// in it follow their grid
+pub fn remap_outline_groups(
+    groups: &mut [OutlineGroup],
+    grid: &Coordinate,
+    axis: OutlineAxis,
+    remap: &dyn Fn(u32) -> u32,
+) {
+    let depth = grid.row_cols.len();
+    for group in groups.iter_mut() {
+        if group.parent == *grid && group.axis == axis {
+            let (start, end) = (remap(group.start.get()), remap(group.end.get()));
+            group.start = NonZeroU32::new(start.min(end)).unwrap();
+            group.end = NonZeroU32::new(start.max(end)).unwrap();
+        } else if in_grid(&group.parent, grid) {
+            let line = remap(line_of(&group.parent, depth, axis));
+            group.parent = with_line(&group.parent, depth, axis, line);
+        }
+    }
+}
+
+// Moves the row heights & column widths along with the lines moved by `move_line`: those of
+// the moved lines of `grid` themselves, and those of the grids nested in them
+pub fn move_sizes(
+    row_heights: &mut HashMap<Row, f64>,
+    col_widths: &mut HashMap<Col, f64>,
+    grid: &Coordinate,
+    axis: OutlineAxis,
+    from: u32,
+    to: u32,
+) {
+    let remap = |n| moved_line(n, from, to);
+    rekey_sizes(
+        row_heights,
+        |Row(c, n)| (c, n),
+        Row,
+        grid,
+        axis,
+        OutlineAxis::Rows,
+        &remap,
+        None,
+    );
+    rekey_sizes(
+        col_widths,
+        |Col(c, n)| (c, n),
+        Col,
+        grid,
+        axis,
+        OutlineAxis::Cols,
+        &remap,
+        None,
+    );
+}
+
+// Gives the line inserted by `duplicate_line` (and the grids nested in it) the sizes of the one
+// it's a copy of, moving the sizes of the lines after it over by one
+pub fn duplicate_sizes(
+    row_heights: &mut HashMap<Row, f64>,
+    col_widths: &mut HashMap<Col, f64>,
+    grid: &Coordinate,
+    axis: OutlineAxis,
+    line: u32,
+) {
+    let remap = |n| if n > line { n + 1 } else { n };
+    rekey_sizes(
+        row_heights,
+        |Row(c, n)| (c, n),
+        Row,
+        grid,
+        axis,
+        OutlineAxis::Rows,
+        &remap,
+        Some(line),
+    );
+    rekey_sizes(
+        col_widths,
+        |Col(c, n)| (c, n),
+        Col,
+        grid,
+        axis,
+        OutlineAxis::Cols,
+        &remap,
+        Some(line),
+    );
+}
+
+// The coordinate `coord` takes when the cell `from` (with everything nested in it) takes the
+// place of `to`, if it's `from` or nested in it
+fn rebased(coord: &Coordinate, from: &Coordinate, to: &Coordinate) -> Option<Coordinate> {
+    if !coord.row_cols.starts_with(&from.row_cols) {
+        return None;
+    }
+    let mut moved = to.clone();
+    moved
+        .row_cols
+        .extend(coord.row_cols[from.row_cols.len()..].iter().cloned());
+    Some(moved)
+}
+
+// Swaps cells `a` and `b` of the same grid, with everything nested in them. References to either
+// (or to cells nested in them) are updated to keep referring to the same cells.
+// Returns false if they aren't two cells of the same grid, or if either is part of merged cells.
+pub fn swap_cells(
+    grammars: &mut HashMap<Coordinate, Grammar>,
+    a: &Coordinate,
+    b: &Coordinate,
+) -> bool {
+    let grid = match (a.parent(), b.parent()) {
+        (Some(grid), Some(other)) if grid == other && a != b => grid,
+        _ => return false,
+    };
+    let merged = match grammars.get(&grid) {
+        Some(grid) => {
+            let in_merge = |c: &Coordinate| {
+                grid.spanned_by(c.row_col()).is_some()
+                    || grid
+                        .style
+                        .spans
+                        .iter()
+                        .any(|(anchor, _)| *anchor == c.row_col())
+                    || grammars
+                        .get(c)
+                        .is_some_and(|g| g.style.row_span.0 != 0 || g.style.col_span.0 != 0)
+            };
+            in_merge(a) || in_merge(b)
+        }
+        None => return false,
+    };
+    if merged || !grammars.contains_key(a) || !grammars.contains_key(b) {
+        return false;
+    }
+    let swapped = |coord: &Coordinate| rebased(coord, a, b).or_else(|| rebased(coord, b, a));
+    let cells: Vec<(Coordinate, Grammar)> = grammars
+        .iter()
+        .filter(|(c, _)| swapped(c).is_some())
+        .map(|(c, g)| (c.clone(), g.clone()))
+        .collect();
+    for (coord, _) in cells.iter() {
+        grammars.remove(coord);
+    }
+    for (coord, grammar) in cells {
+        grammars.insert(swapped(&coord).unwrap(), grammar);
+    }
+    for grammar in grammars.values_mut() {
+        retarget_grammar(grammar, &swapped, &|_, _, _| None);
+    }
+    true
+}
+
+// Swaps the row heights & column widths of the grids nested in cells swapped by `swap_cells`.
+// The sizes of the rows & columns of the cells themselves stay, since they're the grid's.
+pub fn swap_sizes(
+    row_heights: &mut HashMap<Row, f64>,
+    col_widths: &mut HashMap<Col, f64>,
+    a: &Coordinate,
+    b: &Coordinate,
+) {
+    let swapped = |coord: &Coordinate| rebased(coord, a, b).or_else(|| rebased(coord, b, a));
+    *row_heights = row_heights
+        .drain()
+        .map(|(Row(c, n), size)| (Row(swapped(&c).unwrap_or(c), n), size))
+        .collect();
+    *col_widths = col_widths
+        .drain()
+        .map(|(Col(c, n), size)| (Col(swapped(&c).unwrap_or(c), n), size))
+        .collect();
+}
+
+// Moves the outline groups of the grids nested in cells swapped by `swap_cells` along with them
+pub fn swap_outline_groups(groups: &mut [OutlineGroup], a: &Coordinate, b: &Coordinate) {
+    for group in groups.iter_mut() {
+        if let Some(moved) = rebased(&group.parent, a, b).or_else(|| rebased(&group.parent, b, a)) {
+            group.parent = moved;
+        }
+    }
+}
+
+// Re-keys the sizes of one kind of line (`key_axis`): `split` and `join` take their keys apart
+// and put them back together. When `copied` is a line, its sizes are copied to the line after
+// it once the lines have been remapped.
+#[allow(clippy::too_many_arguments)]
+fn rekey_sizes<K: std::hash::Hash + Eq>(
+    sizes: &mut HashMap<K, f64>,
+    split: impl Fn(&K) -> (&Coordinate, &NonZeroU32),
+    join: impl Fn(Coordinate, NonZeroU32) -> K,
+    grid: &Coordinate,
+    axis: OutlineAxis,
+    key_axis: OutlineAxis,
+    remap: &dyn Fn(u32) -> u32,
+    copied: Option<u32>,
+) {
+    let depth = grid.row_cols.len();
+    let entries: Vec<(Coordinate, NonZeroU32, f64)> = sizes
+        .drain()
+        .map(|(key, size)| {
+            let (parent, n) = split(&key);
+            (parent.clone(), *n, size)
+        })
+        .collect();
+    for (parent, n, size) in entries {
+        if parent == *grid && axis == key_axis {
+            let line = NonZeroU32::new(remap(n.get())).unwrap();
+            if copied == Some(n.get()) {
+                sizes.insert(
+                    join(parent.clone(), NonZeroU32::new(n.get() + 1).unwrap()),
+                    size,
+                );
+            }
+            sizes.insert(join(parent, line), size);
+        } else if in_grid(&parent, grid) {
+            let line = line_of(&parent, depth, axis);
+            if copied == Some(line) {
+                sizes.insert(join(with_line(&parent, depth, axis, line + 1), n), size);
+            }
+            sizes.insert(join(with_line(&parent, depth, axis, remap(line)), n), size);
+        } else {
+            sizes.insert(join(parent, n), size);
+        }
+    }
+}
+
+// The number of the last row (or column) of `grid`, if it's a grid
+fn last_line(
+    grammars: &HashMap<Coordinate, Grammar>,
+    grid: &Coordinate,
+    axis: OutlineAxis,
+) -> Option<u32> {
+    match grammars.get(grid) {
+        Some(Grammar {
+            kind: Kind::Grid(sub_coords),
+            ..
+        }) => sub_coords
+            .iter()
+            .map(|(r, c)| match axis {
+                OutlineAxis::Rows => r.get(),
+                OutlineAxis::Cols => c.get(),
+            })
+            .max(),
+        _ => None,
+    }
+}
+
+// whether `coord` is nested (at any depth) in `grid`
+fn in_grid(coord: &Coordinate, grid: &Coordinate) -> bool {
+    let depth = grid.row_cols.len();
+    coord.row_cols.len() > depth && coord.row_cols[..depth] == grid.row_cols[..]
+}
+
+// the line of the grid `coord` is in, for a coordinate nested in a grid at `depth`
+fn line_of(coord: &Coordinate, depth: usize, axis: OutlineAxis) -> u32 {
+    let (row, col) = coord.row_cols[depth];
+    match axis {
+        OutlineAxis::Rows => row.get(),
+        OutlineAxis::Cols => col.get(),
+    }
+}
+
+fn with_line(coord: &Coordinate, depth: usize, axis: OutlineAxis, line: u32) -> Coordinate {
+    let mut moved = coord.clone();
+    let (row, col) = &mut moved.row_cols[depth];
+    let n = match axis {
+        OutlineAxis::Rows => row,
+        OutlineAxis::Cols => col,
+    };
+    *n = NonZeroU32::new(line).unwrap();
+    moved
+}
+
+// Re-keys everything nested in `grid` by the line it's in, `remap` giving the new number of each
+// line, and rewrites the references of every grammar to follow the cells they refer to
+fn remap_lines(
+    grammars: &mut HashMap<Coordinate, Grammar>,
+    grid: &Coordinate,
+    axis: OutlineAxis,
+    remap: &dyn Fn(u32) -> u32,
+) {
+    let depth = grid.row_cols.len();
+    let new_coord = |coord: &Coordinate| -> Option<Coordinate> {
+        if !in_grid(coord, grid) {
+            return None;
+        }
+        Some(with_line(
+            coord,
+            depth,
+            axis,
+            remap(line_of(coord, depth, axis)),
+        ))
+    };
+    let cells: Vec<(Coordinate, Grammar)> = grammars
+        .iter()
+        .filter(|(c, _)| in_grid(c, grid))
+        .map(|(c, g)| (c.clone(), g.clone()))
+        .collect();
+    for (coord, _) in cells.iter() {
+        grammars.remove(coord);
+    }
+    for (coord, grammar) in cells {
+        let moved = new_coord(&coord).unwrap_or(coord);
+        grammars.insert(moved, grammar);
+    }
+    let new_line = |g: &Coordinate, line_axis: OutlineAxis, n: u32| {
+        if g == grid && line_axis == axis {
+            Some(remap(n))
+        } else {
+            None
+        }
+    };
+    for grammar in grammars.values_mut() {
+        retarget_grammar(grammar, &new_coord, &new_line);
+    }
+    // merged areas stretch between the new places of their first & last lines
+    for (coord, grammar) in grammars.iter_mut() {
+        if coord.parent().as_ref() == Some(grid) {
+            let merged = merge_span(grammar, axis);
+            if merged.0 != 0 {
+                *merged = (remap(merged.0), remap(merged.1));
+            }
+        }
+    }
+    if let Some(Grammar {
+        kind: Kind::Grid(sub_coords),
+        style,
+        ..
+    }) = grammars.get_mut(grid)
+    {
+        for sub_coord in sub_coords.iter_mut() {
+            let moved = new_coord(&Coordinate::child_of(grid, *sub_coord));
+            if let Some(moved) = moved {
+                *sub_coord = moved.row_col();
+            }
+        }
+        for (anchor, extent) in style.spans.iter_mut() {
+            let first = along(anchor, axis).get();
+            let last = first + along(extent, axis).get() - 1;
+            let (first, last) = (remap(first), remap(last));
+            *along_mut(anchor, axis) = NonZeroU32::new(first).unwrap();
+            *along_mut(extent, axis) = NonZeroU32::new(last - first + 1).unwrap();
+        }
+    }
+}
+
+// The merged areas of `grid` along `axis`, as their first & last lines: those of the cells that
+// span several lines of the grid, and those of the cells merged together
+fn merged_lines(
+    grammars: &HashMap<Coordinate, Grammar>,
+    grid: &Coordinate,
+    axis: OutlineAxis,
+) -> Vec<(u32, u32)> {
+    let mut merged: Vec<(u32, u32)> = match grammars.get(grid) {
+        Some(grammar) => grammar
+            .style
+            .spans
+            .iter()
+            .map(|(anchor, extent)| {
+                let first = along(anchor, axis).get();
+                (first, first + along(extent, axis).get() - 1)
+            })
+            .collect(),
+        None => vec![],
+    };
+    for (coord, grammar) in grammars.iter() {
+        if coord.parent().as_ref() == Some(grid) {
+            let span = match axis {
+                OutlineAxis::Rows => grammar.style.row_span,
+                OutlineAxis::Cols => grammar.style.col_span,
+            };
+            if span.0 != 0 {
+                merged.push(span);
+            }
+        }
+    }
+    merged
+}
+
+// the lines of its grid a merged cell is merged over along `axis`, `(0, 0)` if it isn't merged
+fn merge_span(grammar: &mut Grammar, axis: OutlineAxis) -> &mut (u32, u32) {
+    match axis {
+        OutlineAxis::Rows => &mut grammar.style.row_span,
+        OutlineAxis::Cols => &mut grammar.style.col_span,
+    }
+}
+
+// the row (or column) of a sub_coord, or the rows (or columns) of a span
+fn along(pair: &(NonZeroU32, NonZeroU32), axis: OutlineAxis) -> NonZeroU32 {
+    match axis {
+        OutlineAxis::Rows => pair.0,
+        OutlineAxis::Cols => pair.1,
+    }
+}
+
+fn along_mut(pair: &mut (NonZeroU32, NonZeroU32), axis: OutlineAxis) -> &mut NonZeroU32 {
+    match axis {
+        OutlineAxis::Rows => &mut pair.0,
+        OutlineAxis::Cols => &mut pair.1,
+    }
+}
+
+// Rewrites every reference `grammar` holds to follow the cells it refers to: `retarget` gives the
+// new coordinate of the cells that moved, and `retarget_line` the new number of the rows & columns
+// that moved, by the grid they were in. The references are the formulas of inputs, the sources of
+// gauges & reminder conditions, what lookups look up, the cells of definitions, and the
+// visibility condition, style bindings & button arguments of the style.
+pub fn retarget_grammar(
+    grammar: &mut Grammar,
+    retarget: &dyn Fn(&Coordinate) -> Option<Coordinate>,
+    retarget_line: &dyn Fn(&Coordinate, OutlineAxis, u32) -> Option<u32>,
+) {
+    match &mut grammar.kind {
+        Kind::Input(text) if is_formula(text) => *text = rewrite_references(text, retarget),
+        Kind::Interactive(_, Interactive::Gauge(source, _, _))
+        | Kind::Interactive(_, Interactive::Reminder(ReminderTrigger::When(source), _)) => {
+            *source = retarget_source(source, retarget)
+        }
+        Kind::Lookup(_, Some(lookup)) => {
+            if let Some(moved) = retarget_lookup(lookup, retarget, retarget_line) {
+                *lookup = moved;
+            }
+        }
+        Kind::Defn(_, coord, rules) => {
+            for c in std::iter::once(coord).chain(rules.iter_mut().map(|(_, c)| c)) {
+                if let Some(moved) = retarget(c) {
+                    *c = moved;
+                }
+            }
+        }
+        _ => (),
+    }
+    let style = &mut grammar.style;
+    let sources = vec![
+        &mut style.visible_when,
+        &mut style.bindings.background,
+        &mut style.bindings.font_color,
+        &mut style.bindings.width,
+    ];
+    for source in sources.into_iter().flatten() {
+        *source = retarget_source(source, retarget);
+    }
+    if let Some(action) = &mut style.on_click {
+        for arg in action.args.iter_mut() {
+            *arg = retarget_source(arg, retarget);
+        }
+    }
+}
+
+// A formula, or the cell it names (like the source of gauges), with its references retargeted.
+// Anything else (like the name of a named cell) is left as it is.
+fn retarget_source(source: &str, retarget: &dyn Fn(&Coordinate) -> Option<Coordinate>) -> String {
+    if is_formula(source) {
+        return rewrite_references(source, retarget);
+    }
+    match Coordinate::parse(source.trim()).and_then(|c| retarget(&c)) {
+        Some(moved) => moved.to_string(),
+        None => source.to_string(),
+    }
+}
+
+// What a lookup looks up once the cells (or the lines) it refers to have moved, if they did.
+// A range is only retargeted while its corners stay in the same grid.
+fn retarget_lookup(
+    lookup: &Lookup,
+    retarget: &dyn Fn(&Coordinate) -> Option<Coordinate>,
+    retarget_line: &dyn Fn(&Coordinate, OutlineAxis, u32) -> Option<u32>,
+) -> Option<Lookup> {
+    let line = |grid: &Coordinate, axis: OutlineAxis, n: NonZeroU32| {
+        let moved = (retarget(grid), retarget_line(grid, axis, n.get()));
+        match moved {
+            (None, None) => None,
+            (moved_grid, moved_line) => Some((
+                moved_grid.unwrap_or_else(|| grid.clone()),
+                NonZeroU32::new(moved_line.unwrap_or_else(|| n.get()))?,
+            )),
+        }
+    };
+    match lookup {
+        Lookup::Cell(coord) => retarget(coord).map(Lookup::Cell),
+        Lookup::Range { parent, start, end } => {
+            let first = Coordinate::child_of(parent, *start);
+            let last = Coordinate::child_of(parent, *end);
+            let (first, last) = match (retarget(&first), retarget(&last)) {
+                (None, None) => return None,
+                (first_moved, last_moved) => {
+                    (first_moved.unwrap_or(first), last_moved.unwrap_or(last))
+                }
+            };
+            match (first.parent(), last.parent()) {
+                (Some(parent), Some(other)) if parent == other => Some(Lookup::Range {
+                    parent,
+                    start: first.row_col(),
+                    end: last.row_col(),
+                }),
+                _ => None,
+            }
+        }
+        Lookup::Row(Row(grid, n)) => {
+            line(grid, OutlineAxis::Rows, *n).map(|(g, n)| Lookup::Row(Row(g, n)))
+        }
+        Lookup::Col(Col(grid, n)) => {
+            line(grid, OutlineAxis::Cols, *n).map(|(g, n)| Lookup::Col(Col(g, n)))
+        }
+    }
+}
+
+// The text of a formula with each reference `retarget` gives a new coordinate for replaced by it.
+// Both corners of a range are retargeted on their own.
+pub fn rewrite_references(text: &str, retarget: &dyn Fn(&Coordinate) -> Option<Coordinate>) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut rewritten = String::new();
+    let mut position = 0;
+    for ((start, end), reference) in reference_spans(text) {
+        let replacement = match &reference {
+            Reference::Cell(coord) => retarget(coord).map(|c| c.to_string()),
+            Reference::Range(first, last) => match (retarget(first), retarget(last)) {
+                (None, None) => None,
+                (first_moved, last_moved) => Some(format! {"{}:{}",
+                    first_moved.unwrap_or_else(|| first.clone()).to_string(),
+                    last_moved.unwrap_or_else(|| last.clone()).to_string()
+                }),
+            },
+        };
+        if let Some(replacement) = replacement {
+            rewritten.extend(chars[position..start].iter());
+            rewritten.push_str(&replacement);
+            position = end;
+        }
+    }
+    rewritten.extend(chars[position..].iter());
+    rewritten
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use crate::util::non_zero_u32_tuple;
+
+    // a 3x2 grid at root whose first column holds the row number, with a nested grid in B2
+    fn table() -> HashMap<Coordinate, Grammar> {
+        let mut grammars = hashmap! {
+            coord!("root") => Grammar {
+                kind: Kind::Grid(
+                    (1..=3)
+                        .flat_map(|r| (1..=2).map(move |c| non_zero_u32_tuple((r, c))))
+                        .collect(),
+                ),
+                ..Grammar::default()
+            },
+            coord!("root-B2") => Grammar {
+                kind: Kind::Grid(vec![non_zero_u32_tuple((1, 1))]),
+                ..Grammar::default()
+            },
+            coord!("root-B2-A1") => Grammar::input("", "nested"),
+        };
+        for row in 1..=3 {
+            grammars.insert(
+                Coordinate::child_of(&coord!("root"), non_zero_u32_tuple((row, 1))),
+                Grammar::input("", &row.to_string()),
+            );
+        }
+        grammars.insert(coord!("root-B1"), Grammar::input("", "=root-A3 + 1"));
+        grammars.insert(
+            coord!("root-B3"),
+            Grammar::input("", "=SUM(root-A1:root-A2)"),
+        );
+        grammars
+    }
+
+    fn text(grammars: &HashMap<Coordinate, Grammar>, c: &str) -> String {
+        grammars[&Coordinate::parse(c).unwrap()].text_value()
+    }
+
+    #[test]
+    fn test_moved_line() {
+        let moved: Vec<u32> = (1..=4).map(|n| moved_line(n, 1, 3)).collect();
+        assert_eq!(moved, vec![3, 1, 2, 4]);
+        let moved: Vec<u32> = (1..=4).map(|n| moved_line(n, 3, 1)).collect();
+        assert_eq!(moved, vec![2, 3, 1, 4]);
+    }
+
+    #[test]
+    fn test_move_line() {
+        let mut grammars = table();
+        let first = grammars.get_mut(&coord!("root-A1")).unwrap();
+        first.style.visible_when = Some("root-A3".to_string());
+        assert!(move_line(
+            &mut grammars,
+            &coord!("root"),
+            OutlineAxis::Rows,
+            3,
+            1
+        ));
+        assert_eq!(
+            (
+                text(&grammars, "root-A1"),
+                text(&grammars, "root-A2"),
+                text(&grammars, "root-A3")
+            ),
+            ("3".to_string(), "1".to_string(), "2".to_string())
+        );
+        // nested grammars move along with their row
+        assert_eq!(text(&grammars, "root-B3-A1"), "nested");
+        assert!(!grammars.contains_key(&coord!("root-B2-A1")));
+        // and formulas keep referring to the same cells
+        assert_eq!(text(&grammars, "root-B2"), "=root-A1 + 1");
+        assert_eq!(text(&grammars, "root-B1"), "=SUM(root-A2:root-A3)");
+        // as do the other references cells hold
+        let condition = &grammars[&coord!("root-A2")].style.visible_when;
+        assert_eq!(condition.as_ref().map(|c| c.as_str()), Some("root-A1"));
+        assert!(!move_line(
+            &mut grammars,
+            &coord!("root"),
+            OutlineAxis::Rows,
+            1,
+            4
+        ));
+        assert!(!move_line(
+            &mut grammars,
+            &coord!("root-A1"),
+            OutlineAxis::Rows,
+            1,
+            2
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_line() {
+        let mut grammars = table();
+        assert!(duplicate_line(
+            &mut grammars,
+            &coord!("root"),
+            OutlineAxis::Rows,
+            1
+        ));
+        assert_eq!(text(&grammars, "root-A2"), "1");
+        assert_eq!(text(&grammars, "root-A3"), "2");
+        assert_eq!(text(&grammars, "root-B3-A1"), "nested");
+        // references to other rows follow them, the copy's references to its own row point at it
+        assert_eq!(text(&grammars, "root-B1"), "=root-A4 + 1");
+        assert_eq!(text(&grammars, "root-B2"), "=root-A4 + 1");
+        assert_eq!(text(&grammars, "root-B4"), "=SUM(root-A1:root-A3)");
+        match &grammars[&coord!("root")].kind {
+            Kind::Grid(sub_coords) => assert_eq!(sub_coords.len(), 8),
+            _ => panic!("root should still be a grid"),
+        }
+
+        assert!(duplicate_line(
+            &mut grammars,
+            &coord!("root"),
+            OutlineAxis::Cols,
+            1
+        ));
+        assert_eq!(text(&grammars, "root-B4"), "3");
+        assert_eq!(text(&grammars, "root-C1"), "=root-A4 + 1");
+    }
+
+    #[test]
+    fn test_insert_line() {
+        let mut grammars = table();
+        // A1 spans the first two rows, and a cell outside the grid looks up A3 and the second row
+        grammars.get_mut(&coord!("root")).unwrap().style.spans =
+            vec![(non_zero_u32_tuple((1, 1)), non_zero_u32_tuple((2, 1)))];
+        grammars.insert(
+            coord!("meta-A1"),
+            Grammar {
+                kind: Kind::Lookup("3".to_string(), Some(Lookup::Cell(coord!("root-A3")))),
+                ..Grammar::default()
+            },
+        );
+        grammars.insert(
+            coord!("meta-A2"),
+            Grammar {
+                kind: Kind::Lookup(
+                    "".to_string(),
+                    Some(Lookup::Row(Row(
+                        coord!("root"),
+                        NonZeroU32::new(2).unwrap(),
+                    ))),
+                ),
+                ..Grammar::default()
+            },
+        );
+        let mut groups = vec![OutlineGroup {
+            parent: coord!("root"),
+            axis: OutlineAxis::Rows,
+            start: NonZeroU32::new(2).unwrap(),
+            end: NonZeroU32::new(3).unwrap(),
+            collapsed: false,
+        }];
+        assert!(insert_line(
+            &mut grammars,
+            &coord!("root"),
+            OutlineAxis::Rows,
+            2
+        ));
+        remap_outline_groups(&mut groups, &coord!("root"), OutlineAxis::Rows, &|n| {
+            if n >= 2 {
+                n + 1
+            } else {
+                n
+            }
+        });
+        assert_eq!(text(&grammars, "root-A4"), "3");
+        assert_eq!(
+            grammars[&coord!("meta-A1")].kind,
+            Kind::Lookup("3".to_string(), Some(Lookup::Cell(coord!("root-A4"))))
+        );
+        assert_eq!(
+            grammars[&coord!("meta-A2")].kind,
+            Kind::Lookup(
+                "".to_string(),
+                Some(Lookup::Row(Row(
+                    coord!("root"),
+                    NonZeroU32::new(3).unwrap()
+                )))
+            )
+        );
+        // the inserted row is inside the span, which grows over it
+        assert_eq!(
+            grammars[&coord!("root")].style.spans,
+            vec![(non_zero_u32_tuple((1, 1)), non_zero_u32_tuple((3, 1)))]
+        );
+        assert_eq!((groups[0].start.get(), groups[0].end.get()), (3, 4));
+        // rows can't be moved out of the span
+        assert!(!move_line(
+            &mut grammars,
+            &coord!("root"),
+            OutlineAxis::Rows,
+            3,
+            4
+        ));
+
+        // merged cells only in the duplicated row are merged in its copy too
+        for cell in ["root-A4", "root-B4"].iter() {
+            let grammar = grammars.get_mut(&Coordinate::parse(cell).unwrap()).unwrap();
+            grammar.style.row_span = (4, 4);
+            grammar.style.col_span = (1, 2);
+        }
+        assert!(duplicate_line(
+            &mut grammars,
+            &coord!("root"),
+            OutlineAxis::Rows,
+            4
+        ));
+        assert_eq!(grammars[&coord!("root-B4")].style.row_span, (4, 4));
+        assert_eq!(grammars[&coord!("root-B5")].style.row_span, (5, 5));
+    }
+
+    #[test]
+    fn test_swap_cells() {
+        let mut grammars = table();
+        grammars.insert(
+            coord!("meta-A1"),
+            Grammar {
+                kind: Kind::Lookup("".to_string(), Some(Lookup::Cell(coord!("root-B2-A1")))),
+                ..Grammar::default()
+            },
+        );
+        assert!(swap_cells(
+            &mut grammars,
+            &coord!("root-B2"),
+            &coord!("root-B3")
+        ));
+        // nested grammars go along with their cell
+        assert_eq!(text(&grammars, "root-B3-A1"), "nested");
+        assert_eq!(text(&grammars, "root-B2"), "=SUM(root-A1:root-A2)");
+        assert!(!grammars.contains_key(&coord!("root-B2-A1")));
+        assert_eq!(
+            grammars[&coord!("meta-A1")].kind,
+            Kind::Lookup("".to_string(), Some(Lookup::Cell(coord!("root-B3-A1"))))
+        );
+        assert!(swap_cells(
+            &mut grammars,
+            &coord!("root-A3"),
+            &coord!("root-A1")
+        ));
+        assert_eq!(text(&grammars, "root-A1"), "3");
+        // formulas keep referring to the same cells
+        assert_eq!(text(&grammars, "root-B1"), "=root-A1 + 1");
+        assert_eq!(text(&grammars, "root-B2"), "=SUM(root-A3:root-A2)");
+
+        assert!(!swap_cells(
+            &mut grammars,
+            &coord!("root-A1"),
+            &coord!("root-B3-A1")
+        ));
+        assert!(!swap_cells(
+            &mut grammars,
+            &coord!("root-A1"),
+            &coord!("root-A4")
+        ));
+        assert!(!swap_cells(
+            &mut grammars,
+            &coord!("root"),
+            &coord!("root-A1")
+        ));
+        // merged cells stay where they are
+        grammars.get_mut(&coord!("root-A2")).unwrap().style.row_span = (2, 3);
+        assert!(!swap_cells(
+            &mut grammars,
+            &coord!("root-A1"),
+            &coord!("root-A2")
+        ));
+
+        let mut groups = vec![OutlineGroup {
+            parent: coord!("root-B2"),
+            axis: OutlineAxis::Rows,
+            start: NonZeroU32::new(1).unwrap(),
+            end: NonZeroU32::new(2).unwrap(),
+            collapsed: false,
+        }];
+        swap_outline_groups(&mut groups, &coord!("root-B2"), &coord!("root-B3"));
+        assert_eq!(groups[0].parent, coord!("root-B3"));
+
+        let mut row_heights = hashmap! {
+            Row(coord!("root"), NonZeroU32::new(2).unwrap()) => 20.0,
+            Row(coord!("root-B3"), NonZeroU32::new(1).unwrap()) => 5.0,
+        };
+        let mut col_widths = HashMap::new();
+        swap_sizes(
+            &mut row_heights,
+            &mut col_widths,
+            &coord!("root-B2"),
+            &coord!("root-B3"),
+        );
+        assert_eq!(
+            row_heights,
+            hashmap! {
+                Row(coord!("root"), NonZeroU32::new(2).unwrap()) => 20.0,
+                Row(coord!("root-B2"), NonZeroU32::new(1).unwrap()) => 5.0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_line_sizes() {
+        let row = |grid: &str, n: u32| {
+            Row(
+                Coordinate::parse(grid).unwrap(),
+                NonZeroU32::new(n).unwrap(),
+            )
+        };
+        let mut row_heights = hashmap! {
+            row("root", 1) => 10.0,
+            row("root", 2) => 20.0,
+            row("root", 3) => 30.0,
+            row("root-B2", 1) => 5.0,
+        };
+        let mut col_widths = hashmap! {
+            Col(coord!("root"), NonZeroU32::new(1).unwrap()) => 90.0,
+        };
+        move_sizes(
+            &mut row_heights,
+            &mut col_widths,
+            &coord!("root"),
+            OutlineAxis::Rows,
+            2,
+            3,
+        );
+        assert_eq!(
+            row_heights,
+            hashmap! {
+                row("root", 1) => 10.0,
+                row("root", 3) => 20.0,
+                row("root", 2) => 30.0,
+                row("root-B3", 1) => 5.0,
+            }
+        );
+        duplicate_sizes(
+            &mut row_heights,
+            &mut col_widths,
+            &coord!("root"),
+            OutlineAxis::Rows,
+            3,
+        );
+        assert_eq!(row_heights[&row("root", 4)], 20.0);
+        assert_eq!(row_heights[&row("root-B4", 1)], 5.0);
+        assert_eq!(row_heights[&row("root-B3", 1)], 5.0);
+        assert_eq!(col_widths.len(), 1);
+    }
+}
@@ -0,0 +1,213 @@
+use serde::{Deserialize, Serialize};
+
+use crate::value::{Date, Value};
+
+// Numbers, dates and amounts of money are kept in the session in one canonical form (`1234.5`,
+// `2020-02-29`, `12.50 EUR`), whatever the locale of who typed them, and shown the way the
+// locale of the user looking at them writes them (see `Preferences::locale`). Only how cells
+// are shown changes: they're still edited, saved and calculated with in canonical form.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum Locale {
+    // values are shown as they're stored
+    #[default]
+    Canonical,
+    EnUs,
+    EnGb,
+    DeDe,
+    FrFr,
+    JaJp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DateOrder {
+    Ymd,
+    Dmy,
+    Mdy,
+}
+
+// How a locale writes numbers, dates and money
+struct Conventions {
+    decimal: char,
+    // between the groups of 3 digits of the integer part
+    group: char,
+    date_order: DateOrder,
+    date_separator: char,
+    // whether the currency symbol goes after the amount ("12,50 €") rather than before it
+    symbol_after: bool,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 6] = [
+        Locale::Canonical,
+        Locale::EnUs,
+        Locale::EnGb,
+        Locale::DeDe,
+        Locale::FrFr,
+        Locale::JaJp,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Locale::Canonical => "As stored (1234.5, 2020-02-29)",
+            Locale::EnUs => "English (US)",
+            Locale::EnGb => "English (UK)",
+            Locale::DeDe => "German",
+            Locale::FrFr => "French",
+            Locale::JaJp => "Japanese",
+        }
+    }
+
+    fn conventions(&self) -> Option<Conventions> {
+        let (decimal, group, date_order, date_separator, symbol_after) = match self {
+            Locale::Canonical => return None,
+            Locale::EnUs => ('.', ',', DateOrder::Mdy, '/', false),
+            Locale::EnGb => ('.', ',', DateOrder::Dmy, '/', false),
+            Locale::DeDe => (',', '.', DateOrder::Dmy, '.', true),
+            // a narrow no-break space, so numbers don't wrap
+            Locale::FrFr => (',', '\u{202f}', DateOrder::Dmy, '/', true),
+            Locale::JaJp => ('.', ',', DateOrder::Ymd, '/', false),
+        };
+        Some(Conventions {
+            decimal,
+            group,
+            date_order,
+            date_separator,
+            symbol_after,
+        })
+    }
+}
+
+// The text of a cell as it's shown in `locale`: numbers, dates and amounts of money the way it
+// writes them, anything else as it is
+pub fn localize(text: &str, locale: Locale) -> String {
+    let conventions = match locale.conventions() {
+        Some(conventions) => conventions,
+        None => return text.to_string(),
+    };
+    match Value::parse(text) {
+        Value::Number(_) => {
+            format_number(text.trim(), &conventions).unwrap_or_else(|| text.to_string())
+        }
+        Value::Date(date) => format_date(&date, &conventions),
+        Value::Text(_) => match parse_money(text) {
+            Some((amount, currency)) => {
+                format_money(amount, currency, &conventions).unwrap_or_else(|| text.to_string())
+            }
+            None => text.to_string(),
+        },
+    }
+}
+
+// A canonical number (`-1234.50`) with the decimal separator and digit grouping of the locale,
+// keeping its digits as they're written. `None` for numbers in other forms, like `1e6`.
+fn format_number(number: &str, conventions: &Conventions) -> Option<String> {
+    let (sign, digits) = match number.chars().next() {
+        Some(c) if c == '-' || c == '+' => (&number[..1], &number[1..]),
+        _ => ("", number),
+    };
+    let mut parts = digits.splitn(2, '.');
+    let integer = parts.next().unwrap_or("");
+    let fraction = parts.next();
+    if integer.is_empty()
+        || !integer
+            .chars()
+            .chain(fraction.unwrap_or("").chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let mut grouped = String::new();
+    for (i, c) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 && integer.len() > 3 {
+            grouped.push(conventions.group);
+        }
+        grouped.push(c);
+    }
+    Some(match fraction {
+        Some(fraction) => format! {"{}{}{}{}", sign, grouped, conventions.decimal, fraction},
+        None => format! {"{}{}", sign, grouped},
+    })
+}
+
+fn format_date(date: &Date, conventions: &Conventions) -> String {
+    let (year, month, day) = (
+        format! {"{:04}", date.year},
+        format! {"{:02}", date.month},
+        format! {"{:02}", date.day},
+    );
+    let parts = match conventions.date_order {
+        DateOrder::Ymd => [year, month, day],
+        DateOrder::Dmy => [day, month, year],
+        DateOrder::Mdy => [month, day, year],
+    };
+    parts.join(&conventions.date_separator.to_string())
+}
+
+// An amount of money in canonical form: a number and an ISO 4217 currency code, either way
+// around (`12.50 EUR`, `EUR 12.50`)
+fn parse_money(text: &str) -> Option<(&str, &str)> {
+    let mut words = text.split_whitespace();
+    let (first, second) = (words.next()?, words.next()?);
+    if words.next().is_some() {
+        return None;
+    }
+    let is_code = |w: &str| w.len() == 3 && w.chars().all(|c| c.is_ascii_uppercase());
+    let is_amount = |w: &str| w.parse::<f64>().is_ok_and(|n| n.is_finite());
+    if is_amount(first) && is_code(second) {
+        Some((first, second))
+    } else if is_code(first) && is_amount(second) {
+        Some((second, first))
+    } else {
+        None
+    }
+}
+
+fn format_money(amount: &str, currency: &str, conventions: &Conventions) -> Option<String> {
+    let symbol = match currency {
+        "USD" => "$",
+        "EUR" => "\u{20ac}",
+        "GBP" => "\u{a3}",
+        "JPY" => "\u{a5}",
+        code => code,
+    };
+    let (sign, amount) = match amount.strip_prefix('-') {
+        Some(amount) => ("-", amount),
+        None => ("", amount),
+    };
+    let number = format_number(amount, conventions)?;
+    Some(if conventions.symbol_after {
+        format! {"{}{} {}", sign, number, symbol}
+    } else if symbol == currency {
+        // codes are set apart from the amount, symbols aren't
+        format! {"{}{} {}", sign, symbol, number}
+    } else {
+        format! {"{}{}{}", sign, symbol, number}
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_localize() {
+        assert_eq!(localize("1234567.50", Locale::EnUs), "1,234,567.50");
+        assert_eq!(localize(" -1234.5 ", Locale::DeDe), "-1.234,5");
+        assert_eq!(localize("999", Locale::FrFr), "999");
+        assert_eq!(localize("12345", Locale::FrFr), "12\u{202f}345");
+        assert_eq!(localize("1e6", Locale::DeDe), "1e6");
+        assert_eq!(localize("1234.5", Locale::Canonical), "1234.5");
+
+        assert_eq!(localize("2020-02-29", Locale::EnUs), "02/29/2020");
+        assert_eq!(localize("2020-02-29", Locale::EnGb), "29/02/2020");
+        assert_eq!(localize("2020-02-29", Locale::DeDe), "29.02.2020");
+        assert_eq!(localize("2020-02-29", Locale::JaJp), "2020/02/29");
+
+        assert_eq!(localize("1234.50 EUR", Locale::DeDe), "1.234,50 \u{20ac}");
+        assert_eq!(localize("EUR 1234.50", Locale::EnGb), "\u{20ac}1,234.50");
+        assert_eq!(localize("-5 USD", Locale::EnUs), "-$5");
+        assert_eq!(localize("12 CHF", Locale::JaJp), "CHF 12");
+        assert_eq!(localize("3 apples", Locale::EnUs), "3 apples");
+        assert_eq!(localize("TRUE", Locale::DeDe), "TRUE");
+    }
+}
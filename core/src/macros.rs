@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::coordinate::Coordinate;
+use crate::export::cell_text;
+use crate::grammar::Grammar;
+
+// Macros are kept in the session, so the buttons of a sheet can run them (see `ButtonAction`)
+// without any code outside of it. A macro is either actions recorded in the app, replayed like
+// a repro log, or a script.
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum MacroBody {
+    // the recorded actions, as the UI's `replay::ReplayStep`s (which is why they're JSON here).
+    // `{param}` in their text is replaced with the argument of the parameter when they're played.
+    Recorded(Vec<Value>),
+    // JavaScript, run as the body of a function of `args` (the arguments by parameter name),
+    // `cell` (which gives the text of a cell, by coordinate or name) and `set` (which sets it)
+    Script(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Macro {
+    pub name: String,
+    #[serde(default)]
+    pub params: Vec<String>,
+    pub body: MacroBody,
+}
+
+impl Macro {
+    pub fn describe(&self) -> String {
+        match &self.body {
+            MacroBody::Recorded(steps) => format! {
+                "{} recorded step{}",
+                steps.len(),
+                if steps.len() == 1 { "" } else { "s" }
+            },
+            MacroBody::Script(_) => "script".to_string(),
+        }
+    }
+}
+
+// ButtonAction is what a button cell runs when it's clicked: a macro of the session, with its
+// arguments taken from cells (by coordinate, or the name of a named cell), one per parameter
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct ButtonAction {
+    pub macro_name: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+// The cell `reference` is: a coordinate, or the name of a named cell (the first one in
+// coordinate order, if there are several)
+pub fn resolve(grammars: &HashMap<Coordinate, Grammar>, reference: &str) -> Option<Coordinate> {
+    let reference = reference.trim();
+    if let Some(coord) = Coordinate::parse(reference).filter(|c| grammars.contains_key(c)) {
+        return Some(coord);
+    }
+    let mut named: Vec<&Coordinate> = grammars
+        .iter()
+        .filter(|(_, g)| !reference.is_empty() && g.name == reference)
+        .map(|(c, _)| c)
+        .collect();
+    named.sort_by(|a, b| a.row_cols.cmp(&b.row_cols));
+    named.first().map(|c| (*c).clone())
+}
+
+// The arguments `action` runs its macro with, by parameter name
+pub fn arguments(
+    grammars: &HashMap<Coordinate, Grammar>,
+    macro_: &Macro,
+    action: &ButtonAction,
+) -> Result<HashMap<String, String>, String> {
+    macro_
+        .params
+        .iter()
+        .enumerate()
+        .map(|(i, param)| {
+            let reference = action
+                .args
+                .get(i)
+                .filter(|r| !r.trim().is_empty())
+                .ok_or_else(|| format! {"no cell is given for the argument {}", param})?;
+            let coord = resolve(grammars, reference)
+                .ok_or_else(|| format! {"{} isn't a cell", reference.trim()})?;
+            Ok((param.clone(), cell_text(grammars, &coord)))
+        })
+        .collect()
+}
+
+// The recorded steps with `{param}` replaced by the arguments in all of their text
+pub fn substitute(steps: &[Value], args: &HashMap<String, String>) -> Vec<Value> {
+    steps
+        .iter()
+        .map(|step| substitute_value(step, args))
+        .collect()
+}
+
+fn substitute_value(value: &Value, args: &HashMap<String, String>) -> Value {
+    match value {
+        Value::String(text) => {
+            Value::String(args.iter().fold(text.clone(), |text, (param, arg)| {
+                text.replace(&format! {"{{{}}}", param}, arg)
+            }))
+        }
+        Value::Array(values) => {
+            Value::Array(values.iter().map(|v| substitute_value(v, args)).collect())
+        }
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(k, v)| (k.clone(), substitute_value(v, args)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn test_arguments() {
+        let mut rate = Grammar::input("", "0.2");
+        rate.name = "rate".to_string();
+        let grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => rate,
+            coord!("root-B1") => Grammar::input("", "150"),
+        };
+        let macro_ = Macro {
+            name: "apply rate".to_string(),
+            params: vec!["rate".to_string(), "amount".to_string()],
+            body: MacroBody::Recorded(vec![serde_json::json!({
+                "action": "change-input",
+                "coord": "root-A2",
+                "value": "={amount} * {rate}",
+            })]),
+        };
+        let mut action = ButtonAction {
+            macro_name: "apply rate".to_string(),
+            args: vec!["rate".to_string()],
+        };
+        assert_eq!(
+            arguments(&grammars, &macro_, &action),
+            Err("no cell is given for the argument amount".to_string())
+        );
+        action.args.push("root-B1".to_string());
+        let args = arguments(&grammars, &macro_, &action).unwrap();
+        assert_eq!(args["rate"], "0.2");
+        assert_eq!(args["amount"], "150");
+
+        match &macro_.body {
+            MacroBody::Recorded(steps) => assert_eq!(
+                substitute(steps, &args)[0]["value"],
+                Value::String("=150 * 0.2".to_string())
+            ),
+            body => panic!("expected recorded steps, got {:?}", body),
+        }
+    }
+}
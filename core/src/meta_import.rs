@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+
+use crate::coordinate::{Col, Coordinate, Row};
+use crate::defn::unique_name;
+use crate::formula::is_formula;
+use crate::fragment::{extract, graft};
+use crate::grammar::{Grammar, Kind, Lookup};
+use crate::lines::rewrite_references;
+use crate::session::Session;
+
+// MetaImport is the state of the "Import meta grammars" dialog: the session file grammars are
+// imported from, the meta grammars it has (by name) and the ones checked to be imported
+#[derive(Debug, Clone)]
+pub struct MetaImport {
+    pub path: String,
+    pub grammars: HashMap<Coordinate, Grammar>,
+    pub listed: Vec<(String, Coordinate)>,
+    pub selected: Vec<Coordinate>,
+}
+
+impl MetaImport {
+    pub fn new(path: String, session: Session) -> MetaImport {
+        let listed = meta_grammars(&session.grammars);
+        MetaImport {
+            path,
+            grammars: session.grammars,
+            listed,
+            selected: vec![],
+        }
+    }
+
+    pub fn toggle(&mut self, coord: &Coordinate) {
+        match self.selected.iter().position(|c| c == coord) {
+            Some(index) => {
+                self.selected.remove(index);
+            }
+            None => self.selected.push(coord.clone()),
+        }
+    }
+}
+
+// The named grammars of the meta sheet (its first column), in row order
+pub fn meta_grammars(grammars: &HashMap<Coordinate, Grammar>) -> Vec<(String, Coordinate)> {
+    let mut listed: Vec<(String, Coordinate)> = grammars
+        .iter()
+        .filter(|(c, g)| {
+            c.row_cols.len() == 2
+                && c.parent().is_some_and(|p| p.to_string() == "meta")
+                && c.col().get() == 1
+                && !g.name.is_empty()
+        })
+        .map(|(c, g)| (g.name.clone(), c.clone()))
+        .collect();
+    listed.sort_by(|(_, a), (_, b)| a.row_cols.cmp(&b.row_cols));
+    listed
+}
+
+// The names `selected` grammars are imported under, by their name in the other session: their
+// own, unless it's `taken` (or by a grammar imported before), see `unique_name`
+pub fn import_names(
+    listed: &[(String, Coordinate)],
+    selected: &[Coordinate],
+    taken: &[String],
+) -> HashMap<String, String> {
+    let mut used: Vec<String> = taken.to_vec();
+    let mut names = HashMap::new();
+    for (name, _) in listed.iter().filter(|(_, c)| selected.contains(c)) {
+        let imported = unique_name(name, &used);
+        used.push(imported.clone());
+        names.insert(name.clone(), imported);
+    }
+    names
+}
+
+// Copies the grammar at `from` in `source` (and everything nested in it) to `dest` in
+// `grammars`. References within the copied grammars (definitions, lookups and formulas) follow
+// them to `dest`, and the names in `names` (see `import_names`) are given to the grammar itself
+// and to the definition bindings & rules that use them.
+pub fn import_grammar(
+    source: &HashMap<Coordinate, Grammar>,
+    from: &Coordinate,
+    dest: &Coordinate,
+    names: &HashMap<String, String>,
+    grammars: &mut HashMap<Coordinate, Grammar>,
+) -> bool {
+    let fragment = match extract(source, &HashMap::new(), &HashMap::new(), from) {
+        Some(fragment) => fragment,
+        None => return false,
+    };
+    graft(&fragment, grammars, dest);
+    let depth = from.row_cols.len();
+    let moved = |coord: &Coordinate| -> Option<Coordinate> {
+        if coord.row_cols.len() < depth || coord.row_cols[..depth] != from.row_cols[..] {
+            return None;
+        }
+        let mut moved = dest.clone();
+        moved
+            .row_cols
+            .extend(coord.row_cols[depth..].iter().cloned());
+        Some(moved)
+    };
+    let follow = |coord: &Coordinate| moved(coord).unwrap_or_else(|| coord.clone());
+    let rename = |name: &str| names.get(name).cloned().unwrap_or_else(|| name.to_string());
+    for cell in fragment.cells.iter() {
+        let mut coord = dest.clone();
+        coord.row_cols.extend(cell.path.iter().cloned());
+        let grammar = match grammars.get_mut(&coord) {
+            Some(grammar) => grammar,
+            None => continue,
+        };
+        grammar.name = rename(&grammar.name);
+        match &mut grammar.kind {
+            Kind::Defn(binding, defn_coord, rules) => {
+                *binding = rename(binding);
+                *defn_coord = follow(defn_coord);
+                for (rule, rule_coord) in rules.iter_mut() {
+                    *rule = rename(rule);
+                    *rule_coord = follow(rule_coord);
+                }
+            }
+            Kind::Lookup(_, Some(lookup)) => match lookup {
+                Lookup::Cell(c) => *c = follow(c),
+                Lookup::Range { parent, .. } => *parent = follow(parent),
+                Lookup::Row(Row(c, _)) | Lookup::Col(Col(c, _)) => *c = follow(c),
+            },
+            Kind::Input(text) if is_formula(text) => {
+                *text = rewrite_references(text, &moved);
+            }
+            _ => (),
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use std::num::NonZeroU32;
+
+    fn library() -> HashMap<Coordinate, Grammar> {
+        hashmap! {
+            coord!("meta") => Grammar::as_grid(NonZeroU32::new(3).unwrap(), NonZeroU32::new(1).unwrap()),
+            coord!("meta-A1") => Grammar::text("point", ""),
+            coord!("meta-A2") => Grammar {
+                kind: Kind::Defn(
+                    "shape".to_string(),
+                    coord!("meta-A2"),
+                    vec![("point".to_string(), coord!("meta-A2-A1"))],
+                ),
+                ..Grammar::text("shape", "")
+            },
+            coord!("meta-A2-A1") => Grammar::input("", "=meta-A2-A2 * 2"),
+            coord!("meta-A2-A2") => Grammar {
+                kind: Kind::Lookup("".to_string(), Some(Lookup::Cell(coord!("meta-A2-A1")))),
+                ..Grammar::default()
+            },
+            coord!("meta-A3") => Grammar::text("", "unnamed"),
+            coord!("root-A1") => Grammar::text("point", "1, 2"),
+        }
+    }
+
+    #[test]
+    fn test_meta_grammars() {
+        assert_eq!(
+            meta_grammars(&library()),
+            vec![
+                ("point".to_string(), coord!("meta-A1")),
+                ("shape".to_string(), coord!("meta-A2")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_import_names() {
+        let listed = meta_grammars(&library());
+        let selected = vec![coord!("meta-A1"), coord!("meta-A2")];
+        let taken = vec!["point".to_string(), "point_2".to_string()];
+        let names = import_names(&listed, &selected, &taken);
+        assert_eq!(names.get("point"), Some(&"point_3".to_string()));
+        assert_eq!(names.get("shape"), Some(&"shape".to_string()));
+        let names = import_names(&listed, &[coord!("meta-A2")], &taken);
+        assert_eq!(names.len(), 1);
+    }
+
+    #[test]
+    fn test_import_grammar() {
+        let source = library();
+        let mut grammars = hashmap! {
+            coord!("meta-A1") => Grammar::text("point", ""),
+        };
+        let names = hashmap! {
+            "point".to_string() => "point_2".to_string(),
+            "shape".to_string() => "shape".to_string(),
+        };
+        assert!(import_grammar(
+            &source,
+            &coord!("meta-A2"),
+            &coord!("meta-A5"),
+            &names,
+            &mut grammars
+        ));
+        assert_eq!(
+            grammars.get(&coord!("meta-A5")).unwrap().kind,
+            Kind::Defn(
+                "shape".to_string(),
+                coord!("meta-A5"),
+                vec![("point_2".to_string(), coord!("meta-A5-A1"))],
+            )
+        );
+        assert_eq!(
+            grammars.get(&coord!("meta-A5-A1")).unwrap().kind,
+            Kind::Input("=meta-A5-A2 * 2".to_string())
+        );
+        assert_eq!(
+            grammars.get(&coord!("meta-A5-A2")).unwrap().kind,
+            Kind::Lookup("".to_string(), Some(Lookup::Cell(coord!("meta-A5-A1"))))
+        );
+        // the grammar already there is left alone
+        assert_eq!(grammars.get(&coord!("meta-A1")).unwrap().name, "point");
+        assert_eq!(grammars.len(), 4);
+        assert!(!import_grammar(
+            &source,
+            &coord!("meta-C9"),
+            &coord!("meta-A6"),
+            &names,
+            &mut grammars
+        ));
+    }
+}
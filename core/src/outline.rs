@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroU32;
+use std::option::Option;
+
+use crate::coordinate::Coordinate;
+
+// Axis an outline group runs along
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum OutlineAxis {
+    Rows,
+    Cols,
+}
+
+// OutlineGroup is a contiguous run of rows or columns inside a single grid (`parent`)
+// that can be collapsed down to its first row/column, which acts as the "summary" line.
+// Outline groups are stored in the session so the collapsed state survives save/load.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct OutlineGroup {
+    pub parent: Coordinate,
+    pub axis: OutlineAxis,
+    pub start: NonZeroU32,
+    pub end: NonZeroU32,
+    pub collapsed: bool,
+}
+
+impl OutlineGroup {
+    // builds a group out of the first (top-leftmost) and last (bottom-rightmost) cells of a
+    // selection, which need to share the same parent grid
+    pub fn from_selection(
+        first: &Coordinate,
+        last: &Coordinate,
+        axis: OutlineAxis,
+    ) -> Option<OutlineGroup> {
+        let parent = first.parent()?;
+        if last.parent() != Some(parent.clone()) {
+            return None;
+        }
+        let (start, end) = match axis {
+            OutlineAxis::Rows => (first.row(), last.row()),
+            OutlineAxis::Cols => (first.col(), last.col()),
+        };
+        let (start, end) = if start > end { (end, start) } else { (start, end) };
+        // a group of one line has nothing to collapse into its summary line
+        if start == end {
+            return None;
+        }
+        Some(OutlineGroup {
+            parent,
+            axis,
+            start,
+            end,
+            collapsed: false,
+        })
+    }
+
+    // index of `coord` along this group's axis, if `coord` (or one of its ancestors)
+    // is a direct child of the group's parent grid
+    fn index_of(&self, coord: &Coordinate) -> Option<u32> {
+        let depth = self.parent.row_cols.len();
+        if coord.row_cols.len() <= depth || coord.row_cols[..depth] != self.parent.row_cols[..] {
+            return None;
+        }
+        let (row, col) = coord.row_cols[depth];
+        Some(match self.axis {
+            OutlineAxis::Rows => row.get(),
+            OutlineAxis::Cols => col.get(),
+        })
+    }
+
+    // whether `coord` is inside this group, including the summary line
+    pub fn contains(&self, coord: &Coordinate) -> bool {
+        self.index_of(coord)
+            .map(|i| self.start.get() <= i && i <= self.end.get())
+            .unwrap_or(false)
+    }
+
+    // whether `coord` is hidden by this group, i.e. the group is collapsed and the coordinate
+    // is inside the group but not on its summary line
+    pub fn hides(&self, coord: &Coordinate) -> bool {
+        self.collapsed
+            && self
+                .index_of(coord)
+                .map(|i| self.start.get() < i && i <= self.end.get())
+                .unwrap_or(false)
+    }
+
+    pub fn overlaps(&self, other: &OutlineGroup) -> bool {
+        self.parent == other.parent
+            && self.axis == other.axis
+            && self.start <= other.end
+            && other.start <= self.end
+    }
+}
+
+// Whether a coordinate should be skipped when rendering (and so printing) or navigating.
+// Exports leave it in: collapsing a group folds the grid on screen, not the data it holds.
+pub fn is_hidden(groups: &[OutlineGroup], coord: &Coordinate) -> bool {
+    groups.iter().any(|g| g.hides(coord))
+}
+
+// Groups that belong to a grid, along with their index in the session's list of groups
+// (the index is what `Action::ToggleOutlineGroup` and `Action::RemoveOutlineGroup` refer to)
+pub fn groups_of(groups: &[OutlineGroup], parent: &Coordinate) -> Vec<(usize, OutlineGroup)> {
+    groups
+        .iter()
+        .enumerate()
+        .filter(|(_, g)| &g.parent == parent)
+        .map(|(i, g)| (i, g.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+
+    #[test]
+    fn test_from_selection() {
+        let group = OutlineGroup::from_selection(
+            &coord!("root-A3"),
+            &coord!("root-B1"),
+            OutlineAxis::Rows,
+        )
+        .unwrap();
+        assert_eq!(group.parent, coord!("root"));
+        assert_eq!(group.start.get(), 1);
+        assert_eq!(group.end.get(), 3);
+        assert!(!group.collapsed);
+
+        // selections spanning different grids or a single line can't be grouped
+        assert_eq!(
+            OutlineGroup::from_selection(
+                &coord!("root-A1"),
+                &coord!("root-A1-B2"),
+                OutlineAxis::Rows
+            ),
+            None
+        );
+        assert_eq!(
+            OutlineGroup::from_selection(
+                &coord!("root-A1"),
+                &coord!("root-A2"),
+                OutlineAxis::Cols
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_hides() {
+        let mut group = OutlineGroup::from_selection(
+            &coord!("root-A2"),
+            &coord!("root-A4"),
+            OutlineAxis::Rows,
+        )
+        .unwrap();
+        assert!(!group.hides(&coord!("root-A3")));
+        group.collapsed = true;
+        // summary line stays visible
+        assert!(!group.hides(&coord!("root-B2")));
+        assert!(group.hides(&coord!("root-B3")));
+        assert!(group.hides(&coord!("root-C4-A1")));
+        assert!(!group.hides(&coord!("root-A5")));
+        assert!(!group.hides(&coord!("meta-A3")));
+        assert!(is_hidden(&[group], &coord!("root-A4")));
+    }
+}
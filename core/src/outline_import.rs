@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+
+use crate::coordinate::Coordinate;
+use crate::external::write_table;
+use crate::grammar::Grammar;
+
+// Importing outlines (OPML, Markdown lists and indented plain text) into nested grids. Each item
+// of an outline takes a row of the grid of its level: its text in the first column, and the grid
+// of the items under it in the second, so every level of the outline is a grid nested one deeper.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutlineNode {
+    pub text: String,
+    pub children: Vec<OutlineNode>,
+}
+
+impl OutlineNode {
+    fn new(text: String) -> OutlineNode {
+        OutlineNode { text, children: vec![] }
+    }
+}
+
+// Parses an outline by the extension of its file: .opml (or .xml) as OPML, .md as Markdown, and
+// anything else as indented text
+pub fn parse_outline(path: &str, content: &str) -> Result<Vec<OutlineNode>, String> {
+    let path = path.to_lowercase();
+    let nodes = if path.ends_with(".opml") || path.ends_with(".xml") {
+        parse_opml(content)?
+    } else {
+        parse_indented(content, path.ends_with(".md") || path.ends_with(".markdown"))
+    };
+    if nodes.is_empty() {
+        return Err("the outline has no items".to_string());
+    }
+    Ok(nodes)
+}
+
+// The `<outline>` elements of an OPML document, by their `text` (or `title`) attribute
+fn parse_opml(content: &str) -> Result<Vec<OutlineNode>, String> {
+    // the items whose element is still open, the outermost first
+    let mut open: Vec<OutlineNode> = vec![OutlineNode::new(String::new())];
+    let mut rest = content;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start..];
+        let end = rest
+            .find('>')
+            .ok_or_else(|| "an element of the outline isn't closed".to_string())?;
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+        if tag.starts_with("/outline") {
+            if open.len() < 2 {
+                return Err("an </outline> doesn't close anything".to_string());
+            }
+            let node = open.pop().unwrap();
+            open.last_mut().unwrap().children.push(node);
+        } else if tag.starts_with("outline") {
+            let text = attribute(tag, "text")
+                .or_else(|| attribute(tag, "title"))
+                .unwrap_or_default();
+            let node = OutlineNode::new(unescape(&text));
+            if tag.ends_with('/') {
+                open.last_mut().unwrap().children.push(node);
+            } else {
+                open.push(node);
+            }
+        }
+    }
+    if open.len() > 1 {
+        return Err("an <outline> isn't closed".to_string());
+    }
+    Ok(open.pop().unwrap().children)
+}
+
+// The value of the attribute `name` of a tag, quoted with either quote
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let mut search = tag;
+    while let Some(i) = search.find(name) {
+        let before = search[..i].chars().last();
+        let after = search[i + name.len()..].trim_start();
+        search = &search[i + name.len()..];
+        if !before.is_some_and(char::is_whitespace) || !after.starts_with('=') {
+            continue;
+        }
+        let value = after[1..].trim_start();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let value = &value[1..];
+        return value.find(quote).map(|end| value[..end].to_string());
+    }
+    None
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest.find(';').map(|end| &rest[1..end]);
+        let c = match entity {
+            Some("amp") => Some('&'),
+            Some("lt") => Some('<'),
+            Some("gt") => Some('>'),
+            Some("quot") => Some('"'),
+            Some("apos") => Some('\''),
+            Some(e) if e.starts_with("#x") => u32::from_str_radix(&e[2..], 16).ok().and_then(std::char::from_u32),
+            Some(e) if e.starts_with('#') => e[1..].parse().ok().and_then(std::char::from_u32),
+            _ => None,
+        };
+        match (c, entity) {
+            (Some(c), Some(entity)) => {
+                unescaped.push(c);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+// Lines nested by their indentation (a tab counts as 4 spaces), skipping blank ones. In Markdown,
+// list markers (`-`, `*`, `+`, `1.`) are left out, and headings nest by their level, with the
+// lists under them nested in them.
+fn parse_indented(content: &str, markdown: bool) -> Vec<OutlineNode> {
+    // the (level, text) of every item
+    let mut items: Vec<(usize, String)> = vec![];
+    // the indentation of every level of the current list, and the level lists start at (one
+    // under the last heading)
+    let mut indents: Vec<usize> = vec![];
+    let mut base = 0;
+    for line in content.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let indent: usize = line
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .map(|c| if c == '\t' { 4 } else { 1 })
+            .sum();
+        let text = line.trim();
+        if markdown {
+            let hashes = text.chars().take_while(|c| *c == '#').count();
+            if hashes > 0 && text[hashes..].starts_with(' ') {
+                items.push((hashes - 1, text[hashes..].trim().to_string()));
+                base = hashes;
+                indents.clear();
+                continue;
+            }
+        }
+        while indents.last().is_some_and(|last| *last > indent) {
+            indents.pop();
+        }
+        if indents.last() != Some(&indent) {
+            indents.push(indent);
+        }
+        let text = if markdown { strip_marker(text) } else { text };
+        items.push((base + indents.len() - 1, text.to_string()));
+    }
+    nest(&items)
+}
+
+fn strip_marker(text: &str) -> &str {
+    for marker in &["- ", "* ", "+ "] {
+        if let Some(item) = text.strip_prefix(marker) {
+            return item.trim_start();
+        }
+    }
+    let digits = text.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 && (text[digits..].starts_with(". ") || text[digits..].starts_with(") ")) {
+        return text[digits + 2..].trim_start();
+    }
+    text
+}
+
+// Nests items under the last item of a lower level before them. Items skipping levels are nested
+// one level under that item.
+fn nest(items: &[(usize, String)]) -> Vec<OutlineNode> {
+    let mut open: Vec<(usize, OutlineNode)> = vec![];
+    let mut roots = vec![];
+    let close = |open: &mut Vec<(usize, OutlineNode)>, roots: &mut Vec<OutlineNode>| {
+        let (_, node) = open.pop().unwrap();
+        match open.last_mut() {
+            Some((_, parent)) => parent.children.push(node),
+            None => roots.push(node),
+        }
+    };
+    for (level, text) in items {
+        while open.last().is_some_and(|(l, _)| l >= level) {
+            close(&mut open, &mut roots);
+        }
+        open.push((*level, OutlineNode::new(text.clone())));
+    }
+    while !open.is_empty() {
+        close(&mut open, &mut roots);
+    }
+    roots
+}
+
+// Replaces the contents of `grid` with the items of an outline, one per row, each with the grid
+// of the items under it next to it
+pub fn write_outline(grammars: &mut HashMap<Coordinate, Grammar>, grid: &Coordinate, nodes: &[OutlineNode]) {
+    let nested = nodes.iter().any(|node| !node.children.is_empty());
+    let table: Vec<Vec<String>> = nodes
+        .iter()
+        .map(|node| {
+            if nested {
+                vec![node.text.clone(), String::new()]
+            } else {
+                vec![node.text.clone()]
+            }
+        })
+        .collect();
+    write_table(grammars, grid, &table);
+    for (row, node) in nodes.iter().enumerate() {
+        if !node.children.is_empty() {
+            let sub_coord = (NonZeroU32::new(row as u32 + 1).unwrap(), NonZeroU32::new(2).unwrap());
+            write_outline(grammars, &Coordinate::child_of(grid, sub_coord), &node.children);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use crate::external::grid_table;
+
+    fn node(text: &str, children: Vec<OutlineNode>) -> OutlineNode {
+        OutlineNode {
+            text: text.to_string(),
+            children,
+        }
+    }
+
+    #[test]
+    fn test_parse_outline() {
+        let expected = vec![
+            node("fruit", vec![node("apple", vec![]), node("pear & quince", vec![])]),
+            node("veg", vec![]),
+        ];
+        let opml = r#"<?xml version="1.0"?>
+            <opml version="2.0"><head><title>shop</title></head><body>
+              <outline text="fruit">
+                <outline text="apple"/>
+                <outline title='x' text="pear &amp; quince" />
+              </outline>
+              <outline text="veg"></outline>
+            </body></opml>"#;
+        assert_eq!(parse_outline("shop.OPML", opml), Ok(expected.clone()));
+        assert!(parse_outline("shop.opml", "<opml><outline text=\"fruit\"></opml>").is_err());
+
+        let text = "fruit\n\tapple\n    pear & quince\n\nveg\n";
+        assert_eq!(parse_outline("shop.txt", text), Ok(expected.clone()));
+
+        let markdown = "- fruit\n  * apple\n  2. pear & quince\n- veg\n";
+        assert_eq!(parse_outline("shop.md", markdown), Ok(expected));
+
+        let markdown = "# shop\n- fruit\n  - apple\n## other\nveg\n";
+        assert_eq!(
+            parse_outline("shop.md", markdown),
+            Ok(vec![node(
+                "shop",
+                vec![
+                    node("fruit", vec![node("apple", vec![])]),
+                    node("other", vec![node("veg", vec![])])
+                ]
+            )])
+        );
+        assert!(parse_outline("empty.txt", "\n  \n").is_err());
+    }
+
+    #[test]
+    fn test_write_outline() {
+        let mut grammars = HashMap::new();
+        let outline = vec![
+            node("fruit", vec![node("apple", vec![]), node("pear", vec![])]),
+            node("veg", vec![]),
+        ];
+        write_outline(&mut grammars, &coord!("root-A1"), &outline);
+        let table = |rows: &[&[&str]]| -> Vec<Vec<String>> {
+            rows.iter().map(|r| r.iter().map(|s| s.to_string()).collect()).collect()
+        };
+        assert_eq!(
+            grid_table(&grammars, &coord!("root-A1")),
+            Some(table(&[&["fruit", "[2x1 grid: apple...]"], &["veg", ""]]))
+        );
+        assert_eq!(
+            grid_table(&grammars, &coord!("root-A1-B1")),
+            Some(table(&[&["apple"], &["pear"]]))
+        );
+    }
+}
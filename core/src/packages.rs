@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+use crate::coordinate::Coordinate;
+use crate::fragment::Fragment;
+
+// A registry is a local folder or a URL with an `index.json` listing the grammar packs
+// it offers. Packs are JSON files next to the index (or anywhere, by URL).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RegistryIndex {
+    pub packs: Vec<PackListing>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PackListing {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    // relative to the registry, or a full URL
+    pub location: String,
+}
+
+// GrammarPack is a collection of meta grammars (including definitions and their styles),
+// each installed as a row of the meta sheet
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GrammarPack {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    pub grammars: Vec<PackGrammar>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PackGrammar {
+    pub name: String,
+    pub fragment: Fragment,
+}
+
+// A pack installed into a session, and the meta rows its grammars were installed at,
+// so it can be updated or removed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct InstalledPack {
+    pub name: String,
+    pub version: String,
+    pub registry: String,
+    pub coords: Vec<Coordinate>,
+}
+
+pub fn is_url(location: &str) -> bool {
+    location.starts_with("http://") || location.starts_with("https://")
+}
+
+// Where the index of a registry is
+pub fn index_location(registry: &str) -> String {
+    resolve(registry, "index.json")
+}
+
+// Where a file listed by a registry is
+pub fn resolve(registry: &str, location: &str) -> String {
+    if is_url(location) || location.starts_with('/') {
+        location.to_string()
+    } else {
+        format! {"{}/{}", registry.trim_end_matches('/'), location}
+    }
+}
+
+// Compares dotted versions number by number (1.10.0 > 1.9.2), a missing number counts as 0
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let numbers = |v: &str| -> Vec<u64> {
+        v.trim_start_matches('v')
+            .split('.')
+            .map(|n| n.trim().parse().unwrap_or(0))
+            .collect()
+    };
+    let (a, b) = (numbers(a), numbers(b));
+    for i in 0..a.len().max(b.len()) {
+        match a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0)) {
+            Ordering::Equal => continue,
+            ordering => return ordering,
+        }
+    }
+    Ordering::Equal
+}
+
+// The newer version a registry has of an installed pack, if any
+pub fn update_for<'a>(installed: &InstalledPack, index: &'a RegistryIndex) -> Option<&'a PackListing> {
+    index
+        .packs
+        .iter()
+        .filter(|listing| listing.name == installed.name)
+        .filter(|listing| compare_versions(&listing.version, &installed.version) == Ordering::Greater)
+        .max_by(|a, b| compare_versions(&a.version, &b.version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> RegistryIndex {
+        serde_json::from_str(
+            r#"{"packs": [
+                {"name": "finance", "version": "1.2.0", "location": "finance-1.2.0.json"},
+                {"name": "finance", "version": "1.10.0", "location": "finance-1.10.0.json"},
+                {"name": "charts", "version": "0.1", "description": "bar & line", "location": "https://example.com/charts.json"}
+            ]}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("1.10.0", "1.9.2"), Ordering::Greater);
+        assert_eq!(compare_versions("v1.2", "1.2.0"), Ordering::Equal);
+        assert_eq!(compare_versions("0.1", "0.1.1"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_update_for() {
+        let mut installed = InstalledPack {
+            name: "finance".to_string(),
+            version: "1.2.0".to_string(),
+            registry: "/packs".to_string(),
+            coords: vec![],
+        };
+        assert_eq!(update_for(&installed, &index()).map(|l| l.version.as_str()), Some("1.10.0"));
+        installed.version = "1.10.0".to_string();
+        assert!(update_for(&installed, &index()).is_none());
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(index_location("/home/me/packs/"), "/home/me/packs/index.json");
+        assert_eq!(resolve("https://example.com/ise", "finance.json"), "https://example.com/ise/finance.json");
+        assert_eq!(resolve("/packs", "https://example.com/charts.json"), "https://example.com/charts.json");
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+use crate::calc::{evaluate_formula, Values, Variables};
+use crate::coordinate::Coordinate;
+use crate::gauge::source_formula;
+use crate::grammar::{Grammar, Interactive, Kind, ReminderTrigger};
+
+// Reminder cells fire a desktop notification with their text when their trigger comes: a local
+// date & time, or a condition (a formula, or a cell it reads from, like the source of gauges)
+// becoming true. A fired reminder doesn't fire again until it's re-armed, which happens when
+// its time is moved back into the future or its condition stops holding.
+
+// how often the app checks whether reminder times have come, in seconds
+pub const REMINDER_INTERVAL_S: u64 = 15;
+
+// Reminder is a reminder cell, as listed in the Reminders panel
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reminder {
+    pub coord: Coordinate,
+    pub message: String,
+    pub trigger: ReminderTrigger,
+    pub fired: bool,
+}
+
+// What a reminder cell says when it fires
+pub fn message_of(coord: &Coordinate, text: &str) -> String {
+    match text.trim() {
+        "" => format! {"Reminder in {}", coord.to_string()},
+        text => text.to_string(),
+    }
+}
+
+// The reminders of a session: the ones due at a time first, soonest first, then the ones
+// without a (valid) time yet and the ones waiting on a condition
+pub fn reminders(grammars: &HashMap<Coordinate, Grammar>) -> Vec<Reminder> {
+    let mut reminders: Vec<Reminder> = grammars
+        .iter()
+        .filter_map(|(coord, g)| match &g.kind {
+            Kind::Interactive(text, Interactive::Reminder(trigger, fired)) => Some(Reminder {
+                coord: coord.clone(),
+                message: message_of(coord, text),
+                trigger: trigger.clone(),
+                fired: *fired,
+            }),
+            _ => None,
+        })
+        .collect();
+    reminders.sort_by(|a, b| {
+        let key = |r: &Reminder| match &r.trigger {
+            ReminderTrigger::At(time) => match parse_time(time) {
+                Some(time) => (0, time),
+                None => (1, String::new()),
+            },
+            ReminderTrigger::When(_) => (2, String::new()),
+        };
+        key(a)
+            .cmp(&key(b))
+            .then_with(|| a.coord.row_cols.cmp(&b.coord.row_cols))
+    });
+    reminders
+}
+
+// A reminder time as `YYYY-MM-DDTHH:MM` (a space may separate the date and time)
+pub fn parse_time(text: &str) -> Option<String> {
+    let time = text.trim().replacen(' ', "T", 1);
+    let digits_at = |range: std::ops::Range<usize>| {
+        time.get(range)
+            .is_some_and(|part| part.chars().all(|c| c.is_ascii_digit()))
+    };
+    let well_formed = time.len() == 16
+        && digits_at(0..4)
+        && &time[4..5] == "-"
+        && digits_at(5..7)
+        && &time[7..8] == "-"
+        && digits_at(8..10)
+        && &time[10..11] == "T"
+        && digits_at(11..13)
+        && &time[13..14] == ":"
+        && digits_at(14..16);
+    if well_formed {
+        Some(time)
+    } else {
+        None
+    }
+}
+
+// Fires the reminders that are due at `now` (local time, `YYYY-MM-DDTHH:MM:SS`), and re-arms the
+// ones that aren't anymore, returning the (cell, message) of the ones that fired. Conditions are
+// only checked with `values` when `conditions` is set, as values are only kept for the current
+// session.
+pub fn check(
+    grammars: &mut HashMap<Coordinate, Grammar>,
+    variables: &Variables,
+    values: &mut Values,
+    now: &str,
+    conditions: bool,
+) -> Vec<(Coordinate, String)> {
+    let mut fired = vec![];
+    for reminder in reminders(grammars) {
+        let due = match &reminder.trigger {
+            ReminderTrigger::At(time) => match parse_time(time) {
+                Some(time) => time.as_str() <= now,
+                None => false,
+            },
+            ReminderTrigger::When(_) if !conditions => continue,
+            ReminderTrigger::When(condition) if condition.trim().is_empty() => false,
+            ReminderTrigger::When(condition) => {
+                evaluate_formula(grammars, variables, values, &source_formula(condition))
+                    .as_bool()
+                    .unwrap_or(false)
+            }
+        };
+        if due == reminder.fired {
+            continue;
+        }
+        if let Some(Grammar {
+            kind: Kind::Interactive(_, Interactive::Reminder(_, was_fired)),
+            ..
+        }) = grammars.get_mut(&reminder.coord)
+        {
+            *was_fired = due;
+        }
+        if due {
+            fired.push((reminder.coord, reminder.message));
+        }
+    }
+    fired
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use std::num::NonZeroU32;
+
+    fn reminder(text: &str, trigger: ReminderTrigger) -> Grammar {
+        Grammar {
+            kind: Kind::Interactive(text.to_string(), Interactive::Reminder(trigger, false)),
+            ..Grammar::default()
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let mut grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => Grammar::input("", "90"),
+            coord!("root-B1") => reminder("over budget", ReminderTrigger::When("=root-A1 > 100".to_string())),
+            coord!("root-A2") => reminder("", ReminderTrigger::At("2026-10-16 09:30".to_string())),
+            coord!("root-B2") => reminder("standup", ReminderTrigger::At("2026-10-16T09:00".to_string())),
+        };
+        assert_eq!(
+            reminders(&grammars)
+                .iter()
+                .map(|r| r.message.clone())
+                .collect::<Vec<String>>(),
+            vec!["standup", "Reminder in root-A2", "over budget"]
+        );
+
+        let mut values = Values::new();
+        assert_eq!(
+            check(&mut grammars, &Variables::new(), &mut values, "2026-10-16T09:15:00", true),
+            vec![(coord!("root-B2"), "standup".to_string())]
+        );
+        // fired reminders don't fire again
+        assert_eq!(
+            check(&mut grammars, &Variables::new(), &mut values, "2026-10-16T09:45:00", true),
+            vec![(coord!("root-A2"), "Reminder in root-A2".to_string())]
+        );
+
+        grammars.insert(coord!("root-A1"), Grammar::input("", "120"));
+        assert_eq!(
+            check(&mut grammars, &Variables::new(), &mut values, "2026-10-16T09:45:00", false),
+            vec![]
+        );
+        assert_eq!(
+            check(&mut grammars, &Variables::new(), &mut values, "2026-10-16T09:45:00", true),
+            vec![(coord!("root-B1"), "over budget".to_string())]
+        );
+        // the condition stops holding, re-arming the reminder
+        grammars.insert(coord!("root-A1"), Grammar::input("", "80"));
+        assert_eq!(
+            check(&mut grammars, &Variables::new(), &mut values, "2026-10-16T09:45:00", true),
+            vec![]
+        );
+        match &grammars[&coord!("root-B1")].kind {
+            Kind::Interactive(_, Interactive::Reminder(_, fired)) => assert!(!fired),
+            kind => panic!("expected a reminder, got {:?}", kind),
+        }
+        assert_eq!(parse_time("tomorrow"), None);
+    }
+}
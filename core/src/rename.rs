@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+
+use crate::coordinate::Coordinate;
+use crate::grammar::{Grammar, Kind};
+
+// Usage is a place where the name of a meta grammar is recorded, which renaming it rewrites.
+// Suggestions are matched by name, so leaving any of these behind would break them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Usage {
+    // the meta grammar itself
+    Suggestion(Coordinate),
+    // a grammar completed from it
+    Instance(Coordinate),
+    // the binding name of a definition
+    Binding(Coordinate),
+    // a rule of a definition, by its index
+    Rule(Coordinate, usize),
+}
+
+impl Usage {
+    pub fn coord(&self) -> &Coordinate {
+        match self {
+            Usage::Suggestion(c) | Usage::Instance(c) | Usage::Binding(c) | Usage::Rule(c, _) => c,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Usage::Suggestion(c) => format! {"{}: the meta grammar", c.to_string()},
+            Usage::Instance(c) => format! {"{}: completed from it", c.to_string()},
+            Usage::Binding(c) => format! {"{}: definition binding", c.to_string()},
+            Usage::Rule(c, i) => format! {"{}: rule {} of a definition", c.to_string(), i + 1},
+        }
+    }
+}
+
+// Where the name of the meta grammar at `suggestion` is used, itself first
+pub fn usages(grammars: &HashMap<Coordinate, Grammar>, suggestion: &Coordinate) -> Vec<Usage> {
+    let name = match grammars.get(suggestion) {
+        Some(grammar) if !grammar.name.is_empty() => grammar.name.clone(),
+        _ => return vec![],
+    };
+    let mut found = vec![];
+    for (coord, grammar) in grammars.iter() {
+        // the meta grammar's own definition binding is renamed along with it
+        if grammar.name == name && coord != suggestion {
+            found.push(Usage::Instance(coord.clone()));
+        }
+        if let Kind::Defn(binding, _, rules) = &grammar.kind {
+            if *binding == name {
+                found.push(Usage::Binding(coord.clone()));
+            }
+            for (i, (rule, _)) in rules.iter().enumerate() {
+                if *rule == name {
+                    found.push(Usage::Rule(coord.clone(), i));
+                }
+            }
+        }
+    }
+    found.sort_by(|a, b| a.coord().row_cols.cmp(&b.coord().row_cols));
+    found.insert(0, Usage::Suggestion(suggestion.clone()));
+    found
+}
+
+// Checks `new_name` can be given to the meta grammar at `suggestion`, which is in `meta_suggestions`
+pub fn check_name(meta_suggestions: &[(String, Coordinate)], suggestion: &Coordinate, new_name: &str) -> Result<(), String> {
+    let new_name = new_name.trim();
+    if new_name.is_empty() {
+        return Err("the name can't be empty".to_string());
+    }
+    if new_name.contains(char::is_whitespace) {
+        return Err("the name can't contain spaces".to_string());
+    }
+    if meta_suggestions.iter().any(|(name, c)| name == new_name && c != suggestion) {
+        return Err(format! {"there's already a meta grammar named {}", new_name});
+    }
+    Ok(())
+}
+
+pub fn apply_rename(grammars: &mut HashMap<Coordinate, Grammar>, usages: &[Usage], new_name: &str) {
+    for usage in usages.iter() {
+        let grammar = match grammars.get_mut(usage.coord()) {
+            Some(grammar) => grammar,
+            None => continue,
+        };
+        match usage {
+            Usage::Suggestion(_) | Usage::Instance(_) => grammar.name = new_name.to_string(),
+            Usage::Binding(_) => {
+                if let Kind::Defn(binding, _, _) = &mut grammar.kind {
+                    *binding = new_name.to_string();
+                }
+            }
+            Usage::Rule(_, i) => {
+                if let Kind::Defn(_, _, rules) = &mut grammar.kind {
+                    if let Some(rule) = rules.get_mut(*i) {
+                        rule.0 = new_name.to_string();
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+
+    #[test]
+    fn test_rename() {
+        let mut grammars = hashmap! {
+            coord!("meta-A3") => Grammar::text("point", ""),
+            coord!("meta-A4") => Grammar {
+                kind: Kind::Defn("shape".to_string(), coord!("meta-A4"), vec![("point".to_string(), coord!("meta-A4-A1"))]),
+                ..Grammar::text("shape", "")
+            },
+            coord!("root-A1") => Grammar::text("point", "1, 2"),
+            coord!("root-A2") => Grammar::text("", "point"),
+        };
+        let found = usages(&grammars, &coord!("meta-A3"));
+        assert_eq!(
+            found,
+            vec![
+                Usage::Suggestion(coord!("meta-A3")),
+                // root sorts before meta
+                Usage::Instance(coord!("root-A1")),
+                Usage::Rule(coord!("meta-A4"), 0),
+            ]
+        );
+        let suggestions = vec![("point".to_string(), coord!("meta-A3")), ("shape".to_string(), coord!("meta-A4"))];
+        assert!(check_name(&suggestions, &coord!("meta-A3"), "shape").is_err());
+        assert!(check_name(&suggestions, &coord!("meta-A3"), "my point").is_err());
+        assert!(check_name(&suggestions, &coord!("meta-A3"), "vertex").is_ok());
+
+        apply_rename(&mut grammars, &found, "vertex");
+        assert_eq!(grammars[&coord!("meta-A3")].name, "vertex");
+        assert_eq!(grammars[&coord!("root-A1")].name, "vertex");
+        assert_eq!(grammars[&coord!("root-A2")].name, "");
+        match &grammars[&coord!("meta-A4")].kind {
+            Kind::Defn(binding, _, rules) => {
+                assert_eq!(binding, "shape");
+                assert_eq!(rules[0].0, "vertex");
+            }
+            _ => unreachable!(),
+        }
+        assert!(usages(&grammars, &coord!("root-A2")).is_empty());
+    }
+}
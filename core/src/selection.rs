@@ -0,0 +1,246 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::coordinate::Coordinate;
+use crate::export::Region;
+use crate::grammar::Grammar;
+
+// Selection is the set of selected cells: one or more rectangular ranges, each within a grid. A
+// range is spanned from the cell it was started at (its anchor) to the cell it's extended to by
+// Shift+clicking or dragging, and Ctrl+clicking starts another range, keeping the ones before it.
+// The last range is the primary one, which actions that take a single range apply to.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Selection {
+    ranges: Vec<Region>,
+    anchor: Option<Coordinate>,
+    // whether the last range is the one spanned from `anchor`, which extending it replaces
+    extending: bool,
+}
+
+impl Selection {
+    // Starts a new selection at `coord`, dropping the ranges selected so far
+    pub fn start(&mut self, grammars: &HashMap<Coordinate, Grammar>, coord: &Coordinate) {
+        self.ranges.clear();
+        self.add(grammars, coord);
+    }
+
+    // Starts another range at `coord`, keeping the ranges selected so far. The range covers just
+    // `coord` (or the merged cell it's part of) until it's extended.
+    pub fn add(&mut self, grammars: &HashMap<Coordinate, Grammar>, coord: &Coordinate) {
+        self.anchor = Some(coord.clone());
+        self.extending = false;
+        if let Some(range) = normalize(grammars, coord, coord) {
+            self.ranges.push(range);
+            self.extending = true;
+        }
+    }
+
+    // Spans the current range from its anchor to `coord` (see `normalize`). Returns false if no
+    // range was started, or the two cells aren't in a grid together.
+    pub fn extend(&mut self, grammars: &HashMap<Coordinate, Grammar>, coord: &Coordinate) -> bool {
+        let range = match self
+            .anchor
+            .as_ref()
+            .and_then(|anchor| normalize(grammars, anchor, coord))
+        {
+            Some(range) => range,
+            None => return false,
+        };
+        if self.extending {
+            self.ranges.pop();
+        }
+        self.ranges.push(range);
+        self.extending = true;
+        true
+    }
+
+    pub fn clear(&mut self) {
+        *self = Selection::default();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn ranges(&self) -> &[Region] {
+        &self.ranges
+    }
+
+    pub fn primary(&self) -> Option<&Region> {
+        self.ranges.last()
+    }
+
+    // The top-leftmost and bottom-rightmost cells of the primary range
+    pub fn corners(&self) -> Option<(Coordinate, Coordinate)> {
+        self.primary()
+            .map(|r| (r.cell(r.rows.0, r.cols.0), r.cell(r.rows.1, r.cols.1)))
+    }
+
+    // Every selected cell once, range by range and row by row within a range
+    pub fn coords(&self) -> impl Iterator<Item = Coordinate> + '_ {
+        let mut seen = HashSet::new();
+        self.ranges
+            .iter()
+            .flat_map(|r| {
+                (r.rows.0..=r.rows.1).flat_map(move |row| (r.cols.0..=r.cols.1).map(move |col| r.cell(row, col)))
+            })
+            .filter(move |coord| seen.insert(coord.clone()))
+    }
+
+    // Whether `coord`, or the cell it's nested in, is selected
+    pub fn contains(&self, coord: &Coordinate) -> bool {
+        self.ranges.iter().any(|r| {
+            let depth = r.parent.row_cols.len() + 1;
+            if coord.row_cols.len() < depth {
+                return false;
+            }
+            let cell = coord.truncate(depth).unwrap_or_else(|| coord.clone());
+            let (row, col) = cell.row_col();
+            cell.parent().as_ref() == Some(&r.parent)
+                && (r.rows.0..=r.rows.1).contains(&row.get())
+                && (r.cols.0..=r.cols.1).contains(&col.get())
+        })
+    }
+}
+
+// The rectangular range between two cells, in the innermost grid they're both in: a cell nested
+// deeper than that is taken by the cell of the grid it's nested in. The range is then grown until
+// it covers whole merged and spanning cells, so it never cuts through one.
+pub fn normalize(grammars: &HashMap<Coordinate, Grammar>, a: &Coordinate, b: &Coordinate) -> Option<Region> {
+    let (mut a, mut b) = (a.clone(), b.clone());
+    while a.row_cols.len() > b.row_cols.len() {
+        a = a.parent()?;
+    }
+    while b.row_cols.len() > a.row_cols.len() {
+        b = b.parent()?;
+    }
+    while a.parent() != b.parent() {
+        a = a.parent()?;
+        b = b.parent()?;
+    }
+    let mut region = Region::from_selection(&a, &b)?;
+    loop {
+        let mut grown = region.clone();
+        let mut cover = |(rows, cols): ((u32, u32), (u32, u32))| {
+            grown.rows = (grown.rows.0.min(rows.0), grown.rows.1.max(rows.1));
+            grown.cols = (grown.cols.0.min(cols.0), grown.cols.1.max(cols.1));
+        };
+        for row in region.rows.0..=region.rows.1 {
+            for col in region.cols.0..=region.cols.1 {
+                let style = match grammars.get(&region.cell(row, col)) {
+                    Some(g) => &g.style,
+                    None => continue,
+                };
+                // a span of (0, 0) is a cell that isn't merged
+                if style.row_span.0 != 0 && style.col_span.0 != 0 {
+                    cover((style.row_span, style.col_span));
+                }
+            }
+        }
+        let spans = grammars.get(&region.parent).map_or(&[][..], |g| &g.style.spans[..]);
+        for ((row, col), (rows, cols)) in spans.iter() {
+            let last = (row.get() + rows.get() - 1, col.get() + cols.get() - 1);
+            if row.get() <= region.rows.1
+                && region.rows.0 <= last.0
+                && col.get() <= region.cols.1
+                && region.cols.0 <= last.1
+            {
+                cover(((row.get(), last.0), (col.get(), last.1)));
+            }
+        }
+        if grown == region {
+            return Some(region);
+        }
+        region = grown;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use crate::util::non_zero_u32_tuple;
+    use std::num::NonZeroU32;
+
+    fn grid(rows: u32, cols: u32) -> Grammar {
+        Grammar::as_grid(NonZeroU32::new(rows).unwrap(), NonZeroU32::new(cols).unwrap())
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut grammars = hashmap! {
+            coord!("root") => grid(4, 4),
+            coord!("root-B2") => grid(2, 2),
+        };
+        // dragging up and to the left gives the same range as down and to the right
+        let range = normalize(&grammars, &coord!("root-C3"), &coord!("root-A2")).unwrap();
+        assert_eq!(
+            (range.parent.clone(), range.rows, range.cols),
+            (coord!("root"), (2, 3), (1, 3))
+        );
+        // a nested cell is taken by the cell of the grid it's nested in
+        let range = normalize(&grammars, &coord!("root-B2-A1"), &coord!("root-D4")).unwrap();
+        assert_eq!((range.rows, range.cols), ((2, 4), (2, 4)));
+        assert_eq!(normalize(&grammars, &coord!("root"), &coord!("root")), None);
+
+        // ranges are grown over merged cells, and cells spanning others
+        let mut merged = Grammar::input("", "");
+        merged.style.row_span = (3, 4);
+        merged.style.col_span = (1, 1);
+        grammars.insert(coord!("root-A3"), merged);
+        grammars
+            .get_mut(&coord!("root"))
+            .unwrap()
+            .style
+            .spans
+            .push((non_zero_u32_tuple((1, 3)), non_zero_u32_tuple((1, 2))));
+        let range = normalize(&grammars, &coord!("root-A3"), &coord!("root-C3")).unwrap();
+        assert_eq!((range.rows, range.cols), ((3, 4), (1, 3)));
+        let range = normalize(&grammars, &coord!("root-A2"), &coord!("root-A3")).unwrap();
+        assert_eq!((range.rows, range.cols), ((2, 4), (1, 1)));
+        let range = normalize(&grammars, &coord!("root-A1"), &coord!("root-C1")).unwrap();
+        assert_eq!((range.rows, range.cols), ((1, 1), (1, 4)));
+    }
+
+    #[test]
+    fn test_selection() {
+        let grammars = hashmap! { coord!("root") => grid(4, 4) };
+        let mut selection = Selection::default();
+        selection.start(&grammars, &coord!("root-A1"));
+        assert_eq!(selection.corners(), Some((coord!("root-A1"), coord!("root-A1"))));
+        assert!(selection.extend(&grammars, &coord!("root-B1")));
+        // extending again replaces the range
+        assert!(selection.extend(&grammars, &coord!("root-B2")));
+        assert_eq!(selection.corners(), Some((coord!("root-A1"), coord!("root-B2"))));
+
+        // another range is added, overlapping the first one
+        selection.add(&grammars, &coord!("root-B2"));
+        assert_eq!(selection.ranges().len(), 2);
+        assert!(selection.extend(&grammars, &coord!("root-C2")));
+        assert_eq!(selection.ranges().len(), 2);
+        assert_eq!(selection.corners(), Some((coord!("root-B2"), coord!("root-C2"))));
+        assert_eq!(
+            selection.coords().collect::<Vec<_>>(),
+            vec![
+                coord!("root-A1"),
+                coord!("root-B1"),
+                coord!("root-A2"),
+                coord!("root-B2"),
+                coord!("root-C2")
+            ]
+        );
+        assert!(selection.contains(&coord!("root-C2")));
+        assert!(selection.contains(&coord!("root-B1-A1")));
+        assert!(!selection.contains(&coord!("root-C1")));
+        assert!(!selection.contains(&coord!("root")));
+
+        selection.start(&grammars, &coord!("root-D4"));
+        assert_eq!(selection.ranges().len(), 1);
+        assert!(!selection.extend(&grammars, &coord!("root")));
+        // the range started at a cell is kept when extending it fails
+        assert_eq!(selection.corners(), Some((coord!("root-D4"), coord!("root-D4"))));
+        selection.start(&grammars, &coord!("root"));
+        assert!(selection.is_empty());
+        selection.clear();
+        assert!(!selection.extend(&grammars, &coord!("root-A1")));
+    }
+}
@@ -0,0 +1,521 @@
+use serde::{
+    de::Error,
+    ser::{SerializeStruct, SerializeTupleVariant, Serializer},
+    Deserialize, Deserializer, Serialize,
+};
+use std::collections::HashMap;
+use std::option::Option;
+
+use crate::assets::Asset;
+use crate::calc::IterationSettings;
+use crate::coordinate::{Col, Coordinate, Row};
+use crate::external::ExternalSource;
+use crate::grammar::{Grammar, Interactive, Kind};
+use crate::macros::Macro;
+use crate::outline::OutlineGroup;
+use crate::packages::InstalledPack;
+use crate::style::Style;
+use crate::variables::Variable;
+
+// The version of the session file format this app writes. Files are stamped with it as
+// `version`, and older ones are brought up to it by `MIGRATIONS` as they're read.
+pub const SESSION_VERSION: u32 = 2;
+
+// Session encapsulates the serializable state of the application that gets stored to disk
+// in a .ise file (which is just a JSON file)
+#[derive(Deserialize, Debug, Clone)]
+pub struct Session {
+    pub title: String,
+    pub root: Grammar,
+    pub meta: Grammar,
+    pub grammars: HashMap<Coordinate, Grammar>,
+    #[serde(default)]
+    pub outline_groups: Vec<OutlineGroup>,
+    #[serde(default)]
+    pub sheets: Vec<Sheet>,
+    #[serde(default)]
+    pub metadata: SessionMetadata,
+    #[serde(default)]
+    pub bookmarks: Vec<Bookmark>,
+    #[serde(default)]
+    pub iteration: IterationSettings,
+    #[serde(default)]
+    pub external_sources: Vec<ExternalSource>,
+    #[serde(default)]
+    pub assets: Vec<Asset>,
+    #[serde(default)]
+    pub packages: Vec<InstalledPack>,
+    #[serde(default)]
+    pub macros: Vec<Macro>,
+    // whether the reminder cells of the session are kept from firing
+    #[serde(default)]
+    pub mute_reminders: bool,
+    #[serde(default)]
+    pub variables: Vec<Variable>,
+    // only filled in as the session is saved, and taken out again as it's loaded: the layout
+    // the app is showing lives in the model
+    #[serde(default)]
+    pub layout: SessionLayout,
+    // not saved into the session file, see `Workspace`
+    #[serde(skip)]
+    pub view: TabView,
+}
+#[cfg(feature = "stdweb")]
+js_serializable!(Session);
+#[cfg(feature = "stdweb")]
+js_deserializable!(Session);
+
+// TabView is the state of a tab that isn't part of its session file: where the session was
+// last saved to or loaded from, and how it was being viewed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct TabView {
+    pub file_path: Option<String>,
+    // scrollLeft & scrollTop of the main area
+    pub scroll: (f64, f64),
+    pub zoom: f32,
+    // whether the session has changes that haven't been saved
+    pub dirty: bool,
+    // recent entries of the go-to box and search panel in this tab, newest first
+    pub goto_history: Vec<String>,
+    pub search_history: Vec<String>,
+    // shell commands the user allowed to run in this tab, asked again after a restart
+    #[serde(skip)]
+    pub allowed_commands: Vec<String>,
+    // likewise, the sources of macro scripts the user allowed to run in this tab
+    #[serde(skip)]
+    pub allowed_scripts: Vec<String>,
+}
+
+impl Default for TabView {
+    fn default() -> Self {
+        TabView {
+            file_path: None,
+            scroll: (0.0, 0.0),
+            zoom: 1.0,
+            dirty: false,
+            goto_history: vec![],
+            search_history: vec![],
+            allowed_commands: vec![],
+            allowed_scripts: vec![],
+        }
+    }
+}
+
+// SessionLayout is how a session was laid out when it was saved: the sizes of its rows and
+// columns, how far it was zoomed and which cell was active
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct SessionLayout {
+    pub col_widths: Vec<(Col, f64)>,
+    pub row_heights: Vec<(Row, f64)>,
+    pub zoom: f32,
+    pub active_cell: Option<Coordinate>,
+}
+
+impl Default for SessionLayout {
+    fn default() -> Self {
+        SessionLayout {
+            col_widths: vec![],
+            row_heights: vec![],
+            zoom: 1.0,
+            active_cell: None,
+        }
+    }
+}
+
+type Migration = fn(&mut serde_json::Map<String, serde_json::Value>);
+
+// `MIGRATIONS[n]` upgrades a session file of version n + 1 to version n + 2, so a new version of
+// the format comes with the migration of the files of the one before. Files from before versions
+// were written have none, and are version 1.
+const MIGRATIONS: [Migration; 1] = [
+    // version 1 files have no layout, so they're laid out with the default sizes like they were
+    |session| {
+        session.insert("layout".to_string(), serde_json::json!({}));
+    },
+];
+
+// Brings the content of a session file of any version up to `SESSION_VERSION`
+pub fn migrate(session: &mut serde_json::Value) -> Result<(), String> {
+    let fields = session
+        .as_object_mut()
+        .ok_or_else(|| "not a session file: it isn't a JSON object".to_string())?;
+    let version = match fields.get("version") {
+        None => 1,
+        Some(version) => match version.as_u64() {
+            Some(version) if version >= 1 => version as u32,
+            _ => return Err(format! {"not a session file: {} isn't a version", version}),
+        },
+    };
+    if version > SESSION_VERSION {
+        return Err(format! {
+            "the session is version {} of the format, saved by a newer version of the app (this one reads up to {})",
+            version, SESSION_VERSION
+        });
+    }
+    for migration in MIGRATIONS.iter().skip(version as usize - 1) {
+        migration(fields);
+    }
+    fields.insert("version".to_string(), SESSION_VERSION.into());
+    Ok(())
+}
+
+// Reads the content of a session file, migrating it from older versions of the format and
+// checking it has a root grid to show
+pub fn parse_session(content: &str) -> Result<Session, String> {
+    let mut value: serde_json::Value =
+        serde_json::from_str(content).map_err(|e| format! {"not a session file: {}", e})?;
+    migrate(&mut value)?;
+    let session: Session = serde_json::from_value(value).map_err(|e| format! {"not a session file: {}", e})?;
+    match session.root.kind {
+        Kind::Grid(_) => Ok(session),
+        _ => Err("the session has no root grid".to_string()),
+    }
+}
+
+// Sheet is an extra named top-level grid of a session besides `root` and `meta`,
+// so one session file can hold several related sheets like a workbook.
+// Sheet coordinates look like `sheet1`, `sheet2`, ... whereas `name` is what's shown to users.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Sheet {
+    pub name: String,
+    pub coord: Coordinate,
+}
+
+// Bookmark is a named viewport that can be recalled from the bookmarks panel
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Bookmark {
+    pub name: String,
+    pub view_root: Coordinate,
+    // scrollLeft & scrollTop of the main area
+    pub scroll: (f64, f64),
+    pub zoom: f32,
+    // first & last cell of a range to highlight when the bookmark is recalled
+    pub range: Option<(Coordinate, Coordinate)>,
+}
+
+// SessionMetadata describes a session file. Timestamps are ISO 8601 strings, `created` is set
+// when the session is first created and `modified` every time it's saved.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct SessionMetadata {
+    pub author: String,
+    pub description: String,
+    pub created: String,
+    pub modified: String,
+}
+
+// Session Custom Serialization
+impl Serialize for Session {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("Session", 17)?;
+        state.serialize_field("version", &SESSION_VERSION)?;
+        state.serialize_field("title", &self.title)?;
+        state.serialize_field("root", &self.root)?;
+        state.serialize_field("meta", &self.meta)?;
+        state.serialize_field("grammars", &self.grammars)?;
+        state.serialize_field("outline_groups", &self.outline_groups)?;
+        state.serialize_field("sheets", &self.sheets)?;
+        state.serialize_field("metadata", &self.metadata)?;
+        state.serialize_field("bookmarks", &self.bookmarks)?;
+        state.serialize_field("iteration", &self.iteration)?;
+        state.serialize_field("external_sources", &self.external_sources)?;
+        state.serialize_field("assets", &self.assets)?;
+        state.serialize_field("packages", &self.packages)?;
+        state.serialize_field("macros", &self.macros)?;
+        state.serialize_field("mute_reminders", &self.mute_reminders)?;
+        state.serialize_field("variables", &self.variables)?;
+        state.serialize_field("layout", &self.layout)?;
+        state.end()
+    }
+}
+
+// Coordinate Custom Deserialization
+impl<'de> Deserialize<'de> for Coordinate {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Coordinate::parse(&s).ok_or_else(|| D::Error::custom(format! {"invalid coordinate {}", s}))
+    }
+}
+
+// Style Custom Serialization
+impl Serialize for Style {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("Style", 19)?;
+        state.serialize_field("width", &self.width)?;
+        state.serialize_field("height", &self.height)?;
+        state.serialize_field("border_color", &self.border_color)?;
+        state.serialize_field("border_collapse", &self.border_collapse)?;
+        state.serialize_field("font_weight", &self.font_weight)?;
+        state.serialize_field("font_color", &self.font_color)?;
+        state.serialize_field("col_span", &self.col_span)?;
+        state.serialize_field("row_span", &self.row_span)?;
+        state.serialize_field("display", &self.display)?;
+        state.serialize_field("spans", &self.spans)?;
+        state.serialize_field("auto_expand", &self.auto_expand)?;
+        state.serialize_field("category", &self.category)?;
+        state.serialize_field("placeholder", &self.placeholder)?;
+        state.serialize_field("no_spellcheck", &self.no_spellcheck)?;
+        state.serialize_field("shape", &self.shape)?;
+        state.serialize_field("nested_defaults", &self.nested_defaults)?;
+        state.serialize_field("visible_when", &self.visible_when)?;
+        state.serialize_field("bindings", &self.bindings)?;
+        state.serialize_field("on_click", &self.on_click)?;
+        state.end()
+    }
+}
+
+// Grammar Custom Serialization
+impl Serialize for Grammar {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("Grammar", 3)?;
+        state.serialize_field("name", &self.name)?;
+        state.serialize_field("style", &self.style)?;
+        state.serialize_field("kind", &self.kind)?;
+        state.end()
+    }
+}
+
+// Interactive Custom Serialization
+impl Serialize for Interactive {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match &self {
+            Interactive::Button() => {
+                let sv = serializer.serialize_tuple_variant("Interactive", 0, "Button", 0)?;
+                sv.end()
+            }
+            Interactive::Slider(val, min, max) => {
+                let mut sv = serializer.serialize_tuple_variant("Interactive", 1, "Slider", 3)?;
+                sv.serialize_field(val)?;
+                sv.serialize_field(min)?;
+                sv.serialize_field(max)?;
+                sv.end()
+            }
+            Interactive::Toggle(b) => {
+                let mut sv = serializer.serialize_struct("Interactive", 1)?;
+                sv.serialize_field("Toggle", b)?;
+                sv.end()
+            }
+            Interactive::Shell(command, output) => {
+                let mut sv = serializer.serialize_tuple_variant("Interactive", 3, "Shell", 2)?;
+                sv.serialize_field(command)?;
+                sv.serialize_field(output)?;
+                sv.end()
+            }
+            Interactive::Gauge(source, min, max) => {
+                let mut sv = serializer.serialize_tuple_variant("Interactive", 5, "Gauge", 3)?;
+                sv.serialize_field(source)?;
+                sv.serialize_field(min)?;
+                sv.serialize_field(max)?;
+                sv.end()
+            }
+            Interactive::Rating(stars, max) => {
+                let mut sv = serializer.serialize_tuple_variant("Interactive", 6, "Rating", 2)?;
+                sv.serialize_field(stars)?;
+                sv.serialize_field(max)?;
+                sv.end()
+            }
+            Interactive::Stepper(value, step, min, max) => {
+                let mut sv = serializer.serialize_tuple_variant("Interactive", 7, "Stepper", 4)?;
+                sv.serialize_field(value)?;
+                sv.serialize_field(step)?;
+                sv.serialize_field(min)?;
+                sv.serialize_field(max)?;
+                sv.end()
+            }
+            Interactive::Timer(elapsed) => {
+                let mut sv = serializer.serialize_struct("Interactive", 1)?;
+                sv.serialize_field("Timer", elapsed)?;
+                sv.end()
+            }
+            Interactive::Reminder(trigger, fired) => {
+                let mut sv = serializer.serialize_tuple_variant("Interactive", 9, "Reminder", 2)?;
+                sv.serialize_field(trigger)?;
+                sv.serialize_field(fired)?;
+                sv.end()
+            }
+            Interactive::ColorPicker(color) => {
+                let mut sv = serializer.serialize_struct("Interactive", 1)?;
+                sv.serialize_field("ColorPicker", color)?;
+                sv.end()
+            }
+        }
+    }
+}
+
+// kind Custom Serialization
+impl Serialize for Kind {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match &self {
+            Kind::Text(s) => {
+                let mut sv = serializer.serialize_struct("kind", 1)?;
+                sv.serialize_field("Text", s)?;
+                sv.end()
+            }
+            Kind::Input(s) => {
+                let mut sv = serializer.serialize_struct("kind", 1)?;
+                sv.serialize_field("Input", s)?;
+                sv.end()
+            }
+            Kind::Interactive(s, x) => {
+                let mut sv = serializer.serialize_tuple_variant("kind", 0, "Interactive", 2)?;
+                sv.serialize_field(s)?;
+                sv.serialize_field(x)?;
+                sv.end()
+            }
+            Kind::Grid(v) => {
+                let mut sv = serializer.serialize_struct("kind", 1)?;
+                sv.serialize_field("Grid", v)?;
+                sv.end()
+            }
+            Kind::Lookup(s, x) => {
+                let mut sv = serializer.serialize_tuple_variant("Kind", 0, "Lookup", 2)?;
+                sv.serialize_field(s)?;
+                sv.serialize_field(x)?;
+                sv.end()
+            }
+            Kind::Defn(s, c, rules) => {
+                let mut sv = serializer.serialize_tuple_variant("Kind", 0, "Defn", 3)?;
+                sv.serialize_field(s)?;
+                sv.serialize_field(c)?;
+                sv.serialize_field(rules)?;
+                sv.end()
+            }
+            Kind::Editor(s) => serializer.serialize_newtype_variant("Kind", 0, "Editor", s),
+            Kind::Transclude(path, c) => {
+                let mut sv = serializer.serialize_tuple_variant("Kind", 0, "Transclude", 2)?;
+                sv.serialize_field(path)?;
+                sv.serialize_field(c)?;
+                sv.end()
+            }
+        }
+    }
+}
+
+// Coordinate Custom Serialization
+impl Serialize for Coordinate {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use crate::coord_col;
+    use crate::grammar::Lookup;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn test_session_round_trip() {
+        let mut session = Session {
+            title: "round trip".to_string(),
+            root: Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(1).unwrap()),
+            meta: Grammar::default(),
+            grammars: hashmap! {
+                coord!("root") => Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(4).unwrap()),
+                coord!("root-A1") => Grammar::input("", "apples"),
+                coord!("root-B1") => Grammar {
+                    kind: Kind::Lookup("A1".to_string(), Some(Lookup::Cell(coord!("root-A1")))),
+                    ..Grammar::default()
+                },
+                coord!("root-C1") => Grammar {
+                    kind: Kind::Defn(
+                        "fruit".to_string(),
+                        coord!("root-C1"),
+                        vec![("name".to_string(), coord!("root-A1"))],
+                    ),
+                    ..Grammar::default()
+                },
+                coord!("root-D1") => Grammar {
+                    kind: Kind::Editor("notes".to_string()),
+                    ..Grammar::default()
+                },
+            },
+            outline_groups: vec![],
+            sheets: vec![],
+            metadata: SessionMetadata::default(),
+            bookmarks: vec![],
+            iteration: IterationSettings::default(),
+            external_sources: vec![],
+            assets: vec![],
+            packages: vec![],
+            macros: vec![],
+            mute_reminders: true,
+            variables: vec![],
+            layout: SessionLayout {
+                col_widths: vec![(coord_col!("root", "A"), 120.0)],
+                row_heights: vec![(Row(coord!("root"), NonZeroU32::new(1).unwrap()), 40.0)],
+                zoom: 1.5,
+                active_cell: Some(coord!("root-A1")),
+            },
+            view: TabView::default(),
+        };
+        session.view.dirty = true;
+        let content = serde_json::to_string(&session).unwrap();
+        assert!(content.starts_with(&format! {"{{\"version\":{},", SESSION_VERSION}));
+        let loaded = parse_session(&content).unwrap();
+        assert_eq!(loaded.title, session.title);
+        for (coord, grammar) in session.grammars.iter() {
+            assert_eq!(loaded.grammars[coord].kind, grammar.kind);
+        }
+        assert!(loaded.mute_reminders);
+        assert_eq!(loaded.layout, session.layout);
+        // how the tab was viewed isn't part of the file
+        assert_eq!(loaded.view, TabView::default());
+        assert_eq!(serde_json::to_string(&loaded).unwrap().len(), content.len());
+    }
+
+    #[test]
+    fn test_migrate() {
+        // files from before versions were written are version 1, and have no layout
+        let mut old = serde_json::json!({ "title": "old" });
+        migrate(&mut old).unwrap();
+        assert_eq!(old["version"], SESSION_VERSION);
+        assert_eq!(old["layout"], serde_json::json!({}));
+        let layout: SessionLayout = serde_json::from_value(old["layout"].clone()).unwrap();
+        assert_eq!(layout, SessionLayout::default());
+
+        let mut current = serde_json::json!({ "version": SESSION_VERSION, "layout": { "zoom": 2.0 } });
+        migrate(&mut current).unwrap();
+        assert_eq!(current["layout"]["zoom"], 2.0);
+
+        let mut newer = serde_json::json!({ "version": SESSION_VERSION + 1 });
+        assert!(migrate(&mut newer).unwrap_err().contains("newer version"));
+        assert!(migrate(&mut serde_json::json!({ "version": "two" })).is_err());
+        assert!(migrate(&mut serde_json::json!([])).is_err());
+        assert!(parse_session("{\"version\": 1, \"title\": \"no root\"}").is_err());
+    }
+
+    #[test]
+    fn test_coordinate_deserialize() {
+        let coord: Coordinate = serde_json::from_str("\"root-A1-B2\"").unwrap();
+        assert_eq!(coord, coord!("root-A1-B2"));
+        // malformed coordinates are errors rather than panics
+        let error = serde_json::from_str::<Coordinate>("\"root-A1x\"").unwrap_err();
+        assert!(error.to_string().contains("invalid coordinate root-A1x"));
+    }
+}
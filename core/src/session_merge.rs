@@ -0,0 +1,404 @@
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+
+use crate::coordinate::Coordinate;
+use crate::export::cell_text;
+use crate::fragment::{extract, graft};
+use crate::grammar::{Grammar, Kind};
+use crate::session::Session;
+
+// Merging two copies of a session that were edited apart: "mine", the one that's open, and
+// "theirs", the file merged into it. They're merged coordinate by coordinate: cells only one of
+// them has are kept, grids both have are merged cell by cell (growing to fit the rows and columns
+// of either), and cells both have with different contents are conflicts, each resolved by a
+// `MergeChoice`.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MergeChoice {
+    Mine,
+    Theirs,
+    // mine stays where it is, and their version of its row is added below the rows of its grid
+    Both,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeConflict {
+    pub coord: Coordinate,
+    // the text of the cell in either session, like it's exported (see `cell_text`)
+    pub mine: String,
+    pub theirs: String,
+}
+
+// SessionMerge is the state of the merge dialog: the session merged in, its conflicts with the
+// current one, and how each of them is resolved (keeping mine unless told otherwise)
+#[derive(Debug, Clone)]
+pub struct SessionMerge {
+    pub path: String,
+    pub theirs: Session,
+    pub conflicts: Vec<MergeConflict>,
+    pub choices: HashMap<Coordinate, MergeChoice>,
+}
+
+impl SessionMerge {
+    pub fn new(mine: &Session, path: String, theirs: Session) -> SessionMerge {
+        SessionMerge {
+            path,
+            conflicts: merge_conflicts(&mine.grammars, &theirs.grammars),
+            theirs,
+            choices: HashMap::new(),
+        }
+    }
+
+    pub fn choice(&self, coord: &Coordinate) -> MergeChoice {
+        self.choices.get(coord).cloned().unwrap_or(MergeChoice::Mine)
+    }
+
+    // Resolves every conflict the same way
+    pub fn choose_all(&mut self, choice: MergeChoice) {
+        self.choices = self.conflicts.iter().map(|c| (c.coord.clone(), choice)).collect();
+    }
+
+    // The merged session, which is mine with the grammars of both
+    pub fn merged(&self, mine: &Session) -> Session {
+        Session {
+            title: format! {"{} (merged)", mine.title},
+            grammars: merge_grammars(&mine.grammars, &self.theirs.grammars, &self.choices),
+            ..mine.clone()
+        }
+    }
+}
+
+// The cells both grammar maps have with different contents, in coordinate order. The cells of
+// grids both have are compared one by one, the cells nested in a conflicting cell aren't.
+pub fn merge_conflicts(
+    mine: &HashMap<Coordinate, Grammar>,
+    theirs: &HashMap<Coordinate, Grammar>,
+) -> Vec<MergeConflict> {
+    let mut conflicts = vec![];
+    for coord in top_level(mine, theirs) {
+        collect_conflicts(mine, theirs, &coord, &mut conflicts);
+    }
+    conflicts
+}
+
+fn collect_conflicts(
+    mine: &HashMap<Coordinate, Grammar>,
+    theirs: &HashMap<Coordinate, Grammar>,
+    coord: &Coordinate,
+    conflicts: &mut Vec<MergeConflict>,
+) {
+    if let (Some(m), Some(t)) = (mine.get(coord), theirs.get(coord)) {
+        match (&m.kind, &t.kind) {
+            (Kind::Grid(_), Kind::Grid(_)) => {
+                for cell in grid_cells(mine, theirs, coord) {
+                    collect_conflicts(mine, theirs, &cell, conflicts);
+                }
+            }
+            _ if same_contents(m, t) => {}
+            _ => conflicts.push(MergeConflict {
+                coord: coord.clone(),
+                mine: cell_text(mine, coord),
+                theirs: cell_text(theirs, coord),
+            }),
+        }
+    }
+}
+
+// Merges their grammars into mine, resolving conflicts by `choices` (keeping mine for the ones
+// left out)
+pub fn merge_grammars(
+    mine: &HashMap<Coordinate, Grammar>,
+    theirs: &HashMap<Coordinate, Grammar>,
+    choices: &HashMap<Coordinate, MergeChoice>,
+) -> HashMap<Coordinate, Grammar> {
+    let mut merged = mine.clone();
+    for coord in top_level(mine, theirs) {
+        merge_cell(mine, theirs, choices, &coord, &mut merged);
+    }
+    merged
+}
+
+// Merges the cell at `coord`, returning whether their version of its row is to be added to its
+// grid (see `MergeChoice::Both`)
+fn merge_cell(
+    mine: &HashMap<Coordinate, Grammar>,
+    theirs: &HashMap<Coordinate, Grammar>,
+    choices: &HashMap<Coordinate, MergeChoice>,
+    coord: &Coordinate,
+    merged: &mut HashMap<Coordinate, Grammar>,
+) -> bool {
+    match (mine.get(coord), theirs.get(coord)) {
+        (Some(m), Some(t)) => match (&m.kind, &t.kind) {
+            (Kind::Grid(_), Kind::Grid(_)) => {
+                merge_grid(mine, theirs, choices, coord, merged);
+                false
+            }
+            _ if same_contents(m, t) => false,
+            _ => match choices.get(coord) {
+                Some(MergeChoice::Theirs) => {
+                    take_theirs(theirs, coord, merged, coord);
+                    false
+                }
+                // a top-level cell has no grid to add a row to, so it's kept
+                Some(MergeChoice::Both) => coord.parent().is_some(),
+                _ => false,
+            },
+        },
+        (None, Some(_)) => {
+            take_theirs(theirs, coord, merged, coord);
+            false
+        }
+        _ => false,
+    }
+}
+
+fn merge_grid(
+    mine: &HashMap<Coordinate, Grammar>,
+    theirs: &HashMap<Coordinate, Grammar>,
+    choices: &HashMap<Coordinate, MergeChoice>,
+    grid: &Coordinate,
+    merged: &mut HashMap<Coordinate, Grammar>,
+) {
+    // mine keeps the cells it has (and leaves out, under its merged cells), and gets the ones that
+    // are new: the cells past its edge that theirs doesn't leave out, and the rows added for theirs
+    let my_coords = sub_coords(mine.get(grid));
+    let mut coords = my_coords.clone();
+    let my_size = size(mine.get(grid));
+    let their_size = size(theirs.get(grid));
+    let their_coords = sub_coords(theirs.get(grid));
+    let theirs_has =
+        |row: u32, col: u32| row > their_size.0 || col > their_size.1 || their_coords.contains(&(row, col));
+
+    let mut their_rows: Vec<u32> = vec![];
+    for cell in grid_cells(mine, theirs, grid) {
+        let (row, col) = cell.row_col();
+        let (row, col) = (row.get(), col.get());
+        if row <= my_size.0 && col <= my_size.1 {
+            if !my_coords.contains(&(row, col)) {
+                continue;
+            }
+        } else if theirs_has(row, col) {
+            coords.push((row, col));
+        } else {
+            continue;
+        }
+        if !mine.contains_key(&cell) && !theirs.contains_key(&cell) {
+            // neither has a cell here, as the grids differ in both rows and columns
+            merged.insert(cell, Grammar::input("", ""));
+        } else if merge_cell(mine, theirs, choices, &cell, merged) {
+            their_rows.push(row);
+        }
+    }
+    // cells come row by row, so the rows of a row's conflicts are next to each other
+    their_rows.dedup();
+    let (rows, cols) = union_size(mine, theirs, grid);
+    for (i, row) in their_rows.iter().enumerate() {
+        let new_row = rows + i as u32 + 1;
+        for col in (1..=cols).filter(|col| theirs_has(*row, *col)) {
+            coords.push((new_row, col));
+            let src = child(grid, *row, col);
+            let dest = child(grid, new_row, col);
+            if theirs.contains_key(&src) {
+                take_theirs(theirs, &src, merged, &dest);
+            } else {
+                merged.insert(dest, Grammar::input("", ""));
+            }
+        }
+    }
+    if let Some(g) = merged.get_mut(grid) {
+        g.kind = Kind::Grid(
+            coords
+                .into_iter()
+                .map(|(row, col)| (NonZeroU32::new(row).unwrap(), NonZeroU32::new(col).unwrap()))
+                .collect(),
+        );
+    }
+}
+
+// Copies their cell at `src`, and everything nested in it, over the merged one at `dest`
+fn take_theirs(
+    theirs: &HashMap<Coordinate, Grammar>,
+    src: &Coordinate,
+    merged: &mut HashMap<Coordinate, Grammar>,
+    dest: &Coordinate,
+) {
+    if let Some(fragment) = extract(theirs, &HashMap::new(), &HashMap::new(), src) {
+        graft(&fragment, merged, dest);
+    }
+}
+
+// Cells are the same if they hold the same thing, however they're styled
+fn same_contents(mine: &Grammar, theirs: &Grammar) -> bool {
+    mine.kind == theirs.kind && mine.name == theirs.name
+}
+
+// The cells of the grids (`root`, `meta`, ...) that aren't nested in any other, in coordinate order
+fn top_level(mine: &HashMap<Coordinate, Grammar>, theirs: &HashMap<Coordinate, Grammar>) -> Vec<Coordinate> {
+    let mut coords: Vec<Coordinate> = mine
+        .keys()
+        .chain(theirs.keys().filter(|c| !mine.contains_key(c)))
+        .filter(|c| c.parent().is_none())
+        .cloned()
+        .collect();
+    coords.sort_by(|a, b| a.row_cols.cmp(&b.row_cols));
+    coords
+}
+
+// The rows and columns a grid has in either session
+fn union_size(
+    mine: &HashMap<Coordinate, Grammar>,
+    theirs: &HashMap<Coordinate, Grammar>,
+    grid: &Coordinate,
+) -> (u32, u32) {
+    let (mine, theirs) = (size(mine.get(grid)), size(theirs.get(grid)));
+    (mine.0.max(theirs.0), mine.1.max(theirs.1))
+}
+
+// The rows and columns of a grid, as far as its cells reach
+fn size(grid: Option<&Grammar>) -> (u32, u32) {
+    sub_coords(grid)
+        .into_iter()
+        .fold((0, 0), |(rows, cols), (r, c)| (rows.max(r), cols.max(c)))
+}
+
+fn sub_coords(grid: Option<&Grammar>) -> Vec<(u32, u32)> {
+    match grid {
+        Some(Grammar {
+            kind: Kind::Grid(sub_coords),
+            ..
+        }) => sub_coords.iter().map(|(r, c)| (r.get(), c.get())).collect(),
+        _ => vec![],
+    }
+}
+
+// Every cell of a grid as it's merged, row by row
+fn grid_cells(
+    mine: &HashMap<Coordinate, Grammar>,
+    theirs: &HashMap<Coordinate, Grammar>,
+    grid: &Coordinate,
+) -> Vec<Coordinate> {
+    let (rows, cols) = union_size(mine, theirs, grid);
+    (1..=rows)
+        .flat_map(|row| (1..=cols).map(move |col| child(grid, row, col)))
+        .collect()
+}
+
+fn child(grid: &Coordinate, row: u32, col: u32) -> Coordinate {
+    Coordinate::child_of(grid, (NonZeroU32::new(row).unwrap(), NonZeroU32::new(col).unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use crate::util::non_zero_u32_tuple;
+
+    fn grid(rows: u32, cols: u32) -> Grammar {
+        Grammar::as_grid(NonZeroU32::new(rows).unwrap(), NonZeroU32::new(cols).unwrap())
+    }
+
+    #[test]
+    fn test_merge_grammars() {
+        let mine = hashmap! {
+            coord!("root") => grid(2, 2),
+            coord!("root-A1") => Grammar::input("", "apples"),
+            coord!("root-B1") => Grammar::input("", "3"),
+            coord!("root-A2") => Grammar::input("", "pears"),
+            coord!("root-B2") => Grammar::input("", "5"),
+        };
+        let mut theirs = mine.clone();
+        theirs.insert(coord!("root"), grid(3, 2));
+        theirs.insert(coord!("root-B1"), Grammar::input("", "4"));
+        theirs.insert(coord!("root-B2"), grid(1, 1));
+        theirs.insert(coord!("root-B2-A1"), Grammar::input("", "x"));
+        theirs.insert(coord!("root-A3"), Grammar::input("", "plums"));
+        theirs.insert(coord!("root-B3"), Grammar::input("", "1"));
+        // restyling a cell isn't a conflict
+        theirs.get_mut(&coord!("root-A1")).unwrap().style.font_weight = 600;
+
+        let conflicts = merge_conflicts(&mine, &theirs);
+        assert_eq!(
+            conflicts,
+            vec![
+                MergeConflict {
+                    coord: coord!("root-B1"),
+                    mine: "3".to_string(),
+                    theirs: "4".to_string(),
+                },
+                MergeConflict {
+                    coord: coord!("root-B2"),
+                    mine: "5".to_string(),
+                    theirs: "[1x1 grid: x...]".to_string(),
+                },
+            ]
+        );
+
+        // mine is kept unless told otherwise, with the rows they added
+        let merged = merge_grammars(&mine, &theirs, &HashMap::new());
+        assert_eq!(merged[&coord!("root-B1")].text_value(), "3");
+        assert_eq!(merged[&coord!("root-B2")].text_value(), "5");
+        assert!(!merged.contains_key(&coord!("root-B2-A1")));
+        assert_eq!(merged[&coord!("root-A3")].text_value(), "plums");
+
+        let choices = hashmap! {
+            coord!("root-B1") => MergeChoice::Both,
+            coord!("root-B2") => MergeChoice::Theirs,
+        };
+        let merged = merge_grammars(&mine, &theirs, &choices);
+        assert_eq!(merged[&coord!("root-B1")].text_value(), "3");
+        assert_eq!(merged[&coord!("root-B2-A1")].text_value(), "x");
+        // their version of the first row is added below the rows of the grid
+        assert_eq!(merged[&coord!("root-A4")].text_value(), "apples");
+        assert_eq!(merged[&coord!("root-B4")].text_value(), "4");
+        match &merged[&coord!("root")].kind {
+            Kind::Grid(sub_coords) => {
+                assert_eq!(sub_coords.len(), 8);
+                assert!(sub_coords.contains(&non_zero_u32_tuple((4, 2))));
+            }
+            _ => panic!("root should stay a grid"),
+        }
+    }
+
+    #[test]
+    fn test_merge_grid_keeps_merged_cells() {
+        // A1 is merged over B1, which mine leaves out of its grid
+        let mut mine = hashmap! {
+            coord!("root") => grid(2, 2),
+            coord!("root-A1") => Grammar::input("", "title"),
+            coord!("root-A2") => Grammar::input("", "a"),
+            coord!("root-B2") => Grammar::input("", "b"),
+        };
+        if let Some(Grammar {
+            kind: Kind::Grid(sub_coords),
+            style,
+            ..
+        }) = mine.get_mut(&coord!("root"))
+        {
+            sub_coords.retain(|c| *c != non_zero_u32_tuple((1, 2)));
+            style
+                .spans
+                .push((non_zero_u32_tuple((1, 1)), non_zero_u32_tuple((1, 2))));
+        }
+        mine.get_mut(&coord!("root-A1")).unwrap().style.col_span = (1, 2);
+        let mut theirs = mine.clone();
+        theirs.insert(coord!("root"), grid(2, 3));
+        theirs.insert(coord!("root-B1"), Grammar::input("", "b1"));
+        theirs.insert(coord!("root-C1"), Grammar::input("", "c1"));
+        theirs.insert(coord!("root-C2"), Grammar::input("", "c2"));
+
+        let merged = merge_grammars(&mine, &theirs, &HashMap::new());
+        let root = &merged[&coord!("root")];
+        match &root.kind {
+            Kind::Grid(sub_coords) => {
+                assert_eq!(sub_coords.len(), 5);
+                assert!(!sub_coords.contains(&non_zero_u32_tuple((1, 2))));
+                assert!(sub_coords.contains(&non_zero_u32_tuple((1, 3))));
+                assert!(sub_coords.contains(&non_zero_u32_tuple((2, 3))));
+            }
+            _ => panic!("root should stay a grid"),
+        }
+        assert_eq!(root.style.spans, mine[&coord!("root")].style.spans);
+        assert!(!merged.contains_key(&coord!("root-B1")));
+        assert_eq!(merged[&coord!("root-C1")].text_value(), "c1");
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+
+use crate::coordinate::Coordinate;
+use crate::grammar::{Grammar, Kind};
+use crate::util::row_col_to_string;
+use crate::value::Value;
+
+// Shape is what a cell expects to be filled with, declared as text (see `Style::shape`):
+// - `any`, `text`, `number` or `date` for a cell that isn't a grid (empty cells fit any of them)
+// - `grid` for a grid of any size, or `grid(s1, s2, ...)` for a grid with a column for each shape
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Any,
+    Text,
+    Number,
+    Date,
+    Grid(Option<Vec<Shape>>),
+}
+
+impl Shape {
+    pub fn parse(text: &str) -> Result<Shape, String> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut position = 0;
+        let shape = parse_shape(&chars, &mut position)?;
+        skip_spaces(&chars, &mut position);
+        if position < chars.len() {
+            return Err(format! {"unexpected {:?} in shape {:?}", chars[position], text});
+        }
+        Ok(shape)
+    }
+
+    // how the shape is described in mismatches, e.g. "a 2-column grid of (text, number)"
+    pub fn describe(&self) -> String {
+        match self {
+            Shape::Any => "anything but a grid".to_string(),
+            Shape::Text => "text".to_string(),
+            Shape::Number => "a number".to_string(),
+            Shape::Date => "a date".to_string(),
+            Shape::Grid(None) => "a grid".to_string(),
+            Shape::Grid(Some(cols)) => format! {
+                "a {}-column grid of ({})",
+                cols.len(),
+                cols.iter().map(|c| c.name()).collect::<Vec<String>>().join(", ")
+            },
+        }
+    }
+
+    // the shape as it's declared
+    fn name(&self) -> String {
+        match self {
+            Shape::Any => "any".to_string(),
+            Shape::Text => "text".to_string(),
+            Shape::Number => "number".to_string(),
+            Shape::Date => "date".to_string(),
+            Shape::Grid(None) => "grid".to_string(),
+            Shape::Grid(Some(cols)) => format! {
+                "grid({})",
+                cols.iter().map(|c| c.name()).collect::<Vec<String>>().join(", ")
+            },
+        }
+    }
+}
+
+fn skip_spaces(chars: &[char], position: &mut usize) {
+    while *position < chars.len() && chars[*position].is_whitespace() {
+        *position += 1;
+    }
+}
+
+fn parse_shape(chars: &[char], position: &mut usize) -> Result<Shape, String> {
+    skip_spaces(chars, position);
+    let start = *position;
+    while *position < chars.len() && chars[*position].is_alphabetic() {
+        *position += 1;
+    }
+    let word: String = chars[start..*position]
+        .iter()
+        .collect::<String>()
+        .to_lowercase();
+    let shape = match word.as_str() {
+        "any" => Shape::Any,
+        "text" | "string" => Shape::Text,
+        "number" => Shape::Number,
+        "date" => Shape::Date,
+        "grid" => Shape::Grid(None),
+        "" => return Err("expected a shape".to_string()),
+        other => return Err(format! {"unknown shape {:?}", other}),
+    };
+    skip_spaces(chars, position);
+    if shape != Shape::Grid(None) || chars.get(*position) != Some(&'(') {
+        return Ok(shape);
+    }
+    *position += 1;
+    let mut cols = vec![parse_shape(chars, position)?];
+    loop {
+        skip_spaces(chars, position);
+        match chars.get(*position) {
+            Some(',') => {
+                *position += 1;
+                cols.push(parse_shape(chars, position)?);
+            }
+            Some(')') => {
+                *position += 1;
+                return Ok(Shape::Grid(Some(cols)));
+            }
+            _ => return Err("expected , or ) in the columns of a grid".to_string()),
+        }
+    }
+}
+
+// Why the grammar at `coord` (and what's nested in it) doesn't have `shape`, if it doesn't.
+// Cells are named relative to `coord`, so the grammar can be checked where it comes from.
+pub fn mismatch(
+    grammars: &HashMap<Coordinate, Grammar>,
+    coord: &Coordinate,
+    shape: &Shape,
+) -> Option<String> {
+    check(grammars, coord, shape, coord)
+}
+
+fn check(
+    grammars: &HashMap<Coordinate, Grammar>,
+    coord: &Coordinate,
+    shape: &Shape,
+    root: &Coordinate,
+) -> Option<String> {
+    let grammar = grammars.get(coord)?;
+    let location = if coord == root {
+        "the cell".to_string()
+    } else {
+        coord.row_cols[root.row_cols.len()..]
+            .iter()
+            .map(|(row, col)| row_col_to_string((row.get(), col.get())))
+            .collect::<Vec<String>>()
+            .join("-")
+    };
+    let found = match &grammar.kind {
+        Kind::Grid(_) => "a grid".to_string(),
+        Kind::Defn(name, _, _) => format! {"a {} definition", name},
+        _ => format! {"{:?}", grammar.text_value()},
+    };
+    let fail = || Some(format! {"{} is {}, not {}", location, found, shape.describe()});
+    let text = grammar.text_value();
+    match (shape, &grammar.kind) {
+        (Shape::Grid(_), Kind::Grid(_)) => (),
+        (Shape::Grid(_), _) | (_, Kind::Grid(_)) => return fail(),
+        (Shape::Any, _) | (Shape::Text, _) => return None,
+        (_, _) if text.trim().is_empty() => return None,
+        (Shape::Number, _) => match Value::parse(&text) {
+            Value::Number(_) => return None,
+            _ => return fail(),
+        },
+        (Shape::Date, _) => match Value::parse(&text) {
+            Value::Date(_) => return None,
+            _ => return fail(),
+        },
+    }
+    let cols = match shape {
+        Shape::Grid(Some(cols)) => cols,
+        _ => return None,
+    };
+    let mut sub_coords = match &grammar.kind {
+        Kind::Grid(sub_coords) => sub_coords.clone(),
+        _ => return None,
+    };
+    let width = sub_coords
+        .iter()
+        .map(|(_, col)| col.get())
+        .max()
+        .unwrap_or(0) as usize;
+    if width != cols.len() {
+        return Some(format! {"{} has {} columns, not {}", location, width, shape.describe()});
+    }
+    sub_coords.sort();
+    sub_coords.iter().find_map(|sub_coord| {
+        let child = Coordinate::child_of(coord, *sub_coord);
+        check(
+            grammars,
+            &child,
+            &cols[sub_coord.1.get() as usize - 1],
+            root,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Shape::parse(" Number "), Ok(Shape::Number));
+        assert_eq!(
+            Shape::parse("grid(string, grid(date))"),
+            Ok(Shape::Grid(Some(vec![
+                Shape::Text,
+                Shape::Grid(Some(vec![Shape::Date]))
+            ])))
+        );
+        assert_eq!(Shape::parse("grid"), Ok(Shape::Grid(None)));
+        assert!(Shape::parse("grid(text").is_err());
+        assert!(Shape::parse("numbers").is_err());
+        assert!(Shape::parse("text number").is_err());
+        assert_eq!(
+            Shape::parse("grid(text, number)").unwrap().describe(),
+            "a 2-column grid of (text, number)"
+        );
+    }
+
+    #[test]
+    fn test_mismatch() {
+        let mut grammars = hashmap! {
+            coord!("meta-A1") => Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("meta-A1-A1") => Grammar::input("", "apples"),
+            coord!("meta-A1-B1") => Grammar::input("", "3"),
+            coord!("meta-A1-A2") => Grammar::input("", "pears"),
+            coord!("meta-A1-B2") => Grammar::input("", ""),
+            coord!("meta-A2") => Grammar::input("", "12"),
+        };
+        let fits = |grammars: &HashMap<Coordinate, Grammar>, coord, shape: &str| {
+            mismatch(grammars, &coord, &Shape::parse(shape).unwrap())
+        };
+        assert_eq!(
+            fits(&grammars, coord!("meta-A1"), "grid(text, number)"),
+            None
+        );
+        assert_eq!(fits(&grammars, coord!("meta-A1"), "grid"), None);
+        assert_eq!(fits(&grammars, coord!("meta-A2"), "number"), None);
+        assert_eq!(
+            fits(&grammars, coord!("meta-A1"), "grid(text)"),
+            Some("the cell has 2 columns, not a 1-column grid of (text)".to_string())
+        );
+        assert_eq!(
+            fits(&grammars, coord!("meta-A2"), "grid"),
+            Some("the cell is \"12\", not a grid".to_string())
+        );
+        assert_eq!(
+            fits(&grammars, coord!("meta-A1"), "number"),
+            Some("the cell is a grid, not a number".to_string())
+        );
+        grammars.insert(coord!("meta-A1-B2"), Grammar::input("", "lots"));
+        assert_eq!(
+            fits(&grammars, coord!("meta-A1"), "grid(text, number)"),
+            Some("B2 is \"lots\", not a number".to_string())
+        );
+    }
+}
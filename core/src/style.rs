@@ -1,5 +1,5 @@
 use pest::Parser;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::num::NonZeroU32;
 use std::option::Option;
@@ -10,10 +10,10 @@ use crate::coordinate;
 use crate::coordinate::*;
 use crate::grammar;
 use crate::grammar::{Grammar, Interactive, Kind, Lookup};
-use crate::model::Model;
+use crate::macros::ButtonAction;
+use crate::suggestion::SuggestionCategory;
 use crate::util::non_zero_u32_tuple;
 use crate::{coord, coord_col, coord_row, row_col_vec};
-use yew::html::Component;
 
 #[derive(Parser)]
 #[grammar = "coordinate.pest"]
@@ -21,7 +21,7 @@ pub struct CoordinateParser;
 
 // Style contains the relevant CSS properties for styling
 // a grammar Cell or Grid
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct Style {
     pub width: f64,            // CSS: width
     pub height: f64,           // CSS: height
@@ -32,8 +32,51 @@ pub struct Style {
     pub col_span: (u32, u32),
     pub row_span: (u32, u32),
     pub display: bool,
+    // spans of a grid's children, as (sub_coord, (rows, cols)) entries for the children
+    // that stretch over more than one row or column of the grid
+    #[serde(default)]
+    pub spans: Vec<((NonZeroU32, NonZeroU32), (NonZeroU32, NonZeroU32))>,
+    // whether a grid grows a new row/column when its last row/column is typed into
+    #[serde(default)]
+    pub auto_expand: bool,
+    // category a meta grammar is listed under in the completion dropdown,
+    // inferred from its kind when not set
+    #[serde(default)]
+    pub category: Option<SuggestionCategory>,
+    // marks a cell of a snippet grammar as a placeholder to be filled in after completion,
+    // the label is shown in the cell until it's filled
+    #[serde(default)]
+    pub placeholder: Option<String>,
+    // opts a text cell out of spellchecking
+    #[serde(default)]
+    pub no_spellcheck: bool,
+    // shape the grammar completed into this cell is expected to have, see `shape::Shape`
+    #[serde(default)]
+    pub shape: Option<String>,
+    // sizes for the grids nested in this grid (and further down, unless they have their own),
+    // in place of the ones from preferences, see `grid_defaults`
+    #[serde(default)]
+    pub nested_defaults: Option<GridDefaults>,
+    // formula (or cell) the cell is only shown while it's true, see `visibility`
+    #[serde(default)]
+    pub visible_when: Option<String>,
+    // formulas (or cells) some style attributes are driven by, see `bindings`
+    #[serde(default)]
+    pub bindings: StyleBindings,
+    // what the bound attributes last evaluated to, applied over the others in `get_style`
+    #[serde(skip)]
+    pub computed: ComputedStyle,
+    // the macro a button cell runs when it's clicked, see `macros`
+    #[serde(default)]
+    pub on_click: Option<ButtonAction>,
+    // identifies the cell across renders while it's moved around (see `keys::assign_keys`),
+    // it isn't saved
+    #[serde(skip)]
+    pub key: u64,
 }
+#[cfg(feature = "stdweb")]
 js_serializable!(Style);
+#[cfg(feature = "stdweb")]
 js_deserializable!(Style);
 
 impl Style {
@@ -48,6 +91,18 @@ impl Style {
             col_span: (0, 0),
             row_span: (0, 0),
             display: true,
+            spans: vec![],
+            auto_expand: false,
+            category: None,
+            placeholder: None,
+            no_spellcheck: false,
+            shape: None,
+            nested_defaults: None,
+            visible_when: None,
+            bindings: StyleBindings::default(),
+            computed: ComputedStyle::default(),
+            on_click: None,
+            key: 0,
         }
     }
 
@@ -72,6 +127,91 @@ impl Style {
     }
 }
 
+// GridDefaults are the number of rows & columns a new nested grid gets, and the height & width
+// its cells start with
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct GridDefaults {
+    pub rows: u32,
+    pub cols: u32,
+    pub row_height: f64,
+    pub col_width: f64,
+}
+
+impl Default for GridDefaults {
+    fn default() -> Self {
+        GridDefaults {
+            rows: 3,
+            cols: 3,
+            row_height: 30.0,
+            col_width: 90.0,
+        }
+    }
+}
+
+// StyleBindings are the formulas (or cells) style attributes of a cell are driven by, e.g. the
+// background bound to a color picker cell
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct StyleBindings {
+    pub background: Option<String>,
+    pub font_color: Option<String>,
+    pub width: Option<String>,
+}
+
+impl StyleBindings {
+    pub fn is_empty(&self) -> bool {
+        self.background.is_none() && self.font_color.is_none() && self.width.is_none()
+    }
+}
+
+// ComputedStyle is what the bound attributes of a cell evaluated to, for the ones that evaluated
+// to a color (or a width)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ComputedStyle {
+    pub background: Option<String>,
+    pub font_color: Option<String>,
+    pub width: Option<f64>,
+}
+
+impl ComputedStyle {
+    // the CSS of the computed colors, which goes after the rest of a cell's style
+    fn to_css(&self) -> String {
+        let mut css = String::new();
+        if let Some(background) = &self.background {
+            css.push_str(&format! {"background: {};\n", background});
+        }
+        if let Some(color) = &self.font_color {
+            css.push_str(&format! {"color: {};\n", color});
+        }
+        css
+    }
+}
+
+// The defaults set for a grid nested at `coord`: those of the closest grid it's in that
+// overrides them (or of `coord` itself, when it's a grid that does)
+pub fn nested_defaults(
+    grammars: &HashMap<Coordinate, Grammar>,
+    coord: &Coordinate,
+) -> Option<GridDefaults> {
+    let mut current = Some(coord.clone());
+    while let Some(c) = current {
+        if let Some(defaults) = grammars.get(&c).and_then(|g| g.style.nested_defaults) {
+            return Some(defaults);
+        }
+        current = c.parent();
+    }
+    None
+}
+
+// The defaults for a grid nested at `coord`, `fallback` where none of the grids it's in set them
+pub fn grid_defaults(
+    grammars: &HashMap<Coordinate, Grammar>,
+    coord: &Coordinate,
+    fallback: GridDefaults,
+) -> GridDefaults {
+    nested_defaults(grammars, coord).unwrap_or(fallback)
+}
+
 pub fn get_style(
     model_grammar: &Grammar,
     model_col_widths: &HashMap<coordinate::Col, f64>,
@@ -86,8 +226,9 @@ pub fn get_style(
     }
     let (col_span, row_span, mut col_width, mut row_height) = {
         let s = &model_grammar.style;
-        (s.col_span, s.row_span, s.width, s.height)
+        (s.col_span, s.row_span, s.computed.width.unwrap_or(s.width), s.height)
     };
+    let computed = grammar.style.computed.to_css();
     let mut s_col_span = String::new();
     let mut s_row_span = String::new();
     let n_col_span = col_span.1 - col_span.0;
@@ -109,22 +250,22 @@ pub fn get_style(
             };
         }
         return format! {
-            "{}\nwidth: {}px;\nheight: {}px;{} {}",
+            "{}\nwidth: {}px;\nheight: {}px;{} {}{}",
             grammar.style(coord), col_width, row_height,
-            s_col_span, s_row_span,
+            s_col_span, s_row_span, computed,
         };
     }
 
     if let Kind::Grid(_) = grammar.kind {
         return format! {
-            "{}\nwidth: fit-content;\nheight: fit-content;\n",
-            grammar.style(coord),
+            "{}\nwidth: fit-content;\nheight: fit-content;\n{}",
+            grammar.style(coord), computed,
         };
     }
 
     format! {
-        "{}\nwidth: {}px;\nheight: {}px;\n",
-        grammar.style(coord), col_width, row_height,
+        "{}\nwidth: {}px;\nheight: {}px;\n{}",
+        grammar.style(coord), col_width, row_height, computed,
     }
 }
 
@@ -158,6 +299,28 @@ mod tests {
         // assert_ne!(Style::default().to_string(),  String::from("/* border: 1px; NOTE: ignoring Style::border_* for now */\n    border-collapse: inherit;\n    font-weight: 400;\n    color: black;\n" ));
     }
 
+    #[test]
+    fn test_grid_defaults() {
+        let component = GridDefaults {
+            rows: 2,
+            cols: 4,
+            row_height: 24.0,
+            col_width: 60.0,
+        };
+        let mut grid = grammar::Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(1).unwrap());
+        grid.style.nested_defaults = Some(component);
+        let grammars = hashmap! {
+            coord!("root") => grammar::Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => grid,
+            coord!("root-A1-A1") => grammar::Grammar::default(),
+            coord!("root-B1") => grammar::Grammar::default(),
+        };
+        let fallback = GridDefaults::default();
+        assert_eq!(grid_defaults(&grammars, &coord!("root-A1-A1"), fallback), component);
+        assert_eq!(grid_defaults(&grammars, &coord!("root-A1"), fallback), component);
+        assert_eq!(grid_defaults(&grammars, &coord!("root-B1"), fallback), fallback);
+    }
+
     #[test]
     fn test_get_style() {
         //Test type Grid
@@ -0,0 +1,235 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::coordinate::Coordinate;
+use crate::grammar::{Grammar, Kind};
+use crate::util::{non_zero_u32_tuple, special_row_col};
+
+// Category a meta grammar is listed under in the completion dropdown
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SuggestionCategory {
+    Widgets,
+    Definitions,
+    Snippets,
+    Driver,
+}
+
+// number of entries shown in the "Recent" section of the dropdown
+pub const RECENT_LIMIT: usize = 3;
+
+impl SuggestionCategory {
+    // order in which sections appear in the dropdown
+    pub const ALL: [SuggestionCategory; 4] = [
+        SuggestionCategory::Widgets,
+        SuggestionCategory::Definitions,
+        SuggestionCategory::Snippets,
+        SuggestionCategory::Driver,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SuggestionCategory::Widgets => "Widgets",
+            SuggestionCategory::Definitions => "Definitions",
+            SuggestionCategory::Snippets => "Snippets",
+            SuggestionCategory::Driver => "Driver-provided",
+        }
+    }
+
+    // used to cycle a meta grammar's category from the menu bar
+    pub fn next(&self) -> SuggestionCategory {
+        match self {
+            SuggestionCategory::Widgets => SuggestionCategory::Definitions,
+            SuggestionCategory::Definitions => SuggestionCategory::Snippets,
+            SuggestionCategory::Snippets => SuggestionCategory::Driver,
+            SuggestionCategory::Driver => SuggestionCategory::Widgets,
+        }
+    }
+}
+
+// The category of a meta grammar is the one it's been tagged with, otherwise it's
+// inferred from its kind
+pub fn category_of(grammar: &Grammar) -> SuggestionCategory {
+    if let Some(category) = grammar.style.category {
+        return category;
+    }
+    match grammar.kind {
+        Kind::Interactive(_, _) => SuggestionCategory::Widgets,
+        Kind::Grid(_) | Kind::Defn(_, _, _) => SuggestionCategory::Definitions,
+        _ => SuggestionCategory::Snippets,
+    }
+}
+
+// Splits suggestions into sections, in the order of `SuggestionCategory::ALL`.
+// Empty sections are left out.
+pub fn group_by_category(
+    suggestions: &[(Coordinate, Grammar)],
+) -> Vec<(SuggestionCategory, Vec<(Coordinate, Grammar)>)> {
+    SuggestionCategory::ALL
+        .iter()
+        .filter_map(|category| {
+            let section: Vec<(Coordinate, Grammar)> = suggestions
+                .iter()
+                .filter(|(_, g)| category_of(g) == *category)
+                .cloned()
+                .collect();
+            if section.is_empty() {
+                None
+            } else {
+                Some((*category, section))
+            }
+        })
+        .collect()
+}
+
+// The most used of `suggestions` according to `usage` (completion counts by grammar name),
+// most used first. Suggestions that were never used are left out.
+pub fn most_used(
+    usage: &HashMap<String, u32>,
+    suggestions: &[(Coordinate, Grammar)],
+    limit: usize,
+) -> Vec<(Coordinate, Grammar)> {
+    let mut used: Vec<(u32, (Coordinate, Grammar))> = suggestions
+        .iter()
+        .filter_map(|(c, g)| usage.get(&g.name).map(|count| (*count, (c.clone(), g.clone()))))
+        .filter(|(count, _)| *count > 0)
+        .collect();
+    // sort by count (descending), then by name so the order is stable
+    used.sort_by(|(a_count, (_, a)), (b_count, (_, b))| {
+        b_count.cmp(a_count).then(a.name.cmp(&b.name))
+    });
+    used.into_iter().take(limit).map(|(_, s)| s).collect()
+}
+
+// Orders `suggestions` most used first, keeping the meta sheet order between equally used ones
+pub fn by_usage(usage: &HashMap<String, u32>, suggestions: &[(Coordinate, Grammar)]) -> Vec<(Coordinate, Grammar)> {
+    let mut sorted = suggestions.to_vec();
+    sorted.sort_by_key(|(_, g)| std::cmp::Reverse(usage.get(&g.name).cloned().unwrap_or(0)));
+    sorted
+}
+
+// How much a meta grammar is used: the times it was completed (across sessions, from the
+// preferences) and the grammars in the session that were made from it
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrammarUsage {
+    pub coord: Coordinate,
+    pub name: String,
+    pub completions: u32,
+    pub instances: usize,
+}
+
+impl GrammarUsage {
+    pub fn is_unused(&self) -> bool {
+        self.completions == 0 && self.instances == 0
+    }
+}
+
+// Usage of each of the meta grammars suggestions are made from, most used first
+pub fn usage_report(
+    grammars: &HashMap<Coordinate, Grammar>,
+    meta_suggestions: &[(String, Coordinate)],
+    usage: &HashMap<String, u32>,
+) -> Vec<GrammarUsage> {
+    let meta = special_row_col("meta").map(non_zero_u32_tuple);
+    let mut instances: HashMap<&str, usize> = HashMap::new();
+    for (coord, grammar) in grammars.iter() {
+        // sheet roots and the meta sheet don't count
+        if coord.row_cols.len() < 2 || coord.row_cols.first() == meta.as_ref() || grammar.name.is_empty() {
+            continue;
+        }
+        *instances.entry(grammar.name.as_str()).or_insert(0) += 1;
+    }
+    let mut report: Vec<GrammarUsage> = meta_suggestions
+        .iter()
+        .map(|(name, coord)| GrammarUsage {
+            coord: coord.clone(),
+            name: name.clone(),
+            completions: usage.get(name).cloned().unwrap_or(0),
+            instances: instances.get(name.as_str()).cloned().unwrap_or(0),
+        })
+        .collect();
+    report.sort_by(|a, b| {
+        (b.completions as usize + b.instances)
+            .cmp(&(a.completions as usize + a.instances))
+            .then(a.name.cmp(&b.name))
+    });
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+
+    #[test]
+    fn test_category_of() {
+        assert_eq!(
+            category_of(&Grammar::default_button()),
+            SuggestionCategory::Widgets
+        );
+        assert_eq!(
+            category_of(&Grammar::input("", "")),
+            SuggestionCategory::Snippets
+        );
+        let mut tagged = Grammar::input("", "");
+        tagged.style.category = Some(SuggestionCategory::Driver);
+        assert_eq!(category_of(&tagged), SuggestionCategory::Driver);
+    }
+
+    #[test]
+    fn test_group_by_category() {
+        let suggestions = vec![
+            (coord!("meta-A1"), Grammar::input("a", "")),
+            (coord!("meta-A2"), Grammar::default_toggle()),
+            (coord!("meta-A3"), Grammar::input("b", "")),
+        ];
+        let groups = group_by_category(&suggestions);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].0, SuggestionCategory::Widgets);
+        assert_eq!(groups[1].0, SuggestionCategory::Snippets);
+        assert_eq!(groups[1].1.len(), 2);
+    }
+
+    #[test]
+    fn test_most_used() {
+        let suggestions = vec![
+            (coord!("meta-A1"), Grammar::input("a", "")),
+            (coord!("meta-A2"), Grammar::input("b", "")),
+            (coord!("meta-A3"), Grammar::input("c", "")),
+        ];
+        let usage = hashmap! {
+            "a".to_string() => 1,
+            "c".to_string() => 5,
+        };
+        let recent: Vec<String> = most_used(&usage, &suggestions, RECENT_LIMIT)
+            .into_iter()
+            .map(|(_, g)| g.name)
+            .collect();
+        assert_eq!(recent, vec!["c".to_string(), "a".to_string()]);
+        assert_eq!(most_used(&usage, &suggestions, 1).len(), 1);
+        let ordered: Vec<String> = by_usage(&usage, &suggestions).into_iter().map(|(_, g)| g.name).collect();
+        assert_eq!(ordered, vec!["c".to_string(), "a".to_string(), "b".to_string()]);
+    }
+
+    #[test]
+    fn test_usage_report() {
+        let grammars = hashmap! {
+            coord!("meta-A1") => Grammar::input("point", ""),
+            coord!("meta-A2") => Grammar::input("shape", ""),
+            coord!("meta-A3") => Grammar::input("unused", ""),
+            coord!("root-A1") => Grammar::input("point", "1, 2"),
+            coord!("root-A2") => Grammar::input("point", "3, 4"),
+        };
+        let meta_suggestions = vec![
+            ("point".to_string(), coord!("meta-A1")),
+            ("shape".to_string(), coord!("meta-A2")),
+            ("unused".to_string(), coord!("meta-A3")),
+        ];
+        let usage = hashmap! { "shape".to_string() => 1 };
+        let report = usage_report(&grammars, &meta_suggestions, &usage);
+        let names: Vec<&str> = report.iter().map(|u| u.name.as_str()).collect();
+        assert_eq!(names, vec!["point", "shape", "unused"]);
+        assert_eq!(report[0].instances, 2);
+        assert_eq!(report[1].completions, 1);
+        assert!(report[2].is_unused());
+    }
+}
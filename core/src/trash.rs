@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+
+use crate::coordinate::Coordinate;
+use crate::grammar::{Grammar, Kind};
+use crate::lines::insert_line;
+use crate::outline::OutlineAxis;
+
+// how many deletions the trash of a session keeps, dropping the oldest ones first
+pub const TRASH_LIMIT: usize = 50;
+
+// TrashEntry is what a structural deletion (of a row, a column or nested grids) took out of a
+// session: the grammars it removed by the coordinates they had, which stay restorable for as
+// long as the session is open, whatever happens to the undo history
+#[derive(Debug, Clone)]
+pub struct TrashEntry {
+    pub label: String,
+    pub deleted_at: String,
+    pub grammars: HashMap<Coordinate, Grammar>,
+    // the row or column of a grid the deletion removed, if it removed a whole one
+    pub line: Option<(Coordinate, OutlineAxis, u32)>,
+}
+
+impl TrashEntry {
+    // The entry for deleting row or column `line` of `grid`, taken before it's deleted: every
+    // cell of the line, with what's nested in them
+    pub fn of_line(
+        label: &str,
+        deleted_at: &str,
+        grammars: &HashMap<Coordinate, Grammar>,
+        grid: &Coordinate,
+        axis: OutlineAxis,
+        line: u32,
+    ) -> Option<TrashEntry> {
+        let depth = grid.row_cols.len() + 1;
+        let removed: HashMap<Coordinate, Grammar> = grammars
+            .iter()
+            .filter(|(c, _)| {
+                c.row_cols.len() >= depth && c.row_cols.starts_with(&grid.row_cols) && {
+                    let (row, col) = c.row_cols[depth - 1];
+                    match axis {
+                        OutlineAxis::Rows => row.get() == line,
+                        OutlineAxis::Cols => col.get() == line,
+                    }
+                }
+            })
+            .map(|(c, g)| (c.clone(), g.clone()))
+            .collect();
+        if removed.is_empty() {
+            return None;
+        }
+        Some(TrashEntry {
+            label: label.to_string(),
+            deleted_at: deleted_at.to_string(),
+            grammars: removed,
+            line: Some((grid.clone(), axis, line)),
+        })
+    }
+
+    // The entry for clearing `coords`, taken before they're cleared: the nested grids among
+    // them with everything in them (clearing other cells only drops their text, which isn't
+    // kept)
+    pub fn of_cells(
+        label: &str,
+        deleted_at: &str,
+        grammars: &HashMap<Coordinate, Grammar>,
+        coords: &[Coordinate],
+    ) -> Option<TrashEntry> {
+        let grids: Vec<&Coordinate> = coords
+            .iter()
+            .filter(|c| match grammars.get(c).map(|g| &g.kind) {
+                Some(Kind::Grid(_)) => c.parent().is_some(),
+                _ => false,
+            })
+            .collect();
+        let removed: HashMap<Coordinate, Grammar> = grammars
+            .iter()
+            .filter(|(c, _)| grids.iter().any(|g| c.row_cols.starts_with(&g.row_cols)))
+            .map(|(c, g)| (c.clone(), g.clone()))
+            .collect();
+        if removed.is_empty() {
+            return None;
+        }
+        Some(TrashEntry {
+            label: label.to_string(),
+            deleted_at: deleted_at.to_string(),
+            grammars: removed,
+            line: None,
+        })
+    }
+
+    // The removed grammars that aren't nested in other removed ones, in coordinate order
+    pub fn roots(&self) -> Vec<Coordinate> {
+        let mut roots: Vec<Coordinate> = self
+            .grammars
+            .keys()
+            .filter(|c| c.parent().is_none_or(|p| !self.grammars.contains_key(&p)))
+            .cloned()
+            .collect();
+        roots.sort_by(|a, b| a.row_cols.cmp(&b.row_cols));
+        roots
+    }
+
+    pub fn describe(&self) -> String {
+        let roots = self.roots();
+        let shown: Vec<String> = roots.iter().take(3).map(|c| c.to_string()).collect();
+        format! {
+            "{} cell{} ({}{})",
+            self.grammars.len(),
+            if self.grammars.len() == 1 { "" } else { "s" },
+            shown.join(", "),
+            if roots.len() > shown.len() { ", ..." } else { "" }
+        }
+    }
+}
+
+// Adds a deletion to the trash of a session, newest first
+pub fn push_entry(trash: &mut Vec<TrashEntry>, entry: TrashEntry) {
+    trash.insert(0, entry);
+    trash.truncate(TRASH_LIMIT);
+}
+
+// Puts the grammars of `entry` back into `grammars`. A whole row or column goes back in a line
+// inserted where it was. Other cells go back where they were, as long as what took their place
+// since is empty.
+pub fn restore(
+    grammars: &mut HashMap<Coordinate, Grammar>,
+    entry: &TrashEntry,
+) -> Result<(), String> {
+    let roots = entry.roots();
+    match &entry.line {
+        Some((grid, axis, line)) => {
+            if !insert_line(grammars, grid, *axis, *line) {
+                return Err(format! {"{} isn't a grid anymore", grid.to_string()});
+            }
+        }
+        None => {
+            for root in roots.iter() {
+                match root.parent().map(|p| grammars.get(&p).map(|g| &g.kind)) {
+                    Some(Some(Kind::Grid(_))) | None => (),
+                    _ => return Err(format! {"the grid {} was in is gone", root.to_string()}),
+                }
+                let taken = match grammars.get(root) {
+                    Some(Grammar {
+                        kind: Kind::Grid(_),
+                        ..
+                    }) => true,
+                    Some(grammar) => !grammar.text_value().trim().is_empty(),
+                    None => false,
+                };
+                if taken {
+                    return Err(format! {"{} has been filled in since", root.to_string()});
+                }
+            }
+        }
+    }
+    for (coord, grammar) in entry.grammars.iter() {
+        grammars.insert(coord.clone(), grammar.clone());
+    }
+    for root in roots {
+        let parent = match root.parent() {
+            Some(parent) => parent,
+            None => continue,
+        };
+        if let Some(Grammar {
+            kind: Kind::Grid(sub_coords),
+            ..
+        }) = grammars.get_mut(&parent)
+        {
+            if !sub_coords.contains(&root.row_col()) {
+                sub_coords.push(root.row_col());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use std::num::NonZeroU32;
+
+    fn grid() -> HashMap<Coordinate, Grammar> {
+        hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => Grammar::input("", "apples"),
+            coord!("root-B1") => Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(1).unwrap()),
+            coord!("root-B1-A1") => Grammar::input("", "3"),
+            coord!("root-A2") => Grammar::input("", "pears"),
+            coord!("root-B2") => Grammar::input("", "=root-A2"),
+        }
+    }
+
+    fn text(grammars: &HashMap<Coordinate, Grammar>, coord: Coordinate) -> String {
+        grammars.get(&coord).unwrap().text_value()
+    }
+
+    #[test]
+    fn test_of_line() {
+        let entry = TrashEntry::of_line(
+            "Delete row",
+            "",
+            &grid(),
+            &coord!("root"),
+            OutlineAxis::Rows,
+            1,
+        )
+        .unwrap();
+        assert_eq!(entry.roots(), vec![coord!("root-A1"), coord!("root-B1")]);
+        assert_eq!(entry.describe(), "3 cells (root-A1, root-B1)");
+        let entry = TrashEntry::of_line(
+            "Delete column",
+            "",
+            &grid(),
+            &coord!("root"),
+            OutlineAxis::Cols,
+            2,
+        )
+        .unwrap();
+        assert_eq!(entry.roots(), vec![coord!("root-B1"), coord!("root-B2")]);
+        assert!(TrashEntry::of_line(
+            "Delete row",
+            "",
+            &grid(),
+            &coord!("root"),
+            OutlineAxis::Rows,
+            3
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_restore() {
+        // deleting the first row moves the second one up
+        let before = grid();
+        let entry = TrashEntry::of_line(
+            "Delete row",
+            "",
+            &before,
+            &coord!("root"),
+            OutlineAxis::Rows,
+            1,
+        )
+        .unwrap();
+        let mut grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => Grammar::input("", "pears"),
+            coord!("root-B1") => Grammar::input("", "=root-A1"),
+        };
+        assert_eq!(restore(&mut grammars, &entry), Ok(()));
+        assert_eq!(text(&grammars, coord!("root-A1")), "apples");
+        assert_eq!(text(&grammars, coord!("root-B1-A1")), "3");
+        assert_eq!(text(&grammars, coord!("root-A2")), "pears");
+        assert_eq!(text(&grammars, coord!("root-B2")), "=root-A2");
+        match &grammars.get(&coord!("root")).unwrap().kind {
+            Kind::Grid(sub_coords) => assert_eq!(sub_coords.len(), 4),
+            kind => panic!("root should still be a grid, got {:?}", kind),
+        }
+
+        // a cleared grid goes back where it was, unless that's been filled in since
+        let entry = TrashEntry::of_cells(
+            "Delete cells",
+            "",
+            &before,
+            &[coord!("root-A1"), coord!("root-B1")],
+        )
+        .unwrap();
+        assert_eq!(entry.roots(), vec![coord!("root-B1")]);
+        let mut cleared = before.clone();
+        cleared.remove(&coord!("root-B1-A1"));
+        cleared.insert(coord!("root-B1"), Grammar::input("", "lots"));
+        assert_eq!(
+            restore(&mut cleared, &entry),
+            Err("root-B1 has been filled in since".to_string())
+        );
+        cleared.insert(coord!("root-B1"), Grammar::input("", ""));
+        assert_eq!(restore(&mut cleared, &entry), Ok(()));
+        assert_eq!(text(&cleared, coord!("root-B1-A1")), "3");
+    }
+}
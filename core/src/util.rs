@@ -0,0 +1,113 @@
+use std::char::from_u32;
+use std::num::NonZeroU32;
+use std::ops::Deref;
+
+pub fn non_zero_u32_tuple(val: (u32, u32)) -> (NonZeroU32, NonZeroU32) {
+    let (row, col) = val;
+    (NonZeroU32::new(row).unwrap(), NonZeroU32::new(col).unwrap())
+}
+
+pub fn row_col_to_string((row, col): (u32, u32)) -> String {
+    let row_str = row.to_string();
+    let col_str = from_u32(col + 64).unwrap();
+    format! {"{}{}", col_str, row_str}
+}
+
+// top-level grids are `root`, `meta`, and any number of extra sheets `sheet1`, `sheet2`, ...
+// which take up the top-level slots after root (1, 1) and meta (1, 2)
+pub fn special_row_col(special: &str) -> Option<(u32, u32)> {
+    match special {
+        "root" => Some((1, 1)),
+        "meta" => Some((1, 2)),
+        s if s.starts_with("sheet") => s["sheet".len()..]
+            .parse::<u32>()
+            .ok()
+            .filter(|n| *n > 0)
+            .map(|n| (1, n + 2)),
+        _ => None,
+    }
+}
+
+pub fn coord_show(row_cols: Vec<(u32, u32)>) -> Option<String> {
+    match row_cols.split_first() {
+        Some((&(1, 1), rest)) => {
+            let mut output = "root".to_string();
+            for rc in rest.iter() {
+                output.push('-');
+                output.push_str(row_col_to_string(*rc).deref());
+            }
+            Some(output)
+        }
+        Some((&(1, 2), rest)) => {
+            let mut output = "meta".to_string();
+            for rc in rest.iter() {
+                output.push('-');
+                output.push_str(row_col_to_string(*rc).deref());
+            }
+            Some(output)
+        }
+        Some((&(1, sheet), rest)) if sheet > 2 => {
+            let mut output = format! {"sheet{}", sheet - 2};
+            for rc in rest.iter() {
+                output.push('-');
+                output.push_str(row_col_to_string(*rc).deref());
+            }
+            Some(output)
+        }
+        _ => None,
+    }
+}
+
+// macro for easily defining a vector of non-zero tuples
+// used in Coordinate::root() below
+#[macro_export]
+macro_rules! row_col_vec {
+    ( $( $x:expr ), * ) => {
+        {
+            let mut v: Vec<(NonZeroU32, NonZeroU32)> = Vec::new();
+            $(
+                v.push(non_zero_u32_tuple($x));
+            )*
+            v
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_non_zero_u32_tuple() {
+        assert_eq!(
+            non_zero_u32_tuple((1, 2)),
+            (NonZeroU32::new(1).unwrap(), NonZeroU32::new(2).unwrap())
+        );
+        assert_ne!(
+            non_zero_u32_tuple((1, 2)),
+            (NonZeroU32::new(2).unwrap(), NonZeroU32::new(2).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_row_col_to_string() {
+        assert_eq!(row_col_to_string((2, 2)), "B2");
+        assert_ne!(row_col_to_string((2, 2)), "A2");
+    }
+
+    #[test]
+    fn test_coord_show() {
+        assert_eq!(coord_show(vec![(1, 1), (1, 1)]).unwrap(), "root-A1");
+        assert_ne!(coord_show(vec![(1, 1), (1, 1)]).unwrap(), "root");
+        assert_eq!(coord_show(vec![(1, 3), (2, 1)]).unwrap(), "sheet1-A2");
+    }
+
+    #[test]
+    fn test_special_row_col() {
+        assert_eq!(special_row_col("root"), Some((1, 1)));
+        assert_eq!(special_row_col("meta"), Some((1, 2)));
+        assert_eq!(special_row_col("sheet3"), Some((1, 5)));
+        assert_eq!(special_row_col("sheet0"), None);
+        assert_eq!(special_row_col("sheet"), None);
+    }
+}
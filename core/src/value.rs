@@ -0,0 +1,229 @@
+use std::fmt;
+
+// Value is how the text of an Input cell is interpreted: as a number, a date (YYYY-MM-DD),
+// or plain text. Commands that work on typed values (like stepping with Ctrl+Up/Down) go
+// through this, so cells that don't hold a number or date are left alone.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Date(Date),
+    Text(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+// How far a value is stepped: numbers by a step (see `Preferences::numeric_step`),
+// dates by days or months
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepUnit {
+    Step(f64),
+    Days,
+    Months,
+}
+
+impl Value {
+    pub fn parse(text: &str) -> Value {
+        let trimmed = text.trim();
+        if let Ok(n) = trimmed.parse::<f64>() {
+            if n.is_finite() {
+                return Value::Number(n);
+            }
+        }
+        if let Some(date) = Date::parse(trimmed) {
+            return Value::Date(date);
+        }
+        Value::Text(text.to_string())
+    }
+
+    // `None` for text, or for a unit that doesn't apply to the value (numbers only step by
+    // a number, dates only by days or months)
+    pub fn step(&self, unit: StepUnit, count: i32) -> Option<Value> {
+        match (self, unit) {
+            (Value::Number(n), StepUnit::Step(step)) => {
+                let stepped = n + step * count as f64;
+                // round away float noise (0.1 + 0.2) to the precision of the number or step
+                let decimals = decimals_of(*n).max(decimals_of(step));
+                let factor = 10f64.powi(decimals as i32);
+                Some(Value::Number((stepped * factor).round() / factor))
+            }
+            (Value::Date(d), StepUnit::Days) => Some(Value::Date(d.add_days(count as i64))),
+            (Value::Date(d), StepUnit::Months) => Some(Value::Date(d.add_months(count))),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Date(d) => write!(f, "{:04}-{:02}-{:02}", d.year, d.month, d.day),
+            Value::Text(t) => f.write_str(t),
+        }
+    }
+}
+
+// Steps the text of a cell, or returns `None` if it isn't a value that can be stepped
+pub fn step_text(text: &str, unit: StepUnit, count: i32) -> Option<String> {
+    Value::parse(text).step(unit, count).map(|v| v.to_string())
+}
+
+fn decimals_of(n: f64) -> usize {
+    let s = n.to_string();
+    s.find('.').map_or(0, |i| s.len() - i - 1).min(10)
+}
+
+impl Date {
+    // YYYY-MM-DD
+    pub fn parse(text: &str) -> Option<Date> {
+        let mut parts = text.splitn(3, '-');
+        let (y, m, d) = (parts.next()?, parts.next()?, parts.next()?);
+        if y.len() != 4 || m.len() != 2 || d.len() != 2 {
+            return None;
+        }
+        let date = Date {
+            year: y.parse().ok()?,
+            month: m.parse().ok()?,
+            day: d.parse().ok()?,
+        };
+        if date.month < 1 || date.month > 12 || date.day < 1 || date.day > days_in_month(date.year, date.month) {
+            return None;
+        }
+        Some(date)
+    }
+
+    pub fn add_days(&self, days: i64) -> Date {
+        Date::from_days(self.to_days() + days)
+    }
+
+    // the day is clamped to the end of shorter months (Jan 31 + 1 month = Feb 28/29)
+    pub fn add_months(&self, months: i32) -> Date {
+        let total = self.year * 12 + (self.month as i32 - 1) + months;
+        let year = total.div_euclid(12);
+        let month = total.rem_euclid(12) as u32 + 1;
+        Date {
+            year,
+            month,
+            day: self.day.min(days_in_month(year, month)),
+        }
+    }
+
+    // days from `other` to this date, negative if this date is earlier
+    pub fn days_since(&self, other: &Date) -> i64 {
+        self.to_days() - other.to_days()
+    }
+
+    // 0 for Monday to 6 for Sunday (1970-01-01 was a Thursday)
+    pub fn weekday(&self) -> u32 {
+        (self.to_days() + 3).rem_euclid(7) as u32
+    }
+
+    pub fn first_of_month(&self) -> Date {
+        Date { day: 1, ..*self }
+    }
+
+    // days since 1970-01-01 (Howard Hinnant's days_from_civil)
+    fn to_days(self) -> i64 {
+        let y = if self.month <= 2 { self.year - 1 } else { self.year } as i64;
+        let era = y.div_euclid(400);
+        let yoe = y - era * 400;
+        let m = self.month as i64;
+        let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + self.day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146097 + doe - 719468
+    }
+
+    fn from_days(days: i64) -> Date {
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = (yoe + era * 400 + if month <= 2 { 1 } else { 0 }) as i32;
+        Date { year, month, day }
+    }
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Elapsed time as `m:ss`, or `h:mm:ss` from an hour on
+pub fn format_duration(seconds: f64) -> String {
+    let total = seconds.max(0.0).floor() as u64;
+    let (hours, minutes, seconds) = (total / 3600, total / 60 % 60, total % 60);
+    if hours > 0 {
+        format! {"{}:{:02}:{:02}", hours, minutes, seconds}
+    } else {
+        format! {"{}:{:02}", minutes, seconds}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(0.0), "0:00");
+        assert_eq!(format_duration(65.9), "1:05");
+        assert_eq!(format_duration(3725.0), "1:02:05");
+        assert_eq!(format_duration(-3.0), "0:00");
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Value::parse(" 42 "), Value::Number(42.0));
+        assert_eq!(
+            Value::parse("2020-02-29"),
+            Value::Date(Date {
+                year: 2020,
+                month: 2,
+                day: 29
+            })
+        );
+        assert_eq!(Value::parse("2019-02-29"), Value::Text("2019-02-29".to_string()));
+        assert_eq!(Value::parse("inf"), Value::Text("inf".to_string()));
+    }
+
+    #[test]
+    fn test_step_numbers() {
+        assert_eq!(step_text("41", StepUnit::Step(1.0), 1), Some("42".to_string()));
+        assert_eq!(step_text("0.1", StepUnit::Step(0.2), 1), Some("0.3".to_string()));
+        assert_eq!(step_text("1", StepUnit::Step(0.5), -3), Some("-0.5".to_string()));
+        assert_eq!(step_text("apple", StepUnit::Step(1.0), 1), None);
+        assert_eq!(step_text("3", StepUnit::Days, 1), None);
+    }
+
+    #[test]
+    fn test_step_dates() {
+        assert_eq!(step_text("2020-02-28", StepUnit::Days, 1), Some("2020-02-29".to_string()));
+        assert_eq!(step_text("2020-12-31", StepUnit::Days, 1), Some("2021-01-01".to_string()));
+        assert_eq!(step_text("2020-03-01", StepUnit::Days, -1), Some("2020-02-29".to_string()));
+        assert_eq!(step_text("2020-01-31", StepUnit::Months, 1), Some("2020-02-29".to_string()));
+        assert_eq!(step_text("2020-01-15", StepUnit::Months, -1), Some("2019-12-15".to_string()));
+        assert_eq!(step_text("2020-01-15", StepUnit::Step(1.0), 1), None);
+    }
+
+    #[test]
+    fn test_weekdays() {
+        let date = Date::parse("2020-03-18").unwrap();
+        assert_eq!(date.weekday(), 2);
+        assert_eq!(Date::parse("2020-03-22").unwrap().weekday(), 6);
+        assert_eq!(date.days_since(&Date::parse("2020-02-28").unwrap()), 19);
+        assert_eq!(date.first_of_month(), Date::parse("2020-03-01").unwrap());
+    }
+}
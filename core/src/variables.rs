@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::calc::{uses, FormulaValue, Variables};
+use crate::coordinate::Coordinate;
+use crate::value::Value;
+
+// Variables are named constants kept in the session (edited in the Variables panel), which
+// formulas, visibility conditions and style bindings read as `$name`, e.g. `=root-A1 * $tax_rate`,
+// without a cell having to hold them. Whatever reads a variable is re-evaluated when it changes.
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Variable {
+    pub name: String,
+    // as typed in: a number, TRUE/FALSE, or text
+    pub value: String,
+}
+
+// letters, digits and underscores, not starting with a digit
+pub fn is_valid_name(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| !c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+pub fn value_of(text: &str) -> FormulaValue {
+    match Value::parse(text) {
+        Value::Number(n) => FormulaValue::Number(n),
+        _ if text.trim().eq_ignore_ascii_case("true") => FormulaValue::Bool(true),
+        _ if text.trim().eq_ignore_ascii_case("false") => FormulaValue::Bool(false),
+        _ => FormulaValue::Text(text.to_string()),
+    }
+}
+
+// The values of the variables, by name (the first one wins, if a name is taken twice)
+pub fn values(variables: &[Variable]) -> Variables {
+    let mut values = Variables::new();
+    for variable in variables.iter().filter(|v| is_valid_name(&v.name)) {
+        values
+            .entry(variable.name.clone())
+            .or_insert_with(|| value_of(&variable.value));
+    }
+    values
+}
+
+// The names of the variables that were added, removed or changed between `before` and `after`
+pub fn changed(before: &Variables, after: &Variables) -> HashSet<String> {
+    before
+        .keys()
+        .chain(after.keys())
+        .filter(|name| before.get(*name) != after.get(*name))
+        .cloned()
+        .collect()
+}
+
+// The cells whose formulas read any of the variables `changed`
+pub fn readers(
+    formulas: &HashMap<Coordinate, String>,
+    changed: &HashSet<String>,
+) -> HashSet<Coordinate> {
+    if changed.is_empty() {
+        return HashSet::new();
+    }
+    formulas
+        .iter()
+        .filter(|(_, formula)| uses(formula).iter().any(|name| changed.contains(name)))
+        .map(|(coord, _)| coord.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::{formula_cells, recalculate, Values};
+    use crate::coord;
+    use crate::deps::DepGraph;
+    use crate::grammar::Grammar;
+    use std::num::NonZeroU32;
+
+    fn variable(name: &str, value: &str) -> Variable {
+        Variable {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_variables() {
+        let grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(3).unwrap(), NonZeroU32::new(1).unwrap()),
+            coord!("root-A1") => Grammar::input("", "100"),
+            coord!("root-A2") => Grammar::input("", "=root-A1 * (1 + $tax_rate)"),
+            coord!("root-A3") => Grammar::input("", "=IF($show_net, root-A1, root-A2) & $unit"),
+        };
+        let mut session = vec![
+            variable("tax_rate", "0.25"),
+            variable("show_net", "false"),
+            variable("unit", " EUR"),
+            variable("2nd", "1"),
+        ];
+        let before = values(&session);
+        assert_eq!(before.len(), 3);
+        assert_eq!(before["show_net"], FormulaValue::Bool(false));
+
+        let mut values_ = Values::new();
+        let cells: Vec<Coordinate> = formula_cells(&grammars).keys().cloned().collect();
+        let pending = cells.iter().cloned().collect();
+        recalculate(&grammars, &before, &mut values_, &cells, &pending);
+        assert_eq!(values_[&coord!("root-A2")], FormulaValue::Number(125.0));
+        assert_eq!(
+            values_[&coord!("root-A3")],
+            FormulaValue::Text("125 EUR".to_string())
+        );
+
+        // changing the rate recalculates the formula reading it and the ones reading that one
+        session[0].value = "0.5".to_string();
+        session.remove(2);
+        let after = values(&session);
+        let changed = changed(&before, &after);
+        assert_eq!(
+            changed,
+            vec!["tax_rate".to_string(), "unit".to_string()]
+                .into_iter()
+                .collect()
+        );
+        let mut affected = readers(&formula_cells(&grammars), &changed);
+        let mut graph = DepGraph::default();
+        graph.update(&formula_cells(&grammars));
+        affected.extend(graph.dependents(&affected));
+        assert_eq!(
+            affected,
+            vec![coord!("root-A2"), coord!("root-A3")]
+                .into_iter()
+                .collect()
+        );
+
+        let cells: Vec<Coordinate> = affected.iter().cloned().collect();
+        recalculate(&grammars, &after, &mut values_, &cells, &affected);
+        assert_eq!(values_[&coord!("root-A2")], FormulaValue::Number(150.0));
+        // variables that don't exist are "#NAME?"
+        assert_eq!(
+            values_[&coord!("root-A3")],
+            FormulaValue::Error("#NAME?".to_string())
+        );
+    }
+}
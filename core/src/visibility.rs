@@ -0,0 +1,133 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::calc::{evaluate_formula, reads, Values, Variables};
+use crate::coordinate::Coordinate;
+use crate::gauge::source_formula;
+use crate::grammar::Grammar;
+
+// A cell can be shown only while a condition holds (`Style::visible_when`), e.g. a sub-grid
+// that's only there while a toggle is checked. Conditions are formulas (or a cell they read
+// from, like the source of gauges), re-evaluated whenever a cell they read from changes,
+// directly or through other formulas. The cell is hidden (`Style::display`) while its
+// condition is false.
+
+// The visibility condition of every cell that has one, as a formula
+pub fn conditions(grammars: &HashMap<Coordinate, Grammar>) -> HashMap<Coordinate, String> {
+    grammars
+        .iter()
+        .filter_map(|(coord, g)| {
+            g.style
+                .visible_when
+                .as_ref()
+                .map(|condition| (coord.clone(), source_formula(condition)))
+        })
+        .filter(|(_, formula)| !formula.is_empty())
+        .collect()
+}
+
+// The cells whose conditions read from any of `changed`
+pub fn readers(
+    conditions: &HashMap<Coordinate, String>,
+    changed: &HashSet<Coordinate>,
+) -> HashSet<Coordinate> {
+    conditions
+        .iter()
+        .filter(|(_, formula)| reads(formula).iter().any(|c| changed.contains(c)))
+        .map(|(coord, _)| coord.clone())
+        .collect()
+}
+
+// Whether a condition holds, reading formula cells from `values`. A condition that doesn't
+// evaluate to true or false holds, so a mistake in it doesn't make the cell disappear.
+pub fn holds(
+    grammars: &HashMap<Coordinate, Grammar>,
+    variables: &Variables,
+    values: &mut Values,
+    condition: &str,
+) -> bool {
+    evaluate_formula(grammars, variables, values, &source_formula(condition))
+        .as_bool()
+        .unwrap_or(true)
+}
+
+// Shows or hides each of `cells` by its condition (cells without one are shown), returning the
+// ones that were shown or hidden
+pub fn apply_conditions(
+    grammars: &mut HashMap<Coordinate, Grammar>,
+    variables: &Variables,
+    values: &mut Values,
+    cells: &[Coordinate],
+) -> Vec<Coordinate> {
+    let conditions = conditions(grammars);
+    let mut toggled = vec![];
+    for cell in cells {
+        let visible = match conditions.get(cell) {
+            Some(condition) => holds(grammars, variables, values, condition),
+            None => true,
+        };
+        if let Some(grammar) = grammars.get_mut(cell) {
+            if grammar.style.display != visible {
+                grammar.style.display = visible;
+                toggled.push(cell.clone());
+            }
+        }
+    }
+    toggled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use crate::grammar::{Interactive, Kind};
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn test_apply_conditions() {
+        let mut details =
+            Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(1).unwrap());
+        details.style.visible_when = Some("root-A1".to_string());
+        let mut total = Grammar::input("", "120");
+        total.style.visible_when = Some("=root-A3 > 100".to_string());
+        let mut grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(3).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => Grammar {
+                kind: Kind::Interactive("show details".to_string(), Interactive::Toggle(false)),
+                ..Grammar::default()
+            },
+            coord!("root-B1") => details,
+            coord!("root-A2") => Grammar::input("", "50"),
+            coord!("root-A3") => Grammar::input("", "=root-A2 * 2"),
+            coord!("root-B3") => total,
+        };
+        let conditions = conditions(&grammars);
+        assert_eq!(conditions[&coord!("root-B1")], "=root-A1");
+        let changed = vec![coord!("root-A3")].into_iter().collect();
+        assert_eq!(
+            readers(&conditions, &changed),
+            vec![coord!("root-B3")].into_iter().collect()
+        );
+
+        let mut values = Values::new();
+        let cells = vec![coord!("root-B1"), coord!("root-B3")];
+        assert_eq!(
+            apply_conditions(&mut grammars, &Variables::new(), &mut values, &cells),
+            vec![coord!("root-B1"), coord!("root-B3")]
+        );
+        assert!(!grammars[&coord!("root-B1")].style.display);
+
+        // checking the toggle shows the grid again
+        grammars.insert(
+            coord!("root-A1"),
+            Grammar {
+                kind: Kind::Interactive("show details".to_string(), Interactive::Toggle(true)),
+                ..Grammar::default()
+            },
+        );
+        assert_eq!(
+            apply_conditions(&mut grammars, &Variables::new(), &mut values, &cells),
+            vec![coord!("root-B1")]
+        );
+        assert!(grammars[&coord!("root-B1")].style.display);
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+
+use crate::coordinate::{Col, Coordinate, Row};
+
+// zoom levels the fit commands stay within, so a single cell doesn't fill the whole screen and
+// a huge grid doesn't shrink down to nothing
+pub const MIN_ZOOM: f32 = 0.1;
+pub const MAX_ZOOM: f32 = 4.0;
+
+// The size (width, height) of rows `rows` and columns `cols` (first & last, inclusive) of `grid`
+// when shown unzoomed, adding up their sizes. Lines that haven't been resized take `defaults`
+// (column width, row height).
+pub fn content_size(
+    row_heights: &HashMap<Row, f64>,
+    col_widths: &HashMap<Col, f64>,
+    grid: &Coordinate,
+    rows: (u32, u32),
+    cols: (u32, u32),
+    defaults: (f64, f64),
+) -> (f64, f64) {
+    let width = (cols.0..=cols.1)
+        .filter_map(NonZeroU32::new)
+        .map(|col| {
+            *col_widths
+                .get(&Col(grid.clone(), col))
+                .unwrap_or(&defaults.0)
+        })
+        .sum();
+    let height = (rows.0..=rows.1)
+        .filter_map(NonZeroU32::new)
+        .map(|row| {
+            *row_heights
+                .get(&Row(grid.clone(), row))
+                .unwrap_or(&defaults.1)
+        })
+        .sum();
+    (width, height)
+}
+
+// The zoom level at which content of size `content` exactly fits in `viewport`, both
+// (width, height)
+pub fn fit_zoom(content: (f64, f64), viewport: (f64, f64)) -> f32 {
+    if content.0 <= 0.0 || content.1 <= 0.0 {
+        return 1.0;
+    }
+    let zoom = (viewport.0 / content.0).min(viewport.1 / content.1) as f32;
+    zoom.clamp(MIN_ZOOM, MAX_ZOOM)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+
+    #[test]
+    fn test_content_size() {
+        let row_heights = hashmap! {
+            Row(coord!("root"), NonZeroU32::new(2).unwrap()) => 50.0,
+        };
+        let col_widths = hashmap! {
+            Col(coord!("root"), NonZeroU32::new(1).unwrap()) => 200.0,
+            Col(coord!("root-A1"), NonZeroU32::new(2).unwrap()) => 10.0,
+        };
+        assert_eq!(
+            content_size(
+                &row_heights,
+                &col_widths,
+                &coord!("root"),
+                (1, 3),
+                (1, 2),
+                (90.0, 30.0)
+            ),
+            (290.0, 110.0)
+        );
+        assert_eq!(
+            content_size(
+                &row_heights,
+                &col_widths,
+                &coord!("root"),
+                (3, 3),
+                (2, 2),
+                (90.0, 30.0)
+            ),
+            (90.0, 30.0)
+        );
+    }
+
+    #[test]
+    fn test_fit_zoom() {
+        assert_eq!(fit_zoom((400.0, 100.0), (800.0, 600.0)), 2.0);
+        assert_eq!(fit_zoom((1600.0, 300.0), (800.0, 600.0)), 0.5);
+        assert_eq!(fit_zoom((10.0, 10.0), (800.0, 600.0)), MAX_ZOOM);
+        assert_eq!(fit_zoom((100_000.0, 10.0), (800.0, 600.0)), MIN_ZOOM);
+        assert_eq!(fit_zoom((0.0, 0.0), (800.0, 600.0)), 1.0);
+    }
+}
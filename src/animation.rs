@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+// Animated transitions for structural changes: where the cells are on screen is captured
+// before the change is applied, and once it's rendered each cell (found by its key, see
+// `keys::cell_ids`) is put back where it was with a transform that then eases away, so it
+// slides & stretches into place. New cells fade in, and removed ones fade out where they were.
+
+// how long a transition takes
+pub const ANIMATION_MS: u32 = 200;
+// above this many cells, changes snap into place rather than animate
+pub const MAX_ANIMATED_CELLS: usize = 2000;
+
+// Records where the cells of `cells` (element ids by key) are before a change is applied
+pub fn capture(cells: &HashMap<String, String>) {
+    js! {
+        const cells = @{cells};
+        const keys = {};
+        for (const key in cells) {
+            keys[cells[key]] = key;
+        }
+        const before = {};
+        for (const key in cells) {
+            const element = document.getElementById(cells[key]);
+            if (!element) {
+                continue;
+            }
+            // the cell this one is nested in, which carries it along when it moves
+            const outer = element.parentElement && element.parentElement.closest("[id^='cell-']");
+            before[key] = {
+                rect: element.getBoundingClientRect(),
+                node: element.cloneNode(true),
+                parent: outer ? keys[outer.id] : null,
+            };
+        }
+        window.iseAnimationBefore = before;
+    }
+}
+
+// Animates the cells of `cells` from where `capture` found them to where they've been
+// rendered, once the change has been rendered
+pub fn play(cells: &HashMap<String, String>) {
+    js! {
+        const cells = @{cells};
+        const duration = @{ANIMATION_MS};
+        const before = window.iseAnimationBefore || {};
+        window.iseAnimationBefore = null;
+        if (window.matchMedia("(prefers-reduced-motion: reduce)").matches) {
+            return;
+        }
+        requestAnimationFrame(() => {
+            const moves = {};
+            for (const key in cells) {
+                const element = document.getElementById(cells[key]);
+                const old = before[key];
+                if (!element) {
+                    continue;
+                }
+                if (!old) {
+                    element.classList.add("cell-entering");
+                    setTimeout(() => element.classList.remove("cell-entering"), duration);
+                    continue;
+                }
+                const now = element.getBoundingClientRect();
+                const move = {
+                    element: element,
+                    dx: old.rect.left - now.left,
+                    dy: old.rect.top - now.top,
+                    sx: now.width ? old.rect.width / now.width : 1,
+                    sy: now.height ? old.rect.height / now.height : 1,
+                    parent: old.parent,
+                };
+                if (Math.abs(move.dx) >= 0.5 || Math.abs(move.dy) >= 0.5
+                    || Math.abs(move.sx - 1) >= 0.01 || Math.abs(move.sy - 1) >= 0.01) {
+                    moves[key] = move;
+                }
+            }
+            for (const key in moves) {
+                const move = moves[key];
+                // cells nested in a moving cell move along with it
+                if (move.parent && moves[move.parent]) {
+                    continue;
+                }
+                const style = move.element.style;
+                style.transition = "none";
+                style.transformOrigin = "top left";
+                style.transform = "translate(" + move.dx + "px, " + move.dy + "px) scale(" + move.sx + ", " + move.sy + ")";
+                move.element.getBoundingClientRect();
+                style.transition = "transform " + duration + "ms ease-out";
+                style.transform = "";
+                setTimeout(() => {
+                    style.transition = "";
+                    style.transformOrigin = "";
+                }, duration);
+            }
+            for (const key in before) {
+                const old = before[key];
+                const removed = (key) => !(key in cells) || !document.getElementById(cells[key]);
+                if (!removed(key) || (old.parent && removed(old.parent) && before[old.parent])) {
+                    continue;
+                }
+                const ghost = old.node;
+                ghost.removeAttribute("id");
+                ghost.classList.add("cell-leaving");
+                ghost.style.left = old.rect.left + "px";
+                ghost.style.top = old.rect.top + "px";
+                ghost.style.width = old.rect.width + "px";
+                ghost.style.height = old.rect.height + "px";
+                document.body.appendChild(ghost);
+                requestAnimationFrame(() => { ghost.style.opacity = 0; });
+                setTimeout(() => ghost.remove(), duration);
+            }
+        });
+    }
+}
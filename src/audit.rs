@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use crate::coordinate::Coordinate;
+use crate::grammar::{Grammar, Kind, Lookup};
+use crate::search::lookup_target_exists;
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+// Problem is a reference in a session whose target no longer exists (or is only partly there),
+// as shown in the problems panel
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub coord: Coordinate,
+    pub severity: Severity,
+    pub message: String,
+}
+
+// Scans every reference in `grammars` for missing targets:
+// - lookups whose cell, row, column or range parent is gone (errors)
+// - range lookups whose corners fall outside of their grid (warnings)
+// - definitions whose definition grid or rule cells are gone
+// Problems are sorted by severity, then by coordinate.
+pub fn audit(grammars: &HashMap<Coordinate, Grammar>) -> Vec<Problem> {
+    let mut problems = vec![];
+    for (coord, grammar) in grammars.iter() {
+        match &grammar.kind {
+            Kind::Lookup(_, Some(lookup)) => {
+                if !lookup_target_exists(lookup, grammars) {
+                    problems.push(Problem {
+                        coord: coord.clone(),
+                        severity: Severity::Error,
+                        message: format! {"lookup target {} doesn't exist", describe_lookup(lookup)},
+                    });
+                } else if let Lookup::Range { parent, start, end } = lookup {
+                    for corner in [start, end].iter() {
+                        let corner_coord = Coordinate::child_of(parent, **corner);
+                        if !grammars.contains_key(&corner_coord) {
+                            problems.push(Problem {
+                                coord: coord.clone(),
+                                severity: Severity::Warning,
+                                message: format! {"lookup range corner {} is outside of its grid", corner_coord.to_string()},
+                            });
+                        }
+                    }
+                }
+            }
+            Kind::Defn(name, defn_coord, rules) => {
+                if !grammars.contains_key(defn_coord) {
+                    problems.push(Problem {
+                        coord: coord.clone(),
+                        severity: Severity::Error,
+                        message: format! {"definition {} refers to missing grid {}", name, defn_coord.to_string()},
+                    });
+                }
+                for (rule_name, rule_coord) in rules.iter() {
+                    if !grammars.contains_key(rule_coord) {
+                        problems.push(Problem {
+                            coord: coord.clone(),
+                            severity: Severity::Warning,
+                            message: format! {"rule {} of {} refers to missing cell {}", rule_name, name, rule_coord.to_string()},
+                        });
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+    problems.sort_by(|a, b| {
+        a.severity
+            .partial_cmp(&b.severity)
+            .unwrap()
+            .then(a.coord.row_cols.cmp(&b.coord.row_cols))
+    });
+    problems
+}
+
+fn describe_lookup(lookup: &Lookup) -> String {
+    match lookup {
+        Lookup::Cell(c) => c.to_string(),
+        Lookup::Range { parent, .. } => format! {"range in {}", parent.to_string()},
+        Lookup::Row(row) => format! {"row {} of {}", row.1, row.0.to_string()},
+        Lookup::Col(col) => format! {"column {} of {}", col.1, col.0.to_string()},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use crate::style::Style;
+    use crate::util::non_zero_u32_tuple;
+    use std::num::NonZeroU32;
+
+    fn lookup(target: Lookup) -> Grammar {
+        Grammar {
+            name: "".to_string(),
+            style: Style::default(),
+            kind: Kind::Lookup("".to_string(), Some(target)),
+        }
+    }
+
+    #[test]
+    fn test_audit() {
+        let grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => lookup(Lookup::Cell(coord!("root-A2"))),
+            coord!("root-A2") => lookup(Lookup::Cell(coord!("root-D4"))),
+            coord!("root-B1") => lookup(Lookup::Range {
+                parent: coord!("root"),
+                start: non_zero_u32_tuple((1, 1)),
+                end: non_zero_u32_tuple((3, 3)),
+            }),
+            coord!("root-B2") => Grammar::input("", ""),
+        };
+        let problems = audit(&grammars);
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0].coord, coord!("root-A2"));
+        assert_eq!(problems[0].severity, Severity::Error);
+        assert_eq!(problems[1].coord, coord!("root-B1"));
+        assert_eq!(problems[1].severity, Severity::Warning);
+    }
+}
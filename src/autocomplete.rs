@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use crate::coordinate::{Col, Coordinate};
+use crate::grammar::{Grammar, Kind};
+
+// ColumnIndex counts, for every grid column, how many times each distinct value appears in
+// the column's Input cells. It's rebuilt after every update (like `Model::meta_suggestions`)
+// and used to offer completions from values already entered in the same column.
+pub type ColumnIndex = HashMap<Col, HashMap<String, u32>>;
+
+// number of column values offered in the completion dropdown
+pub const COLUMN_COMPLETION_LIMIT: usize = 5;
+
+pub fn build_column_index(grammars: &HashMap<Coordinate, Grammar>) -> ColumnIndex {
+    let mut index = ColumnIndex::new();
+    for (coord, grammar) in grammars.iter() {
+        // top-level grids (root, meta and sheets) don't sit in a column
+        if coord.parent().is_none() {
+            continue;
+        }
+        if let Kind::Input(value) = &grammar.kind {
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+            *index
+                .entry(coord.full_col())
+                .or_insert_with(HashMap::new)
+                .entry(value.to_string())
+                .or_insert(0) += 1;
+        }
+    }
+    index
+}
+
+// Values of `col` that complete `typed` (case-insensitive prefix match), most frequent first.
+// The value being typed doesn't complete itself.
+pub fn column_completions(index: &ColumnIndex, col: &Col, typed: &str, limit: usize) -> Vec<String> {
+    let typed = typed.trim().to_lowercase();
+    let mut completions: Vec<(&String, &u32)> = match index.get(col) {
+        Some(values) => values
+            .iter()
+            .filter(|(value, _)| {
+                let value = value.to_lowercase();
+                value.starts_with(&typed) && value != typed
+            })
+            .collect(),
+        None => return vec![],
+    };
+    // sort by frequency (descending), then alphabetically so the order is stable
+    completions.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+    completions
+        .into_iter()
+        .take(limit)
+        .map(|(value, _)| value.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn test_column_completions() {
+        let grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(4).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => Grammar::input("", "apple"),
+            coord!("root-A2") => Grammar::input("", "Avocado"),
+            coord!("root-A3") => Grammar::input("", "avocado"),
+            coord!("root-A4") => Grammar::input("", "avocado"),
+            coord!("root-B1") => Grammar::input("", "apricot"),
+        };
+        let index = build_column_index(&grammars);
+        let col_a = coord!("root-A1").full_col();
+        assert_eq!(
+            column_completions(&index, &col_a, "a", COLUMN_COMPLETION_LIMIT),
+            vec!["avocado".to_string(), "Avocado".to_string(), "apple".to_string()]
+        );
+        // values from other columns aren't offered
+        assert_eq!(
+            column_completions(&index, &col_a, "apr", COLUMN_COMPLETION_LIMIT),
+            Vec::<String>::new()
+        );
+        // nor is the value that's already been typed out in full
+        assert_eq!(
+            column_completions(&index, &col_a, "apple", COLUMN_COMPLETION_LIMIT),
+            Vec::<String>::new()
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+// JSON-RPC 2.0 error codes
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+// the command was understood but couldn't be carried out
+pub const COMMAND_FAILED: i64 = -32000;
+
+// The automation API lets external tools and test harnesses drive the app over JSON-RPC 2.0.
+// When it's enabled in Settings, the main process (static/main.js) listens on localhost for
+// POSTs carrying `Authorization: Bearer <token>`, and hands their bodies to the renderer, which
+// runs the command and replies with the response to send back.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct AutomationSettings {
+    pub enabled: bool,
+    pub port: u16,
+    pub token: String,
+}
+
+impl Default for AutomationSettings {
+    fn default() -> Self {
+        AutomationSettings {
+            enabled: false,
+            port: 7931,
+            token: String::new(),
+        }
+    }
+}
+
+// Commands of the automation API, by their JSON-RPC method
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    // the text of a cell
+    GetCell { coord: String },
+    // sets the text of an input cell
+    SetCell { coord: String, value: String },
+    // runs a shell command cell
+    RunShell { coord: String },
+    // the cells of a grid as CSV
+    ExportCsv { grid: String },
+    // opens a session file in a new tab
+    OpenSession { path: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: String) -> RpcError {
+        RpcError { code, message }
+    }
+}
+
+// Parses a request into its id and command. The id is `Value::Null` when the request is too
+// broken to have one.
+pub fn parse_request(body: &str) -> (Value, Result<Command, RpcError>) {
+    let request: Value = match serde_json::from_str(body) {
+        Ok(request) => request,
+        Err(e) => return (Value::Null, Err(RpcError::new(PARSE_ERROR, e.to_string()))),
+    };
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    if request.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+        return (id, Err(RpcError::new(INVALID_REQUEST, "not a JSON-RPC 2.0 request".to_string())));
+    }
+    let method = match request.get("method").and_then(Value::as_str) {
+        Some(method) => method,
+        None => return (id, Err(RpcError::new(INVALID_REQUEST, "missing method".to_string()))),
+    };
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+    let param = |name: &str| -> Result<String, RpcError> {
+        match params.get(name) {
+            Some(Value::String(s)) => Ok(s.clone()),
+            // numbers and booleans are set as their text
+            Some(v @ Value::Number(_)) | Some(v @ Value::Bool(_)) => Ok(v.to_string()),
+            _ => Err(RpcError::new(INVALID_PARAMS, format! {"missing string param {}", name})),
+        }
+    };
+    let command = match method {
+        "get_cell" => param("coord").map(|coord| Command::GetCell { coord }),
+        "set_cell" => param("coord").and_then(|coord| param("value").map(|value| Command::SetCell { coord, value })),
+        "run_shell" => param("coord").map(|coord| Command::RunShell { coord }),
+        "export_csv" => param("grid").map(|grid| Command::ExportCsv { grid }),
+        "open_session" => param("path").map(|path| Command::OpenSession { path }),
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, format! {"unknown method {}", method})),
+    };
+    (id, command)
+}
+
+pub fn response(id: Value, result: Result<Value, RpcError>) -> String {
+    let response = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": e.code, "message": e.message } }),
+    };
+    response.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request() {
+        let (id, command) = parse_request(r#"{"jsonrpc": "2.0", "id": 1, "method": "set_cell", "params": {"coord": "root-A1", "value": 42}}"#);
+        assert_eq!(id, json!(1));
+        assert_eq!(
+            command,
+            Ok(Command::SetCell {
+                coord: "root-A1".to_string(),
+                value: "42".to_string(),
+            })
+        );
+        let (_, command) = parse_request(r#"{"jsonrpc": "2.0", "id": "a", "method": "get_cell", "params": {}}"#);
+        assert_eq!(command.unwrap_err().code, INVALID_PARAMS);
+        let (_, command) = parse_request(r#"{"jsonrpc": "2.0", "id": 2, "method": "format_disk"}"#);
+        assert_eq!(command.unwrap_err().code, METHOD_NOT_FOUND);
+        let (_, command) = parse_request(r#"{"id": 3, "method": "get_cell"}"#);
+        assert_eq!(command.unwrap_err().code, INVALID_REQUEST);
+        let (id, command) = parse_request("{");
+        assert_eq!((id, command.unwrap_err().code), (Value::Null, PARSE_ERROR));
+    }
+
+    #[test]
+    fn test_response() {
+        assert_eq!(response(json!(1), Ok(json!("7"))), r#"{"id":1,"jsonrpc":"2.0","result":"7"}"#);
+        assert_eq!(
+            response(json!(2), Err(RpcError::new(COMMAND_FAILED, "nope".to_string()))),
+            r#"{"error":{"code":-32000,"message":"nope"},"id":2,"jsonrpc":"2.0"}"#
+        );
+    }
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+use yew::format::Json;
+use yew::services::storage::StorageService;
+
+use crate::session::Session;
+use crate::workspace::TabView;
+
+// key under which the autosave is kept in localStorage
+const AUTOSAVE_KEY: &str = "ise.autosave";
+
+// Autosave is the tabs with unsaved changes, stored every `Preferences::autosave_interval_s`
+// seconds. Exiting the app cleanly drops it (the workspace keeps every tab then), so one that's
+// still there on launch is from a crash, and the user is offered to recover it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Autosave {
+    // when it was stored, in local time (see `now_local`)
+    pub saved_at: String,
+    pub tabs: Vec<(Session, TabView)>,
+}
+
+impl Autosave {
+    // `None` if there's no autosave, or it can't be read anymore
+    pub fn load(storage: &StorageService) -> Option<Autosave> {
+        let Json(autosave) = storage.restore(AUTOSAVE_KEY);
+        autosave.ok().filter(|a: &Autosave| !a.tabs.is_empty())
+    }
+
+    pub fn store(&self, storage: &mut StorageService) {
+        storage.store(AUTOSAVE_KEY, Json(self));
+    }
+
+    pub fn clear(storage: &mut StorageService) {
+        storage.remove(AUTOSAVE_KEY);
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use crate::coordinate::Coordinate;
+use crate::export::{cell_text, Region};
+use crate::grammar::Grammar;
+use crate::value::Date;
+
+// How much of the calendar is shown at once
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CalendarMode {
+    Month,
+    Week,
+}
+
+// CalendarView plots the rows of a grid on a calendar, on the date in their `date_col`
+// column and labelled with their `title_col` column. `anchor` is a day of the month (or
+// week) shown.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalendarView {
+    pub grid: Coordinate,
+    pub date_col: u32,
+    pub title_col: u32,
+    pub mode: CalendarMode,
+    pub anchor: Date,
+}
+
+// A row of the grid, on the day it's plotted on
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalendarEntry {
+    pub date: Date,
+    pub row: u32,
+    pub title: String,
+}
+
+impl CalendarView {
+    // the month (or week) before or after the one shown
+    pub fn step(&self, count: i32) -> CalendarView {
+        let anchor = match self.mode {
+            CalendarMode::Month => self.anchor.add_months(count),
+            CalendarMode::Week => self.anchor.add_days(7 * i64::from(count)),
+        };
+        CalendarView { anchor, ..self.clone() }
+    }
+
+    // the days shown, in whole weeks from Monday: the weeks of the anchor's month, or its week
+    pub fn days(&self) -> Vec<Date> {
+        let (first, count) = match self.mode {
+            CalendarMode::Month => {
+                let first = self.anchor.first_of_month();
+                let last = first.add_months(1).add_days(-1);
+                let start = first.add_days(-i64::from(first.weekday()));
+                let end = last.add_days(6 - i64::from(last.weekday()));
+                (start, end.days_since(&start) + 1)
+            }
+            CalendarMode::Week => (self.anchor.add_days(-i64::from(self.anchor.weekday())), 7),
+        };
+        (0..count).map(|i| first.add_days(i)).collect()
+    }
+}
+
+// The rows of the grid whose date column holds a date (YYYY-MM-DD), by date
+pub fn entries(
+    grammars: &HashMap<Coordinate, Grammar>,
+    grid: &Coordinate,
+    date_col: u32,
+    title_col: u32,
+) -> Vec<CalendarEntry> {
+    let region = match Region::of_grid(grammars, grid) {
+        Some(region) => region,
+        None => return vec![],
+    };
+    let mut entries: Vec<CalendarEntry> = (region.rows.0..=region.rows.1)
+        .filter_map(|row| {
+            let date = Date::parse(cell_text(grammars, &region.cell(row, date_col)).trim())?;
+            Some(CalendarEntry {
+                date,
+                row,
+                title: cell_text(grammars, &region.cell(row, title_col)),
+            })
+        })
+        .collect();
+    entries.sort_by_key(|e| (e.date.year, e.date.month, e.date.day, e.row));
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn test_entries() {
+        let grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(3).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => Grammar::input("", "standup"),
+            coord!("root-B1") => Grammar::input("", "2020-03-18"),
+            coord!("root-A2") => Grammar::input("", "no date"),
+            coord!("root-B2") => Grammar::input("", "someday"),
+            coord!("root-A3") => Grammar::input("", "review"),
+            coord!("root-B3") => Grammar::input("", "2020-03-02"),
+        };
+        let found = entries(&grammars, &coord!("root"), 2, 1);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].title, "review");
+        assert_eq!(found[0].row, 3);
+        assert_eq!(found[1].date, Date::parse("2020-03-18").unwrap());
+    }
+
+    #[test]
+    fn test_days() {
+        let view = CalendarView {
+            grid: coord!("root"),
+            date_col: 1,
+            title_col: 2,
+            mode: CalendarMode::Month,
+            anchor: Date::parse("2020-03-18").unwrap(),
+        };
+        // March 2020 starts on a Sunday and ends on a Tuesday
+        let days = view.days();
+        assert_eq!(days.len(), 42);
+        assert_eq!(days[0], Date::parse("2020-02-24").unwrap());
+        assert_eq!(days[41], Date::parse("2020-04-05").unwrap());
+
+        let week = CalendarView {
+            mode: CalendarMode::Week,
+            ..view.clone()
+        };
+        assert_eq!(week.days()[0], Date::parse("2020-03-16").unwrap());
+        assert_eq!(week.step(1).anchor, Date::parse("2020-03-25").unwrap());
+        assert_eq!(view.step(-1).anchor, Date::parse("2020-02-18").unwrap());
+    }
+}
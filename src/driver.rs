@@ -0,0 +1,574 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::coordinate::Coordinate;
+use crate::export::{cell_text, Region};
+use crate::grammar::{Grammar, Kind};
+
+// how many entries of a driver's audit log, and lines of its console, are kept
+pub const AUDIT_LIMIT: usize = 200;
+pub const CONSOLE_LIMIT: usize = 500;
+// how many misc files of drivers are uploaded to the main process at once
+pub const UPLOAD_PARALLELISM: usize = 4;
+
+// drivers are evaluated as `ise-driver/{name}.js` (see `wrap_source`), which is how errors
+// thrown from them are told apart
+const SOURCE_PREFIX: &str = "ise-driver/";
+// the globals node integration adds to the window, which drivers run without
+pub const NODE_GLOBALS: [&str; 8] =
+    ["require", "process", "module", "exports", "Buffer", "global", "__dirname", "__filename"];
+
+// Capability is a privileged operation drivers have to declare in their `manifest.json`, and
+// that the user is asked to grant the first time a driver attempts it through the `ise` bridge
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum Capability {
+    WriteCells,
+    Network,
+    ReadFiles,
+}
+
+impl Capability {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Capability::WriteCells => "change cells of the session",
+            Capability::Network => "access the network",
+            Capability::ReadFiles => "read files on this computer",
+        }
+    }
+}
+
+// DriverManifest is the `manifest.json` in a driver's directory. A driver without one
+// declares no capabilities, so it can only read cells.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct DriverManifest {
+    #[serde(default)]
+    pub capabilities: Vec<Capability>,
+}
+
+pub fn parse_manifest(content: &str) -> Result<DriverManifest, String> {
+    serde_json::from_str(content).map_err(|e| format! {"invalid driver manifest: {}", e})
+}
+
+// DriverCall is a request a driver made through the bridge, answered by settling the
+// promise `id` the bridge returned to it
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct DriverCall {
+    pub driver: String,
+    pub id: u32,
+    pub op: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl DriverCall {
+    // the capability the operation needs, if any
+    pub fn capability(&self) -> Result<Option<Capability>, String> {
+        match self.op.as_str() {
+            "getCell" | "subscribe" | "unsubscribe" => Ok(None),
+            // hooks transform the whole session when it's saved or loaded
+            "setCell" | "registerHook" => Ok(Some(Capability::WriteCells)),
+            "fetch" => Ok(Some(Capability::Network)),
+            "readFile" => Ok(Some(Capability::ReadFiles)),
+            op => Err(format! {"unknown operation {}", op}),
+        }
+    }
+}
+
+// The user's answer to a permission prompt, persisted in preferences per driver
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DriverGrant {
+    pub driver: String,
+    pub capability: Capability,
+    pub granted: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decision {
+    Allow,
+    // the user hasn't been asked yet
+    Ask,
+    Block(String),
+}
+
+// Whether `driver` may use `capability`: it has to be declared in its manifest and granted
+pub fn decide(
+    declared: &[Capability],
+    grants: &[DriverGrant],
+    driver: &str,
+    capability: Capability,
+) -> Decision {
+    if !declared.contains(&capability) {
+        return Decision::Block(format! {"{:?} isn't declared in the driver's manifest", capability});
+    }
+    match grants.iter().find(|g| g.driver == driver && g.capability == capability) {
+        Some(grant) if grant.granted => Decision::Allow,
+        Some(_) => Decision::Block(format! {"{:?} was denied", capability}),
+        None => Decision::Ask,
+    }
+}
+
+// Records `grant`, replacing an earlier answer for the same driver & capability
+pub fn set_grant(grants: &mut Vec<DriverGrant>, grant: DriverGrant) {
+    grants.retain(|g| !(g.driver == grant.driver && g.capability == grant.capability));
+    grants.push(grant);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    pub time: String,
+    pub op: String,
+    pub detail: String,
+    // `None` if the operation was allowed, otherwise why it was blocked
+    pub blocked: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConsoleLevel {
+    Log,
+    Warn,
+    Error,
+}
+
+impl ConsoleLevel {
+    // from the name of the `console` method that was called
+    pub fn from_method(method: &str) -> ConsoleLevel {
+        match method {
+            "warn" => ConsoleLevel::Warn,
+            "error" => ConsoleLevel::Error,
+            _ => ConsoleLevel::Log,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsoleLine {
+    pub time: String,
+    pub level: ConsoleLevel,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadStatus {
+    Loading,
+    Loaded,
+    // the driver's script threw while it ran
+    Failed(String),
+}
+
+// Driver is a driver loaded in this window, and what it has done through the bridge
+#[derive(Debug, Clone, PartialEq)]
+pub struct Driver {
+    pub name: String,
+    pub manifest: DriverManifest,
+    pub status: LoadStatus,
+    pub log: Vec<AuditEntry>,
+    // what it wrote to its console, and the errors it didn't catch
+    pub console: Vec<ConsoleLine>,
+}
+
+impl Driver {
+    pub fn new(name: String) -> Driver {
+        Driver {
+            name,
+            manifest: DriverManifest::default(),
+            status: LoadStatus::Loading,
+            log: vec![],
+            console: vec![],
+        }
+    }
+
+    pub fn print(&mut self, line: ConsoleLine) {
+        self.console.push(line);
+        if self.console.len() > CONSOLE_LIMIT {
+            self.console.remove(0);
+        }
+    }
+
+    pub fn audit(&mut self, entry: AuditEntry) {
+        self.log.push(entry);
+        if self.log.len() > AUDIT_LIMIT {
+            self.log.remove(0);
+        }
+    }
+}
+
+// Subscription is a driver's interest in some cells (and everything nested in them), whose
+// changes are sent to it after each action. `values` is the text of the cells as last sent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subscription {
+    pub driver: String,
+    pub id: u32,
+    pub cells: Vec<Coordinate>,
+    pub values: HashMap<Coordinate, String>,
+}
+
+// The cells of a subscription: a coordinate, or a range of cells of the same grid (`root-A1:root-B3`)
+pub fn parse_range(range: &str) -> Result<Vec<Coordinate>, String> {
+    let invalid = || format! {"{} isn't a coordinate or range", range};
+    let mut ends = range.split(':').map(|c| Coordinate::parse(c.trim()));
+    match (ends.next(), ends.next(), ends.next()) {
+        (Some(Some(cell)), None, None) => Ok(vec![cell]),
+        (Some(Some(first)), Some(Some(last)), None) => {
+            let region = Region::from_selection(&first, &last).ok_or_else(invalid)?;
+            let mut cells = vec![];
+            for row in region.rows.0..=region.rows.1 {
+                for col in region.cols.0..=region.cols.1 {
+                    cells.push(region.cell(row, col));
+                }
+            }
+            Ok(cells)
+        }
+        _ => Err(invalid()),
+    }
+}
+
+// The text of the cells in or nested in `cells`, leaving out grids
+pub fn watched_values(
+    grammars: &HashMap<Coordinate, Grammar>,
+    cells: &[Coordinate],
+) -> HashMap<Coordinate, String> {
+    grammars
+        .iter()
+        .filter(|(_, g)| match g.kind {
+            Kind::Grid(_) => false,
+            _ => true,
+        })
+        .filter(|(coord, _)| watches(cells, coord))
+        .map(|(coord, _)| (coord.clone(), cell_text(grammars, coord)))
+        .collect()
+}
+
+fn watches(cells: &[Coordinate], coord: &Coordinate) -> bool {
+    cells.iter().any(|cell| {
+        let depth = cell.row_cols.len();
+        coord.row_cols.len() >= depth && coord.row_cols[..depth] == cell.row_cols[..]
+    })
+}
+
+// The changes to the cells of `subscription` since it was last updated, which it's updated
+// with. Only the cells in `changed` are read again, or every cell it watches if that's `None`.
+pub fn watched_changes(
+    grammars: &HashMap<Coordinate, Grammar>,
+    subscription: &mut Subscription,
+    changed: Option<&HashSet<Coordinate>>,
+) -> Vec<CellChange> {
+    let changed = match changed {
+        Some(changed) => changed,
+        None => {
+            let values = watched_values(grammars, &subscription.cells);
+            let changes = diff(&subscription.values, &values);
+            subscription.values = values;
+            return changes;
+        }
+    };
+    let coords: Vec<&Coordinate> = changed.iter().filter(|c| watches(&subscription.cells, c)).collect();
+    let old: HashMap<Coordinate, String> = coords
+        .iter()
+        .filter_map(|c| subscription.values.get(*c).map(|value| ((*c).clone(), value.clone())))
+        .collect();
+    let new: HashMap<Coordinate, String> = coords
+        .iter()
+        .filter(|c| match grammars.get(**c).map(|g| &g.kind) {
+            Some(Kind::Grid(_)) | None => false,
+            _ => true,
+        })
+        .map(|c| ((*c).clone(), cell_text(grammars, c)))
+        .collect();
+    for coord in coords {
+        match new.get(coord) {
+            Some(value) => subscription.values.insert(coord.clone(), value.clone()),
+            None => subscription.values.remove(coord),
+        };
+    }
+    diff(&old, &new)
+}
+
+// A change sent to a subscribed driver. `old` is null for cells that were added, and `new`
+// for cells that were removed.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CellChange {
+    pub coord: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+pub fn diff(old: &HashMap<Coordinate, String>, new: &HashMap<Coordinate, String>) -> Vec<CellChange> {
+    let mut changed: Vec<&Coordinate> = new
+        .iter()
+        .filter(|(coord, value)| old.get(coord) != Some(value))
+        .map(|(coord, _)| coord)
+        .chain(old.keys().filter(|coord| !new.contains_key(coord)))
+        .collect();
+    changed.sort_by(|a, b| a.row_cols.cmp(&b.row_cols));
+    changed
+        .into_iter()
+        .map(|coord| CellChange {
+            coord: coord.to_string(),
+            old: old.get(coord).cloned(),
+            new: new.get(coord).cloned(),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UploadState {
+    Queued,
+    Uploading,
+    Uploaded,
+    Failed(String),
+}
+
+// UploadBatch is the misc files of a driver being uploaded to the main process, which serves
+// them to the driver's main file
+#[derive(Debug, Clone, PartialEq)]
+pub struct UploadBatch {
+    pub driver: String,
+    pub files: Vec<(String, UploadState)>,
+}
+
+impl UploadBatch {
+    pub fn new(driver: String, names: Vec<String>) -> UploadBatch {
+        UploadBatch {
+            driver,
+            files: names.into_iter().map(|name| (name, UploadState::Queued)).collect(),
+        }
+    }
+
+    pub fn set(&mut self, name: &str, state: UploadState) {
+        if let Some(file) = self.files.iter_mut().find(|(n, _)| n == name) {
+            file.1 = state;
+        }
+    }
+
+    // (files that are done uploading or failed to, all files)
+    pub fn progress(&self) -> (usize, usize) {
+        let finished = self
+            .files
+            .iter()
+            .filter(|(_, state)| match state {
+                UploadState::Uploaded | UploadState::Failed(_) => true,
+                _ => false,
+            })
+            .count();
+        (finished, self.files.len())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        let (finished, total) = self.progress();
+        finished == total
+    }
+
+    // what to tell the user once the batch is finished, and whether it's an error
+    pub fn summary(&self) -> (String, bool) {
+        let failures: Vec<String> = self
+            .files
+            .iter()
+            .filter_map(|(name, state)| match state {
+                UploadState::Failed(e) => Some(format! {"{} ({})", name, e}),
+                _ => None,
+            })
+            .collect();
+        if failures.is_empty() {
+            (format! {"Uploaded {} files of {}", self.files.len(), self.driver}, false)
+        } else {
+            let message = format! {
+                "{} of {} files of {} failed to upload: {}",
+                failures.len(),
+                self.files.len(),
+                self.driver,
+                failures.join(", ")
+            };
+            (message, true)
+        }
+    }
+}
+
+// The name of a driver from its main file, `{name}.js`
+pub fn driver_name(main_file: &str) -> String {
+    main_file.trim_end_matches(".js").to_string()
+}
+
+// The script a driver is evaluated as: a function the bridge calls with the driver's own `ise`
+// bridge, its `console`, a `window` (`globalThis`, `self`) hiding node's globals, and nothing for
+// `NODE_GLOBALS`. Since its code runs in a function, the `var`s and functions it declares stay
+// its own rather than global.
+pub fn wrap_source(name: &str, code: &str) -> String {
+    format! {
+        "(function (ise, console, window, globalThis, self, {globals}) {{\n\
+         {code}\n\
+         }})\n\
+         //# sourceURL={prefix}{file}.js\n",
+        globals = NODE_GLOBALS.join(", "),
+        code = code,
+        prefix = SOURCE_PREFIX,
+        file = name,
+    }
+}
+
+// The driver an error came from, given the file name or stack trace it was reported with
+pub fn driver_of_source(source: &str) -> Option<String> {
+    let start = source.find(SOURCE_PREFIX)? + SOURCE_PREFIX.len();
+    let end = source[start..].find(".js")?;
+    Some(source[start..start + end].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn test_decide() {
+        let manifest = parse_manifest(r#"{"capabilities": ["write-cells", "network"]}"#).unwrap();
+        let mut grants = vec![];
+        assert_eq!(decide(&manifest.capabilities, &grants, "chart", Capability::Network), Decision::Ask);
+        match decide(&manifest.capabilities, &grants, "chart", Capability::ReadFiles) {
+            Decision::Block(_) => (),
+            decision => panic!("undeclared capability should be blocked, got {:?}", decision),
+        }
+
+        let grant = |granted| DriverGrant {
+            driver: "chart".to_string(),
+            capability: Capability::Network,
+            granted,
+        };
+        set_grant(&mut grants, grant(false));
+        assert_eq!(
+            decide(&manifest.capabilities, &grants, "chart", Capability::Network),
+            Decision::Block("Network was denied".to_string())
+        );
+        set_grant(&mut grants, grant(true));
+        assert_eq!(grants.len(), 1);
+        assert_eq!(decide(&manifest.capabilities, &grants, "chart", Capability::Network), Decision::Allow);
+        // grants are per driver
+        assert_eq!(decide(&manifest.capabilities, &grants, "other", Capability::Network), Decision::Ask);
+
+        assert!(parse_manifest(r#"{"capabilities": ["format-disk"]}"#).is_err());
+        assert_eq!(parse_manifest("{}").unwrap().capabilities, vec![]);
+    }
+
+    #[test]
+    fn test_call_capability() {
+        let call: DriverCall =
+            serde_json::from_str(r#"{"driver": "chart", "id": 3, "op": "setCell", "args": ["root-A1", "3"]}"#)
+                .unwrap();
+        assert_eq!(call.capability(), Ok(Some(Capability::WriteCells)));
+        let call = DriverCall { op: "getCell".to_string(), ..call };
+        assert_eq!(call.capability(), Ok(None));
+        let call = DriverCall { op: "exec".to_string(), ..call };
+        assert!(call.capability().is_err());
+        assert_eq!(driver_name("chart.js"), "chart");
+    }
+
+    #[test]
+    fn test_subscriptions() {
+        let mut grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => Grammar::input("", "1"),
+            coord!("root-A2") => Grammar::input("", "2"),
+            coord!("root-B1") => Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(1).unwrap()),
+            coord!("root-B1-A1") => Grammar::input("", "nested"),
+            coord!("root-B2") => Grammar::input("", "outside"),
+        };
+        let cells = parse_range("root-A1:root-B1").unwrap();
+        assert_eq!(cells, vec![coord!("root-A1"), coord!("root-B1")]);
+        assert!(parse_range("root-A1:meta-A1").is_err());
+        assert!(parse_range("A1").is_err());
+
+        let old = watched_values(&grammars, &cells);
+        assert_eq!(old.len(), 2);
+        grammars.insert(coord!("root-A1"), Grammar::input("", "3"));
+        grammars.insert(coord!("root-B2"), Grammar::input("", "ignored"));
+        grammars.remove(&coord!("root-B1-A1"));
+        let new = watched_values(&grammars, &cells);
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                CellChange {
+                    coord: "root-A1".to_string(),
+                    old: Some("1".to_string()),
+                    new: Some("3".to_string()),
+                },
+                CellChange {
+                    coord: "root-B1-A1".to_string(),
+                    old: Some("nested".to_string()),
+                    new: None,
+                },
+            ]
+        );
+        assert!(diff(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn test_watched_changes() {
+        let mut grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(1).unwrap()),
+            coord!("root-A1") => Grammar::input("", "1"),
+            coord!("root-A2") => Grammar::input("", "2"),
+        };
+        let cells = vec![coord!("root-A1"), coord!("root-A2")];
+        let values = watched_values(&grammars, &cells);
+        let mut subscription = Subscription { driver: "chart".to_string(), id: 1, cells, values };
+        grammars.insert(coord!("root-A1"), Grammar::input("", "3"));
+        grammars.insert(coord!("root-A2"), Grammar::input("", "4"));
+        grammars.insert(coord!("root-B1"), Grammar::input("", "unwatched"));
+        // only the cells said to have changed are read again
+        let changed: HashSet<Coordinate> = vec![coord!("root-A1"), coord!("root-B1")].into_iter().collect();
+        assert_eq!(
+            watched_changes(&grammars, &mut subscription, Some(&changed)),
+            vec![CellChange {
+                coord: "root-A1".to_string(),
+                old: Some("1".to_string()),
+                new: Some("3".to_string()),
+            }]
+        );
+        assert_eq!(subscription.values.len(), 2);
+        assert_eq!(watched_changes(&grammars, &mut subscription, Some(&changed)), vec![]);
+        grammars.remove(&coord!("root-A1"));
+        let changes = watched_changes(&grammars, &mut subscription, None);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(subscription.values, hashmap! { coord!("root-A2") => "4".to_string() });
+    }
+
+    #[test]
+    fn test_upload_batch() {
+        let mut batch = UploadBatch::new("chart".to_string(), vec!["a.css".to_string(), "b.png".to_string()]);
+        batch.set("a.css", UploadState::Uploading);
+        assert_eq!(batch.progress(), (0, 2));
+        batch.set("a.css", UploadState::Uploaded);
+        assert!(!batch.is_finished());
+        batch.set("b.png", UploadState::Failed("EACCES".to_string()));
+        assert!(batch.is_finished());
+        assert_eq!(
+            batch.summary(),
+            ("1 of 2 files of chart failed to upload: b.png (EACCES)".to_string(), true)
+        );
+        assert_eq!(UploadBatch::new("chart".to_string(), vec![]).summary().1, false);
+    }
+
+    #[test]
+    fn test_sources() {
+        let source = wrap_source("chart", "draw();");
+        assert!(source.starts_with("(function (ise, console, window, globalThis, self, require, "));
+        assert!(!source.contains("iseBridge"));
+        assert!(source.ends_with("//# sourceURL=ise-driver/chart.js\n"));
+        assert_eq!(
+            driver_of_source("TypeError: x is undefined\n    at draw (ise-driver/chart.js:12:5)"),
+            Some("chart".to_string())
+        );
+        assert_eq!(driver_of_source("http://localhost/index.js"), None);
+
+        let mut driver = Driver::new("chart".to_string());
+        for i in 0..CONSOLE_LIMIT + 1 {
+            driver.print(ConsoleLine {
+                time: String::new(),
+                level: ConsoleLevel::from_method("info"),
+                text: i.to_string(),
+            });
+        }
+        assert_eq!(driver.console.len(), CONSOLE_LIMIT);
+        assert_eq!(driver.console[0].text, "1");
+    }
+}
@@ -0,0 +1,5 @@
+// Exporters that turn (parts of) a session into formats used outside of the app. The ones that
+// don't draw anything live in ise-core.
+pub use ise_core::export::*;
+
+pub mod raster;
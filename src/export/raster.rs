@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use crate::coordinate::{Col, Coordinate, Row};
+use crate::export::{cell_text, Region};
+use crate::grammar::Grammar;
+
+// default sizes of rows & columns that haven't been resized
+const DEFAULT_ROW_HEIGHT: f64 = 30.0;
+const DEFAULT_COL_WIDTH: f64 = 90.0;
+
+// RasterCell is a cell laid out for drawing onto a canvas, in pixels relative to the
+// top-left corner of the exported region
+#[derive(Debug, Clone, PartialEq)]
+pub struct RasterCell {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub text: String,
+    pub font_weight: i32,
+    pub font_color: String,
+    pub border_color: String,
+}
+
+// Lays out the cells of a region the way they're shown in the grid, taking spanning and
+// merged cells into account, but with collapsed outline groups expanded like other exports
+// (see `outline::is_hidden`). Returns the (width, height) of the whole image and its cells.
+pub fn layout(
+    grammars: &HashMap<Coordinate, Grammar>,
+    col_widths: &HashMap<Col, f64>,
+    row_heights: &HashMap<Row, f64>,
+    region: &Region,
+) -> ((f64, f64), Vec<RasterCell>) {
+    let col_width = |col: u32| {
+        *col_widths
+            .get(&region.cell(1, col).full_col())
+            .unwrap_or(&DEFAULT_COL_WIDTH)
+    };
+    let row_height = |row: u32| {
+        *row_heights
+            .get(&region.cell(row, 1).full_row())
+            .unwrap_or(&DEFAULT_ROW_HEIGHT)
+    };
+    let parent_grammar = grammars.get(&region.parent);
+    let mut cells = vec![];
+    let mut y = 0.0;
+    for row in region.rows.0..=region.rows.1 {
+        let mut x = 0.0;
+        for col in region.cols.0..=region.cols.1 {
+            let coord = region.cell(row, col);
+            let covered = parent_grammar
+                .map(|p| p.spanned_by(coord.row_col()).is_some())
+                .unwrap_or(false);
+            match grammars.get(&coord) {
+                Some(g) if g.style.display && !covered => {
+                    // spanning cells stretch over the rows/cols they cover, within the region
+                    let (rows, cols) = parent_grammar
+                        .map(|p| p.span_of(coord.row_col()))
+                        .map(|(r, c)| (r.get(), c.get()))
+                        .unwrap_or((1, 1));
+                    let last_col = (col + cols - 1).min(region.cols.1);
+                    let last_row = (row + rows - 1).min(region.rows.1);
+                    cells.push(RasterCell {
+                        x,
+                        y,
+                        width: (col..=last_col).map(|c| col_width(c)).sum(),
+                        height: (row..=last_row).map(|r| row_height(r)).sum(),
+                        text: cell_text(grammars, &coord),
+                        font_weight: g.style.font_weight,
+                        font_color: g.style.font_color.clone(),
+                        border_color: g.style.border_color.clone(),
+                    });
+                }
+                _ => (),
+            }
+            x += col_width(col);
+        }
+        y += row_height(row);
+    }
+    let width = (region.cols.0..=region.cols.1).map(|c| col_width(c)).sum();
+    ((width, y), cells)
+}
+
+// Draws the cells onto a canvas, then either copies the image to the clipboard
+// or saves it as a PNG file
+pub fn render(size: (f64, f64), cells: &[RasterCell], copy: bool) {
+    let cells: Vec<(f64, f64, f64, f64, String, i32, String, String)> = cells
+        .iter()
+        .map(|c| {
+            (
+                c.x,
+                c.y,
+                c.width,
+                c.height,
+                c.text.clone(),
+                c.font_weight,
+                c.font_color.clone(),
+                c.border_color.clone(),
+            )
+        })
+        .collect();
+    let cells = serde_json::to_string(&cells).unwrap();
+    js! {
+        const cells = JSON.parse(@{cells});
+        const canvas = document.createElement("canvas");
+        // draw at twice the size so text stays crisp on high density screens
+        const scale = 2;
+        canvas.width = @{size.0} * scale;
+        canvas.height = @{size.1} * scale;
+        const ctx = canvas.getContext("2d");
+        ctx.scale(scale, scale);
+        ctx.fillStyle = "white";
+        ctx.fillRect(0, 0, @{size.0}, @{size.1});
+        ctx.textBaseline = "middle";
+        for (const [x, y, width, height, text, fontWeight, fontColor, borderColor] of cells) {
+            ctx.strokeStyle = borderColor;
+            ctx.strokeRect(x + 0.5, y + 0.5, width - 1, height - 1);
+            ctx.save();
+            ctx.beginPath();
+            ctx.rect(x, y, width, height);
+            ctx.clip();
+            ctx.font = fontWeight + " 16px 'Lucida Console', Monaco, monospace";
+            ctx.fillStyle = fontColor;
+            ctx.fillText(text, x + 4, y + height / 2);
+            ctx.restore();
+        }
+        const dataUrl = canvas.toDataURL("image/png");
+        if (@{copy}) {
+            const { clipboard, nativeImage } = require("electron");
+            clipboard.writeImage(nativeImage.createFromDataURL(dataUrl));
+        } else {
+            const link = document.createElement("a");
+            link.href = dataUrl;
+            link.download = "selection.png";
+            link.click();
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use crate::util::non_zero_u32_tuple;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn test_layout() {
+        let mut root = Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(2).unwrap());
+        // A1 spans over B1
+        root.style
+            .spans
+            .push((non_zero_u32_tuple((1, 1)), non_zero_u32_tuple((1, 2))));
+        let grammars = hashmap! {
+            coord!("root") => root,
+            coord!("root-A1") => Grammar::input("", "title"),
+            coord!("root-B1") => Grammar::input("", ""),
+            coord!("root-A2") => Grammar::input("", "a"),
+            coord!("root-B2") => Grammar::input("", "b"),
+        };
+        let col_widths = hashmap! { coord!("root-B1").full_col() => 50.0 };
+        let row_heights = HashMap::new();
+        let region = Region::of_grid(&grammars, &coord!("root")).unwrap();
+        let ((width, height), cells) = layout(&grammars, &col_widths, &row_heights, &region);
+        assert_eq!((width, height), (140.0, 60.0));
+        assert_eq!(cells.len(), 3);
+        assert_eq!(cells[0].text, "title");
+        assert_eq!(cells[0].width, 140.0);
+        assert_eq!((cells[2].x, cells[2].y), (90.0, 30.0));
+    }
+}
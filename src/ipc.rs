@@ -0,0 +1,173 @@
+use electron_sys::ipc_renderer;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use wasm_bindgen::JsValue;
+use yew::Callback;
+
+// Messages between the renderer and Electron's main process (static/main.js) go through two
+// channels, one each way. Their names carry the protocol version, so a renderer and main process
+// that don't speak the same version ignore each other instead of misreading messages.
+pub const REQUEST_CHANNEL: &str = "ise-ipc-v1";
+pub const EVENT_CHANNEL: &str = "ise-ipc-v1-event";
+// requests sent with `invoke` are answered on this channel without blocking the renderer
+pub const INVOKE_CHANNEL: &str = "ise-ipc-v1-invoke";
+
+// Request is a message from the renderer to the main process
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Request {
+    // a misc file of a driver, served under its name by the app protocol. `data` is its content
+    // base64 encoded, since it needn't be text
+    UploadDriverMiscFile { name: String, data: String },
+    SetSpellcheckDictionary { words: Vec<String> },
+    WatchDataSource { path: String },
+    // closes the watcher `WatchDataSource` started
+    UnwatchDataSource { path: String },
+    // replies with the chosen path, or null if the dialog was cancelled
+    ChooseFile { save: bool, filter_name: String, extensions: Vec<String> },
+    // replies with an `Event::ShellFinished` once the command exits
+    RunShell { coord: String, command: String },
+    // replies with the session files opened from the OS before the renderer was ready
+    TakeOpenedFiles,
+    // shows a file in the OS file manager (Finder, Explorer, ...)
+    RevealFile { path: String },
+    // replies with the text at the URL, only once it's fetched, so it's sent with `invoke`
+    FetchUrl { url: String },
+    // a desktop notification, for reminder cells that fire
+    ShowNotification { title: String, body: String },
+    QuitConfirmed,
+    // starts (or restarts) the automation server with these settings, or stops it
+    ConfigureAutomation { enabled: bool, port: u16, token: String },
+    // the JSON-RPC response to an `Event::AutomationCall`
+    AutomationReply { id: u32, response: String },
+}
+
+// Reply of the main process to a request sent with `send_sync`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "status", content = "value", rename_all = "kebab-case")]
+pub enum Reply {
+    Ok(Value),
+    Error(String),
+}
+
+// Event is a message the main process sends the renderer on its own
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Event {
+    ShellFinished { coord: String, succeeded: bool, output: String },
+    DataSourceChanged { path: String },
+    // a session file opened from the OS (double-clicked, or passed on the command line)
+    OpenFile { path: String },
+    ConfirmQuit,
+    // a JSON-RPC request to the automation server, answered with a `Request::AutomationReply`
+    AutomationCall { id: u32, body: String },
+}
+
+pub fn encode(request: &Request) -> String {
+    serde_json::to_string(request).unwrap()
+}
+
+// Decodes the reply to a request, which is `None` if the main process didn't handle it at all
+pub fn decode_reply(reply: Option<String>) -> Result<Value, String> {
+    let reply = reply.ok_or_else(|| "the main process didn't reply".to_string())?;
+    match serde_json::from_str(&reply) {
+        Ok(Reply::Ok(value)) => Ok(value),
+        Ok(Reply::Error(e)) => Err(e),
+        Err(e) => Err(format! {"invalid reply from the main process: {}", e}),
+    }
+}
+
+pub fn decode_event(message: &str) -> Result<Event, String> {
+    serde_json::from_str(message).map_err(|e| format! {"invalid message from the main process: {}", e})
+}
+
+// Sends a request and waits for the main process to reply
+pub fn send_sync(request: &Request) -> Result<Value, String> {
+    let args = [JsValue::from_str(&encode(request))];
+    decode_reply(ipc_renderer.send_sync(REQUEST_CHANNEL, Box::new(args)).as_string())
+}
+
+// Sends a request without blocking, `callback` gets the reply once it comes
+pub fn invoke(request: &Request, callback: Callback<Result<Value, String>>) {
+    let on_reply = move |reply: Option<String>| callback.emit(decode_reply(reply));
+    js! {
+        let on_reply = @{stdweb::Once(on_reply)};
+        require("electron").ipcRenderer.invoke(@{INVOKE_CHANNEL}, @{encode(request)}).then(
+            (reply) => on_reply(reply),
+            (e) => on_reply(JSON.stringify({ status: "error", value: String(e && e.message || e) }))
+        );
+    };
+}
+
+// Sends a request without waiting, for requests answered (if at all) by an `Event`
+pub fn send(request: &Request) {
+    let args = [JsValue::from_str(&encode(request))];
+    ipc_renderer.send(REQUEST_CHANNEL, Box::new(args));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let request = Request::ChooseFile {
+            save: true,
+            filter_name: "Fragment".to_string(),
+            extensions: vec!["json".to_string()],
+        };
+        assert_eq!(
+            encode(&request),
+            r#"{"type":"choose-file","save":true,"filter_name":"Fragment","extensions":["json"]}"#
+        );
+        assert_eq!(encode(&Request::QuitConfirmed), r#"{"type":"quit-confirmed"}"#);
+        assert_eq!(
+            encode(&Request::FetchUrl {
+                url: "https://example.com/a.csv".to_string()
+            }),
+            r#"{"type":"fetch-url","url":"https://example.com/a.csv"}"#
+        );
+        // binary misc files make it through unchanged
+        let content = vec![0x89, b'P', b'N', b'G', 0xff, 0xfe, 0x00];
+        let request = Request::UploadDriverMiscFile {
+            name: "icon.png".to_string(),
+            data: base64::encode(&content),
+        };
+        assert_eq!(
+            encode(&request),
+            r#"{"type":"upload-driver-misc-file","name":"icon.png","data":"iVBOR//+AA=="}"#
+        );
+        assert_eq!(base64::decode("iVBOR//+AA==").unwrap(), content);
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(
+            decode_reply(Some(r#"{"status":"ok","value":"/home/me/a.json"}"#.to_string())),
+            Ok(Value::String("/home/me/a.json".to_string()))
+        );
+        assert_eq!(
+            decode_reply(Some(r#"{"status":"error","value":"EACCES"}"#.to_string())),
+            Err("EACCES".to_string())
+        );
+        assert!(decode_reply(None).is_err());
+        assert!(decode_reply(Some("true".to_string())).is_err());
+
+        assert_eq!(
+            decode_event(r#"{"type":"shell-finished","coord":"root-A1","succeeded":true,"output":"hi"}"#),
+            Ok(Event::ShellFinished {
+                coord: "root-A1".to_string(),
+                succeeded: true,
+                output: "hi".to_string(),
+            })
+        );
+        assert_eq!(
+            decode_event(r#"{"type":"automation-call","id":4,"body":"{}"}"#),
+            Ok(Event::AutomationCall {
+                id: 4,
+                body: "{}".to_string(),
+            })
+        );
+        assert!(decode_event(r#"{"type":"print"}"#).is_err());
+    }
+}
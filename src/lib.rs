@@ -15,14 +15,41 @@ extern crate stdweb;
 #[macro_use]
 extern crate pest_derive;
 
-pub mod coordinate;
-pub mod grammar;
-pub mod grammar_map;
+pub mod animation;
+pub mod audit;
+pub mod autocomplete;
+pub mod automation;
+pub mod autosave;
+pub mod calendar;
+pub mod driver;
+pub mod export;
+pub mod ipc;
 pub mod model;
-pub mod session;
-pub mod style;
+pub mod plugin;
+pub mod preferences;
+pub mod replay;
+pub mod search;
+pub mod search_history;
+pub mod session_info;
+pub mod shell;
+pub mod stats;
+pub mod timeline;
+pub mod transclusion;
 pub mod util;
 pub mod view;
+pub mod workspace;
+
+// the UI-independent modules live in ise-core (core/), and are re-exported so they're still
+// reachable as `crate::coordinate`, `crate::session`, ...
+pub use ise_core::{
+    assets, bindings, calc, cli, clipboard, clipboard_history, coordinate, dedup, defn, deps,
+    diagnostics, entry, external, formula, fragment, gauge, goal_seek, grammar, grammar_map,
+    history, hooks, integrity, keys, lines, locale, macros, meta_import, outline, outline_import,
+    packages, reminders, rename, selection, session, session_merge, shape, style, suggestion,
+    trash, value, variables, visibility, zoom,
+};
+pub use ise_core::{coord, coord_col, coord_row, g, grid, row_col_vec};
+
 pub mod codemirror;
 
 use crate::model::Model;
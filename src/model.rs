@@ -1,5 +1,4 @@
-use electron_sys::ipc_renderer;
-use pest::Parser;
+use std::cell::{Cell, RefCell};
 use std::collections::{HashMap, HashSet};
 extern crate csv;
 use csv::Error;
@@ -8,53 +7,155 @@ use std::iter::IntoIterator;
 use std::num::NonZeroU32;
 use std::ops::Deref;
 use std::option::Option;
+use std::time::Duration;
 use stdweb::traits::IEvent;
 use stdweb::unstable::{TryFrom, TryInto};
 use stdweb::web::{document, IElement, INode, IParentNode};
-use wasm_bindgen::JsValue;
 use yew::events::{KeyDownEvent, KeyPressEvent, KeyUpEvent};
+use yew::format::{Nothing, Text};
 use yew::prelude::*;
 use yew::services::reader::{File, FileData, ReaderService, ReaderTask};
+use yew::services::storage::{Area, StorageService};
+use yew::services::interval::{IntervalService, IntervalTask};
+use yew::services::timeout::{TimeoutService, TimeoutTask};
+use yew::services::fetch::{FetchService, FetchTask, Request, Response};
 use yew::services::ConsoleService;
 
+use crate::assets::{collect_garbage, sidecar_dir, unique_name, Asset, ASSET_PREFIX};
+use crate::autocomplete::{build_column_index, ColumnIndex};
+use crate::bindings::{
+    apply_bindings, bindings as style_bindings, readers as binding_readers,
+    variable_readers as binding_variable_readers,
+};
+use crate::animation;
+use crate::automation::{parse_request, response, Command, RpcError, COMMAND_FAILED};
+use crate::autosave::Autosave;
+use crate::calc::{
+    formula_cells, formula_text, iterate, recalculate, spill, CalcMode, FormulaValue, IterationOutcome,
+    IterationSettings, Spills, Values, Variables, CALC_BATCH,
+};
+use crate::calendar::{entries, CalendarMode, CalendarView};
+use crate::clipboard::{lay_out, parse_tsv, paste_table, to_html, to_tsv, HtmlCell, PastedTable};
+use crate::clipboard_history::{paste_entry, push_entry as push_clip, ClipEntry};
+use crate::deps::DepGraph;
 use crate::coordinate::{Col, Coordinate, Row};
-use crate::grammar::{Grammar, Kind, Lookup};
+use crate::dedup::{duplicate_rows, remove_rows};
+use crate::defn::{
+    bind_arguments, definition_from_region, instantiate, parameters, propagate, range_values,
+    substitute, DefnArguments, Propagation,
+};
+use crate::diagnostics::history_bytes;
+use crate::driver::{
+    decide, diff, driver_name, driver_of_source, parse_manifest, parse_range, set_grant,
+    watched_changes, watched_values, wrap_source, AuditEntry, Capability, ConsoleLevel,
+    ConsoleLine, Decision, Driver, DriverCall, DriverGrant, LoadStatus, Subscription, UploadBatch,
+    UploadState, NODE_GLOBALS, UPLOAD_PARALLELISM,
+};
+use crate::export::delimited::{flatten, to_delimited};
+use crate::export::dot::{to_dot, DotOptions};
+use crate::export::json::to_json;
+use crate::export::markdown::to_markdown;
+use crate::export::schema::{scaffold, to_json_schema};
+use crate::export::{cell_text, raster, Region};
+use crate::external::{csv_url, grid_table, parse_table, to_csv, write_table, ExternalSource};
+use crate::formula::{colored_references, is_formula, parse as parse_formula, Reference};
+use crate::gauge::source_formula;
+use crate::fragment::{extract, graft, parse as parse_fragment, CellSize};
+use crate::goal_seek::{goal_seek, GoalSeek};
+use crate::grammar::{
+    parse_color, Grammar, Interactive, Kind, KindTag, Lookup, ReminderTrigger, ShellOutput,
+};
 use crate::grammar_map::*;
-use crate::session::Session;
-use crate::style::Style;
-use crate::util::{move_grammar, non_zero_u32_tuple, resize, resize_diff};
+use crate::history::{EditKind, History, Snapshot};
+use crate::hooks::{run_hooks, Builtin, Hook, HookSource, Stage};
+use crate::integrity::{orphans, prune_orphans, repair, repair_sizes, Inconsistency};
+use crate::keys::{assign_keys, cell_ids};
+use crate::lines::{
+    duplicate_line, duplicate_sizes, move_line, move_sizes, moved_line, remap_outline_groups, swap_cells,
+    swap_outline_groups, swap_sizes,
+};
+use crate::macros::{arguments, resolve as resolve_cell, substitute as substitute_args, ButtonAction, Macro, MacroBody};
+use crate::meta_import::{import_grammar, import_names, MetaImport};
+use crate::ipc::{self, Event};
+use crate::outline::{is_hidden, OutlineAxis, OutlineGroup};
+use crate::outline_import::{parse_outline, write_outline};
+use crate::packages::{index_location, is_url, resolve, GrammarPack, InstalledPack, PackListing, RegistryIndex};
+use crate::plugin::{cell_values, registry, Plugin};
+use crate::preferences::{MetaGrammar, Preferences};
+use crate::reminders::{check as check_reminders, parse_time, REMINDER_INTERVAL_S};
+use crate::rename::{apply_rename, check_name, usages};
+use crate::replay::{action_of, step_of, Replay, ReplayStep, ReproLog, PLAY_INTERVAL_MS};
+use crate::search::set_style_attr;
+use crate::search_history::{merged_history, push_history, step_cursor, HistoryKind};
+use crate::selection::Selection;
+use crate::session_merge::{MergeChoice, SessionMerge};
+use crate::session::{parse_session, Bookmark, Session, SessionLayout, SessionMetadata, Sheet};
+use crate::session_info::{session_info, SessionInfo};
+use crate::shape::{mismatch, Shape};
+use crate::shell::{command_of, write_output};
+use crate::style::{grid_defaults, nested_defaults, GridDefaults, Style, StyleBindings};
+use crate::suggestion::{category_of, SuggestionCategory};
+use crate::timeline::TimelineView;
+use crate::transclusion::{reload, Transclusion};
+use crate::trash::{push_entry, restore as restore_trash, TrashEntry};
+use crate::util::{
+    append_grid_line, apply_definition_grammar, caret_offset, clear_cell, convert_kind,
+    copy_table_to_clipboard, copy_to_clipboard, draw_reference_overlay, main_scroll, main_size,
+    move_grammar, non_zero_u32_tuple, now_iso, now_local, now_ms, placeholders_of, read_clipboard,
+    resize, resize_cells, resize_diff, row_col_to_string, set_caret, set_main_scroll, today,
+};
+use crate::value::{step_text, StepUnit};
+use crate::variables::{
+    changed as changed_variables, readers as variable_readers, values as variable_values, Variable,
+};
+use crate::visibility::{
+    apply_conditions, conditions as visibility_conditions, readers as visibility_readers,
+};
+use crate::zoom::{content_size, fit_zoom};
 use crate::view::{
-    view_context_menu, view_file_popup, view_grammar, view_menu_bar, view_side_nav, view_tab_bar,
+    view_bindings_dialog, view_button_action_dialog, view_calendar, view_condition_dialog,
+    view_context_menu, view_defn_arguments, view_driver_prompt, view_file_popup,
+    view_focused_grid, view_goal_seek, view_grammar, view_import_url_dialog, view_menu_bar, view_meta_import,
+    view_nested_defaults_dialog, view_notifications, view_propagation, view_recovery_prompt, view_rename_dialog,
+    view_repro_bar, view_script_confirm, view_script_dialog, view_session_info, view_session_merge, view_shape_dialog,
+    view_sheet_bar, view_shell_confirm, view_side_nav, view_split_panes, view_status_bar,
+    view_tab_bar, view_timeline, view_transclude_dialog, view_unsaved_prompt,
+};
+use crate::workspace::{
+    moved_index, remaining_index, untitled_title, TabView, Workspace, WorkspaceTab,
 };
 use crate::{coord, coord_col, coord_row, g, grid, row_col_vec};
 
-#[derive(Parser)]
-#[grammar = "coordinate.pest"]
-pub struct CoordinateParser;
-
 // Model contains the entire state of the application
 #[derive(Debug)]
 pub struct Model {
     // Parts of the application state are described below:
 
     // - `view_root` represents the parent grammar that the view starts rendering from
-    view_root: Coordinate,
+    pub view_root: Coordinate,
+
+    // - `split_view` holds the state of the second viewport when split view is on.
+    //   The focused pane always renders from `view_root` and owns `active_cell`; while the
+    //   other one renders, `rendered_pane` holds its index & active cell (see `view::cell_id`)
+    pub split_view: Option<SplitView>,
+    pub rendered_pane: RefCell<Option<(usize, Option<Coordinate>)>>,
+
+    // - `focused_grid` is the nested grid currently opened in the full-size grid editor, if any
+    pub focused_grid: Option<Coordinate>,
+    // - `calendar` is the grid plotted in the calendar view, and `timeline` the one rendered
+    //   as a timeline, if they're open
+    pub calendar: Option<CalendarView>,
+    pub timeline: Option<TimelineView>,
 
     // - `active_cell`
     pub active_cell: Option<Coordinate>,
     pub focus_cell: Option<Coordinate>,
 
-    // - `first_select_cell` is the top-leftmost cell in a selection
-    // - `last_select_cell` is the bottom-rightmost cell in a selection
-    pub first_select_cell: Option<Coordinate>,
-    pub last_select_cell: Option<Coordinate>,
+    // - `selection` is the selected ranges of cells (see `Selection`)
+    pub selection: Selection,
 
     pub secondary_selections: HashSet<Coordinate>,
 
-    // TODO: are `min_select_cell` and `max_select_cell` still useful
-    pub min_select_cell: Option<Coordinate>,
-    pub max_select_cell: Option<Coordinate>,
-
     // - `shift_key_pressed` is a simple indicator for when shift key is togridled
     pub shift_key_pressed: bool,
 
@@ -66,6 +167,190 @@ pub struct Model {
     // - `meta_suggestions` contains a map of the name of suggestions to the
     //   suggested grammars stored in coord_col!("meta", "A")
     pub meta_suggestions: Vec<(String, Coordinate)>,
+    // - `placeholders` are the placeholder cells left to fill in after a snippet grammar
+    //   has been completed. While it's non-empty, Tab hops between them.
+    pub placeholders: Vec<Coordinate>,
+    // - `history` holds the snapshots that undoable actions can be undone to. Changes to the
+    //   session that don't record a snapshot of their own get one when they're applied (see
+    //   `unrecorded_change`), `typing` being the input cell (by session index) whose edits are
+    //   being recorded as a single one.
+    pub history: History<Snapshot>,
+    typing: Option<(usize, Coordinate)>,
+    // - `history_cursor` is the entry being shown while browsing the history of the go-to box
+    //   or search panel with Up/Down, and `goto_text` what's shown in the go-to box meanwhile
+    pub history_cursor: Option<(HistoryKind, usize)>,
+    // - `formula_edit` is the formula cell being edited, whose references are highlighted on
+    //   the grid until the edit is committed (by moving to another cell) or canceled (Escape)
+    pub formula_edit: Option<Coordinate>,
+    // - `values` are the computed values of formula cells, and `stale` the formulas affected by
+    //   edits made in manual calculation mode that haven't been recalculated yet.
+    //   `calc_inputs` is the text of every input cell as of the last update (edits are found by
+    //   comparing against it), `variable_inputs` the same for the variables of the session, and
+    //   `calc_queue` the formulas left to recalculate in batches (out of `calc_total`), the next
+    //   of which runs in `calc_task`. `deps` is the dependency graph of the formulas as of the
+    //   last update, which finds the formulas an edit affects and the ones in cycles.
+    pub values: Values,
+    pub stale: HashSet<Coordinate>,
+    calc_inputs: HashMap<Coordinate, String>,
+    variable_inputs: Variables,
+    calc_session: Option<usize>,
+    pub deps: DepGraph,
+    pub calc_queue: Vec<Coordinate>,
+    pub calc_total: usize,
+    calc_task: Option<TimeoutTask>,
+    // - `calc_report` is the outcome of the last iterative calculation, if there were cycles
+    pub calc_report: Option<String>,
+    // - `spills` are the cells array formulas spill their values into (see `calc::spill`)
+    pub spills: Spills,
+    // - `visibility_inputs` are the visibility conditions of cells (see `visibility`) as of the
+    //   last update, and `visibility_queue` the cells whose conditions are re-evaluated once the
+    //   formulas they read from have been recalculated. `binding_inputs` & `binding_queue` are
+    //   the same for the style bindings of cells (see `bindings`).
+    visibility_inputs: HashMap<Coordinate, String>,
+    visibility_queue: HashSet<Coordinate>,
+    binding_inputs: HashMap<Coordinate, StyleBindings>,
+    binding_queue: HashSet<Coordinate>,
+    // - `goal_seek` is the state of the goal seek dialog, while it's open
+    pub goal_seek: Option<GoalSeek>,
+    // - `session_info` is the structure report of the current session, while the session info
+    //   dialog is open
+    pub session_info: Option<SessionInfo>,
+    // - `meta_import` is the session file meta grammars are being imported from, while the
+    //   import dialog is open
+    pub meta_import: Option<MetaImport>,
+    // - `session_merge` is the session file being merged into the current session, while the
+    //   merge dialog is open
+    pub session_merge: Option<SessionMerge>,
+    // - `defn_arguments` is the argument prompt of a definition with parameters being
+    //   instantiated, while it's open
+    pub defn_arguments: Option<DefnArguments>,
+    // - `propagation` is the definition whose changes are being propagated to its instances,
+    //   while the propagate changes dialog is open
+    pub propagation: Option<Propagation>,
+    // - `duplicates` are the rows of a grid found to repeat earlier rows, highlighted as a
+    //   preview until they're removed or the preview is cleared
+    pub duplicates: Option<(Coordinate, Vec<u32>)>,
+    // - `transclusions` are the session files `Kind::Transclude` cells show grids from, by path,
+    //   and `transclusion_watchers` the `fs.watch` handles of the paths watched for changes
+    pub transclusions: HashMap<String, Transclusion>,
+    transclusion_watchers: HashMap<String, stdweb::Value>,
+    // - `transclude_form` is the (path, coordinate) typed into the transclude dialog, while it's open
+    pub transclude_form: Option<(String, String)>,
+    // - `rename_form` is the meta grammar being renamed and the new name typed in, while the
+    //   rename dialog is open
+    pub rename_form: Option<(Coordinate, String)>,
+    // - `shape_form` is the cell whose expected shape is being declared and the shape typed in,
+    //   while the expected shape dialog is open
+    pub shape_form: Option<(Coordinate, String)>,
+    // - `nested_defaults_form` is the grid whose nested grid defaults are being set and the
+    //   defaults typed in, while the nested grid defaults dialog is open
+    pub nested_defaults_form: Option<(Coordinate, GridDefaults)>,
+    // - `condition_form` is the cell whose visibility condition is being set and the condition
+    //   typed in, while the visibility condition dialog is open
+    pub condition_form: Option<(Coordinate, String)>,
+    // - `bindings_form` is the cell whose style bindings are being set and the bindings typed
+    //   in, while the style bindings dialog is open
+    pub bindings_form: Option<(Coordinate, StyleBindings)>,
+    // - `script_form` is the macro script being written (or edited), while the script dialog is
+    //   open, and `button_form` the button cell whose action is being set and the action typed
+    //   in, while the button action dialog is open
+    pub script_form: Option<Macro>,
+    pub button_form: Option<(Coordinate, ButtonAction)>,
+    // - `watched_sources` are the paths of the external sources (see `ExternalSource`) watched
+    //   for changes, and `external_conflicts` the bound grids that weren't reimported because
+    //   they were edited by hand, waiting for the user to overwrite or unbind them
+    watched_sources: HashSet<String>,
+    pub external_conflicts: Vec<Coordinate>,
+    // - `import_url_form` is the URL typed into the import from URL dialog and whether the grid
+    //   keeps a link to it, while the dialog is open, and `fetching_sources` the grids whose
+    //   remote CSV is being fetched
+    pub import_url_form: Option<(String, bool)>,
+    pub fetching_sources: HashSet<Coordinate>,
+    // - `shell_confirm` is the shell command cell waiting for the user to allow its command,
+    //   and `running_shells` the ones whose command is running
+    pub shell_confirm: Option<Coordinate>,
+    pub running_shells: HashSet<Coordinate>,
+    // - `script_confirm` is the button cell waiting for the user to allow the script of its macro
+    pub script_confirm: Option<Coordinate>,
+    // - `running_timers` are the timer cells that are running, by session index, all ticked by
+    //   the one `timer_task` (there's none while no timer runs), `last_tick` being when the
+    //   elapsed times were last written (ms since the epoch)
+    pub running_timers: HashSet<(usize, Coordinate)>,
+    timer_task: Option<IntervalTask>,
+    last_tick: f64,
+    // - `reminder_task` checks every `REMINDER_INTERVAL_S` whether reminder cells are due (see
+    //   `reminders`), those waiting on a condition are also checked whenever values change
+    reminder_task: IntervalTask,
+    // - `autosave_task` autosaves the tabs with unsaved changes (there's none while autosaving is
+    //   off), and `recovery` is the autosave left by a crash, until the user recovers or
+    //   discards it. Nothing's autosaved meanwhile, so it isn't overwritten.
+    autosave_task: Option<IntervalTask>,
+    pub recovery: Option<Autosave>,
+    // - `recording` is the repro log being recorded, and the index of the session it records;
+    //   `replay` the one being replayed, played by `replay_task` while it plays. `applying` is
+    //   set while an action is applied, so only the outermost actions are recorded.
+    pub recording: Option<(usize, ReproLog)>,
+    // - `macro_recording` is the macro being recorded: the index of the session it records, the
+    //   name it's saved under and the steps recorded so far (the same ones as repro logs)
+    pub macro_recording: Option<(usize, String, Vec<ReplayStep>)>,
+    pub replay: Option<Replay>,
+    replay_task: Option<IntervalTask>,
+    applying: bool,
+    // - `last_update_ms` is how long the last outermost action took to apply, and `last_view_ms`
+    //   how long the view took to render after it, shown in the Diagnostics panel along with
+    //   `history_bytes`, cached with the history's (records, undo steps, redo steps) it's of
+    pub last_update_ms: f64,
+    pub last_view_ms: Cell<f64>,
+    history_bytes: Cell<Option<((usize, usize, usize), usize)>>,
+    // - `registries` are the indexes of the package registries in preferences, or why they
+    //   couldn't be loaded, and `fetch_tasks` the requests for them and their packs
+    pub registries: HashMap<String, Result<RegistryIndex, String>>,
+    fetch_tasks: Vec<FetchTask>,
+    // - `drivers` are the drivers loaded in this window (see `Driver`), and `driver_prompts`
+    //   the calls of drivers waiting for the user to grant or deny the capability they need.
+    //   `driver_bridge` loads them & talks to them; it's only held here, never on `window`, so
+    //   a driver can't reach any bridge but the one it was handed (see `wrap_source`)
+    pub drivers: Vec<Driver>,
+    pub driver_prompts: Vec<DriverCall>,
+    driver_bridge: stdweb::Value,
+    // - `subscriptions` are the cells drivers asked to be told about changes to (in whichever
+    //   session is current), see `dispatch_subscriptions`. `changed_cells` are the cells whose
+    //   text changed since they were last told: the calc inputs that changed & the formulas
+    //   they affect (see `track_changes`), and ticking timers. It's `None` after switching
+    //   sessions, when every cell they watch has to be read again
+    pub subscriptions: Vec<Subscription>,
+    changed_cells: Option<HashSet<Coordinate>>,
+    // - `driver_hooks` are the hooks drivers registered to transform sessions as they're saved
+    //   & loaded, run along with the builtin ones from preferences (see `run_session_hooks`)
+    pub driver_hooks: Vec<Hook>,
+    // - `trash` holds the rows, columns & nested grids deleted from each session (by session
+    //   index, newest first), restorable until the tab is closed (see `TrashEntry`)
+    pub trash: HashMap<usize, Vec<TrashEntry>>,
+    // - `clipboard_history` holds the cells copied last (newest first) with everything nested
+    //   in them, to paste again into any tab (see `ClipEntry`)
+    pub clipboard_history: Vec<ClipEntry>,
+    // - `next_cell_key` is the last key given to a cell, see `keys::assign_keys`
+    next_cell_key: u64,
+    // - `uploads` are the misc files of drivers being uploaded to the main process, of which
+    //   `upload_queue` are waiting for one of the `uploads_in_flight` to finish
+    pub uploads: Vec<UploadBatch>,
+    upload_queue: Vec<(String, FileData)>,
+    uploads_in_flight: usize,
+    // - `notifications` are shown in the corner of the window until they're dismissed
+    pub notifications: Vec<Notification>,
+    next_notification: u32,
+    // - `plugins` are the Rust plugins compiled into the bundle (see `Plugin`), and
+    //   `plugin_values` the text of every cell as they were last told about it
+    pub plugins: Vec<Box<dyn Plugin>>,
+    plugin_values: HashMap<Coordinate, String>,
+    pub goto_text: Option<String>,
+    // - `pending_guard` is the action waiting on the unsaved changes prompt, if any
+    pub pending_guard: Option<GuardedAction>,
+    // - `structural_query` is the query typed into the search panel (see `StructuralQuery`)
+    pub structural_query: String,
+    // - `column_index` holds the distinct values of each grid column, which are also
+    //   offered as completions when typing into a cell of that column
+    pub column_index: ColumnIndex,
 
     // - `lookups` represent an ordered list of coordinates that have lookups corresponding
     // to them. the indexes are used to generate correspoding color coding for each lookup
@@ -92,19 +377,31 @@ pub struct Model {
     // - `resizing` is an optional reference to the current coordinate being resized
     //    (which is None if no resizing is happening)
     pub resizing: Option<Coordinate>,
+    // - `dragged_row` is the grid and number of the row being dragged by its handle, if any
+    pub dragged_row: Option<(Coordinate, u32)>,
+    //   `resize_before` is the state from before the drag started, recorded as a single undo
+    //   step once it ends
+    resize_before: Option<Snapshot>,
 
     // - `link` is a function of the Yew framework for referring back to the current component
     //    so actions can be chained, for instance
     pub link: ComponentLink<Model>,
 
     // - `default_nested_row_cols` shows the default number of rows and columns
-    //   created by Ctrl+G or the "Nest Grid" button
+    //   created by Ctrl+G or the "Nest Grid" button, from preferences unless the active cell is
+    //   in a grid that sets its own (see `nested_row_cols`)
     // - `default_definition_name` shows the default name of the grammar created
     //   by Ctrl+G the "Add Definition" button
     pub default_nested_row_cols: (NonZeroU32, NonZeroU32),
 
     pub context_menu_position: Option<(f64, f64)>,
 
+    // - `tab_menu` is the tab whose menu is open, and where; `renaming_tab` the tab whose title
+    //   is being edited in the tab bar, and `dragged_tab` the one being dragged to another place
+    pub tab_menu: Option<(usize, (f64, f64))>,
+    pub renaming_tab: Option<usize>,
+    pub dragged_tab: Option<usize>,
+
     pub default_definition_name: String,
 
     // - `mouse_cursor` corresponds to the appearance of the mouse cursor
@@ -117,6 +414,185 @@ pub struct Model {
 
     // - `tasks` are used to store asynchronous requests to read/load files
     pub tasks: Vec<ReaderTask>,
+
+    // - `preferences` are user settings persisted to localStorage through `storage`
+    pub preferences: Preferences,
+    storage: StorageService,
+}
+
+// Notification is a message shown in the corner of the window until it's dismissed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub id: u32,
+    pub text: String,
+    pub error: bool,
+}
+
+// Pane is the state of a viewport that's not currently focused
+#[derive(Debug, Clone)]
+pub struct Pane {
+    pub view_root: Coordinate,
+    pub active_cell: Option<Coordinate>,
+}
+
+// SplitView shows two independent viewports of the same session side by side. The
+// unfocused pane's view root & active cell are parked in `other` and swapped with the
+// model's own when it gets focused.
+#[derive(Debug, Clone)]
+pub struct SplitView {
+    pub other: Pane,
+    // - `focused` is the index of the focused pane (0 = left, 1 = right)
+    pub focused: usize,
+}
+
+impl Action {
+    // whether the action moves cells around, which is animated rather than snapped into place
+    // (see `animation`)
+    fn animates(&self) -> bool {
+        match self {
+            Action::InsertRow
+            | Action::InsertCol
+            | Action::DeleteRow
+            | Action::DeleteCol
+            | Action::DuplicateRow
+            | Action::DuplicateCol
+            | Action::MoveRow(_, _, _)
+            | Action::SwapActiveCell(_, _)
+            | Action::MoveActiveLine(_, _)
+            | Action::MergeCells()
+            | Action::SetCellSpan(_, _)
+            | Action::ToggleOutlineGroup(_)
+            | Action::RestoreTrash(_)
+            | Action::Undo
+            | Action::Redo
+            | Action::ZoomIn
+            | Action::ZoomOut
+            | Action::ZoomToFit
+            | Action::ZoomToSelection
+            | Action::ZoomReset => true,
+            _ => false,
+        }
+    }
+
+    // whether the action changes the contents of the current session, leaving it with unsaved changes
+    fn mutates_session(&self) -> bool {
+        match self {
+            Action::ChangeInput(_, _)
+            | Action::DoCompletion(_, _)
+            | Action::CycleSuggestionCategory(_)
+            | Action::TogglePlaceholder(_)
+            | Action::ConvertKind(_)
+            | Action::Undo
+            | Action::Redo
+            | Action::BulkConvert(_, _)
+            | Action::BulkRestyle(_, _, _)
+            | Action::BulkDelete(_)
+            | Action::ToggleCellSpellcheck(_)
+            | Action::StepValue(_, _, _)
+            | Action::PickReference(_)
+            | Action::SetIterationSettings(_)
+            | Action::RemoveDuplicates
+            | Action::PruneOrphans
+            | Action::RepairIntegrity
+            | Action::ApplyTransclude
+            | Action::ApplyRename
+            | Action::ApplyShape
+            | Action::ApplyNestedDefaults
+            | Action::ResetNestedDefaults
+            | Action::ApplyCondition
+            | Action::ApplyBindings
+            | Action::SaveMacroRecording
+            | Action::SaveScript
+            | Action::RemoveMacro(_)
+            | Action::ApplyButtonAction
+            | Action::AddVariable
+            | Action::RenameVariable(_, _)
+            | Action::SetVariable(_, _)
+            | Action::RemoveVariable(_)
+            | Action::BindExternalSource(_, _)
+            | Action::RefreshExternalSource(_, _)
+            | Action::UnbindExternalSource(_)
+            | Action::UrlFetched(_, _, _, _)
+            | Action::ConfigureShell(_, _, _)
+            | Action::SetColor(_, _)
+            | Action::ConfigureGauge(_, _, _, _)
+            | Action::SetToggle(_, _)
+            | Action::SetRating(_, _)
+            | Action::StepStepper(_, _)
+            | Action::StartTimer(_)
+            | Action::StopTimer(_)
+            | Action::ResetTimer(_)
+            | Action::ConfigureReminder(_, _, _)
+            | Action::ToggleMuteReminders
+            | Action::AddAsset(_, _)
+            | Action::InsertAssetReference(_)
+            | Action::ImportCell
+            | Action::ImportSchema
+            | Action::ImportOutline
+            | Action::RestoreTrash(_)
+            | Action::PackFetched(_, _)
+            | Action::UninstallPack(_)
+            | Action::ApplyMetaImport
+            | Action::ShellFinished(_, _, _)
+            | Action::ClearReference(_)
+            | Action::RetargetReference(_, _)
+            | Action::SetSessionTitle(_)
+            | Action::SetSessionMetadata(_)
+            | Action::AddNestedGrid(_, _)
+            | Action::MoveRow(_, _, _)
+            | Action::SwapActiveCell(_, _)
+            | Action::MoveActiveLine(_, _)
+            | Action::DuplicateRow
+            | Action::DuplicateCol
+            | Action::InsertRow
+            | Action::InsertCol
+            | Action::DeleteRow
+            | Action::DeleteCol
+            | Action::AddSheet
+            | Action::RenameSheet(_, _)
+            | Action::RemoveSheet(_)
+            | Action::RangeDelete()
+            | Action::Lookup(_, _)
+            | Action::MergeCells()
+            | Action::SetCellSpan(_, _)
+            | Action::PasteCells
+            | Action::PasteClip(_)
+            | Action::ToggleAutoExpand(_)
+            | Action::AddOutlineGroup(_)
+            | Action::ToggleOutlineGroup(_)
+            | Action::RemoveOutlineGroup(_)
+            | Action::ToggleLookup(_)
+            | Action::AddDefinition(_, _)
+            | Action::DefinitionFromSelection(_)
+            | Action::ApplyDefnArguments
+            | Action::ApplyPropagation
+            | Action::LoadCSVFile(_, _)
+            | Action::AddBookmark(_)
+            | Action::RemoveBookmark(_)
+            | Action::NewEditor => true,
+            _ => false,
+        }
+    }
+}
+
+// Actions that would lose unsaved changes, and are held back until the user picks
+// save, discard or cancel in the unsaved changes prompt
+#[derive(Debug, Clone)]
+pub enum GuardedAction {
+    Recreate,
+    ReadSession(File),
+    // replaces the current tab's session with a newer copy of its file
+    ReloadSession(Session),
+    // closes the tabs at these indices
+    CloseTabs(Vec<usize>),
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GuardChoice {
+    Save,
+    Discard,
+    Cancel,
 }
 
 #[derive(Debug)]
@@ -144,6 +620,8 @@ pub enum CursorType {
 
 pub enum SelectMsg {
     Start(Coordinate),
+    // starts another range, keeping the ones selected so far (Ctrl+click)
+    Add(Coordinate),
     End(Coordinate),
 }
 
@@ -163,21 +641,334 @@ pub enum Action {
         /* source: */ Coordinate,
         /* destination */ Coordinate,
     ),
+    // collapse/expand a section of the completion dropdown
+    ToggleSuggestionCategory(SuggestionCategory),
+    // move a meta grammar to the next category of the completion dropdown
+    CycleSuggestionCategory(Coordinate),
+    // collapse a nested grid to a summary chip, or expand it again (see `Model::is_collapsed`)
+    ToggleCollapsedGrid(Coordinate),
+    // mark/unmark a cell of a snippet grammar as a placeholder
+    TogglePlaceholder(Coordinate),
+    // convert the active cell into another kind of grammar
+    ConvertKind(KindTag),
+    // undoes the last change to a session (see `History`), or redoes the last one undone
+    Undo,
+    Redo,
+
+    // structural search panel & bulk actions on its results
+    SetStructuralQuery(String),
+    // records the current structural query in the search history
+    CommitStructuralQuery,
+    // jumps to a coordinate typed into the go-to box
+    GoTo(String),
+    BrowseHistory(HistoryKind, /* older: */ bool),
+    BulkConvert(Vec<Coordinate>, KindTag),
+    BulkRestyle(Vec<Coordinate>, /* attribute */ String, /* value */ String),
+    BulkDelete(Vec<Coordinate>),
+
+    // copies the selection (or the grid of the active cell) as a Markdown table
+    CopyAsMarkdown,
+    // copies the selection (or the grid of the active cell) as tab-separated values and as an
+    // HTML table, merged cells included
+    CopyCells,
+    // pastes the table on the clipboard (an HTML one, or tab-separated values) into the active
+    // cell, as a nested grid merging the cells the table merges
+    PasteCells,
+    // pastes a copy from the clipboard history at the active cell, structure and all, or drops
+    // it (or all of them)
+    PasteClip(usize),
+    DiscardClip(usize),
+    ClearClipboardHistory,
+    // renders the selection (or the grid of the active cell) as an image, which is
+    // copied to the clipboard or saved as a PNG
+    ExportImage(/* copy: */ bool),
+    // exports the selection (or the grid of the active cell) as JSON records, headed by its
+    // first row, to the clipboard or a file (see `json::to_json`)
+    ExportJson(/* copy: */ bool),
+
+    // opt a cell in/out of spellchecking
+    ToggleCellSpellcheck(Coordinate),
+    // steps the number or date in an input cell up (1) or down (-1)
+    StepValue(Coordinate, i32, StepUnit),
+    // inserts the coordinate of a clicked cell at the caret of the reference being edited
+    PickReference(Coordinate),
+    // stops highlighting the references of the formula being edited
+    EndFormulaEdit,
+    // recalculates stale formulas (or all of them, if none are stale)
+    Recalculate,
+    // recalculates the next batch of `Model::calc_queue`
+    CalcStep,
+    SetIterationSettings(IterationSettings),
+    // goal seek dialog: opened on the active cell as the target, edited, previewed, then either
+    // applied (setting the input cell to the value found) or closed
+    OpenGoalSeek,
+    UpdateGoalSeek(GoalSeek),
+    PreviewGoalSeek,
+    ApplyGoalSeek,
+    CloseGoalSeek,
+    // highlights the duplicate rows of the grid of the selection, keyed on the selected columns
+    // (or all columns, without a selection)
+    FindDuplicates,
+    RemoveDuplicates,
+    // rows of a grid are dragged by their handle and dropped onto another row of the same grid
+    DragRow(Option<(Coordinate, /* row */ u32)>),
+    MoveRow(Coordinate, /* from */ u32, /* to */ u32),
+    // copies the row (or column) of the active cell, with everything nested in it, into a new
+    // one right after it
+    DuplicateRow,
+    DuplicateCol,
+    // swaps the active cell (with everything nested in it) with its neighbor along `axis`, or
+    // moves its row (or column) over by one, towards the end of the grid if `forward`
+    SwapActiveCell(OutlineAxis, /* forward: */ bool),
+    MoveActiveLine(OutlineAxis, /* forward: */ bool),
+    ClearDuplicates,
+    // removes the grammars no grid refers to anymore (see `integrity::orphans`)
+    PruneOrphans,
+    // repairs what the consistency checker finds (see `integrity::check`)
+    RepairIntegrity,
+    // transclude dialog: turns the active cell into a view of a grid in another session file
+    OpenTransclude,
+    UpdateTransclude(/* path */ String, /* coordinate */ String),
+    ApplyTransclude,
+    CloseTransclude,
+    // rename dialog: renames a meta grammar along with the grammars completed from it and the
+    // definitions referring to it, which are listed before the rename is applied
+    OpenRename(Coordinate),
+    UpdateRename(String),
+    ApplyRename,
+    CloseRename,
+    // expected shape dialog: declares the shape grammars completed into a cell should have (see
+    // `shape::Shape`), an empty shape expects nothing
+    OpenShape(Coordinate),
+    UpdateShape(String),
+    ApplyShape,
+    CloseShape,
+    // nested grid defaults dialog: sets the size of the grids nested in a grid & their cells
+    // (see `Style::nested_defaults`), or resets it to the one from preferences
+    OpenNestedDefaults(Coordinate),
+    UpdateNestedDefaults(GridDefaults),
+    ApplyNestedDefaults,
+    ResetNestedDefaults,
+    CloseNestedDefaults,
+    // visibility condition dialog: sets the formula (or cell) a cell is only shown while it's
+    // true, see `visibility`, an empty condition always shows it
+    OpenCondition(Coordinate),
+    UpdateCondition(String),
+    ApplyCondition,
+    CloseCondition,
+    // style bindings dialog: sets the formulas (or cells) the background, font color and width
+    // of a cell are driven by, see `bindings`
+    OpenBindings(Coordinate),
+    UpdateBindings(StyleBindings),
+    ApplyBindings,
+    CloseBindings,
+    // (re)reads a transcluded session file, when it's first needed and whenever it changes on disk
+    LoadTransclusion(String),
+    // grids bound to CSV/JSON files: the file of the active cell is chosen in a dialog,
+    // and its grid refreshed whenever the file changes on disk (or on demand), unless it was
+    // edited by hand since the last import and overwriting isn't forced
+    ChooseExternalSource,
+    BindExternalSource(Coordinate, /* path */ String),
+    ExternalSourceChanged(/* path */ String),
+    RefreshExternalSource(Coordinate, /* overwrite hand edits */ bool),
+    UnbindExternalSource(Coordinate),
+    // import from URL dialog: imports a remote CSV (like a published Google Sheet, see
+    // `csv_url`) into the active cell, once or keeping a link it's refreshed from. It's fetched
+    // by the main process, which replies with the text or why it couldn't be fetched.
+    OpenImportUrl,
+    UpdateImportUrl(/* url */ String, /* keep link */ bool),
+    ApplyImportUrl,
+    CloseImportUrl,
+    UrlFetched(Coordinate, /* url */ String, /* overwrite hand edits */ bool, Result<String, String>),
+    // shell command cells: a command runs once the user has allowed it in the current tab,
+    // and its output (or error) comes back from the main process when it exits
+    ConfigureShell(Coordinate, /* command */ String, ShellOutput),
+    // sets the color of a color picker cell, `#rgb` or `#rrggbb`
+    SetColor(Coordinate, String),
+    // sets the source (a formula or a cell), min and max of a gauge cell
+    ConfigureGauge(Coordinate, String, f64, f64),
+    // checks or unchecks a toggle cell
+    SetToggle(Coordinate, bool),
+    // gives a rating cell that many stars (the same number again clears it)
+    SetRating(Coordinate, u32),
+    // moves a stepper cell that many steps up, or down when negative
+    StepStepper(Coordinate, i32),
+    // timer cells of the current session: started, stopped and reset from their buttons, and
+    // ticked every second while any of them runs
+    StartTimer(Coordinate),
+    StopTimer(Coordinate),
+    ResetTimer(Coordinate),
+    TimerTick,
+    // reminder cells: their message & trigger are set in the cell (which re-arms them), they're
+    // checked periodically, and all of a session's are muted or unmuted from the Reminders panel
+    ConfigureReminder(Coordinate, /* message */ String, ReminderTrigger),
+    CheckReminders,
+    ToggleMuteReminders,
+    RunShell(Coordinate),
+    ConfirmShell(/* allow */ bool),
+    // macros of the session (see `macros::Macro`): recorded from the actions applied to it, or
+    // written as scripts, and run by the button cells bound to them (see `ButtonAction`). A
+    // script runs once the user has allowed it in the current tab, like shell commands.
+    StartMacroRecording,
+    UpdateMacroRecordingName(String),
+    SaveMacroRecording,
+    CancelMacroRecording,
+    OpenScript(/* the macro to edit, or a new one */ Option<String>),
+    UpdateScript(Macro),
+    SaveScript,
+    CloseScript,
+    RemoveMacro(String),
+    OpenButtonAction(Coordinate),
+    UpdateButtonAction(ButtonAction),
+    ApplyButtonAction,
+    CloseButtonAction,
+    RunButton(Coordinate),
+    ConfirmScript(/* allow */ bool),
+    // variables of the session (see `variables::Variable`), added, renamed, set and removed
+    // by index from the Variables panel
+    AddVariable,
+    RenameVariable(usize, String),
+    SetVariable(usize, /* value */ String),
+    RemoveVariable(usize),
+    ShellFinished(Coordinate, /* succeeded */ bool, /* stdout, or the error */ String),
+    // a JSON-RPC request to the automation server (see `automation::Command`), by the id the
+    // main process answers it under
+    AutomationCall(u32, /* body */ String),
+    // assets of the session (see `Asset`), added from a file and referenced from the active cell
+    ReadAsset(File),
+    AddAsset(FileData, /* mime type */ String),
+    InsertAssetReference(/* asset name */ String),
+    // saves the active cell (with everything nested in it) to a fragment file, or replaces it
+    // with one (see `Fragment`)
+    ExportCell,
+    ImportCell,
+    // saves the formula & lookup dependencies (of the active grid, or the whole session) as a
+    // Graphviz DOT file, with how grids nest when `true`
+    ExportDependencyGraph(bool),
+    // saves a JSON Schema of the active table grid (see `to_json_schema`), or scaffolds a new grid
+    // in the active cell from one
+    ExportSchema,
+    ImportSchema,
+    // replaces the active cell with an outline file (OPML, Markdown or indented text), each level
+    // of it nested a grid deeper (see `write_outline`)
+    ImportOutline,
+    // puts a deletion from the trash of the current session back where it was taken from, or
+    // drops it (or all of them) for good
+    RestoreTrash(usize),
+    DiscardTrash(usize),
+    EmptyTrash,
+    // repro logs for bug reports (see `ReproLog`): the current session is recorded from when
+    // recording starts along with the actions applied to it since, saved to a file, and
+    // replayed from one step by step (or played) into a new tab
+    StartRecording,
+    StopRecording,
+    ExportRepro,
+    ImportRepro,
+    StepReplay,
+    PlayReplay(bool),
+    CloseReplay,
+    // grammar packs: registries are (re)loaded from preferences, then packs are installed into
+    // (or updated in, or removed from) the meta sheet of the session
+    RefreshRegistries,
+    RegistryLoaded(/* registry */ String, Result<String, String>),
+    InstallPack(/* registry */ String, PackListing),
+    PackFetched(/* registry */ String, Result<String, String>),
+    UninstallPack(/* pack name */ String),
+    // meta grammars are imported from another session file: it's chosen, some of its meta
+    // grammars are checked, then copied into the meta sheet (renamed if their name is taken)
+    OpenMetaImport,
+    ToggleMetaImport(Coordinate),
+    ApplyMetaImport,
+    CloseMetaImport,
+    // another copy of the session is merged with the current one: it's chosen, each cell both
+    // changed is resolved (for one conflict, or all of them at once), then the merged session
+    // opens in a new tab
+    OpenSessionMerge,
+    SetMergeChoice(/* conflict, or all of them */ Option<Coordinate>, MergeChoice),
+    ApplySessionMerge,
+    CloseSessionMerge,
+
+    // quick fixes from the problems panel
+    ClearReference(Coordinate),
+    RetargetReference(Coordinate, /* new target */ Coordinate),
+
+    // placeholder-hopping mode, entered after completing a snippet with placeholders
+    NextPlaceholder,
+    ExitPlaceholderMode,
 
     SetActiveMenu(Option<i32>),
 
     AskFileName(),
 
     ReadSession(/* filename: */ File),
+    // opens a session file from the OS or dropped onto the window in a new tab, or reloads it
+    // in its tab if it's already open
+    OpenSessionPath(String),
 
     LoadSession(FileData),
 
     SaveSession(),
+    // writes the current session to a file chosen in a save dialog, leaving the tab on the file
+    // it was saved to or loaded from
+    SaveCopy,
+    // writes the grid being viewed to a .csv (or .tsv) file chosen in a save dialog, with its
+    // nested grids flattened if the preferences say so (see `delimited::flatten`)
+    ExportCsv,
+    // session info dialog: a report of the cells of the current session (see `SessionInfo`),
+    // exportable as JSON
+    OpenSessionInfo,
+    ExportSessionInfo,
+    CloseSessionInfo,
 
     SetSessionTitle(String),
+    SetSessionMetadata(SessionMetadata),
+    // saves the open tabs (& how they're viewed) so they can be restored on the next launch
+    PersistWorkspace,
+    // stores the tabs with unsaved changes (see `Autosave`), and recovers or discards the ones
+    // a crash left on launch
+    Autosave,
+    RecoverAutosave,
+    DiscardAutosave,
+    // tabs, by their index in the tab bar: the tab menu is opened by right-clicking one, and
+    // tabs are reordered by dragging them onto another. New tabs open on a blank session.
+    NewTab,
+    SwitchTab(usize),
+    ShowTabMenu(usize, (f64, f64)),
+    StartRenameTab(usize),
+    RenameTab(usize, String),
+    DuplicateTab(usize),
+    CloseTab(usize),
+    CloseOtherTabs(usize),
+    RevealTabFile(usize),
+    DragTab(Option<usize>),
+    MoveTab(/* from */ usize, /* to */ usize),
+    // bookmarks the current viewport (& selection) under a name
+    AddBookmark(String),
+    RecallBookmark(usize),
+    RemoveBookmark(usize),
+    // sent by electron when the app's about to quit
+    RequestQuit,
+    ResolveGuard(GuardChoice),
     ReadDriverFiles(Vec<File>),
+    LoadDriverManifest(/* driver */ String, FileData, /* main file */ File),
     LoadDriverMainFile(FileData),
-    UploadDriverMiscFile(FileData),
+    UploadDriverMiscFile(/* driver */ String, FileData),
+    DriverFileUploaded(/* driver */ String, /* file name */ String, Result<(), String>),
+    // calls drivers make through the `ise` bridge, which are checked against their manifest
+    // and the capabilities the user granted them (asking if they haven't been asked yet)
+    DriverRequest(DriverCall),
+    ResolveDriverPrompt(/* grant */ bool),
+    RevokeDriverGrant(/* driver */ String, Capability),
+    // what drivers write to their console, the errors they don't catch (reported with the
+    // file name or stack trace they came from) and whether their script ran
+    DriverConsole(/* driver */ String, ConsoleLevel, String),
+    DriverError(/* source */ String, /* message */ String),
+    DriverLoaded(/* driver */ String, /* error, empty if it ran */ String),
+    ClearDriverConsole(/* driver */ String),
+    Notify(String, /* error */ bool),
+    DismissNotification(u32),
+    // runs a command contributed by a plugin (see `Plugin::contribute_commands`)
+    RunPluginCommand(/* plugin index */ usize, /* command id */ &'static str),
 
     // Grid Operations
     AddNestedGrid(Coordinate, (u32 /*rows*/, u32 /*cols*/)),
@@ -189,20 +980,62 @@ pub enum Action {
     Recreate,
     ZoomIn,
     ZoomOut,
+    // zooms so the whole grid being viewed, or the selection, fits in the window
+    ZoomToFit,
+    ZoomToSelection,
     ZoomReset,
 
+    // Split View
+    ToggleSplitView,
+    FocusPane(/* index: 0 = left, 1 = right */ usize),
+    SetViewRoot(/* pane */ usize, Coordinate),
+
+    // Sheets (extra top-level grids of a session)
+    AddSheet,
+    SwitchSheet(Coordinate),
+    RenameSheet(Coordinate, /* name */ String),
+    RemoveSheet(Coordinate),
+
+    // Full-size editor for a nested grid
+    OpenFocusedGrid(Coordinate),
+    CloseFocusedGrid,
+    // calendar view of the grid of the active cell, on the dates in the active cell's column
+    OpenCalendar,
+    UpdateCalendar(CalendarView),
+    // jumps from a calendar entry to the first cell of its row
+    JumpFromCalendar(/* row */ u32),
+    CloseCalendar,
+    // timeline of the grid of the active cell, from the dates in the active cell's column to
+    // the ones in the next column
+    OpenTimeline,
+    UpdateTimeline(TimelineView),
+    JumpFromTimeline(/* row */ u32),
+    CloseTimeline,
+
     NewEditor,
 
     Resize(ResizeMsg),
     SetCursorType(CursorType),
     Select(SelectMsg),
     RangeDelete(),
+    // sets a style attribute of every selected cell, like `BulkRestyle`
+    RestyleSelection(/* attribute */ String, /* value */ String),
 
     Lookup(
         /* source: */ Coordinate,
         /* lookup_type: */ Lookup,
     ),
     MergeCells(),
+    // stretch a cell over (rows, cols) of its parent grid, hiding the cells it covers
+    SetCellSpan(Coordinate, (NonZeroU32 /*rows*/, NonZeroU32 /*cols*/)),
+    // turn on/off growing a grid when its last row/col gets typed into
+    ToggleAutoExpand(/* grid */ Coordinate),
+
+    // Outline Operations
+    // group the selected rows/cols so they can be collapsed to their first row/col
+    AddOutlineGroup(OutlineAxis),
+    ToggleOutlineGroup(/* index into Session::outline_groups */ usize),
+    RemoveOutlineGroup(usize),
 
     ChangeDefaultNestedGrid((NonZeroU32, NonZeroU32)),
 
@@ -212,9 +1045,26 @@ pub enum Action {
     ToggleLookup(Coordinate),
 
     AddDefinition(Coordinate, /* name */ String),
+    // wraps the selection (headers & the cells beside them) into a definition on the meta sheet,
+    // and replaces it with an instance of the definition
+    DefinitionFromSelection(/* name */ String),
+    // argument prompt of a definition with parameters, opened when it's completed into a cell:
+    // edited, then applied (stamping the definition with the arguments substituted) or closed
+    UpdateDefnArguments(DefnArguments),
+    ApplyDefnArguments,
+    CloseDefnArguments,
+    // propagate changes dialog of a definition on the meta sheet: previews how each instance
+    // changes when it's stamped again, instances can be opted out, then the rest are re-stamped
+    OpenPropagation(Coordinate),
+    TogglePropagation(Coordinate),
+    ApplyPropagation,
+    ClosePropagation,
 
     TogridleShiftKey(bool),
 
+    // Preferences
+    SetPreferences(Preferences),
+
     // Alerts and stuff
     Alert(String),
 
@@ -239,748 +1089,5414 @@ impl Model {
         &mut self.sessions[self.current_session_index]
     }
 
+    // the key per-user state of the current session is kept under in the preferences: its file,
+    // or its title while it's unsaved
+    pub fn session_key(&self) -> String {
+        let session = self.get_session();
+        session.view.file_path.clone().unwrap_or_else(|| session.title.clone())
+    }
+
+    // Whether a nested grid is collapsed to a summary chip, which is rendered instead of its
+    // cells. It's up to each user, so it's kept in the preferences rather than the session.
+    pub fn is_collapsed(&self, coord: &Coordinate) -> bool {
+        coord.parent().is_some()
+            && self
+                .preferences
+                .collapsed_grids
+                .get(&self.session_key())
+                .map_or(false, |grids| grids.contains(&coord.to_string()))
+    }
+
+    // the nested grid the context menu collapses or expands: the active cell if it's a nested
+    // grid, otherwise the nested grid it's in
+    pub fn collapse_target(&self) -> Option<Coordinate> {
+        let active = self.active_cell.clone()?;
+        let is_grid = |c: &Coordinate| match self.get_session().grammars.get(c).map(|g| &g.kind) {
+            Some(Kind::Grid(_)) => c.parent().is_some(),
+            _ => false,
+        };
+        if is_grid(&active) {
+            return Some(active);
+        }
+        active.parent().filter(|p| is_grid(p))
+    }
+
+    // the values of the variables of the current session, which formulas read as `$name`
+    pub fn variables(&self) -> Variables {
+        variable_values(&self.get_session().variables)
+    }
+
     // only use this if you need a COPY of the current session
     // i.e. not changing its values
     pub fn to_session(&self) -> Session {
         self.get_session().clone()
     }
 
-    fn load_session(&mut self, session: Session) {
+    // a copy of the current session to save, along with how it's laid out
+    fn saved_session(&self) -> Session {
+        let mut session = self.to_session();
+        session.layout = SessionLayout {
+            col_widths: self.col_widths.iter().map(|(col, w)| (col.clone(), *w)).collect(),
+            row_heights: self.row_heights.iter().map(|(row, h)| (row.clone(), *h)).collect(),
+            zoom: self.zoom,
+            active_cell: self.active_cell.clone(),
+        };
+        session
+    }
+
+    // lays the current session out like it was saved, before it's repaired so the rows and
+    // columns the layout doesn't size get the default sizes
+    fn apply_layout(&mut self, layout: SessionLayout) {
+        self.col_widths.extend(layout.col_widths);
+        self.row_heights.extend(layout.row_heights);
+        self.zoom = layout.zoom;
+        self.get_session_mut().view.zoom = layout.zoom;
+        if let Some(active) = layout.active_cell {
+            if self.get_session().grammars.contains_key(&active) {
+                self.active_cell = Some(active);
+            }
+        }
+    }
+
+    fn load_session(&mut self, mut session: Session) {
+        let follow_ups = self.run_session_hooks(Stage::PostLoad, &mut session);
+        let layout = std::mem::take(&mut session.layout);
         // self.get_session_mut().title = session.title;
         self.get_session_mut().root = session.root;
         self.get_session_mut().meta = session.meta;
         self.get_session_mut().grammars = session.grammars;
+        self.get_session_mut().outline_groups = session.outline_groups;
+        self.get_session_mut().sheets = session.sheets;
+        self.get_session_mut().metadata = session.metadata;
+        self.get_session_mut().bookmarks = session.bookmarks;
+        self.get_session_mut().iteration = session.iteration;
+        self.get_session_mut().external_sources = session.external_sources;
+        self.get_session_mut().assets = session.assets;
+        self.get_session_mut().packages = session.packages;
+        self.get_session_mut().macros = session.macros;
+        self.get_session_mut().mute_reminders = session.mute_reminders;
+        self.get_session_mut().variables = session.variables;
+        self.apply_layout(layout);
+        // formulas are recalculated from scratch
+        self.calc_session = None;
+        self.repair_on_load();
+        self.run_hook_follow_ups(follow_ups);
     }
 
-    fn query_parent(&self, coord_parent: Coordinate) -> Vec<Coordinate> {
-        self.get_session()
-            .grammars
-            .keys()
-            .clone()
-            .filter_map(|k| {
-                if k.parent() == Some(coord_parent.clone()) {
-                    Some(k.clone())
-                } else {
-                    None
-                }
-            })
-            .collect()
+    // Runs the hooks of `stage` over `session` (see `hooks::run_hooks`): the builtin ones from
+    // preferences along with the ones drivers registered. Failing hooks are reported, and the
+    // builtins the app carries out itself are returned, to be run once the session is in place.
+    fn run_session_hooks(&mut self, stage: Stage, session: &mut Session) -> Vec<Builtin> {
+        let mut hooks = self.preferences.hooks.clone();
+        hooks.extend(self.driver_hooks.iter().cloned());
+        let mut follow_ups = vec![];
+        let bridge = &self.driver_bridge;
+        let failures = run_hooks(&hooks, stage, session, |hook, session| match &hook.source {
+            HookSource::Builtin(builtin) if builtin.is_pure() => builtin.apply(session),
+            HookSource::Builtin(builtin) => {
+                follow_ups.push(*builtin);
+                Ok(())
+            }
+            HookSource::Driver(driver) => run_driver_hook(bridge, driver, &hook.name, session),
+        });
+        for (name, e) in failures {
+            let message = format! {"The \"{}\" hook failed {}, and was skipped: {}", name, stage.label(), e};
+            self.update(Action::Notify(message, true));
+        }
+        follow_ups
     }
 
-    fn query_col(&self, coord_col: Col) -> Vec<Coordinate> {
-        self.get_session()
-            .grammars
-            .keys()
-            .clone()
-            .filter_map(|k| {
-                if k.row_cols.len() == 1
-                /* ignore root & meta */
-                {
-                    None
-                } else if k.full_col() == coord_col {
-                    Some(k.clone())
-                } else {
-                    None
+    fn run_hook_follow_ups(&mut self, follow_ups: Vec<Builtin>) {
+        for builtin in follow_ups {
+            match builtin {
+                Builtin::Recalculate => {
+                    self.update(Action::Recalculate);
                 }
-            })
-            .collect()
+                Builtin::RefreshExternalSources => {
+                    let grids: Vec<Coordinate> = self
+                        .get_session()
+                        .external_sources
+                        .iter()
+                        .map(|source| source.grid.clone())
+                        .collect();
+                    for grid in grids {
+                        self.update(Action::RefreshExternalSource(grid, false));
+                    }
+                }
+                _ => (),
+            }
+        }
     }
 
-    // Gotta move
-
-    fn query_row(&self, coord_row: Row) -> Vec<Coordinate> {
-        self.get_session()
-            .grammars
-            .keys()
-            .clone()
-            .filter_map(|k| {
-                if k.row_cols.len() == 1
-                /* ignore root & meta */
-                {
-                    None
-                } else if k.full_row() == coord_row {
-                    Some(k.clone())
-                } else {
-                    None
-                }
-            })
-            .collect()
+    // runs the consistency checker over the current session (see `integrity::check`), repairing
+    // what it finds
+    fn repair_integrity(&mut self) -> Vec<Inconsistency> {
+        let defaults = (self.preferences.default_row_height, self.preferences.default_col_width);
+        let mut grammars = std::mem::take(&mut self.get_session_mut().grammars);
+        let repaired = repair(&mut grammars, &mut self.row_heights, &mut self.col_widths, defaults);
+        self.get_session_mut().grammars = grammars;
+        repaired
     }
-    fn reassign(
-        &mut self,
-        coord: Coordinate,
-        mut grammars: HashMap<Coordinate, Grammar>,
-        i: i32,
-    ) -> HashMap<Coordinate, Grammar> {
-        // let mut grammars = self.get_session_mut().grammars.clone();
-        let new_parent: Coordinate;
-        if coord.col().get() == 1 || coord.row().get() == 1 {
-            return grammars;
+
+    // sessions are checked as they're loaded: the row & column sizes missing for them (which
+    // aren't saved) are filled in, and anything else is reported, to be repaired from the
+    // diagnostics panel once the user agrees to, as repairing can remove grammars
+    fn repair_on_load(&mut self) {
+        let defaults = (self.preferences.default_row_height, self.preferences.default_col_width);
+        let session = &self.sessions[self.current_session_index];
+        let found = repair_sizes(&session.grammars, &mut self.row_heights, &mut self.col_widths, defaults);
+        if !found.is_empty() {
+            let text = format! {"Found {} inconsistencies in {}, see Diagnostics to repair them", found.len(), session.title};
+            self.update(Action::Notify(text, true));
         }
-        // height and width initial value
-        // let mut tmp_heigth = 30.0;
-        // let mut tmp_width = 90.0;
+    }
 
-        if let Some(Grammar {
-            kind: Kind::Grid(sub_coords),
-            name: _,
-            style: _,
-        }) = &mut grammars.get(&coord.clone())
-        {
-            match i {
-                0 => {
-                    new_parent = Coordinate::child_of(
-                        &coord.parent().unwrap(),
-                        (NonZeroU32::new(coord.row().get() - 1).unwrap(), coord.col()),
-                    )
+    // (re)builds the current session's root and meta grids from the layout in preferences
+    pub fn bootstrap_session(&mut self) {
+        self.calc_session = None;
+        let prefs = self.preferences.clone();
+        let (rows, cols) = (prefs.default_rows.max(1), prefs.default_cols.max(1));
+        let mut map = HashMap::new();
+        build_grammar_map(
+            &mut map,
+            coord!("root"),
+            MapEntry::Grid(
+                (1..=rows)
+                    .map(|row| {
+                        (1..=cols)
+                            .map(|col| {
+                                let name = row_col_to_string((row, col));
+                                Box::new(g!(Grammar::input("".to_string(), name)))
+                            })
+                            .collect()
+                    })
+                    .collect(),
+            ),
+        );
+        let mut meta_entries = vec![
+            vec![Box::new(g!(Grammar::input("", "A1")))],
+            vec![Box::new(g!(Grammar::input("", "A2")))],
+        ];
+        for meta_grammar in prefs.default_meta_grammars.iter() {
+            match meta_grammar {
+                MetaGrammar::Button => {
+                    meta_entries.push(vec![Box::new(g!(Grammar::default_button()))]);
                 }
-                _ => {
-                    new_parent = Coordinate::child_of(
-                        &coord.parent().unwrap(),
-                        (coord.row(), NonZeroU32::new(coord.col().get() - 1).unwrap()),
-                    )
+                MetaGrammar::Slider => {
+                    meta_entries.push(vec![Box::new(g!(Grammar::default_slider()))]);
+                }
+                MetaGrammar::Toggle => {
+                    meta_entries.push(vec![Box::new(g!(Grammar::default_toggle()))]);
+                }
+                MetaGrammar::ColorPicker => {
+                    meta_entries.push(vec![Box::new(g!(Grammar::default_color_picker()))]);
                 }
+                MetaGrammar::Gauge => {
+                    meta_entries.push(vec![Box::new(g!(Grammar::default_gauge()))]);
+                }
+                MetaGrammar::Rating => {
+                    meta_entries.push(vec![Box::new(g!(Grammar::default_rating()))]);
+                }
+                MetaGrammar::Stepper => {
+                    meta_entries.push(vec![Box::new(g!(Grammar::default_stepper()))]);
+                }
+                MetaGrammar::Timer => {
+                    meta_entries.push(vec![Box::new(g!(Grammar::default_timer()))]);
+                }
+                MetaGrammar::Reminder => {
+                    meta_entries.push(vec![Box::new(g!(Grammar::default_reminder()))]);
+                }
+                MetaGrammar::Shell => {
+                    meta_entries.push(vec![Box::new(g!(Grammar::default_shell()))]);
+                }
+                // the definition grammar is applied once the meta grid has been built
+                MetaGrammar::Definition => {
+                    meta_entries.push(vec![Box::new(g!(Grammar::default()))]);
+                }
+            }
+        }
+        for plugin in self.plugins.iter() {
+            for grammar in plugin.contribute_grammars() {
+                meta_entries.push(vec![Box::new(g!(grammar))]);
             }
+        }
+        let definition_row = prefs
+            .default_meta_grammars
+            .iter()
+            .position(|g| *g == MetaGrammar::Definition)
+            .map(|i| (i + 3) as u32);
+        build_grammar_map(&mut map, coord!("meta"), MapEntry::Grid(meta_entries));
+        assert!(map.contains_key(&(coord!("root"))));
+
+        let session = self.get_session_mut();
+        session.root = map[&coord!("root")].clone();
+        session.root.name = "root".to_string();
+        session.meta = map[&coord!("meta")].clone();
+        session.meta.name = "meta".to_string();
+        session.grammars = map;
+        session.outline_groups = vec![];
+        session.sheets = vec![];
+        session.bookmarks = vec![];
+        session.iteration = IterationSettings::default();
+        session.external_sources = vec![];
+        session.assets = vec![];
+        session.packages = vec![];
+        session.metadata = SessionMetadata {
+            created: now_iso(),
+            ..SessionMetadata::default()
+        };
 
-            // take old parent coord to copy its grammar into the new_parent coordinate for each sub_coordinates
-            let mut grammar_copy = grammars.clone();
-            // let current_width = grammar_copy[&coord.clone()].style.width;
-            // let current_height = grammar_copy[&coord.clone()].style.height;
-            // // check if active cell row height and width is greater than default value
-            // if current_width > tmp_width {
-            //     // set height argument to active cell height if greater
-            //     //Get the actual amount of cell being created and use it instead of "3" being HARD CODED.
-            //     tmp_width = current_width / 3.0;
-            // }
-            // if current_height > tmp_heigth {
-            //     // set width argument to active cell width if greater
-            //     //Get the actual amount of cell being created and use it instead of "3" being HARD CODED.
-            //     tmp_heigth = current_height / 3.0;
-            // }
+        self.col_widths = HashMap::new();
+        self.row_heights = HashMap::new();
+        for row in 1..=rows {
+            self.row_heights.insert(
+                Row(coord!("root"), NonZeroU32::new(row).unwrap()),
+                prefs.default_row_height,
+            );
+        }
+        for col in 1..=cols {
+            self.col_widths.insert(
+                Col(coord!("root"), NonZeroU32::new(col).unwrap()),
+                prefs.default_col_width,
+            );
+        }
+        self.col_widths.insert(coord_col!("meta", "A"), 180.0);
+        if let Some(row) = definition_row {
+            apply_definition_grammar(
+                self,
+                Coordinate::child_of(&coord!("meta"), non_zero_u32_tuple((row, 1))),
+            );
+        }
 
-            // let mut rows = 0;
-            // let mut cols = 0;
+        self.view_root = coord!("root");
+        self.active_cell = Some(coord!("root-A1"));
+        self.focus_cell = None;
+        self.selection.clear();
+    }
 
-            for child_ in sub_coords {
-<<<<<<< HEAD
-                let old_coord = Coordinate::child_of(&coord, *child_);
-                let new_coord = Coordinate::child_of(&new_parent, *child_);
-                grammar_copy.insert(
-                    new_coord.clone(),
-                    grammar_copy
-                        .get(&old_coord.clone())
-                        .clone()
-                        .unwrap()
-                        .clone(),
-                );
-=======
-                
-                let old_coord = Coordinate::child_of(&Coord, *child_);
-                // info!("Old {:?}", old_coord);
-                let new_coord = Coordinate::child_of(&new_parent, *child_);
-                // info!("New {:?}", new_coord);
-                grammar_copy.insert(new_coord.clone(), grammar_copy.get(&old_coord.clone()).clone().unwrap().clone());
->>>>>>> hieule/fix_bug
-                grammar_copy.remove(&old_coord);
+    // the part of the session exports apply to: the (primary) selected range,
+    // or the whole grid of the active cell if nothing's selected
+    pub fn export_region(&self) -> Option<Region> {
+        match self.selection.primary() {
+            Some(region) => Some(region.clone()),
+            None => self
+                .active_cell
+                .clone()
+                .and_then(|c| c.parent())
+                .and_then(|grid| Region::of_grid(&self.get_session().grammars, &grid)),
+        }
+    }
 
-                // if child_.0.get() > rows{
-                //     rows = child_.0.get();
-                // }
-                // if child_.1.get() > cols{
-                //     cols = child_.1.get();
-                // }
+    // Runs `action` right away if it won't lose unsaved changes, otherwise holds it back and
+    // asks the user whether to save or discard them first
+    fn guard(&mut self, action: GuardedAction) -> ShouldRender {
+        let dirty = match action {
+            GuardedAction::Quit => self.sessions.iter().any(|s| s.view.dirty),
+            GuardedAction::CloseTabs(ref closed) => closed.iter().any(|i| self.sessions[*i].view.dirty),
+            _ => self.get_session().view.dirty,
+        };
+        if dirty {
+            self.pending_guard = Some(action);
+            return true;
+        }
+        self.run_guarded(action)
+    }
 
-                if let Some(Grammar {
-                    kind: Kind::Grid(_sub_coords),
-                    name: _,
-                    style: _,
-                }) = &mut grammars.get(&old_coord.clone())
-                {
-                    grammar_copy = self.reassign(old_coord.clone(), grammar_copy.clone(), i);
+    fn run_guarded(&mut self, action: GuardedAction) -> ShouldRender {
+        match action {
+            GuardedAction::Recreate => {
+                self.bootstrap_session();
+                self.get_session_mut().view.dirty = false;
+                true
+            }
+            GuardedAction::ReadSession(file) => {
+                // electron exposes the full path of opened files
+                let file_path: Option<String> = js! { return @{&file}.path || null; }
+                    .try_into()
+                    .unwrap_or(None);
+                self.get_session_mut().view.file_path = file_path;
+                self.tasks.push(
+                    self.reader
+                        .read_file(file, self.link.callback(Action::LoadSession)),
+                );
+                false
+            }
+            GuardedAction::ReloadSession(mut session) => {
+                if let Some(path) = self.get_session().view.file_path.clone() {
+                    load_sidecar_assets(&sidecar_dir(&path), &mut session.assets);
                 }
+                self.load_session(session);
+                self.get_session_mut().view.dirty = false;
+                true
+            }
+            GuardedAction::CloseTabs(closed) => {
+                self.close_tabs(&closed);
+                true
+            }
+            GuardedAction::Quit => {
+                self.update(Action::PersistWorkspace);
+                let _ = ipc::send_sync(&ipc::Request::QuitConfirmed);
+                false
             }
-
-            // info!("row {:?} col {:?}", rows, cols);
-            // resize(
-            //     self,
-            //     coord,
-            //     (rows as f64) * (/* default row height */tmp_heigth),
-            //     (cols as f64) * (/* default col width */tmp_width),
-            // );
-            grammars = grammar_copy;
         }
-
-        grammars
     }
-}
 
-impl Component for Model {
-    type Message = Action;
-    type Properties = ();
+    // The cell whose reference is being edited, if any: the active cell when it's a lookup or a
+    // formula (an input starting with "="). While there is one, clicking on other cells inserts
+    // their coordinates into it instead of making them active.
+    pub fn reference_target(&self) -> Option<Coordinate> {
+        let active = self.active_cell.clone()?;
+        match self.get_session().grammars.get(&active).map(|g| &g.kind) {
+            Some(Kind::Lookup(_, _)) => Some(active),
+            Some(Kind::Input(v)) if v.starts_with('=') => Some(active),
+            _ => None,
+        }
+    }
 
-    fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
-        let root_grammar = Grammar {
-            name: "root".to_string(),
-            style: Style::default(),
-            kind: Kind::Grid(row_col_vec![(1, 1), (2, 1), (3, 1), (1, 2), (2, 2), (3, 2)]),
-        };
-        let meta_grammar = Grammar {
-            name: "meta".to_string(),
-            style: Style::default(),
-            kind: Kind::Grid(row_col_vec![(1, 1), (2, 1), (3, 1), (4, 1), (5, 1), (6, 1)]),
+    // Works out which formulas need recalculating after an update: the ones whose text changed,
+    // and the ones reading (directly or through other formulas) from a cell whose text changed.
+    // In automatic mode they're recalculated right away, in manual mode they're marked stale.
+    fn track_changes(&mut self) {
+        if self.calc_session != Some(self.current_session_index) {
+            self.values.clear();
+            self.spills.clear();
+            self.stale.clear();
+            self.calc_queue.clear();
+            self.calc_inputs.clear();
+            self.variable_inputs.clear();
+            self.visibility_inputs.clear();
+            self.visibility_queue.clear();
+            self.binding_inputs.clear();
+            self.binding_queue.clear();
+            self.deps = DepGraph::default();
+            self.calc_session = Some(self.current_session_index);
+            self.changed_cells = None;
+        }
+        let conditions = visibility_conditions(&self.get_session().grammars);
+        let bindings = style_bindings(&self.get_session().grammars);
+        let variables = self.variables();
+        let changed_names = changed_variables(&self.variable_inputs, &variables);
+        self.visibility_queue.extend(changed_entries(&self.visibility_inputs, &conditions));
+        self.visibility_queue.extend(variable_readers(&conditions, &changed_names));
+        self.binding_queue.extend(changed_entries(&self.binding_inputs, &bindings));
+        self.binding_queue.extend(binding_variable_readers(&bindings, &changed_names));
+        self.variable_inputs = variables;
+        let formulas = formula_cells(&self.get_session().grammars);
+        self.deps.update(&formulas);
+        let (inputs, affected, readers) = {
+            let grammars = &self.get_session().grammars;
+            let inputs: HashMap<Coordinate, String> = grammars
+                .iter()
+                .filter_map(|(c, g)| formula_text(&g.kind).map(|text| (c.clone(), text)))
+                .collect();
+            // formulas reading a variable that changed count as changed themselves
+            let mut changed: HashSet<Coordinate> = variable_readers(&formulas, &changed_names);
+            changed.extend(
+                inputs
+                    .iter()
+                    .filter(|(c, text)| self.calc_inputs.get(c) != Some(text))
+                    .map(|(c, _)| c.clone()),
+            );
+            changed.extend(self.calc_inputs.keys().filter(|c| !inputs.contains_key(c)).cloned());
+            if changed.is_empty() {
+                self.visibility_inputs = conditions;
+                self.binding_inputs = bindings;
+                self.apply_style_formulas();
+                self.fire_reminders();
+                return;
+            }
+            let mut affected = self.deps.dependents(&changed);
+            // conditions & bindings reading from the changed cells, or from the formulas they affect
+            let mut read = affected.clone();
+            read.extend(changed.iter().cloned());
+            let readers = (visibility_readers(&conditions, &read), binding_readers(&bindings, &read));
+            if let Some(cells) = &mut self.changed_cells {
+                cells.extend(changed.iter().chain(affected.iter()).cloned());
+            }
+            affected.extend(changed.into_iter().filter(|c| formulas.contains_key(c)));
+            (inputs, affected, readers)
         };
-        let mut m = Model {
-            view_root: coord!("root"),
-            col_widths: hashmap! {
-               coord_col!("root","A") => 90.0,
-               coord_col!("root","B") => 90.0,
-               coord_col!("root","C") => 90.0,
-               coord_col!("meta","A") => 180.0,
-               coord_col!("meta-A3","A") => 90.0,
-               coord_col!("meta-A3","B") => 180.0,
-            },
-            row_heights: hashmap! {
-               coord_row!("root","1") => 30.0,
-               coord_row!("root","2") => 30.0,
-               coord_row!("root","3") => 30.0,
-               coord_row!("meta","1") => 180.0,
-            },
+        self.calc_inputs = inputs;
+        self.visibility_inputs = conditions;
+        self.binding_inputs = bindings;
+        self.visibility_queue.extend(readers.0);
+        self.binding_queue.extend(readers.1);
+        let spilled: HashSet<Coordinate> = self.spills.values().flatten().cloned().collect();
+        self.values
+            .retain(|c, _| formulas.contains_key(c) || spilled.contains(c));
+        self.stale.retain(|c| formulas.contains_key(c));
+        match self.preferences.calc_mode {
+            CalcMode::Automatic => self.start_calc(affected.into_iter().collect()),
+            CalcMode::Manual => self.stale.extend(affected),
+        }
+        if self.calc_queue.is_empty() {
+            self.apply_style_formulas();
+            self.fire_reminders();
+        }
+    }
+
+    // fires the reminders of the sessions that are due, as desktop notifications, returning
+    // whether any did. Conditions are only checked in the current session, the only one whose
+    // formulas have values.
+    fn fire_reminders(&mut self) -> bool {
+        let now = now_local();
+        let current = self.current_session_index;
+        let mut any = false;
+        for (index, session) in self.sessions.iter_mut().enumerate() {
+            if session.mute_reminders {
+                continue;
+            }
+            let variables = variable_values(&session.variables);
+            let fired = if index == current {
+                check_reminders(&mut session.grammars, &variables, &mut self.values, &now, true)
+            } else {
+                check_reminders(&mut session.grammars, &variables, &mut Values::new(), &now, false)
+            };
+            for (_, message) in fired.iter() {
+                ipc::send(&ipc::Request::ShowNotification {
+                    title: session.title.clone(),
+                    body: message.clone(),
+                });
+            }
+            if !fired.is_empty() {
+                session.view.dirty = true;
+                any = true;
+            }
+        }
+        any
+    }
+
+    // shows or hides the cells whose visibility conditions are waiting to be re-evaluated, and
+    // recomputes the styles of the ones whose bindings are
+    fn apply_style_formulas(&mut self) {
+        let index = self.current_session_index;
+        let variables = self.variables();
+        if !self.visibility_queue.is_empty() {
+            let cells: Vec<Coordinate> = self.visibility_queue.drain().collect();
+            apply_conditions(&mut self.sessions[index].grammars, &variables, &mut self.values, &cells);
+        }
+        if !self.binding_queue.is_empty() {
+            let cells: Vec<Coordinate> = self.binding_queue.drain().collect();
+            apply_bindings(&mut self.sessions[index].grammars, &variables, &mut self.values, &cells);
+        }
+    }
+
+    // With iterative calculation on, formulas in cycles are iterated until they settle (instead
+    // of being "#CYCLE!"), then the formulas reading from them are recalculated
+    fn iterate_cycles(&mut self) {
+        let grammars = self.get_session().grammars.clone();
+        let cyclic = self.deps.cyclic();
+        if cyclic.is_empty() {
+            self.calc_report = None;
+            return;
+        }
+        let settings = self.get_session().iteration.clone();
+        let variables = self.variables();
+        let outcome = iterate(&grammars, &variables, &mut self.values, &cyclic, &settings);
+        self.calc_report = Some(match outcome {
+            IterationOutcome::Converged(passes) => format! {"Converged after {} iterations", passes},
+            IterationOutcome::Diverged(cells) => format! {
+                "Did not converge: {}",
+                cells.iter().map(|c| c.to_string()).collect::<Vec<String>>().join(", ")
+            },
+        });
+        let readers: HashSet<Coordinate> = self.deps.dependents(&cyclic).difference(&cyclic).cloned().collect();
+        let readers_list: Vec<Coordinate> = readers.iter().cloned().collect();
+        recalculate(&grammars, &variables, &mut self.values, &readers_list, &readers);
+    }
+
+    // Without iterative calculation, every formula in a cycle is "#CYCLE!" (whichever of them
+    // evaluation happened to start from), then the formulas reading from them are recalculated
+    fn mark_cycles(&mut self) {
+        let cyclic = self.deps.cyclic();
+        self.calc_report = match cyclic.len() {
+            0 => None,
+            n => Some(format! {"{} formulas in circular references", n}),
+        };
+        let cycle_error = FormulaValue::Error("#CYCLE!".to_string());
+        let unmarked: HashSet<Coordinate> = cyclic
+            .iter()
+            .filter(|c| self.values.get(c) != Some(&cycle_error))
+            .cloned()
+            .collect();
+        if unmarked.is_empty() {
+            return;
+        }
+        for cell in unmarked.iter() {
+            self.values.insert(cell.clone(), cycle_error.clone());
+        }
+        let grammars = self.get_session().grammars.clone();
+        let variables = self.variables();
+        let readers: HashSet<Coordinate> = self.deps.dependents(&unmarked).difference(&cyclic).cloned().collect();
+        let readers_list: Vec<Coordinate> = readers.iter().cloned().collect();
+        recalculate(&grammars, &variables, &mut self.values, &readers_list, &readers);
+    }
+
+    // spreads array results over their neighboring cells, then recalculates the formulas reading
+    // from the cells that were spilled into
+    fn spill_arrays(&mut self) {
+        let grammars = self.get_session().grammars.clone();
+        let before: HashSet<Coordinate> = self.spills.values().flatten().cloned().collect();
+        spill(&grammars, &mut self.values, &mut self.spills);
+        let mut spilled: HashSet<Coordinate> = self.spills.values().flatten().cloned().collect();
+        spilled.extend(before);
+        if spilled.is_empty() {
+            return;
+        }
+        let readers = self.deps.dependents(&spilled);
+        let variables = self.variables();
+        let readers_list: Vec<Coordinate> = readers.iter().cloned().collect();
+        recalculate(&grammars, &variables, &mut self.values, &readers_list, &readers);
+    }
+
+    // queues formulas for recalculation. Small recalculations happen right away, larger ones
+    // in batches so the status bar can show their progress.
+    fn start_calc(&mut self, cells: Vec<Coordinate>) {
+        for cell in cells {
+            if !self.calc_queue.contains(&cell) {
+                self.calc_queue.push(cell);
+            }
+        }
+        self.calc_total = self.calc_queue.len();
+        if self.calc_queue.len() <= CALC_BATCH {
+            self.update(Action::CalcStep);
+        } else if self.calc_task.is_none() {
+            self.schedule_calc_step();
+        }
+    }
+
+    fn schedule_calc_step(&mut self) {
+        let callback = self.link.callback(|_| Action::CalcStep);
+        self.calc_task = Some(TimeoutService::new().spawn(Duration::from_millis(0), callback));
+    }
+
+    // (re)draws the highlights of the references of the formula being edited, in the colors
+    // they have in the formula's text
+    fn sync_reference_overlay(&self) {
+        let text = match self
+            .formula_edit
+            .as_ref()
+            .and_then(|c| self.get_session().grammars.get(c))
+            .map(|g| &g.kind)
+        {
+            Some(Kind::Input(text)) if is_formula(text) => text.clone(),
+            _ => "".to_string(),
+        };
+        let highlights = colored_references(&text)
+            .into_iter()
+            .map(|(reference, color)| {
+                let (first, last) = match reference {
+                    Reference::Cell(c) => (c.clone(), c),
+                    Reference::Range(start, end) => (start, end),
+                };
+                (first.to_string(), last.to_string(), color.to_string())
+            })
+            .collect();
+        draw_reference_overlay(highlights);
+    }
+
+    // fetches a file from a URL, or reads it from disk
+    fn fetch_text(&mut self, location: String, callback: Callback<Result<String, String>>) {
+        if is_url(&location) {
+            let request = match Request::get(location.deref()).body(Nothing) {
+                Ok(request) => request,
+                Err(e) => return callback.emit(Err(e.to_string())),
+            };
+            let task = FetchService::new().fetch(
+                request,
+                Callback::from(move |response: Response<Text>| {
+                    let (meta, body) = response.into_parts();
+                    callback.emit(match body {
+                        Ok(body) if meta.status.is_success() => Ok(body),
+                        Ok(_) => Err(format! {"HTTP {}", meta.status}),
+                        Err(e) => Err(e.to_string()),
+                    })
+                }),
+            );
+            self.fetch_tasks.push(task);
+        } else {
+            let content = js! {
+                try {
+                    return require("fs").readFileSync(@{location.clone()}, "utf8");
+                } catch (e) {
+                    return null;
+                }
+            };
+            callback.emit(content.into_string().ok_or_else(|| format! {"couldn't read {}", location}));
+        }
+    }
+
+    // the coordinate of a new row at the end of the meta sheet, added to the meta grid
+    fn next_meta_row(&mut self) -> Coordinate {
+        let max_row = self
+            .query_col(coord_col!("meta", "A"))
+            .iter()
+            .map(|c| c.row().get())
+            .max()
+            .unwrap_or(0);
+        let sub_coord = non_zero_u32_tuple((max_row + 1, 1));
+        if let Kind::Grid(sub_coords) = &mut self.get_session_mut().meta.kind {
+            sub_coords.push(sub_coord);
+        }
+        if let Some(Grammar {
+            kind: Kind::Grid(sub_coords),
+            ..
+        }) = self.get_session_mut().grammars.get_mut(&coord!("meta"))
+        {
+            sub_coords.push(sub_coord);
+        }
+        Coordinate::child_of(&coord!("meta"), sub_coord)
+    }
+
+    // starts uploading queued driver files, as many at once as `UPLOAD_PARALLELISM` allows. The
+    // main process serves them to the driver's main file (see `ipc::Request`)
+    fn pump_uploads(&mut self) {
+        while self.uploads_in_flight < UPLOAD_PARALLELISM && !self.upload_queue.is_empty() {
+            let (driver, file_data) = self.upload_queue.remove(0);
+            if let Some(batch) = self.uploads.iter_mut().find(|b| b.driver == driver) {
+                batch.set(&file_data.name, UploadState::Uploading);
+            }
+            let request = ipc::Request::UploadDriverMiscFile {
+                name: file_data.name.clone(),
+                data: base64::encode(&file_data.content),
+            };
+            let name = file_data.name;
+            let callback = self.link.callback(move |reply: Result<serde_json::Value, String>| {
+                Action::DriverFileUploaded(driver.clone(), name.clone(), reply.map(|_| ()))
+            });
+            self.uploads_in_flight += 1;
+            ipc::invoke(&request, callback);
+        }
+    }
+
+    // the loaded driver named `name`, registered if it isn't yet
+    fn driver_mut(&mut self, name: &str) -> &mut Driver {
+        let index = match self.drivers.iter().position(|d| d.name == name) {
+            Some(index) => index,
+            None => {
+                self.drivers.push(Driver::new(name.to_string()));
+                self.drivers.len() - 1
+            }
+        };
+        &mut self.drivers[index]
+    }
+
+    fn audit_driver_call(&mut self, call: &DriverCall, blocked: Option<String>) {
+        self.driver_mut(&call.driver).audit(AuditEntry {
+            time: now_iso(),
+            op: call.op.clone(),
+            detail: call.args.join(", "),
+            blocked,
+        });
+    }
+
+    // runs a call of a driver that's been allowed, and settles it
+    fn run_driver_call(&mut self, call: DriverCall) -> ShouldRender {
+        let arg = |i: usize| call.args.get(i).cloned().unwrap_or_default();
+        let result = match call.op.as_str() {
+            "getCell" => match Coordinate::parse(&arg(0)) {
+                Some(coord) if self.get_session().grammars.contains_key(&coord) => {
+                    Ok(cell_text(&self.get_session().grammars, &coord))
+                }
+                _ => Err(format! {"{} isn't a cell", arg(0)}),
+            },
+            "setCell" => self.set_cell_text(&arg(0), arg(1)).map(|_| String::new()),
+            "subscribe" => match (arg(0).parse::<u32>(), parse_range(&arg(1))) {
+                (Ok(id), Ok(cells)) => {
+                    let values = watched_values(&self.get_session().grammars, &cells);
+                    self.subscriptions.push(Subscription {
+                        driver: call.driver.clone(),
+                        id,
+                        cells,
+                        values,
+                    });
+                    Ok(String::new())
+                }
+                (_, Err(e)) => Err(e),
+                (Err(e), _) => Err(e.to_string()),
+            },
+            "registerHook" => {
+                let stage = match arg(0).as_str() {
+                    "pre-save" => Some(Stage::PreSave),
+                    "post-load" => Some(Stage::PostLoad),
+                    _ => None,
+                };
+                match (stage, arg(2).parse::<i32>()) {
+                    (Some(stage), Ok(order)) => {
+                        let source = HookSource::Driver(call.driver.clone());
+                        self.driver_hooks.retain(|h| !(h.source == source && h.name == arg(1)));
+                        self.driver_hooks.push(Hook {
+                            name: arg(1),
+                            stage,
+                            order,
+                            source,
+                            enabled: true,
+                        });
+                        Ok(String::new())
+                    }
+                    (None, _) => Err(format! {"unknown stage {}, hooks run at pre-save or post-load", arg(0)}),
+                    (_, Err(e)) => Err(e.to_string()),
+                }
+            }
+            "unsubscribe" => {
+                let id = arg(0).parse::<u32>().ok();
+                self.subscriptions.retain(|s| !(s.driver == call.driver && Some(s.id) == id));
+                Ok(String::new())
+            }
+            // the driver makes the request, or reads the file, itself once it's allowed
+            _ => Ok(String::new()),
+        };
+        // reading cells needs no capability, so it isn't worth auditing
+        if call.capability() != Ok(None) {
+            self.audit_driver_call(&call, None);
+        }
+        match result {
+            Ok(value) => settle_driver_call(&self.driver_bridge, call.id, true, value),
+            Err(e) => settle_driver_call(&self.driver_bridge, call.id, false, e),
+        }
+        true
+    }
+
+    // sets an input (or color picker) cell from outside the grid, by drivers and automation
+    fn set_cell_text(&mut self, coord_text: &str, value: String) -> Result<(), String> {
+        let coord = Coordinate::parse(coord_text).ok_or_else(|| format! {"{} isn't a cell", coord_text})?;
+        match self.get_session().grammars.get(&coord).map(|g| &g.kind) {
+            Some(Kind::Input(_)) => {
+                let before = self.snapshot();
+                self.update(Action::ChangeInput(coord, value));
+                self.history.record(before);
+                Ok(())
+            }
+            Some(Kind::Interactive(_, Interactive::ColorPicker(_))) => match parse_color(&value) {
+                Some(color) => {
+                    self.update(Action::SetColor(coord, color));
+                    Ok(())
+                }
+                None => Err(format! {"{} isn't a color", value}),
+            },
+            _ => Err(format! {"{} isn't an input cell", coord_text}),
+        }
+    }
+
+    // runs a command of the automation API on the current session
+    fn run_automation_command(&mut self, command: Command) -> Result<serde_json::Value, RpcError> {
+        let failed = |message: String| RpcError::new(COMMAND_FAILED, message);
+        let cell = |text: &str| Coordinate::parse(text).ok_or_else(|| failed(format! {"{} isn't a cell", text}));
+        match command {
+            Command::GetCell { coord } => {
+                let grammars = &self.get_session().grammars;
+                match cell(&coord)? {
+                    c if grammars.contains_key(&c) => Ok(serde_json::Value::String(cell_text(grammars, &c))),
+                    _ => Err(failed(format! {"{} isn't a cell", coord})),
+                }
+            }
+            Command::SetCell { coord, value } => {
+                self.set_cell_text(&coord, value).map_err(failed)?;
+                Ok(serde_json::Value::Null)
+            }
+            // the command's output is written into the session once it finishes
+            Command::RunShell { coord } => {
+                let coord = cell(&coord)?;
+                if command_of(&self.get_session().grammars, &coord).is_none() {
+                    return Err(failed(format! {"{} isn't a command cell", coord.to_string()}));
+                }
+                self.update(Action::RunShell(coord));
+                Ok(serde_json::Value::Null)
+            }
+            Command::ExportCsv { grid } => {
+                let table = grid_table(&self.get_session().grammars, &cell(&grid)?)
+                    .ok_or_else(|| failed(format! {"{} isn't a grid", grid}))?;
+                Ok(serde_json::Value::String(to_csv(&table)))
+            }
+            Command::OpenSession { path } => {
+                let exists: bool = js! { return require("fs").existsSync(@{&path}); }
+                    .try_into()
+                    .unwrap_or(false);
+                if !exists {
+                    return Err(failed(format! {"{} doesn't exist", path}));
+                }
+                self.update(Action::OpenSessionPath(path));
+                Ok(serde_json::Value::Null)
+            }
+        }
+    }
+
+    // sends drivers the changes to the cells they subscribed to since they were last told
+    fn dispatch_subscriptions(&mut self) {
+        let changed = self.changed_cells.replace(HashSet::new());
+        if changed.as_ref().map_or(false, |cells| cells.is_empty()) {
+            return;
+        }
+        let grammars = &self.sessions[self.current_session_index].grammars;
+        for subscription in self.subscriptions.iter_mut() {
+            let changes = watched_changes(grammars, subscription, changed.as_ref());
+            if changes.is_empty() {
+                continue;
+            }
+            let changes = serde_json::to_string(&changes).unwrap();
+            js! {
+                @{&self.driver_bridge}.notify(@{subscription.id}, @{changes});
+            };
+        }
+    }
+
+    // writes the time since the last tick into the running timers, forgetting the ones that
+    // aren't timers anymore
+    fn tick_timers(&mut self) {
+        let now = now_ms();
+        let elapsed = (now - self.last_tick) / 1000.0;
+        self.last_tick = now;
+        let sessions = &mut self.sessions;
+        self.running_timers.retain(|(index, coord)| {
+            match sessions.get_mut(*index).and_then(|s| s.grammars.get_mut(coord)) {
+                Some(Grammar {
+                    kind: Kind::Interactive(_, Interactive::Timer(total)),
+                    ..
+                }) => {
+                    *total += elapsed;
+                    true
+                }
+                _ => false,
+            }
+        });
+    }
+
+    // tells plugins about the cells that changed since they were last told
+    fn dispatch_cell_changes(&mut self) {
+        if self.plugins.is_empty() {
+            return;
+        }
+        let session = &self.sessions[self.current_session_index];
+        let values = cell_values(&session.grammars);
+        let changes = diff(&self.plugin_values, &values);
+        let mut follow_ups = vec![];
+        for plugin in self.plugins.iter_mut() {
+            for change in changes.iter() {
+                if let Some(coord) = Coordinate::parse(&change.coord) {
+                    follow_ups.extend(plugin.on_cell_change(
+                        &coord,
+                        change.old.as_ref().map(|s| s.as_str()),
+                        change.new.as_ref().map(|s| s.as_str()),
+                        session,
+                    ));
+                }
+            }
+        }
+        self.plugin_values = values;
+        self.link.send_message_batch(follow_ups);
+    }
+
+    // removes the grammars of an installed pack from the meta sheet, returns whether it was installed
+    fn uninstall_pack(&mut self, name: &str) -> bool {
+        let session = self.get_session_mut();
+        let pack = match session.packages.iter().position(|p| p.name == name) {
+            Some(index) => session.packages.remove(index),
+            None => return false,
+        };
+        for coord in pack.coords.iter() {
+            let depth = coord.row_cols.len();
+            session
+                .grammars
+                .retain(|c, _| !(c.row_cols.len() >= depth && c.row_cols[..depth] == coord.row_cols[..]));
+            let sub_coord = coord.row_col();
+            if let Kind::Grid(sub_coords) = &mut session.meta.kind {
+                sub_coords.retain(|c| *c != sub_coord);
+            }
+            if let Some(Grammar {
+                kind: Kind::Grid(sub_coords),
+                ..
+            }) = session.grammars.get_mut(&coord!("meta"))
+            {
+                sub_coords.retain(|c| *c != sub_coord);
+            }
+        }
+        true
+    }
+
+    // loads the session files transcluded in the current session that haven't been read yet
+    fn sync_transclusions(&mut self) {
+        let unread: HashSet<String> = self
+            .get_session()
+            .grammars
+            .values()
+            .filter_map(|g| match &g.kind {
+                Kind::Transclude(path, _) if !self.transclusions.contains_key(path) => Some(path.clone()),
+                _ => None,
+            })
+            .collect();
+        for path in unread {
+            self.update(Action::LoadTransclusion(path));
+        }
+    }
+
+    // forgets the session files no open session transcludes anymore (as their cells were removed
+    // or retargeted), closing their watchers
+    fn release_transclusions(&mut self) {
+        let transcluded: HashSet<&String> = self
+            .sessions
+            .iter()
+            .flat_map(|session| session.grammars.values())
+            .filter_map(|g| match &g.kind {
+                Kind::Transclude(path, _) => Some(path),
+                _ => None,
+            })
+            .collect();
+        let released: Vec<String> = self
+            .transclusions
+            .keys()
+            .filter(|path| !transcluded.contains(path))
+            .cloned()
+            .collect();
+        for path in released {
+            if let Some(watcher) = self.transclusion_watchers.remove(&path) {
+                js! { @{watcher}.close(); };
+            }
+            self.transclusions.remove(&path);
+        }
+    }
+
+    // watches the files of the current session's external sources that aren't watched yet, and
+    // refreshes their grids in case the files changed while they weren't watched
+    fn sync_external_sources(&mut self) {
+        let unwatched: HashSet<String> = self
+            .get_session()
+            .external_sources
+            .iter()
+            .filter(|source| !source.is_remote() && !self.watched_sources.contains(&source.path))
+            .map(|source| source.path.clone())
+            .collect();
+        for path in unwatched {
+            match ipc::send_sync(&ipc::Request::WatchDataSource { path: path.clone() }) {
+                Ok(_) => {
+                    self.watched_sources.insert(path.clone());
+                    self.update(Action::ExternalSourceChanged(path));
+                }
+                Err(e) => info! {"couldn't watch {}: {}", path, e},
+            }
+        }
+    }
+
+    // stops watching the files no external source of an open session is bound to anymore
+    fn unwatch_external_sources(&mut self) {
+        let bound: HashSet<&String> = self
+            .sessions
+            .iter()
+            .flat_map(|session| session.external_sources.iter())
+            .map(|source| &source.path)
+            .collect();
+        let unbound: Vec<String> = self
+            .watched_sources
+            .iter()
+            .filter(|path| !bound.contains(path))
+            .cloned()
+            .collect();
+        for path in unbound {
+            if let Err(e) = ipc::send_sync(&ipc::Request::UnwatchDataSource { path: path.clone() }) {
+                info! {"couldn't stop watching {}: {}", path, e};
+            }
+            self.watched_sources.remove(&path);
+        }
+    }
+
+    // reimports a table read (or fetched) from an external source into its grid, unless the grid
+    // was edited by hand since the last import and `overwrite` isn't set
+    fn reimport_source(&mut self, source: ExternalSource, table: Vec<Vec<String>>, overwrite: bool) -> bool {
+        let grid = source.grid.clone();
+        if !overwrite && source.is_hand_edited(&self.get_session().grammars) {
+            if !self.external_conflicts.contains(&grid) {
+                self.external_conflicts.push(grid);
+            }
+            return true;
+        }
+        self.external_conflicts.retain(|c| *c != grid);
+        let before = self.snapshot();
+        let mut changed = grid_table(&self.get_session().grammars, &grid) != Some(table.clone());
+        if changed {
+            write_table(&mut self.get_session_mut().grammars, &grid, &table);
+        }
+        // the snapshot replaced is undone along with the grid, or it would be taken for hand edits
+        if let Some(source) = self
+            .get_session_mut()
+            .external_sources
+            .iter_mut()
+            .find(|s| s.grid == grid && s.snapshot != table)
+        {
+            source.snapshot = table;
+            changed = true;
+        }
+        if changed {
+            self.history.record(before);
+        }
+        // the active cell may have been replaced by the grid's new cells
+        if let Some(active) = self.active_cell.clone() {
+            if !self.get_session().grammars.contains_key(&active) {
+                self.active_cell = Some(grid);
+            }
+        }
+        true
+    }
+
+    // has the main process fetch a remote CSV for `grid`, see `Action::UrlFetched`
+    fn fetch_url(&mut self, grid: Coordinate, url: String, overwrite: bool) {
+        self.fetching_sources.insert(grid.clone());
+        let request = ipc::Request::FetchUrl { url: url.clone() };
+        let callback = self.link.callback(move |reply: Result<serde_json::Value, String>| {
+            let content = reply.and_then(|value| {
+                value.as_str().map(|text| text.to_string()).ok_or_else(|| "the main process didn't send any text".to_string())
+            });
+            Action::UrlFetched(grid.clone(), url.clone(), overwrite, content)
+        });
+        ipc::invoke(&request, callback);
+    }
+
+    // adds an entry to both the current tab's and the global history of an input
+    fn record_history(&mut self, kind: HistoryKind, entry: &str) {
+        let mut preferences = self.preferences.clone();
+        let view = &mut self.get_session_mut().view;
+        match kind {
+            HistoryKind::GoTo => {
+                push_history(&mut view.goto_history, entry);
+                push_history(&mut preferences.goto_history, entry);
+            }
+            HistoryKind::Search => {
+                push_history(&mut view.search_history, entry);
+                push_history(&mut preferences.search_history, entry);
+            }
+        }
+        self.history_cursor = None;
+        self.update(Action::SetPreferences(preferences));
+    }
+
+    // reopens the tabs of a workspace in place of the blank one the app starts with: the ones
+    // saved to a file from it, the others as they were kept, each viewed the way it was
+    fn restore_workspace(&mut self, workspace: Workspace) {
+        let blank = self.sessions.len();
+        let mut views = vec![];
+        let mut active = None;
+        for (index, tab) in workspace.tabs.into_iter().enumerate() {
+            match (tab.session, tab.view.file_path.clone()) {
+                (Some(session), _) => self.sessions.push(session),
+                (None, Some(path)) => match read_session_file(&path) {
+                    Ok(session) => self.open_tab(path, session),
+                    Err(e) => {
+                        self.update(Action::Notify(format! {"Couldn't reopen {}: {}", path, e}, true));
+                        continue;
+                    }
+                },
+                (None, None) => continue,
+            }
+            if index == workspace.active_tab {
+                active = Some(views.len());
+            }
+            views.push(tab.view);
+        }
+        if views.is_empty() {
+            return;
+        }
+        self.sessions.drain(..blank);
+        // opening tabs keeps how the one before was viewed, so views are restored once all are open
+        for (session, view) in self.sessions.iter_mut().zip(views) {
+            session.view = view;
+        }
+        self.current_session_index = active.unwrap_or(0);
+        let view = self.get_session().view.clone();
+        self.zoom = view.zoom;
+        set_main_scroll(view.scroll);
+    }
+
+    // (re)starts autosaving at the interval in preferences, or stops it if it's off
+    fn schedule_autosave(&mut self) {
+        let interval = self.preferences.autosave_interval_s;
+        self.autosave_task = if interval > 0 {
+            let callback = self.link.callback(|_| Action::Autosave);
+            Some(IntervalService::new().spawn(Duration::from_secs(interval as u64), callback))
+        } else {
+            None
+        };
+    }
+
+    // shows the tab at `index`, keeping how the current one was viewed
+    fn switch_tab(&mut self, index: usize) {
+        if index >= self.sessions.len() || index == self.current_session_index {
+            return;
+        }
+        let zoom = self.zoom;
+        let view = &mut self.get_session_mut().view;
+        view.scroll = main_scroll();
+        view.zoom = zoom;
+        self.current_session_index = index;
+        let view = self.get_session().view.clone();
+        self.zoom = view.zoom;
+        set_main_scroll(view.scroll);
+        self.view_root = coord!("root");
+        self.active_cell = Some(coord!("root-A1"));
+        self.selection.clear();
+    }
+
+    // opens the session file at `path` in a new tab
+    fn open_tab(&mut self, path: String, mut session: Session) {
+        load_sidecar_assets(&sidecar_dir(&path), &mut session.assets);
+        let follow_ups = self.run_session_hooks(Stage::PostLoad, &mut session);
+        let layout = std::mem::take(&mut session.layout);
+        session.view = TabView {
+            file_path: Some(path),
+            ..TabView::default()
+        };
+        self.sessions.push(session);
+        self.switch_tab(self.sessions.len() - 1);
+        self.apply_layout(layout);
+        self.repair_on_load();
+        self.run_hook_follow_ups(follow_ups);
+    }
+
+    // The element ids of the cells of the current session by key, keying the cells that don't
+    // have one yet. Sessions too big to animate have none.
+    fn cell_keys(&mut self) -> HashMap<String, String> {
+        if self.get_session().grammars.len() > animation::MAX_ANIMATED_CELLS {
+            return HashMap::new();
+        }
+        let mut next_key = self.next_cell_key;
+        let grammars = &mut self.get_session_mut().grammars;
+        assign_keys(grammars, &mut next_key);
+        let ids = cell_ids(grammars);
+        self.next_cell_key = next_key;
+        ids
+    }
+
+    // The defaults for a grid nested at `coord`, see `style::grid_defaults`
+    pub fn grid_defaults(&self, coord: &Coordinate) -> GridDefaults {
+        grid_defaults(&self.get_session().grammars, coord, self.preferences.nested_grid)
+    }
+
+    // The size of the grid Ctrl+G or the "Nest Grid" button nest in the active cell: the one
+    // the grid it's in sets, or else the one picked in the menu bar
+    pub fn nested_row_cols(&self) -> (u32, u32) {
+        let overridden = self
+            .active_cell
+            .as_ref()
+            .and_then(|active| nested_defaults(&self.get_session().grammars, active));
+        match overridden {
+            Some(defaults) => (defaults.rows.max(1), defaults.cols.max(1)),
+            None => {
+                let (r, c) = self.default_nested_row_cols;
+                (r.get(), c.get())
+            }
+        }
+    }
+
+    // The trash entry for what `action` is about to delete, if it deletes a row, a column or
+    // nested grids
+    fn trash_entry(&self, action: &Action) -> Option<TrashEntry> {
+        let grammars = &self.get_session().grammars;
+        let deleted_at = now_iso();
+        match action {
+            Action::DeleteRow | Action::DeleteCol => {
+                let cell = self.focus_cell.clone().or_else(|| self.active_cell.clone())?;
+                let grid = cell.parent()?;
+                let (label, axis, line) = match action {
+                    Action::DeleteRow => ("Deleted row", OutlineAxis::Rows, cell.row().get()),
+                    _ => ("Deleted column", OutlineAxis::Cols, cell.col().get()),
+                };
+                TrashEntry::of_line(label, &deleted_at, grammars, &grid, axis, line)
+            }
+            Action::BulkDelete(coords) => {
+                TrashEntry::of_cells("Deleted cells", &deleted_at, grammars, coords)
+            }
+            Action::RangeDelete() => {
+                let coords: Vec<Coordinate> = self
+                    .selection
+                    .coords()
+                    .filter(|c| grammars.contains_key(c))
+                    .collect();
+                TrashEntry::of_cells("Deleted range", &deleted_at, grammars, &coords)
+            }
+            _ => None,
+        }
+    }
+
+    // Points everything that refers to a tab by its index at where it is now, once tabs have
+    // been moved or closed. `new_index` maps the old index of a tab to its new one, or to
+    // `None` if it was closed, which drops whatever belonged to it.
+    fn reindex_tabs(&mut self, new_index: &dyn Fn(usize) -> Option<usize>) {
+        self.calc_session = self.calc_session.and_then(|i| new_index(i));
+        self.running_timers = self
+            .running_timers
+            .drain()
+            .filter_map(|(i, coord)| new_index(i).map(|i| (i, coord)))
+            .collect();
+        self.recording = self
+            .recording
+            .take()
+            .and_then(|(i, log)| new_index(i).map(|i| (i, log)));
+        self.replay = self.replay.take().and_then(|mut replay| {
+            replay.session_index = new_index(replay.session_index)?;
+            Some(replay)
+        });
+        if self.replay.is_none() {
+            self.replay_task = None;
+        }
+        self.trash = self
+            .trash
+            .drain()
+            .filter_map(|(i, entries)| new_index(i).map(|i| (i, entries)))
+            .collect();
+        self.history.retain_mut(|snapshot| match new_index(snapshot.session_index) {
+            Some(i) => {
+                snapshot.session_index = i;
+                true
+            }
+            None => false,
+        });
+        self.tab_menu = None;
+        self.renaming_tab = None;
+        self.dragged_tab = None;
+    }
+
+    // closes the tabs at `closed`, showing the closest one left open after the current one (or
+    // before it). Closing every tab leaves a new session in their place.
+    fn close_tabs(&mut self, closed: &[usize]) {
+        let current = self.current_session_index;
+        let shown = (current..self.sessions.len())
+            .chain((0..current).rev())
+            .find(|i| !closed.contains(i));
+        let zoom = self.zoom;
+        let view = &mut self.get_session_mut().view;
+        view.scroll = main_scroll();
+        view.zoom = zoom;
+        let (kept, dropped): (Vec<_>, Vec<_>) = std::mem::take(&mut self.sessions)
+            .into_iter()
+            .enumerate()
+            .partition(|(i, _)| !closed.contains(i));
+        self.sessions = kept.into_iter().map(|(_, session)| session).collect();
+        self.reindex_tabs(&|i| remaining_index(i, closed));
+        match shown.and_then(|i| remaining_index(i, closed)) {
+            Some(index) => self.current_session_index = index,
+            None => {
+                let (_, mut session) = dropped.into_iter().next().expect("a tab was open");
+                session.title = "my session".to_string();
+                session.view = TabView::default();
+                self.sessions.push(session);
+                self.current_session_index = 0;
+                self.bootstrap_session();
+            }
+        }
+        let view = self.get_session().view.clone();
+        self.zoom = view.zoom;
+        set_main_scroll(view.scroll);
+        self.view_root = coord!("root");
+        self.active_cell = Some(coord!("root-A1"));
+        self.selection.clear();
+        self.update(Action::PersistWorkspace);
+    }
+
+    // adds an action to the repro log being recorded, if it applies to the recorded session and
+    // is one that's recorded (see `ReplayStep`)
+    fn record_step(&mut self, action: &Action) {
+        let current = self.current_session_index;
+        if let Some((index, log)) = self.recording.as_mut() {
+            if *index == current && self.replay.is_none() {
+                if let Some(step) = step_of(action) {
+                    log.steps.push(step);
+                }
+            }
+        }
+        if let Some((index, _, steps)) = self.macro_recording.as_mut() {
+            if *index == current && self.replay.is_none() {
+                if let Some(step) = step_of(action) {
+                    steps.push(step);
+                }
+            }
+        }
+    }
+
+    // whether the current session is being recorded to a repro log
+    fn is_recording(&self) -> bool {
+        let current = self.current_session_index;
+        self.replay.is_none() && self.recording.as_ref().map_or(false, |(index, _)| *index == current)
+    }
+
+    // marks the repro log being recorded incomplete, warning about it the first time
+    fn mark_repro_incomplete(&mut self) {
+        match self.recording.as_mut() {
+            Some((_, log)) if !log.incomplete => log.incomplete = true,
+            _ => return,
+        }
+        let message = "An action that can't be recorded changed the session, so the repro is incomplete";
+        self.update(Action::Notify(message.to_string(), true));
+    }
+
+    // The state to record for an action that changes the session, in case it doesn't record
+    // one itself (along with how many were recorded before it, to tell). Edits typed into the
+    // same input cell one after the other are undone together, so only the first records one.
+    fn unrecorded_change(&mut self, action: &Action) -> Option<(usize, Snapshot)> {
+        match action {
+            Action::Undo | Action::Redo => {
+                self.typing = None;
+                return None;
+            }
+            Action::SetActiveCell(_) => self.typing = None,
+            _ => (),
+        }
+        if !action.mutates_session() {
+            return None;
+        }
+        match action {
+            Action::ChangeInput(coord, _) => {
+                let typing = Some((self.current_session_index, coord.clone()));
+                if self.typing == typing {
+                    return None;
+                }
+                self.typing = typing;
+            }
+            _ => self.typing = None,
+        }
+        Some((self.history.records(), self.snapshot()))
+    }
+
+    // whether the session `before` was taken of (or the row & column sizes) changed since, in
+    // what's saved of it
+    fn changed_since(&self, before: &Snapshot) -> bool {
+        let (old, new) = match self.sessions.get(before.session_index) {
+            Some(session) => (&before.session, session),
+            None => return false,
+        };
+        before.row_heights != self.row_heights
+            || before.col_widths != self.col_widths
+            || old.grammars != new.grammars
+            || (&old.title, &old.root, &old.meta) != (&new.title, &new.root, &new.meta)
+            || old.outline_groups != new.outline_groups
+            || old.sheets != new.sheets
+            || old.metadata != new.metadata
+            || old.bookmarks != new.bookmarks
+            || old.iteration != new.iteration
+            || old.external_sources != new.external_sources
+            || old.assets != new.assets
+            || old.packages != new.packages
+            || old.macros != new.macros
+            || old.variables != new.variables
+            || old.mute_reminders != new.mute_reminders
+    }
+
+    // roughly how much memory the undo history takes (see `diagnostics::history_bytes`),
+    // estimated again only once the history changes
+    pub fn history_bytes(&self) -> usize {
+        let key = (self.history.records(), self.history.undo_len(), self.history.redo_len());
+        match self.history_bytes.get() {
+            Some((cached, bytes)) if cached == key => bytes,
+            _ => {
+                let bytes = history_bytes(&self.history);
+                self.history_bytes.set(Some((key, bytes)));
+                bytes
+            }
+        }
+    }
+
+    // the state undoable actions record before they're applied
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            session_index: self.current_session_index,
+            session: self.get_session().clone(),
+            kind: EditKind::Content,
+            row_heights: self.row_heights.clone(),
+            col_widths: self.col_widths.clone(),
+        }
+    }
+
+    // resizes grafted (or pasted) cells to the sizes they came with, keeping the current row
+    // height or column width where they came without one
+    fn apply_sizes(&mut self, sizes: Vec<(Coordinate, CellSize)>) {
+        for (coord, (row_height, col_width)) in sizes {
+            let defaults = self.grid_defaults(&coord);
+            let defaults = (defaults.row_height, defaults.col_width);
+            let row_height = row_height
+                .or_else(|| self.row_heights.get(&coord.full_row()).cloned())
+                .unwrap_or(defaults.0);
+            let col_width = col_width
+                .or_else(|| self.col_widths.get(&coord.full_col()).cloned())
+                .unwrap_or(defaults.1);
+            resize(self, coord, row_height, col_width, defaults);
+        }
+    }
+
+    // sets the zoom level so `region` fits in the main area, with a bit of room around it
+    fn zoom_to(&mut self, region: &Region) {
+        let defaults = (self.preferences.default_col_width, self.preferences.default_row_height);
+        let content = content_size(&self.row_heights, &self.col_widths, &region.parent, region.rows, region.cols, defaults);
+        let (width, height) = main_size();
+        let margin = 20.0;
+        self.zoom = fit_zoom(content, ((width - margin).max(1.0), (height - margin).max(1.0)));
+    }
+
+    // duplicates the row or column of the active cell, making its copy of the active cell active
+    fn duplicate_active_line(&mut self, axis: OutlineAxis) -> ShouldRender {
+        let active = match self.active_cell.clone() {
+            Some(active) => active,
+            None => return false,
+        };
+        let grid = match active.parent() {
+            Some(grid) => grid,
+            None => return false,
+        };
+        let (row, col) = (active.row().get(), active.col().get());
+        let (line, copy) = match axis {
+            OutlineAxis::Rows => (row, (row + 1, col)),
+            OutlineAxis::Cols => (col, (row, col + 1)),
+        };
+        let before = self.snapshot();
+        if !duplicate_line(&mut self.get_session_mut().grammars, &grid, axis, line) {
+            return false;
+        }
+        duplicate_sizes(&mut self.row_heights, &mut self.col_widths, &grid, axis, line);
+        let groups = &mut self.get_session_mut().outline_groups;
+        remap_outline_groups(groups, &grid, axis, &|n| if n > line { n + 1 } else { n });
+        self.history.record(before);
+        self.selection.clear();
+        self.active_cell = Some(Coordinate::child_of(&grid, non_zero_u32_tuple(copy)));
+        true
+    }
+
+    fn swap_active_cell(&mut self, axis: OutlineAxis, forward: bool) -> ShouldRender {
+        let active = match self.active_cell.clone() {
+            Some(active) => active,
+            None => return false,
+        };
+        let neighbor = match (axis, forward) {
+            (OutlineAxis::Rows, false) => active.neighbor_above(),
+            (OutlineAxis::Rows, true) => active.neighbor_below(),
+            (OutlineAxis::Cols, false) => active.neighbor_left(),
+            (OutlineAxis::Cols, true) => active.neighbor_right(),
+        };
+        let neighbor = match neighbor {
+            Some(neighbor) => neighbor,
+            None => return false,
+        };
+        let before = self.snapshot();
+        if !swap_cells(&mut self.get_session_mut().grammars, &active, &neighbor) {
+            return false;
+        }
+        swap_sizes(&mut self.row_heights, &mut self.col_widths, &active, &neighbor);
+        swap_outline_groups(&mut self.get_session_mut().outline_groups, &active, &neighbor);
+        self.history.record(before);
+        // the swapped cell stays active
+        self.selection.clear();
+        self.active_cell = Some(neighbor);
+        true
+    }
+
+    fn move_active_line(&mut self, axis: OutlineAxis, forward: bool) -> ShouldRender {
+        let active = match self.active_cell.clone() {
+            Some(active) => active,
+            None => return false,
+        };
+        let grid = match active.parent() {
+            Some(grid) => grid,
+            None => return false,
+        };
+        let (row, col) = (active.row().get(), active.col().get());
+        let line = match axis {
+            OutlineAxis::Rows => row,
+            OutlineAxis::Cols => col,
+        };
+        let to = if forward { line + 1 } else { line - 1 };
+        let before = self.snapshot();
+        if !move_line(&mut self.get_session_mut().grammars, &grid, axis, line, to) {
+            return false;
+        }
+        move_sizes(&mut self.row_heights, &mut self.col_widths, &grid, axis, line, to);
+        let groups = &mut self.get_session_mut().outline_groups;
+        remap_outline_groups(groups, &grid, axis, &|n| moved_line(n, line, to));
+        self.history.record(before);
+        // the moved line stays active
+        let moved = match axis {
+            OutlineAxis::Rows => (to, col),
+            OutlineAxis::Cols => (row, to),
+        };
+        self.selection.clear();
+        self.active_cell = Some(Coordinate::child_of(&grid, non_zero_u32_tuple(moved)));
+        true
+    }
+
+    // the state recorded by actions that only change how the session is laid out
+    pub fn layout_snapshot(&self) -> Snapshot {
+        Snapshot {
+            kind: EditKind::Layout,
+            ..self.snapshot()
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: Snapshot) {
+        if let Some(session) = self.sessions.get_mut(snapshot.session_index) {
+            *session = snapshot.session;
+            self.current_session_index = snapshot.session_index;
+            self.row_heights = snapshot.row_heights;
+            self.col_widths = snapshot.col_widths;
+        }
+    }
+
+    // Checks what was just completed (or imported) into `dest` against `declared`, the shape
+    // expected there beforehand, and keeps the declaration on the cell. A mismatch is undone back
+    // to `before` (returning false) when the preferences block them, and only warned about
+    // otherwise.
+    fn check_shape(&mut self, declared: Option<String>, dest: &Coordinate, before: Snapshot) -> bool {
+        let declared = match declared {
+            Some(declared) => declared,
+            None => return true,
+        };
+        if let Some(g) = self.get_session_mut().grammars.get_mut(dest) {
+            g.style.shape = Some(declared.clone());
+        }
+        let shape = match Shape::parse(&declared) {
+            Ok(shape) => shape,
+            Err(_) => return true,
+        };
+        let reason = match mismatch(&self.get_session().grammars, dest, &shape) {
+            Some(reason) => reason,
+            None => return true,
+        };
+        let message = format! {"{} expects {}: {}", dest.to_string(), shape.describe(), reason};
+        if self.preferences.block_shape_mismatches {
+            self.restore(before);
+            self.update(Action::Alert(message));
+            return false;
+        }
+        self.update(Action::Notify(message, true));
+        true
+    }
+
+    fn query_parent(&self, coord_parent: Coordinate) -> Vec<Coordinate> {
+        self.get_session()
+            .grammars
+            .keys()
+            .clone()
+            .filter_map(|k| {
+                if k.parent() == Some(coord_parent.clone()) {
+                    Some(k.clone())
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn query_col(&self, coord_col: Col) -> Vec<Coordinate> {
+        self.get_session()
+            .grammars
+            .keys()
+            .clone()
+            .filter_map(|k| {
+                if k.row_cols.len() == 1
+                /* ignore root & meta */
+                {
+                    None
+                } else if k.full_col() == coord_col {
+                    Some(k.clone())
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    // Gotta move
+
+    fn query_row(&self, coord_row: Row) -> Vec<Coordinate> {
+        self.get_session()
+            .grammars
+            .keys()
+            .clone()
+            .filter_map(|k| {
+                if k.row_cols.len() == 1
+                /* ignore root & meta */
+                {
+                    None
+                } else if k.full_row() == coord_row {
+                    Some(k.clone())
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+    fn reassign(
+        &mut self,
+        coord: Coordinate,
+        mut grammars: HashMap<Coordinate, Grammar>,
+        i: i32,
+    ) -> HashMap<Coordinate, Grammar> {
+        // let mut grammars = self.get_session_mut().grammars.clone();
+        let new_parent: Coordinate;
+        if coord.col().get() == 1 || coord.row().get() == 1 {
+            return grammars;
+        }
+        // height and width initial value
+        // let mut tmp_heigth = 30.0;
+        // let mut tmp_width = 90.0;
+
+        if let Some(Grammar {
+            kind: Kind::Grid(sub_coords),
+            name: _,
+            style: _,
+        }) = &mut grammars.get(&coord.clone())
+        {
+            match i {
+                0 => {
+                    new_parent = Coordinate::child_of(
+                        &coord.parent().unwrap(),
+                        (NonZeroU32::new(coord.row().get() - 1).unwrap(), coord.col()),
+                    )
+                }
+                _ => {
+                    new_parent = Coordinate::child_of(
+                        &coord.parent().unwrap(),
+                        (coord.row(), NonZeroU32::new(coord.col().get() - 1).unwrap()),
+                    )
+                }
+            }
+
+            // take old parent coord to copy its grammar into the new_parent coordinate for each sub_coordinates
+            let mut grammar_copy = grammars.clone();
+            // let current_width = grammar_copy[&coord.clone()].style.width;
+            // let current_height = grammar_copy[&coord.clone()].style.height;
+            // // check if active cell row height and width is greater than default value
+            // if current_width > tmp_width {
+            //     // set height argument to active cell height if greater
+            //     //Get the actual amount of cell being created and use it instead of "3" being HARD CODED.
+            //     tmp_width = current_width / 3.0;
+            // }
+            // if current_height > tmp_heigth {
+            //     // set width argument to active cell width if greater
+            //     //Get the actual amount of cell being created and use it instead of "3" being HARD CODED.
+            //     tmp_heigth = current_height / 3.0;
+            // }
+
+            // let mut rows = 0;
+            // let mut cols = 0;
+
+            for child_ in sub_coords {
+<<<<<<< HEAD
+                let old_coord = Coordinate::child_of(&coord, *child_);
+                let new_coord = Coordinate::child_of(&new_parent, *child_);
+                grammar_copy.insert(
+                    new_coord.clone(),
+                    grammar_copy
+                        .get(&old_coord.clone())
+                        .clone()
+                        .unwrap()
+                        .clone(),
+                );
+=======
+                
+                let old_coord = Coordinate::child_of(&Coord, *child_);
+                // info!("Old {:?}", old_coord);
+                let new_coord = Coordinate::child_of(&new_parent, *child_);
+                // info!("New {:?}", new_coord);
+                grammar_copy.insert(new_coord.clone(), grammar_copy.get(&old_coord.clone()).clone().unwrap().clone());
+>>>>>>> hieule/fix_bug
+                grammar_copy.remove(&old_coord);
+
+                // if child_.0.get() > rows{
+                //     rows = child_.0.get();
+                // }
+                // if child_.1.get() > cols{
+                //     cols = child_.1.get();
+                // }
+
+                if let Some(Grammar {
+                    kind: Kind::Grid(_sub_coords),
+                    name: _,
+                    style: _,
+                }) = &mut grammars.get(&old_coord.clone())
+                {
+                    grammar_copy = self.reassign(old_coord.clone(), grammar_copy.clone(), i);
+                }
+            }
+
+            // info!("row {:?} col {:?}", rows, cols);
+            // resize(
+            //     self,
+            //     coord,
+            //     (rows as f64) * (/* default row height */tmp_heigth),
+            //     (cols as f64) * (/* default col width */tmp_width),
+            // );
+            grammars = grammar_copy;
+        }
+
+        grammars
+    }
+}
+
+impl Component for Model {
+    type Message = Action;
+    type Properties = ();
+
+    fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let storage = StorageService::new(Area::Local).expect("localStorage should be available");
+        let preferences = Preferences::load(&storage);
+        let nested_grid = preferences.nested_grid;
+        let mut m = Model {
+            view_root: coord!("root"),
+            split_view: None,
+            rendered_pane: RefCell::new(None),
+            focused_grid: None,
+            calendar: None,
+            timeline: None,
+            // sizes are filled in by `bootstrap_session` below
+            col_widths: HashMap::new(),
+            row_heights: HashMap::new(),
             active_cell: Some(coord!("root-A1")),
             meta_suggestions: vec![
                 ("js_grammar".to_string(), coord!("meta-A1")),
                 ("java_grammar".to_string(), coord!("meta-A2")),
                 ("defn".to_string(), coord!("meta-A3")),
             ],
+            column_index: ColumnIndex::new(),
+            placeholders: vec![],
+            history: History::new(),
+            typing: None,
+            structural_query: String::new(),
+            pending_guard: None,
+            history_cursor: None,
+            formula_edit: None,
+            values: Values::new(),
+            stale: HashSet::new(),
+            calc_inputs: HashMap::new(),
+            variable_inputs: Variables::new(),
+            calc_session: None,
+            visibility_inputs: HashMap::new(),
+            visibility_queue: HashSet::new(),
+            binding_inputs: HashMap::new(),
+            binding_queue: HashSet::new(),
+            calc_queue: vec![],
+            calc_total: 0,
+            calc_task: None,
+            calc_report: None,
+            deps: DepGraph::default(),
+            spills: Spills::new(),
+            goal_seek: None,
+            session_info: None,
+            meta_import: None,
+            session_merge: None,
+            defn_arguments: None,
+            propagation: None,
+            duplicates: None,
+            transclusions: HashMap::new(),
+            transclusion_watchers: HashMap::new(),
+            transclude_form: None,
+            import_url_form: None,
+            fetching_sources: HashSet::new(),
+            rename_form: None,
+            shape_form: None,
+            nested_defaults_form: None,
+            condition_form: None,
+            bindings_form: None,
+            script_form: None,
+            button_form: None,
+            watched_sources: HashSet::new(),
+            external_conflicts: vec![],
+            shell_confirm: None,
+            script_confirm: None,
+            running_shells: HashSet::new(),
+            running_timers: HashSet::new(),
+            timer_task: None,
+            last_tick: 0.0,
+            reminder_task: IntervalService::new().spawn(
+                Duration::from_secs(REMINDER_INTERVAL_S),
+                link.callback(|_| Action::CheckReminders),
+            ),
+            autosave_task: None,
+            recovery: None,
+            recording: None,
+            macro_recording: None,
+            replay: None,
+            replay_task: None,
+            applying: false,
+            last_update_ms: 0.0,
+            last_view_ms: Cell::new(0.0),
+            history_bytes: Cell::new(None),
+            registries: HashMap::new(),
+            fetch_tasks: vec![],
+            drivers: vec![],
+            driver_prompts: vec![],
+            driver_bridge: stdweb::Value::Null,
+            subscriptions: vec![],
+            changed_cells: Some(HashSet::new()),
+            driver_hooks: vec![],
+            trash: HashMap::new(),
+            clipboard_history: vec![],
+            next_cell_key: 0,
+            uploads: vec![],
+            upload_queue: vec![],
+            uploads_in_flight: 0,
+            notifications: vec![],
+            next_notification: 0,
+            plugins: registry(),
+            plugin_values: HashMap::new(),
+            goto_text: None,
+
+            console: ConsoleService::new(),
+            reader: ReaderService::new(),
+
+            focus_cell: None,
+            selection: Selection::default(),
+
+            secondary_selections: HashSet::new(),
+
+            zoom: 1.0,
+            file_popup: false,
+
+            sessions: vec![blank_session("my session".to_string())],
+
+            current_session_index: 0,
+
+            side_menus: vec![
+                SideMenu {
+                    name: "Home".to_string(),
+                    icon_path: "assets/logo.png".to_string(),
+                },
+                SideMenu {
+                    name: "File Explorer".to_string(),
+                    icon_path: "assets/folder_icon.png".to_string(),
+                },
+                SideMenu {
+                    name: "Settings".to_string(),
+                    icon_path: "assets/settings_icon.png".to_string(),
+                },
+                SideMenu {
+                    name: "Search".to_string(),
+                    icon_path: "assets/search_icon.svg".to_string(),
+                },
+                SideMenu {
+                    name: "Problems".to_string(),
+                    icon_path: "assets/problems_icon.svg".to_string(),
+                },
+                SideMenu {
+                    name: "Statistics".to_string(),
+                    icon_path: "assets/stats_icon.svg".to_string(),
+                },
+                SideMenu {
+                    name: "Packages".to_string(),
+                    icon_path: "assets/packages_icon.svg".to_string(),
+                },
+                SideMenu {
+                    name: "Drivers".to_string(),
+                    icon_path: "assets/drivers_icon.svg".to_string(),
+                },
+                SideMenu {
+                    name: "Assets".to_string(),
+                    icon_path: "assets/assets_icon.svg".to_string(),
+                },
+                SideMenu {
+                    name: "Bookmarks".to_string(),
+                    icon_path: "assets/bookmark_icon.svg".to_string(),
+                },
+                SideMenu {
+                    name: "Trash".to_string(),
+                    icon_path: "assets/trash_icon.svg".to_string(),
+                },
+                SideMenu {
+                    name: "Clipboard".to_string(),
+                    icon_path: "assets/clipboard_icon.svg".to_string(),
+                },
+                SideMenu {
+                    name: "Macros".to_string(),
+                    icon_path: "assets/macros_icon.svg".to_string(),
+                },
+                SideMenu {
+                    name: "Reminders".to_string(),
+                    icon_path: "assets/reminders_icon.svg".to_string(),
+                },
+                SideMenu {
+                    name: "Variables".to_string(),
+                    icon_path: "assets/variables_icon.svg".to_string(),
+                },
+                SideMenu {
+                    name: "Diagnostics".to_string(),
+                    icon_path: "assets/diagnostics_icon.svg".to_string(),
+                },
+                SideMenu {
+                    name: "Info".to_string(),
+                    icon_path: "assets/info_icon.png".to_string(),
+                },
+            ],
+            open_side_menu: None,
+
+            resizing: None,
+            resize_before: None,
+
+            link,
+            tasks: vec![],
+
+            focus_node_ref: NodeRef::default(),
+            next_focus_node_ref: NodeRef::default(),
+
+            shift_key_pressed: false,
+
+            default_nested_row_cols: non_zero_u32_tuple((
+                nested_grid.rows.max(1),
+                nested_grid.cols.max(1),
+            )),
+
+            context_menu_position: None,
+            dragged_row: None,
+            tab_menu: None,
+            renaming_tab: None,
+            dragged_tab: None,
+
+            default_definition_name: "".to_string(),
+
+            mouse_cursor: CursorType::Default,
+
+            lookups: vec![],
+
+            preferences,
+            storage,
+        };
+        m.bootstrap_session();
+        sync_spellcheck_dictionary(&m.preferences);
+        sync_automation(&m.preferences);
+        // resume from the tabs that were open when the app was last closed
+        if let Some(workspace) = Workspace::load(&m.storage) {
+            m.restore_workspace(workspace);
+        }
+        m.recovery = Autosave::load(&m.storage);
+        m.schedule_autosave();
+        let persist_workspace = m.link.callback(|_: ()| Action::PersistWorkspace);
+        let on_exit = move || persist_workspace.emit(());
+        js! {
+            window.addEventListener("beforeunload", @{on_exit});
+        };
+        // everything the main process sends comes in on one channel (see `ipc::Event`)
+        let main_event = m.link.callback(|message: String| match ipc::decode_event(&message) {
+            Ok(Event::ConfirmQuit) => Action::RequestQuit,
+            Ok(Event::ShellFinished {
+                coord,
+                succeeded,
+                output,
+            }) => match Coordinate::parse(&coord) {
+                Some(coord) => Action::ShellFinished(coord, succeeded, output),
+                None => Action::Noop,
+            },
+            Ok(Event::DataSourceChanged { path }) => Action::ExternalSourceChanged(path),
+            Ok(Event::OpenFile { path }) => Action::OpenSessionPath(path),
+            Ok(Event::AutomationCall { id, body }) => Action::AutomationCall(id, body),
+            Err(e) => Action::Alert(e),
+        });
+        let on_main_event = move |message: String| main_event.emit(message);
+        js! {
+            let on_main_event = @{on_main_event};
+            require("electron").ipcRenderer.on(@{ipc::EVENT_CHANNEL}, (event, message) => on_main_event(message));
+        };
+        if let Ok(serde_json::Value::Array(paths)) = ipc::send_sync(&ipc::Request::TakeOpenedFiles) {
+            let actions = paths
+                .into_iter()
+                .filter_map(|path| path.as_str().map(|p| Action::OpenSessionPath(p.to_string())))
+                .collect();
+            m.link.send_message_batch(actions);
+        }
+        // session files dropped onto the window open in new tabs. Electron exposes their paths,
+        // drops of anything else are left to the cells they land on
+        let open_dropped = m.link.callback(Action::OpenSessionPath);
+        let on_drop = move |path: String| open_dropped.emit(path);
+        js! {
+            let on_drop = @{on_drop};
+            let session_files = (event) => Array.from(event.dataTransfer && event.dataTransfer.files || [])
+                .filter((file) => file.path && file.name.toLowerCase().endsWith(".json"));
+            document.addEventListener("dragover", (event) => {
+                if (Array.from(event.dataTransfer.types || []).includes("Files")) {
+                    event.preventDefault();
+                }
+            });
+            document.addEventListener("drop", (event) => {
+                let files = session_files(event);
+                if (files.length > 0) {
+                    event.preventDefault();
+                    files.forEach((file) => on_drop(file.path));
+                }
+            });
+        };
+        let driver_request = m.link.callback(|call: String| match serde_json::from_str(&call) {
+            Ok(call) => Action::DriverRequest(call),
+            Err(e) => Action::Alert(format! {"invalid driver call: {}", e}),
+        });
+        let on_driver_request = move |call: String| driver_request.emit(call);
+        let driver_print = m.link.callback(|(driver, method, text): (String, String, String)| {
+            Action::DriverConsole(driver, ConsoleLevel::from_method(&method), text)
+        });
+        let on_driver_print =
+            move |driver: String, method: String, text: String| driver_print.emit((driver, method, text));
+        let driver_error = m.link.callback(|(source, message)| Action::DriverError(source, message));
+        let on_driver_error = move |source: String, message: String| driver_error.emit((source, message));
+        let driver_loaded = m.link.callback(|(driver, error)| Action::DriverLoaded(driver, error));
+        let on_driver_loaded = move |driver: String, error: String| driver_loaded.emit((driver, error));
+        // drivers reach the session through a bridge whose calls return promises, settled by
+        // `settle_driver_call`. Network & file access happen in the driver once they're allowed.
+        // What they log goes to their console in the drivers panel as well as devtools. They run
+        // without node's globals, and see `window` through a proxy hiding them.
+        m.driver_bridge = js! {
+            let request = @{on_driver_request};
+            let loaded = @{on_driver_loaded};
+            let print = @{on_driver_print};
+            let report_error = @{on_driver_error};
+            let pending = {};
+            let subscriptions = {};
+            let hooks = {};
+            let next_id = 1;
+            let format = (arg) => {
+                if (typeof arg === "string") {
+                    return arg;
+                }
+                if (arg instanceof Error) {
+                    return String(arg.stack || arg);
+                }
+                try {
+                    return JSON.stringify(arg);
+                } catch (e) {
+                    return String(arg);
+                }
+            };
+            window.addEventListener("error", (event) => {
+                let source = (event.filename || "") + "\n" + (event.error && event.error.stack || "");
+                report_error(source, event.message);
+            });
+            window.addEventListener("unhandledrejection", (event) => {
+                let reason = event.reason;
+                report_error(reason && reason.stack || "", "Uncaught (in promise) " + format(reason));
+            });
+            let hidden = new Set(@{NODE_GLOBALS.to_vec()});
+            let sandbox = new Proxy(window, {
+                get: (target, key) => {
+                    if (hidden.has(key)) {
+                        return undefined;
+                    }
+                    let value = target[key];
+                    // methods like `fetch` have to be called on the real window
+                    return typeof value === "function" && !value.prototype ? value.bind(target) : value;
+                },
+                has: (target, key) => !hidden.has(key) && key in target,
+                set: (target, key, value) => {
+                    target[key] = value;
+                    return true;
+                },
+            });
+            let for_driver = (driver) => {
+                let call = (op, args) => new Promise((resolve, reject) => {
+                    let id = next_id++;
+                    pending[id] = { resolve, reject };
+                    request(JSON.stringify({ driver, id, op, args }));
+                });
+                let bridge = {
+                    getCell: (coord) => call("getCell", [coord]),
+                    setCell: (coord, value) => call("setCell", [coord, String(value)]),
+                    fetch: (url, options) => call("fetch", [url]).then(() => window.fetch(url, options)),
+                    readFile: (path) => call("readFile", [path])
+                        .then(() => require("fs").readFileSync(path, "utf8")),
+                    console: ["log", "info", "debug", "warn", "error"].reduce((console, method) => {
+                        console[method] = (...args) => {
+                            window.console[method](...args);
+                            print(driver, method, args.map(format).join(" "));
+                        };
+                        return console;
+                    }, Object.create(window.console)),
+                    // `callback` gets the changes to the cells in `range` after each action,
+                    // as a list of { coord, old, new }
+                    subscribe: (range, callback) => {
+                        let id = next_id++;
+                        subscriptions[id] = (changes) => {
+                            try {
+                                callback(changes);
+                            } catch (e) {
+                                bridge.console.error(e);
+                            }
+                        };
+                        return call("subscribe", [String(id), range]).then(() => id, (e) => {
+                            delete subscriptions[id];
+                            throw e;
+                        });
+                    },
+                    unsubscribe: (id) => {
+                        delete subscriptions[id];
+                        return call("unsubscribe", [String(id)]);
+                    },
+                    // `hook` transforms the session (as it's saved in a file) at `stage`,
+                    // "pre-save" or "post-load", after the hooks of a lower `order`
+                    registerHook: (stage, name, hook, order) => {
+                        hooks[driver + "/" + name] = hook;
+                        return call("registerHook", [stage, name, String(order || 0)]);
+                    },
+                };
+                return bridge;
+            };
+            return {
+                // runs a driver's source (see `wrap_source`) with a bridge of its own
+                load: (driver, source) => {
+                    let bridge = for_driver(driver);
+                    try {
+                        (0, eval)(source).call(sandbox, bridge, bridge.console, sandbox, sandbox, sandbox);
+                        loaded(driver, "");
+                    } catch (e) {
+                        loaded(driver, String(e && e.stack || e));
+                    }
+                },
+                notify: (id, changes) => {
+                    let callback = subscriptions[id];
+                    if (callback) {
+                        callback(JSON.parse(changes));
+                    }
+                },
+                // runs the hook `name` of `driver` on a session (as JSON), which it can change in
+                // place or return a new one
+                runHook: (driver, name, session) => {
+                    let hook = hooks[driver + "/" + name];
+                    if (!hook) {
+                        return JSON.stringify({ ok: false, value: "its driver isn't loaded" });
+                    }
+                    try {
+                        let parsed = JSON.parse(session);
+                        let result = hook(parsed);
+                        return JSON.stringify({ ok: true, value: JSON.stringify(result === undefined ? parsed : result) });
+                    } catch (e) {
+                        return JSON.stringify({ ok: false, value: String(e && e.message || e) });
+                    }
+                },
+                settle: (id, ok, value) => {
+                    let call = pending[id];
+                    if (call) {
+                        delete pending[id];
+                        ok ? call.resolve(value) : call.reject(new Error(value));
+                    }
+                },
+            };
+        };
+        // load suggestions from
+        m.meta_suggestions = m
+            .query_col(coord_col!("meta", "A"))
+            .iter()
+            .filter_map(|coord| {
+                if let Some(name) = m.get_session().grammars.get(coord).map(|g| g.name.clone()) {
+                    Some((name, coord.clone()))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        m.column_index = build_column_index(&m.get_session().grammars);
+        m
+    }
+
+    fn update(&mut self, event_type: Self::Message) -> ShouldRender {
+        // actions applied by other actions on their way aren't recorded, replaying the outer
+        // action applies them again
+        let outermost = !self.applying;
+        if outermost {
+            self.record_step(&event_type);
+        }
+        // an action that changes the recorded session without being recorded itself leaves the
+        // repro log incomplete, which is told by it recording undo history
+        let unreplayable = outermost && self.is_recording() && step_of(&event_type).is_none();
+        let records = self.history.records();
+        // what a structural deletion removes is taken before it's applied, and put in the trash
+        // once it turns out to have removed something
+        let trashed = if outermost {
+            self.trash_entry(&event_type)
+                .map(|entry| (self.current_session_index, self.get_session().grammars.len(), entry))
+        } else {
+            None
+        };
+        let animated = outermost && self.preferences.animations && event_type.animates();
+        if animated {
+            animation::capture(&self.cell_keys());
+        }
+        let unrecorded = if outermost { self.unrecorded_change(&event_type) } else { None };
+        self.applying = true;
+        let started = now_ms();
+        let should_render = self.apply(event_type);
+        if outermost {
+            self.applying = false;
+            self.last_update_ms = now_ms() - started;
+        }
+        if let Some((records, before)) = unrecorded {
+            if self.history.records() == records && self.changed_since(&before) {
+                self.history.record(before);
+            }
+        }
+        if unreplayable && self.history.records() != records {
+            self.mark_repro_incomplete();
+        }
+        if animated && should_render {
+            animation::play(&self.cell_keys());
+        }
+        if let Some((index, count, entry)) = trashed {
+            if index == self.current_session_index && self.get_session().grammars.len() < count {
+                push_entry(self.trash.entry(index).or_default(), entry);
+            }
+        }
+        should_render
+    }
+
+    fn view(&self) -> Html {
+        let started = now_ms();
+        let html = self.render();
+        self.last_view_ms.set(now_ms() - started);
+        html
+    }
+}
+
+impl Model {
+    // The update function is split into sub-update functions that
+    // are specifc to each EventType
+    fn apply(&mut self, event_type: Action) -> ShouldRender {
+        let mutated = event_type.mutates_session();
+        if mutated {
+            self.get_session_mut().view.dirty = true;
+        }
+        if !self.plugins.is_empty() {
+            let session = &self.sessions[self.current_session_index];
+            let mut follow_ups = vec![];
+            for plugin in self.plugins.iter_mut() {
+                follow_ups.extend(plugin.on_action(&event_type, session));
+            }
+            self.link.send_message_batch(follow_ups);
+        }
+        let should_render = match event_type {
+            Action::Noop => false,
+
+            Action::SetPreferences(preferences) => {
+                if preferences.custom_dictionary != self.preferences.custom_dictionary {
+                    sync_spellcheck_dictionary(&preferences);
+                }
+                if preferences.automation != self.preferences.automation {
+                    sync_automation(&preferences);
+                }
+                preferences.store(&mut self.storage);
+                if preferences.nested_grid != self.preferences.nested_grid {
+                    self.default_nested_row_cols = non_zero_u32_tuple((
+                        preferences.nested_grid.rows.max(1),
+                        preferences.nested_grid.cols.max(1),
+                    ));
+                }
+                let reschedule = preferences.autosave_interval_s != self.preferences.autosave_interval_s;
+                let recalculate = preferences.calc_mode == CalcMode::Automatic
+                    && self.preferences.calc_mode == CalcMode::Manual
+                    && !self.stale.is_empty();
+                self.preferences = preferences;
+                if reschedule {
+                    self.schedule_autosave();
+                }
+                if recalculate {
+                    self.update(Action::Recalculate);
+                }
+                true
+            }
+
+            Action::Alert(message) => {
+                self.console.log(&message);
+                // TODO: make this into a more visual thing
+                false
+            }
+
+            Action::ChangeInput(coord, new_value) => {
+                self.formula_edit = if is_formula(&new_value) {
+                    Some(coord.clone())
+                } else {
+                    None
+                };
+                set_data_cell(&coord.clone(), new_value.clone().to_string());
+                let is_placeholder = self.placeholders.contains(&coord);
+                if let Some(g) = self.get_session_mut().grammars.get_mut(&coord) {
+                    match g {
+                        Grammar {
+                            kind: Kind::Input(_),
+                            ..
+                        } => {
+                            g.kind = Kind::Input(new_value.clone());
+                            // a placeholder that's been typed into is filled
+                            if !new_value.is_empty() && is_placeholder {
+                                g.style.placeholder = None;
+                            }
+                        }
+                        Grammar {
+                            kind: Kind::Lookup(_, lookup_type),
+                            ..
+                        } => {
+                            g.kind = Kind::Lookup(new_value, lookup_type.clone());
+                        }
+                        _ => (),
+                    }
+                }
+
+                // grids that auto-expand get a new row/col once their last row/col is typed into
+                let parent_grammar = coord
+                    .parent()
+                    .and_then(|p| self.get_session().grammars.get(&p).cloned());
+                if let Some(Grammar {
+                    kind: Kind::Grid(sub_coords),
+                    style,
+                    ..
+                }) = parent_grammar
+                {
+                    if style.auto_expand && !new_value.is_empty() {
+                        let parent = coord.parent().unwrap();
+                        let (row, col) = coord.row_col();
+                        let is_last_row = sub_coords.iter().all(|(r, _)| *r <= row);
+                        let is_last_col = sub_coords.iter().all(|(_, c)| *c <= col);
+                        if is_last_row {
+                            append_grid_line(self, &parent, true);
+                        }
+                        if is_last_col {
+                            append_grid_line(self, &parent, false);
+                        }
+                        return is_last_row || is_last_col;
+                    }
+                }
+
+                false
+            }
+
+            Action::ToggleAutoExpand(coord) => {
+                let before = self.layout_snapshot();
+                if let Some(g @ Grammar {
+                    kind: Kind::Grid(_),
+                    ..
+                }) = self.get_session_mut().grammars.get_mut(&coord)
+                {
+                    g.style.auto_expand = !g.style.auto_expand;
+                    self.history.record(before);
+                    return true;
+                }
+                false
+            }
+
+            Action::SetActiveCell(coord) => {
+                self.formula_edit = match self.get_session().grammars.get(&coord).map(|g| &g.kind) {
+                    Some(Kind::Input(text)) if is_formula(text) => Some(coord.clone()),
+                    _ => None,
+                };
+                self.active_cell = Some(coord.clone());
+                self.focus_cell = Some(coord.clone());
+                focus_on_cell(&coord);
+                true
+            }
+
+            Action::NextSuggestion(coord, index) => {
+                let next_suggestion_id =
+                    format! {"cell-{}-suggestion-{}", coord.to_string(), index};
+                js! {
+                    try {
+                        let element = document.getElementById(@{next_suggestion_id.clone()});
+                        element.focus();
+                    } catch (e) {
+                        console.log("cannot focus on next suggestion");
+                    }
+                };
+                true
+            }
+
+            Action::ReadCSVFile(file, coord) => {
+                let upload_callback = self.link.callback(move |file_data: FileData| {
+                    Action::LoadCSVFile(file_data.clone(), coord.clone())
+                });
+                let task = self.reader.read_file(file, upload_callback.clone());
+                self.tasks.push(task);
+                false
+            }
+
+            Action::LoadCSVFile(file_data, coordinate) => {
+                let csv = std::str::from_utf8(&file_data.content).unwrap().to_string();
+                let mut reader = csv::Reader::from_reader(csv.as_bytes());
+                let mut grid: Vec<Vec<String>> = Vec::new();
+                let headers_csv = reader.headers().unwrap();
+                let mut header_row: Vec<String> = Vec::new();
+                let len_header = headers_csv.len() as i32;
+
+                for header in 0..len_header {
+                    let header_usize = header as usize;
+                    header_row.push(headers_csv.get(header_usize).unwrap().to_string());
+                }
+                grid.push(header_row);
+
+                for row in reader.records() {
+                    let mut grid_row = Vec::new();
+                    let row = row.unwrap();
+                    let lenght_r = row.len() as i32;
+                    for cell in 0..lenght_r {
+                        let cell_usize = cell as usize;
+                        grid_row.push(row.get(cell_usize).unwrap().to_string());
+                    }
+                    grid.push(grid_row);
+                }
+                let num_rows = grid.len();
+                let num_cols = grid[0].len();
+
+                self.update(Action::AddNestedGrid(
+                    coordinate.clone(),
+                    (num_rows as u32, num_cols as u32),
+                ));
+
+                let parent = coordinate.parent().unwrap();
+                if let Some(Grammar {
+                    kind: Kind::Grid(sub_coords),
+                    name,
+                    style,
+                }) = self.get_session().grammars.get(&parent)
+                {
+                    let mut grammar = self.get_session().grammars.clone();
+                    for coord_ in sub_coords {
+                        let row_ = coord_.0.get() as usize;
+                        let col_ = coord_.1.get() as usize;
+                        let c = Coordinate::child_of(&coordinate, *coord_);
+                        let grid_: &str = &grid[row_ - 1][col_ - 1];
+                        grammar.remove(&c);
+                        grammar.insert(c, Grammar::input("", grid_));
+                    }
+                    self.get_session_mut().grammars = grammar;
+                }
+
+                true
+            }
+
+            Action::Select(SelectMsg::Start(coord)) => {
+                let session = &self.sessions[self.current_session_index];
+                self.selection.start(&session.grammars, &coord);
+                true
+            }
+            Action::Select(SelectMsg::Add(coord)) => {
+                let session = &self.sessions[self.current_session_index];
+                self.selection.add(&session.grammars, &coord);
+                true
+            }
+            Action::Select(SelectMsg::End(coord)) => {
+                let session = &self.sessions[self.current_session_index];
+                self.selection.extend(&session.grammars, &coord)
+            }
+
+            Action::RangeDelete() => {
+                let region = match self.selection.primary() {
+                    Some(region) => region.clone(),
+                    None => return false,
+                };
+                // the cells of every range are cleared, including the ones added with Ctrl+click
+                let coords: Vec<Coordinate> = self.selection.coords().collect();
+                for coord in coords.iter() {
+                    clear_cell(self, coord);
+                }
+
+                let row_range = region.rows.0..=region.rows.1;
+                let col_range = region.cols.0..=region.cols.1;
+                let parent_check = Some(region.parent);
+
+                let mut ref_grammars = self.get_session_mut().grammars.clone();
+<<<<<<< HEAD
+                for (coord, grammar) in ref_grammars.clone().iter_mut() {
+                    if row_range.contains(&coord.row().get())
+                        && col_range.contains(&coord.col().get())
+                        && coord.parent() == parent_check
+                    {
+                        let get_kind = grammar.kind.clone();
+                        match get_kind {
+                            Kind::Input(value) => {
+                                grammar.kind = Kind::Input("".to_string());
+                                self.get_session_mut()
+=======
+                for (coord, grammar) in ref_grammars.clone().iter_mut() {              
+                        if row_range.contains(&coord.row().get()) && col_range.contains(&coord.col().get()) && coord.parent() == parent_check                    
+                        {                                                       
+                            let get_kind = grammar.kind.clone();
+                            match get_kind {
+                                Kind::Input(value) => {
+                                    grammar.kind =  Kind::Input("".to_string());
+                                    set_data_cell(&coord, "".to_string());                                 
+                                    self.get_session_mut()
+>>>>>>> hieule/fix_bug
+                                    .grammars
+                                    .insert(coord.clone(), grammar.clone());
+                            }
+                            Kind::Grid(sub_coords) => {
+                                for (c, g) in ref_grammars.clone().iter_mut() {
+                                    if c.parent().is_some() && c.parent().unwrap() == coord.clone()
+                                    {
+                                        g.kind = Kind::Input("".to_string());
+                                        self.get_session_mut()
+                                            .grammars
+                                            .insert(c.clone(), g.clone());
+<<<<<<< HEAD
+=======
+                                            set_data_cell(&c, "".to_string());
+                                        }
+>>>>>>> hieule/fix_bug
+                                    }
+                                }
+                            }
+                            _ => continue,
+                        }
+                    }
+                }
+                true
+            }
+
+            Action::MergeCells() => {
+                let region = match self.selection.ranges() {
+                    [region] => region.clone(),
+                    [] => {
+                        info!("Expect for select of two coord");
+                        return false;
+                    }
+                    _ => return self.update(Action::Alert("only a single range can be merged".to_string())),
+                };
+                let (first_row, first_col) = (region.rows.0, region.cols.0);
+                let (last_row, last_col) = (region.rows.1, region.cols.1);
+                // merging clears the merged cells, so it's undone as a content edit
+                let before = self.snapshot();
+
+                let mut merge_height = 0.00;
+                let mut merge_width = 0.00;
+                let mut max_coord = Coordinate::default();
+                let mut max_grammar = Grammar::default();
+                // any nested cell can be merged, including those of meta and other sheets
+                let coords: Vec<Coordinate> = self.selection.coords().collect();
+                for coord in coords {
+                    let mut grammar = match self.get_session().grammars.get(&coord) {
+                        Some(grammar) => grammar.clone(),
+                        None => continue,
+                    };
+                    let coord_style = grammar.style.clone();
+                    if coord_style.display != false {
+                        if coord.row().get() == last_row {
+                            merge_width = merge_width + coord_style.width;
+                        }
+                        if coord.col().get() == last_col {
+                            merge_height = merge_height + coord_style.height;
+                        }
+                        if coord.row().get() == last_row && (coord.col().get() == last_col) {
+                            max_coord = coord.clone();
+                            max_grammar = grammar.clone();
+                        } else {
+                            grammar.style.display = false;
+                        }
+                    }
+                    grammar.kind = Kind::Input("".to_string());
+                    grammar.style.col_span = (first_col, last_col);
+                    grammar.style.row_span = (first_row, last_row);
+                    self.get_session_mut().grammars.insert(coord, grammar);
+                }
+                max_grammar.kind = Kind::Input("".to_string());
+                max_grammar.style.width = merge_width;
+                max_grammar.style.height = merge_height;
+                max_grammar.style.col_span = (first_col, last_col);
+                max_grammar.style.row_span = (first_row, last_row);
+                self.get_session_mut()
+                    .grammars
+                    .insert(max_coord.clone(), max_grammar.clone());
+                self.history.record(before);
+                true
+            }
+
+            Action::SetCellSpan(coord, (rows, cols)) => {
+                let parent = match coord.parent() {
+                    Some(parent) => parent,
+                    None => return false,
+                };
+                let mut parent_grammar = match self.get_session().grammars.get(&parent) {
+                    Some(g @ Grammar {
+                        kind: Kind::Grid(_),
+                        ..
+                    }) => g.clone(),
+                    _ => return false,
+                };
+                let sub_coords = match &parent_grammar.kind {
+                    Kind::Grid(sub_coords) => sub_coords.clone(),
+                    _ => vec![],
+                };
+                let (row, col) = coord.row_col();
+                let (last_row, last_col) = (row.get() + rows.get() - 1, col.get() + cols.get() - 1);
+                // the span has to stay inside the grid...
+                if !sub_coords.contains(&non_zero_u32_tuple((last_row, last_col))) {
+                    info! {"[Action::SetCellSpan] span goes past the edge of {}", parent.to_string()};
+                    return false;
+                }
+                // ... and can't overlap with the span of another child
+                let overlaps = parent_grammar.style.spans.iter().any(|((r, c), (rs, cs))| {
+                    (*r, *c) != (row, col)
+                        && r.get() <= last_row
+                        && row.get() < r.get() + rs.get()
+                        && c.get() <= last_col
+                        && col.get() < c.get() + cs.get()
+                });
+                if overlaps {
+                    info! {"[Action::SetCellSpan] span overlaps with another spanning cell"};
+                    return false;
+                }
+                let before = self.layout_snapshot();
+                parent_grammar.style.spans.retain(|(c, _)| *c != (row, col));
+                if rows.get() > 1 || cols.get() > 1 {
+                    parent_grammar.style.spans.push(((row, col), (rows, cols)));
+                }
+                // size the spanning cell to cover the rows & columns it spans
+                let defaults = self.grid_defaults(&parent);
+                let width: f64 = (col.get()..=last_col)
+                    .map(|c| {
+                        *self
+                            .col_widths
+                            .get(&Col(parent.clone(), NonZeroU32::new(c).unwrap()))
+                            .unwrap_or(&defaults.col_width)
+                    })
+                    .sum();
+                let height: f64 = (row.get()..=last_row)
+                    .map(|r| {
+                        *self
+                            .row_heights
+                            .get(&Row(parent.clone(), NonZeroU32::new(r).unwrap()))
+                            .unwrap_or(&defaults.row_height)
+                    })
+                    .sum();
+                self.get_session_mut()
+                    .grammars
+                    .insert(parent.clone(), parent_grammar);
+                if let Some(g) = self.get_session_mut().grammars.get_mut(&coord) {
+                    g.style.width = width + /* inner borders */ 2.0 * (cols.get() - 1) as f64;
+                    g.style.height = height + 2.0 * (rows.get() - 1) as f64;
+                }
+                self.history.record(before);
+                self.selection.clear();
+                true
+            }
+
+            Action::AddOutlineGroup(axis) => {
+                let group = match self.selection.corners() {
+                    Some((first, last)) => OutlineGroup::from_selection(&first, &last, axis),
+                    None => None,
+                };
+                match group {
+                    Some(group) => {
+                        // nested outline levels aren't supported yet, so overlapping groups
+                        // on the same axis are rejected
+                        if self
+                            .get_session()
+                            .outline_groups
+                            .iter()
+                            .any(|g| g.overlaps(&group))
+                        {
+                            info! {"[Action::AddOutlineGroup] selection overlaps an existing group"};
+                            return false;
+                        }
+                        self.get_session_mut().outline_groups.push(group);
+                        true
+                    }
+                    None => {
+                        info! {"[Action::AddOutlineGroup] expect a selection of more than one row/col within a grid"};
+                        false
+                    }
+                }
+            }
+
+            Action::ToggleOutlineGroup(index) => {
+                let group = match self.get_session().outline_groups.get(index) {
+                    Some(group) => group.clone(),
+                    None => return false,
+                };
+                self.get_session_mut().outline_groups[index].collapsed = !group.collapsed;
+                // move the active cell onto the summary line if it just got hidden
+                if let Some(active) = self.active_cell.clone() {
+                    if is_hidden(&self.get_session().outline_groups, &active) {
+                        let depth = group.parent.row_cols.len();
+                        let mut summary = active.truncate(depth + 1).unwrap_or(active.clone());
+                        match group.axis {
+                            OutlineAxis::Rows => summary.row_cols[depth].0 = group.start,
+                            OutlineAxis::Cols => summary.row_cols[depth].1 = group.start,
+                        }
+                        self.active_cell = Some(summary);
+                    }
+                }
+                true
+            }
+
+            Action::RemoveOutlineGroup(index) => {
+                if index < self.get_session().outline_groups.len() {
+                    self.get_session_mut().outline_groups.remove(index);
+                }
+                true
+            }
+
+            Action::DoCompletion(source_coord, dest_coord) => {
+                // count the completion towards the "Recent" section of the dropdown
+                if let Some(name) = self.get_session().grammars.get(&source_coord).map(|g| g.name.clone()) {
+                    let mut preferences = self.preferences.clone();
+                    *preferences.suggestion_usage.entry(name).or_insert(0) += 1;
+                    self.update(Action::SetPreferences(preferences));
+                }
+                // definitions with parameters prompt for their arguments before they're stamped
+                let params = parameters(&self.get_session().grammars, &source_coord);
+                if !params.is_empty() {
+                    self.defn_arguments = Some(DefnArguments::new(source_coord, dest_coord, params));
+                    return true;
+                }
+                // definitions are stamped with their sub-rules, other grammars are copied over
+                let before = self.snapshot();
+                let declared = self.get_session().grammars.get(&dest_coord).and_then(|g| g.style.shape.clone());
+                if !instantiate(&mut self.get_session_mut().grammars, &source_coord, &dest_coord) {
+                    move_grammar(self, source_coord, dest_coord.clone());
+                }
+                if !self.check_shape(declared, &dest_coord, before) {
+                    return true;
+                }
+                // snippets with placeholders enter placeholder-hopping mode at their first placeholder
+                self.placeholders = placeholders_of(&self.get_session().grammars, &dest_coord);
+                if let Some(first) = self.placeholders.first().cloned() {
+                    self.update(Action::SetActiveCell(first));
+                }
+                true
+            }
+
+            Action::ConvertKind(tag) => {
+                let coord = match self.active_cell.clone() {
+                    Some(c) => c,
+                    None => return false,
+                };
+                let before = self.snapshot();
+                if convert_kind(self, &coord, tag) {
+                    self.history.record(before);
+                    return true;
+                }
+                info! {"[Action::ConvertKind] cannot convert {} into {}", coord.to_string(), tag.label()};
+                false
+            }
+
+            Action::SetStructuralQuery(query) => {
+                self.structural_query = query;
+                true
+            }
+
+            Action::CommitStructuralQuery => {
+                let query = self.structural_query.clone();
+                self.record_history(HistoryKind::Search, &query);
+                false
+            }
+
+            Action::GoTo(text) => {
+                self.goto_text = None;
+                self.history_cursor = None;
+                let coord = match Coordinate::parse(text.trim()) {
+                    Some(c) if self.get_session().grammars.contains_key(&c) => c,
+                    _ => {
+                        info! {"[Action::GoTo] no cell at {}", text};
+                        return true;
+                    }
+                };
+                self.record_history(HistoryKind::GoTo, &text);
+                // cells outside of the shown sheet are reached by switching to their sheet
+                let sheet = coord.truncate(1).unwrap_or(coord.clone());
+                if self.view_root.truncate(1).unwrap_or(self.view_root.clone()) != sheet {
+                    self.view_root = sheet;
+                }
+                self.update(Action::SetActiveCell(coord))
+            }
+
+            Action::BrowseHistory(kind, older) => {
+                let (tab, global) = match kind {
+                    HistoryKind::GoTo => (
+                        &self.get_session().view.goto_history,
+                        &self.preferences.goto_history,
+                    ),
+                    HistoryKind::Search => (
+                        &self.get_session().view.search_history,
+                        &self.preferences.search_history,
+                    ),
+                };
+                let history = merged_history(tab, global);
+                let cursor = match self.history_cursor {
+                    Some((k, i)) if k == kind => Some(i),
+                    _ => None,
+                };
+                let cursor = step_cursor(cursor, history.len(), older);
+                self.history_cursor = cursor.map(|i| (kind, i));
+                let entry = cursor.map(|i| history[i].clone());
+                match kind {
+                    HistoryKind::GoTo => self.goto_text = entry,
+                    HistoryKind::Search => self.structural_query = entry.unwrap_or_default(),
+                }
+                true
+            }
+
+            Action::BulkConvert(coords, tag) => {
+                let before = self.snapshot();
+                let mut converted = false;
+                for coord in coords.iter() {
+                    converted |= convert_kind(self, coord, tag);
+                }
+                if converted {
+                    self.history.record(before);
+                }
+                converted
+            }
+
+            Action::BulkRestyle(coords, attr, value) => {
+                let before = self.layout_snapshot();
+                let mut restyled = false;
+                for coord in coords.iter() {
+                    if let Some(g) = self.get_session_mut().grammars.get_mut(coord) {
+                        restyled |= set_style_attr(&mut g.style, &attr, &value);
+                    }
+                }
+                if restyled {
+                    self.history.record(before);
+                } else {
+                    info! {"[Action::BulkRestyle] cannot set {} to {}", attr, value};
+                }
+                restyled
+            }
+
+            Action::RestyleSelection(attr, value) => {
+                let coords: Vec<Coordinate> = self.selection.coords().collect();
+                if coords.is_empty() {
+                    return self.update(Action::Notify("Select the cells to restyle".to_string(), true));
+                }
+                self.update(Action::BulkRestyle(coords, attr, value))
+            }
+
+            Action::BulkDelete(coords) => {
+                if coords.is_empty() {
+                    return false;
+                }
+                let before = self.snapshot();
+                for coord in coords.iter() {
+                    clear_cell(self, coord);
+                }
+                self.history.record(before);
+                true
+            }
+
+            Action::CopyAsMarkdown => {
+                match self.export_region() {
+                    Some(region) => {
+                        copy_to_clipboard(to_markdown(&self.get_session().grammars, &region))
+                    }
+                    None => info! {"[Action::CopyAsMarkdown] nothing to copy"},
+                }
+                false
+            }
+
+            Action::ExportJson(copy) => {
+                let region = match self.export_region() {
+                    Some(region) => region,
+                    None => return self.update(Action::Alert("select the table to export first".to_string())),
+                };
+                let content = to_json(&self.get_session().grammars, &region, self.preferences.json_export);
+                if copy {
+                    copy_to_clipboard(content);
+                    return self.update(Action::Notify("Copied as JSON".to_string(), false));
+                }
+                let path = match choose_file(true, "JSON", &["json"]) {
+                    Some(path) => path,
+                    None => return false,
+                };
+                let written: bool = js! {
+                    try {
+                        require("fs").writeFileSync(@{path.clone()}, @{content});
+                        return true;
+                    } catch (e) {
+                        return false;
+                    }
+                }
+                .try_into()
+                .unwrap_or(false);
+                if !written {
+                    return self.update(Action::Alert(format! {"couldn't write {}", path}));
+                }
+                self.update(Action::Notify(format! {"Exported {} to {}", region.parent.to_string(), path}, false))
+            }
+
+            Action::CopyCells => {
+                if self.selection.ranges().len() > 1 {
+                    return self.update(Action::Alert("only a single range can be copied".to_string()));
+                }
+                let region = match self.export_region() {
+                    Some(region) => region,
+                    None => {
+                        info! {"[Action::CopyCells] nothing to copy"};
+                        return false;
+                    }
+                };
+                let grammars = &self.get_session().grammars;
+                copy_table_to_clipboard(to_tsv(grammars, &region), to_html(grammars, &region));
+                let entry = ClipEntry::of_region(&now_iso(), grammars, &self.row_heights, &self.col_widths, &region);
+                match entry {
+                    Some(entry) => {
+                        push_clip(&mut self.clipboard_history, entry);
+                        true
+                    }
+                    None => false,
+                }
+            }
+
+            Action::PasteClip(position) => {
+                let entry = match self.clipboard_history.get(position) {
+                    Some(entry) => entry.clone(),
+                    None => return false,
+                };
+                let active = match self.active_cell.clone() {
+                    Some(active) => active,
+                    None => return self.update(Action::Alert("pick a cell to paste into".to_string())),
+                };
+                let before = self.snapshot();
+                let sizes = match paste_entry(&mut self.get_session_mut().grammars, &entry, &active) {
+                    Ok(sizes) => sizes,
+                    Err(e) => return self.update(Action::Alert(format! {"couldn't paste: {}", e})),
+                };
+                self.apply_sizes(sizes);
+                self.history.record(before);
+                self.column_index = build_column_index(&self.get_session().grammars);
+                true
+            }
+
+            Action::DiscardClip(position) => {
+                if position < self.clipboard_history.len() {
+                    self.clipboard_history.remove(position);
+                    return true;
+                }
+                false
+            }
+
+            Action::ClearClipboardHistory => {
+                self.clipboard_history.clear();
+                true
+            }
+
+            Action::PasteCells => {
+                let active = match self.active_cell.clone() {
+                    Some(active) => active,
+                    None => return false,
+                };
+                let (rows, text) = read_clipboard();
+                let table = match rows.map(|rows| serde_json::from_str::<Vec<Vec<HtmlCell>>>(&rows)) {
+                    Some(Ok(rows)) => lay_out(&rows),
+                    Some(Err(e)) => return self.update(Action::Alert(format! {"couldn't read the pasted table: {}", e})),
+                    None => PastedTable {
+                        cells: parse_tsv(&text),
+                        spans: vec![],
+                    },
+                };
+                let (rows, cols) = (table.cells.len(), table.cells.first().map_or(0, |row| row.len()));
+                if rows == 0 || cols == 0 {
+                    info! {"[Action::PasteCells] nothing to paste"};
+                    return false;
+                }
+                // a single cell is pasted as text
+                if rows == 1 && cols == 1 {
+                    let text = table.cells[0][0].clone();
+                    return self.update(Action::ChangeInput(active, text));
+                }
+                if active.parent().is_none() {
+                    info! {"[Action::PasteCells] can't paste over the root grid"};
+                    return false;
+                }
+                let before = self.snapshot();
+                paste_table(&mut self.get_session_mut().grammars, &active, &table);
+                let defaults = self.grid_defaults(&active);
+                resize_cells(self, &active, (defaults.row_height, defaults.col_width));
+                // size merged cells to cover the rows & columns they span, like SetCellSpan does
+                for ((row, col), (span_rows, span_cols)) in table.spans.iter() {
+                    let width: f64 = (col.get()..col.get() + span_cols.get())
+                        .map(|c| {
+                            *self
+                                .col_widths
+                                .get(&Col(active.clone(), NonZeroU32::new(c).unwrap()))
+                                .unwrap_or(&defaults.col_width)
+                        })
+                        .sum();
+                    let height: f64 = (row.get()..row.get() + span_rows.get())
+                        .map(|r| {
+                            *self
+                                .row_heights
+                                .get(&Row(active.clone(), NonZeroU32::new(r).unwrap()))
+                                .unwrap_or(&defaults.row_height)
+                        })
+                        .sum();
+                    let coord = Coordinate::child_of(&active, (*row, *col));
+                    if let Some(g) = self.get_session_mut().grammars.get_mut(&coord) {
+                        g.style.width = width + /* inner borders */ 2.0 * (span_cols.get() - 1) as f64;
+                        g.style.height = height + 2.0 * (span_rows.get() - 1) as f64;
+                    }
+                }
+                resize(
+                    self,
+                    active.clone(),
+                    (rows as f64) * defaults.row_height,
+                    (cols as f64) * defaults.col_width,
+                    (defaults.row_height, defaults.col_width),
+                );
+                self.history.record(before);
+                self.column_index = build_column_index(&self.get_session().grammars);
+                true
+            }
+
+            Action::ExportImage(copy) => {
+                match self.export_region() {
+                    Some(region) => {
+                        let (size, cells) = raster::layout(
+                            &self.get_session().grammars,
+                            &self.col_widths,
+                            &self.row_heights,
+                            &region,
+                        );
+                        raster::render(size, &cells, copy);
+                    }
+                    None => info! {"[Action::ExportImage] nothing to export"},
+                }
+                false
+            }
+
+            Action::ToggleCellSpellcheck(coord) => {
+                let before = self.layout_snapshot();
+                if let Some(g) = self.get_session_mut().grammars.get_mut(&coord) {
+                    g.style.no_spellcheck = !g.style.no_spellcheck;
+                    self.history.record(before);
+                    return true;
+                }
+                false
+            }
+
+            Action::StepValue(coord, count, unit) => {
+                let stepped = match self.get_session().grammars.get(&coord) {
+                    Some(Grammar {
+                        kind: Kind::Input(value),
+                        ..
+                    }) => step_text(value, unit, count),
+                    _ => None,
+                };
+                match stepped {
+                    Some(value) => {
+                        let before = self.snapshot();
+                        self.update(Action::ChangeInput(coord, value));
+                        self.history.record(before);
+                        true
+                    }
+                    None => false,
+                }
+            }
+
+            Action::PickReference(picked) => {
+                let editing = match self.reference_target() {
+                    Some(c) if c != picked => c,
+                    _ => return false,
+                };
+                let value = match self.get_session().grammars.get(&editing).map(|g| &g.kind) {
+                    Some(Kind::Input(v)) | Some(Kind::Lookup(v, _)) => v.clone(),
+                    _ => return false,
+                };
+                let reference = picked.to_string();
+                let mut chars: Vec<char> = value.chars().collect();
+                let offset = caret_offset(&editing).unwrap_or(chars.len()).min(chars.len());
+                chars.splice(offset..offset, reference.chars());
+                let new_value: String = chars.into_iter().collect();
+                self.update(Action::ChangeInput(editing.clone(), new_value));
+                set_caret(&editing, offset + reference.chars().count());
+                true
+            }
+
+            Action::EndFormulaEdit => {
+                self.formula_edit = None;
+                true
+            }
+
+            Action::Recalculate => {
+                let cells: Vec<Coordinate> = if self.stale.is_empty() {
+                    formula_cells(&self.get_session().grammars).into_iter().map(|(c, _)| c).collect()
+                } else {
+                    self.stale.drain().collect()
+                };
+                self.start_calc(cells);
+                true
+            }
+
+            Action::CalcStep => {
+                self.calc_task = None;
+                let split = self.calc_queue.len().saturating_sub(CALC_BATCH);
+                let pending: HashSet<Coordinate> = self.calc_queue.iter().cloned().collect();
+                let batch = self.calc_queue.split_off(split);
+                let grammars = self.get_session().grammars.clone();
+                let variables = self.variables();
+                recalculate(&grammars, &variables, &mut self.values, &batch, &pending);
+                if !self.calc_queue.is_empty() {
+                    self.schedule_calc_step();
+                } else {
+                    if self.get_session().iteration.enabled {
+                        self.iterate_cycles();
+                    } else {
+                        self.mark_cycles();
+                    }
+                    self.spill_arrays();
+                    self.apply_style_formulas();
+                    self.fire_reminders();
+                }
+                true
+            }
+
+            Action::OpenGoalSeek => {
+                self.goal_seek = Some(GoalSeek {
+                    target: self.active_cell.as_ref().map_or("".to_string(), |c| c.to_string()),
+                    ..GoalSeek::default()
+                });
+                true
+            }
+
+            Action::UpdateGoalSeek(form) => {
+                // editing the form invalidates the preview
+                self.goal_seek = Some(GoalSeek {
+                    preview: None,
+                    ..form
+                });
+                true
+            }
+
+            Action::PreviewGoalSeek => {
+                let form = match self.goal_seek.clone() {
+                    Some(form) => form,
+                    None => return false,
+                };
+                let preview = match (
+                    Coordinate::parse(form.target.trim()),
+                    form.desired.trim().parse::<f64>(),
+                    Coordinate::parse(form.variable.trim()),
+                ) {
+                    (Some(target), Ok(desired), Some(variable)) => {
+                        goal_seek(&self.get_session().grammars, &self.variables(), &target, desired, &variable)
+                    }
+                    (None, _, _) => Err(format! {"invalid target cell: {}", form.target}),
+                    (_, Err(_), _) => Err(format! {"invalid value: {}", form.desired}),
+                    (_, _, None) => Err(format! {"invalid input cell: {}", form.variable}),
+                };
+                self.goal_seek = Some(GoalSeek {
+                    preview: Some(preview),
+                    ..form
+                });
+                true
+            }
+
+            Action::ApplyGoalSeek => {
+                let (variable, input) = match &self.goal_seek {
+                    Some(GoalSeek {
+                        variable,
+                        preview: Some(Ok(solution)),
+                        ..
+                    }) => match Coordinate::parse(variable.trim()) {
+                        Some(c) => (c, solution.input),
+                        None => return false,
+                    },
+                    _ => return false,
+                };
+                let before = self.snapshot();
+                self.goal_seek = None;
+                self.update(Action::ChangeInput(variable, input.to_string()));
+                self.history.record(before);
+                true
+            }
+
+            Action::CloseGoalSeek => {
+                self.goal_seek = None;
+                true
+            }
+
+            Action::FindDuplicates => {
+                let (grid, key_cols) = match self.selection.primary() {
+                    Some(region) => (region.parent.clone(), (region.cols.0..=region.cols.1).collect()),
+                    None => match self.active_cell.as_ref().and_then(|c| c.parent()) {
+                        Some(grid) => (grid, vec![]),
+                        None => return false,
+                    },
+                };
+                let rows = duplicate_rows(&self.get_session().grammars, &grid, &key_cols);
+                if rows.is_empty() {
+                    self.duplicates = None;
+                    return self.update(Action::Alert("no duplicate rows found".to_string()));
+                }
+                self.duplicates = Some((grid, rows));
+                true
+            }
+
+            Action::RemoveDuplicates => {
+                let (grid, rows) = match self.duplicates.take() {
+                    Some(duplicates) => duplicates,
+                    None => return false,
+                };
+                let before = self.snapshot();
+                remove_rows(&mut self.get_session_mut().grammars, &grid, &rows);
+                self.history.record(before);
+                // the active cell may have been removed or moved
+                self.selection.clear();
+                if let Some(active) = self.active_cell.clone() {
+                    if !self.get_session().grammars.contains_key(&active) {
+                        self.active_cell = Some(Coordinate::child_of(&grid, non_zero_u32_tuple((1, 1))));
+                    }
+                }
+                true
+            }
+
+            Action::DragRow(row) => {
+                self.dragged_row = row;
+                true
+            }
+
+            Action::MoveRow(grid, from, to) => {
+                self.dragged_row = None;
+                let before = self.snapshot();
+                if !move_line(&mut self.get_session_mut().grammars, &grid, OutlineAxis::Rows, from, to) {
+                    return false;
+                }
+                move_sizes(&mut self.row_heights, &mut self.col_widths, &grid, OutlineAxis::Rows, from, to);
+                let groups = &mut self.get_session_mut().outline_groups;
+                remap_outline_groups(groups, &grid, OutlineAxis::Rows, &|n| moved_line(n, from, to));
+                self.history.record(before);
+                // the moved row stays active
+                self.selection.clear();
+                self.active_cell = Some(Coordinate::child_of(&grid, non_zero_u32_tuple((to, 1))));
+                true
+            }
+
+            Action::DuplicateRow => self.duplicate_active_line(OutlineAxis::Rows),
+
+            Action::DuplicateCol => self.duplicate_active_line(OutlineAxis::Cols),
+
+            Action::SwapActiveCell(axis, forward) => self.swap_active_cell(axis, forward),
+
+            Action::MoveActiveLine(axis, forward) => self.move_active_line(axis, forward),
+
+            Action::ClearDuplicates => {
+                self.duplicates = None;
+                true
+            }
+
+            Action::PruneOrphans => {
+                if orphans(&self.get_session().grammars).is_empty() {
+                    return false;
+                }
+                let before = self.snapshot();
+                let pruned = prune_orphans(&mut self.get_session_mut().grammars);
+                self.history.record(before);
+                self.update(Action::Notify(format! {"Pruned {} orphaned grammars", pruned.len()}, false))
+            }
+
+            Action::RepairIntegrity => {
+                let before = self.snapshot();
+                let repaired = self.repair_integrity();
+                if repaired.is_empty() {
+                    return false;
+                }
+                self.history.record(before);
+                self.update(Action::Notify(format! {"Repaired {} inconsistencies", repaired.len()}, false))
+            }
+
+            Action::OpenTransclude => {
+                self.transclude_form = Some(("".to_string(), "root".to_string()));
+                true
+            }
+
+            Action::UpdateTransclude(path, coord) => {
+                self.transclude_form = Some((path, coord));
+                true
+            }
+
+            Action::ApplyTransclude => {
+                let ((path, source), active) = match (self.transclude_form.clone(), self.active_cell.clone()) {
+                    (Some(form), Some(active)) => (form, active),
+                    _ => return false,
+                };
+                let path = path.trim().to_string();
+                if path.is_empty() {
+                    return self.update(Action::Alert("enter the path of a session file".to_string()));
+                }
+                let source = match Coordinate::parse(source.trim()) {
+                    Some(source) => source,
+                    None => return self.update(Action::Alert(format! {"{} isn't a coordinate", source})),
+                };
+                let before = self.snapshot();
+                if let Some(g) = self.get_session_mut().grammars.get_mut(&active) {
+                    g.kind = Kind::Transclude(path.clone(), source);
+                }
+                self.history.record(before);
+                self.transclude_form = None;
+                // reload, in case the file was broken the last time it was read
+                self.update(Action::LoadTransclusion(path));
+                true
+            }
+
+            Action::CloseTransclude => {
+                self.transclude_form = None;
+                true
+            }
+
+            Action::OpenRename(coord) => {
+                let name = match self.get_session().grammars.get(&coord) {
+                    Some(grammar) if !grammar.name.is_empty() => grammar.name.clone(),
+                    _ => return self.update(Action::Alert("only named meta grammars can be renamed".to_string())),
+                };
+                self.rename_form = Some((coord, name));
+                true
+            }
+
+            Action::UpdateRename(name) => {
+                if let Some((_, new_name)) = self.rename_form.as_mut() {
+                    *new_name = name;
+                }
+                true
+            }
+
+            Action::ApplyRename => {
+                let (coord, new_name) = match self.rename_form.clone() {
+                    Some(form) => form,
+                    None => return false,
+                };
+                if let Err(e) = check_name(&self.meta_suggestions, &coord, &new_name) {
+                    return self.update(Action::Alert(e));
+                }
+                let new_name = new_name.trim().to_string();
+                let old_name = match self.get_session().grammars.get(&coord) {
+                    Some(grammar) => grammar.name.clone(),
+                    None => return false,
+                };
+                let found = usages(&self.get_session().grammars, &coord);
+                let before = self.snapshot();
+                apply_rename(&mut self.get_session_mut().grammars, &found, &new_name);
+                self.history.record(before);
+                info! {"renamed {} to {} in {} places", old_name, new_name, found.len()};
+                // keep the completions counted towards "Recent" under the new name
+                if let Some(count) = self.preferences.suggestion_usage.get(&old_name).cloned() {
+                    let mut preferences = self.preferences.clone();
+                    preferences.suggestion_usage.remove(&old_name);
+                    *preferences.suggestion_usage.entry(new_name).or_insert(0) += count;
+                    self.update(Action::SetPreferences(preferences));
+                }
+                self.rename_form = None;
+                true
+            }
+
+            Action::CloseRename => {
+                self.rename_form = None;
+                true
+            }
+
+            Action::OpenShape(coord) => {
+                let declared = match self.get_session().grammars.get(&coord) {
+                    Some(grammar) => grammar.style.shape.clone().unwrap_or_default(),
+                    None => return false,
+                };
+                self.shape_form = Some((coord, declared));
+                true
+            }
+
+            Action::UpdateShape(declared) => {
+                if let Some((_, text)) = self.shape_form.as_mut() {
+                    *text = declared;
+                }
+                true
+            }
+
+            Action::ApplyShape => {
+                let (coord, declared) = match self.shape_form.clone() {
+                    Some(form) => form,
+                    None => return false,
+                };
+                let declared = declared.trim().to_string();
+                if !declared.is_empty() {
+                    if let Err(e) = Shape::parse(&declared) {
+                        return self.update(Action::Alert(e));
+                    }
+                }
+                let before = self.snapshot();
+                if let Some(g) = self.get_session_mut().grammars.get_mut(&coord) {
+                    g.style.shape = Some(declared).filter(|d| !d.is_empty());
+                }
+                self.history.record(before);
+                self.shape_form = None;
+                true
+            }
+
+            Action::CloseShape => {
+                self.shape_form = None;
+                true
+            }
+
+            Action::OpenNestedDefaults(grid) => {
+                match self.get_session().grammars.get(&grid).map(|g| &g.kind) {
+                    Some(Kind::Grid(_)) => (),
+                    _ => return false,
+                }
+                self.nested_defaults_form = Some((grid.clone(), self.grid_defaults(&grid)));
+                true
+            }
+
+            Action::UpdateNestedDefaults(defaults) => {
+                if let Some((_, form)) = self.nested_defaults_form.as_mut() {
+                    *form = defaults;
+                }
+                true
+            }
+
+            Action::ApplyNestedDefaults | Action::ResetNestedDefaults => {
+                let (grid, defaults) = match self.nested_defaults_form.take() {
+                    Some(form) => form,
+                    None => return false,
+                };
+                let before = self.snapshot();
+                if let Some(g) = self.get_session_mut().grammars.get_mut(&grid) {
+                    g.style.nested_defaults = match event_type {
+                        Action::ApplyNestedDefaults => Some(GridDefaults {
+                            rows: defaults.rows.max(1),
+                            cols: defaults.cols.max(1),
+                            ..defaults
+                        }),
+                        _ => None,
+                    };
+                }
+                self.history.record(before);
+                true
+            }
+
+            Action::CloseNestedDefaults => {
+                self.nested_defaults_form = None;
+                true
+            }
+
+            Action::OpenCondition(coord) => {
+                let condition = match self.get_session().grammars.get(&coord) {
+                    Some(grammar) => grammar.style.visible_when.clone().unwrap_or_default(),
+                    None => return false,
+                };
+                self.condition_form = Some((coord, condition));
+                true
+            }
+
+            Action::UpdateCondition(condition) => {
+                if let Some((_, text)) = self.condition_form.as_mut() {
+                    *text = condition;
+                }
+                true
+            }
+
+            Action::ApplyCondition => {
+                let (coord, condition) = match self.condition_form.clone() {
+                    Some(form) => form,
+                    None => return false,
+                };
+                let condition = condition.trim().to_string();
+                if !condition.is_empty() {
+                    if let Err(e) = parse_formula(&source_formula(&condition)) {
+                        return self.update(Action::Alert(format! {"{}: {}", condition, e.message}));
+                    }
+                }
+                let before = self.snapshot();
+                if let Some(g) = self.get_session_mut().grammars.get_mut(&coord) {
+                    g.style.visible_when = Some(condition).filter(|c| !c.is_empty());
+                }
+                self.history.record(before);
+                self.condition_form = None;
+                true
+            }
+
+            Action::CloseCondition => {
+                self.condition_form = None;
+                true
+            }
+
+            Action::OpenBindings(coord) => {
+                let bindings = match self.get_session().grammars.get(&coord) {
+                    Some(grammar) => grammar.style.bindings.clone(),
+                    None => return false,
+                };
+                self.bindings_form = Some((coord, bindings));
+                true
+            }
+
+            Action::UpdateBindings(bindings) => {
+                if let Some((_, form)) = self.bindings_form.as_mut() {
+                    *form = bindings;
+                }
+                true
+            }
+
+            Action::ApplyBindings => {
+                let (coord, form) = match self.bindings_form.clone() {
+                    Some(form) => form,
+                    None => return false,
+                };
+                let mut bindings = StyleBindings::default();
+                for (source, bound) in vec![
+                    (form.background, &mut bindings.background),
+                    (form.font_color, &mut bindings.font_color),
+                    (form.width, &mut bindings.width),
+                ] {
+                    let source = source.unwrap_or_default().trim().to_string();
+                    if source.is_empty() {
+                        continue;
+                    }
+                    if let Err(e) = parse_formula(&source_formula(&source)) {
+                        return self.update(Action::Alert(format! {"{}: {}", source, e.message}));
+                    }
+                    *bound = Some(source);
+                }
+                let before = self.snapshot();
+                if let Some(g) = self.get_session_mut().grammars.get_mut(&coord) {
+                    g.style.bindings = bindings;
+                }
+                self.history.record(before);
+                self.bindings_form = None;
+                true
+            }
+
+            Action::CloseBindings => {
+                self.bindings_form = None;
+                true
+            }
+
+            Action::LoadTransclusion(path) => {
+                let content = js! {
+                    try {
+                        return require("fs").readFileSync(@{path.clone()}, "utf8");
+                    } catch (e) {
+                        return null;
+                    }
+                };
+                let content = content
+                    .into_string()
+                    .ok_or_else(|| format! {"couldn't read {}", path});
+                let previous = self.transclusions.remove(&path);
+                self.transclusions.insert(path.clone(), reload(previous, content));
+                if !self.transclusion_watchers.contains_key(&path) {
+                    let changed = self.link.callback(|path: String| Action::LoadTransclusion(path));
+                    let watched_path = path.clone();
+                    let on_change = move || changed.emit(watched_path.clone());
+                    // a missing file can't be watched yet, it's tried again on the next load
+                    let watcher = js! {
+                        try {
+                            return require("fs").watch(@{path.clone()}, { persistent: false }, @{on_change});
+                        } catch (e) {
+                            return null;
+                        }
+                    };
+                    if !watcher.is_null() {
+                        self.transclusion_watchers.insert(path, watcher);
+                    }
+                }
+                true
+            }
+
+            Action::ChooseExternalSource => {
+                let coord = match self.active_cell.clone() {
+                    Some(coord) => coord,
+                    None => return false,
+                };
+                match choose_file(false, "Data", &["csv", "json"]) {
+                    Some(path) => self.update(Action::BindExternalSource(coord, path)),
+                    None => false,
+                }
+            }
+
+            Action::BindExternalSource(grid, path) => {
+                let sources = &mut self.get_session_mut().external_sources;
+                sources.retain(|source| source.grid != grid);
+                sources.push(ExternalSource::new(grid.clone(), path));
+                self.external_conflicts.retain(|c| *c != grid);
+                self.update(Action::RefreshExternalSource(grid, false))
+            }
+
+            Action::ExternalSourceChanged(path) => {
+                // the file may be half-written or being replaced, it's read again on the next change
+                let table = match read_source(&path) {
+                    Ok(table) => table,
+                    Err(_) => return false,
+                };
+                let grids: Vec<Coordinate> = self
+                    .get_session()
+                    .external_sources
+                    .iter()
+                    .filter(|source| source.path == path && source.snapshot != table)
+                    .map(|source| source.grid.clone())
+                    .collect();
+                let mut changed = false;
+                for grid in grids {
+                    changed = self.update(Action::RefreshExternalSource(grid, false)) || changed;
+                }
+                changed
+            }
+
+            Action::RefreshExternalSource(grid, overwrite) => {
+                let source = match self.get_session().external_sources.iter().find(|s| s.grid == grid) {
+                    Some(source) => source.clone(),
+                    None => return false,
+                };
+                if source.is_remote() {
+                    self.fetch_url(grid, source.path, overwrite);
+                    return true;
+                }
+                let table = match read_source(&source.path) {
+                    Ok(table) => table,
+                    Err(e) => return self.update(Action::Alert(format! {"{}: {}", source.path, e})),
+                };
+                self.reimport_source(source, table, overwrite)
+            }
+
+            Action::UnbindExternalSource(grid) => {
+                self.get_session_mut().external_sources.retain(|s| s.grid != grid);
+                self.external_conflicts.retain(|c| *c != grid);
+                true
+            }
+
+            Action::OpenImportUrl => {
+                if self.active_cell.is_none() {
+                    return self.update(Action::Alert("select the cell to import into first".to_string()));
+                }
+                self.import_url_form = Some(("".to_string(), true));
+                true
+            }
+
+            Action::UpdateImportUrl(url, keep_link) => {
+                self.import_url_form = Some((url, keep_link));
+                true
+            }
+
+            Action::ApplyImportUrl => {
+                let ((url, keep_link), grid) = match (self.import_url_form.clone(), self.active_cell.clone()) {
+                    (Some(form), Some(active)) => (form, active),
+                    _ => return false,
+                };
+                let url = match csv_url(&url) {
+                    Ok(url) => url,
+                    Err(e) => return self.update(Action::Alert(e)),
+                };
+                self.import_url_form = None;
+                if keep_link {
+                    self.update(Action::BindExternalSource(grid, url))
+                } else {
+                    self.fetch_url(grid, url, true);
+                    true
+                }
+            }
+
+            Action::CloseImportUrl => {
+                self.import_url_form = None;
+                true
+            }
+
+            Action::UrlFetched(grid, url, overwrite, content) => {
+                self.fetching_sources.remove(&grid);
+                let table = match content.and_then(|content| parse_table(&url, &content)) {
+                    Ok(table) => table,
+                    Err(e) => return self.update(Action::Alert(format! {"{}: {}", url, e})),
+                };
+                // the grid may have been unbound, or bound to another URL, while it was fetched
+                let source = self
+                    .get_session()
+                    .external_sources
+                    .iter()
+                    .find(|s| s.grid == grid && s.path == url)
+                    .cloned();
+                match source {
+                    Some(source) => self.reimport_source(source, table, overwrite),
+                    None => {
+                        write_table(&mut self.get_session_mut().grammars, &grid, &table);
+                        if let Some(active) = self.active_cell.clone() {
+                            if !self.get_session().grammars.contains_key(&active) {
+                                self.active_cell = Some(grid.clone());
+                            }
+                        }
+                        self.update(Action::Notify(format! {"Imported {} into {}", url, grid.to_string()}, false))
+                    }
+                }
+            }
+
+            Action::ConfigureShell(coord, command, output) => {
+                if let Some(Grammar {
+                    kind: Kind::Interactive(_, interactive @ Interactive::Shell(_, _)),
+                    ..
+                }) = self.get_session_mut().grammars.get_mut(&coord)
+                {
+                    *interactive = Interactive::Shell(command, output);
+                    return true;
+                }
+                false
+            }
+
+            Action::SetColor(coord, color) => {
+                let color = match parse_color(&color) {
+                    Some(color) => color,
+                    None => return self.update(Action::Alert(format! {"{} isn't a color", color})),
+                };
+                let before = self.snapshot();
+                match self.get_session_mut().grammars.get_mut(&coord) {
+                    Some(Grammar {
+                        kind: Kind::Interactive(_, Interactive::ColorPicker(current)),
+                        ..
+                    }) if *current != color => *current = color,
+                    _ => return false,
+                }
+                self.history.record(before);
+                true
+            }
+
+            Action::ConfigureGauge(coord, source, min, max) => {
+                let before = self.snapshot();
+                match self.get_session_mut().grammars.get_mut(&coord) {
+                    Some(Grammar {
+                        kind: Kind::Interactive(_, gauge @ Interactive::Gauge(_, _, _)),
+                        ..
+                    }) => *gauge = Interactive::Gauge(source.trim().to_string(), min, max),
+                    _ => return false,
+                }
+                self.history.record(before);
+                true
+            }
+
+            Action::SetToggle(coord, on) => {
+                let before = self.snapshot();
+                match self.get_session_mut().grammars.get_mut(&coord) {
+                    Some(Grammar {
+                        kind: Kind::Interactive(_, Interactive::Toggle(checked)),
+                        ..
+                    }) => *checked = on,
+                    _ => return false,
+                }
+                self.history.record(before);
+                true
+            }
+
+            Action::SetRating(coord, stars) => {
+                let before = self.snapshot();
+                match self.get_session_mut().grammars.get_mut(&coord) {
+                    Some(Grammar {
+                        kind: Kind::Interactive(_, rating @ Interactive::Rating(_, _)),
+                        ..
+                    }) => *rating = rating.rated(stars).unwrap(),
+                    _ => return false,
+                }
+                self.history.record(before);
+                true
+            }
+
+            Action::StepStepper(coord, count) => {
+                let before = self.snapshot();
+                match self.get_session_mut().grammars.get_mut(&coord) {
+                    Some(Grammar {
+                        kind: Kind::Interactive(_, stepper @ Interactive::Stepper(_, _, _, _)),
+                        ..
+                    }) => *stepper = stepper.stepped(count).unwrap(),
+                    _ => return false,
+                }
+                self.history.record(before);
+                true
+            }
+
+            Action::StartTimer(coord) => {
+                match self.get_session().grammars.get(&coord).map(|g| &g.kind) {
+                    Some(Kind::Interactive(_, Interactive::Timer(_))) => (),
+                    _ => return false,
+                }
+                // bring the running timers up to now, so the new one doesn't get their time
+                self.tick_timers();
+                self.running_timers.insert((self.current_session_index, coord));
+                if self.timer_task.is_none() {
+                    let callback = self.link.callback(|_| Action::TimerTick);
+                    self.timer_task = Some(IntervalService::new().spawn(Duration::from_secs(1), callback));
+                }
+                true
+            }
+
+            Action::StopTimer(coord) => {
+                self.tick_timers();
+                self.running_timers.remove(&(self.current_session_index, coord));
+                if self.running_timers.is_empty() {
+                    self.timer_task = None;
+                }
+                true
+            }
+
+            Action::ResetTimer(coord) => {
+                let before = self.snapshot();
+                match self.get_session_mut().grammars.get_mut(&coord) {
+                    Some(Grammar {
+                        kind: Kind::Interactive(_, Interactive::Timer(elapsed)),
+                        ..
+                    }) => *elapsed = 0.0,
+                    _ => return false,
+                }
+                self.history.record(before);
+                self.update(Action::StopTimer(coord))
+            }
+
+            Action::TimerTick => {
+                self.tick_timers();
+                if self.running_timers.is_empty() {
+                    self.timer_task = None;
+                }
+                // ticks skip the bookkeeping after other actions, only drivers watching the
+                // timers need to hear about them
+                let current = self.current_session_index;
+                if let Some(cells) = &mut self.changed_cells {
+                    cells.extend(
+                        self.running_timers.iter().filter(|(i, _)| *i == current).map(|(_, c)| c.clone()),
+                    );
+                }
+                self.dispatch_subscriptions();
+                return true;
+            }
+
+            Action::ConfigureReminder(coord, message, trigger) => {
+                let trigger = match trigger {
+                    ReminderTrigger::At(time) if !time.trim().is_empty() => match parse_time(&time) {
+                        Some(time) => ReminderTrigger::At(time),
+                        None => return self.update(Action::Alert(format! {"{} isn't a date & time", time})),
+                    },
+                    ReminderTrigger::At(_) => ReminderTrigger::At(String::new()),
+                    ReminderTrigger::When(condition) => {
+                        let condition = condition.trim().to_string();
+                        if !condition.is_empty() {
+                            if let Err(e) = parse_formula(&source_formula(&condition)) {
+                                return self.update(Action::Alert(format! {"{}: {}", condition, e.message}));
+                            }
+                        }
+                        ReminderTrigger::When(condition)
+                    }
+                };
+                let before = self.snapshot();
+                match self.get_session_mut().grammars.get_mut(&coord) {
+                    Some(Grammar {
+                        kind: Kind::Interactive(text, reminder @ Interactive::Reminder(_, _)),
+                        ..
+                    }) => {
+                        *text = message;
+                        *reminder = Interactive::Reminder(trigger, false);
+                    }
+                    _ => return false,
+                }
+                self.history.record(before);
+                true
+            }
+
+            // like timer ticks, skips the bookkeeping after other actions
+            Action::CheckReminders => return self.fire_reminders(),
+
+            Action::ToggleMuteReminders => {
+                let session = self.get_session_mut();
+                session.mute_reminders = !session.mute_reminders;
+                true
+            }
+
+            Action::RunShell(coord) => {
+                let command = match command_of(&self.get_session().grammars, &coord) {
+                    Some((command, _)) => command,
+                    None => return false,
+                };
+                if command.trim().is_empty() {
+                    return self.update(Action::Alert("enter a command to run first".to_string()));
+                }
+                if self.running_shells.contains(&coord) {
+                    return false;
+                }
+                if !self.get_session().view.allowed_commands.contains(&command) {
+                    self.shell_confirm = Some(coord);
+                    return true;
+                }
+                ipc::send(&ipc::Request::RunShell {
+                    coord: coord.to_string(),
+                    command,
+                });
+                self.running_shells.insert(coord);
+                true
+            }
+
+            Action::ConfirmShell(allow) => {
+                let coord = match self.shell_confirm.take() {
+                    Some(coord) => coord,
+                    None => return false,
+                };
+                if !allow {
+                    return true;
+                }
+                if let Some((command, _)) = command_of(&self.get_session().grammars, &coord) {
+                    self.get_session_mut().view.allowed_commands.push(command);
+                }
+                self.update(Action::RunShell(coord))
+            }
+
+            Action::StartMacroRecording => {
+                self.macro_recording = Some((self.current_session_index, String::new(), vec![]));
+                true
+            }
+
+            Action::UpdateMacroRecordingName(name) => {
+                if let Some((_, recording_name, _)) = self.macro_recording.as_mut() {
+                    *recording_name = name;
+                }
+                true
+            }
+
+            Action::SaveMacroRecording => {
+                let (index, name, steps) = match self.macro_recording.take() {
+                    Some(recording) => recording,
+                    None => return false,
+                };
+                if index != self.current_session_index {
+                    return true;
+                }
+                let steps = steps.iter().filter_map(|step| serde_json::to_value(step).ok()).collect();
+                let taken: Vec<String> = self.get_session().macros.iter().map(|m| m.name.clone()).collect();
+                let name = match name.trim() {
+                    "" => "macro",
+                    name => name,
+                };
+                let macro_ = Macro {
+                    name: crate::defn::unique_name(name, &taken),
+                    params: vec![],
+                    body: MacroBody::Recorded(steps),
+                };
+                let before = self.snapshot();
+                self.get_session_mut().macros.push(macro_);
+                self.history.record(before);
+                true
+            }
+
+            Action::CancelMacroRecording => {
+                self.macro_recording = None;
+                true
+            }
+
+            Action::OpenScript(name) => {
+                let existing = name.and_then(|name| self.get_session().macros.iter().find(|m| m.name == name).cloned());
+                self.script_form = Some(existing.unwrap_or_else(|| Macro {
+                    name: String::new(),
+                    params: vec![],
+                    body: MacroBody::Script(String::new()),
+                }));
+                true
+            }
+
+            Action::UpdateScript(macro_) => {
+                if let Some(form) = self.script_form.as_mut() {
+                    *form = macro_;
+                }
+                true
+            }
+
+            Action::SaveScript => {
+                let mut macro_ = match self.script_form.clone() {
+                    Some(form) => form,
+                    None => return false,
+                };
+                macro_.name = macro_.name.trim().to_string();
+                if macro_.name.is_empty() {
+                    return self.update(Action::Alert("enter a name for the macro first".to_string()));
+                }
+                macro_.params = macro_.params.iter().map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect();
+                let before = self.snapshot();
+                let macros = &mut self.get_session_mut().macros;
+                match macros.iter_mut().find(|m| m.name == macro_.name) {
+                    Some(existing) => *existing = macro_,
+                    None => macros.push(macro_),
+                }
+                self.history.record(before);
+                self.script_form = None;
+                true
+            }
+
+            Action::CloseScript => {
+                self.script_form = None;
+                true
+            }
+
+            Action::RemoveMacro(name) => {
+                let before = self.snapshot();
+                self.get_session_mut().macros.retain(|m| m.name != name);
+                self.history.record(before);
+                true
+            }
+
+            Action::AddVariable => {
+                let taken: Vec<String> =
+                    self.get_session().variables.iter().map(|v| v.name.clone()).collect();
+                let name = crate::defn::unique_name("variable", &taken);
+                self.get_session_mut().variables.push(Variable {
+                    name,
+                    value: "0".to_string(),
+                });
+                true
+            }
+
+            Action::RenameVariable(index, name) => {
+                match self.get_session_mut().variables.get_mut(index) {
+                    Some(variable) => variable.name = name.trim().trim_start_matches('$').to_string(),
+                    None => return false,
+                }
+                true
+            }
+
+            Action::SetVariable(index, value) => {
+                match self.get_session_mut().variables.get_mut(index) {
+                    Some(variable) => variable.value = value,
+                    None => return false,
+                }
+                true
+            }
+
+            Action::RemoveVariable(index) => {
+                let variables = &mut self.get_session_mut().variables;
+                if index >= variables.len() {
+                    return false;
+                }
+                variables.remove(index);
+                true
+            }
+
+            Action::OpenButtonAction(coord) => {
+                let action = match self.get_session().grammars.get(&coord) {
+                    Some(Grammar { kind: Kind::Interactive(_, Interactive::Button()), style, .. }) => {
+                        style.on_click.clone().unwrap_or_default()
+                    }
+                    _ => return self.update(Action::Alert("only button cells run macros".to_string())),
+                };
+                self.button_form = Some((coord, action));
+                true
+            }
+
+            Action::UpdateButtonAction(action) => {
+                if let Some((_, form)) = self.button_form.as_mut() {
+                    *form = action;
+                }
+                true
+            }
+
+            Action::ApplyButtonAction => {
+                let (coord, mut action) = match self.button_form.clone() {
+                    Some(form) => form,
+                    None => return false,
+                };
+                action.macro_name = action.macro_name.trim().to_string();
+                action.args = action.args.iter().map(|a| a.trim().to_string()).filter(|a| !a.is_empty()).collect();
+                let before = self.snapshot();
+                if let Some(g) = self.get_session_mut().grammars.get_mut(&coord) {
+                    g.style.on_click = Some(action).filter(|a| !a.macro_name.is_empty());
+                }
+                self.history.record(before);
+                self.button_form = None;
+                true
+            }
+
+            Action::CloseButtonAction => {
+                self.button_form = None;
+                true
+            }
+
+            Action::RunButton(coord) => {
+                let session = self.get_session();
+                let action = match session.grammars.get(&coord).and_then(|g| g.style.on_click.clone()) {
+                    Some(action) => action,
+                    None => return false,
+                };
+                let macro_ = match session.macros.iter().find(|m| m.name == action.macro_name) {
+                    Some(macro_) => macro_.clone(),
+                    None => return self.update(Action::Alert(format! {"there's no macro named {}", action.macro_name})),
+                };
+                let args = match arguments(&session.grammars, &macro_, &action) {
+                    Ok(args) => args,
+                    Err(e) => return self.update(Action::Alert(format! {"{}: {}", macro_.name, e})),
+                };
+                match macro_.body {
+                    MacroBody::Recorded(steps) => {
+                        for step in substitute_args(&steps, &args) {
+                            match serde_json::from_value::<ReplayStep>(step) {
+                                Ok(step) => {
+                                    self.update(action_of(&step));
+                                }
+                                Err(e) => return self.update(Action::Alert(format! {"{}: {}", macro_.name, e})),
+                            }
+                        }
+                    }
+                    MacroBody::Script(source) => {
+                        if !self.get_session().view.allowed_scripts.contains(&source) {
+                            self.script_confirm = Some(coord);
+                            return true;
+                        }
+                        let writes = match run_script(&self.get_session().grammars, &source, &args) {
+                            Ok(writes) => writes,
+                            Err(e) => return self.update(Action::Alert(format! {"{}: {}", macro_.name, e})),
+                        };
+                        for (reference, value) in writes {
+                            let written = match resolve_cell(&self.get_session().grammars, &reference) {
+                                Some(target) => self.set_cell_text(&target.to_string(), value),
+                                None => Err(format! {"{} isn't a cell", reference}),
+                            };
+                            if let Err(e) = written {
+                                return self.update(Action::Alert(format! {"{}: {}", macro_.name, e}));
+                            }
+                        }
+                    }
+                }
+                true
+            }
+
+            Action::ConfirmScript(allow) => {
+                let coord = match self.script_confirm.take() {
+                    Some(coord) => coord,
+                    None => return false,
+                };
+                if !allow {
+                    return true;
+                }
+                if let Some(source) = script_of(self.get_session(), &coord) {
+                    self.get_session_mut().view.allowed_scripts.push(source);
+                }
+                self.update(Action::RunButton(coord))
+            }
+
+            Action::ShellFinished(coord, succeeded, stdout) => {
+                self.running_shells.remove(&coord);
+                let output = match command_of(&self.get_session().grammars, &coord) {
+                    Some((_, output)) => output,
+                    None => return true,
+                };
+                if !succeeded {
+                    return self.update(Action::Alert(format! {"{} failed: {}", coord.to_string(), stdout}));
+                }
+                let before = self.snapshot();
+                match write_output(&mut self.get_session_mut().grammars, &coord, output, &stdout) {
+                    Ok(_) => self.history.record(before),
+                    Err(e) => return self.update(Action::Alert(e)),
+                }
+                true
+            }
+
+            Action::AutomationCall(id, body) => {
+                let (rpc_id, command) = parse_request(&body);
+                let result = if self.preferences.automation.enabled {
+                    command.and_then(|command| self.run_automation_command(command))
+                } else {
+                    Err(RpcError::new(COMMAND_FAILED, "automation is turned off".to_string()))
+                };
+                ipc::send(&ipc::Request::AutomationReply {
+                    id,
+                    response: response(rpc_id, result),
+                });
+                true
+            }
+
+            Action::ReadAsset(file) => {
+                let mime: String = js! { return @{&file}.type || "application/octet-stream"; }
+                    .try_into()
+                    .unwrap_or_else(|_| "application/octet-stream".to_string());
+                let callback = self.link.callback(move |file_data| Action::AddAsset(file_data, mime.clone()));
+                let task = self.reader.read_file(file, callback);
+                self.tasks.push(task);
+                false
+            }
+
+            Action::AddAsset(file_data, mime) => {
+                let name = unique_name(&self.get_session().assets, &file_data.name);
+                let asset = Asset::new(name.clone(), mime, &file_data.content);
+                self.get_session_mut().assets.push(asset);
+                // unreferenced assets are dropped on save, so the new one is referenced right away
+                self.update(Action::InsertAssetReference(name))
+            }
+
+            Action::InsertAssetReference(name) => {
+                let active = match self.active_cell.clone() {
+                    Some(active) => active,
+                    None => return true,
+                };
+                let reference = format! {"{}{}", ASSET_PREFIX, name};
+                let before = self.snapshot();
+                match self.get_session_mut().grammars.get_mut(&active).map(|g| &mut g.kind) {
+                    Some(Kind::Input(text)) | Some(Kind::Text(text)) => {
+                        *text = if text.trim().is_empty() {
+                            reference
+                        } else {
+                            format! {"{} {}", text, reference}
+                        };
+                    }
+                    _ => return self.update(Action::Alert("assets can only be referenced from text cells".to_string())),
+                }
+                self.history.record(before);
+                true
+            }
+
+            Action::ExportCell => {
+                let active = match self.active_cell.clone() {
+                    Some(active) => active,
+                    None => return false,
+                };
+                let fragment = match extract(&self.get_session().grammars, &self.row_heights, &self.col_widths, &active) {
+                    Some(fragment) => fragment,
+                    None => return false,
+                };
+                let path = match choose_file(true, "Cell fragment", &["json"]) {
+                    Some(path) => path,
+                    None => return false,
+                };
+                let content = serde_json::to_string_pretty(&fragment).unwrap();
+                let written: bool = js! {
+                    try {
+                        require("fs").writeFileSync(@{path.clone()}, @{content});
+                        return true;
+                    } catch (e) {
+                        return false;
+                    }
+                }
+                .try_into()
+                .unwrap_or(false);
+                if !written {
+                    return self.update(Action::Alert(format! {"couldn't write {}", path}));
+                }
+                false
+            }
+
+            Action::ExportDependencyGraph(hierarchy) => {
+                // a grid narrows the graph down to its cells
+                let subtree = self.active_cell.clone().filter(|active| {
+                    match self.get_session().grammars.get(active).map(|g| &g.kind) {
+                        Some(Kind::Grid(_)) => true,
+                        _ => false,
+                    }
+                });
+                let dot = to_dot(&self.get_session().grammars, &DotOptions { subtree, hierarchy });
+                let path = match choose_file(true, "Graphviz graph", &["dot", "gv"]) {
+                    Some(path) => path,
+                    None => return false,
+                };
+                let written: bool = js! {
+                    try {
+                        require("fs").writeFileSync(@{path.clone()}, @{dot});
+                        return true;
+                    } catch (e) {
+                        return false;
+                    }
+                }
+                .try_into()
+                .unwrap_or(false);
+                if !written {
+                    return self.update(Action::Alert(format! {"couldn't write {}", path}));
+                }
+                false
+            }
+
+            Action::ExportSchema => {
+                // a cell of a table exports the whole table
+                let grid = match self.active_cell.clone() {
+                    Some(active) => match self.get_session().grammars.get(&active).map(|g| &g.kind) {
+                        Some(Kind::Grid(_)) => active,
+                        _ => active.parent().unwrap_or(active),
+                    },
+                    None => return false,
+                };
+                let schema = match to_json_schema(&self.get_session().grammars, &grid) {
+                    Some(schema) => schema,
+                    None => return self.update(Action::Alert("pick a grid to export".to_string())),
+                };
+                let path = match choose_file(true, "JSON Schema", &["json"]) {
+                    Some(path) => path,
+                    None => return false,
+                };
+                let content = serde_json::to_string_pretty(&schema).unwrap();
+                let written: bool = js! {
+                    try {
+                        require("fs").writeFileSync(@{path.clone()}, @{content});
+                        return true;
+                    } catch (e) {
+                        return false;
+                    }
+                }
+                .try_into()
+                .unwrap_or(false);
+                if !written {
+                    return self.update(Action::Alert(format! {"couldn't write {}", path}));
+                }
+                false
+            }
+
+            Action::ImportSchema => {
+                let active = match self.active_cell.clone() {
+                    Some(active) => active,
+                    None => return false,
+                };
+                if active.parent().is_none() {
+                    return self.update(Action::Alert("pick a cell to scaffold the grid in".to_string()));
+                }
+                let path = match choose_file(false, "JSON Schema", &["json"]) {
+                    Some(path) => path,
+                    None => return false,
+                };
+                let content = js! {
+                    try {
+                        return require("fs").readFileSync(@{path.clone()}, "utf8");
+                    } catch (e) {
+                        return null;
+                    }
+                };
+                let schema = match content
+                    .into_string()
+                    .ok_or_else(|| format! {"couldn't read {}", path})
+                    .and_then(|content| {
+                        serde_json::from_str::<serde_json::Value>(&content).map_err(|e| format! {"{}: {}", path, e})
+                    }) {
+                    Ok(schema) => schema,
+                    Err(e) => return self.update(Action::Alert(e)),
+                };
+                let before = self.snapshot();
+                if let Err(e) = scaffold(&mut self.get_session_mut().grammars, &active, &schema) {
+                    self.restore(before);
+                    return self.update(Action::Alert(e));
+                }
+                self.history.record(before);
+                true
+            }
+
+            Action::RestoreTrash(position) => {
+                let index = self.current_session_index;
+                let entry = match self.trash.get(&index).and_then(|t| t.get(position)) {
+                    Some(entry) => entry.clone(),
+                    None => return false,
+                };
+                let before = self.snapshot();
+                if let Err(e) = restore_trash(&mut self.get_session_mut().grammars, &entry) {
+                    self.restore(before);
+                    return self.update(Action::Alert(format! {"couldn't restore: {}", e}));
+                }
+                self.history.record(before);
+                if let Some(trash) = self.trash.get_mut(&index) {
+                    trash.remove(position);
+                }
+                self.column_index = build_column_index(&self.get_session().grammars);
+                self.update(Action::Notify(format! {"Restored {}", entry.describe()}, false));
+                true
+            }
+
+            Action::DiscardTrash(position) => {
+                match self.trash.get_mut(&self.current_session_index) {
+                    Some(trash) if position < trash.len() => {
+                        trash.remove(position);
+                        true
+                    }
+                    _ => false,
+                }
+            }
+
+            Action::EmptyTrash => self.trash.remove(&self.current_session_index).is_some(),
+
+            Action::ImportCell => {
+                let active = match self.active_cell.clone() {
+                    Some(active) => active,
+                    None => return false,
+                };
+                if active.parent().is_none() {
+                    return self.update(Action::Alert("pick a cell to import into".to_string()));
+                }
+                let path = match choose_file(false, "Cell fragment", &["json"]) {
+                    Some(path) => path,
+                    None => return false,
+                };
+                let content = js! {
+                    try {
+                        return require("fs").readFileSync(@{path.clone()}, "utf8");
+                    } catch (e) {
+                        return null;
+                    }
+                };
+                let fragment = match content
+                    .into_string()
+                    .ok_or_else(|| format! {"couldn't read {}", path})
+                    .and_then(|content| parse_fragment(&content))
+                {
+                    Ok(fragment) => fragment,
+                    Err(e) => return self.update(Action::Alert(e)),
+                };
+                let before = self.snapshot();
+                let declared = self.get_session().grammars.get(&active).and_then(|g| g.style.shape.clone());
+                let sizes = graft(&fragment, &mut self.get_session_mut().grammars, &active);
+                self.apply_sizes(sizes);
+                if !self.check_shape(declared, &active, before.clone()) {
+                    return true;
+                }
+                self.history.record(before);
+                true
+            }
+
+            Action::ImportOutline => {
+                let active = match self.active_cell.clone() {
+                    Some(active) if active.parent().is_some() => active,
+                    _ => return self.update(Action::Alert("pick a cell to import into".to_string())),
+                };
+                let path = match choose_file(false, "Outline", &["opml", "xml", "md", "markdown", "txt"]) {
+                    Some(path) => path,
+                    None => return false,
+                };
+                let content = js! {
+                    try {
+                        return require("fs").readFileSync(@{path.clone()}, "utf8");
+                    } catch (e) {
+                        return null;
+                    }
+                };
+                let outline = match content
+                    .into_string()
+                    .ok_or_else(|| format! {"couldn't read {}", path})
+                    .and_then(|content| parse_outline(&path, &content))
+                {
+                    Ok(outline) => outline,
+                    Err(e) => return self.update(Action::Alert(e)),
+                };
+                write_outline(&mut self.get_session_mut().grammars, &active, &outline);
+                self.active_cell = Some(active);
+                true
+            }
+
+            Action::StartRecording => {
+                let log = ReproLog {
+                    initial: self.get_session().clone(),
+                    active_cell: self.active_cell.clone(),
+                    steps: vec![],
+                    incomplete: false,
+                };
+                self.recording = Some((self.current_session_index, log));
+                true
+            }
+
+            Action::StopRecording => {
+                self.recording = None;
+                true
+            }
+
+            Action::ExportRepro => {
+                let content = match &self.recording {
+                    Some((_, log)) => serde_json::to_string(log).unwrap(),
+                    None => return self.update(Action::Alert("start recording first".to_string())),
+                };
+                let path = match choose_file(true, "Repro log", &["json"]) {
+                    Some(path) => path,
+                    None => return false,
+                };
+                let written: bool = js! {
+                    try {
+                        require("fs").writeFileSync(@{path.clone()}, @{content});
+                        return true;
+                    } catch (e) {
+                        return false;
+                    }
+                }
+                .try_into()
+                .unwrap_or(false);
+                if !written {
+                    return self.update(Action::Alert(format! {"couldn't write {}", path}));
+                }
+                false
+            }
+
+            Action::ImportRepro => {
+                let path = match choose_file(false, "Repro log", &["json"]) {
+                    Some(path) => path,
+                    None => return false,
+                };
+                let content = js! {
+                    try {
+                        return require("fs").readFileSync(@{path.clone()}, "utf8");
+                    } catch (e) {
+                        return null;
+                    }
+                };
+                let log: ReproLog = match content
+                    .into_string()
+                    .ok_or_else(|| format! {"couldn't read {}", path})
+                    .and_then(|content| serde_json::from_str(&content).map_err(|e| format! {"not a repro log: {}", e}))
+                {
+                    Ok(log) => log,
+                    Err(e) => return self.update(Action::Alert(e)),
+                };
+                // replayed into a tab of its own, without a file so it isn't saved over anything
+                let mut session = log.initial.clone();
+                session.view = TabView::default();
+                self.sessions.push(session);
+                self.switch_tab(self.sessions.len() - 1);
+                self.active_cell = log.active_cell.clone();
+                self.replay_task = None;
+                if log.incomplete {
+                    let message = format! {"{} is incomplete, replaying it won't reproduce the session", path};
+                    self.update(Action::Notify(message, true));
+                }
+                self.replay = Some(Replay::new(log, self.current_session_index));
+                true
+            }
+
+            Action::StepReplay => {
+                let (index, step) = match self.replay.as_mut() {
+                    Some(replay) => (replay.session_index, replay.advance()),
+                    None => return false,
+                };
+                let step = match step {
+                    Some(step) => step,
+                    None => return self.update(Action::PlayReplay(false)),
+                };
+                self.switch_tab(index);
+                self.update(action_of(&step));
+                if self.replay.as_ref().map_or(false, |r| r.is_done()) {
+                    self.update(Action::PlayReplay(false));
+                }
+                true
+            }
+
+            Action::PlayReplay(play) => {
+                let replay = match self.replay.as_mut() {
+                    Some(replay) => replay,
+                    None => return false,
+                };
+                replay.playing = play && !replay.is_done();
+                self.replay_task = if replay.playing {
+                    let callback = self.link.callback(|_| Action::StepReplay);
+                    Some(IntervalService::new().spawn(Duration::from_millis(PLAY_INTERVAL_MS), callback))
+                } else {
+                    None
+                };
+                true
+            }
+
+            Action::CloseReplay => {
+                self.replay = None;
+                self.replay_task = None;
+                true
+            }
+
+            Action::RefreshRegistries => {
+                self.registries.clear();
+                for registry in self.preferences.package_registries.clone() {
+                    let loaded_registry = registry.clone();
+                    let callback = self
+                        .link
+                        .callback(move |content| Action::RegistryLoaded(loaded_registry.clone(), content));
+                    self.fetch_text(index_location(&registry), callback);
+                }
+                true
+            }
+
+            Action::RegistryLoaded(registry, content) => {
+                let index = content.and_then(|content| {
+                    serde_json::from_str::<RegistryIndex>(&content).map_err(|e| format! {"not a registry index: {}", e})
+                });
+                self.registries.insert(registry, index);
+                true
+            }
+
+            Action::InstallPack(registry, listing) => {
+                let fetched_registry = registry.clone();
+                let callback = self
+                    .link
+                    .callback(move |content| Action::PackFetched(fetched_registry.clone(), content));
+                self.fetch_text(resolve(&registry, &listing.location), callback);
+                false
+            }
+
+            Action::PackFetched(registry, content) => {
+                let pack = match content.and_then(|content| {
+                    serde_json::from_str::<GrammarPack>(&content).map_err(|e| format! {"not a grammar pack: {}", e})
+                }) {
+                    Ok(pack) => pack,
+                    Err(e) => return self.update(Action::Alert(e)),
+                };
+                let before = self.snapshot();
+                // an update replaces the grammars of the installed version
+                self.uninstall_pack(&pack.name);
+                let mut coords = vec![];
+                for pack_grammar in pack.grammars.iter() {
+                    let coord = self.next_meta_row();
+                    graft(&pack_grammar.fragment, &mut self.get_session_mut().grammars, &coord);
+                    if let Some(g) = self.get_session_mut().grammars.get_mut(&coord) {
+                        g.name = pack_grammar.name.clone();
+                    }
+                    coords.push(coord);
+                }
+                self.get_session_mut().packages.push(InstalledPack {
+                    name: pack.name,
+                    version: pack.version,
+                    registry,
+                    coords,
+                });
+                self.history.record(before);
+                true
+            }
 
-            console: ConsoleService::new(),
-            reader: ReaderService::new(),
+            Action::UninstallPack(name) => {
+                let before = self.snapshot();
+                if self.uninstall_pack(&name) {
+                    self.history.record(before);
+                }
+                true
+            }
 
-            focus_cell: None,
-            first_select_cell: None,
-            last_select_cell: None,
+            Action::OpenMetaImport => {
+                let path = match choose_file(false, "Session", &["json"]) {
+                    Some(path) => path,
+                    None => return false,
+                };
+                let content = js! {
+                    try {
+                        return require("fs").readFileSync(@{&path}, "utf8");
+                    } catch (e) {
+                        return null;
+                    }
+                }
+                .into_string()
+                .ok_or_else(|| "couldn't read the file".to_string());
+                let session = match content.and_then(|content| parse_session(&content)) {
+                    Ok(session) => session,
+                    Err(e) => return self.update(Action::Alert(format! {"Couldn't open {}: {}", path, e})),
+                };
+                let import = MetaImport::new(path, session);
+                if import.listed.is_empty() {
+                    return self.update(Action::Notify(format! {"{} has no meta grammars", import.path}, true));
+                }
+                self.meta_import = Some(import);
+                true
+            }
 
-            secondary_selections: HashSet::new(),
+            Action::ToggleMetaImport(coord) => {
+                if let Some(import) = self.meta_import.as_mut() {
+                    import.toggle(&coord);
+                    return true;
+                }
+                false
+            }
 
-            min_select_cell: None,
-            max_select_cell: None,
-            zoom: 1.0,
-            file_popup: false,
+            Action::ApplyMetaImport => {
+                let import = match self.meta_import.take() {
+                    Some(import) => import,
+                    None => return false,
+                };
+                let taken: Vec<String> = self.meta_suggestions.iter().map(|(name, _)| name.clone()).collect();
+                let names = import_names(&import.listed, &import.selected, &taken);
+                let before = self.snapshot();
+                for (_, from) in import.listed.iter().filter(|(_, c)| import.selected.contains(c)) {
+                    let dest = self.next_meta_row();
+                    import_grammar(&import.grammars, from, &dest, &names, &mut self.get_session_mut().grammars);
+                }
+                self.history.record(before);
+                let renamed: Vec<String> = names
+                    .iter()
+                    .filter(|(name, imported)| name != imported)
+                    .map(|(name, imported)| format! {"{} as {}", name, imported})
+                    .collect();
+                let message = if renamed.is_empty() {
+                    format! {"Imported {} meta grammars", names.len()}
+                } else {
+                    format! {"Imported {} meta grammars ({})", names.len(), renamed.join(", ")}
+                };
+                self.update(Action::Notify(message, false))
+            }
 
-            sessions: vec![Session {
-                title: "my session".to_string(),
-                root: root_grammar.clone(),
-                meta: meta_grammar.clone(),
-                grammars: {
-                    let mut map = HashMap::new();
-                    build_grammar_map(
-                        &mut map,
-                        coord!("root"),
-                        grid![
-                            [
-                                g!(Grammar::input("", "A1")),
-                                g!(Grammar::input("", "B1")),
-                                g!(Grammar::input("", "C1"))
-                            ],
-                            [
-                                g!(Grammar::input("", "A2")),
-                                g!(Grammar::input("", "B2")),
-                                g!(Grammar::input("", "C2"))
-                            ],
-                            [
-                                g!(Grammar::input("", "A3")),
-                                g!(Grammar::input("", "B3")),
-                                g!(Grammar::input("", "C3"))
-                            ]
-                        ],
-                    );
-                    build_grammar_map(
-                        &mut map,
-                        coord!("meta"),
-                        grid![
-                            [g!(Grammar::input("", "A1"))],
-                            [g!(Grammar::input("", "A2"))],
-                            [g!(Grammar::default_button())],
-                            [g!(Grammar::default_slider())],
-                            [g!(Grammar::default_toggle())]
-                        ],
-                    );
-                    build_grammar_map(
-                        &mut map,
-                        coord!("meta-A6"),
-                        grid![
-                            [
-                                g!(Grammar {
-                                    name: "defn_label".to_string(),
-                                    style: {
-                                        let mut s = Style::default();
-                                        s.font_weight = 600;
-                                        s
-                                    },
-                                    kind: Kind::Text("Define Grammar".to_string()),
-                                }),
-                                g!(Grammar {
-                                    name: "defn_name".to_string(),
-                                    style: Style::default(),
-                                    kind: Kind::Input(String::new()),
-                                })
-                            ],
-                            [grid![
-                                [
-                                    g!(Grammar::input("rule_name", "")),
-                                    g!(Grammar::input("rule_grammar", ""))
-                                ],
-                                [
-                                    g!(Grammar::input("rule_name", "")),
-                                    g!(Grammar::input("rule_grammar", ""))
-                                ]
-                            ]]
-                        ],
-                    );
-                    assert!(map.contains_key(&(coord!("root"))));
-                    map
-                },
-            }],
+            Action::CloseMetaImport => {
+                self.meta_import = None;
+                true
+            }
 
-            current_session_index: 0,
+            Action::OpenSessionMerge => {
+                let path = match choose_file(false, "Session", &["json"]) {
+                    Some(path) => path,
+                    None => return false,
+                };
+                let content = js! {
+                    try {
+                        return require("fs").readFileSync(@{&path}, "utf8");
+                    } catch (e) {
+                        return null;
+                    }
+                }
+                .into_string()
+                .ok_or_else(|| "couldn't read the file".to_string());
+                let theirs = match content.and_then(|content| parse_session(&content)) {
+                    Ok(session) => session,
+                    Err(e) => return self.update(Action::Alert(format! {"Couldn't open {}: {}", path, e})),
+                };
+                self.session_merge = Some(SessionMerge::new(self.get_session(), path, theirs));
+                true
+            }
 
-            side_menus: vec![
-                SideMenu {
-                    name: "Home".to_string(),
-                    icon_path: "assets/logo.png".to_string(),
-                },
-                SideMenu {
-                    name: "File Explorer".to_string(),
-                    icon_path: "assets/folder_icon.png".to_string(),
-                },
-                SideMenu {
-                    name: "Settings".to_string(),
-                    icon_path: "assets/settings_icon.png".to_string(),
-                },
-                SideMenu {
-                    name: "Info".to_string(),
-                    icon_path: "assets/info_icon.png".to_string(),
-                },
-            ],
-            open_side_menu: None,
+            Action::SetMergeChoice(coord, choice) => {
+                match (self.session_merge.as_mut(), coord) {
+                    (Some(merge), Some(coord)) => {
+                        merge.choices.insert(coord, choice);
+                    }
+                    (Some(merge), None) => merge.choose_all(choice),
+                    (None, _) => return false,
+                }
+                true
+            }
 
-            resizing: None,
+            Action::ApplySessionMerge => {
+                let merge = match self.session_merge.take() {
+                    Some(merge) => merge,
+                    None => return false,
+                };
+                let mut merged = merge.merged(&self.saved_session());
+                let layout = std::mem::take(&mut merged.layout);
+                // the merged session has no file yet, so it isn't saved over either of the two
+                merged.view = TabView::default();
+                self.sessions.push(merged);
+                self.switch_tab(self.sessions.len() - 1);
+                self.apply_layout(layout);
+                self.repair_on_load();
+                self.update(Action::PersistWorkspace);
+                let message = format! {"Merged {} ({} conflicts) into a new tab", merge.path, merge.conflicts.len()};
+                self.update(Action::Notify(message, false))
+            }
 
-            link,
-            tasks: vec![],
+            Action::CloseSessionMerge => {
+                self.session_merge = None;
+                true
+            }
 
-            focus_node_ref: NodeRef::default(),
-            next_focus_node_ref: NodeRef::default(),
+            Action::SetIterationSettings(settings) => {
+                self.get_session_mut().iteration = settings;
+                self.calc_report = None;
+                self.update(Action::Recalculate)
+            }
 
-            shift_key_pressed: false,
+            Action::ClearReference(coord) => {
+                let before = self.snapshot();
+                let grammars = self.get_session().grammars.clone();
+                let cleared = match self.get_session_mut().grammars.get_mut(&coord) {
+                    Some(g) => match &mut g.kind {
+                        Kind::Lookup(_, lookup) if lookup.is_some() => {
+                            *lookup = None;
+                            true
+                        }
+                        // definitions drop the rules whose cells are gone
+                        Kind::Defn(_, _, rules) => {
+                            let len = rules.len();
+                            rules.retain(|(_, c)| grammars.contains_key(c));
+                            rules.len() != len
+                        }
+                        _ => false,
+                    },
+                    None => false,
+                };
+                if cleared {
+                    self.history.record(before);
+                }
+                cleared
+            }
 
-            default_nested_row_cols: non_zero_u32_tuple((3, 3)),
+            Action::RetargetReference(coord, target) => {
+                if !self.get_session().grammars.contains_key(&target) {
+                    info! {"[Action::RetargetReference] {} doesn't exist", target.to_string()};
+                    return false;
+                }
+                let before = self.snapshot();
+                if let Some(Grammar {
+                    kind: Kind::Lookup(_, lookup),
+                    ..
+                }) = self.get_session_mut().grammars.get_mut(&coord)
+                {
+                    *lookup = Some(Lookup::Cell(target));
+                    self.history.record(before);
+                    return true;
+                }
+                false
+            }
 
-            context_menu_position: None,
+            Action::Undo => {
+                // the current state is redone as the same kind of change as the one undone
+                let kind = self.history.last().map_or(EditKind::Content, |s| s.kind);
+                let current = Snapshot {
+                    kind,
+                    ..self.snapshot()
+                };
+                if let Some(snapshot) = self.history.undo(current) {
+                    self.restore(snapshot);
+                    return true;
+                }
+                false
+            }
 
-            default_definition_name: "".to_string(),
+            Action::Redo => {
+                // the current state is undone again as the same kind of change as the one redone
+                let kind = self.history.next().map_or(EditKind::Content, |s| s.kind);
+                let current = Snapshot {
+                    kind,
+                    ..self.snapshot()
+                };
+                if let Some(snapshot) = self.history.redo(current) {
+                    self.restore(snapshot);
+                    return true;
+                }
+                false
+            }
 
-            mouse_cursor: CursorType::Default,
+            Action::TogglePlaceholder(coord) => {
+                if let Some(g) = self.get_session_mut().grammars.get_mut(&coord) {
+                    g.style.placeholder = match g.style.placeholder {
+                        Some(_) => None,
+                        None => Some(g.name.clone()),
+                    };
+                    return true;
+                }
+                false
+            }
 
-            lookups: vec![],
-        };
-        // load suggestions from
-        m.meta_suggestions = m
-            .query_col(coord_col!("meta", "A"))
-            .iter()
-            .filter_map(|coord| {
-                if let Some(name) = m.get_session().grammars.get(coord).map(|g| g.name.clone()) {
-                    Some((name, coord.clone()))
-                } else {
-                    None
+            Action::NextPlaceholder => {
+                // filled placeholders are done with, the rest are visited in order
+                let grammars = &self.get_session().grammars;
+                let remaining: Vec<Coordinate> = self
+                    .placeholders
+                    .iter()
+                    .filter(|c| grammars.get(c).map_or(false, |g| g.style.placeholder.is_some()))
+                    .cloned()
+                    .collect();
+                let next = match self.active_cell.clone() {
+                    Some(active) => remaining
+                        .iter()
+                        .find(|c| c.row_cols > active.row_cols)
+                        .or(remaining.first())
+                        .cloned(),
+                    None => remaining.first().cloned(),
+                };
+                self.placeholders = remaining;
+                match next {
+                    Some(c) => self.update(Action::SetActiveCell(c)),
+                    None => true,
                 }
-            })
-            .collect();
-        m
-    }
+            }
 
-    // The update function is split into sub-update functions that
-    // are specifc to each EventType
-    fn update(&mut self, event_type: Self::Message) -> ShouldRender {
-        let should_render = match event_type {
-            Action::Noop => false,
+            Action::ExitPlaceholderMode => {
+                self.placeholders = vec![];
+                true
+            }
 
-            Action::Alert(message) => {
-                self.console.log(&message);
-                // TODO: make this into a more visual thing
-                false
+            Action::ToggleSuggestionCategory(category) => {
+                let mut preferences = self.preferences.clone();
+                let collapsed = &mut preferences.collapsed_suggestion_categories;
+                if let Some(index) = collapsed.iter().position(|c| *c == category) {
+                    collapsed.remove(index);
+                } else {
+                    collapsed.push(category);
+                }
+                self.update(Action::SetPreferences(preferences))
             }
 
-            Action::ChangeInput(coord, new_value) => {
-                set_data_cell(&coord.clone(), new_value.clone().to_string());
-                if let Some(g) = self.get_session_mut().grammars.get_mut(&coord) {
-                    match g {
-                        Grammar {
-                            kind: Kind::Input(_),
-                            ..
-                        } => {
-                            g.kind = Kind::Input(new_value);
-                        }
-                        Grammar {
-                            kind: Kind::Lookup(_, lookup_type),
-                            ..
-                        } => {
-                            g.kind = Kind::Lookup(new_value, lookup_type.clone());
-                        }
-                        _ => (),
+            Action::ToggleCollapsedGrid(coord) => {
+                let key = self.session_key();
+                let grid = coord.to_string();
+                let mut preferences = self.preferences.clone();
+                let collapsed = preferences.collapsed_grids.entry(key.clone()).or_default();
+                if let Some(index) = collapsed.iter().position(|c| *c == grid) {
+                    collapsed.remove(index);
+                } else {
+                    collapsed.push(grid.clone());
+                    // the cells of a collapsed grid aren't shown, so they can't stay active
+                    let inside = |c: &Coordinate| c.to_string().starts_with(&format! {"{}-", grid});
+                    if self.active_cell.as_ref().map_or(false, inside) {
+                        self.active_cell = Some(coord.clone());
                     }
                 }
-                
+                if collapsed.is_empty() {
+                    preferences.collapsed_grids.remove(&key);
+                }
+                self.update(Action::SetPreferences(preferences))
+            }
+
+            Action::CycleSuggestionCategory(coord) => {
+                if let Some(g) = self.get_session_mut().grammars.get_mut(&coord) {
+                    g.style.category = Some(category_of(g).next());
+                    return true;
+                }
                 false
             }
 
-            Action::SetActiveCell(coord) => {
-                self.active_cell = Some(coord.clone());
-                self.focus_cell = Some(coord.clone());
-                focus_on_cell(&coord);
+            Action::SetActiveMenu(active_menu) => {
+                self.open_side_menu = active_menu;
                 true
             }
+            // Read File and Adds Task
+            Action::ReadSession(file) => self.guard(GuardedAction::ReadSession(file)),
 
-            Action::NextSuggestion(coord, index) => {
-                let next_suggestion_id =
-                    format! {"cell-{}-suggestion-{}", coord.to_string(), index};
-                js! {
-                    try {
-                        let element = document.getElementById(@{next_suggestion_id.clone()});
-                        element.focus();
-                    } catch (e) {
-                        console.log("cannot focus on next suggestion");
+            Action::OpenSessionPath(path) => {
+                let session = match read_session_file(&path) {
+                    Ok(session) => session,
+                    Err(e) => return self.update(Action::Notify(format! {"Couldn't open {}: {}", path, e}, true)),
+                };
+                let open = self
+                    .sessions
+                    .iter()
+                    .position(|s| s.view.file_path.as_ref() == Some(&path));
+                match open {
+                    Some(index) => {
+                        self.switch_tab(index);
+                        self.guard(GuardedAction::ReloadSession(session))
                     }
+                    None => {
+                        self.open_tab(path, session);
+                        true
+                    }
+                }
+            }
+
+            // Deserialize and Loads Session
+            Action::LoadSession(file_data) => {
+                let content = String::from_utf8(file_data.content)
+                    .map_err(|_| "the file isn't text".to_string());
+                let mut session = match content.and_then(|content| parse_session(&content)) {
+                    Ok(session) => session,
+                    Err(e) => return self.update(Action::Alert(format! {"Couldn't open {}: {}", file_data.name, e})),
                 };
+                if let Some(path) = self.get_session().view.file_path.clone() {
+                    load_sidecar_assets(&sidecar_dir(&path), &mut session.assets);
+                }
+                self.load_session(session);
+                self.get_session_mut().view.dirty = false;
+                true
+            }
+            // Popup file name
+            Action::AskFileName() => {
+                self.file_popup = !self.file_popup;
                 true
             }
+            // File Saving
+            Action::SaveSession() => {
+                // Stamp & copy session
+                let metadata = &mut self.get_session_mut().metadata;
+                metadata.modified = now_iso();
+                if metadata.created.is_empty() {
+                    metadata.created = metadata.modified.clone();
+                }
+                // assets no cell refers to anymore aren't saved
+                let grammars = self.get_session().grammars.clone();
+                collect_garbage(&mut self.get_session_mut().assets, &grammars);
+                // orphaned grammars are reported rather than dropped, so they can be looked at
+                // before they're pruned from the Diagnostics panel
+                let orphaned = orphans(&grammars).len();
+                if orphaned > 0 {
+                    self.update(Action::Notify(
+                        format! {"{} orphaned grammars were saved, prune them from the Diagnostics panel", orphaned},
+                        false,
+                    ));
+                }
+                let mut current_session = self.saved_session();
+                let follow_ups = self.run_session_hooks(Stage::PreSave, &mut current_session);
+                self.run_hook_follow_ups(follow_ups);
+                // the session goes back to the file it was opened from (or last saved to), where
+                // its sidecar assets are loaded from, or to a new file named after its title
+                let filename = match self.get_session().view.file_path.clone() {
+                    Some(path) => path,
+                    None => current_session.title.to_string() + ".json",
+                };
+                // in Electron the assets go to a folder next to the session file, web builds
+                // keep them in the file, as do the assets that couldn't be written to the folder
+                let written = save_sidecar_assets(&sidecar_dir(&filename), &current_session.assets);
+                for asset in current_session.assets.iter_mut().filter(|a| written.contains(&a.name)) {
+                    asset.data = String::new();
+                }
+                let content = serde_json::to_string(&current_session).unwrap();
+                // the file is replaced, so saving again doesn't leave two sessions in it
+                let written: bool = js! {
+                    try {
+                        require("fs").writeFileSync(@{filename.clone()}, @{content});
+                        return true;
+                    } catch (e) {
+                        return false;
+                    }
+                }
+                .try_into()
+                .unwrap_or(false);
+                if !written {
+                    return self.update(Action::Alert(format! {"couldn't write {}", filename}));
+                }
+                self.get_session_mut().view.file_path = Some(filename);
+                self.get_session_mut().view.dirty = false;
+                // Conditionnal Closing
+                if self.file_popup {self.update(Action::AskFileName());};
 
-            Action::ReadCSVFile(file, coord) => {
-                let upload_callback = self.link.callback(move |file_data: FileData| {
-                    Action::LoadCSVFile(file_data.clone(), coord.clone())
-                });
-                let task = self.reader.read_file(file, upload_callback.clone());
-                self.tasks.push(task);
-                false
+                true
             }
 
-            Action::LoadCSVFile(file_data, coordinate) => {
-                let csv = std::str::from_utf8(&file_data.content).unwrap().to_string();
-                let mut reader = csv::Reader::from_reader(csv.as_bytes());
-                let mut grid: Vec<Vec<String>> = Vec::new();
-                let headers_csv = reader.headers().unwrap();
-                let mut header_row: Vec<String> = Vec::new();
-                let len_header = headers_csv.len() as i32;
+            Action::ExportCsv => {
+                let grid = self.view_root.clone();
+                let table = match flatten(&self.get_session().grammars, &grid, self.preferences.flatten_csv_export) {
+                    Some(table) => table,
+                    None => return self.update(Action::Alert(format! {"{} isn't a grid", grid.to_string()})),
+                };
+                let path = match choose_file(true, "CSV", &["csv", "tsv"]) {
+                    Some(path) => path,
+                    None => return false,
+                };
+                let delimiter = if path.to_lowercase().ends_with(".tsv") { b'\t' } else { b',' };
+                let content = to_delimited(&table, delimiter);
+                let written: bool = js! {
+                    try {
+                        require("fs").writeFileSync(@{path.clone()}, @{content});
+                        return true;
+                    } catch (e) {
+                        return false;
+                    }
+                }
+                .try_into()
+                .unwrap_or(false);
+                if !written {
+                    return self.update(Action::Alert(format! {"couldn't write {}", path}));
+                }
+                self.update(Action::Notify(format! {"Exported {} to {}", grid.to_string(), path}, false))
+            }
 
-                for header in 0..len_header {
-                    let header_usize = header as usize;
-                    header_row.push(headers_csv.get(header_usize).unwrap().to_string());
+            Action::SaveCopy => {
+                let path = match choose_file(true, "Session", &["json"]) {
+                    Some(path) => path,
+                    None => return false,
+                };
+                let grammars = self.get_session().grammars.clone();
+                let mut copy = self.saved_session();
+                copy.metadata.modified = now_iso();
+                if copy.metadata.created.is_empty() {
+                    copy.metadata.created = copy.metadata.modified.clone();
                 }
-                grid.push(header_row);
+                collect_garbage(&mut copy.assets, &grammars);
+                let follow_ups = self.run_session_hooks(Stage::PreSave, &mut copy);
+                self.run_hook_follow_ups(follow_ups);
+                let written = save_sidecar_assets(&sidecar_dir(&path), &copy.assets);
+                for asset in copy.assets.iter_mut().filter(|a| written.contains(&a.name)) {
+                    asset.data = String::new();
+                }
+                let content = serde_json::to_string(&copy).unwrap();
+                let written: bool = js! {
+                    try {
+                        require("fs").writeFileSync(@{path.clone()}, @{content});
+                        return true;
+                    } catch (e) {
+                        return false;
+                    }
+                }
+                .try_into()
+                .unwrap_or(false);
+                if !written {
+                    return self.update(Action::Alert(format! {"couldn't write {}", path}));
+                }
+                self.update(Action::Notify(format! {"Saved a copy to {}", path}, false))
+            }
 
-                for row in reader.records() {
-                    let mut grid_row = Vec::new();
-                    let row = row.unwrap();
-                    let lenght_r = row.len() as i32;
-                    for cell in 0..lenght_r {
-                        let cell_usize = cell as usize;
-                        grid_row.push(row.get(cell_usize).unwrap().to_string());
+            Action::OpenSessionInfo => {
+                // -1 when the session hasn't been saved, or its file is gone
+                let size: f64 = match self.get_session().view.file_path.clone() {
+                    Some(path) => js! {
+                        try {
+                            return require("fs").statSync(@{path}).size;
+                        } catch (e) {
+                            return -1;
+                        }
+                    }
+                    .try_into()
+                    .unwrap_or(-1.0),
+                    None => -1.0,
+                };
+                let file_size = Some(size).filter(|size| *size >= 0.0).map(|size| size as u64);
+                self.session_info = Some(session_info(self.get_session(), file_size));
+                true
+            }
+
+            Action::ExportSessionInfo => {
+                let info = match &self.session_info {
+                    Some(info) => info.clone(),
+                    None => return false,
+                };
+                let path = match choose_file(true, "JSON", &["json"]) {
+                    Some(path) => path,
+                    None => return false,
+                };
+                let content = serde_json::to_string_pretty(&info).unwrap();
+                let written: bool = js! {
+                    try {
+                        require("fs").writeFileSync(@{path.clone()}, @{content});
+                        return true;
+                    } catch (e) {
+                        return false;
                     }
-                    grid.push(grid_row);
                 }
-                let num_rows = grid.len();
-                let num_cols = grid[0].len();
+                .try_into()
+                .unwrap_or(false);
+                if !written {
+                    return self.update(Action::Alert(format! {"couldn't write {}", path}));
+                }
+                self.update(Action::Notify(format! {"Exported session info to {}", path}, false))
+            }
 
-                self.update(Action::AddNestedGrid(
-                    coordinate.clone(),
-                    (num_rows as u32, num_cols as u32),
-                ));
+            Action::CloseSessionInfo => {
+                self.session_info = None;
+                true
+            }
 
-                let parent = coordinate.parent().unwrap();
-                if let Some(Grammar {
-                    kind: Kind::Grid(sub_coords),
-                    name,
-                    style,
-                }) = self.get_session().grammars.get(&parent)
-                {
-                    let mut grammar = self.get_session().grammars.clone();
-                    for coord_ in sub_coords {
-                        let row_ = coord_.0.get() as usize;
-                        let col_ = coord_.1.get() as usize;
-                        let c = Coordinate::child_of(&coordinate, *coord_);
-                        let grid_: &str = &grid[row_ - 1][col_ - 1];
-                        grammar.remove(&c);
-                        grammar.insert(c, Grammar::input("", grid_));
-                    }
-                    self.get_session_mut().grammars = grammar;
-                }
+            Action::SetSessionTitle(name) => {
+                self.get_session_mut().title = name;
+                true
+            }
 
+            Action::SetSessionMetadata(metadata) => {
+                self.get_session_mut().metadata = metadata;
                 true
             }
 
-            Action::Select(SelectMsg::Start(coord)) => {
-                self.first_select_cell = Some(coord.clone());
-                self.last_select_cell = None;
+            Action::AddBookmark(name) => {
+                let range = self.selection.corners();
+                let bookmark = Bookmark {
+                    name,
+                    view_root: self.view_root.clone(),
+                    scroll: main_scroll(),
+                    zoom: self.zoom,
+                    range,
+                };
+                self.get_session_mut().bookmarks.push(bookmark);
                 true
             }
-            Action::Select(SelectMsg::End(coord)) => {
-                if let Some(mut selection_start) = self.first_select_cell.clone() {
-                    // ensure that selection_start and selection_end have common parent
-                    let mut common_parent = selection_start.parent();
-                    let mut selection_end = Some(coord.clone());
-                    let depth_start = selection_start.row_cols.len();
-                    let depth_end = selection_end.clone().unwrap().row_cols.len();
-                    // depend on which select coord has higher depth, find their common parent
-                    if depth_start < depth_end {
-                        while selection_end.clone().and_then(|c| c.parent()) != common_parent {
-                            selection_end = selection_end.and_then(|c| c.parent());
-                        }
+
+            Action::RecallBookmark(index) => {
+                let bookmark = match self.get_session().bookmarks.get(index) {
+                    Some(b) => b.clone(),
+                    None => return false,
+                };
+                if !self.get_session().grammars.contains_key(&bookmark.view_root) {
+                    let text = format! {"Can't recall {}, {} was deleted", bookmark.name, bookmark.view_root.to_string()};
+                    return self.update(Action::Notify(text, true));
+                }
+                self.view_root = bookmark.view_root;
+                self.zoom = bookmark.zoom;
+                if let Some((first, last)) = bookmark.range {
+                    let grammars = &self.sessions[self.current_session_index].grammars;
+                    if grammars.contains_key(&first) && grammars.contains_key(&last) {
+                        self.selection.start(grammars, &first);
+                        self.selection.extend(grammars, &last);
+                        self.active_cell = Some(first);
                     } else {
-                        common_parent = selection_end.clone().unwrap().parent();
-                        while selection_start.parent() != common_parent {
-                            selection_start = selection_start.parent().unwrap();
-                        }
+                        let text = format! {"The cells {} highlights were deleted", bookmark.name};
+                        self.update(Action::Notify(text, true));
                     }
-                    // find the min of row,col and max of row,col in selected region
-                    // which may contain a span coord that has smaller or larger row,col
-                    let (mut start_row, mut start_col) = selection_start.clone().row_col();
-                    let (mut end_row, mut end_col) = selection_end.clone().unwrap().row_col();
-                    if start_row > end_row {
-                        let tmp = start_row.clone();
-                        start_row = end_row;
-                        end_row = tmp;
-                    }
-                    if start_col > end_col {
-                        let tmp = start_col.clone();
-                        start_col = end_col;
-                        end_col = tmp;
-                    }
-                    let depth_check = selection_start.row_cols.len().clone();
-                    let ref_grammas = self.get_session().grammars.clone();
-                    let mut check = false;
-                    while !check {
-                        check = true;
-                        let row_range = start_row.get()..=end_row.get();
-                        let col_range = start_col.get()..=end_col.get();
-                        for (coord, grammar) in ref_grammas.iter() {
-                            let (coord_row, coord_col) = coord.clone().row_col();
-                            let coord_depth = coord.clone().row_cols.len();
-                            if row_range.contains(&coord_row.get())
-                                && col_range.contains(&coord_col.get())
-                                && (coord_depth == depth_check)
-                            {
-                                let col_span = grammar.clone().style.col_span;
-                                let row_span = grammar.clone().style.row_span;
-                                if col_span.0 != 0 && col_span.1 != 0 {
-                                    if col_span.0 < start_col.get() {
-                                        start_col = NonZeroU32::new(col_span.0).unwrap();
-                                        check = false;
-                                    }
-                                    if col_span.1 > end_col.get() {
-                                        end_col = NonZeroU32::new(col_span.1).unwrap();
-                                        check = false;
-                                    }
-                                }
-                                if row_span.0 != 0 && row_span.1 != 0 {
-                                    if row_span.0 < start_row.get() {
-                                        start_row = NonZeroU32::new(row_span.0).unwrap();
-                                        check = false;
-                                    }
-                                    if row_span.1 > end_row.get() {
-                                        end_row = NonZeroU32::new(row_span.1).unwrap();
-                                        check = false;
-                                    }
-                                }
-                            }
-                        }
-                    }
-
-                    selection_start.row_cols[depth_check - 1] = (start_row, start_col);
-                    selection_end.as_mut().unwrap().row_cols[depth_check - 1] = (end_row, end_col);
-                    self.first_select_cell = Some(selection_start.clone());
-                    self.last_select_cell = selection_end.clone();
                 }
+                set_main_scroll(bookmark.scroll);
                 true
             }
 
-            Action::RangeDelete() => {
-                let (first_row, first_col) = self.first_select_cell.clone().unwrap().row_col();
-                let (last_row, last_col) = self.last_select_cell.clone().unwrap().row_col();
-
-                let row_range = first_row.get()..=last_row.get();
-                let col_range = first_col.get()..=last_col.get();
+            Action::RemoveBookmark(index) => {
+                if index < self.get_session().bookmarks.len() {
+                    self.get_session_mut().bookmarks.remove(index);
+                    return true;
+                }
+                false
+            }
 
-                let parent_check = self.last_select_cell.clone().unwrap().parent();
-                let depth_check = self.last_select_cell.clone().unwrap().row_cols.len();
+            Action::RequestQuit => self.guard(GuardedAction::Quit),
 
-                let mut ref_grammars = self.get_session_mut().grammars.clone();
-<<<<<<< HEAD
-                for (coord, grammar) in ref_grammars.clone().iter_mut() {
-                    if row_range.contains(&coord.row().get())
-                        && col_range.contains(&coord.col().get())
-                        && coord.parent() == parent_check
-                    {
-                        let get_kind = grammar.kind.clone();
-                        match get_kind {
-                            Kind::Input(value) => {
-                                grammar.kind = Kind::Input("".to_string());
-                                self.get_session_mut()
-=======
-                for (coord, grammar) in ref_grammars.clone().iter_mut() {              
-                        if row_range.contains(&coord.row().get()) && col_range.contains(&coord.col().get()) && coord.parent() == parent_check                    
-                        {                                                       
-                            let get_kind = grammar.kind.clone();
-                            match get_kind {
-                                Kind::Input(value) => {
-                                    grammar.kind =  Kind::Input("".to_string());
-                                    set_data_cell(&coord, "".to_string());                                 
-                                    self.get_session_mut()
->>>>>>> hieule/fix_bug
-                                    .grammars
-                                    .insert(coord.clone(), grammar.clone());
-                            }
-                            Kind::Grid(sub_coords) => {
-                                for (c, g) in ref_grammars.clone().iter_mut() {
-                                    if c.parent().is_some() && c.parent().unwrap() == coord.clone()
-                                    {
-                                        g.kind = Kind::Input("".to_string());
-                                        self.get_session_mut()
-                                            .grammars
-                                            .insert(c.clone(), g.clone());
-<<<<<<< HEAD
-=======
-                                            set_data_cell(&c, "".to_string());
-                                        }
->>>>>>> hieule/fix_bug
-                                    }
-                                }
-                            }
-                            _ => continue,
+            Action::ResolveGuard(choice) => {
+                let action = match self.pending_guard.take() {
+                    Some(action) => action,
+                    None => return false,
+                };
+                match choice {
+                    GuardChoice::Cancel => return true,
+                    GuardChoice::Save => {
+                        // quitting (or closing tabs) saves every tab with unsaved changes it would
+                        // lose, the rest only the current one
+                        let current = self.current_session_index;
+                        let to_save: Vec<usize> = match action {
+                            GuardedAction::Quit => (0..self.sessions.len())
+                                .filter(|i| self.sessions[*i].view.dirty)
+                                .collect(),
+                            GuardedAction::CloseTabs(ref closed) => closed
+                                .iter()
+                                .cloned()
+                                .filter(|i| self.sessions[*i].view.dirty)
+                                .collect(),
+                            _ => vec![current],
+                        };
+                        for index in to_save {
+                            self.current_session_index = index;
+                            self.update(Action::SaveSession());
                         }
+                        self.current_session_index = current;
                     }
+                    GuardChoice::Discard => (),
                 }
+                self.run_guarded(action);
                 true
             }
 
-            Action::MergeCells() => {
-                if self.first_select_cell.is_none() || self.last_select_cell.is_none() {
-                    info!("Expect for select of two coord");
-                    return false;
+            Action::PersistWorkspace => {
+                let zoom = self.zoom;
+                let view = &mut self.get_session_mut().view;
+                view.scroll = main_scroll();
+                view.zoom = zoom;
+                let workspace = Workspace {
+                    tabs: self.sessions.iter().map(WorkspaceTab::of).collect(),
+                    active_tab: self.current_session_index,
+                };
+                workspace.store(&mut self.storage);
+                // the workspace has every tab as it is now, which makes the autosave redundant
+                if self.recovery.is_none() {
+                    Autosave::clear(&mut self.storage);
                 }
-                let (first_row, first_col) = self.first_select_cell.clone().unwrap().row_col();
-                let (last_row, last_col) = self.last_select_cell.clone().unwrap().row_col();
-
-                let depth_check = self.last_select_cell.clone().unwrap().row_cols.len();
-                let parent_check = self.last_select_cell.clone().unwrap().parent();
+                false
+            }
 
-                let row_range = first_row.get()..=last_row.get();
-                let col_range = first_col.get()..=last_col.get();
+            Action::Autosave => {
+                if self.recovery.is_some() {
+                    return false;
+                }
+                let tabs: Vec<(Session, TabView)> = self
+                    .sessions
+                    .iter()
+                    .filter(|s| s.view.dirty)
+                    .map(|s| (s.clone(), s.view.clone()))
+                    .collect();
+                if tabs.is_empty() {
+                    Autosave::clear(&mut self.storage);
+                } else {
+                    let autosave = Autosave {
+                        saved_at: now_local(),
+                        tabs,
+                    };
+                    autosave.store(&mut self.storage);
+                }
+                false
+            }
 
-                let mut merge_height = 0.00;
-                let mut merge_width = 0.00;
-                let mut max_coord = Coordinate::default();
-                let mut max_grammar = Grammar::default();
-                let mut ref_grammas = self.get_session_mut().grammars.clone();
-                for (coord, grammar) in ref_grammas.iter_mut() {
-                    if coord.to_string().contains("root-") {
-                        if row_range.contains(&coord.row().get())
-                            && col_range.contains(&coord.col().get())
-                            && coord.parent() == parent_check
-                        {
-                            let coord_style = grammar.style.clone();
-                            if coord_style.display != false {
-                                if coord.row().get() == last_row.get() {
-                                    merge_width = merge_width + coord_style.width;
-                                }
-                                if coord.col().get() == last_col.get() {
-                                    merge_height = merge_height + coord_style.height;
-                                }
-                                if coord.row().get() == last_row.get()
-                                    && (coord.col().get() == last_col.get())
-                                {
-                                    max_coord = coord.clone();
-                                    max_grammar = grammar.clone();
-                                } else {
-                                    grammar.style.display = false;
-                                }
-                            }
-                            grammar.kind = Kind::Input("".to_string());
-                            grammar.style.col_span.0 = first_col.get();
-                            grammar.style.col_span.1 = last_col.get();
-                            grammar.style.row_span.0 = first_row.get();
-                            grammar.style.row_span.1 = last_row.get();
-                            self.get_session_mut()
-                                .grammars
-                                .insert(coord.clone(), grammar.clone());
+            Action::RecoverAutosave => {
+                let autosave = match self.recovery.take() {
+                    Some(autosave) => autosave,
+                    None => return false,
+                };
+                let mut first = None;
+                for (mut session, view) in autosave.tabs {
+                    session.view = TabView { dirty: true, ..view };
+                    // tabs of the same file are replaced, the others are opened next to them
+                    let open = session
+                        .view
+                        .file_path
+                        .as_ref()
+                        .and_then(|path| self.sessions.iter().position(|s| s.view.file_path.as_ref() == Some(path)));
+                    let index = match open {
+                        Some(index) => {
+                            self.sessions[index] = session;
+                            index
+                        }
+                        None => {
+                            self.sessions.push(session);
+                            self.sessions.len() - 1
                         }
+                    };
+                    first = first.or(Some(index));
+                }
+                if let Some(index) = first {
+                    if index == self.current_session_index {
+                        self.view_root = coord!("root");
+                        self.active_cell = Some(coord!("root-A1"));
                     }
+                    self.switch_tab(index);
                 }
-                max_grammar.kind = Kind::Input("".to_string());
-                max_grammar.style.width = merge_width;
-                max_grammar.style.height = merge_height;
-                max_grammar.style.col_span.0 = first_col.get();
-                max_grammar.style.col_span.1 = last_col.get();
-                max_grammar.style.row_span.0 = first_row.get();
-                max_grammar.style.row_span.1 = last_row.get();
-                self.get_session_mut()
-                    .grammars
-                    .insert(max_coord.clone(), max_grammar.clone());
+                self.update(Action::PersistWorkspace);
                 true
             }
 
-            Action::DoCompletion(source_coord, dest_coord) => {
-                move_grammar(self, source_coord, dest_coord.clone());
+            Action::DiscardAutosave => {
+                self.recovery = None;
+                Autosave::clear(&mut self.storage);
                 true
             }
 
-            Action::SetActiveMenu(active_menu) => {
-                self.open_side_menu = active_menu;
+            Action::NewTab => {
+                let titles: Vec<&str> = self.sessions.iter().map(|s| s.title.as_str()).collect();
+                let session = blank_session(untitled_title(&titles));
+                self.sessions.push(session);
+                self.switch_tab(self.sessions.len() - 1);
+                self.bootstrap_session();
+                self.update(Action::PersistWorkspace);
                 true
             }
-            // Read File and Adds Task
-            Action::ReadSession(file) => {
-                self.tasks.push(
-                    self.reader
-                        .read_file(file, self.link.callback(Action::LoadSession)),
-                );
-                false
+
+            Action::SwitchTab(index) => {
+                self.switch_tab(index);
+                true
             }
 
-            // Deserialize and Loads Session
-            Action::LoadSession(file_data) => {
-                use std::str;
-                let session: Session =  serde_json::from_str(str::from_utf8(&file_data.content).unwrap()).unwrap();
-                self.load_session(session);
+            Action::ShowTabMenu(index, pos) => {
+                self.context_menu_position = None;
+                self.tab_menu = Some((index, pos));
                 true
             }
-            // Popup file name
-            Action::AskFileName() => {
-                self.file_popup = !self.file_popup;
+
+            Action::StartRenameTab(index) => {
+                self.renaming_tab = Some(index);
                 true
             }
-            // File Saving
-            Action::SaveSession() => {
-                // Imports
-                use js_sys::{Function, JsString};
-                use node_sys::fs as node_fs;
-                use node_sys::Buffer;
-                // Session Copy
-                let current_session = self.to_session();
-                // File naming
-                let j = serde_json::to_string(&current_session.clone());
-                let filename = current_session.title.to_string() + ".json";
-                let jsfilename = JsString::from(filename);
-                let jsbuffer = Buffer::from_string(&JsString::from(j.unwrap()), None);
-                let jscallback = Function::new_no_args("{}");
-                // File append
-                node_fs::append_file(&jsfilename, &jsbuffer, None, &jscallback);
-                // Conditionnal Closing
-                if self.file_popup {self.update(Action::AskFileName());};
 
+            Action::RenameTab(index, title) => {
+                self.renaming_tab = None;
+                let title = title.trim().to_string();
+                match self.sessions.get_mut(index) {
+                    Some(session) if !title.is_empty() && title != session.title => {
+                        session.title = title;
+                        session.view.dirty = true;
+                    }
+                    _ => return true,
+                }
+                self.update(Action::PersistWorkspace);
                 true
             }
 
-            Action::SetSessionTitle(name) => {
-                self.get_session_mut().title = name;
+            Action::DuplicateTab(index) => {
+                let mut session = match self.sessions.get(index) {
+                    Some(session) => session.clone(),
+                    None => return false,
+                };
+                // the copy isn't saved anywhere yet, so saving it asks for a file of its own
+                session.title = format! {"{} copy", session.title};
+                session.view = TabView {
+                    dirty: true,
+                    ..TabView::default()
+                };
+                self.sessions.insert(index + 1, session);
+                self.reindex_tabs(&|i| Some(if i > index { i + 1 } else { i }));
+                if self.current_session_index > index {
+                    self.current_session_index += 1;
+                }
+                self.switch_tab(index + 1);
+                self.update(Action::PersistWorkspace);
+                true
+            }
+
+            Action::CloseTab(index) => {
+                if index >= self.sessions.len() {
+                    return false;
+                }
+                self.guard(GuardedAction::CloseTabs(vec![index]))
+            }
+
+            Action::CloseOtherTabs(index) => {
+                let closed: Vec<usize> = (0..self.sessions.len()).filter(|i| *i != index).collect();
+                if closed.is_empty() {
+                    return false;
+                }
+                self.guard(GuardedAction::CloseTabs(closed))
+            }
+
+            Action::RevealTabFile(index) => {
+                let path = match self.sessions.get(index).and_then(|s| s.view.file_path.clone()) {
+                    Some(path) => path,
+                    None => return false,
+                };
+                if let Err(e) = ipc::send_sync(&ipc::Request::RevealFile { path }) {
+                    return self.update(Action::Alert(e));
+                }
+                false
+            }
+
+            Action::DragTab(index) => {
+                self.dragged_tab = index;
+                false
+            }
+
+            Action::MoveTab(from, to) => {
+                self.dragged_tab = None;
+                if from == to || from >= self.sessions.len() || to >= self.sessions.len() {
+                    return false;
+                }
+                let session = self.sessions.remove(from);
+                self.sessions.insert(to, session);
+                self.current_session_index = moved_index(self.current_session_index, from, to);
+                self.reindex_tabs(&|i| Some(moved_index(i, from, to)));
+                self.update(Action::PersistWorkspace);
                 true
             }
 
@@ -1032,53 +6548,230 @@ impl Component for Model {
                     )
                 };
 
+                let manifest = misc_files.iter().find(|f| f.name() == "manifest.json").cloned();
+                let name = driver_name(&main_file.name());
+
                 // upload misc files so they can be served by electron to be used by main driver file
+                self.uploads.retain(|batch| batch.driver != name);
+                if !misc_files.is_empty() {
+                    let names = misc_files.iter().map(|f| f.name()).collect();
+                    self.uploads.push(UploadBatch::new(name.clone(), names));
+                }
+                let driver = name.clone();
                 let upload_callback = self
                     .link
-                    .callback(|file_data| Action::UploadDriverMiscFile(file_data));
+                    .callback(move |file_data| Action::UploadDriverMiscFile(driver.clone(), file_data));
                 for file in misc_files {
                     let task = self.reader.read_file(file, upload_callback.clone());
                     self.tasks.push(task);
                 }
 
-                // Load main driver file. After this task has been scheduled and executed, the
-                // driver is ready for use.
-                self.tasks.push(
-                    self.reader
-                        .read_file(main_file, self.link.callback(Action::LoadDriverMainFile)),
-                );
+                // the manifest is read before the main file, so the driver's capabilities are
+                // known by the time it runs
+                if let Some(manifest) = manifest {
+                    let callback = self.link.callback(move |file_data| {
+                        Action::LoadDriverManifest(name.clone(), file_data, main_file.clone())
+                    });
+                    let task = self.reader.read_file(manifest, callback);
+                    self.tasks.push(task);
+                    return false;
+                }
+
+                // Load main driver file. After this task has been scheduled and executed, the
+                // driver is ready for use.
+                self.tasks.push(
+                    self.reader
+                        .read_file(main_file, self.link.callback(Action::LoadDriverMainFile)),
+                );
+
+                false
+            }
+
+            Action::LoadDriverManifest(name, file_data, main_file) => {
+                let manifest = std::str::from_utf8(&file_data.content)
+                    .map_err(|e| e.to_string())
+                    .and_then(parse_manifest);
+                match manifest {
+                    Ok(manifest) => self.driver_mut(&name).manifest = manifest,
+                    // the driver still loads, without any capabilities
+                    Err(e) => {
+                        self.update(Action::Alert(format! {"{}: {}", name, e}));
+                    }
+                }
+                self.tasks.push(
+                    self.reader
+                        .read_file(main_file, self.link.callback(Action::LoadDriverMainFile)),
+                );
+                false
+            }
+
+            Action::UploadDriverMiscFile(driver, file_data) => {
+                self.upload_queue.push((driver, file_data));
+                self.pump_uploads();
+                true
+            }
+
+            Action::DriverFileUploaded(driver, name, result) => {
+                self.uploads_in_flight -= 1;
+                let batch = match self.uploads.iter_mut().find(|b| b.driver == driver) {
+                    Some(batch) => batch,
+                    None => return false,
+                };
+                batch.set(
+                    &name,
+                    match result {
+                        Ok(()) => UploadState::Uploaded,
+                        Err(e) => UploadState::Failed(e),
+                    },
+                );
+                let summary = if batch.is_finished() { Some(batch.summary()) } else { None };
+                self.pump_uploads();
+                if let Some((text, error)) = summary {
+                    self.update(Action::Notify(text, error));
+                }
+                true
+            }
+
+            Action::Notify(text, error) => {
+                self.next_notification += 1;
+                self.notifications.push(Notification {
+                    id: self.next_notification,
+                    text,
+                    error,
+                });
+                true
+            }
+
+            Action::DismissNotification(id) => {
+                self.notifications.retain(|n| n.id != id);
+                true
+            }
+
+            Action::LoadDriverMainFile(main_file_data) => {
+                info! {"Loading Driver: {}", &main_file_data.name};
+                let file_contents = std::str::from_utf8(&main_file_data.content).unwrap();
+                let name = driver_name(&main_file_data.name);
+                self.driver_mut(&name).status = LoadStatus::Loading;
+                let file_contents = wrap_source(&name, file_contents);
+                js! {
+                    @{&self.driver_bridge}.load(@{name}, @{file_contents});
+                };
+                true
+            }
+
+            Action::DriverRequest(call) => {
+                let declared = match self.drivers.iter().find(|d| d.name == call.driver) {
+                    Some(driver) => driver.manifest.capabilities.clone(),
+                    None => {
+                        let reason = format! {"{} isn't a loaded driver", call.driver};
+                        settle_driver_call(&self.driver_bridge, call.id, false, reason);
+                        return false;
+                    }
+                };
+                let decision = match call.capability() {
+                    Ok(None) => Decision::Allow,
+                    Ok(Some(capability)) => {
+                        decide(&declared, &self.preferences.driver_grants, &call.driver, capability)
+                    }
+                    Err(e) => Decision::Block(e),
+                };
+                match decision {
+                    Decision::Allow => self.run_driver_call(call),
+                    Decision::Ask => {
+                        self.driver_prompts.push(call);
+                        true
+                    }
+                    Decision::Block(reason) => {
+                        self.audit_driver_call(&call, Some(reason.clone()));
+                        settle_driver_call(&self.driver_bridge, call.id, false, reason);
+                        true
+                    }
+                }
+            }
+
+            Action::ResolveDriverPrompt(grant) => {
+                if self.driver_prompts.is_empty() {
+                    return false;
+                }
+                let call = self.driver_prompts.remove(0);
+                let capability = match call.capability() {
+                    Ok(Some(capability)) => capability,
+                    _ => return true,
+                };
+                let mut preferences = self.preferences.clone();
+                let driver = call.driver.clone();
+                set_grant(
+                    &mut preferences.driver_grants,
+                    DriverGrant {
+                        driver: driver.clone(),
+                        capability,
+                        granted: grant,
+                    },
+                );
+                self.update(Action::SetPreferences(preferences));
+                // other calls waiting on the same answer are decided by it too
+                let (answered, waiting): (Vec<DriverCall>, Vec<DriverCall>) = self
+                    .driver_prompts
+                    .drain(..)
+                    .partition(|c| c.driver == driver && c.capability() == Ok(Some(capability)));
+                self.driver_prompts = waiting;
+                for call in std::iter::once(call).chain(answered) {
+                    self.update(Action::DriverRequest(call));
+                }
+                true
+            }
+
+            Action::RevokeDriverGrant(driver, capability) => {
+                let mut preferences = self.preferences.clone();
+                preferences
+                    .driver_grants
+                    .retain(|g| !(g.driver == driver && g.capability == capability));
+                self.update(Action::SetPreferences(preferences))
+            }
 
-                false
+            Action::DriverConsole(name, level, text) => {
+                self.driver_mut(&name).print(ConsoleLine {
+                    time: now_iso(),
+                    level,
+                    text,
+                });
+                true
+            }
+
+            Action::DriverError(source, message) => {
+                let name = match driver_of_source(&source) {
+                    Some(name) => name,
+                    None => return false,
+                };
+                let driver = self.driver_mut(&name);
+                // a script that doesn't parse never gets to report that it ran
+                if driver.status == LoadStatus::Loading {
+                    driver.status = LoadStatus::Failed(message.clone());
+                }
+                self.update(Action::DriverConsole(name, ConsoleLevel::Error, message))
+            }
+
+            Action::DriverLoaded(name, error) => {
+                if error.is_empty() {
+                    self.driver_mut(&name).status = LoadStatus::Loaded;
+                    return true;
+                }
+                self.driver_mut(&name).status = LoadStatus::Failed(error.clone());
+                self.update(Action::DriverConsole(name, ConsoleLevel::Error, error))
             }
 
-            Action::UploadDriverMiscFile(file_data) => {
-                // Here, we use some electron APIs to call out to the main process in JS.
-                // For this, we use the `electron_sys` library which is pretty experimental but
-                // feature complete.
-                // See here for documentation how to communicate between the main and renderer proess in Electron:
-                //   https://www.tutorialspoint.com/electron/electron_inter_process_communication.htm
-                // And here, for the documentation for the electon_sys Rust bindings for electron.ipcRenderer:
-                //   https://docs.rs/electron-sys/0.4.0/electron_sys/struct.IpcRenderer.html
-
-                let args: [JsValue; 2] = [
-                    JsValue::from_str(file_data.name.deref()),
-                    JsValue::from_str(std::str::from_utf8(&file_data.content).unwrap()),
-                ];
-                ipc_renderer.send_sync("upload-driver-misc-file", Box::new(args));
+            Action::RunPluginCommand(index, id) => {
+                let session = &self.sessions[self.current_session_index];
+                let follow_ups = match self.plugins.get_mut(index) {
+                    Some(plugin) => plugin.run_command(id, session, self.active_cell.as_ref()),
+                    None => return false,
+                };
+                self.link.send_message_batch(follow_ups);
                 false
             }
 
-            Action::LoadDriverMainFile(main_file_data) => {
-                info! {"Loading Driver: {}", &main_file_data.name};
-                let file_contents = std::str::from_utf8(&main_file_data.content).unwrap();
-                // dump file contents into script tag and attach to the DOM
-                let script = document().create_element("script").unwrap();
-                script.set_text_content(file_contents);
-                let _ = script.set_attribute("type", "text/javascript");
-                let _ = script.set_attribute("class", "ise-driver");
-                let _ = script.set_attribute("defer", "true");
-                let head = document().query_selector("head").unwrap().unwrap();
-                head.append_child(&script);
+            Action::ClearDriverConsole(name) => {
+                self.driver_mut(&name).console.clear();
                 true
             }
 
@@ -1090,9 +6783,11 @@ impl Component for Model {
 
                 
                 let mut change_active = false;
-                // height and width initial value
-                let mut tmp_heigth = 30.0;
-                let mut tmp_width = 90.0;
+                // height and width initial value, from the grids the new one is nested in or
+                // preferences
+                let defaults = self.grid_defaults(&coord);
+                let mut tmp_heigth = defaults.row_height;
+                let mut tmp_width = defaults.col_width;
 
                 let current_cell = self.active_cell.clone();
                 set_data_cell(&current_cell.clone().unwrap(), "".to_string());
@@ -1129,21 +6824,6 @@ impl Component for Model {
                         self.get_session_mut()
                             .grammars
                             .insert(new_coord.clone(), Grammar::default());
-                        if current_grammar.style.col_span.0 == 0
-                            && current_grammar.style.row_span.0 == 0
-                        {
-                            // initialize row & col heights as well
-                            if !&self.row_heights.contains_key(&new_coord.clone().full_row()) {
-                                &self
-                                    .row_heights
-                                    .insert(new_coord.clone().full_row(), tmp_heigth);
-                            }
-                            if !&self.col_widths.contains_key(&new_coord.clone().full_col()) {
-                                &self
-                                    .col_widths
-                                    .insert(new_coord.clone().full_col(), tmp_width);
-                            }
-                        }
                     }
 <<<<<<< HEAD
 =======
@@ -1158,11 +6838,16 @@ impl Component for Model {
                 self.get_session_mut()
                     .grammars
                     .insert(coord.clone(), grammar.clone());
+                if current_grammar.style.col_span.0 == 0 && current_grammar.style.row_span.0 == 0 {
+                    // initialize row & col heights as well
+                    resize_cells(self, &coord, (tmp_heigth, tmp_width));
+                }
                 resize(
                     self,
                     coord.clone(),
                     (rows as f64) * (/* default row height */tmp_heigth),
                     (cols as f64) * (/* default col width */tmp_width),
+                    (defaults.row_height, defaults.col_width),
                 );
 <<<<<<< HEAD
 
@@ -1186,6 +6871,277 @@ impl Component for Model {
                 true
             }
 
+            Action::ZoomToFit => {
+                let region = match Region::of_grid(&self.get_session().grammars, &self.view_root) {
+                    Some(region) => region,
+                    None => return false,
+                };
+                self.zoom_to(&region);
+                set_main_scroll((0.0, 0.0));
+                true
+            }
+
+            Action::ZoomToSelection => {
+                let region = match self.selection.primary() {
+                    Some(region) => region.clone(),
+                    None => return self.update(Action::Notify("Select a range to zoom to".to_string(), true)),
+                };
+                self.zoom_to(&region);
+                // the selection is scrolled to once it's been shown at the new zoom level
+                let first = format! {"cell-{}", region.cell(region.rows.0, region.cols.0).to_string()};
+                js! {
+                    setTimeout(function() {
+                        let cell = document.getElementById(@{first});
+                        if (cell) {
+                            cell.scrollIntoView({ block: "start", inline: "start" });
+                        }
+                    }, 0);
+                };
+                true
+            }
+
+            Action::ToggleSplitView => {
+                self.split_view = match self.split_view {
+                    Some(_) => None,
+                    None => Some(SplitView {
+                        other: Pane {
+                            view_root: self.view_root.clone(),
+                            active_cell: self.active_cell.clone(),
+                        },
+                        focused: 0,
+                    }),
+                };
+                true
+            }
+
+            Action::FocusPane(index) => {
+                if let Some(split_view) = &mut self.split_view {
+                    if split_view.focused == index {
+                        return false;
+                    }
+                    std::mem::swap(&mut self.view_root, &mut split_view.other.view_root);
+                    std::mem::swap(&mut self.active_cell, &mut split_view.other.active_cell);
+                    split_view.focused = index;
+                    self.focus_cell = self.active_cell.clone();
+                    return true;
+                }
+                false
+            }
+
+            Action::SetViewRoot(index, coord) => {
+                match self.get_session().grammars.get(&coord) {
+                    Some(Grammar {
+                        kind: Kind::Grid(_),
+                        ..
+                    }) => (),
+                    _ => {
+                        info! {"[Action::SetViewRoot] {} is not a grid", coord.to_string()};
+                        return false;
+                    }
+                }
+                match &mut self.split_view {
+                    Some(split_view) if split_view.focused != index => {
+                        split_view.other.view_root = coord;
+                    }
+                    _ => {
+                        self.view_root = coord;
+                    }
+                }
+                true
+            }
+
+            Action::AddSheet => {
+                // take the first free top-level slot after root (1, 1) and meta (1, 2)
+                let next_col = self
+                    .get_session()
+                    .sheets
+                    .iter()
+                    .map(|sheet| sheet.coord.col().get())
+                    .max()
+                    .unwrap_or(2)
+                    + 1;
+                let sheet_coord = Coordinate {
+                    row_cols: vec![non_zero_u32_tuple((1, next_col))],
+                };
+                let (rows, cols) = (
+                    self.preferences.default_rows.max(1),
+                    self.preferences.default_cols.max(1),
+                );
+                let mut sheet_grammar =
+                    Grammar::as_grid(NonZeroU32::new(rows).unwrap(), NonZeroU32::new(cols).unwrap());
+                sheet_grammar.name = sheet_coord.to_string();
+                if let Kind::Grid(sub_coords) = sheet_grammar.kind.clone() {
+                    for sub_coord in sub_coords {
+                        self.get_session_mut()
+                            .grammars
+                            .insert(Coordinate::child_of(&sheet_coord, sub_coord), Grammar::default());
+                    }
+                }
+                for row in 1..=rows {
+                    self.row_heights.insert(
+                        Row(sheet_coord.clone(), NonZeroU32::new(row).unwrap()),
+                        self.preferences.default_row_height,
+                    );
+                }
+                for col in 1..=cols {
+                    self.col_widths.insert(
+                        Col(sheet_coord.clone(), NonZeroU32::new(col).unwrap()),
+                        self.preferences.default_col_width,
+                    );
+                }
+                self.get_session_mut()
+                    .grammars
+                    .insert(sheet_coord.clone(), sheet_grammar);
+                let name = format! {"Sheet {}", next_col - 2};
+                self.get_session_mut().sheets.push(Sheet {
+                    name,
+                    coord: sheet_coord.clone(),
+                });
+                self.update(Action::SwitchSheet(sheet_coord))
+            }
+
+            Action::SwitchSheet(coord) => {
+                self.view_root = coord.clone();
+                self.active_cell = Some(Coordinate::child_of(&coord, non_zero_u32_tuple((1, 1))));
+                self.selection.clear();
+                true
+            }
+
+            Action::RenameSheet(coord, name) => {
+                if let Some(sheet) = self
+                    .get_session_mut()
+                    .sheets
+                    .iter_mut()
+                    .find(|sheet| sheet.coord == coord)
+                {
+                    sheet.name = name;
+                }
+                true
+            }
+
+            Action::RemoveSheet(coord) => {
+                self.get_session_mut().sheets.retain(|sheet| sheet.coord != coord);
+                self.get_session_mut()
+                    .grammars
+                    .retain(|c, _| !c.row_cols.starts_with(&coord.row_cols));
+                self.col_widths
+                    .retain(|Col(parent, _), _| !parent.row_cols.starts_with(&coord.row_cols));
+                self.row_heights
+                    .retain(|Row(parent, _), _| !parent.row_cols.starts_with(&coord.row_cols));
+                if self.view_root.row_cols.starts_with(&coord.row_cols) {
+                    return self.update(Action::SwitchSheet(coord!("root")));
+                }
+                true
+            }
+
+            Action::OpenFocusedGrid(coord) => {
+                // open the closest grid containing the coordinate (or the coordinate itself)
+                let mut current = Some(coord);
+                while let Some(c) = current.clone() {
+                    if let Some(Grammar {
+                        kind: Kind::Grid(_),
+                        ..
+                    }) = self.get_session().grammars.get(&c)
+                    {
+                        break;
+                    }
+                    current = c.parent();
+                }
+                self.focused_grid = current;
+                true
+            }
+
+            Action::CloseFocusedGrid => {
+                self.focused_grid = None;
+                true
+            }
+
+            Action::OpenCalendar => {
+                let active = match self.active_cell.clone() {
+                    Some(active) => active,
+                    None => return self.update(Action::Alert("select a cell of the date column".to_string())),
+                };
+                let grid = match active.parent() {
+                    Some(grid) if !grid.row_cols.is_empty() => grid,
+                    _ => return false,
+                };
+                let date_col = active.row_col().1.get();
+                // rows are labelled with the first other column
+                let title_col = if date_col == 1 { 2 } else { 1 };
+                // open on the first dated row, or today
+                let anchor = entries(&self.get_session().grammars, &grid, date_col, title_col)
+                    .first()
+                    .map_or_else(today, |e| e.date);
+                self.calendar = Some(CalendarView {
+                    grid,
+                    date_col,
+                    title_col,
+                    mode: CalendarMode::Month,
+                    anchor,
+                });
+                true
+            }
+
+            Action::UpdateCalendar(calendar) => {
+                self.calendar = Some(calendar);
+                true
+            }
+
+            Action::JumpFromCalendar(row) => {
+                let grid = match self.calendar.take() {
+                    Some(calendar) => calendar.grid,
+                    None => return false,
+                };
+                let cell = Coordinate::child_of(&grid, non_zero_u32_tuple((row, 1)));
+                self.update(Action::SetActiveCell(cell))
+            }
+
+            Action::CloseCalendar => {
+                self.calendar = None;
+                true
+            }
+
+            Action::OpenTimeline => {
+                let active = match self.active_cell.clone() {
+                    Some(active) => active,
+                    None => return self.update(Action::Alert("select a cell of the start date column".to_string())),
+                };
+                let grid = match active.parent() {
+                    Some(grid) if !grid.row_cols.is_empty() => grid,
+                    _ => return false,
+                };
+                let start_col = active.row_col().1.get();
+                // rows are labelled with the first column that isn't a date
+                let title_col = (1..).find(|c| *c != start_col && *c != start_col + 1).unwrap();
+                self.timeline = Some(TimelineView {
+                    grid,
+                    start_col,
+                    end_col: start_col + 1,
+                    title_col,
+                    zoom: 3,
+                });
+                true
+            }
+
+            Action::UpdateTimeline(timeline) => {
+                self.timeline = Some(timeline);
+                true
+            }
+
+            Action::JumpFromTimeline(row) => {
+                let grid = match self.timeline.take() {
+                    Some(timeline) => timeline.grid,
+                    None => return false,
+                };
+                let cell = Coordinate::child_of(&grid, non_zero_u32_tuple((row, 1)));
+                self.update(Action::SetActiveCell(cell))
+            }
+
+            Action::CloseTimeline => {
+                self.timeline = None;
+                true
+            }
+
             Action::InsertCol => {
                 if let Some(coord) = self.active_cell.clone() {
                     // find the bottom-most coord
@@ -1522,84 +7478,13 @@ impl Component for Model {
             //     };
             //     true
             // }
-            Action::Recreate => {
-                self.get_session_mut().grammars = {
-                    info! {"~rec is being fired"}
-                    let mut map = HashMap::new();
-                    build_grammar_map(
-                        &mut map,
-                        coord!("root"),
-                        grid![
-                            [
-                                g!(Grammar::input("", "A1")),
-                                g!(Grammar::input("", "B1")),
-                                g!(Grammar::input("", "C1"))
-                            ],
-                            [
-                                g!(Grammar::input("", "A2")),
-                                g!(Grammar::input("", "B2")),
-                                g!(Grammar::input("", "C2"))
-                            ],
-                            [
-                                g!(Grammar::input("", "A3")),
-                                g!(Grammar::input("", "B3")),
-                                g!(Grammar::input("", "C3"))
-                            ]
-                        ],
-                    );
-                    build_grammar_map(
-                        &mut map,
-                        coord!("meta"),
-                        grid![
-                            [g!(Grammar::input("", "A1"))],
-                            [g!(Grammar::input("", "A2"))],
-                            [g!(Grammar::default_button())],
-                            [g!(Grammar::default_slider())],
-                            [g!(Grammar::default_toggle())]
-                        ],
-                    );
-                    build_grammar_map(
-                        &mut map,
-                        coord!("meta-A6"),
-                        grid![
-                            [
-                                g!(Grammar {
-                                    name: "defn_label".to_string(),
-                                    style: {
-                                        let mut s = Style::default();
-                                        s.font_weight = 600;
-                                        s
-                                    },
-                                    kind: Kind::Text("Define Grammar".to_string()),
-                                }),
-                                g!(Grammar {
-                                    name: "defn_name".to_string(),
-                                    style: Style::default(),
-                                    kind: Kind::Input(String::new()),
-                                })
-                            ],
-                            [grid![
-                                [
-                                    g!(Grammar::input("rule_name", "")),
-                                    g!(Grammar::input("rule_grammar", ""))
-                                ],
-                                [
-                                    g!(Grammar::input("rule_name", "")),
-                                    g!(Grammar::input("rule_grammar", ""))
-                                ]
-                            ]]
-                        ],
-                    );
-                    assert!(map.contains_key(&(coord!("root"))));
-                    map
-                };
-                true
-            }
+            Action::Recreate => self.guard(GuardedAction::Recreate),
 
             Action::Resize(msg) => {
                 match msg {
                     ResizeMsg::Start(coord) => {
                         self.resizing = Some(coord);
+                        self.resize_before = Some(self.layout_snapshot());
                     }
                     ResizeMsg::X(offset_x) => {
                         if let Some(coord) = self.resizing.clone() {
@@ -1616,6 +7501,13 @@ impl Component for Model {
                     ResizeMsg::End => {
                         self.resizing = None;
                         self.mouse_cursor = CursorType::Default;
+                        // the whole drag is undone at once, and a click that didn't resize
+                        // anything isn't undone at all
+                        if let Some(before) = self.resize_before.take() {
+                            if before.row_heights != self.row_heights || before.col_widths != self.col_widths {
+                                self.history.record(before);
+                            }
+                        }
                     }
                 }
                 true
@@ -1696,7 +7588,123 @@ impl Component for Model {
                 // give moved grammar name {defn_name} as specified in "Add Definition" button
                 if let Some(g) = self.get_session_mut().grammars.get_mut(&defn_coord) {
                     g.name = defn_name;
+                    g.style.category = Some(SuggestionCategory::Definitions);
+                }
+                true
+            }
+
+            Action::DefinitionFromSelection(name) => {
+                let (grid, rows, cols) = match self.selection.primary() {
+                    Some(region) => (region.parent.clone(), region.rows, region.cols),
+                    None => return self.update(Action::Alert("select the headers and cells of the definition".to_string())),
+                };
+                let taken: Vec<String> = self.meta_suggestions.iter().map(|(name, _)| name.clone()).collect();
+                let name = crate::defn::unique_name(if name.is_empty() { "definition" } else { &name }, &taken);
+                let before = self.snapshot();
+                let defn_coord = self.next_meta_row();
+                let result = definition_from_region(
+                    &mut self.get_session_mut().grammars,
+                    &grid,
+                    rows,
+                    cols,
+                    &name,
+                    &defn_coord,
+                );
+                if let Err(e) = result {
+                    self.restore(before);
+                    return self.update(Action::Alert(e));
+                }
+                // the selection collapses into its first cell, holding the instance
+                let instance = Coordinate::child_of(&grid, non_zero_u32_tuple((rows.0, cols.0)));
+                for row in rows.0..=rows.1 {
+                    for col in cols.0..=cols.1 {
+                        clear_cell(self, &Coordinate::child_of(&grid, non_zero_u32_tuple((row, col))));
+                    }
+                }
+                instantiate(&mut self.get_session_mut().grammars, &defn_coord, &instance);
+                self.history.record(before);
+                self.selection.clear();
+                self.active_cell = Some(instance);
+                true
+            }
+
+            Action::UpdateDefnArguments(form) => {
+                // editing the form clears the last error
+                self.defn_arguments = Some(DefnArguments { error: None, ..form });
+                true
+            }
+
+            Action::ApplyDefnArguments => {
+                let form = match self.defn_arguments.clone() {
+                    Some(form) => form,
+                    None => return false,
+                };
+                let grammars = &self.get_session().grammars;
+                let values = if form.range.trim().is_empty() {
+                    Ok(form.values.clone())
+                } else {
+                    range_values(grammars, &form.range)
+                };
+                let arguments = match values.and_then(|values| bind_arguments(&form.parameters, &values)) {
+                    Ok(arguments) => arguments,
+                    Err(e) => {
+                        self.defn_arguments = Some(DefnArguments { error: Some(e), ..form });
+                        return true;
+                    }
+                };
+                let before = self.snapshot();
+                self.defn_arguments = None;
+                let declared = self.get_session().grammars.get(&form.dest).and_then(|g| g.style.shape.clone());
+                let grammars = &mut self.get_session_mut().grammars;
+                if !instantiate(grammars, &form.defn, &form.dest) {
+                    return false;
+                }
+                substitute(grammars, &form.dest, &arguments);
+                if !self.check_shape(declared, &form.dest, before.clone()) {
+                    return true;
+                }
+                self.history.record(before);
+                self.placeholders = placeholders_of(&self.get_session().grammars, &form.dest);
+                self.update(Action::SetActiveCell(form.dest))
+            }
+
+            Action::CloseDefnArguments => {
+                self.defn_arguments = None;
+                true
+            }
+
+            Action::OpenPropagation(defn) => {
+                self.propagation = Some(Propagation::new(&self.get_session().grammars, defn));
+                true
+            }
+
+            Action::TogglePropagation(instance) => {
+                if let Some(propagation) = self.propagation.as_mut() {
+                    propagation.toggle(&instance);
+                    return true;
+                }
+                false
+            }
+
+            Action::ApplyPropagation => {
+                let propagation = match self.propagation.take() {
+                    Some(propagation) => propagation,
+                    None => return false,
+                };
+                let included = propagation.included();
+                if included.is_empty() {
+                    return true;
                 }
+                let before = self.snapshot();
+                for instance in included.iter() {
+                    propagate(&mut self.get_session_mut().grammars, &propagation.defn, instance);
+                }
+                self.history.record(before);
+                self.update(Action::Notify(format! {"Updated {} instances", included.len()}, false))
+            }
+
+            Action::ClosePropagation => {
+                self.propagation = None;
                 true
             }
 
@@ -1718,6 +7726,7 @@ impl Component for Model {
 
             Action::HideContextMenu => {
                 self.context_menu_position = None;
+                self.tab_menu = None;
                 true
             }
 
@@ -1808,11 +7817,24 @@ impl Component for Model {
                 }
             })
             .collect();
+        self.column_index = build_column_index(&self.get_session().grammars);
+        self.sync_reference_overlay();
+        self.sync_transclusions();
+        self.sync_external_sources();
+        if mutated || self.calc_session != Some(self.current_session_index) {
+            self.release_transclusions();
+            self.unwatch_external_sources();
+            self.track_changes();
+        }
+        self.dispatch_subscriptions();
+        if mutated {
+            self.dispatch_cell_changes();
+        }
 
         should_render
     }
 
-    fn view(&self) -> Html {
+    fn render(&self) -> Html {
         let is_resizing = self.resizing.is_some();
         // for integration tests
         let serialized_model = serde_json::to_string(&self.get_session()).unwrap();
@@ -1822,10 +7844,7 @@ impl Component for Model {
             CursorType::EW => "ew-resize",
             CursorType::Default => "default",
         }};
-        let (default_row, default_col) = {
-            let (r, c) = self.default_nested_row_cols.clone();
-            (r.get(), c.get())
-        };
+        let (default_row, default_col) = self.nested_row_cols();
         let active_cell = self.active_cell.clone().expect("active_cell should be set");
         html! {
             <div
@@ -1834,11 +7853,40 @@ impl Component for Model {
             })>
                 { view_file_popup(&self) }
 
+                { view_unsaved_prompt(&self) }
+                { view_recovery_prompt(&self) }
+
+                { view_goal_seek(&self) }
+                { view_session_info(&self) }
+                { view_meta_import(&self) }
+                { view_session_merge(&self) }
+                { view_defn_arguments(&self) }
+                { view_propagation(&self) }
+                { view_transclude_dialog(&self) }
+                { view_import_url_dialog(&self) }
+                { view_rename_dialog(&self) }
+                { view_shape_dialog(&self) }
+                { view_nested_defaults_dialog(&self) }
+                { view_condition_dialog(&self) }
+                { view_bindings_dialog(&self) }
+                { view_script_dialog(&self) }
+                { view_button_action_dialog(&self) }
+                { view_shell_confirm(&self) }
+                { view_script_confirm(&self) }
+                { view_driver_prompt(&self) }
+                { view_notifications(&self) }
+                { view_repro_bar(&self) }
+
+                { view_focused_grid(&self) }
+                { view_calendar(&self) }
+                { view_timeline(&self) }
+
                 { view_side_nav(&self) }
 
                 { view_menu_bar(&self) }
 
                 { view_tab_bar(&self) }
+                { view_sheet_bar(&self) }
                 <div class="main">
 
                     <div id="grammars" class="grid-wrapper" style={zoom}
@@ -1862,6 +7910,18 @@ impl Component for Model {
                         onkeydown=self.link.callback(move |e: KeyDownEvent| {
                             if e.key() == "Shift" {
                                 Action::TogridleShiftKey(true)
+                            } else if e.key() == "F9" {
+                                e.prevent_default();
+                                Action::Recalculate
+                            } else if e.ctrl_key() && e.code() == "Digit9" {
+                                // Ctrl+9 fits the grid to the window, Ctrl+Shift+9 the selection
+                                e.prevent_default();
+                                if e.shift_key() { Action::ZoomToSelection } else { Action::ZoomToFit }
+                            } else if e.ctrl_key() && (e.code() == "KeyZ" || e.code() == "KeyY") {
+                                // Ctrl+Z undoes, Ctrl+Y (or Ctrl+Shift+Z) redoes, instead of the
+                                // text of the cell being edited
+                                e.prevent_default();
+                                if e.code() == "KeyY" || e.shift_key() { Action::Redo } else { Action::Undo }
                             } else {
                                 Action::Noop
 
@@ -1896,10 +7956,17 @@ impl Component for Model {
                         /*onclick=self.link.callback(move |e: ClickEvent| {
                             Action::HideContextMenu
                         })*/>
-                        { view_grammar(&self, coord!{"root"}) }
+                        {
+                            if self.split_view.is_some() {
+                                view_split_panes(&self)
+                            } else {
+                                view_grammar(&self, self.view_root.clone())
+                            }
+                        }
                         { view_context_menu(&self) }
                     </div>
                 </div>
+                { view_status_bar(&self) }
                 <input id="integration-test-model-dump" style="width: 0;height: 0;">{serialized_model}</input>
 
             </div>
@@ -1907,6 +7974,20 @@ impl Component for Model {
     }
 }
 
+// the cells whose entry in `now` isn't the one they had `before` (or that only have one in either)
+fn changed_entries<T: PartialEq>(
+    before: &HashMap<Coordinate, T>,
+    now: &HashMap<Coordinate, T>,
+) -> HashSet<Coordinate> {
+    let mut changed: HashSet<Coordinate> = now
+        .iter()
+        .filter(|(c, entry)| before.get(c) != Some(entry))
+        .map(|(c, _)| c.clone())
+        .collect();
+    changed.extend(before.keys().filter(|c| !now.contains_key(c)).cloned());
+    changed
+}
+
 fn key_combination<K>(e: &K) -> String
 where
     K: IKeyboardEvent,
@@ -1920,6 +8001,243 @@ where
     }
 }
 
+// hands the custom dictionary over to electron, which adds its words to the spellchecker
+fn sync_spellcheck_dictionary(preferences: &Preferences) {
+    let words = preferences.custom_dictionary.clone();
+    if let Err(e) = ipc::send_sync(&ipc::Request::SetSpellcheckDictionary { words }) {
+        info! {"couldn't set the spellcheck dictionary: {}", e};
+    }
+}
+
+// starts, restarts or stops the automation server in electron to match the preferences
+fn sync_automation(preferences: &Preferences) {
+    let settings = &preferences.automation;
+    let request = ipc::Request::ConfigureAutomation {
+        enabled: settings.enabled && !settings.token.is_empty(),
+        port: settings.port,
+        token: settings.token.clone(),
+    };
+    if let Err(e) = ipc::send_sync(&request) {
+        info! {"couldn't configure automation: {}", e};
+    }
+}
+
+// Asks for a file to open or save to in a native dialog
+fn choose_file(save: bool, filter_name: &str, extensions: &[&str]) -> Option<String> {
+    let request = ipc::Request::ChooseFile {
+        save,
+        filter_name: filter_name.to_string(),
+        extensions: extensions.iter().map(|e| e.to_string()).collect(),
+    };
+    match ipc::send_sync(&request) {
+        Ok(serde_json::Value::String(path)) => Some(path),
+        Ok(_) => None,
+        Err(e) => {
+            info! {"couldn't open a file dialog: {}", e};
+            None
+        }
+    }
+}
+
+// Writes the assets of a session to its sidecar folder, removing the files of assets that were
+// dropped. Returns the names of the assets written, none outside of Electron, where there's no
+// file system to write to. Assets without data (whose file couldn't be loaded) aren't written
+// over their files.
+fn save_sidecar_assets(dir: &str, assets: &[Asset]) -> Vec<String> {
+    let names: Vec<String> = assets.iter().map(|a| a.name.clone()).collect();
+    let data: Vec<String> = assets.iter().map(|a| a.data.clone()).collect();
+    let written = js! {
+        if (typeof require !== "function") {
+            return [];
+        }
+        const fs = require("fs");
+        const path = require("path");
+        const dir = @{dir};
+        const names = @{names};
+        const data = @{data};
+        try {
+            if (!fs.existsSync(dir)) {
+                if (names.length === 0) {
+                    return [];
+                }
+                fs.mkdirSync(dir);
+            }
+            fs.readdirSync(dir)
+                .filter(file => !names.includes(file))
+                .forEach(file => fs.unlinkSync(path.join(dir, file)));
+        } catch (e) {
+            console.log("couldn't save the assets to " + dir, e);
+            return [];
+        }
+        return names.filter((name, i) => {
+            if (data[i] === "") {
+                return false;
+            }
+            try {
+                fs.writeFileSync(path.join(dir, name), Buffer.from(data[i], "base64"));
+                return true;
+            } catch (e) {
+                console.log("couldn't save the asset " + name + " to " + dir, e);
+                return false;
+            }
+        });
+    };
+    written.try_into().unwrap_or_default()
+}
+
+// Reads the content of assets that were saved to a sidecar folder
+fn load_sidecar_assets(dir: &str, assets: &mut Vec<Asset>) {
+    for asset in assets.iter_mut().filter(|a| a.data.is_empty()) {
+        let path = format! {"{}/{}", dir, asset.name};
+        let data = js! {
+            try {
+                return require("fs").readFileSync(@{path.clone()}).toString("base64");
+            } catch (e) {
+                return null;
+            }
+        };
+        match data.into_string() {
+            Some(data) => asset.data = data,
+            None => info! {"missing asset {}", path},
+        }
+    }
+}
+
+// settles the promise a driver's call through the bridge returned
+fn settle_driver_call(bridge: &stdweb::Value, id: u32, ok: bool, value: String) {
+    js! {
+        @{bridge}.settle(@{id}, @{ok}, @{value});
+    };
+}
+
+// runs the hook `name` a driver registered (see the bridge's `runHook`) on `session`
+fn run_driver_hook(
+    bridge: &stdweb::Value,
+    driver: &str,
+    name: &str,
+    session: &mut Session,
+) -> Result<(), String> {
+    let content = serde_json::to_string(session).map_err(|e| e.to_string())?;
+    let reply = js! {
+        return @{bridge}.runHook(@{driver}, @{name}, @{content});
+    }
+    .into_string()
+    .unwrap_or_default();
+    let reply: serde_json::Value = serde_json::from_str(&reply).map_err(|e| e.to_string())?;
+    let value = reply["value"].as_str().unwrap_or_default();
+    if reply["ok"] != true {
+        return Err(value.to_string());
+    }
+    let view = session.view.clone();
+    *session = parse_session(value)?;
+    session.view = view;
+    Ok(())
+}
+
+// the script of the macro the button cell at `coord` runs, if it runs one
+fn script_of(session: &Session, coord: &Coordinate) -> Option<String> {
+    let action = session.grammars.get(coord)?.style.on_click.as_ref()?;
+    match &session.macros.iter().find(|m| m.name == action.macro_name)?.body {
+        MacroBody::Script(source) => Some(source.clone()),
+        MacroBody::Recorded(_) => None,
+    }
+}
+
+// runs a macro script (see `MacroBody::Script`) with the text of every cell readable through
+// `cell`, returning the (cell, text) writes it made with `set` in order
+fn run_script(
+    grammars: &HashMap<Coordinate, Grammar>,
+    source: &str,
+    args: &HashMap<String, String>,
+) -> Result<Vec<(String, String)>, String> {
+    let texts: HashMap<String, String> = grammars.keys().map(|c| (c.to_string(), cell_text(grammars, c))).collect();
+    let names: HashMap<String, String> = grammars
+        .values()
+        .filter(|g| !g.name.is_empty())
+        .filter_map(|g| resolve_cell(grammars, &g.name).map(|c| (g.name.clone(), c.to_string())))
+        .collect();
+    let context = serde_json::to_string(&(args, texts, names)).map_err(|e| e.to_string())?;
+    let reply = js! {
+        try {
+            var context = JSON.parse(@{context});
+            var args = context[0], texts = context[1], names = context[2];
+            var writes = [];
+            var key = function (reference) {
+                reference = String(reference).trim();
+                return names.hasOwnProperty(reference) ? names[reference] : reference;
+            };
+            var cell = function (reference) {
+                var k = key(reference);
+                return texts.hasOwnProperty(k) ? texts[k] : null;
+            };
+            var set = function (reference, value) {
+                writes.push([String(reference).trim(), String(value)]);
+                texts[key(reference)] = String(value);
+            };
+            new Function("args", "cell", "set", @{source})(args, cell, set);
+            return JSON.stringify({ ok: true, writes: writes });
+        } catch (e) {
+            return JSON.stringify({ ok: false, error: String(e) });
+        }
+    }
+    .into_string()
+    .unwrap_or_default();
+    let reply: serde_json::Value = serde_json::from_str(&reply).map_err(|e| e.to_string())?;
+    if reply["ok"] != true {
+        return Err(reply["error"].as_str().unwrap_or_default().to_string());
+    }
+    serde_json::from_value(reply["writes"].clone()).map_err(|e| e.to_string())
+}
+
+// A session with nothing in it yet, see `Model::bootstrap_session`
+fn blank_session(title: String) -> Session {
+    Session {
+        title,
+        root: Grammar::default(),
+        meta: Grammar::default(),
+        grammars: HashMap::new(),
+        outline_groups: vec![],
+        sheets: vec![],
+        metadata: SessionMetadata::default(),
+        bookmarks: vec![],
+        iteration: IterationSettings::default(),
+        external_sources: vec![],
+        assets: vec![],
+        packages: vec![],
+        macros: vec![],
+        mute_reminders: false,
+        variables: vec![],
+        layout: SessionLayout::default(),
+        view: TabView::default(),
+    }
+}
+
+// reads and parses the session file at `path`
+fn read_session_file(path: &str) -> Result<Session, String> {
+    let content = js! {
+        try {
+            return require("fs").readFileSync(@{path}, "utf8");
+        } catch (e) {
+            return null;
+        }
+    };
+    let content = content.into_string().ok_or_else(|| "couldn't read the file".to_string())?;
+    parse_session(&content)
+}
+
+// reads and parses the file of an external source
+fn read_source(path: &str) -> Result<Vec<Vec<String>>, String> {
+    let content = js! {
+        try {
+            return require("fs").readFileSync(@{path}, "utf8");
+        } catch (e) {
+            return null;
+        }
+    };
+    let content = content.into_string().ok_or_else(|| "couldn't read the file".to_string())?;
+    parse_table(path, &content)
+}
+
 fn focus_on_cell(c: &Coordinate) {
     let cell_id = format! {"cell-{}", c.to_string()};   
     js! {
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use crate::coordinate::Coordinate;
+use crate::export::cell_text;
+use crate::grammar::{Grammar, Kind};
+use crate::model::Action;
+use crate::session::Session;
+
+#[cfg(feature = "plugin-word-count")]
+pub mod word_count;
+
+// Command is an entry a plugin adds to the context menu
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Command {
+    pub id: &'static str,
+    pub label: &'static str,
+}
+
+// Plugin is an extension written in Rust and compiled into the bundle (see `registry`). Unlike
+// JS drivers, it gets typed access to actions and the session without going through Electron.
+// Every hook is optional, and the actions hooks return are dispatched after the current one.
+pub trait Plugin: Debug {
+    fn name(&self) -> &'static str;
+
+    // called with every action before it's reduced
+    fn on_action(&mut self, _action: &Action, _session: &Session) -> Vec<Action> {
+        vec![]
+    }
+
+    // called for every cell whose text was changed by an action, `None` if the cell was
+    // added or removed
+    fn on_cell_change(
+        &mut self,
+        _coord: &Coordinate,
+        _old: Option<&str>,
+        _new: Option<&str>,
+        _session: &Session,
+    ) -> Vec<Action> {
+        vec![]
+    }
+
+    fn contribute_commands(&self) -> Vec<Command> {
+        vec![]
+    }
+
+    // runs one of the commands the plugin contributed, on the active cell
+    fn run_command(&mut self, _id: &str, _session: &Session, _active: Option<&Coordinate>) -> Vec<Action> {
+        vec![]
+    }
+
+    // named grammars added to the meta sheet of new sessions, which are then completed like
+    // any other meta grammar
+    fn contribute_grammars(&self) -> Vec<Grammar> {
+        vec![]
+    }
+}
+
+// The plugins compiled into the bundle, each behind its own `plugin-*` feature
+pub fn registry() -> Vec<Box<dyn Plugin>> {
+    #[allow(unused_mut)]
+    let mut plugins: Vec<Box<dyn Plugin>> = vec![];
+    #[cfg(feature = "plugin-word-count")]
+    plugins.push(Box::new(word_count::WordCount::default()));
+    plugins
+}
+
+// The text of every cell but grids, which plugins are told about changes to
+pub fn cell_values(grammars: &HashMap<Coordinate, Grammar>) -> HashMap<Coordinate, String> {
+    grammars
+        .iter()
+        .filter(|(_, g)| match g.kind {
+            Kind::Grid(_) => false,
+            _ => true,
+        })
+        .map(|(coord, _)| (coord.clone(), cell_text(grammars, coord)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn test_cell_values() {
+        let grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => Grammar::input("", "apple"),
+            coord!("root-B1") => Grammar::text("", "pear"),
+        };
+        let values = cell_values(&grammars);
+        assert_eq!(values.len(), 2);
+        assert_eq!(values.get(&coord!("root-B1")).map(|s| s.as_str()), Some("pear"));
+    }
+}
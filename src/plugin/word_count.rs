@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+
+use crate::coordinate::Coordinate;
+use crate::grammar::Grammar;
+use crate::model::Action;
+use crate::plugin::{Command, Plugin};
+use crate::session::Session;
+
+pub fn count_words(text: &str) -> usize {
+    text.split_whitespace().count()
+}
+
+// WordCount keeps a running count of the words in each cell, and reports the total of the
+// session (or of the active cell and everything nested in it)
+#[derive(Debug, Default)]
+pub struct WordCount {
+    counts: HashMap<Coordinate, usize>,
+}
+
+impl WordCount {
+    fn total_under(&self, root: &Coordinate) -> usize {
+        let depth = root.row_cols.len();
+        self.counts
+            .iter()
+            .filter(|(c, _)| c.row_cols.len() >= depth && c.row_cols[..depth] == root.row_cols[..])
+            .map(|(_, count)| count)
+            .sum()
+    }
+}
+
+impl Plugin for WordCount {
+    fn name(&self) -> &'static str {
+        "word count"
+    }
+
+    fn on_cell_change(
+        &mut self,
+        coord: &Coordinate,
+        _old: Option<&str>,
+        new: Option<&str>,
+        _session: &Session,
+    ) -> Vec<Action> {
+        match new {
+            Some(text) => self.counts.insert(coord.clone(), count_words(text)),
+            None => self.counts.remove(coord),
+        };
+        vec![]
+    }
+
+    fn contribute_commands(&self) -> Vec<Command> {
+        vec![
+            Command {
+                id: "count-session",
+                label: "Count Words",
+            },
+            Command {
+                id: "count-cell",
+                label: "Count Words in Cell",
+            },
+        ]
+    }
+
+    fn run_command(&mut self, id: &str, _session: &Session, active: Option<&Coordinate>) -> Vec<Action> {
+        let message = match (id, active) {
+            ("count-cell", Some(active)) => {
+                format! {"{} words in {}", self.total_under(active), active.to_string()}
+            }
+            _ => format! {"{} words in the session", self.counts.values().sum::<usize>()},
+        };
+        vec![Action::Alert(message)]
+    }
+
+    fn contribute_grammars(&self) -> Vec<Grammar> {
+        vec![Grammar::text("note", "")]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_words() {
+        assert_eq!(count_words("  the quick\nbrown fox "), 4);
+        assert_eq!(count_words(""), 0);
+    }
+}
@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use yew::format::Json;
+use yew::services::storage::StorageService;
+
+use crate::automation::AutomationSettings;
+use crate::calc::CalcMode;
+use crate::driver::DriverGrant;
+use crate::entry::EnterBehavior;
+use crate::export::json::JsonExport;
+use crate::hooks::{default_hooks, Hook};
+use crate::locale::Locale;
+use crate::style::GridDefaults;
+use crate::suggestion::SuggestionCategory;
+
+// key under which preferences are kept in localStorage
+const PREFERENCES_KEY: &str = "ise.preferences";
+
+// Grammars that can be stamped onto the meta sheet when a session is created
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum MetaGrammar {
+    Button,
+    Slider,
+    Toggle,
+    ColorPicker,
+    Gauge,
+    Rating,
+    Stepper,
+    Timer,
+    Reminder,
+    Shell,
+    Definition,
+}
+
+// Preferences are per-user settings which, unlike the Session, don't get saved into
+// .ise files. They're persisted in the browser's localStorage instead.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Preferences {
+    // - layout of the root grid for new (or reset) sessions
+    pub default_rows: u32,
+    pub default_cols: u32,
+    pub default_row_height: f64,
+    pub default_col_width: f64,
+    // - size of nested grids & their cells, where the grids they're in don't say otherwise
+    //   (see `Style::nested_defaults`)
+    pub nested_grid: GridDefaults,
+    // - grammars placed on the meta sheet of new (or reset) sessions
+    pub default_meta_grammars: Vec<MetaGrammar>,
+    // - completion dropdown: how many times each suggestion (by name) has been completed,
+    //   and the sections the user has collapsed
+    pub suggestion_usage: HashMap<String, u32>,
+    pub collapsed_suggestion_categories: Vec<SuggestionCategory>,
+    // - nested grids collapsed to a summary chip, as coordinates, by session (its file, or its
+    //   title while it's unsaved, see `Model::session_key`)
+    pub collapsed_grids: HashMap<String, Vec<String>>,
+    // - spellchecking of text cells, and the domain terms that shouldn't be flagged
+    pub spellcheck: bool,
+    pub custom_dictionary: Vec<String>,
+    // - recent entries of the go-to box and search panel across all tabs, newest first
+    pub goto_history: Vec<String>,
+    pub search_history: Vec<String>,
+    // - how often tabs with unsaved changes are autosaved, in seconds (0 turns it off, see
+    //   `Autosave`)
+    pub autosave_interval_s: u32,
+    // - how much Ctrl+Up/Down adds to or subtracts from a numeric cell
+    pub numeric_step: f64,
+    // - whether formulas are recalculated automatically or on demand
+    pub calc_mode: CalcMode,
+    // - what Enter does in a cell (see `entry::next_cell`)
+    pub enter_behavior: EnterBehavior,
+    // - how numbers, dates & money are shown, whatever form they're stored in (see `locale`)
+    pub locale: Locale,
+    // - whether completing a grammar that doesn't fit a cell's expected shape is refused,
+    //   rather than only warned about (see `shape::mismatch`)
+    pub block_shape_mismatches: bool,
+    // - whether Export CSV spreads nested grids over columns of their own (see `delimited`)
+    pub flatten_csv_export: bool,
+    // - how tables are exported as JSON records (see `json::to_json`)
+    pub json_export: JsonExport,
+    // - whether inserting, deleting & merging cells and zooming animate (see `animation`)
+    pub animations: bool,
+    // - folders or URLs grammar packs are installed from (see `RegistryIndex`)
+    pub package_registries: Vec<String>,
+    // - what the user answered when drivers asked for a capability (see `Capability`)
+    pub driver_grants: Vec<DriverGrant>,
+    // - the local JSON-RPC server external tools drive the app through
+    pub automation: AutomationSettings,
+    // - the builtin hooks that transform sessions as they're saved & loaded, and whether
+    //   they're on (drivers register theirs while they're loaded, see `Model::driver_hooks`)
+    pub hooks: Vec<Hook>,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Preferences {
+            default_rows: 3,
+            default_cols: 3,
+            default_row_height: 30.0,
+            default_col_width: 90.0,
+            nested_grid: GridDefaults::default(),
+            default_meta_grammars: vec![
+                MetaGrammar::Button,
+                MetaGrammar::Slider,
+                MetaGrammar::Toggle,
+                MetaGrammar::Definition,
+            ],
+            suggestion_usage: HashMap::new(),
+            collapsed_suggestion_categories: vec![],
+            collapsed_grids: HashMap::new(),
+            spellcheck: true,
+            custom_dictionary: vec![],
+            goto_history: vec![],
+            search_history: vec![],
+            autosave_interval_s: 60,
+            numeric_step: 1.0,
+            calc_mode: CalcMode::Automatic,
+            enter_behavior: EnterBehavior::Down,
+            locale: Locale::Canonical,
+            block_shape_mismatches: false,
+            flatten_csv_export: false,
+            json_export: JsonExport::default(),
+            animations: true,
+            package_registries: vec![],
+            driver_grants: vec![],
+            automation: AutomationSettings::default(),
+            hooks: default_hooks(),
+        }
+    }
+}
+
+impl Preferences {
+    // falls back to the default preferences if none were stored or they can't be read
+    pub fn load(storage: &StorageService) -> Preferences {
+        let Json(preferences) = storage.restore(PREFERENCES_KEY);
+        preferences.unwrap_or_default()
+    }
+
+    pub fn store(&self, storage: &mut StorageService) {
+        storage.store(PREFERENCES_KEY, Json(self));
+    }
+}
@@ -0,0 +1,277 @@
+use serde::{Deserialize, Serialize};
+
+use crate::coordinate::Coordinate;
+use crate::grammar::{KindTag, Lookup};
+use crate::model::{Action, SelectMsg};
+use crate::session::Session;
+
+// how long each step is shown for while a replay plays, in ms
+pub const PLAY_INTERVAL_MS: u64 = 500;
+
+// ReplayStep is a recorded action in a form that's saved to a repro file and applied again when
+// it's replayed. Only actions that change the session, or the cells later actions apply to,
+// are recorded.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub enum ReplayStep {
+    SetActiveCell { coord: Coordinate },
+    SelectStart { coord: Coordinate },
+    SelectAdd { coord: Coordinate },
+    SelectEnd { coord: Coordinate },
+    ChangeInput { coord: Coordinate, value: String },
+    DoCompletion { source: Coordinate, destination: Coordinate },
+    ConvertKind { kind: KindTag },
+    TogglePlaceholder { coord: Coordinate },
+    AddNestedGrid { coord: Coordinate, rows: u32, cols: u32 },
+    InsertRow,
+    InsertCol,
+    DeleteRow,
+    DeleteCol,
+    RangeDelete,
+    MergeCells,
+    MoveRow { grid: Coordinate, from: u32, to: u32 },
+    DuplicateRow,
+    DuplicateCol,
+    Lookup { coord: Coordinate, lookup: Lookup },
+    ToggleLookup { coord: Coordinate },
+    ToggleAutoExpand { grid: Coordinate },
+    AddDefinition { coord: Coordinate, name: String },
+    DefinitionFromSelection { name: String },
+    SetColor { coord: Coordinate, color: String },
+    SetToggle { coord: Coordinate, on: bool },
+    SetRating { coord: Coordinate, stars: u32 },
+    StepStepper { coord: Coordinate, steps: i32 },
+    AddSheet,
+    SwitchSheet { sheet: Coordinate },
+    RenameSheet { sheet: Coordinate, name: String },
+    RemoveSheet { sheet: Coordinate },
+    SetSessionTitle { title: String },
+    PruneOrphans,
+    RepairIntegrity,
+    Undo,
+    Redo,
+}
+
+// ReproLog is what a repro file holds: the session as it was when recording started, the cell
+// that was active then, and the steps recorded since. It's `incomplete` once an action that
+// changed the session couldn't be recorded, replaying it then won't reproduce the session.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReproLog {
+    pub initial: Session,
+    pub active_cell: Option<Coordinate>,
+    pub steps: Vec<ReplayStep>,
+    #[serde(default)]
+    pub incomplete: bool,
+}
+
+// Replay is a repro log being replayed into the tab at `session_index`, `position` steps in
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub log: ReproLog,
+    pub session_index: usize,
+    pub position: usize,
+    pub playing: bool,
+}
+
+impl Replay {
+    pub fn new(log: ReproLog, session_index: usize) -> Replay {
+        Replay {
+            log,
+            session_index,
+            position: 0,
+            playing: false,
+        }
+    }
+
+    // the next step to apply, moving past it
+    pub fn advance(&mut self) -> Option<ReplayStep> {
+        let step = self.log.steps.get(self.position).cloned()?;
+        self.position += 1;
+        Some(step)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.position >= self.log.steps.len()
+    }
+}
+
+// The step an action is recorded as, if it's recorded at all
+pub fn step_of(action: &Action) -> Option<ReplayStep> {
+    let step = match action {
+        Action::SetActiveCell(coord) => ReplayStep::SetActiveCell { coord: coord.clone() },
+        Action::Select(SelectMsg::Start(coord)) => ReplayStep::SelectStart { coord: coord.clone() },
+        Action::Select(SelectMsg::Add(coord)) => ReplayStep::SelectAdd { coord: coord.clone() },
+        Action::Select(SelectMsg::End(coord)) => ReplayStep::SelectEnd { coord: coord.clone() },
+        Action::ChangeInput(coord, value) => ReplayStep::ChangeInput {
+            coord: coord.clone(),
+            value: value.clone(),
+        },
+        Action::DoCompletion(source, destination) => ReplayStep::DoCompletion {
+            source: source.clone(),
+            destination: destination.clone(),
+        },
+        Action::ConvertKind(kind) => ReplayStep::ConvertKind { kind: *kind },
+        Action::TogglePlaceholder(coord) => ReplayStep::TogglePlaceholder { coord: coord.clone() },
+        Action::AddNestedGrid(coord, (rows, cols)) => ReplayStep::AddNestedGrid {
+            coord: coord.clone(),
+            rows: *rows,
+            cols: *cols,
+        },
+        Action::InsertRow => ReplayStep::InsertRow,
+        Action::InsertCol => ReplayStep::InsertCol,
+        Action::DeleteRow => ReplayStep::DeleteRow,
+        Action::DeleteCol => ReplayStep::DeleteCol,
+        Action::RangeDelete() => ReplayStep::RangeDelete,
+        Action::MergeCells() => ReplayStep::MergeCells,
+        Action::MoveRow(grid, from, to) => ReplayStep::MoveRow {
+            grid: grid.clone(),
+            from: *from,
+            to: *to,
+        },
+        Action::DuplicateRow => ReplayStep::DuplicateRow,
+        Action::DuplicateCol => ReplayStep::DuplicateCol,
+        Action::Lookup(coord, lookup) => ReplayStep::Lookup {
+            coord: coord.clone(),
+            lookup: lookup.clone(),
+        },
+        Action::ToggleLookup(coord) => ReplayStep::ToggleLookup { coord: coord.clone() },
+        Action::ToggleAutoExpand(grid) => ReplayStep::ToggleAutoExpand { grid: grid.clone() },
+        Action::AddDefinition(coord, name) => ReplayStep::AddDefinition {
+            coord: coord.clone(),
+            name: name.clone(),
+        },
+        Action::DefinitionFromSelection(name) => ReplayStep::DefinitionFromSelection { name: name.clone() },
+        Action::SetColor(coord, color) => ReplayStep::SetColor {
+            coord: coord.clone(),
+            color: color.clone(),
+        },
+        Action::SetToggle(coord, on) => ReplayStep::SetToggle {
+            coord: coord.clone(),
+            on: *on,
+        },
+        Action::SetRating(coord, stars) => ReplayStep::SetRating {
+            coord: coord.clone(),
+            stars: *stars,
+        },
+        Action::StepStepper(coord, steps) => ReplayStep::StepStepper {
+            coord: coord.clone(),
+            steps: *steps,
+        },
+        Action::AddSheet => ReplayStep::AddSheet,
+        Action::SwitchSheet(sheet) => ReplayStep::SwitchSheet { sheet: sheet.clone() },
+        Action::RenameSheet(sheet, name) => ReplayStep::RenameSheet {
+            sheet: sheet.clone(),
+            name: name.clone(),
+        },
+        Action::RemoveSheet(sheet) => ReplayStep::RemoveSheet { sheet: sheet.clone() },
+        Action::SetSessionTitle(title) => ReplayStep::SetSessionTitle { title: title.clone() },
+        Action::PruneOrphans => ReplayStep::PruneOrphans,
+        Action::RepairIntegrity => ReplayStep::RepairIntegrity,
+        Action::Undo => ReplayStep::Undo,
+        Action::Redo => ReplayStep::Redo,
+        _ => return None,
+    };
+    Some(step)
+}
+
+// The action a recorded step is replayed as
+pub fn action_of(step: &ReplayStep) -> Action {
+    match step.clone() {
+        ReplayStep::SetActiveCell { coord } => Action::SetActiveCell(coord),
+        ReplayStep::SelectStart { coord } => Action::Select(SelectMsg::Start(coord)),
+        ReplayStep::SelectAdd { coord } => Action::Select(SelectMsg::Add(coord)),
+        ReplayStep::SelectEnd { coord } => Action::Select(SelectMsg::End(coord)),
+        ReplayStep::ChangeInput { coord, value } => Action::ChangeInput(coord, value),
+        ReplayStep::DoCompletion { source, destination } => Action::DoCompletion(source, destination),
+        ReplayStep::ConvertKind { kind } => Action::ConvertKind(kind),
+        ReplayStep::TogglePlaceholder { coord } => Action::TogglePlaceholder(coord),
+        ReplayStep::AddNestedGrid { coord, rows, cols } => Action::AddNestedGrid(coord, (rows, cols)),
+        ReplayStep::InsertRow => Action::InsertRow,
+        ReplayStep::InsertCol => Action::InsertCol,
+        ReplayStep::DeleteRow => Action::DeleteRow,
+        ReplayStep::DeleteCol => Action::DeleteCol,
+        ReplayStep::RangeDelete => Action::RangeDelete(),
+        ReplayStep::MergeCells => Action::MergeCells(),
+        ReplayStep::MoveRow { grid, from, to } => Action::MoveRow(grid, from, to),
+        ReplayStep::DuplicateRow => Action::DuplicateRow,
+        ReplayStep::DuplicateCol => Action::DuplicateCol,
+        ReplayStep::Lookup { coord, lookup } => Action::Lookup(coord, lookup),
+        ReplayStep::ToggleLookup { coord } => Action::ToggleLookup(coord),
+        ReplayStep::ToggleAutoExpand { grid } => Action::ToggleAutoExpand(grid),
+        ReplayStep::AddDefinition { coord, name } => Action::AddDefinition(coord, name),
+        ReplayStep::DefinitionFromSelection { name } => Action::DefinitionFromSelection(name),
+        ReplayStep::SetColor { coord, color } => Action::SetColor(coord, color),
+        ReplayStep::SetToggle { coord, on } => Action::SetToggle(coord, on),
+        ReplayStep::SetRating { coord, stars } => Action::SetRating(coord, stars),
+        ReplayStep::StepStepper { coord, steps } => Action::StepStepper(coord, steps),
+        ReplayStep::AddSheet => Action::AddSheet,
+        ReplayStep::SwitchSheet { sheet } => Action::SwitchSheet(sheet),
+        ReplayStep::RenameSheet { sheet, name } => Action::RenameSheet(sheet, name),
+        ReplayStep::RemoveSheet { sheet } => Action::RemoveSheet(sheet),
+        ReplayStep::SetSessionTitle { title } => Action::SetSessionTitle(title),
+        ReplayStep::PruneOrphans => Action::PruneOrphans,
+        ReplayStep::RepairIntegrity => Action::RepairIntegrity,
+        ReplayStep::Undo => Action::Undo,
+        ReplayStep::Redo => Action::Redo,
+    }
+}
+
+// A short description of a step, listed in the replay panel
+pub fn step_label(step: &ReplayStep) -> String {
+    match step {
+        ReplayStep::SetActiveCell { coord } => format! {"select {}", coord.to_string()},
+        ReplayStep::SelectStart { coord } => format! {"start selection at {}", coord.to_string()},
+        ReplayStep::SelectAdd { coord } => format! {"add a selection at {}", coord.to_string()},
+        ReplayStep::SelectEnd { coord } => format! {"end selection at {}", coord.to_string()},
+        ReplayStep::ChangeInput { coord, value } => format! {"type {:?} into {}", value, coord.to_string()},
+        ReplayStep::DoCompletion { source, destination } => {
+            format! {"complete {} from {}", destination.to_string(), source.to_string()}
+        }
+        ReplayStep::ConvertKind { kind } => format! {"convert to {}", kind.label()},
+        ReplayStep::AddNestedGrid { coord, rows, cols } => {
+            format! {"nest a {}x{} grid in {}", rows, cols, coord.to_string()}
+        }
+        ReplayStep::MoveRow { grid, from, to } => {
+            format! {"move row {} of {} to {}", from, grid.to_string(), to}
+        }
+        other => {
+            // the tag the step is saved under is descriptive enough for the rest
+            let saved = serde_json::to_value(other).unwrap_or_default();
+            saved["action"].as_str().unwrap_or_default().replace('-', " ")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+
+    #[test]
+    fn test_step_round_trip() {
+        let steps = vec![
+            ReplayStep::SetActiveCell { coord: coord!("root-A1") },
+            ReplayStep::ChangeInput {
+                coord: coord!("root-A1"),
+                value: "=1 + 2".to_string(),
+            },
+            ReplayStep::AddNestedGrid {
+                coord: coord!("root-B1"),
+                rows: 2,
+                cols: 3,
+            },
+            ReplayStep::ConvertKind { kind: KindTag::Rating },
+            ReplayStep::InsertRow,
+        ];
+        for step in steps.iter() {
+            assert_eq!(step_of(&action_of(step)).as_ref(), Some(step));
+        }
+        let saved = serde_json::to_string(&steps).unwrap();
+        assert!(saved.contains(r#"{"action":"change-input","coord":"root-A1","value":"=1 + 2"}"#));
+        assert_eq!(serde_json::from_str::<Vec<ReplayStep>>(&saved).unwrap(), steps);
+        assert!(step_of(&Action::ZoomIn).is_none());
+
+        assert_eq!(step_label(&steps[1]), r#"type "=1 + 2" into root-A1"#);
+        assert_eq!(step_label(&steps[4]), "insert row");
+    }
+}
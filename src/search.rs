@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+
+use crate::coordinate::Coordinate;
+use crate::grammar::{Grammar, Kind, KindTag, Lookup};
+use crate::style::Style;
+
+// StructuralQuery finds cells by what they are rather than what they contain.
+// Queries are written as space-separated terms, all of which need to match:
+//
+//   kind:toggle           cells of a kind (see `KindTag`)
+//   name:foo              cells whose grammar name contains "foo"
+//   style:font_weight=600 cells with a style attribute set to a value
+//   under:root-B2         cells nested (at any depth) under a coordinate
+//   broken                lookups whose target doesn't exist anymore
+//
+// e.g. "kind:toggle under:root-B2" finds all the toggles under root-B2
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StructuralQuery {
+    pub kind: Option<KindTag>,
+    pub name: Option<String>,
+    pub style: Option<(String, String)>,
+    pub under: Option<Coordinate>,
+    pub broken: bool,
+}
+
+impl StructuralQuery {
+    // returns an error message for terms that can't be understood
+    pub fn parse(query: &str) -> Result<StructuralQuery, String> {
+        let mut q = StructuralQuery::default();
+        for term in query.split_whitespace() {
+            let (key, value) = match term.find(':') {
+                Some(i) => (&term[..i], &term[i + 1..]),
+                None => (term, ""),
+            };
+            match key {
+                "kind" => {
+                    q.kind = Some(
+                        KindTag::ALL
+                            .iter()
+                            .find(|t| t.label().eq_ignore_ascii_case(value))
+                            .cloned()
+                            .ok_or(format! {"unknown kind: {}", value})?,
+                    )
+                }
+                "name" => q.name = Some(value.to_string()),
+                "style" => {
+                    let mut parts = value.splitn(2, '=');
+                    match (parts.next(), parts.next()) {
+                        (Some(attr), Some(v)) if style_attr(&Style::default(), attr).is_some() => {
+                            q.style = Some((attr.to_string(), v.to_string()))
+                        }
+                        _ => return Err(format! {"invalid style term: {}", value}),
+                    }
+                }
+                "under" => {
+                    q.under = Some(
+                        Coordinate::parse(value).ok_or(format! {"invalid coordinate: {}", value})?,
+                    )
+                }
+                "broken" => q.broken = true,
+                _ => return Err(format! {"unknown search term: {}", term}),
+            }
+        }
+        Ok(q)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == StructuralQuery::default()
+    }
+
+    pub fn matches(
+        &self,
+        coord: &Coordinate,
+        grammar: &Grammar,
+        grammars: &HashMap<Coordinate, Grammar>,
+    ) -> bool {
+        if let Some(kind) = self.kind {
+            if grammar.kind_tag() != Some(kind) {
+                return false;
+            }
+        }
+        if let Some(name) = &self.name {
+            if !grammar.name.contains(name.as_str()) {
+                return false;
+            }
+        }
+        if let Some((attr, value)) = &self.style {
+            if style_attr(&grammar.style, attr).as_ref() != Some(value) {
+                return false;
+            }
+        }
+        if let Some(under) = &self.under {
+            let depth = under.row_cols.len();
+            if coord.row_cols.len() <= depth || coord.row_cols[..depth] != under.row_cols[..] {
+                return false;
+            }
+        }
+        if self.broken && !is_broken_lookup(grammar, grammars) {
+            return false;
+        }
+        true
+    }
+
+    // all matching cells, in coordinate order
+    pub fn run(&self, grammars: &HashMap<Coordinate, Grammar>) -> Vec<Coordinate> {
+        let mut results: Vec<Coordinate> = grammars
+            .iter()
+            .filter(|(c, g)| c.parent().is_some() && self.matches(c, g, grammars))
+            .map(|(c, _)| c.clone())
+            .collect();
+        results.sort_by(|a, b| a.row_cols.cmp(&b.row_cols));
+        results
+    }
+}
+
+// Whether `grammar` is a lookup whose target is gone
+pub fn is_broken_lookup(grammar: &Grammar, grammars: &HashMap<Coordinate, Grammar>) -> bool {
+    match &grammar.kind {
+        Kind::Lookup(_, Some(lookup)) => !lookup_target_exists(lookup, grammars),
+        _ => false,
+    }
+}
+
+pub fn lookup_target_exists(lookup: &Lookup, grammars: &HashMap<Coordinate, Grammar>) -> bool {
+    match lookup {
+        Lookup::Cell(c) => grammars.contains_key(c),
+        Lookup::Range { parent, .. } => grammars.contains_key(parent),
+        Lookup::Row(row) => grammars.contains_key(&row.0),
+        Lookup::Col(col) => grammars.contains_key(&col.0),
+    }
+}
+
+// Style attributes that can be searched for and bulk-set, by name
+pub fn style_attr(style: &Style, attr: &str) -> Option<String> {
+    match attr {
+        "font_weight" => Some(style.font_weight.to_string()),
+        "font_color" => Some(style.font_color.clone()),
+        "border_color" => Some(style.border_color.clone()),
+        "display" => Some(style.display.to_string()),
+        "auto_expand" => Some(style.auto_expand.to_string()),
+        _ => None,
+    }
+}
+
+// Returns false if the attribute doesn't exist or the value doesn't fit it
+pub fn set_style_attr(style: &mut Style, attr: &str, value: &str) -> bool {
+    match attr {
+        "font_weight" => value.parse().map(|v| style.font_weight = v).is_ok(),
+        "font_color" => {
+            style.font_color = value.to_string();
+            true
+        }
+        "border_color" => {
+            style.border_color = value.to_string();
+            true
+        }
+        "display" => value.parse().map(|v| style.display = v).is_ok(),
+        "auto_expand" => value.parse().map(|v| style.auto_expand = v).is_ok(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn test_parse() {
+        let q = StructuralQuery::parse("kind:toggle under:root-B2").unwrap();
+        assert_eq!(q.kind, Some(KindTag::Toggle));
+        assert_eq!(q.under, Some(coord!("root-B2")));
+        assert!(!q.broken);
+        let q = StructuralQuery::parse("style:font_weight=600 broken").unwrap();
+        assert_eq!(q.style, Some(("font_weight".to_string(), "600".to_string())));
+        assert!(q.broken);
+        assert!(StructuralQuery::parse("").unwrap().is_empty());
+        assert!(StructuralQuery::parse("kind:spreadsheet").is_err());
+        assert!(StructuralQuery::parse("style:width").is_err());
+        assert!(StructuralQuery::parse("foo").is_err());
+    }
+
+    #[test]
+    fn test_run() {
+        let grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => Grammar::default_toggle(),
+            coord!("root-B2") => Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-B2-A1") => Grammar::default_toggle(),
+            coord!("root-B2-B1") => Grammar {
+                name: "".to_string(),
+                style: Style::default(),
+                kind: Kind::Lookup("".to_string(), Some(Lookup::Cell(coord!("root-C9")))),
+            },
+        };
+        let toggles = StructuralQuery::parse("kind:toggle").unwrap().run(&grammars);
+        assert_eq!(toggles, vec![coord!("root-A1"), coord!("root-B2-A1")]);
+        let nested = StructuralQuery::parse("kind:toggle under:root-B2").unwrap().run(&grammars);
+        assert_eq!(nested, vec![coord!("root-B2-A1")]);
+        let broken = StructuralQuery::parse("broken").unwrap().run(&grammars);
+        assert_eq!(broken, vec![coord!("root-B2-B1")]);
+    }
+
+    #[test]
+    fn test_set_style_attr() {
+        let mut style = Style::default();
+        assert!(set_style_attr(&mut style, "font_weight", "600"));
+        assert_eq!(style_attr(&style, "font_weight"), Some("600".to_string()));
+        assert!(!set_style_attr(&mut style, "font_weight", "bold"));
+        assert!(!set_style_attr(&mut style, "width", "10"));
+    }
+}
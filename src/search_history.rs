@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+
+// max number of entries kept in each history
+pub const HISTORY_LIMIT: usize = 20;
+
+// Inputs that keep a history of what's been entered into them
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum HistoryKind {
+    // the go-to box in the menu bar
+    GoTo,
+    // the structural search panel
+    Search,
+}
+
+// Adds `entry` as the most recent entry of `history` (newest first), moving it to the front
+// if it's already in there
+pub fn push_history(history: &mut Vec<String>, entry: &str) {
+    let entry = entry.trim();
+    if entry.is_empty() {
+        return;
+    }
+    history.retain(|e| e != entry);
+    history.insert(0, entry.to_string());
+    history.truncate(HISTORY_LIMIT);
+}
+
+// The entries browsed with Up/Down: the tab's own history first, then the global history
+// (entries from other tabs)
+pub fn merged_history(tab: &[String], global: &[String]) -> Vec<String> {
+    let mut merged = tab.to_vec();
+    for entry in global.iter() {
+        if !merged.contains(entry) {
+            merged.push(entry.clone());
+        }
+    }
+    merged
+}
+
+// Moves a cursor through a history of `len` entries, from the newest entry (index 0) towards
+// the oldest one. Moving newer than the newest entry leaves the history (None).
+pub fn step_cursor(cursor: Option<usize>, len: usize, older: bool) -> Option<usize> {
+    if len == 0 {
+        return None;
+    }
+    match (cursor, older) {
+        (None, true) => Some(0),
+        (None, false) => None,
+        (Some(i), true) => Some((i + 1).min(len - 1)),
+        (Some(0), false) => None,
+        (Some(i), false) => Some(i - 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_history() {
+        let mut history = vec![];
+        push_history(&mut history, "root-A1");
+        push_history(&mut history, "root-B2");
+        push_history(&mut history, " root-A1 ");
+        push_history(&mut history, "");
+        assert_eq!(history, vec!["root-A1".to_string(), "root-B2".to_string()]);
+        for i in 0..(HISTORY_LIMIT + 5) {
+            push_history(&mut history, &i.to_string());
+        }
+        assert_eq!(history.len(), HISTORY_LIMIT);
+    }
+
+    #[test]
+    fn test_merged_history() {
+        let tab = vec!["a".to_string(), "b".to_string()];
+        let global = vec!["b".to_string(), "c".to_string()];
+        assert_eq!(
+            merged_history(&tab, &global),
+            vec!["a".to_string(), "b".to_string(), "c".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_step_cursor() {
+        assert_eq!(step_cursor(None, 2, true), Some(0));
+        assert_eq!(step_cursor(Some(0), 2, true), Some(1));
+        assert_eq!(step_cursor(Some(1), 2, true), Some(1));
+        assert_eq!(step_cursor(Some(1), 2, false), Some(0));
+        assert_eq!(step_cursor(Some(0), 2, false), None);
+        assert_eq!(step_cursor(None, 0, true), None);
+    }
+}
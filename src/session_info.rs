@@ -0,0 +1,167 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::audit::{audit, Severity};
+use crate::coordinate::Coordinate;
+use crate::formula::{is_formula, reference_spans, Reference};
+use crate::grammar::{Grammar, Interactive, Kind};
+use crate::session::Session;
+
+// SessionInfo is the structure report of a session shown in the "Session info" dialog, and
+// exported as JSON from it
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SessionInfo {
+    pub title: String,
+    pub cells: usize,
+    // number of cells of each kind, by kind name
+    pub kinds: BTreeMap<String, usize>,
+    // number of cells at each coordinate depth (root-A1 is at depth 2)
+    pub depths: BTreeMap<usize, usize>,
+    pub lookups: usize,
+    pub formulas: usize,
+    // lookups & definitions whose target is gone, and formula references to missing cells
+    pub broken_references: usize,
+    // size of the session file on disk, if it's been saved
+    pub file_size: Option<u64>,
+    pub last_saved: Option<String>,
+}
+
+pub fn session_info(session: &Session, file_size: Option<u64>) -> SessionInfo {
+    let grammars = &session.grammars;
+    let mut kinds = BTreeMap::new();
+    let mut depths = BTreeMap::new();
+    let (mut lookups, mut formulas) = (0, 0);
+    for (coord, grammar) in grammars.iter() {
+        *kinds.entry(kind_name(grammar).to_string()).or_insert(0) += 1;
+        *depths.entry(coord.row_cols.len()).or_insert(0) += 1;
+        match &grammar.kind {
+            Kind::Lookup(_, _) => lookups += 1,
+            Kind::Input(text) if is_formula(text) => formulas += 1,
+            _ => (),
+        }
+    }
+    let broken_lookups = audit(grammars)
+        .iter()
+        .filter(|p| p.severity == Severity::Error)
+        .count();
+    let last_saved = Some(session.metadata.modified.clone()).filter(|m| !m.is_empty());
+    SessionInfo {
+        title: session.title.clone(),
+        cells: grammars.len(),
+        kinds,
+        depths,
+        lookups,
+        formulas,
+        broken_references: broken_lookups + broken_formula_references(grammars),
+        file_size,
+        last_saved,
+    }
+}
+
+// the name a grammar's kind is counted under
+fn kind_name(grammar: &Grammar) -> &'static str {
+    match &grammar.kind {
+        Kind::Text(_) => "text",
+        Kind::Input(text) if is_formula(text) => "formula",
+        Kind::Input(_) => "input",
+        Kind::Lookup(_, _) => "lookup",
+        Kind::Interactive(_, interactive) => match interactive {
+            Interactive::Button() => "button",
+            Interactive::Slider(_, _, _) => "slider",
+            Interactive::Toggle(_) => "toggle",
+            Interactive::ColorPicker(_) => "color",
+            Interactive::Rating(_, _) => "rating",
+            Interactive::Stepper(_, _, _, _) => "stepper",
+            Interactive::Shell(_, _) => "shell",
+            Interactive::Gauge(_, _, _) => "gauge",
+            Interactive::Timer(_) => "timer",
+            Interactive::Reminder(_, _) => "reminder",
+        },
+        Kind::Grid(_) => "grid",
+        Kind::Defn(_, _, _) => "definition",
+        Kind::Editor(_) => "editor",
+        Kind::Transclude(_, _) => "transclusion",
+    }
+}
+
+// number of references in formulas to cells that don't exist, counting a range once if either
+// of its corners is missing
+fn broken_formula_references(grammars: &HashMap<Coordinate, Grammar>) -> usize {
+    grammars
+        .values()
+        .filter_map(|grammar| match &grammar.kind {
+            Kind::Input(text) if is_formula(text) => Some(text),
+            _ => None,
+        })
+        .flat_map(|text| reference_spans(text))
+        .filter(|(_, reference)| match reference {
+            Reference::Cell(coord) => !grammars.contains_key(coord),
+            Reference::Range(first, last) => {
+                !grammars.contains_key(first) || !grammars.contains_key(last)
+            }
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::IterationSettings;
+    use crate::coord;
+    use crate::grammar::Lookup;
+    use crate::session::{SessionLayout, SessionMetadata, TabView};
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn test_session_info() {
+        let mut session = Session {
+            title: "info".to_string(),
+            root: Grammar::default(),
+            meta: Grammar::default(),
+            grammars: hashmap! {
+                coord!("root") => Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(2).unwrap()),
+                coord!("root-A1") => Grammar::input("", "=root-A2 + root-C9"),
+                coord!("root-A2") => Grammar::input("", "1"),
+                coord!("root-B1") => Grammar {
+                    kind: Kind::Lookup("".to_string(), Some(Lookup::Cell(coord!("root-D4")))),
+                    ..Grammar::default()
+                },
+                coord!("root-B2") => Grammar::text("", "=SUM(root-A1:root-A9)"),
+            },
+            outline_groups: vec![],
+            sheets: vec![],
+            metadata: SessionMetadata::default(),
+            bookmarks: vec![],
+            iteration: IterationSettings::default(),
+            external_sources: vec![],
+            assets: vec![],
+            packages: vec![],
+            macros: vec![],
+            mute_reminders: false,
+            variables: vec![],
+            layout: SessionLayout::default(),
+            view: TabView::default(),
+        };
+        session.metadata.modified = "2020-05-01T10:00:00Z".to_string();
+        let info = session_info(&session, Some(1024));
+        assert_eq!(info.cells, 5);
+        assert_eq!(
+            info.kinds,
+            btreemap! {
+                "formula".to_string() => 1,
+                "grid".to_string() => 1,
+                "input".to_string() => 1,
+                "lookup".to_string() => 1,
+                "text".to_string() => 1,
+            }
+        );
+        assert_eq!(info.depths, btreemap! { 1 => 1, 2 => 4 });
+        assert_eq!((info.lookups, info.formulas), (1, 1));
+        // the lookup to root-D4 and the reference to root-C9, text cells aren't formulas
+        assert_eq!(info.broken_references, 2);
+        assert_eq!(info.last_saved, Some("2020-05-01T10:00:00Z".to_string()));
+        assert!(serde_json::to_string(&info)
+            .unwrap()
+            .contains(r#""file_size":1024"#));
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+
+use crate::coordinate::Coordinate;
+use crate::external::{parse_table, write_table};
+use crate::grammar::{Grammar, Interactive, Kind, ShellOutput};
+
+// The cell a shell command cell writes its output to: the next one on the right
+pub fn output_cell(grammars: &HashMap<Coordinate, Grammar>, shell: &Coordinate) -> Option<Coordinate> {
+    let (row, col) = shell.row_col();
+    let output = Coordinate::child_of(&shell.parent()?, (row, NonZeroU32::new(col.get() + 1)?));
+    if grammars.contains_key(&output) {
+        Some(output)
+    } else {
+        None
+    }
+}
+
+// Writes the stdout of the command of `shell` into its output cell, returning that cell
+pub fn write_output(
+    grammars: &mut HashMap<Coordinate, Grammar>,
+    shell: &Coordinate,
+    output: ShellOutput,
+    stdout: &str,
+) -> Result<Coordinate, String> {
+    let cell = output_cell(grammars, shell)
+        .ok_or_else(|| format! {"{} has no cell on its right to write the output to", shell.to_string()})?;
+    match output {
+        ShellOutput::Text => {
+            let text = stdout.trim_end().to_string();
+            grammars.insert(cell.clone(), Grammar::input("".to_string(), text));
+        }
+        ShellOutput::Csv => {
+            let table = parse_table("stdout.csv", stdout)?;
+            write_table(grammars, &cell, &table);
+        }
+    }
+    Ok(cell)
+}
+
+// The command of a shell command cell
+pub fn command_of(grammars: &HashMap<Coordinate, Grammar>, shell: &Coordinate) -> Option<(String, ShellOutput)> {
+    match grammars.get(shell).map(|g| &g.kind) {
+        Some(Kind::Interactive(_, Interactive::Shell(command, output))) => {
+            Some((command.clone(), *output))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use crate::export::Region;
+
+    fn sheet() -> HashMap<Coordinate, Grammar> {
+        hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(2).unwrap()),
+            coord!("root-A1") => Grammar::default_shell(),
+            coord!("root-B1") => Grammar::input("", ""),
+        }
+    }
+
+    #[test]
+    fn test_write_output() {
+        let mut grammars = sheet();
+        assert_eq!(
+            write_output(&mut grammars, &coord!("root-A1"), ShellOutput::Text, "hello\n"),
+            Ok(coord!("root-B1"))
+        );
+        assert_eq!(grammars.get(&coord!("root-B1")).unwrap().text_value(), "hello");
+
+        write_output(&mut grammars, &coord!("root-A1"), ShellOutput::Csv, "a,b\n1,2\n").unwrap();
+        assert_eq!(Region::of_grid(&grammars, &coord!("root-B1")).unwrap().rows, (1, 2));
+        assert_eq!(grammars.get(&coord!("root-B1-B2")).unwrap().text_value(), "2");
+
+        // there's nothing on the right of the last column
+        assert!(write_output(&mut grammars, &coord!("root-B1"), ShellOutput::Text, "x").is_err());
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use crate::calc::{FormulaValue, Values};
+use crate::coordinate::Coordinate;
+use crate::export::Region;
+use crate::formula::is_formula;
+use crate::grammar::{Grammar, Kind};
+use crate::value::Value;
+
+// number of bars in the histogram of the statistics panel
+pub const HISTOGRAM_BINS: usize = 10;
+
+// Descriptive statistics of the numbers in a selection, as shown in the statistics panel
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub count: usize,
+    pub sum: f64,
+    pub mean: f64,
+    pub median: f64,
+    // sample standard deviation (0 for a single number)
+    pub stddev: f64,
+    pub min: f64,
+    pub max: f64,
+    // counts of numbers in HISTOGRAM_BINS equal-width bins from min to max
+    pub histogram: Vec<u32>,
+}
+
+// The numbers in a region: input cells holding a number (see `Value`), and formula cells (or
+// cells spilled into) whose computed value is one. Other cells are left out.
+pub fn numbers_in(
+    grammars: &HashMap<Coordinate, Grammar>,
+    values: &Values,
+    region: &Region,
+) -> Vec<f64> {
+    let mut numbers = vec![];
+    for row in region.rows.0..=region.rows.1 {
+        for col in region.cols.0..=region.cols.1 {
+            let coord = region.cell(row, col);
+            let number = match grammars.get(&coord).map(|g| &g.kind) {
+                Some(Kind::Input(text)) if !is_formula(text) && !text.trim().is_empty() => {
+                    match Value::parse(text) {
+                        Value::Number(n) => Some(n),
+                        _ => None,
+                    }
+                }
+                _ => match values.get(&coord) {
+                    Some(FormulaValue::Number(n)) => Some(*n),
+                    _ => None,
+                },
+            };
+            numbers.extend(number);
+        }
+    }
+    numbers
+}
+
+pub fn describe(numbers: &[f64]) -> Option<Stats> {
+    if numbers.is_empty() {
+        return None;
+    }
+    let mut sorted = numbers.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let count = sorted.len();
+    let sum: f64 = sorted.iter().sum();
+    let mean = sum / count as f64;
+    let median = if count % 2 == 0 {
+        (sorted[count / 2 - 1] + sorted[count / 2]) / 2.0
+    } else {
+        sorted[count / 2]
+    };
+    let stddev = if count > 1 {
+        (sorted.iter().map(|n| (n - mean).powi(2)).sum::<f64>() / (count - 1) as f64).sqrt()
+    } else {
+        0.0
+    };
+    let (min, max) = (sorted[0], sorted[count - 1]);
+    let mut histogram = vec![0; HISTOGRAM_BINS];
+    for n in sorted.iter() {
+        let bin = if max > min {
+            (((n - min) / (max - min)) * HISTOGRAM_BINS as f64) as usize
+        } else {
+            0
+        };
+        // the max falls into the last bin
+        histogram[bin.min(HISTOGRAM_BINS - 1)] += 1;
+    }
+    Some(Stats {
+        count,
+        sum,
+        mean,
+        median,
+        stddev,
+        min,
+        max,
+        histogram,
+    })
+}
+
+// The histogram as a line of block characters, scaled to its tallest bar
+pub fn sparkline(histogram: &[u32]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let tallest = histogram.iter().cloned().max().unwrap_or(0);
+    histogram
+        .iter()
+        .map(|&count| {
+            if count == 0 || tallest == 0 {
+                ' '
+            } else {
+                BARS[((count as usize * BARS.len()) - 1) / tallest as usize]
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(describe(&[]), None);
+        let stats = describe(&[4.0, 1.0, 3.0, 2.0]).unwrap();
+        assert_eq!(stats.count, 4);
+        assert_eq!(stats.mean, 2.5);
+        assert_eq!(stats.median, 2.5);
+        assert_eq!((stats.min, stats.max), (1.0, 4.0));
+        assert!((stats.stddev - 1.2909944).abs() < 1e-6);
+        assert_eq!(stats.histogram.iter().sum::<u32>(), 4);
+        assert_eq!(stats.histogram[HISTOGRAM_BINS - 1], 1);
+        let single = describe(&[7.0]).unwrap();
+        assert_eq!((single.median, single.stddev), (7.0, 0.0));
+    }
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[0, 1, 2, 4]), " ▂▄█");
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+
+use crate::coordinate::Coordinate;
+use crate::export::{cell_text, Region};
+use crate::grammar::Grammar;
+use crate::value::Date;
+
+// width of a day on the time axis at each zoom level, in px
+pub const ZOOM_LEVELS: [f64; 6] = [2.0, 4.0, 8.0, 16.0, 32.0, 64.0];
+// days shown before the first and after the last bar
+const MARGIN_DAYS: i64 = 3;
+
+// TimelineView renders the rows of a grid as bars from the date in their `start_col` column
+// to the one in their `end_col` column, labelled with their `title_col` column
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineView {
+    pub grid: Coordinate,
+    pub start_col: u32,
+    pub end_col: u32,
+    pub title_col: u32,
+    // index into `ZOOM_LEVELS`
+    pub zoom: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineBar {
+    pub row: u32,
+    pub title: String,
+    pub start: Date,
+    pub end: Date,
+}
+
+impl TimelineView {
+    pub fn day_width(&self) -> f64 {
+        ZOOM_LEVELS[self.zoom.min(ZOOM_LEVELS.len() - 1)]
+    }
+
+    // zoomed in (positive) or out (negative), within the zoom levels
+    pub fn zoomed(&self, by: i32) -> TimelineView {
+        let zoom = (self.zoom as i32 + by).max(0).min(ZOOM_LEVELS.len() as i32 - 1) as usize;
+        TimelineView { zoom, ..self.clone() }
+    }
+}
+
+// The rows with a start date, in row order. Rows without an end date (or ending before they
+// start) are a single day long.
+pub fn bars(
+    grammars: &HashMap<Coordinate, Grammar>,
+    grid: &Coordinate,
+    start_col: u32,
+    end_col: u32,
+    title_col: u32,
+) -> Vec<TimelineBar> {
+    let region = match Region::of_grid(grammars, grid) {
+        Some(region) => region,
+        None => return vec![],
+    };
+    let date = |row: u32, col: u32| Date::parse(cell_text(grammars, &region.cell(row, col)).trim());
+    (region.rows.0..=region.rows.1)
+        .filter_map(|row| {
+            let start = date(row, start_col)?;
+            let end = date(row, end_col)
+                .filter(|end| end.days_since(&start) >= 0)
+                .unwrap_or(start);
+            Some(TimelineBar {
+                row,
+                title: cell_text(grammars, &region.cell(row, title_col)),
+                start,
+                end,
+            })
+        })
+        .collect()
+}
+
+// The first and last days of the time axis: the bars and today, with a margin
+pub fn axis_range(bars: &[TimelineBar], today: Date) -> (Date, Date) {
+    let (first, last) = bars.iter().fold((today, today), |(first, last), bar| {
+        (
+            if bar.start.days_since(&first) < 0 { bar.start } else { first },
+            if bar.end.days_since(&last) > 0 { bar.end } else { last },
+        )
+    });
+    (first.add_days(-MARGIN_DAYS), last.add_days(MARGIN_DAYS))
+}
+
+// The days of the axis that start a month, labelled on it
+pub fn month_starts(first: Date, last: Date) -> Vec<Date> {
+    let mut month = first.first_of_month();
+    if month.days_since(&first) < 0 {
+        month = month.add_months(1);
+    }
+    let mut starts = vec![];
+    while last.days_since(&month) >= 0 {
+        starts.push(month);
+        month = month.add_months(1);
+    }
+    starts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use std::num::NonZeroU32;
+
+    fn date(text: &str) -> Date {
+        Date::parse(text).unwrap()
+    }
+
+    #[test]
+    fn test_bars() {
+        let grammars = hashmap! {
+            coord!("root") => Grammar::as_grid(NonZeroU32::new(3).unwrap(), NonZeroU32::new(3).unwrap()),
+            coord!("root-A1") => Grammar::input("", "design"),
+            coord!("root-B1") => Grammar::input("", "2020-03-02"),
+            coord!("root-C1") => Grammar::input("", "2020-03-13"),
+            coord!("root-A2") => Grammar::input("", "launch"),
+            coord!("root-B2") => Grammar::input("", "2020-04-01"),
+            coord!("root-C2") => Grammar::input("", "2020-03-01"),
+            coord!("root-A3") => Grammar::input("", "unplanned"),
+        };
+        let found = bars(&grammars, &coord!("root"), 2, 3, 1);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].end, date("2020-03-13"));
+        // ends before it starts
+        assert_eq!(found[1].end, date("2020-04-01"));
+
+        let (first, last) = axis_range(&found, date("2020-03-10"));
+        assert_eq!(first, date("2020-02-28"));
+        assert_eq!(last, date("2020-04-04"));
+        assert_eq!(month_starts(first, last), vec![date("2020-03-01"), date("2020-04-01")]);
+    }
+
+    #[test]
+    fn test_zoom() {
+        let view = TimelineView {
+            grid: coord!("root"),
+            start_col: 2,
+            end_col: 3,
+            title_col: 1,
+            zoom: 2,
+        };
+        assert_eq!(view.day_width(), 8.0);
+        assert_eq!(view.zoomed(10).zoom, ZOOM_LEVELS.len() - 1);
+        assert_eq!(view.zoomed(-10).zoom, 0);
+    }
+}
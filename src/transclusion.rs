@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use crate::coordinate::Coordinate;
+use crate::export::{cell_text, Region};
+use crate::grammar::Grammar;
+use crate::session::Session;
+
+// A session file that `Kind::Transclude` cells show grids from. The model keeps one per path
+// and reloads it whenever the file changes on disk.
+#[derive(Debug, Clone)]
+pub struct Transclusion {
+    pub grammars: HashMap<Coordinate, Grammar>,
+    pub status: TransclusionStatus,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransclusionStatus {
+    // in sync with the file on disk
+    Live,
+    // the file changed but couldn't be reloaded, the last content that loaded is still shown
+    Stale(String),
+    // nothing has loaded from the file yet
+    Broken(String),
+}
+
+// Applies a (re)load of a source file, given its content or why it couldn't be read
+pub fn reload(previous: Option<Transclusion>, content: Result<String, String>) -> Transclusion {
+    let parsed = content.and_then(|content| {
+        serde_json::from_str::<Session>(&content)
+            .map(|session| session.grammars)
+            .map_err(|e| format! {"not a session file: {}", e})
+    });
+    match (parsed, previous) {
+        (Ok(grammars), _) => Transclusion {
+            grammars,
+            status: TransclusionStatus::Live,
+        },
+        (Err(e), Some(previous)) if !previous.is_broken() => Transclusion {
+            grammars: previous.grammars,
+            status: TransclusionStatus::Stale(e),
+        },
+        (Err(e), _) => Transclusion {
+            grammars: HashMap::new(),
+            status: TransclusionStatus::Broken(e),
+        },
+    }
+}
+
+impl Transclusion {
+    pub fn is_broken(&self) -> bool {
+        match self.status {
+            TransclusionStatus::Broken(_) => true,
+            _ => false,
+        }
+    }
+
+    // Text of the grid at `coord` in the source, row by row
+    pub fn rows(&self, coord: &Coordinate) -> Result<Vec<Vec<String>>, String> {
+        let region = Region::of_grid(&self.grammars, coord)
+            .ok_or_else(|| format! {"no grid at {} in the source file", coord.to_string()})?;
+        Ok((region.rows.0..=region.rows.1)
+            .map(|row| {
+                (region.cols.0..=region.cols.1)
+                    .map(|col| cell_text(&self.grammars, &region.cell(row, col)))
+                    .collect()
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use crate::grammar::Kind;
+    use std::num::NonZeroU32;
+
+    fn source() -> String {
+        let root = Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(2).unwrap());
+        serde_json::json!({
+            "title": "prices",
+            "root": root,
+            "meta": Grammar::as_grid(NonZeroU32::new(1).unwrap(), NonZeroU32::new(1).unwrap()),
+            "grammars": {
+                "root": root,
+                "root-A1": Grammar::input("", "apple"),
+                "root-B1": Grammar::input("", "3"),
+            },
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_reload() {
+        let loaded = reload(None, Ok(source()));
+        assert_eq!(loaded.status, TransclusionStatus::Live);
+        assert_eq!(
+            loaded.rows(&coord!("root")),
+            Ok(vec![vec!["apple".to_string(), "3".to_string()]])
+        );
+        assert!(loaded.rows(&coord!("root-A1")).is_err());
+
+        // a half-written file keeps showing what loaded before
+        let stale = reload(Some(loaded), Ok("{\"title\": ".to_string()));
+        assert!(match stale.status {
+            TransclusionStatus::Stale(_) => true,
+            _ => false,
+        });
+        assert!(stale.rows(&coord!("root")).is_ok());
+
+        let broken = reload(None, Err("no such file".to_string()));
+        assert_eq!(broken.status, TransclusionStatus::Broken("no such file".to_string()));
+        assert!(reload(Some(broken), Err("still missing".to_string())).is_broken());
+    }
+
+    #[test]
+    fn test_transclude_kind_round_trip() {
+        let kind = Kind::Transclude("prices.json".to_string(), coord!("root-A2"));
+        let json = serde_json::to_string(&kind).unwrap();
+        assert_eq!(json, r#"{"Transclude":["prices.json","root-A2"]}"#);
+        assert_eq!(serde_json::from_str::<Kind>(&json).unwrap(), kind);
+    }
+}
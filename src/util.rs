@@ -1,20 +1,23 @@
 #![feature(core_intrinsics)]
-use std::char::from_u32;
 use std::collections::HashMap;
 use std::num::NonZeroU32;
 use std::ops::Deref;
 use std::option::Option;
-use stdweb::unstable::TryFrom;
+use stdweb::unstable::{TryFrom, TryInto};
 use stdweb::web::{document, HtmlElement, IHtmlElement, INonElementParentNode};
 use stdweb::Value;
 
 use crate::coordinate::{Col, Coordinate, Row};
-use crate::grammar::{Grammar, Kind};
+use crate::grammar::{Grammar, Kind, KindTag};
 use crate::grammar_map::*;
 use crate::model::Model;
 use crate::style::Style;
+use crate::value::Date;
 use crate::{g, grid, row_col_vec};
 
+// the coordinate helpers moved to ise-core, re-exported so `crate::util` paths keep working
+pub use ise_core::util::{coord_show, non_zero_u32_tuple, row_col_to_string, special_row_col};
+
 // `move_grammar` function does all the necessary operations when copying nested grammars from one
 // coordinate in the grid to another including:
 // - copying each nested grammar all the way to the innermost cell
@@ -31,9 +34,10 @@ pub fn move_grammar(m: &mut Model, source: Coordinate, dest: Coordinate) {
             .grammars
             .insert(dest.clone(), source_grammar.clone());
         // resizes new grammar
-        let row_height = m.row_heights.get(&source.full_row()).unwrap_or(&30.0);
-        let col_width = m.col_widths.get(&source.full_col()).unwrap_or(&90.0);
-        resize(m, dest.clone(), *row_height, *col_width);
+        let defaults = m.grid_defaults(&source);
+        let row_height = *m.row_heights.get(&source.full_row()).unwrap_or(&defaults.row_height);
+        let col_width = *m.col_widths.get(&source.full_col()).unwrap_or(&defaults.col_width);
+        resize(m, dest.clone(), row_height, col_width, (defaults.row_height, defaults.col_width));
         // copying over child grammar values
         if let Kind::Grid(sub_coords) = source_grammar.clone().kind {
             for sub_coord in sub_coords {
@@ -47,37 +51,124 @@ pub fn move_grammar(m: &mut Model, source: Coordinate, dest: Coordinate) {
     }
 }
 
-pub fn non_zero_u32_tuple(val: (u32, u32)) -> (NonZeroU32, NonZeroU32) {
-    let (row, col) = val;
-    (NonZeroU32::new(row).unwrap(), NonZeroU32::new(col).unwrap())
-}
-
-pub fn row_col_to_string((row, col): (u32, u32)) -> String {
-    let row_str = row.to_string();
-    let col_str = from_u32(col + 64).unwrap();
-    format! {"{}{}", col_str, row_str}
+// `placeholders_of` lists the unfilled placeholder cells of the grammar at `coord` (including
+// the grammar itself), in the order Tab visits them: row by row, depth first.
+pub fn placeholders_of(grammars: &HashMap<Coordinate, Grammar>, coord: &Coordinate) -> Vec<Coordinate> {
+    let mut placeholders: Vec<Coordinate> = grammars
+        .iter()
+        .filter(|(c, g)| {
+            g.style.placeholder.is_some()
+                && c.row_cols.len() >= coord.row_cols.len()
+                && c.row_cols[..coord.row_cols.len()] == coord.row_cols[..]
+        })
+        .map(|(c, _)| c.clone())
+        .collect();
+    placeholders.sort_by(|a, b| a.row_cols.cmp(&b.row_cols));
+    placeholders
 }
 
-pub fn coord_show(row_cols: Vec<(u32, u32)>) -> Option<String> {
-    match row_cols.split_first() {
-        Some((&(1, 1), rest)) => {
-            let mut output = "root".to_string();
-            for rc in rest.iter() {
-                output.push('-');
-                output.push_str(row_col_to_string(*rc).deref());
-            }
-            Some(output)
-        }
-        Some((&(1, 2), rest)) => {
-            let mut output = "meta".to_string();
-            for rc in rest.iter() {
-                output.push('-');
-                output.push_str(row_col_to_string(*rc).deref());
+// `convert_kind` converts the grammar at `coord` into another kind, carrying over its text.
+// A grid's text is the text of its first cell. Children of a grid are left in place when it's
+// converted into something else, so converting it back into a grid picks them back up.
+// Returns false if there's nothing to convert.
+pub fn convert_kind(m: &mut Model, coord: &Coordinate, tag: KindTag) -> bool {
+    let grammar = match m.get_session().grammars.get(coord) {
+        Some(g) if coord.parent().is_some() && g.kind_tag().is_some() => g.clone(),
+        _ => return false,
+    };
+    if grammar.kind_tag() == Some(tag) {
+        return false;
+    }
+    let first_child = Coordinate::child_of(coord, non_zero_u32_tuple((1, 1)));
+    let text = match &grammar.kind {
+        Kind::Grid(_) => m
+            .get_session()
+            .grammars
+            .get(&first_child)
+            .map(|g| g.text_value())
+            .unwrap_or_default(),
+        _ => grammar.text_value(),
+    };
+    let kind = match tag.kind_from_text(text.clone()) {
+        Some(kind) => kind,
+        None => {
+            let sub_coords: Vec<(NonZeroU32, NonZeroU32)> = m
+                .get_session()
+                .grammars
+                .keys()
+                .filter(|c| c.parent().as_ref() == Some(coord))
+                .map(|c| c.row_col())
+                .collect();
+            if sub_coords.is_empty() {
+                m.get_session_mut()
+                    .grammars
+                    .insert(first_child, Grammar::input("".to_string(), text));
+                Kind::Grid(vec![non_zero_u32_tuple((1, 1))])
+            } else {
+                Kind::Grid(sub_coords)
             }
-            Some(output)
         }
-        _ => None,
+    };
+    if let Some(g) = m.get_session_mut().grammars.get_mut(coord) {
+        g.kind = kind;
+    }
+    true
+}
+
+// `clear_cell` turns the grammar at `coord` back into an empty input, dropping anything nested in it
+pub fn clear_cell(m: &mut Model, coord: &Coordinate) {
+    let depth = coord.row_cols.len();
+    let grammars = &mut m.get_session_mut().grammars;
+    grammars.retain(|c, _| c.row_cols.len() <= depth || c.row_cols[..depth] != coord.row_cols[..]);
+    if let Some(g) = grammars.get_mut(coord) {
+        g.kind = Kind::Input(String::new());
+    }
+}
+
+// `append_grid_line` adds an empty row (or column) after the last row (or column) of the
+// grid at `grid_coord`, sized like the line before it. Returns false if `grid_coord` isn't a grid.
+pub fn append_grid_line(m: &mut Model, grid_coord: &Coordinate, is_row: bool) -> bool {
+    let mut sub_coords = match m.get_session().grammars.get(grid_coord) {
+        Some(Grammar {
+            kind: Kind::Grid(sub_coords),
+            ..
+        }) => sub_coords.clone(),
+        _ => return false,
+    };
+    let (max_row, max_col) = sub_coords.iter().fold((0, 0), |(max_row, max_col), (r, c)| {
+        (max_row.max(r.get()), max_col.max(c.get()))
+    });
+    let new_sub_coords: Vec<(NonZeroU32, NonZeroU32)> = if is_row {
+        (1..=max_col).map(|c| non_zero_u32_tuple((max_row + 1, c))).collect()
+    } else {
+        (1..=max_row).map(|r| non_zero_u32_tuple((r, max_col + 1))).collect()
+    };
+    for sub_coord in new_sub_coords {
+        m.get_session_mut()
+            .grammars
+            .insert(Coordinate::child_of(grid_coord, sub_coord), Grammar::default());
+        sub_coords.push(sub_coord);
     }
+    let defaults = m.grid_defaults(grid_coord);
+    if is_row {
+        let height = *m
+            .row_heights
+            .get(&Row(grid_coord.clone(), NonZeroU32::new(max_row.max(1)).unwrap()))
+            .unwrap_or(&defaults.row_height);
+        m.row_heights
+            .insert(Row(grid_coord.clone(), NonZeroU32::new(max_row + 1).unwrap()), height);
+    } else {
+        let width = *m
+            .col_widths
+            .get(&Col(grid_coord.clone(), NonZeroU32::new(max_col.max(1)).unwrap()))
+            .unwrap_or(&defaults.col_width);
+        m.col_widths
+            .insert(Col(grid_coord.clone(), NonZeroU32::new(max_col + 1).unwrap()), width);
+    }
+    if let Some(g) = m.get_session_mut().grammars.get_mut(grid_coord) {
+        g.kind = Kind::Grid(sub_coords);
+    }
+    true
 }
 
 pub fn apply_definition_grammar(m: &mut Model, root_coord: Coordinate) {
@@ -130,33 +221,35 @@ pub fn apply_definition_grammar(m: &mut Model, root_coord: Coordinate) {
     );
 }
 
-pub fn resize(m: &mut Model, coord: Coordinate, row_height: f64, col_width: f64) {
+// `defaults` are the (row height, column width) the row & column of `coord` start from when
+// they don't have a size yet
+pub fn resize(
+    m: &mut Model,
+    coord: Coordinate,
+    row_height: f64,
+    col_width: f64,
+    defaults: (f64, f64),
+) {
     if let Some(parent_coord) = coord.parent() {
-        let mut row_height_diff = 0.0;
-        let mut col_width_diff = 0.0;
-        let mut new_row_height = 0.0;
-        let mut new_col_width = 0.0;
         let mut new_grammar = Grammar::default();
-        if let Some(old_row_height) = m.row_heights.get_mut(&coord.full_row()) {
-            if row_height != *old_row_height {
-                // In case for the addnested row is different with the old one
-                new_row_height = row_height + /* horizontal border width */ 2.0;
-            } else {
-                new_row_height = row_height;
-            }
-            row_height_diff = new_row_height - *old_row_height;
-            *old_row_height = new_row_height;
-        }
-        if let Some(old_col_width) = m.col_widths.get_mut(&coord.full_col()) {
-            if col_width != *old_col_width {
-                // In case for the addnested col is different with the old one
-                new_col_width = col_width + /* vertiacl border height */ 2.0;
-            } else {
-                new_col_width = col_width;
-            }
-            col_width_diff = new_col_width - *old_col_width;
-            *old_col_width = new_col_width;
-        }
+        let old_row_height = m.row_heights.entry(coord.full_row()).or_insert(defaults.0);
+        let mut new_row_height = if row_height != *old_row_height {
+            // In case for the addnested row is different with the old one
+            row_height + /* horizontal border width */ 2.0
+        } else {
+            row_height
+        };
+        let row_height_diff = new_row_height - *old_row_height;
+        *old_row_height = new_row_height;
+        let old_col_width = m.col_widths.entry(coord.full_col()).or_insert(defaults.1);
+        let mut new_col_width = if col_width != *old_col_width {
+            // In case for the addnested col is different with the old one
+            col_width + /* vertiacl border height */ 2.0
+        } else {
+            col_width
+        };
+        let col_width_diff = new_col_width - *old_col_width;
+        *old_col_width = new_col_width;
 
         /* Update style width and height for the resize coord and neighbor with same column or row
             Also update new size for its parent coord and associate neighbor.
@@ -203,6 +296,23 @@ pub fn resize_diff(m: &mut Model, coord: Coordinate, row_height_diff: f64, col_w
     }
 }
 
+// Gives the rows & columns of the grid at `grid` that don't have a size yet the (row height,
+// column width) of `defaults`
+pub fn resize_cells(m: &mut Model, grid: &Coordinate, defaults: (f64, f64)) {
+    let sub_coords = match m.get_session().grammars.get(grid) {
+        Some(Grammar {
+            kind: Kind::Grid(sub_coords),
+            ..
+        }) => sub_coords.clone(),
+        _ => return,
+    };
+    for sub_coord in sub_coords {
+        let cell = Coordinate::child_of(grid, sub_coord);
+        m.row_heights.entry(cell.full_row()).or_insert(defaults.0);
+        m.col_widths.entry(cell.full_col()).or_insert(defaults.1);
+    }
+}
+
 // Use width and height values from DOM to resize element
 pub fn dom_resize(m: &mut Model, on: Coordinate) {
     let (height, width) = {
@@ -216,7 +326,8 @@ pub fn dom_resize(m: &mut Model, on: Coordinate) {
         (rect.get_height(), rect.get_width())
     };
     info! {"expanding...: H {}px, W {}px", height.clone(), width.clone()}
-    resize(m, on, height, width);
+    let defaults = m.grid_defaults(&on);
+    resize(m, on, height, width, (defaults.row_height, defaults.col_width));
     /*
     let on_grammar = map.get_mut(&on).unwrap();
     on_grammar.style.height = height.clone();
@@ -240,18 +351,210 @@ pub fn dom_resize(m: &mut Model, on: Coordinate) {
     */
 }
 
-// macro for easily defining a vector of non-zero tuples
-// used in Coordinate::root() below
-#[macro_export]
-macro_rules! row_col_vec {
-    ( $( $x:expr ), * ) => {
-        {
-            let mut v: Vec<(NonZeroU32, NonZeroU32)> = Vec::new();
-            $(
-                v.push(non_zero_u32_tuple($x));
-            )*
-            v
+// the current date, in UTC
+pub fn today() -> Date {
+    Date::parse(&now_iso()[..10]).unwrap_or(Date {
+        year: 1970,
+        month: 1,
+        day: 1,
+    })
+}
+
+// current time in milliseconds since the epoch
+pub fn now_ms() -> f64 {
+    let now: f64 = js! { return Date.now(); }.try_into().unwrap_or_default();
+    now
+}
+
+// current time as an ISO 8601 string
+pub fn now_iso() -> String {
+    let now: String = js! { return new Date().toISOString(); }
+        .try_into()
+        .unwrap_or_default();
+    now
+}
+
+// current local time as `YYYY-MM-DDTHH:MM:SS`, which is how reminder times compare
+pub fn now_local() -> String {
+    let now: String = js! {
+        let now = new Date();
+        return new Date(now.getTime() - now.getTimezoneOffset() * 60000).toISOString().slice(0, 19);
+    }
+    .try_into()
+    .unwrap_or_default();
+    now
+}
+
+// a random hex string to authenticate with, e.g. to the automation server
+pub fn random_token() -> String {
+    let token: String = js! { return require("crypto").randomBytes(16).toString("hex"); }
+        .try_into()
+        .unwrap_or_default();
+    token
+}
+
+// (scrollLeft, scrollTop) of the main area
+pub fn main_scroll() -> (f64, f64) {
+    let scroll: Vec<f64> = js! {
+        let main = document.querySelector(".main");
+        return main ? [main.scrollLeft, main.scrollTop] : [0, 0];
+    }
+    .try_into()
+    .unwrap_or(vec![0.0, 0.0]);
+    (scroll[0], scroll[1])
+}
+
+// (clientWidth, clientHeight) of the main area, the part of the window the grids are shown in
+pub fn main_size() -> (f64, f64) {
+    let size: Vec<f64> = js! {
+        let main = document.querySelector(".main");
+        return main ? [main.clientWidth, main.clientHeight] : [window.innerWidth, window.innerHeight];
+    }
+    .try_into()
+    .unwrap_or(vec![800.0, 600.0]);
+    (size[0], size[1])
+}
+
+// scrolls the main area, once it's been rendered
+pub fn set_main_scroll(scroll: (f64, f64)) {
+    js! {
+        setTimeout(function() {
+            let main = document.querySelector(".main");
+            if (main) {
+                main.scrollLeft = @{scroll.0};
+                main.scrollTop = @{scroll.1};
+            }
+        }, 0);
+    };
+}
+
+// writes `text` to the system clipboard
+pub fn copy_to_clipboard(text: String) {
+    js! {
+        navigator.clipboard.writeText(@{text}).catch(function (e) {
+            console.log("cannot write to clipboard", e);
+        });
+    };
+}
+
+// puts cells on the clipboard both as plain text (TSV) and as an HTML table, for targets that
+// take rich pastes
+pub fn copy_table_to_clipboard(text: String, html: String) {
+    js! {
+        require("electron").clipboard.write({ text: @{text}, html: @{html} });
+    };
+}
+
+// What's on the clipboard: the rows of the first HTML table in there, as JSON `HtmlCell`s (see
+// `clipboard::lay_out`), if there's one, and the plain text
+pub fn read_clipboard() -> (Option<String>, String) {
+    let rows: Option<String> = js! {
+        const html = require("electron").clipboard.readHTML();
+        const table = html && new DOMParser().parseFromString(html, "text/html").querySelector("table");
+        if (!table) {
+            return null;
+        }
+        return JSON.stringify(Array.from(table.rows).map((row) => Array.from(row.cells).map((cell) => ({
+            text: cell.textContent.trim(),
+            rowspan: cell.rowSpan,
+            colspan: cell.colSpan,
+        }))));
+    }
+    .try_into()
+    .unwrap_or_default();
+    let text: String = js! { return require("electron").clipboard.readText(); }
+        .try_into()
+        .unwrap_or_default();
+    (rows, text)
+}
+
+// offset of the caret within the text of the cell `coord`, if the caret is in there
+pub fn caret_offset(coord: &Coordinate) -> Option<usize> {
+    let id = format! {"cell-{}", coord.to_string()};
+    let offset: i32 = js! {
+        let cell = document.getElementById(@{id});
+        let selection = window.getSelection();
+        if (!cell || !selection.rangeCount || !cell.contains(selection.anchorNode)) {
+            return -1;
         }
+        let range = document.createRange();
+        range.setStart(cell.querySelector(".cell-data") || cell, 0);
+        range.setEnd(selection.anchorNode, selection.anchorOffset);
+        return range.toString().length;
+    }
+    .try_into()
+    .unwrap_or(-1);
+    if offset < 0 {
+        None
+    } else {
+        Some(offset as usize)
+    }
+}
+
+// puts the caret at `offset` within the text of the cell `coord`, once it's been re-rendered
+pub fn set_caret(coord: &Coordinate, offset: usize) {
+    let id = format! {"cell-{}", coord.to_string()};
+    let offset = offset as u32;
+    js! {
+        setTimeout(function () {
+            let cell = document.getElementById(@{id});
+            let data = cell && (cell.querySelector(".cell-data") || cell);
+            if (!data) {
+                return;
+            }
+            data.focus();
+            let walker = document.createTreeWalker(data, NodeFilter.SHOW_TEXT);
+            let remaining = @{offset};
+            while (walker.nextNode()) {
+                let node = walker.currentNode;
+                if (remaining <= node.length) {
+                    window.getSelection().collapse(node, remaining);
+                    return;
+                }
+                remaining -= node.length;
+            }
+        }, 0);
+    };
+}
+
+// Outlines each (first cell, last cell, color) range in an overlay on top of the main area,
+// once it's been re-rendered. Ranges whose corners aren't shown are left out, and an empty
+// list clears the overlay.
+pub fn draw_reference_overlay(highlights: Vec<(String, String, String)>) {
+    js! {
+        let highlights = @{highlights};
+        setTimeout(function () {
+            let main = document.querySelector(".main");
+            if (!main) {
+                return;
+            }
+            let overlay = main.querySelector(".reference-overlay");
+            if (!overlay) {
+                overlay = document.createElement("div");
+                overlay.className = "reference-overlay";
+                main.appendChild(overlay);
+            }
+            overlay.innerHTML = "";
+            let origin = main.getBoundingClientRect();
+            highlights.forEach(function (h) {
+                let first = document.getElementById("cell-" + h[0]);
+                let last = document.getElementById("cell-" + h[1]);
+                if (!first || !last) {
+                    return;
+                }
+                let a = first.getBoundingClientRect();
+                let b = last.getBoundingClientRect();
+                let box = document.createElement("div");
+                box.className = "reference-highlight";
+                box.style.left = (Math.min(a.left, b.left) - origin.left + main.scrollLeft) + "px";
+                box.style.top = (Math.min(a.top, b.top) - origin.top + main.scrollTop) + "px";
+                box.style.width = (Math.max(a.right, b.right) - Math.min(a.left, b.left)) + "px";
+                box.style.height = (Math.max(a.bottom, b.bottom) - Math.min(a.top, b.top)) + "px";
+                box.style.borderColor = h[2];
+                box.style.backgroundColor = h[2] + "22";
+                overlay.appendChild(box);
+            });
+        }, 0);
     };
 }
 
@@ -260,26 +563,23 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_non_zero_u32_tuple() {
+    fn test_placeholders_of() {
+        let snippet = Coordinate {
+            row_cols: vec![non_zero_u32_tuple((1, 1)), non_zero_u32_tuple((1, 1))],
+        };
+        let child = |row, col| Coordinate::child_of(&snippet, non_zero_u32_tuple((row, col)));
+        let mut placeholder = Grammar::input("", "");
+        placeholder.style.placeholder = Some("name".to_string());
+        let mut grammars = HashMap::new();
+        grammars.insert(snippet.clone(), Grammar::as_grid(NonZeroU32::new(2).unwrap(), NonZeroU32::new(2).unwrap()));
+        grammars.insert(child(2, 1), placeholder.clone());
+        grammars.insert(child(1, 1), Grammar::input("", ""));
+        grammars.insert(child(1, 2), placeholder.clone());
+        grammars.insert(child(2, 2), placeholder.clone());
         assert_eq!(
-            non_zero_u32_tuple((1, 2)),
-            (NonZeroU32::new(1).unwrap(), NonZeroU32::new(2).unwrap())
+            placeholders_of(&grammars, &snippet),
+            vec![child(1, 2), child(2, 1), child(2, 2)]
         );
-        assert_ne!(
-            non_zero_u32_tuple((1, 2)),
-            (NonZeroU32::new(2).unwrap(), NonZeroU32::new(2).unwrap())
-        );
-    }
-
-    #[test]
-    fn test_row_col_to_string() {
-        assert_eq!(row_col_to_string((2, 2)), "B2");
-        assert_ne!(row_col_to_string((2, 2)), "A2");
-    }
-
-    #[test]
-    fn test_coord_show() {
-        assert_eq!(coord_show(vec![(1, 1), (1, 1)]).unwrap(), "root-A1");
-        assert_ne!(coord_show(vec![(1, 1), (1, 1)]).unwrap(), "root")
+        assert_eq!(placeholders_of(&grammars, &child(1, 1)), vec![]);
     }
 }
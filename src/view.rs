@@ -1,11 +1,10 @@
 #![recursion_limit = "1024"]
-use pest::Parser;
 use std::num::NonZeroU32;
 use std::ops::Deref;
 use stdweb::traits::IEvent;
 use stdweb::unstable::TryFrom;
 use stdweb::unstable::TryInto;
-use stdweb::web::event::IDragEvent;
+use stdweb::web::event::{IDragEvent, MouseButton};
 use stdweb::web::{html_element::InputElement, HtmlElement, IHtmlElement};
 use yew::events::{ClickEvent, IKeyboardEvent, IMouseEvent, KeyPressEvent};
 use yew::prelude::*;
@@ -13,18 +12,50 @@ use yew::services::reader::File;
 use yew::virtual_dom::vlist::VList;
 use yew::{html, ChangeData, Html, InputData};
 
+use crate::assets::{reference_counts, ASSET_PREFIX};
+use crate::audit::{audit, Severity};
+use crate::autocomplete::{column_completions, COLUMN_COMPLETION_LIMIT};
+use crate::calc::{formula_cells, uses, CalcMode, FormulaValue};
+use crate::calendar::{entries, CalendarMode, CalendarView};
 use crate::codemirror::CodeMirror;
 use crate::coordinate::Coordinate;
-use crate::grammar::{Grammar, Interactive, Kind, Lookup};
-use crate::model::{Action, CursorType, Model, ResizeMsg, SelectMsg, SideMenu};
-use crate::style::get_style;
-use crate::util::non_zero_u32_tuple;
+use crate::defn::{instances, DefnArguments};
+use crate::diagnostics::{diagnose, format_bytes};
+use crate::driver::{ConsoleLevel, Driver, LoadStatus, UploadState};
+use crate::entry::{next_cell, EnterBehavior};
+use crate::formula::{colored_references, is_formula, parse as parse_formula, reference_spans, tokenize};
+use crate::gauge::{fraction, gauge_value, GaugeLevel};
+use crate::goal_seek::GoalSeek;
+use crate::grammar::{Grammar, Interactive, Kind, KindTag, Lookup, ReminderTrigger, ShellOutput};
+use crate::history::EditKind;
+use crate::hooks::{HookSource, Stage};
+use crate::integrity::{check, orphans};
+use crate::locale::{localize, Locale};
+use crate::macros::{ButtonAction, Macro, MacroBody};
+use crate::meta_import::import_names;
+use crate::session_merge::MergeChoice;
+use crate::model::{Action, CursorType, GuardChoice, GuardedAction, Model, ResizeMsg, SelectMsg, SideMenu};
+use crate::outline::{groups_of, is_hidden, OutlineAxis};
+use crate::packages::update_for;
+use crate::preferences::{MetaGrammar, Preferences};
+use crate::reminders::{reminders, Reminder};
+use crate::rename::{check_name, usages};
+use crate::replay::step_label;
+use crate::search::StructuralQuery;
+use crate::search_history::HistoryKind;
+use crate::session::Session;
+use crate::shape::{mismatch, Shape};
+use crate::shell::command_of;
+use crate::stats::{describe, numbers_in, sparkline};
+use crate::style::{get_style, GridDefaults, StyleBindings};
+use crate::suggestion::{by_usage, category_of, group_by_category, most_used, usage_report, RECENT_LIMIT};
+use crate::timeline::{axis_range, bars, month_starts, TimelineView};
+use crate::transclusion::TransclusionStatus;
+use crate::util::{non_zero_u32_tuple, random_token, today};
+use crate::value::{format_duration, Date, StepUnit, Value};
+use crate::variables::{is_valid_name, value_of};
 use crate::{coord};
 
-#[derive(Parser)]
-#[grammar = "coordinate.pest"]
-pub struct CoordinateParser;
-
 static mut check: bool = true;
 
 pub fn view_side_nav(m: &Model) -> Html {
@@ -52,153 +83,2739 @@ pub fn view_side_nav(m: &Model) -> Html {
             });
         }
     }
-
+
+    html! {
+        <div class="sidenav">
+            { side_menu_nodes }
+
+            { side_menu_section }
+        </div>
+    }
+}
+pub fn view_file_popup(m: &Model) -> Html {
+    let mut popup = html! { <></> };
+
+    if m.file_popup {
+        popup = html! {
+            <div class="hover_popup">
+                <span class="helper"></span>
+                <div class="_popup">
+                    <div class="popupCloseButton" onclick=m.link.callback(|e| Action::AskFileName())>{"X"}</div>
+                    // Path input under work
+                    /* <label for="path">{"Path"}</label>
+                        <input type="text" name="path" value=m.path onchange=m.link.callback(|v| {
+                            if let ChangeData::Value(s) = v {
+                                return Action::SetPath(s);
+                            }
+                            Action::Noop
+                        })>
+                        </input>*/
+                    <label for="path">{"Name"}</label>
+                    <input type="text" name="name" value=m.get_session().title onchange=m.link.callback(|v| {
+                        if let ChangeData::Value(s) = v {
+                            return Action::SetSessionTitle(s);
+                        }
+                        Action::Noop
+                    })>
+                    </input>
+
+                    <input type="button" value="Save" onclick=m.link.callback(|_| Action::SaveSession()) />
+                </div>
+            </div>
+        };
+    }
+
+    html! {
+        {popup}
+    }
+}
+
+// Prompt shown when an action would lose unsaved changes
+pub fn view_unsaved_prompt(m: &Model) -> Html {
+    let message = match m.pending_guard.as_ref() {
+        None => return html! { <></> },
+        // closing tabs asks about the ones being closed, which may not be the current one
+        Some(GuardedAction::CloseTabs(closed)) => {
+            let dirty: Vec<&Session> = closed
+                .iter()
+                .filter_map(|i| m.sessions.get(*i))
+                .filter(|s| s.view.dirty)
+                .collect();
+            match dirty.as_slice() {
+                [session] => format! {"\"{}\" has unsaved changes.", session.title},
+                _ => format! {"{} tabs have unsaved changes.", dirty.len()},
+            }
+        }
+        Some(_) => format! {"\"{}\" has unsaved changes.", m.get_session().title},
+    };
+    html! {
+        <div class="hover_popup">
+            <span class="helper"></span>
+            <div class="_popup unsaved-prompt">
+                <div>{ message }</div>
+                <div>
+                    <input type="button" value="Save" onclick=m.link.callback(|_| Action::ResolveGuard(GuardChoice::Save)) />
+                    <input type="button" value="Discard" onclick=m.link.callback(|_| Action::ResolveGuard(GuardChoice::Discard)) />
+                    <input type="button" value="Cancel" onclick=m.link.callback(|_| Action::ResolveGuard(GuardChoice::Cancel)) />
+                </div>
+            </div>
+        </div>
+    }
+}
+
+// Offers to recover the tabs autosaved before the app last crashed (see `Autosave`)
+pub fn view_recovery_prompt(m: &Model) -> Html {
+    let autosave = match m.recovery.as_ref() {
+        Some(autosave) => autosave,
+        None => return html! { <></> },
+    };
+    let titles: Vec<String> = autosave.tabs.iter().map(|(session, _)| format! {"\"{}\"", session.title}).collect();
+    html! {
+        <div class="hover_popup">
+            <span class="helper"></span>
+            <div class="_popup unsaved-prompt">
+                <div>{ format!{"The app didn't close properly. Recover the unsaved changes to {} (autosaved {})?",
+                    titles.join(", "), autosave.saved_at.replace('T', " ")} }</div>
+                <div>
+                    <input type="button" value="Recover" onclick=m.link.callback(|_| Action::RecoverAutosave) />
+                    <input type="button" value="Discard" onclick=m.link.callback(|_| Action::DiscardAutosave) />
+                </div>
+            </div>
+        </div>
+    }
+}
+
+// Asks before the command of a shell command cell runs for the first time in a tab
+pub fn view_shell_confirm(m: &Model) -> Html {
+    let command = match m.shell_confirm.as_ref().and_then(|c| command_of(&m.get_session().grammars, c)) {
+        Some((command, _)) => command,
+        None => return html! { <></> },
+    };
+    html! {
+        <div class="hover_popup">
+            <span class="helper"></span>
+            <div class="_popup shell-confirm">
+                <div>{ "Allow this session to run the command below?" }</div>
+                <pre>{ command }</pre>
+                <div>
+                    <input type="button" value="Allow & Run" onclick=m.link.callback(|_| Action::ConfirmShell(true)) />
+                    <input type="button" value="Cancel" onclick=m.link.callback(|_| Action::ConfirmShell(false)) />
+                </div>
+            </div>
+        </div>
+    }
+}
+
+// Asks before the script of the macro a button runs is run for the first time in a tab
+pub fn view_script_confirm(m: &Model) -> Html {
+    let session = m.get_session();
+    let macro_ = m
+        .script_confirm
+        .as_ref()
+        .and_then(|c| session.grammars.get(c))
+        .and_then(|g| g.style.on_click.as_ref())
+        .and_then(|action| session.macros.iter().find(|macro_| macro_.name == action.macro_name));
+    let (name, source) = match macro_ {
+        Some(Macro { name, body: MacroBody::Script(source), .. }) => (name.clone(), source.clone()),
+        _ => return html! { <></> },
+    };
+    html! {
+        <div class="hover_popup">
+            <span class="helper"></span>
+            <div class="_popup shell-confirm">
+                <div>{ format!{"Allow this session to run the script of the macro {}?", name} }</div>
+                <pre>{ source }</pre>
+                <div>
+                    <input type="button" value="Allow & Run" onclick=m.link.callback(|_| Action::ConfirmScript(true)) />
+                    <input type="button" value="Cancel" onclick=m.link.callback(|_| Action::ConfirmScript(false)) />
+                </div>
+            </div>
+        </div>
+    }
+}
+
+// Asks whether a driver may use a capability it declared, the first time it attempts to
+pub fn view_driver_prompt(m: &Model) -> Html {
+    let call = match m.driver_prompts.first() {
+        Some(call) => call,
+        None => return html! { <></> },
+    };
+    let capability = match call.capability() {
+        Ok(Some(capability)) => capability,
+        _ => return html! { <></> },
+    };
+    html! {
+        <div class="hover_popup">
+            <span class="helper"></span>
+            <div class="_popup driver-prompt">
+                <div>{ format!{"The driver \"{}\" wants to {}:", call.driver, capability.describe()} }</div>
+                <pre>{ format!{"{}({})", call.op, call.args.join(", ")} }</pre>
+                <div>
+                    <input type="button" value="Allow" onclick=m.link.callback(|_| Action::ResolveDriverPrompt(true)) />
+                    <input type="button" value="Deny" onclick=m.link.callback(|_| Action::ResolveDriverPrompt(false)) />
+                </div>
+            </div>
+        </div>
+    }
+}
+
+pub fn view_notifications(m: &Model) -> Html {
+    let mut nodes = VList::new();
+    for notification in m.notifications.iter() {
+        let id = notification.id;
+        let class = if notification.error { "notification error" } else { "notification" };
+        nodes.add_child(html! {
+            <div class=class>
+                <span>{ &notification.text }</span>
+                <button class="menu-bar-button" onclick=m.link.callback(move |_| Action::DismissNotification(id))>
+                    { "x" }
+                </button>
+            </div>
+        });
+    }
+    html! { <div class="notifications">{ nodes }</div> }
+}
+
+// Repro bar: shows a repro log being recorded, or controls the one being replayed
+pub fn view_repro_bar(m: &Model) -> Html {
+    if let Some(replay) = &m.replay {
+        let total = replay.log.steps.len();
+        let next = match replay.log.steps.get(replay.position) {
+            Some(step) => format! {"next: {}", step_label(step)},
+            None => "done".to_string(),
+        };
+        let playing = replay.playing;
+        let done = replay.is_done();
+        return html! {
+            <div class="repro-bar">
+                <span class="repro-progress">{ format!{"Replaying step {} of {}", replay.position, total} }</span>
+                <span class="repro-next">{ next }</span>
+                <button disabled={done || playing} onclick=m.link.callback(|_| Action::StepReplay)>{ "Step" }</button>
+                <button disabled={done} onclick=m.link.callback(move |_| Action::PlayReplay(!playing))>
+                    { if playing { "Pause" } else { "Play" } }
+                </button>
+                <button onclick=m.link.callback(|_| Action::CloseReplay)>{ "Close" }</button>
+            </div>
+        };
+    }
+    match &m.recording {
+        Some((_, log)) => html! {
+            <div class="repro-bar recording">
+                <span class="repro-progress">
+                    { format!{"Recording a repro, {} steps", log.steps.len()} }
+                    { if log.incomplete { " (incomplete)" } else { "" } }
+                </span>
+                <button onclick=m.link.callback(|_| Action::ExportRepro)>{ "Export..." }</button>
+                <button onclick=m.link.callback(|_| Action::StopRecording)>{ "Stop" }</button>
+            </div>
+        },
+        None => html! { <></> },
+    }
+}
+
+// Goal seek dialog: finds the value of an input cell for which a formula reaches a desired value,
+// previewed before it's applied
+pub fn view_goal_seek(m: &Model) -> Html {
+    let form = match &m.goal_seek {
+        Some(form) => form.clone(),
+        None => return html! { <></> },
+    };
+    let (target_form, desired_form, variable_form) = (form.clone(), form.clone(), form.clone());
+    let preview = match &form.preview {
+        None => html! { <></> },
+        Some(Ok(solution)) => html! {
+            <div class="goal-seek-preview">
+                { format!{
+                    "Setting {} to {} makes {} = {} ({} iterations)",
+                    form.variable, solution.input, form.target, solution.achieved, solution.iterations
+                } }
+            </div>
+        },
+        Some(Err(message)) => html! { <div class="goal-seek-preview search-error">{ message }</div> },
+    };
+    let can_apply = match &form.preview {
+        Some(Ok(_)) => true,
+        _ => false,
+    };
+    html! {
+        <div class="hover_popup">
+            <span class="helper"></span>
+            <div class="_popup goal-seek">
+                <h3>{ "Goal Seek" }</h3>
+                <label>{ "Set cell " }</label>
+                <input value=form.target.clone() placeholder="root-B3"
+                    oninput=m.link.callback(move |e: InputData| Action::UpdateGoalSeek(GoalSeek { target: e.value, ..target_form.clone() }))>
+                </input>
+                <br></br>
+                <label>{ "To value " }</label>
+                <input value=form.desired.clone() placeholder="100"
+                    oninput=m.link.callback(move |e: InputData| Action::UpdateGoalSeek(GoalSeek { desired: e.value, ..desired_form.clone() }))>
+                </input>
+                <br></br>
+                <label>{ "By changing " }</label>
+                <input value=form.variable.clone() placeholder="root-A1"
+                    oninput=m.link.callback(move |e: InputData| Action::UpdateGoalSeek(GoalSeek { variable: e.value, ..variable_form.clone() }))>
+                </input>
+                { preview }
+                <div>
+                    <input type="button" value="Preview" onclick=m.link.callback(|_| Action::PreviewGoalSeek) />
+                    <input type="button" value="Apply" disabled=!can_apply onclick=m.link.callback(|_| Action::ApplyGoalSeek) />
+                    <input type="button" value="Cancel" onclick=m.link.callback(|_| Action::CloseGoalSeek) />
+                </div>
+            </div>
+        </div>
+    }
+}
+
+// Session info dialog: what the current session is made of, with its file on disk
+pub fn view_session_info(m: &Model) -> Html {
+    let info = match &m.session_info {
+        Some(info) => info,
+        None => return html! { <></> },
+    };
+    let rows = vec![
+        ("cells", info.cells.to_string()),
+        ("formulas", info.formulas.to_string()),
+        ("lookups", info.lookups.to_string()),
+        ("broken references", info.broken_references.to_string()),
+        (
+            "file size",
+            info.file_size.map_or("not saved".to_string(), |size| format_bytes(size as usize)),
+        ),
+        ("last saved", info.last_saved.clone().unwrap_or_else(|| "never".to_string())),
+    ];
+    let mut table = VList::new();
+    for (label, value) in rows {
+        table.add_child(html! {
+            <tr><td>{ label }</td><td class="stat-value">{ value }</td></tr>
+        });
+    }
+    let mut kind_rows = VList::new();
+    for (kind, count) in info.kinds.iter() {
+        kind_rows.add_child(html! {
+            <tr><td>{ kind }</td><td class="stat-value">{ count }</td></tr>
+        });
+    }
+    let most = info.depths.values().cloned().max().unwrap_or(1);
+    let mut depth_rows = VList::new();
+    for (depth, count) in info.depths.iter() {
+        let width = format! {"width: {}%;", count * 100 / most};
+        depth_rows.add_child(html! {
+            <tr>
+                <td>{ depth }</td>
+                <td class="stat-value">{ count }</td>
+                <td class="depth-bar-cell"><div class="depth-bar" style=width></div></td>
+            </tr>
+        });
+    }
+    html! {
+        <div class="hover_popup">
+            <span class="helper"></span>
+            <div class="_popup session-info">
+                <h3>{ format!{"Session Info: {}", info.title} }</h3>
+                <table class="stats-table">{ table }</table>
+                <div class="session-info-columns">
+                    <table class="stats-table">
+                        <tr><th>{ "kind" }</th><th>{ "cells" }</th></tr>
+                        { kind_rows }
+                    </table>
+                    <table class="stats-table">
+                        <tr><th>{ "depth" }</th><th>{ "cells" }</th><th></th></tr>
+                        { depth_rows }
+                    </table>
+                </div>
+                <div>
+                    <input type="button" value="Export JSON..." onclick=m.link.callback(|_| Action::ExportSessionInfo) />
+                    <input type="button" value="Close" onclick=m.link.callback(|_| Action::CloseSessionInfo) />
+                </div>
+            </div>
+        </div>
+    }
+}
+
+// Import meta grammars dialog: the meta grammars of another session file, checked to be copied
+// into the meta sheet. Names already taken show what they'll be imported as.
+pub fn view_meta_import(m: &Model) -> Html {
+    let import = match &m.meta_import {
+        Some(import) => import,
+        None => return html! { <></> },
+    };
+    let taken: Vec<String> = m.meta_suggestions.iter().map(|(name, _)| name.clone()).collect();
+    let names = import_names(&import.listed, &import.selected, &taken);
+    let mut rows = VList::new();
+    for (name, coord) in import.listed.iter() {
+        let checked = import.selected.contains(coord);
+        let category = import.grammars.get(coord).map_or("", |g| category_of(g).label());
+        let renamed = match names.get(name) {
+            Some(imported) if imported != name => format! {"as {}", imported},
+            _ => "".to_string(),
+        };
+        let toggled = coord.clone();
+        rows.add_child(html! {
+            <tr>
+                <td>
+                    <input type="checkbox" checked={checked}
+                        onclick=m.link.callback(move |_: ClickEvent| Action::ToggleMetaImport(toggled.clone()))>
+                    </input>
+                </td>
+                <td>{ name }</td>
+                <td class="search-help">{ category }</td>
+                <td class="meta-import-renamed">{ renamed }</td>
+            </tr>
+        });
+    }
+    html! {
+        <div class="hover_popup">
+            <span class="helper"></span>
+            <div class="_popup meta-import">
+                <h3>{ "Import Meta Grammars" }</h3>
+                <div class="search-help">{ &import.path }</div>
+                <div class="meta-import-list">
+                    <table class="stats-table">{ rows }</table>
+                </div>
+                <div>
+                    <input type="button" value="Import" disabled=import.selected.is_empty()
+                        onclick=m.link.callback(|_| Action::ApplyMetaImport) />
+                    <input type="button" value="Cancel" onclick=m.link.callback(|_| Action::CloseMetaImport) />
+                </div>
+            </div>
+        </div>
+    }
+}
+
+// Merge dialog: the cells that differ between the two sessions, with mine and their version of
+// each and which is kept
+pub fn view_session_merge(m: &Model) -> Html {
+    let merge = match &m.session_merge {
+        Some(merge) => merge,
+        None => return html! { <></> },
+    };
+    let options = [
+        (MergeChoice::Mine, "Mine"),
+        (MergeChoice::Theirs, "Theirs"),
+        (MergeChoice::Both, "Both"),
+    ];
+    let mut rows = VList::new();
+    for conflict in merge.conflicts.iter() {
+        let mut choices = VList::new();
+        for (choice, label) in options.iter() {
+            let (coord, choice) = (conflict.coord.clone(), *choice);
+            let checked = merge.choice(&coord) == choice;
+            choices.add_child(html! {
+                <label class="session-merge-choice">
+                    <input type="radio" name=coord.to_string() checked=checked
+                        onclick=m.link.callback(move |_: ClickEvent| Action::SetMergeChoice(Some(coord.clone()), choice))>
+                    </input>
+                    { label }
+                </label>
+            });
+        }
+        rows.add_child(html! {
+            <tr>
+                <td>{ conflict.coord.to_string() }</td>
+                <td>{ &conflict.mine }</td>
+                <td>{ &conflict.theirs }</td>
+                <td>{ choices }</td>
+            </tr>
+        });
+    }
+    let mut all = VList::new();
+    for (choice, label) in options.iter() {
+        let (choice, value) = (*choice, format! {"All {}", label.to_lowercase()});
+        all.add_child(html! {
+            <input type="button" value=value
+                onclick=m.link.callback(move |_| Action::SetMergeChoice(None, choice)) />
+        });
+    }
+    let summary = if merge.conflicts.is_empty() {
+        "No cells differ between the two sessions".to_string()
+    } else {
+        format! {"{} cells differ between the two sessions. \"Both\" keeps mine and adds their row below the grid.", merge.conflicts.len()}
+    };
+    html! {
+        <div class="hover_popup">
+            <span class="helper"></span>
+            <div class="_popup session-merge">
+                <h3>{ "Merge Sessions" }</h3>
+                <div class="search-help">{ &merge.path }</div>
+                <div class="search-help">{ summary }</div>
+                <div class="session-merge-list">
+                    <table class="stats-table">
+                        <tr><th>{ "Cell" }</th><th>{ "Mine" }</th><th>{ "Theirs" }</th><th>{ "Keep" }</th></tr>
+                        { rows }
+                    </table>
+                </div>
+                <div>{ all }</div>
+                <div>
+                    <input type="button" value="Merge into New Tab" onclick=m.link.callback(|_| Action::ApplySessionMerge) />
+                    <input type="button" value="Cancel" onclick=m.link.callback(|_| Action::CloseSessionMerge) />
+                </div>
+            </div>
+        </div>
+    }
+}
+
+// Argument prompt of a definition with parameters: an argument for each parameter, or a range
+// of cells to read them from in order
+pub fn view_defn_arguments(m: &Model) -> Html {
+    let form = match &m.defn_arguments {
+        Some(form) => form.clone(),
+        None => return html! { <></> },
+    };
+    let mut inputs = VList::new();
+    for (i, parameter) in form.parameters.iter().enumerate() {
+        let edited = form.clone();
+        inputs.add_child(html! {
+            <div>
+                <label>{ format!{"{} ({}) ", parameter.name, parameter.param_type.label()} }</label>
+                <input value=form.values[i].clone() disabled=!form.range.trim().is_empty()
+                    oninput=m.link.callback(move |e: InputData| {
+                        let mut values = edited.values.clone();
+                        values[i] = e.value;
+                        Action::UpdateDefnArguments(DefnArguments { values, ..edited.clone() })
+                    })>
+                </input>
+            </div>
+        });
+    }
+    let range_form = form.clone();
+    let error = match &form.error {
+        Some(e) => html! { <div class="search-error">{ e }</div> },
+        None => html! { <></> },
+    };
+    html! {
+        <div class="hover_popup">
+            <span class="helper"></span>
+            <div class="_popup defn-arguments">
+                <h3>{ format!{"Arguments of {}", form.defn.to_string()} }</h3>
+                { inputs }
+                <label>{ "or read them from " }</label>
+                <input value=form.range.clone() placeholder="root-A1:root-A3"
+                    oninput=m.link.callback(move |e: InputData| Action::UpdateDefnArguments(DefnArguments { range: e.value, ..range_form.clone() }))>
+                </input>
+                { error }
+                <div>
+                    <input type="button" value="Insert" onclick=m.link.callback(|_| Action::ApplyDefnArguments) />
+                    <input type="button" value="Cancel" onclick=m.link.callback(|_| Action::CloseDefnArguments) />
+                </div>
+            </div>
+        </div>
+    }
+}
+
+// Propagate changes dialog: each instance of a definition with the cells that change when it's
+// stamped again from the definition, and a checkbox to leave it as it is
+pub fn view_propagation(m: &Model) -> Html {
+    let propagation = match &m.propagation {
+        Some(propagation) => propagation,
+        None => return html! { <></> },
+    };
+    let mut instance_nodes = VList::new();
+    for (instance, changes) in propagation.instances.iter() {
+        let mut change_rows = VList::new();
+        for change in changes.iter() {
+            change_rows.add_child(html! {
+                <tr>
+                    <td>{ change.coord.to_string() }</td>
+                    <td class="propagation-before">{ change.before.clone().unwrap_or_default() }</td>
+                    <td>{ "→" }</td>
+                    <td class="propagation-after">{ change.after.clone().unwrap_or_default() }</td>
+                </tr>
+            });
+        }
+        let toggled = instance.clone();
+        let status = if changes.is_empty() {
+            html! { <span class="search-help">{ " up to date" }</span> }
+        } else {
+            html! {
+                <input type="checkbox" checked=!propagation.excluded.contains(instance)
+                    onclick=m.link.callback(move |_: ClickEvent| Action::TogglePropagation(toggled.clone()))>
+                </input>
+            }
+        };
+        instance_nodes.add_child(html! {
+            <div class="propagation-instance">
+                { status }
+                <span class="package-name">{ instance.to_string() }</span>
+                <table class="stats-table">{ change_rows }</table>
+            </div>
+        });
+    }
+    let count = propagation.included().len();
+    html! {
+        <div class="hover_popup">
+            <span class="helper"></span>
+            <div class="_popup propagation">
+                <h3>{ format!{"Propagate Changes of {}", propagation.defn.to_string()} }</h3>
+                <div class="search-help">{ "values entered into sub-rules of the same name are kept" }</div>
+                <div class="propagation-instances">{ instance_nodes }</div>
+                <div>
+                    <input type="button" value=format!{"Update {} Instances", count} disabled=count == 0
+                        onclick=m.link.callback(|_| Action::ApplyPropagation) />
+                    <input type="button" value="Cancel" onclick=m.link.callback(|_| Action::ClosePropagation) />
+                </div>
+            </div>
+        </div>
+    }
+}
+
+pub fn view_transclude_dialog(m: &Model) -> Html {
+    let (path, source) = match &m.transclude_form {
+        Some(form) => form.clone(),
+        None => return html! { <></> },
+    };
+    let (path_source, source_path) = (source.clone(), path.clone());
+    html! {
+        <div class="hover_popup">
+            <span class="helper"></span>
+            <div class="_popup transclude-dialog">
+                <h3>{ "Transclude Grid" }</h3>
+                <label>{ "Session file " }</label>
+                <input value=path placeholder="prices.json"
+                    oninput=m.link.callback(move |e: InputData| Action::UpdateTransclude(e.value, path_source.clone()))>
+                </input>
+                <br></br>
+                <label>{ "Grid " }</label>
+                <input value=source placeholder="root"
+                    oninput=m.link.callback(move |e: InputData| Action::UpdateTransclude(source_path.clone(), e.value))>
+                </input>
+                <div>
+                    <input type="button" value="Transclude" onclick=m.link.callback(|_| Action::ApplyTransclude) />
+                    <input type="button" value="Cancel" onclick=m.link.callback(|_| Action::CloseTransclude) />
+                </div>
+            </div>
+        </div>
+    }
+}
+
+pub fn view_import_url_dialog(m: &Model) -> Html {
+    let (url, keep_link) = match &m.import_url_form {
+        Some(form) => form.clone(),
+        None => return html! { <></> },
+    };
+    let link_url = url.clone();
+    html! {
+        <div class="hover_popup">
+            <span class="helper"></span>
+            <div class="_popup import-url-dialog">
+                <h3>{ "Import from URL" }</h3>
+                <label>{ "CSV or Google Sheet " }</label>
+                <input value=url placeholder="https://docs.google.com/spreadsheets/d/..."
+                    oninput=m.link.callback(move |e: InputData| Action::UpdateImportUrl(e.value, keep_link))>
+                </input>
+                <div class="search-help">
+                    { "only links that don't need signing in: sheets shared with anyone with the link, or published to the web" }
+                </div>
+                <input type="checkbox" checked=keep_link
+                    onclick=m.link.callback(move |_ : ClickEvent| Action::UpdateImportUrl(link_url.clone(), !keep_link))>
+                </input>
+                <label>{ "Keep a link to refresh the grid from" }</label>
+                <div>
+                    <input type="button" value="Import" onclick=m.link.callback(|_| Action::ApplyImportUrl) />
+                    <input type="button" value="Cancel" onclick=m.link.callback(|_| Action::CloseImportUrl) />
+                </div>
+            </div>
+        </div>
+    }
+}
+
+pub fn view_shape_dialog(m: &Model) -> Html {
+    let (coord, declared) = match &m.shape_form {
+        Some(form) => form.clone(),
+        None => return html! { <></> },
+    };
+    let described = match Shape::parse(&declared) {
+        _ if declared.trim().is_empty() => "expects nothing".to_string(),
+        Ok(shape) => format! {"expects {}", shape.describe()},
+        Err(e) => e,
+    };
+    html! {
+        <div class="hover_popup">
+            <span class="helper"></span>
+            <div class="_popup shape-dialog">
+                <h3>{ format!{"Expected Shape of {}", coord.to_string()} }</h3>
+                <label>{ "Shape " }</label>
+                <input value=declared placeholder="grid(text, number)"
+                    oninput=m.link.callback(|e: InputData| Action::UpdateShape(e.value))>
+                </input>
+                <div class="shape-description">{ described }</div>
+                <div class="search-help">
+                    { "any, text, number, date or grid, with the shape of each column in parentheses" }
+                </div>
+                <div>
+                    <input type="button" value="Apply" onclick=m.link.callback(|_| Action::ApplyShape) />
+                    <input type="button" value="Cancel" onclick=m.link.callback(|_| Action::CloseShape) />
+                </div>
+            </div>
+        </div>
+    }
+}
+
+pub fn view_condition_dialog(m: &Model) -> Html {
+    let (coord, condition) = match &m.condition_form {
+        Some(form) => form.clone(),
+        None => return html! { <></> },
+    };
+    html! {
+        <div class="hover_popup">
+            <span class="helper"></span>
+            <div class="_popup condition-dialog">
+                <h3>{ format!{"Visibility of {}", coord.to_string()} }</h3>
+                <label>{ "Show when " }</label>
+                <input value=condition placeholder="root-A1"
+                    oninput=m.link.callback(|e: InputData| Action::UpdateCondition(e.value))>
+                </input>
+                <div class="search-help">
+                    { "a formula, or a cell such as a toggle; the cell is hidden while it's false, and always shown when this is empty" }
+                </div>
+                <div>
+                    <input type="button" value="Apply" onclick=m.link.callback(|_| Action::ApplyCondition) />
+                    <input type="button" value="Cancel" onclick=m.link.callback(|_| Action::CloseCondition) />
+                </div>
+            </div>
+        </div>
+    }
+}
+
+pub fn view_bindings_dialog(m: &Model) -> Html {
+    let (coord, bindings) = match &m.bindings_form {
+        Some(form) => form.clone(),
+        None => return html! { <></> },
+    };
+    let field = |label: &str,
+                 placeholder: &str,
+                 source: &Option<String>,
+                 set: fn(&mut StyleBindings, Option<String>)| {
+        let bindings = bindings.clone();
+        html! {
+            <div>
+                <label>{ label }</label>
+                <input value=source.clone().unwrap_or_default() placeholder=placeholder.to_string()
+                    oninput=m.link.callback(move |e: InputData| {
+                        let mut bindings = bindings.clone();
+                        set(&mut bindings, Some(e.value));
+                        Action::UpdateBindings(bindings)
+                    })>
+                </input>
+            </div>
+        }
+    };
+    html! {
+        <div class="hover_popup">
+            <span class="helper"></span>
+            <div class="_popup bindings-dialog">
+                <h3>{ format!{"Style Bindings of {}", coord.to_string()} }</h3>
+                { field("Background", "root-A1", &bindings.background, |b, v| b.background = v) }
+                { field("Font color", "=IF(root-B1 < 0, \"red\", \"black\")", &bindings.font_color, |b, v| b.font_color = v) }
+                { field("Width (px)", "=root-C1 * 10", &bindings.width, |b, v| b.width = v) }
+                <div class="search-help">
+                    { "formulas, or cells such as color pickers; colors are #rrggbb or CSS color names, and empty fields aren't bound" }
+                </div>
+                <div>
+                    <input type="button" value="Apply" onclick=m.link.callback(|_| Action::ApplyBindings) />
+                    <input type="button" value="Cancel" onclick=m.link.callback(|_| Action::CloseBindings) />
+                </div>
+            </div>
+        </div>
+    }
+}
+
+// Script dialog: a macro written in JavaScript, run with its parameters in `args`
+pub fn view_script_dialog(m: &Model) -> Html {
+    let macro_ = match &m.script_form {
+        Some(form) => form.clone(),
+        None => return html! { <></> },
+    };
+    let source = match &macro_.body {
+        MacroBody::Script(source) => source.clone(),
+        MacroBody::Recorded(_) => String::new(),
+    };
+    let (name_form, params_form, source_form) = (macro_.clone(), macro_.clone(), macro_.clone());
+    html! {
+        <div class="hover_popup">
+            <span class="helper"></span>
+            <div class="_popup script-dialog">
+                <h3>{ "Macro Script" }</h3>
+                <div>
+                    <label>{ "Name" }</label>
+                    <input value=macro_.name.clone()
+                        oninput=m.link.callback(move |e: InputData| {
+                            Action::UpdateScript(Macro { name: e.value, ..name_form.clone() })
+                        })>
+                    </input>
+                </div>
+                <div>
+                    <label>{ "Parameters" }</label>
+                    <input value=macro_.params.join(", ") placeholder="rate, amount"
+                        oninput=m.link.callback(move |e: InputData| {
+                            let params = e.value.split(',').map(|p| p.to_string()).collect();
+                            Action::UpdateScript(Macro { params, ..params_form.clone() })
+                        })>
+                    </input>
+                </div>
+                <textarea class="script-source" value=source
+                    oninput=m.link.callback(move |e: InputData| {
+                        Action::UpdateScript(Macro { body: MacroBody::Script(e.value), ..source_form.clone() })
+                    })>
+                </textarea>
+                <div class="search-help">
+                    { "args.rate is the argument of the parameter rate; cell(\"total\") reads a cell by coordinate or name, set(\"root-A1\", text) writes one" }
+                </div>
+                <div>
+                    <input type="button" value="Save" onclick=m.link.callback(|_| Action::SaveScript) />
+                    <input type="button" value="Cancel" onclick=m.link.callback(|_| Action::CloseScript) />
+                </div>
+            </div>
+        </div>
+    }
+}
+
+// Button action dialog: the macro a button cell runs and the cells its arguments are taken from
+pub fn view_button_action_dialog(m: &Model) -> Html {
+    let (coord, action) = match &m.button_form {
+        Some(form) => form.clone(),
+        None => return html! { <></> },
+    };
+    let params = m
+        .get_session()
+        .macros
+        .iter()
+        .find(|macro_| macro_.name == action.macro_name.trim())
+        .map(|macro_| macro_.params.join(", "));
+    let mut macro_options = VList::new();
+    for macro_ in m.get_session().macros.iter() {
+        macro_options.add_child(html! { <option value=macro_.name.clone()></option> });
+    }
+    let (name_form, args_form) = (action.clone(), action.clone());
+    html! {
+        <div class="hover_popup">
+            <span class="helper"></span>
+            <div class="_popup button-action-dialog">
+                <h3>{ format!{"Action of {}", coord.to_string()} }</h3>
+                <div>
+                    <label>{ "Macro" }</label>
+                    <input value=action.macro_name.clone() list="button-action-macros"
+                        oninput=m.link.callback(move |e: InputData| {
+                            Action::UpdateButtonAction(ButtonAction { macro_name: e.value, ..name_form.clone() })
+                        })>
+                    </input>
+                    <datalist id="button-action-macros">{ macro_options }</datalist>
+                </div>
+                <div>
+                    <label>{ "Arguments" }</label>
+                    <input value=action.args.join(", ") placeholder="rate, root-B1"
+                        oninput=m.link.callback(move |e: InputData| {
+                            let args = e.value.split(',').map(|a| a.to_string()).collect();
+                            Action::UpdateButtonAction(ButtonAction { args, ..args_form.clone() })
+                        })>
+                    </input>
+                </div>
+                <div class="search-help">
+                    { match params {
+                        Some(params) if params.is_empty() => "the macro takes no arguments".to_string(),
+                        Some(params) => format!{"cells (coordinates or names) for: {}", params},
+                        None => "an empty macro leaves the button doing nothing".to_string(),
+                    } }
+                </div>
+                <div>
+                    <input type="button" value="Apply" onclick=m.link.callback(|_| Action::ApplyButtonAction) />
+                    <input type="button" value="Cancel" onclick=m.link.callback(|_| Action::CloseButtonAction) />
+                </div>
+            </div>
+        </div>
+    }
+}
+
+pub fn view_nested_defaults_dialog(m: &Model) -> Html {
+    let (grid, defaults) = match &m.nested_defaults_form {
+        Some(form) => form.clone(),
+        None => return html! { <></> },
+    };
+    let field = |label: &str, value: f64, set: fn(&mut GridDefaults, f64)| {
+        html! {
+            <div>
+                <label>{ label }</label>
+                <input type="number" value={value.to_string()}
+                    onchange=m.link.callback(move |e: ChangeData| {
+                        if let ChangeData::Value(v) = e {
+                            if let Ok(v) = v.parse::<f64>() {
+                                let mut defaults = defaults;
+                                set(&mut defaults, v);
+                                return Action::UpdateNestedDefaults(defaults);
+                            }
+                        }
+                        Action::Noop
+                    })>
+                </input>
+            </div>
+        }
+    };
+    html! {
+        <div class="hover_popup">
+            <span class="helper"></span>
+            <div class="_popup nested-defaults-dialog">
+                <h3>{ format!{"Nested Grids in {}", grid.to_string()} }</h3>
+                { field("Rows", defaults.rows as f64, |d, v| d.rows = v.max(1.0) as u32) }
+                { field("Columns", defaults.cols as f64, |d, v| d.cols = v.max(1.0) as u32) }
+                { field("Row height (px)", defaults.row_height, |d, v| d.row_height = v) }
+                { field("Column width (px)", defaults.col_width, |d, v| d.col_width = v) }
+                <div class="search-help">
+                    { "used for the grids nested anywhere in this grid, in place of the ones from preferences" }
+                </div>
+                <div>
+                    <input type="button" value="Apply" onclick=m.link.callback(|_| Action::ApplyNestedDefaults) />
+                    <input type="button" value="Use Preferences" onclick=m.link.callback(|_| Action::ResetNestedDefaults) />
+                    <input type="button" value="Cancel" onclick=m.link.callback(|_| Action::CloseNestedDefaults) />
+                </div>
+            </div>
+        </div>
+    }
+}
+
+pub fn view_rename_dialog(m: &Model) -> Html {
+    let (coord, new_name) = match &m.rename_form {
+        Some(form) => form.clone(),
+        None => return html! { <></> },
+    };
+    let old_name = m.get_session().grammars.get(&coord).map(|g| g.name.clone()).unwrap_or_default();
+    let error = match check_name(&m.meta_suggestions, &coord, &new_name) {
+        Err(e) if new_name.trim() != old_name => html! { <div class="rename-error">{ e }</div> },
+        _ => html! { <></> },
+    };
+    let mut usage_nodes = VList::new();
+    for usage in usages(&m.get_session().grammars, &coord) {
+        usage_nodes.add_child(html! { <li>{ usage.describe() }</li> });
+    }
+    html! {
+        <div class="hover_popup">
+            <span class="helper"></span>
+            <div class="_popup rename-dialog">
+                <h3>{ format!{"Rename {}", old_name} }</h3>
+                <label>{ "New name " }</label>
+                <input value=new_name
+                    oninput=m.link.callback(|e: InputData| Action::UpdateRename(e.value))>
+                </input>
+                { error }
+                <div class="rename-usages-title">{ "Renames:" }</div>
+                <ul class="rename-usages">{ usage_nodes }</ul>
+                <div>
+                    <input type="button" value="Rename" onclick=m.link.callback(|_| Action::ApplyRename) />
+                    <input type="button" value="Cancel" onclick=m.link.callback(|_| Action::CloseRename) />
+                </div>
+            </div>
+        </div>
+    }
+}
+
+// Full-size editor for a single nested grid. The grid is rendered straight out of the
+// session's grammar map, so edits made here show up in the main sheet as well.
+pub fn view_focused_grid(m: &Model) -> Html {
+    let coord = match &m.focused_grid {
+        Some(coord) => coord.clone(),
+        None => return html! { <></> },
+    };
+    html! {
+        <div class="focused-grid-overlay"
+            onkeydown=m.link.callback(|e: KeyDownEvent| {
+                if e.key() == "Escape" { Action::CloseFocusedGrid } else { Action::Noop }
+            })>
+            <div class="focused-grid">
+                <div class="focused-grid-header">
+                    <span>{ coord.to_string() }</span>
+                    <div class="popupCloseButton" onclick=m.link.callback(|_| Action::CloseFocusedGrid)>{"X"}</div>
+                </div>
+                <div class="focused-grid-body">
+                    { view_grammar(m, coord.clone()) }
+                </div>
+            </div>
+        </div>
+    }
+}
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+// Calendar view: the rows of a grid on the days of their date column, clicking a row jumps to it
+pub fn view_calendar(m: &Model) -> Html {
+    let calendar = match &m.calendar {
+        Some(calendar) => calendar.clone(),
+        None => return html! { <></> },
+    };
+    let found = entries(&m.get_session().grammars, &calendar.grid, calendar.date_col, calendar.title_col);
+    let mut day_nodes = VList::new();
+    for weekday in WEEKDAYS.iter() {
+        day_nodes.add_child(html! { <div class="calendar-weekday">{ weekday }</div> });
+    }
+    for day in calendar.days() {
+        let mut entry_nodes = VList::new();
+        for entry in found.iter().filter(|e| e.date == day) {
+            let row = entry.row;
+            entry_nodes.add_child(html! {
+                <div class="calendar-entry" title=entry.title.clone()
+                    onclick=m.link.callback(move |_| Action::JumpFromCalendar(row))>
+                    { entry.title.clone() }
+                </div>
+            });
+        }
+        let class = if day.month == calendar.anchor.month { "calendar-day" } else { "calendar-day other-month" };
+        day_nodes.add_child(html! {
+            <div class=class>
+                <div class="calendar-date">{ day.day }</div>
+                { entry_nodes }
+            </div>
+        });
+    }
+    let (previous, next) = (calendar.step(-1), calendar.step(1));
+    let toggled = CalendarView {
+        mode: match calendar.mode {
+            CalendarMode::Month => CalendarMode::Week,
+            CalendarMode::Week => CalendarMode::Month,
+        },
+        ..calendar.clone()
+    };
+    let (date_view, title_view) = (calendar.clone(), calendar.clone());
+    html! {
+        <div class="focused-grid-overlay"
+            onkeydown=m.link.callback(|e: KeyDownEvent| {
+                if e.key() == "Escape" { Action::CloseCalendar } else { Action::Noop }
+            })>
+            <div class="focused-grid calendar">
+                <div class="focused-grid-header">
+                    <button onclick=m.link.callback(move |_| Action::UpdateCalendar(previous.clone()))>{ "<" }</button>
+                    <span class="calendar-title">
+                        { format!{"{:04}-{:02}", calendar.anchor.year, calendar.anchor.month} }
+                    </span>
+                    <button onclick=m.link.callback(move |_| Action::UpdateCalendar(next.clone()))>{ ">" }</button>
+                    <button onclick=m.link.callback(move |_| Action::UpdateCalendar(toggled.clone()))>
+                        { if calendar.mode == CalendarMode::Month { "Week" } else { "Month" } }
+                    </button>
+                    <label>{ " date column " }</label>
+                    <input class="calendar-column" type="number" min=1 value=calendar.date_col.to_string()
+                        onchange=m.link.callback(move |e: ChangeData| match e {
+                            ChangeData::Value(v) => match v.parse::<u32>() {
+                                Ok(date_col) if date_col > 0 => Action::UpdateCalendar(CalendarView { date_col, ..date_view.clone() }),
+                                _ => Action::Noop,
+                            },
+                            _ => Action::Noop,
+                        })>
+                    </input>
+                    <label>{ " title column " }</label>
+                    <input class="calendar-column" type="number" min=1 value=calendar.title_col.to_string()
+                        onchange=m.link.callback(move |e: ChangeData| match e {
+                            ChangeData::Value(v) => match v.parse::<u32>() {
+                                Ok(title_col) if title_col > 0 => Action::UpdateCalendar(CalendarView { title_col, ..title_view.clone() }),
+                                _ => Action::Noop,
+                            },
+                            _ => Action::Noop,
+                        })>
+                    </input>
+                    <span class="calendar-grid-name">{ format!{" {}", calendar.grid.to_string()} }</span>
+                    <div class="popupCloseButton" onclick=m.link.callback(|_| Action::CloseCalendar)>{"X"}</div>
+                </div>
+                <div class="focused-grid-body calendar-days">
+                    { day_nodes }
+                </div>
+            </div>
+        </div>
+    }
+}
+
+// Timeline view: the rows of a grid as bars between their start and end dates, on a zoomable
+// time axis with a marker for today
+pub fn view_timeline(m: &Model) -> Html {
+    let timeline = match &m.timeline {
+        Some(timeline) => timeline.clone(),
+        None => return html! { <></> },
+    };
+    let found = bars(
+        &m.get_session().grammars,
+        &timeline.grid,
+        timeline.start_col,
+        timeline.end_col,
+        timeline.title_col,
+    );
+    let today = today();
+    let (first, last) = axis_range(&found, today);
+    let day_width = timeline.day_width();
+    let x = |date: &Date| date.days_since(&first) as f64 * day_width;
+    let width = (last.days_since(&first) + 1) as f64 * day_width;
+
+    let mut month_nodes = VList::new();
+    for month in month_starts(first, last) {
+        month_nodes.add_child(html! {
+            <div class="timeline-month" style=format!{"left: {}px", x(&month)}>
+                { format!{"{:04}-{:02}", month.year, month.month} }
+            </div>
+        });
+    }
+    let mut row_nodes = VList::new();
+    for bar in found.iter() {
+        let row = bar.row;
+        let bar_width = (bar.end.days_since(&bar.start) + 1) as f64 * day_width;
+        row_nodes.add_child(html! {
+            <div class="timeline-row">
+                <div class="timeline-bar"
+                    style=format!{"left: {}px; width: {}px", x(&bar.start), bar_width}
+                    title=format!{"{}: {:04}-{:02}-{:02} to {:04}-{:02}-{:02}", bar.title,
+                        bar.start.year, bar.start.month, bar.start.day, bar.end.year, bar.end.month, bar.end.day}
+                    onclick=m.link.callback(move |_| Action::JumpFromTimeline(row))>
+                    { bar.title.clone() }
+                </div>
+            </div>
+        });
+    }
+    let (zoom_in, zoom_out) = (timeline.zoomed(1), timeline.zoomed(-1));
+    let column_input = |label: &'static str, value: u32, set: fn(&TimelineView, u32) -> TimelineView| {
+        let current = timeline.clone();
+        html! {
+            <>
+                <label>{ label }</label>
+                <input class="calendar-column" type="number" min=1 value=value.to_string()
+                    onchange=m.link.callback(move |e: ChangeData| match e {
+                        ChangeData::Value(v) => match v.parse::<u32>() {
+                            Ok(col) if col > 0 => Action::UpdateTimeline(set(&current, col)),
+                            _ => Action::Noop,
+                        },
+                        _ => Action::Noop,
+                    })>
+                </input>
+            </>
+        }
+    };
+    html! {
+        <div class="focused-grid-overlay"
+            onkeydown=m.link.callback(|e: KeyDownEvent| {
+                if e.key() == "Escape" { Action::CloseTimeline } else { Action::Noop }
+            })>
+            <div class="focused-grid timeline">
+                <div class="focused-grid-header">
+                    <button onclick=m.link.callback(move |_| Action::UpdateTimeline(zoom_out.clone()))>{ "-" }</button>
+                    <button onclick=m.link.callback(move |_| Action::UpdateTimeline(zoom_in.clone()))>{ "+" }</button>
+                    { column_input(" start column ", timeline.start_col, |t, start_col| TimelineView { start_col, ..t.clone() }) }
+                    { column_input(" end column ", timeline.end_col, |t, end_col| TimelineView { end_col, ..t.clone() }) }
+                    { column_input(" title column ", timeline.title_col, |t, title_col| TimelineView { title_col, ..t.clone() }) }
+                    <span class="calendar-grid-name">{ format!{" {}", timeline.grid.to_string()} }</span>
+                    <div class="popupCloseButton" onclick=m.link.callback(|_| Action::CloseTimeline)>{"X"}</div>
+                </div>
+                <div class="focused-grid-body">
+                    <div class="timeline-canvas" style=format!{"width: {}px", width}>
+                        <div class="timeline-axis">{ month_nodes }</div>
+                        { row_nodes }
+                        <div class="timeline-today" style=format!{"left: {}px", x(&today)} title="today"></div>
+                    </div>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+pub fn view_side_menu(m: &Model, side_menu: &SideMenu) -> Html {
+    match side_menu.name.deref() {
+        "Home" => {
+            html! {
+                <div class="side-menu-section">
+                    {"THIS IS Home MENU"}
+                </div>
+            }
+        }
+        "File Explorer" => {
+            html! {
+                <div class="side-menu-section">
+                    <h1>
+                        {"File Explorer"}
+                    </h1>
+
+                    <h3>{"load session"}</h3>
+                    <br></br>
+                    <input type="file" onchange=m.link.callback(|value| {
+                        if let ChangeData::Files(files) = value {
+                            if files.len() >= 1 {
+                                if let Some(file) = files.iter().nth(0) {
+                                    return Action::ReadSession(file);
+                                }
+                            } else {
+                                return Action::Alert("Could not load file".to_string());
+                            }
+                        }
+                        Action::Noop
+                    })>
+                    </input>
+                    <h3>{"save session"}</h3>
+                    <br></br>
+                    <input type="text" value=m.get_session().title onchange=m.link.callback(|v| {
+                        if let ChangeData::Value(s) = v {
+                            return Action::SetSessionTitle(s);
+                        }
+                        Action::Noop
+                    })>
+
+                    </input>
+                    <input type="button" value="Save" onclick=m.link.callback(|_| Action::SaveSession())>
+                    </input>
+                    { view_session_metadata(m) }
+                </div>
+            }
+        }
+        "Settings" => {
+            html! {
+                <div class="side-menu-section">
+                    <h1>
+                        {"Settings"}
+                    </h1>
+
+                    { view_session_defaults(m) }
+                    { view_spellcheck_settings(m) }
+                    { view_autosave_settings(m) }
+                    { view_stepping_settings(m) }
+                    { view_entry_settings(m) }
+                    { view_locale_settings(m) }
+                    { view_json_export_settings(m) }
+                    { view_animation_settings(m) }
+                    { view_calc_settings(m) }
+                    { view_hook_settings(m) }
+                    { view_automation_settings(m) }
+
+                    <h3>{"load driver"}</h3>
+                    <br></br>
+                    // drivers will be represented as directories, so we use "webkitdirectory"
+                    // (which isn't standard, but supported in chrome) to get an array of files in
+                    // the dirctory
+                    // https://developer.mozilla.org/en-US/docs/Web/API/HTMLInputElement/webkitdirectory
+                    <input
+                        type="file"
+                        webkitdirectory=""
+                        onchange=m.link.callback(|value| {
+                        if let ChangeData::Files(files) = value {
+                            // `files` will be a flat list with each file's "webkitRelativePath",
+                            // being a full path starting with the directory name.
+                            // ReadDriverFiles will load the .js file with the same name as the
+                            // directory, and upload the rest of the files to be served by electron
+                            let files_list : Vec<File> = files.into_iter().collect();
+                            if files_list.len() >= 1 {
+                                return Action::ReadDriverFiles(files_list);
+                            } else {
+                                return Action::Alert("Could not load Driver".to_string());
+                            }
+                        }
+                        Action::Noop
+                    })>
+                    </input>
+                </div>
+            }
+        }
+        "Search" => view_structural_search(m),
+        "Problems" => view_problems(m),
+        "Statistics" => view_statistics(m),
+        "Packages" => view_packages(m),
+        "Drivers" => view_drivers(m),
+        "Assets" => view_assets(m),
+        "Bookmarks" => view_bookmarks(m),
+        "Trash" => view_trash(m),
+        "Clipboard" => view_clipboard_history(m),
+        "Reminders" => view_reminders(m),
+        "Variables" => view_variables(m),
+        "Macros" => view_macros(m),
+        "Diagnostics" => view_diagnostics(m),
+        "Info" => {
+            html! {
+                <div class="side-menu-section">
+                    {"THIS IS info MENU"}
+                </div>
+            }
+        }
+
+        _ => html! {<> </>},
+    }
+}
+
+// Structural search panel: lists the cells matching a `StructuralQuery`, with jump-to
+// and bulk actions (restyle, convert, delete) over all the results
+fn view_structural_search(m: &Model) -> Html {
+    let (results, error) = match StructuralQuery::parse(&m.structural_query) {
+        Ok(q) if !q.is_empty() => (q.run(&m.get_session().grammars), None),
+        Ok(_) => (vec![], None),
+        Err(e) => (vec![], Some(e)),
+    };
+    let mut result_nodes = VList::new();
+    for coord in results.iter() {
+        let label = m
+            .get_session()
+            .grammars
+            .get(coord)
+            .map(|g| format! {"{}  {}", g.kind_tag().map(|t| t.label()).unwrap_or(""), g.name})
+            .unwrap_or_default();
+        let jump_coord = coord.clone();
+        result_nodes.add_child(html! {
+            <li class="search-result" onclick=m.link.callback(move |_| Action::SetActiveCell(jump_coord.clone()))>
+                <b>{ coord.to_string() }</b>{ format!{"  {}", label} }
+            </li>
+        });
+    }
+    let mut convert_options = VList::new();
+    for tag in KindTag::ALL.iter() {
+        let tag = *tag;
+        let coords = results.clone();
+        convert_options.add_child(html! {
+            <button class="menu-bar-button" onclick=m.link.callback(move |_| Action::BulkConvert(coords.clone(), tag))>
+                { tag.label() }
+            </button>
+        });
+    }
+    let restyle_coords = results.clone();
+    let delete_coords = results.clone();
+    html! {
+        <div class="side-menu-section">
+            <h1>{"Search"}</h1>
+            <input
+                class="structural-query"
+                placeholder="kind:toggle under:root-B2"
+                value=m.structural_query.clone()
+                oninput=m.link.callback(|e: InputData| Action::SetStructuralQuery(e.value))
+                onkeydown=m.link.callback(|e: KeyDownEvent| {
+                    match e.key().deref() {
+                        "Enter" => Action::CommitStructuralQuery,
+                        "ArrowUp" => { e.prevent_default(); Action::BrowseHistory(HistoryKind::Search, true) }
+                        "ArrowDown" => { e.prevent_default(); Action::BrowseHistory(HistoryKind::Search, false) }
+                        _ => Action::Noop,
+                    }
+                })>
+            </input>
+            <div class="search-help">
+                { "terms: kind:<kind> name:<text> style:<attribute>=<value> under:<coordinate> broken" }
+            </div>
+            { error.map_or(html! { <></> }, |e| html! { <div class="search-error">{ e }</div> }) }
+            <h3>{ format!{"{} results", results.len()} }</h3>
+            <div>
+                { "Restyle: " }
+                <input
+                    placeholder="font_weight=600"
+                    size="15"
+                    onchange=m.link.callback(move |e: ChangeData| {
+                        if let ChangeData::Value(v) = e {
+                            let mut parts = v.splitn(2, '=');
+                            if let (Some(attr), Some(value)) = (parts.next(), parts.next()) {
+                                return Action::BulkRestyle(restyle_coords.clone(), attr.trim().to_string(), value.trim().to_string());
+                            }
+                            return Action::Alert(format!{"restyle expects <attribute>=<value>, got: {}", v});
+                        }
+                        Action::Noop
+                    })>
+                </input>
+            </div>
+            <div>{ "Convert to: " }{ convert_options }</div>
+            <button class="menu-bar-button" onclick=m.link.callback(move |_| Action::BulkDelete(delete_coords.clone()))>
+                { "Delete all" }
+            </button>
+            <ul class="search-results">
+                { result_nodes }
+            </ul>
+        </div>
+    }
+}
+
+// Statistics panel: descriptive statistics of the numbers in the selection (or the grid of the
+// active cell), updated as cells change
+fn view_statistics(m: &Model) -> Html {
+    let region = match m.export_region() {
+        Some(region) => region,
+        None => {
+            return html! {
+                <div class="side-menu-section">
+                    <h1>{"Statistics"}</h1>
+                    <div>{ "Select a range of cells" }</div>
+                    { view_grammar_usage(m) }
+                </div>
+            }
+        }
+    };
+    let numbers = numbers_in(&m.get_session().grammars, &m.values, &region);
+    let range = format! {
+        "{}:{}",
+        region.cell(region.rows.0, region.cols.0).to_string(),
+        region.cell(region.rows.1, region.cols.1).to_string()
+    };
+    let body = match describe(&numbers) {
+        None => html! { <div>{ "No numbers in the selection" }</div> },
+        Some(stats) => {
+            let rows = vec![
+                ("count", stats.count.to_string()),
+                ("sum", format_stat(stats.sum)),
+                ("mean", format_stat(stats.mean)),
+                ("median", format_stat(stats.median)),
+                ("std. dev.", format_stat(stats.stddev)),
+                ("min", format_stat(stats.min)),
+                ("max", format_stat(stats.max)),
+            ];
+            let mut table = VList::new();
+            for (label, value) in rows {
+                table.add_child(html! {
+                    <tr><td>{ label }</td><td class="stat-value">{ value }</td></tr>
+                });
+            }
+            html! {
+                <>
+                    <table class="stats-table">{ table }</table>
+                    <div class="stats-sparkline" title="distribution from min to max">
+                        { sparkline(&stats.histogram) }
+                    </div>
+                </>
+            }
+        }
+    };
+    html! {
+        <div class="side-menu-section">
+            <h1>{"Statistics"}</h1>
+            <h3>{ range }</h3>
+            { body }
+            { view_grammar_usage(m) }
+        </div>
+    }
+}
+
+// How much each meta grammar is used, with the unused ones flagged so the meta sheet can be pruned
+fn view_grammar_usage(m: &Model) -> Html {
+    let report = usage_report(&m.get_session().grammars, &m.meta_suggestions, &m.preferences.suggestion_usage);
+    let unused = report.iter().filter(|u| u.is_unused()).count();
+    let mut rows = VList::new();
+    for usage in report {
+        let class = if usage.is_unused() { "grammar-usage unused" } else { "grammar-usage" };
+        let jump_coord = usage.coord.clone();
+        rows.add_child(html! {
+            <tr class=class onclick=m.link.callback(move |_| Action::SetActiveCell(jump_coord.clone()))>
+                <td>{ usage.name.clone() }</td>
+                <td class="stat-value" title="grammars in this session">{ usage.instances }</td>
+                <td class="stat-value" title="times completed">{ usage.completions }</td>
+            </tr>
+        });
+    }
+    html! {
+        <>
+            <h3>{ "Grammar Usage" }</h3>
+            <div class="search-help">{ format!{"{} unused", unused} }</div>
+            <table class="stats-table">
+                <tr><th>{ "grammar" }</th><th>{ "in session" }</th><th>{ "completed" }</th></tr>
+                { rows }
+            </table>
+        </>
+    }
+}
+
+// Diagnostics panel: the size and shape of the session, how much the undo history holds on to,
+// and how long the last action took to apply and render
+fn view_diagnostics(m: &Model) -> Html {
+    let diagnostics = diagnose(&m.get_session().grammars, &m.history);
+    let rows = vec![
+        ("grammars", diagnostics.grammar_count.to_string()),
+        ("grammar map", format_bytes(diagnostics.grammar_map_bytes)),
+        ("undo steps", diagnostics.undo_steps.to_string()),
+        ("redo steps", diagnostics.redo_steps.to_string()),
+        ("undo history", format_bytes(m.history_bytes())),
+        ("last update", format! {"{:.1} ms", m.last_update_ms}),
+        ("last render", format! {"{:.1} ms", m.last_view_ms.get()}),
+    ];
+    let mut table = VList::new();
+    for (label, value) in rows {
+        table.add_child(html! {
+            <tr><td>{ label }</td><td class="stat-value">{ value }</td></tr>
+        });
+    }
+    let most = diagnostics.depths.values().cloned().max().unwrap_or(1);
+    let mut depth_rows = VList::new();
+    for (depth, count) in diagnostics.depths.iter() {
+        let width = format! {"width: {}%;", count * 100 / most};
+        depth_rows.add_child(html! {
+            <tr>
+                <td>{ depth }</td>
+                <td class="stat-value">{ count }</td>
+                <td class="depth-bar-cell"><div class="depth-bar" style=width></div></td>
+            </tr>
+        });
+    }
+    html! {
+        <div class="side-menu-section diagnostics">
+            <h1>{"Diagnostics"}</h1>
+            <table class="stats-table">{ table }</table>
+            <h3>{ "Coordinate Depth" }</h3>
+            <table class="stats-table">
+                <tr><th>{ "depth" }</th><th>{ "grammars" }</th><th></th></tr>
+                { depth_rows }
+            </table>
+            { view_orphans(m) }
+            { view_consistency(m) }
+        </div>
+    }
+}
+
+// What the consistency checker finds in the session, with a button to repair it
+fn view_consistency(m: &Model) -> Html {
+    let found = check(&m.get_session().grammars, &m.row_heights, &m.col_widths);
+    let mut found_nodes = VList::new();
+    for inconsistency in found.iter() {
+        let jump_coord = inconsistency.coord();
+        found_nodes.add_child(html! {
+            <li class="problem problem-warning">
+                <span class="problem-location" onclick=m.link.callback(move |_| Action::SetActiveCell(jump_coord.clone()))>
+                    { inconsistency.coord().to_string() }
+                </span>
+                { format!{"  {}", inconsistency.describe()} }
+            </li>
+        });
+    }
+    html! {
+        <>
+            <h3>{ "Consistency" }</h3>
+            <div class="search-help">
+                { if found.is_empty() { "no inconsistencies".to_string() } else { format!{"{} inconsistencies", found.len()} } }
+            </div>
+            { if found.is_empty() { html! { <></> } } else { html! {
+                <button class="menu-bar-button" onclick=m.link.callback(|_| Action::RepairIntegrity)>{ "Repair" }</button>
+            } } }
+            <ul class="problems">{ found_nodes }</ul>
+        </>
+    }
+}
+
+// Grammars no grid refers to anymore, with a button to prune them
+fn view_orphans(m: &Model) -> Html {
+    let orphaned = orphans(&m.get_session().grammars);
+    if orphaned.is_empty() {
+        return html! {
+            <>
+                <h3>{ "Orphaned Grammars" }</h3>
+                <div class="search-help">{ "none" }</div>
+            </>
+        };
+    }
+    let mut orphan_nodes = VList::new();
+    for coord in orphaned.iter() {
+        orphan_nodes.add_child(html! { <li>{ coord.to_string() }</li> });
+    }
+    html! {
+        <>
+            <h3>{ "Orphaned Grammars" }</h3>
+            <div class="search-help">
+                { format!{"{} grammars aren't reachable from any grid, but are still saved", orphaned.len()} }
+            </div>
+            <button class="menu-bar-button" onclick=m.link.callback(|_| Action::PruneOrphans)>{ "Prune" }</button>
+            <ul class="orphans">{ orphan_nodes }</ul>
+        </>
+    }
+}
+
+fn format_stat(n: f64) -> String {
+    // at most 4 decimals, without trailing zeros
+    let rounded = (n * 10000.0).round() / 10000.0;
+    rounded.to_string()
+}
+
+// Packages panel: the registries grammar packs are installed from, what they offer, and the
+// packs installed in the session, with the newer versions available
+fn view_packages(m: &Model) -> Html {
+    let session = m.get_session();
+    let mut registry_nodes = VList::new();
+    for registry in m.preferences.package_registries.iter() {
+        let prefs = m.preferences.clone();
+        let removed = registry.clone();
+        let listings = match m.registries.get(registry) {
+            None => html! { <div class="search-help">{ "not loaded, refresh to load" }</div> },
+            Some(Err(e)) => html! { <div class="search-error">{ e }</div> },
+            Some(Ok(index)) => {
+                let mut listing_nodes = VList::new();
+                for listing in index.packs.iter() {
+                    let installed = session.packages.iter().find(|p| p.name == listing.name);
+                    let (install_registry, install_listing) = (registry.clone(), listing.clone());
+                    let button = match installed {
+                        Some(pack) if pack.version == listing.version => html! { <span>{ "installed" }</span> },
+                        _ => html! {
+                            <button class="menu-bar-button"
+                                onclick=m.link.callback(move |_| Action::InstallPack(install_registry.clone(), install_listing.clone()))>
+                                { if installed.is_some() { "Switch" } else { "Install" } }
+                            </button>
+                        },
+                    };
+                    listing_nodes.add_child(html! {
+                        <li class="package" title=listing.description.clone()>
+                            <span class="package-name">{ &listing.name }</span>
+                            { format!{"  {}  ", listing.version} }
+                            { button }
+                        </li>
+                    });
+                }
+                html! { <ul class="packages">{ listing_nodes }</ul> }
+            }
+        };
+        registry_nodes.add_child(html! {
+            <div class="package-registry">
+                <h3>
+                    { registry }
+                    <button class="menu-bar-button" onclick=m.link.callback(move |_| {
+                        let mut new_prefs = prefs.clone();
+                        new_prefs.package_registries.retain(|r| *r != removed);
+                        Action::SetPreferences(new_prefs)
+                    })>{ "x" }</button>
+                </h3>
+                { listings }
+            </div>
+        });
+    }
+    let mut installed_nodes = VList::new();
+    for pack in session.packages.iter() {
+        let update = m
+            .registries
+            .get(&pack.registry)
+            .and_then(|index| index.as_ref().ok())
+            .and_then(|index| update_for(pack, index));
+        let update_button = match update {
+            Some(listing) => {
+                let (registry, listing) = (pack.registry.clone(), listing.clone());
+                html! {
+                    <button class="menu-bar-button"
+                        onclick=m.link.callback(move |_| Action::InstallPack(registry.clone(), listing.clone()))>
+                        { format!{"Update to {}", listing.version} }
+                    </button>
+                }
+            }
+            None => html! { <></> },
+        };
+        let name = pack.name.clone();
+        installed_nodes.add_child(html! {
+            <li class="package">
+                <span class="package-name">{ &pack.name }</span>
+                { format!{"  {}  ({} grammars)  ", pack.version, pack.coords.len()} }
+                { update_button }
+                <button class="menu-bar-button" onclick=m.link.callback(move |_| Action::UninstallPack(name.clone()))>
+                    { "Uninstall" }
+                </button>
+            </li>
+        });
+    }
+    let prefs = m.preferences.clone();
+    html! {
+        <div class="side-menu-section">
+            <h1>{"Packages"}</h1>
+            <input
+                placeholder="add a registry folder or URL..."
+                onchange=m.link.callback(move |e: ChangeData| {
+                    if let ChangeData::Value(registry) = e {
+                        let registry = registry.trim().to_string();
+                        if !registry.is_empty() && !prefs.package_registries.contains(&registry) {
+                            let mut new_prefs = prefs.clone();
+                            new_prefs.package_registries.push(registry);
+                            return Action::SetPreferences(new_prefs);
+                        }
+                    }
+                    Action::Noop
+                })>
+            </input>
+            <button class="menu-bar-button" onclick=m.link.callback(|_| Action::RefreshRegistries)>
+                { "Refresh" }
+            </button>
+            <button class="menu-bar-button" onclick=m.link.callback(|_| Action::OpenMetaImport)>
+                { "Import from Session..." }
+            </button>
+            { registry_nodes }
+            <h3>{ "Installed" }</h3>
+            <ul class="packages">{ installed_nodes }</ul>
+        </div>
+    }
+}
+
+// The loaded drivers, the capabilities they declared (and whether they were granted), and the
+// log of what they did through the bridge
+fn view_drivers(m: &Model) -> Html {
+    let mut driver_nodes = VList::new();
+    for driver in m.drivers.iter() {
+        driver_nodes.add_child(view_driver(m, driver));
+    }
+    html! {
+        <div class="side-menu-section">
+            <h1>{ "Drivers" }</h1>
+            {
+                if m.drivers.is_empty() {
+                    html! { <div class="search-help">{ "No drivers are loaded, load one from Settings." }</div> }
+                } else {
+                    html! { <>{ driver_nodes }</> }
+                }
+            }
+        </div>
+    }
+}
+
+fn view_driver(m: &Model, driver: &Driver) -> Html {
+    let mut capability_nodes = VList::new();
+    for capability in driver.manifest.capabilities.iter() {
+        let grant = m
+            .preferences
+            .driver_grants
+            .iter()
+            .find(|g| g.driver == driver.name && g.capability == *capability);
+        let (name, capability) = (driver.name.clone(), *capability);
+        capability_nodes.add_child(html! {
+            <li>
+                { format!{"{}: ", capability.describe()} }
+                {
+                    match grant {
+                        None => html! { <span>{ "not asked yet" }</span> },
+                        Some(grant) => html! {
+                            <>
+                                <span>{ if grant.granted { "granted " } else { "denied " } }</span>
+                                <button class="menu-bar-button"
+                                    onclick=m.link.callback(move |_| Action::RevokeDriverGrant(name.clone(), capability))>
+                                    { "Reset" }
+                                </button>
+                            </>
+                        },
+                    }
+                }
+            </li>
+        });
+    }
+    let mut log_nodes = VList::new();
+    for entry in driver.log.iter().rev() {
+        let (class, outcome) = match &entry.blocked {
+            Some(reason) => ("driver-log-entry blocked", format! {"blocked: {}", reason}),
+            None => ("driver-log-entry", "allowed".to_string()),
+        };
+        log_nodes.add_child(html! {
+            <li class=class title=entry.time.clone()>
+                { format!{"{}({}) {}", entry.op, entry.detail, outcome} }
+            </li>
+        });
+    }
+    let mut console_nodes = VList::new();
+    for line in driver.console.iter() {
+        let class = match line.level {
+            ConsoleLevel::Log => "driver-console-line",
+            ConsoleLevel::Warn => "driver-console-line warn",
+            ConsoleLevel::Error => "driver-console-line error",
+        };
+        console_nodes.add_child(html! {
+            <div class=class title=line.time.clone()>{ &line.text }</div>
+        });
+    }
+    let (status_class, status) = match &driver.status {
+        LoadStatus::Loading => ("driver-status loading", "loading".to_string()),
+        LoadStatus::Loaded => ("driver-status loaded", "loaded".to_string()),
+        LoadStatus::Failed(_) => ("driver-status failed", "failed to load".to_string()),
+    };
+    let upload = match m.uploads.iter().find(|b| b.driver == driver.name) {
+        Some(batch) if !batch.is_finished() => {
+            let (finished, total) = batch.progress();
+            let mut file_nodes = VList::new();
+            for (file, state) in batch.files.iter() {
+                let state = match state {
+                    UploadState::Queued => "queued".to_string(),
+                    UploadState::Uploading => "uploading".to_string(),
+                    UploadState::Uploaded => "uploaded".to_string(),
+                    UploadState::Failed(e) => format! {"failed: {}", e},
+                };
+                file_nodes.add_child(html! { <li>{ format!{"{}: {}", file, state} }</li> });
+            }
+            html! {
+                <div class="driver-upload">
+                    <progress value=finished.to_string() max=total.to_string()></progress>
+                    { format!{" {}/{} files uploaded", finished, total} }
+                    <ul class="driver-log">{ file_nodes }</ul>
+                </div>
+            }
+        }
+        _ => html! { <></> },
+    };
+    let name = driver.name.clone();
+    html! {
+        <div class="driver">
+            <h3>
+                { &driver.name }
+                <span class=status_class>{ status }</span>
+            </h3>
+            { upload }
+            {
+                if driver.manifest.capabilities.is_empty() {
+                    html! { <div class="search-help">{ "declares no capabilities" }</div> }
+                } else {
+                    html! { <ul class="driver-capabilities">{ capability_nodes }</ul> }
+                }
+            }
+            <ul class="driver-log">{ log_nodes }</ul>
+            <div class="driver-console-header">
+                { "Console" }
+                <button class="menu-bar-button" onclick=m.link.callback(move |_| Action::ClearDriverConsole(name.clone()))>
+                    { "Clear" }
+                </button>
+            </div>
+            <div class="driver-console">{ console_nodes }</div>
+        </div>
+    }
+}
+
+// Assets panel: the images and files of the session with how many cells refer to them.
+// Assets nothing refers to are dropped when the session is saved
+fn view_assets(m: &Model) -> Html {
+    let counts = reference_counts(&m.get_session().grammars);
+    let mut asset_nodes = VList::new();
+    for asset in m.get_session().assets.iter() {
+        let count = counts.get(&asset.name).cloned().unwrap_or(0);
+        let name = asset.name.clone();
+        asset_nodes.add_child(html! {
+            <li class=format!{"asset{}", if count == 0 { " asset-unused" } else { "" }}>
+                { if asset.is_image() {
+                    html! { <img class="asset-thumbnail" src=asset.data_url() /> }
+                } else {
+                    html! { <></> }
+                } }
+                <span class="asset-name">{ &asset.name }</span>
+                { format!{"  {} · {} refs  ", asset.mime, count} }
+                <button class="menu-bar-button" onclick=m.link.callback(move |_| Action::InsertAssetReference(name.clone()))>
+                    { "Insert" }
+                </button>
+            </li>
+        });
+    }
+    html! {
+        <div class="side-menu-section">
+            <h1>{"Assets"}</h1>
+            <input type="file" onchange=m.link.callback(|value| {
+                if let ChangeData::Files(files) = value {
+                    if let Some(file) = files.iter().nth(0) {
+                        return Action::ReadAsset(file);
+                    }
+                }
+                Action::Noop
+            })>
+            </input>
+            <div class="search-help">{ "cells refer to assets as asset:<name>" }</div>
+            <ul class="assets">
+                { asset_nodes }
+            </ul>
+        </div>
+    }
+}
+
+// Bookmarks panel: named viewports of the session, recalled with a click
+fn view_bookmarks(m: &Model) -> Html {
+    let mut bookmark_nodes = VList::new();
+    for (index, bookmark) in m.get_session().bookmarks.iter().enumerate() {
+        let details = match &bookmark.range {
+            Some((first, last)) => format! {"{}  {}:{}", bookmark.view_root.to_string(), first.to_string(), last.to_string()},
+            None => bookmark.view_root.to_string(),
+        };
+        bookmark_nodes.add_child(html! {
+            <li class="bookmark">
+                <span class="bookmark-name" onclick=m.link.callback(move |_| Action::RecallBookmark(index))>
+                    { &bookmark.name }
+                </span>
+                { format!{"  {}  ", details} }
+                <button class="menu-bar-button" onclick=m.link.callback(move |_| Action::RemoveBookmark(index))>
+                    { "x" }
+                </button>
+            </li>
+        });
+    }
+    html! {
+        <div class="side-menu-section">
+            <h1>{"Bookmarks"}</h1>
+            <input
+                placeholder="bookmark this view as..."
+                onchange=m.link.callback(|e: ChangeData| {
+                    if let ChangeData::Value(name) = e {
+                        if !name.trim().is_empty() {
+                            return Action::AddBookmark(name.trim().to_string());
+                        }
+                    }
+                    Action::Noop
+                })>
+            </input>
+            <ul class="bookmarks">
+                { bookmark_nodes }
+            </ul>
+        </div>
+    }
+}
+
+// Trash panel: the rows, columns & nested grids deleted from the session since it was opened,
+// newest first, each of which can be put back where it was or dropped for good
+fn view_trash(m: &Model) -> Html {
+    let entries = m
+        .trash
+        .get(&m.current_session_index)
+        .map_or(&[][..], |t| t.as_slice());
+    let mut entry_nodes = VList::new();
+    for (index, entry) in entries.iter().enumerate() {
+        let deleted_at = entry.deleted_at.replace('T', " ");
+        entry_nodes.add_child(html! {
+            <li class="trash-entry">
+                <span class="trash-label">{ &entry.label }</span>
+                <span class="trash-time">{ format!{"  {}", deleted_at.get(..19).unwrap_or(&deleted_at)} }</span>
+                <div class="trash-cells">{ entry.describe() }</div>
+                <button class="menu-bar-button" onclick=m.link.callback(move |_| Action::RestoreTrash(index))>
+                    { "Restore" }
+                </button>
+                <button class="menu-bar-button" onclick=m.link.callback(move |_| Action::DiscardTrash(index))>
+                    { "Discard" }
+                </button>
+            </li>
+        });
+    }
+    html! {
+        <div class="side-menu-section">
+            <h1>{"Trash"}</h1>
+            { if entries.is_empty() {
+                html! { <div class="search-help">{ "deleted rows, columns and nested grids show up here" }</div> }
+            } else {
+                html! {
+                    <button class="menu-bar-button" onclick=m.link.callback(|_| Action::EmptyTrash)>
+                        { "Empty Trash" }
+                    </button>
+                }
+            } }
+            <ul class="trash-entries">
+                { entry_nodes }
+            </ul>
+        </div>
+    }
+}
+
+// Clipboard panel: the cells copied last, newest first, each pasted at the active cell as it
+// was copied
+fn view_clipboard_history(m: &Model) -> Html {
+    let mut entry_nodes = VList::new();
+    for (index, entry) in m.clipboard_history.iter().enumerate() {
+        let copied_at = entry.copied_at.replace('T', " ");
+        entry_nodes.add_child(html! {
+            <li class="clip-entry">
+                <span class="clip-label">{ &entry.label }</span>
+                <span class="clip-time">{ format!{"  {}", copied_at.get(..19).unwrap_or(&copied_at)} }</span>
+                <div class="clip-cells">{ entry.describe() }</div>
+                <button class="menu-bar-button" onclick=m.link.callback(move |_| Action::PasteClip(index))>
+                    { "Paste" }
+                </button>
+                <button class="menu-bar-button" onclick=m.link.callback(move |_| Action::DiscardClip(index))>
+                    { "Discard" }
+                </button>
+            </li>
+        });
+    }
+    html! {
+        <div class="side-menu-section">
+            <h1>{"Clipboard"}</h1>
+            { if m.clipboard_history.is_empty() {
+                html! { <div class="search-help">{ "copied cells show up here, to paste again with everything nested in them" }</div> }
+            } else {
+                html! {
+                    <button class="menu-bar-button" onclick=m.link.callback(|_| Action::ClearClipboardHistory)>
+                        { "Clear" }
+                    </button>
+                }
+            } }
+            <ul class="clip-entries">
+                { entry_nodes }
+            </ul>
+        </div>
+    }
+}
+
+// Reminders panel: the reminder cells of the session, pending ones first, and whether they fire
+fn view_reminders(m: &Model) -> Html {
+    let session = m.get_session();
+    let (pending, fired): (Vec<Reminder>, Vec<Reminder>) =
+        reminders(&session.grammars).into_iter().partition(|r| !r.fired);
+    let mut reminder_nodes = VList::new();
+    for reminder in pending.iter().chain(fired.iter()) {
+        let jump_coord = reminder.coord.clone();
+        let trigger = match &reminder.trigger {
+            ReminderTrigger::At(time) if time.is_empty() => "no time set".to_string(),
+            ReminderTrigger::At(time) => format! {"at {}", time.replacen('T', " ", 1)},
+            ReminderTrigger::When(condition) if condition.is_empty() => "no condition set".to_string(),
+            ReminderTrigger::When(condition) => format! {"when {}", condition},
+        };
+        reminder_nodes.add_child(html! {
+            <li class={ if reminder.fired { "reminder-entry fired" } else { "reminder-entry" } }
+                onclick=m.link.callback(move |_| Action::SetActiveCell(jump_coord.clone()))>
+                <span class="reminder-label">{ &reminder.message }</span>
+                <div class="reminder-when">
+                    { format!{"{}  {}{}", reminder.coord.to_string(), trigger, if reminder.fired { "  (fired)" } else { "" }} }
+                </div>
+            </li>
+        });
+    }
+    html! {
+        <div class="side-menu-section">
+            <h1>{"Reminders"}</h1>
+            <label>
+                <input type="checkbox" checked={!session.mute_reminders}
+                    onclick=m.link.callback(|_| Action::ToggleMuteReminders)>
+                </input>
+                { "Notify me of this session's reminders" }
+            </label>
+            { if pending.is_empty() && fired.is_empty() {
+                html! { <div class="search-help">{ "reminder cells show up here, add one from the meta sheet" }</div> }
+            } else {
+                html! { <></> }
+            } }
+            <ul class="reminder-entries">
+                { reminder_nodes }
+            </ul>
+        </div>
+    }
+}
+
+// Variables panel: the variables of the session, which formulas, visibility conditions and
+// style bindings read as `$name`, with the number of formulas reading each one
+fn view_variables(m: &Model) -> Html {
+    let variables = &m.get_session().variables;
+    let formulas = formula_cells(&m.get_session().grammars);
+    let mut variable_nodes = VList::new();
+    for (index, variable) in variables.iter().enumerate() {
+        let problem = if !is_valid_name(&variable.name) {
+            "names are made of letters, digits and _, and don't start with a digit"
+        } else if variables[..index].iter().any(|v| v.name == variable.name) {
+            "another variable has this name"
+        } else {
+            ""
+        };
+        let kind = match value_of(&variable.value) {
+            FormulaValue::Number(_) => "number",
+            FormulaValue::Bool(_) => "TRUE/FALSE",
+            _ => "text",
+        };
+        let readers = formulas
+            .values()
+            .filter(|formula| uses(formula).contains(&variable.name))
+            .count();
+        variable_nodes.add_child(html! {
+            <li class="variable-entry">
+                <span class="variable-sigil">{ "$" }</span>
+                <input class={ if problem.is_empty() { "variable-name" } else { "variable-name invalid" } }
+                    value=variable.name.clone() title=problem
+                    onchange=m.link.callback(move |e: ChangeData| match e {
+                        ChangeData::Value(name) => Action::RenameVariable(index, name),
+                        _ => Action::Noop,
+                    })>
+                </input>
+                <input class="variable-value" value=variable.value.clone()
+                    onchange=m.link.callback(move |e: ChangeData| match e {
+                        ChangeData::Value(value) => Action::SetVariable(index, value),
+                        _ => Action::Noop,
+                    })>
+                </input>
+                <button class="menu-bar-button" onclick=m.link.callback(move |_| Action::RemoveVariable(index))>
+                    { "Remove" }
+                </button>
+                <div class="variable-usage">
+                    { format!{"{}, read by {} formula{}", kind, readers, if readers == 1 { "" } else { "s" }} }
+                </div>
+            </li>
+        });
+    }
+    html! {
+        <div class="side-menu-section">
+            <h1>{"Variables"}</h1>
+            <button class="menu-bar-button" onclick=m.link.callback(|_| Action::AddVariable)>
+                { "Add Variable" }
+            </button>
+            { if variables.is_empty() {
+                html! { <div class="search-help">{ "constants formulas read as $name, e.g. =root-A1 * $tax_rate" }</div> }
+            } else {
+                html! { <></> }
+            } }
+            <ul class="variable-entries">
+                { variable_nodes }
+            </ul>
+        </div>
+    }
+}
+
+// Macros panel: the macros of the current session, recorded from actions or written as scripts
+fn view_macros(m: &Model) -> Html {
+    let recording = m
+        .macro_recording
+        .as_ref()
+        .filter(|(index, _, _)| *index == m.current_session_index);
+    let recorder = match recording {
+        Some((_, name, steps)) => html! {
+            <div class="macro-recorder">
+                <span class="repro-progress">{ format!{"Recording, {} steps", steps.len()} }</span>
+                <input value=name.clone() placeholder="macro name"
+                    oninput=m.link.callback(|e: InputData| Action::UpdateMacroRecordingName(e.value))>
+                </input>
+                <button class="menu-bar-button" onclick=m.link.callback(|_| Action::SaveMacroRecording)>
+                    { "Save" }
+                </button>
+                <button class="menu-bar-button" onclick=m.link.callback(|_| Action::CancelMacroRecording)>
+                    { "Cancel" }
+                </button>
+            </div>
+        },
+        None => html! {
+            <div class="macro-recorder">
+                <button class="menu-bar-button" onclick=m.link.callback(|_| Action::StartMacroRecording)>
+                    { "Record Macro" }
+                </button>
+                <button class="menu-bar-button" onclick=m.link.callback(|_| Action::OpenScript(None))>
+                    { "New Script" }
+                </button>
+            </div>
+        },
+    };
+    let macros = &m.get_session().macros;
+    let mut macro_nodes = VList::new();
+    for macro_ in macros.iter() {
+        let remove_name = macro_.name.clone();
+        let edit_name = macro_.name.clone();
+        let edit = match macro_.body {
+            MacroBody::Script(_) => html! {
+                <button class="menu-bar-button" onclick=m.link.callback(move |_| Action::OpenScript(Some(edit_name.clone())))>
+                    { "Edit" }
+                </button>
+            },
+            MacroBody::Recorded(_) => html! { <></> },
+        };
+        macro_nodes.add_child(html! {
+            <li class="macro-entry">
+                <span class="macro-name">{ &macro_.name }</span>
+                <span class="macro-body">{ format!{"  {}", macro_.describe()} }</span>
+                { if macro_.params.is_empty() {
+                    html! { <></> }
+                } else {
+                    html! { <div class="macro-params">{ format!{"({})", macro_.params.join(", ")} }</div> }
+                } }
+                { edit }
+                <button class="menu-bar-button" onclick=m.link.callback(move |_| Action::RemoveMacro(remove_name.clone()))>
+                    { "Remove" }
+                </button>
+            </li>
+        });
+    }
+    html! {
+        <div class="side-menu-section">
+            <h1>{"Macros"}</h1>
+            { recorder }
+            { if macros.is_empty() {
+                html! { <div class="search-help">{ "record actions or write a script, then bind a button cell to it with Button Action..." }</div> }
+            } else {
+                html! { <></> }
+            } }
+            <ul class="macro-entries">
+                { macro_nodes }
+            </ul>
+        </div>
+    }
+}
+
+// Problems panel: references whose targets no longer exist, with quick fixes to clear them
+// or point them at another cell
+fn view_problems(m: &Model) -> Html {
+    let problems = audit(&m.get_session().grammars);
+    let mut problem_nodes = VList::new();
+    for problem in problems.iter() {
+        let jump_coord = problem.coord.clone();
+        let clear_coord = problem.coord.clone();
+        let retarget_coord = problem.coord.clone();
+        let is_lookup = match m.get_session().grammars.get(&problem.coord) {
+            Some(Grammar { kind: Kind::Lookup(_, _), .. }) => true,
+            _ => false,
+        };
+        let retarget = if is_lookup {
+            html! {
+                <input
+                    placeholder="retarget to..."
+                    size="12"
+                    onchange=m.link.callback(move |e: ChangeData| {
+                        if let ChangeData::Value(value) = e {
+                            if let Some(target) = Coordinate::parse(value.trim()) {
+                                return Action::RetargetReference(retarget_coord.clone(), target);
+                            }
+                            return Action::Alert(format!{"invalid coordinate: {}", value});
+                        }
+                        Action::Noop
+                    })>
+                </input>
+            }
+        } else {
+            html! { <></> }
+        };
+        problem_nodes.add_child(html! {
+            <li class=format!{"problem problem-{}", match problem.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            }}>
+                <span class="problem-location" onclick=m.link.callback(move |_| Action::SetActiveCell(jump_coord.clone()))>
+                    { problem.coord.to_string() }
+                </span>
+                { format!{"  {}  ", problem.message} }
+                <button class="menu-bar-button" onclick=m.link.callback(move |_| Action::ClearReference(clear_coord.clone()))>
+                    { "Clear" }
+                </button>
+                { retarget }
+            </li>
+        });
+    }
+    html! {
+        <div class="side-menu-section">
+            <h1>{"Problems"}</h1>
+            <h3>{ if problems.is_empty() { "no broken references".to_string() } else { format!{"{} problems", problems.len()} } }</h3>
+            <ul class="problems">
+                { problem_nodes }
+            </ul>
+        </div>
+    }
+}
+
+// Author & description of the current session, along with when it was created and last saved
+fn view_session_metadata(m: &Model) -> Html {
+    let metadata = m.get_session().metadata.clone();
+    let author_metadata = metadata.clone();
+    let description_metadata = metadata.clone();
+    html! {
+        <div class="session-metadata">
+            <h3>{"session info"}</h3>
+            <label>{ "Author" }</label>
+            <br></br>
+            <input type="text" value=metadata.author.clone() onchange=m.link.callback(move |v| {
+                if let ChangeData::Value(s) = v {
+                    let mut new_metadata = author_metadata.clone();
+                    new_metadata.author = s;
+                    return Action::SetSessionMetadata(new_metadata);
+                }
+                Action::Noop
+            })>
+            </input>
+            <br></br>
+            <label>{ "Description" }</label>
+            <br></br>
+            <textarea rows="3" value=metadata.description.clone() onchange=m.link.callback(move |v| {
+                if let ChangeData::Value(s) = v {
+                    let mut new_metadata = description_metadata.clone();
+                    new_metadata.description = s;
+                    return Action::SetSessionMetadata(new_metadata);
+                }
+                Action::Noop
+            })>
+            </textarea>
+            <div>{ format!{"Created: {}", if metadata.created.is_empty() { "-" } else { &metadata.created }} }</div>
+            <div>{ format!{"Modified: {}", if metadata.modified.is_empty() { "never saved" } else { &metadata.modified }} }</div>
+        </div>
+    }
+}
+
+// Settings for the layout that new sessions (and "Reset") start out with
+fn view_session_defaults(m: &Model) -> Html {
+    let prefs = m.preferences.clone();
+    let number_input = |label: &str, value: f64, set: fn(&mut Preferences, f64)| {
+        let prefs = prefs.clone();
+        html! {
+            <div>
+                <label>{ label }</label>
+                <input type="number" value={value.to_string()}
+                    onchange=m.link.callback(move |e: ChangeData| {
+                        if let ChangeData::Value(v) = e {
+                            if let Ok(v) = v.parse::<f64>() {
+                                let mut new_prefs = prefs.clone();
+                                set(&mut new_prefs, v);
+                                return Action::SetPreferences(new_prefs);
+                            }
+                        }
+                        Action::Noop
+                    })>
+                </input>
+            </div>
+        }
+    };
+    let mut meta_grammar_toggles = VList::new();
+    for (label, meta_grammar) in vec![
+        ("Button", MetaGrammar::Button),
+        ("Slider", MetaGrammar::Slider),
+        ("Toggle", MetaGrammar::Toggle),
+        ("Color Picker", MetaGrammar::ColorPicker),
+        ("Gauge", MetaGrammar::Gauge),
+        ("Rating", MetaGrammar::Rating),
+        ("Stepper", MetaGrammar::Stepper),
+        ("Timer", MetaGrammar::Timer),
+        ("Reminder", MetaGrammar::Reminder),
+        ("Shell Command", MetaGrammar::Shell),
+        ("Definition", MetaGrammar::Definition),
+    ] {
+        let prefs = prefs.clone();
+        let checked = prefs.default_meta_grammars.contains(&meta_grammar);
+        meta_grammar_toggles.add_child(html! {
+            <div>
+                <input type="checkbox" checked={checked}
+                    onclick=m.link.callback(move |_ : ClickEvent| {
+                        let mut new_prefs = prefs.clone();
+                        if checked {
+                            new_prefs.default_meta_grammars.retain(|g| *g != meta_grammar);
+                        } else {
+                            new_prefs.default_meta_grammars.push(meta_grammar);
+                        }
+                        Action::SetPreferences(new_prefs)
+                    })>
+                </input>
+                <label>{ label }</label>
+            </div>
+        });
+    }
     html! {
-        <div class="sidenav">
-            { side_menu_nodes }
+        <div>
+            <h3>{"new session layout"}</h3>
+            { number_input("Rows", prefs.default_rows as f64, |p, v| p.default_rows = v.max(1.0) as u32) }
+            { number_input("Columns", prefs.default_cols as f64, |p, v| p.default_cols = v.max(1.0) as u32) }
+            { number_input("Row height (px)", prefs.default_row_height, |p, v| p.default_row_height = v) }
+            { number_input("Column width (px)", prefs.default_col_width, |p, v| p.default_col_width = v) }
+            <h3>{"nested grids"}</h3>
+            { number_input("Rows", prefs.nested_grid.rows as f64, |p, v| p.nested_grid.rows = v.max(1.0) as u32) }
+            { number_input("Columns", prefs.nested_grid.cols as f64, |p, v| p.nested_grid.cols = v.max(1.0) as u32) }
+            { number_input("Row height (px)", prefs.nested_grid.row_height, |p, v| p.nested_grid.row_height = v) }
+            { number_input("Column width (px)", prefs.nested_grid.col_width, |p, v| p.nested_grid.col_width = v) }
+            <h3>{"meta grammars"}</h3>
+            { meta_grammar_toggles }
+        </div>
+    }
+}
 
-            { side_menu_section }
+// How often tabs with unsaved changes are autosaved (see `Autosave`)
+fn view_autosave_settings(m: &Model) -> Html {
+    let autosave_prefs = m.preferences.clone();
+    html! {
+        <div>
+            <h3>{"autosave"}</h3>
+            <label>{ "Autosave unsaved tabs every " }</label>
+            <input type="number" size="6" min="0"
+                value={m.preferences.autosave_interval_s.to_string()}
+                onchange=m.link.callback(move |e: ChangeData| {
+                    if let ChangeData::Value(v) = e {
+                        match v.trim().parse::<u32>() {
+                            Ok(interval) => {
+                                let mut new_prefs = autosave_prefs.clone();
+                                new_prefs.autosave_interval_s = interval;
+                                return Action::SetPreferences(new_prefs);
+                            }
+                            _ => return Action::Alert(format!{"the interval needs to be a whole number of seconds, got: {}", v}),
+                        }
+                    }
+                    Action::Noop
+                })>
+            </input>
+            <label>{ " seconds" }</label>
+            <div class="search-help">{ "0 turns autosaving off" }</div>
         </div>
     }
 }
-pub fn view_file_popup(m: &Model) -> Html {
-    let mut popup = html! { <></> };
 
-    if m.file_popup {
-        popup = html! {
-            <div class="hover_popup">
-                <span class="helper"></span>
-                <div class="_popup">
-                    <div class="popupCloseButton" onclick=m.link.callback(|e| Action::AskFileName())>{"X"}</div>
-                    // Path input under work
-                    /* <label for="path">{"Path"}</label>
-                        <input type="text" name="path" value=m.path onchange=m.link.callback(|v| {
-                            if let ChangeData::Value(s) = v {
-                                return Action::SetPath(s);
+// How much Ctrl+Up/Down steps the number in an input cell by
+fn view_stepping_settings(m: &Model) -> Html {
+    let step_prefs = m.preferences.clone();
+    html! {
+        <div>
+            <h3>{"stepping"}</h3>
+            <label>{ "Ctrl+Up/Down steps numbers by " }</label>
+            <input type="number" size="6"
+                value={m.preferences.numeric_step.to_string()}
+                onchange=m.link.callback(move |e: ChangeData| {
+                    if let ChangeData::Value(v) = e {
+                        match v.trim().parse::<f64>() {
+                            Ok(step) if step > 0.0 && step.is_finite() => {
+                                let mut new_prefs = step_prefs.clone();
+                                new_prefs.numeric_step = step;
+                                return Action::SetPreferences(new_prefs);
                             }
-                            Action::Noop
-                        })>
-                        </input>*/
-                    <label for="path">{"Name"}</label>
-                    <input type="text" name="name" value=m.get_session().title onchange=m.link.callback(|v| {
-                        if let ChangeData::Value(s) = v {
-                            return Action::SetSessionTitle(s);
+                            _ => return Action::Alert(format!{"the step needs to be a positive number, got: {}", v}),
                         }
-                        Action::Noop
+                    }
+                    Action::Noop
+                })>
+            </input>
+        </div>
+    }
+}
+
+fn view_entry_settings(m: &Model) -> Html {
+    let mut options = VList::new();
+    for (behavior, label) in [
+        (EnterBehavior::Down, "moves down"),
+        (EnterBehavior::Right, "moves right"),
+        (EnterBehavior::Newline, "starts a new line"),
+    ]
+    .iter()
+    {
+        let behavior = *behavior;
+        let entry_prefs = m.preferences.clone();
+        options.add_child(html! {
+            <label class="entry-option">
+                <input type="radio" name="enter-behavior" checked={m.preferences.enter_behavior == behavior}
+                    onclick=m.link.callback(move |_ : ClickEvent| {
+                        let mut new_prefs = entry_prefs.clone();
+                        new_prefs.enter_behavior = behavior;
+                        Action::SetPreferences(new_prefs)
                     })>
-                    </input>
+                </input>
+                { label }
+            </label>
+        });
+    }
+    let shape_prefs = m.preferences.clone();
+    html! {
+        <div>
+            <h3>{"data entry"}</h3>
+            <label>{ "Enter in a cell " }</label>
+            { options }
+            <div class="search-help">
+                { "Select a range first to fill it in row by row (or column by column)" }
+            </div>
+            <input type="checkbox" checked={m.preferences.block_shape_mismatches}
+                onclick=m.link.callback(move |_ : ClickEvent| {
+                    let mut new_prefs = shape_prefs.clone();
+                    new_prefs.block_shape_mismatches = !new_prefs.block_shape_mismatches;
+                    Action::SetPreferences(new_prefs)
+                })>
+            </input>
+            <label>{ "Refuse completions that don't fit a cell's expected shape" }</label>
+        </div>
+    }
+}
 
-                    <input type="button" value="Save" onclick=m.link.callback(|_| Action::SaveSession()) />
-                </div>
+// How numbers, dates & money are shown. Cells keep their canonical text, so a session shows
+// in the locale of whoever opens it.
+fn view_locale_settings(m: &Model) -> Html {
+    let mut options = VList::new();
+    for locale in Locale::ALL.iter() {
+        let locale = *locale;
+        let locale_prefs = m.preferences.clone();
+        options.add_child(html! {
+            <label class="entry-option">
+                <input type="radio" name="locale" checked={m.preferences.locale == locale}
+                    onclick=m.link.callback(move |_ : ClickEvent| {
+                        let mut new_prefs = locale_prefs.clone();
+                        new_prefs.locale = locale;
+                        Action::SetPreferences(new_prefs)
+                    })>
+                </input>
+                { locale.label() }
+            </label>
+        });
+    }
+    html! {
+        <div>
+            <h3>{"locale"}</h3>
+            <label>{ "Show numbers, dates & money " }</label>
+            { options }
+            <div class="search-help">
+                { format!{"e.g. {}, {}, {}",
+                    localize("1234.5", m.preferences.locale),
+                    localize("2020-02-29", m.preferences.locale),
+                    localize("12.50 EUR", m.preferences.locale)} }
             </div>
-        };
+        </div>
     }
+}
 
+// How tables are exported as JSON records (see `JsonExport`)
+fn view_json_export_settings(m: &Model) -> Html {
+    let (pretty_prefs, coerce_prefs) = (m.preferences.clone(), m.preferences.clone());
     html! {
-        {popup}
+        <div>
+            <h3>{"JSON export"}</h3>
+            <input type="checkbox" checked={m.preferences.json_export.pretty}
+                onclick=m.link.callback(move |_ : ClickEvent| {
+                    let mut new_prefs = pretty_prefs.clone();
+                    new_prefs.json_export.pretty = !new_prefs.json_export.pretty;
+                    Action::SetPreferences(new_prefs)
+                })>
+            </input>
+            <label>{ "Pretty-print" }</label>
+            <br></br>
+            <input type="checkbox" checked={m.preferences.json_export.coerce}
+                onclick=m.link.callback(move |_ : ClickEvent| {
+                    let mut new_prefs = coerce_prefs.clone();
+                    new_prefs.json_export.coerce = !new_prefs.json_export.coerce;
+                    Action::SetPreferences(new_prefs)
+                })>
+            </input>
+            <label>{ "Export numbers as numbers and empty cells as null" }</label>
+        </div>
     }
 }
 
-pub fn view_side_menu(m: &Model, side_menu: &SideMenu) -> Html {
-    match side_menu.name.deref() {
-        "Home" => {
-            html! {
-                <div class="side-menu-section">
-                    {"THIS IS Home MENU"}
-                </div>
-            }
+// Whether structural changes (inserting, deleting & merging cells, zooming) animate
+fn view_animation_settings(m: &Model) -> Html {
+    let animation_prefs = m.preferences.clone();
+    html! {
+        <div>
+            <h3>{"animations"}</h3>
+            <input type="checkbox" checked={m.preferences.animations}
+                onclick=m.link.callback(move |_ : ClickEvent| {
+                    let mut new_prefs = animation_prefs.clone();
+                    new_prefs.animations = !new_prefs.animations;
+                    Action::SetPreferences(new_prefs)
+                })>
+            </input>
+            <label>{ "Animate cells moving as rows & columns change and when zooming" }</label>
+        </div>
+    }
+}
+
+// The hooks sessions go through as they're saved & loaded, in the order they run. Builtin ones
+// can be turned on & off and reordered, those drivers registered are listed along with them.
+fn view_hook_settings(m: &Model) -> Html {
+    let mut stages = VList::new();
+    for stage in [Stage::PreSave, Stage::PostLoad].iter() {
+        let mut hooks: Vec<(Option<usize>, _)> = m
+            .preferences
+            .hooks
+            .iter()
+            .enumerate()
+            .map(|(i, h)| (Some(i), h))
+            .chain(m.driver_hooks.iter().map(|h| (None, h)))
+            .filter(|(_, h)| h.stage == *stage)
+            .collect();
+        hooks.sort_by(|(_, a), (_, b)| (a.order, &a.name).cmp(&(b.order, &b.name)));
+        let mut rows = VList::new();
+        for (index, hook) in hooks {
+            let row = match (index, &hook.source) {
+                (Some(index), _) => {
+                    let (toggle_prefs, order_prefs) = (m.preferences.clone(), m.preferences.clone());
+                    html! {
+                        <tr>
+                            <td>
+                                <input type="checkbox" checked={hook.enabled}
+                                    onclick=m.link.callback(move |_ : ClickEvent| {
+                                        let mut new_prefs = toggle_prefs.clone();
+                                        new_prefs.hooks[index].enabled = !new_prefs.hooks[index].enabled;
+                                        Action::SetPreferences(new_prefs)
+                                    })>
+                                </input>
+                            </td>
+                            <td>{ &hook.name }</td>
+                            <td>
+                                <input type="number" size="4" value={hook.order.to_string()}
+                                    onchange=m.link.callback(move |e: ChangeData| {
+                                        if let ChangeData::Value(v) = e {
+                                            if let Ok(order) = v.trim().parse::<i32>() {
+                                                let mut new_prefs = order_prefs.clone();
+                                                new_prefs.hooks[index].order = order;
+                                                return Action::SetPreferences(new_prefs);
+                                            }
+                                        }
+                                        Action::Noop
+                                    })>
+                                </input>
+                            </td>
+                        </tr>
+                    }
+                }
+                (None, HookSource::Driver(driver)) => html! {
+                    <tr>
+                        <td></td>
+                        <td>{ format!{"{} (from {})", hook.name, driver} }</td>
+                        <td>{ hook.order }</td>
+                    </tr>
+                },
+                (None, HookSource::Builtin(_)) => html! { <></> },
+            };
+            rows.add_child(row);
         }
-        "File Explorer" => {
-            html! {
-                <div class="side-menu-section">
-                    <h1>
-                        {"File Explorer"}
-                    </h1>
+        stages.add_child(html! {
+            <div>
+                <label>{ format!{"Run {}:", stage.label()} }</label>
+                <table class="stats-table hook-table">{ rows }</table>
+            </div>
+        });
+    }
+    html! {
+        <div>
+            <h3>{"save & load hooks"}</h3>
+            { stages }
+            <div class="search-help">
+                { "Hooks run from the lowest order up. One that fails is skipped, and the others still run." }
+            </div>
+        </div>
+    }
+}
 
-                    <h3>{"load session"}</h3>
-                    <br></br>
-                    <input type="file" onchange=m.link.callback(|value| {
-                        if let ChangeData::Files(files) = value {
-                            if files.len() >= 1 {
-                                if let Some(file) = files.iter().nth(0) {
-                                    return Action::ReadSession(file);
-                                }
-                            } else {
-                                return Action::Alert("Could not load file".to_string());
+fn view_calc_settings(m: &Model) -> Html {
+    let calc_prefs = m.preferences.clone();
+    html! {
+        <div>
+            <h3>{"calculation"}</h3>
+            <input type="checkbox" checked={m.preferences.calc_mode == CalcMode::Manual}
+                onclick=m.link.callback(move |_ : ClickEvent| {
+                    let mut new_prefs = calc_prefs.clone();
+                    new_prefs.calc_mode = match new_prefs.calc_mode {
+                        CalcMode::Automatic => CalcMode::Manual,
+                        CalcMode::Manual => CalcMode::Automatic,
+                    };
+                    Action::SetPreferences(new_prefs)
+                })>
+            </input>
+            <label>{ "Manual (recalculate with F9)" }</label>
+            { view_iteration_settings(m) }
+        </div>
+    }
+}
+
+// iterative calculation is set per session
+fn view_iteration_settings(m: &Model) -> Html {
+    let settings = m.get_session().iteration.clone();
+    let toggle_settings = settings.clone();
+    let max_settings = settings.clone();
+    let epsilon_settings = settings.clone();
+    html! {
+        <div>
+            <input type="checkbox" checked={settings.enabled}
+                onclick=m.link.callback(move |_ : ClickEvent| {
+                    let mut new_settings = toggle_settings.clone();
+                    new_settings.enabled = !new_settings.enabled;
+                    Action::SetIterationSettings(new_settings)
+                })>
+            </input>
+            <label>{ "Iterate circular formulas in this session" }</label>
+            <br></br>
+            <label>{ "max iterations " }</label>
+            <input type="number" size="6" value={settings.max_iterations.to_string()}
+                onchange=m.link.callback(move |e: ChangeData| {
+                    if let ChangeData::Value(v) = e {
+                        if let Ok(max_iterations) = v.trim().parse::<u32>() {
+                            let mut new_settings = max_settings.clone();
+                            new_settings.max_iterations = max_iterations.max(1);
+                            return Action::SetIterationSettings(new_settings);
+                        }
+                        return Action::Alert(format!{"max iterations needs to be a whole number, got: {}", v});
+                    }
+                    Action::Noop
+                })>
+            </input>
+            <br></br>
+            <label>{ "epsilon " }</label>
+            <input type="number" size="8" value={settings.epsilon.to_string()}
+                onchange=m.link.callback(move |e: ChangeData| {
+                    if let ChangeData::Value(v) = e {
+                        match v.trim().parse::<f64>() {
+                            Ok(epsilon) if epsilon >= 0.0 => {
+                                let mut new_settings = epsilon_settings.clone();
+                                new_settings.epsilon = epsilon;
+                                return Action::SetIterationSettings(new_settings);
                             }
+                            _ => return Action::Alert(format!{"epsilon needs to be a non-negative number, got: {}", v}),
                         }
-                        Action::Noop
-                    })>
-                    </input>
-                    <h3>{"save session"}</h3>
-                    <br></br>
-                    <input type="text" value=m.get_session().title onchange=m.link.callback(|v| {
-                        if let ChangeData::Value(s) = v {
-                            return Action::SetSessionTitle(s);
+                    }
+                    Action::Noop
+                })>
+            </input>
+        </div>
+    }
+}
+
+// the automation API, which external tools call with the token as a bearer token
+fn view_automation_settings(m: &Model) -> Html {
+    let settings = m.preferences.automation.clone();
+    let toggle_prefs = m.preferences.clone();
+    let port_prefs = m.preferences.clone();
+    let token_prefs = m.preferences.clone();
+    html! {
+        <div class="automation-settings">
+            <h3>{"automation"}</h3>
+            <div>
+                <input type="checkbox" checked={settings.enabled}
+                    onclick=m.link.callback(move |_ : ClickEvent| {
+                        let mut new_prefs = toggle_prefs.clone();
+                        new_prefs.automation.enabled = !new_prefs.automation.enabled;
+                        if new_prefs.automation.token.is_empty() {
+                            new_prefs.automation.token = random_token();
                         }
-                        Action::Noop
+                        Action::SetPreferences(new_prefs)
                     })>
-
-                    </input>
-                    <input type="button" value="Save" onclick=m.link.callback(|_| Action::SaveSession())>
-                    </input>
-                </div>
-            }
-        }
-        "Settings" => {
-            html! {
-                <div class="side-menu-section">
-                    <h1>
-                        {"Settings"}
-                    </h1>
-
-                    <h3>{"load driver"}</h3>
-                    <br></br>
-                    // drivers will be represented as directories, so we use "webkitdirectory"
-                    // (which isn't standard, but supported in chrome) to get an array of files in
-                    // the dirctory
-                    // https://developer.mozilla.org/en-US/docs/Web/API/HTMLInputElement/webkitdirectory
-                    <input
-                        type="file"
-                        webkitdirectory=""
-                        onchange=m.link.callback(|value| {
-                        if let ChangeData::Files(files) = value {
-                            // `files` will be a flat list with each file's "webkitRelativePath",
-                            // being a full path starting with the directory name.
-                            // ReadDriverFiles will load the .js file with the same name as the
-                            // directory, and upload the rest of the files to be served by electron
-                            let files_list : Vec<File> = files.into_iter().collect();
-                            if files_list.len() >= 1 {
-                                return Action::ReadDriverFiles(files_list);
-                            } else {
-                                return Action::Alert("Could not load Driver".to_string());
+                </input>
+                <label>{ "Accept JSON-RPC calls on localhost" }</label>
+            </div>
+            <label>{ "port " }</label>
+            <input type="number" size="6" value={settings.port.to_string()}
+                onchange=m.link.callback(move |e: ChangeData| {
+                    if let ChangeData::Value(v) = e {
+                        match v.trim().parse::<u16>() {
+                            Ok(port) if port >= 1024 => {
+                                let mut new_prefs = port_prefs.clone();
+                                new_prefs.automation.port = port;
+                                return Action::SetPreferences(new_prefs);
                             }
+                            _ => return Action::Alert(format!{"the port needs to be between 1024 and 65535, got: {}", v}),
                         }
-                        Action::Noop
-                    })>
-                    </input>
-                </div>
-            }
-        }
-        "Info" => {
-            html! {
-                <div class="side-menu-section">
-                    {"THIS IS info MENU"}
-                </div>
-            }
-        }
+                    }
+                    Action::Noop
+                })>
+            </input>
+            <br></br>
+            <label>{ "token " }</label>
+            <input type="text" readonly=true class="automation-token" value={settings.token.clone()}></input>
+            <button onclick=m.link.callback(move |_| {
+                let mut new_prefs = token_prefs.clone();
+                new_prefs.automation.token = random_token();
+                Action::SetPreferences(new_prefs)
+            })>
+                {"Regenerate"}
+            </button>
+        </div>
+    }
+}
 
-        _ => html! {<> </>},
+// Spellchecking of text cells, and the custom dictionary of domain terms (one per line)
+fn view_spellcheck_settings(m: &Model) -> Html {
+    let toggle_prefs = m.preferences.clone();
+    let dictionary_prefs = m.preferences.clone();
+    html! {
+        <div>
+            <h3>{"spellcheck"}</h3>
+            <div>
+                <input type="checkbox" checked={m.preferences.spellcheck}
+                    onclick=m.link.callback(move |_ : ClickEvent| {
+                        let mut new_prefs = toggle_prefs.clone();
+                        new_prefs.spellcheck = !new_prefs.spellcheck;
+                        Action::SetPreferences(new_prefs)
+                    })>
+                </input>
+                <label>{ "Check spelling in text cells" }</label>
+            </div>
+            <label>{ "Custom dictionary" }</label>
+            <br></br>
+            <textarea rows="5"
+                value={m.preferences.custom_dictionary.join("\n")}
+                onchange=m.link.callback(move |e: ChangeData| {
+                    if let ChangeData::Value(v) = e {
+                        let mut new_prefs = dictionary_prefs.clone();
+                        new_prefs.custom_dictionary = v
+                            .lines()
+                            .map(|w| w.trim().to_string())
+                            .filter(|w| !w.is_empty())
+                            .collect();
+                        return Action::SetPreferences(new_prefs);
+                    }
+                    Action::Noop
+                })>
+            </textarea>
+        </div>
     }
 }
 
+// whether the text of a cell gets spellchecked by the browser
+fn spellcheck_enabled(m: &Model, coord: &Coordinate) -> bool {
+    m.preferences.spellcheck
+        && m
+            .get_session()
+            .grammars
+            .get(coord)
+            .map_or(true, |g| !g.style.no_spellcheck)
+}
+
 pub fn view_menu_bar(m: &Model) -> Html {
     let active_cell = m.active_cell.clone();
     let nest_active_cell = m.active_cell.clone();
-    let (default_row, default_col) = {
-        let (r, c) = m.default_nested_row_cols.clone();
-        (r.get(), c.get())
-    };
+    let focus_active_cell = m.active_cell.clone();
+    // the grid containing the active cell, which the "Auto-Expand" toggle applies to
+    let auto_expand_grid = m.active_cell.clone().and_then(|c| c.parent());
+    let auto_expand_on = auto_expand_grid
+        .clone()
+        .and_then(|c| m.get_session().grammars.get(&c))
+        .map(|g| g.style.auto_expand)
+        .unwrap_or(false);
+    let (default_row, default_col) = m.nested_row_cols();
+    let flatten_prefs = m.preferences.clone();
     // SPECIAL MENU BAR ITEMS
     let nest_grid_button = html! {
         /* the "Nest Grid" button is special because
@@ -247,6 +2864,26 @@ pub fn view_menu_bar(m: &Model) -> Html {
             </input>
     };
 
+    // sets a style attribute of every selected cell, typed as <attribute>=<value>
+    let restyle_selection_input = html! {
+        <input
+            class="active-cell-indicator"
+            placeholder="Style selection: font_weight=600"
+            size="24"
+            onchange=m.link.callback(|e: ChangeData| {
+                if let ChangeData::Value(v) = e {
+                    let mut parts = v.splitn(2, '=');
+                    if let (Some(attr), Some(value)) = (parts.next(), parts.next()) {
+                        return Action::RestyleSelection(attr.trim().to_string(), value.trim().to_string());
+                    }
+                    return Action::Alert(format!{"restyle expects <attribute>=<value>, got: {}", v});
+                }
+                Action::Noop
+            })
+            onclick=m.link.callback(|e: ClickEvent| { e.prevent_default(); Action::Noop })>
+        </input>
+    };
+
     let nest_col_input = html! {
         <input
             class="active-cell-indicator"
@@ -268,16 +2905,12 @@ pub fn view_menu_bar(m: &Model) -> Html {
     };
 
     let add_definition_button = {
-        let (can_add_definition, default_name, callback) = match (
-            m.first_select_cell.clone(),
-            m.last_select_cell.clone(),
-        ) {
+        let (can_add_definition, default_name, callback) = match m.selection.corners() {
             // definitions can occur when a range of coordinates are selected where:
-            // - the first (top-leftmost) and last (bottom-rightmost) selected cells have the same parent
             // - the first selected cell is the first (top-leftmost) child of the parent
             // - the last selected cell is the last (bottom-rightmost) child of the parent
             // cell, which should be a Kind::Grid grammar
-            (Some(first), Some(last)) if first.parent() == last.parent() => {
+            Some((first, last)) => {
                 if let Some((Kind::Grid(sub_coords))) = /* get the coordinate of the parent, lookup the grammar, then get the grammar.kind */
                     first
                         .parent()
@@ -363,26 +2996,123 @@ pub fn view_menu_bar(m: &Model) -> Html {
             </button>
         }
     };
+    // stretches the first selected cell over the whole selection,
+    // or resets its span if there is no selection
+    let span_button = {
+        let span = match (m.active_cell.clone(), m.selection.primary()) {
+            (_, Some(region)) => Some((
+                region.cell(region.rows.0, region.cols.0),
+                non_zero_u32_tuple((
+                    region.rows.1 - region.rows.0 + 1,
+                    region.cols.1 - region.cols.0 + 1,
+                )),
+            )),
+            (Some(active), None) => Some((active, non_zero_u32_tuple((1, 1)))),
+            _ => None,
+        };
+        html! {
+            <button id="Span" class="menu-bar-button" onclick=m.link.callback(move |_ : ClickEvent| {
+                if let Some((coord, span)) = &span {
+                    Action::SetCellSpan(coord.clone(), *span)
+                } else { Action::Noop }
+            })>
+                { "Span" }
+            </button>
+        }
+    };
+    // the category of the active meta grammar, shown only when editing the meta sheet
+    let category_button = match m.active_cell.clone() {
+        Some(active) if active.parent() == Some(coord!("meta")) => {
+            let label = m
+                .get_session()
+                .grammars
+                .get(&active)
+                .map(|g| category_of(g).label())
+                .unwrap_or("");
+            html! {
+                <button id="Category" class="menu-bar-button" onclick=m.link.callback(move |_| {
+                    Action::CycleSuggestionCategory(active.clone())
+                })>
+                    { format!{"Category: {}", label} }
+                </button>
+            }
+        }
+        _ => html! { <></> },
+    };
+    // marks cells of snippet grammars on the meta sheet as placeholders
+    let placeholder_button = match m.active_cell.clone() {
+        Some(active) if active.row_cols.len() > 2 && active.row_cols[0] == coord!("meta").row_cols[0] => {
+            html! {
+                <button id="Placeholder" class="menu-bar-button" onclick=m.link.callback(move |_| {
+                    Action::TogglePlaceholder(active.clone())
+                })>
+                    { "Placeholder" }
+                </button>
+            }
+        }
+        _ => html! { <></> },
+    };
     // ALL MENU BAR ITEMS
     html! {
         <div class="menu-bar horizontal-bar">
+            // go-to box: shows the active cell (or selection), and jumps to any cell typed into it
             <input
                 class="active-cell-indicator"
-                disabled=true
                 // TODO: clicking on this should highlight
                 // the active cell
                 value={
-                    match (m.active_cell.clone(), m.first_select_cell.clone(), m.last_select_cell.clone()) {
-                        (_, Some(first_cell), Some(last_cell)) =>
+                    match (m.goto_text.clone(), m.active_cell.clone(), m.selection.corners()) {
+                        (Some(text), _, _) => text,
+                        (_, _, Some((first_cell, last_cell))) =>
                             format!{"{}:{}", first_cell.to_string(), last_cell.to_string()},
-                        (Some(cell), _, _) => cell.to_string(),
+                        (_, Some(cell), _) => cell.to_string(),
                         _ => "".to_string(),
                     }
-                }>
+                }
+                onchange=m.link.callback(|e: ChangeData| {
+                    if let ChangeData::Value(value) = e {
+                        return Action::GoTo(value);
+                    }
+                    Action::Noop
+                })
+                onkeydown=m.link.callback(|e: KeyDownEvent| {
+                    match e.key().deref() {
+                        "ArrowUp" => { e.prevent_default(); Action::BrowseHistory(HistoryKind::GoTo, true) }
+                        "ArrowDown" => { e.prevent_default(); Action::BrowseHistory(HistoryKind::GoTo, false) }
+                        _ => Action::Noop,
+                    }
+                })>
             </input>
             <button id="SaveSession" class="menu-bar-button" onclick=m.link.callback(|_| Action::AskFileName()) >
                 { "Save" }
             </button>
+            <button id="SaveCopy" class="menu-bar-button" onclick=m.link.callback(|_| Action::SaveCopy)>
+                { "Save a Copy..." }
+            </button>
+            <button id="MergeSession" class="menu-bar-button" title="Merge another copy of this session into a new tab"
+                onclick=m.link.callback(|_| Action::OpenSessionMerge)>
+                { "Merge Session..." }
+            </button>
+            <button id="ExportCsv" class="menu-bar-button" title="Export the grid being viewed as CSV or TSV"
+                onclick=m.link.callback(|_| Action::ExportCsv)>
+                { "Export CSV..." }
+            </button>
+            <button id="FlattenCsv" class="menu-bar-button" title="Spread nested grids over columns headed by their path when exporting CSV"
+                onclick=m.link.callback(move |_| {
+                    let mut new_prefs = flatten_prefs.clone();
+                    new_prefs.flatten_csv_export = !new_prefs.flatten_csv_export;
+                    Action::SetPreferences(new_prefs)
+                })>
+                { if m.preferences.flatten_csv_export { "Flatten Nested: On" } else { "Flatten Nested: Off" } }
+            </button>
+            <button id="Undo" class="menu-bar-button" title="Ctrl+Z" disabled={ !m.history.can_undo() }
+                onclick=m.link.callback(|_| Action::Undo)>
+                { "Undo" }
+            </button>
+            <button id="Redo" class="menu-bar-button" title="Ctrl+Y" disabled={ !m.history.can_redo() }
+                onclick=m.link.callback(|_| Action::Redo)>
+                { "Redo" }
+            </button>
             <button class="menu-bar-button">
                 { "Git" }
             </button>
@@ -395,6 +3125,29 @@ pub fn view_menu_bar(m: &Model) -> Html {
             <button id="ZoomOut" class="menu-bar-button" onclick=m.link.callback(|_| Action::ZoomOut)>
                 { "Zoom Out (-)" }
             </button>
+            <button id="ZoomToFit" class="menu-bar-button" title="Ctrl+9" onclick=m.link.callback(|_| Action::ZoomToFit)>
+                { "Fit Window" }
+            </button>
+            <button id="ZoomToSelection" class="menu-bar-button" title="Ctrl+Shift+9" onclick=m.link.callback(|_| Action::ZoomToSelection)>
+                { "Fit Selection" }
+            </button>
+            <button id="FocusGrid" class="menu-bar-button" onclick=m.link.callback(move |_| {
+                if let Some(current) = &focus_active_cell {
+                    Action::OpenFocusedGrid(current.clone())
+                } else { Action::Noop }
+            })>
+                { "Focus Grid" }
+            </button>
+            <button id="AutoExpand" class="menu-bar-button" onclick=m.link.callback(move |_| {
+                if let Some(grid) = auto_expand_grid.clone() {
+                    Action::ToggleAutoExpand(grid)
+                } else { Action::Noop }
+            })>
+                { if auto_expand_on { "Auto-Expand: On" } else { "Auto-Expand: Off" } }
+            </button>
+            <button id="SplitView" class="menu-bar-button" onclick=m.link.callback(|_| Action::ToggleSplitView)>
+                { if m.split_view.is_some() { "Unsplit" } else { "Split View" } }
+            </button>
             <button id="Reset" class="menu-bar-button" onclick=m.link.callback(|_| Action::Recreate)>
                 { "Reset" }
             </button>
@@ -411,6 +3164,16 @@ pub fn view_menu_bar(m: &Model) -> Html {
             <button id="Merge" class="menu-bar-button" onclick=m.link.callback(move |_ : ClickEvent| Action::MergeCells())>
                 { "Merge" }
             </button>
+            { restyle_selection_input }
+            { span_button }
+            { category_button }
+            { placeholder_button }
+            <button id="GroupRows" class="menu-bar-button" onclick=m.link.callback(|_| Action::AddOutlineGroup(OutlineAxis::Rows))>
+                { "Group Rows" }
+            </button>
+            <button id="GroupCols" class="menu-bar-button" onclick=m.link.callback(|_| Action::AddOutlineGroup(OutlineAxis::Cols))>
+                { "Group Columns" }
+            </button>
             <button id="DeleteRow" class="menu-bar-button" onclick=m.link.callback(|_| Action::DeleteRow)>
                 { "Delete Row" }
             </button>
@@ -430,36 +3193,290 @@ pub fn view_menu_bar(m: &Model) -> Html {
     }
 }
 
+// Renders the two viewports of split view side by side. Each pane scrolls independently and
+// has its own root coordinate; pressing on a pane focuses it, making its active cell the
+// model's active cell.
+pub fn view_split_panes(m: &Model) -> Html {
+    let split_view = match &m.split_view {
+        Some(split_view) => split_view,
+        None => return html! { <></> },
+    };
+    let mut panes = VList::new();
+    for index in 0..2 {
+        let is_focused = index == split_view.focused;
+        let pane_root = if is_focused {
+            m.view_root.clone()
+        } else {
+            split_view.other.view_root.clone()
+        };
+        // the unfocused pane shows its own active cell, under ids of its own
+        let grammar = if is_focused {
+            view_grammar(m, pane_root.clone())
+        } else {
+            m.rendered_pane.replace(Some((index, split_view.other.active_cell.clone())));
+            let grammar = view_grammar(m, pane_root.clone());
+            m.rendered_pane.replace(None);
+            grammar
+        };
+        panes.add_child(html! {
+            <div
+                class=format!{"pane {}", if is_focused { "pane-focused" } else { "" }}
+                onmousedown=m.link.callback(move |_ : MouseDownEvent| Action::FocusPane(index))>
+                <div class="pane-header">
+                    <input
+                        class="active-cell-indicator"
+                        placeholder="root"
+                        value={pane_root.to_string()}
+                        onchange=m.link.callback(move |e: ChangeData| {
+                            if let ChangeData::Value(value) = e {
+                                if let Some(c) = Coordinate::parse(value.trim()) {
+                                    return Action::SetViewRoot(index, c);
+                                }
+                                return Action::Alert(format!{"invalid coordinate: {}", value});
+                            }
+                            Action::Noop
+                        })>
+                    </input>
+                </div>
+                { grammar }
+            </div>
+        });
+    }
+    html! {
+        <div class="split-view">
+            { panes }
+        </div>
+    }
+}
+
 pub fn view_tab_bar(m: &Model) -> Html {
     let mut tabs = VList::new();
     for (index, tab) in m.sessions.clone().iter().enumerate() {
-        if (index as usize) == m.current_session_index {
-            tabs.add_child(html! {
-                <button class="tab active-tab">{ tab.title.clone() }</button>
-            });
-        } else {
+        if m.renaming_tab == Some(index) {
             tabs.add_child(html! {
-                <button class="tab">{ tab.title.clone() }</button>
+                <input class="tab tab-rename" size="16" value=tab.title.clone()
+                    onchange=m.link.callback(move |e: ChangeData| match e {
+                        ChangeData::Value(title) => Action::RenameTab(index, title),
+                        _ => Action::Noop,
+                    })>
+                </input>
             });
+            continue;
         }
+        let class = if index == m.current_session_index { "tab active-tab" } else { "tab" };
+        let dragged = m.dragged_tab;
+        tabs.add_child(html! {
+            <button class=class title=session_tooltip(tab) draggable="true"
+                onclick=m.link.callback(move |_: ClickEvent| Action::SwitchTab(index))
+                ondoubleclick=m.link.callback(move |_: DoubleClickEvent| Action::StartRenameTab(index))
+                oncontextmenu=m.link.callback(move |e: ContextMenuEvent| {
+                    e.prevent_default();
+                    Action::ShowTabMenu(index, (e.client_x() as f64, e.client_y() as f64))
+                })
+                ondragstart=m.link.callback(move |_: DragStartEvent| Action::DragTab(Some(index)))
+                ondragover=m.link.callback(move |e: DragOverEvent| {
+                    // only tabs are dropped here, files are dropped onto the window
+                    if dragged.is_some() {
+                        e.prevent_default();
+                    }
+                    Action::Noop
+                })
+                ondrop=m.link.callback(move |e: DragDropEvent| match dragged {
+                    Some(from) => {
+                        e.prevent_default();
+                        Action::MoveTab(from, index)
+                    }
+                    None => Action::Noop,
+                })
+                ondragend=m.link.callback(|_: DragEndEvent| Action::DragTab(None))>
+                { tab_label(tab) }
+                <span class="tab-close" title="Close"
+                    onclick=m.link.callback(move |e: ClickEvent| {
+                        e.stop_propagation();
+                        Action::CloseTab(index)
+                    })>
+                    { "×" }
+                </span>
+            </button>
+        });
     }
     html! {
         <div class="tab-bar horizontal-bar">
             { tabs }
-            <button class="newtab-btn">
+            <button class="newtab-btn" title="New Tab" onclick=m.link.callback(|_| Action::NewTab)>
+                <span>{ "+" }</span>
+            </button>
+            { view_tab_menu(m) }
+        </div>
+    }
+}
+
+// Actions on a tab, opened by right-clicking it
+fn view_tab_menu(m: &Model) -> Html {
+    let (index, (left, top)) = match m.tab_menu {
+        Some(menu) => menu,
+        None => return html! { <></> },
+    };
+    let has_file = m.sessions.get(index).map_or(false, |s| s.view.file_path.is_some());
+    let options = vec![
+        ("Rename", m.link.callback(move |_| Action::StartRenameTab(index)), true),
+        ("Duplicate Tab", m.link.callback(move |_| Action::DuplicateTab(index)), true),
+        ("Close", m.link.callback(move |_| Action::CloseTab(index)), true),
+        (
+            "Close Others",
+            m.link.callback(move |_| Action::CloseOtherTabs(index)),
+            m.sessions.len() > 1,
+        ),
+        (
+            "Reveal File",
+            m.link.callback(move |_| Action::RevealTabFile(index)),
+            has_file,
+        ),
+    ];
+    let mut nodes = VList::new();
+    for (name, callback, enabled) in options {
+        if !enabled {
+            continue;
+        }
+        nodes.add_child(html! {
+            <li class="context-menu-option" onclick=callback>
+                { name }
+            </li>
+        });
+    }
+    html! {
+        <div class="context-menu tab-menu" style=format! {"display: block; top: {}px; left: {}px", top, left}>
+            <ul class="context-menu-options">
+                { nodes }
+            </ul>
+        </div>
+    }
+}
+
+// title of a session's tab, marked with a "*" when it has unsaved changes
+fn tab_label(session: &Session) -> String {
+    if session.view.dirty {
+        format! {"{} *", session.title}
+    } else {
+        session.title.clone()
+    }
+}
+
+// tooltip of a session's tab, summarizing its metadata
+fn session_tooltip(session: &Session) -> String {
+    let metadata = &session.metadata;
+    let mut lines = vec![session.title.clone()];
+    if !metadata.author.is_empty() {
+        lines.push(format! {"by {}", metadata.author});
+    }
+    if !metadata.description.is_empty() {
+        lines.push(metadata.description.clone());
+    }
+    if !metadata.modified.is_empty() {
+        lines.push(format! {"last saved {}", metadata.modified});
+    }
+    lines.join("\n")
+}
+
+// Switcher between the top-level grids of the session: root, plus any extra sheets
+pub fn view_sheet_bar(m: &Model) -> Html {
+    let current_root = m.view_root.truncate(1).unwrap_or(m.view_root.clone());
+    let mut sheets = VList::new();
+    let mut entries = vec![("root".to_string(), coord!("root"))];
+    for sheet in m.get_session().sheets.iter() {
+        entries.push((sheet.name.clone(), sheet.coord.clone()));
+    }
+    // the meta sheet holds the grammars offered as suggestions, and is edited like any other sheet
+    entries.push(("meta".to_string(), coord!("meta")));
+    for (name, sheet_coord) in entries {
+        // root & meta are built into every session, so they can't be renamed or removed
+        let is_root = sheet_coord == coord!("root") || sheet_coord == coord!("meta");
+        if sheet_coord == current_root {
+            let rename_coord = sheet_coord.clone();
+            let remove_coord = sheet_coord.clone();
+            sheets.add_child(html! {
+                <span class="sheet active-sheet">
+                    <input
+                        size="10"
+                        value={name}
+                        disabled={is_root}
+                        onchange=m.link.callback(move |e: ChangeData| {
+                            if let ChangeData::Value(value) = e {
+                                return Action::RenameSheet(rename_coord.clone(), value);
+                            }
+                            Action::Noop
+                        })>
+                    </input>
+                    {
+                        if is_root {
+                            html! { <></> }
+                        } else {
+                            html! {
+                                <button onclick=m.link.callback(move |_ : ClickEvent| Action::RemoveSheet(remove_coord.clone()))>
+                                    { "x" }
+                                </button>
+                            }
+                        }
+                    }
+                </span>
+            });
+        } else {
+            let class = if sheet_coord == coord!("meta") { "sheet meta-sheet" } else { "sheet" };
+            sheets.add_child(html! {
+                <button class=class onclick=m.link.callback(move |_ : ClickEvent| Action::SwitchSheet(sheet_coord.clone()))>
+                    { name }
+                </button>
+            });
+        }
+    }
+    let meta_banner = if current_root == coord!("meta") {
+        html! {
+            <span class="meta-banner">
+                { "Editing meta grammars: named cells in column A are offered as completions" }
+            </span>
+        }
+    } else {
+        html! { <></> }
+    };
+    html! {
+        <div class="sheet-bar horizontal-bar">
+            { sheets }
+            <button class="newsheet-btn" onclick=m.link.callback(|_ : ClickEvent| Action::AddSheet)>
                 <span>{ "+" }</span>
             </button>
+            { meta_banner }
         </div>
     }
 }
 
+// The element id of a cell. The cells of the unfocused pane of the split view get ids of their
+// own, leaving the plain ones, which cells are focused & scrolled to by, to the focused pane.
+pub fn cell_id(m: &Model, coord: &Coordinate) -> String {
+    match &*m.rendered_pane.borrow() {
+        Some((index, _)) => format! {"pane-{}-cell-{}", index, coord.to_string()},
+        None => format! {"cell-{}", coord.to_string()},
+    }
+}
+
 pub fn view_grammar(m: &Model, coord: Coordinate) -> Html {
-    let is_active = m.active_cell.clone() == Some(coord.clone());
+    let active_cell = match &*m.rendered_pane.borrow() {
+        Some((_, active_cell)) => active_cell.clone(),
+        None => m.active_cell.clone(),
+    };
+    let is_active = active_cell == Some(coord.clone());
     if let Some(grammar) = m.get_session().grammars.get(&coord) {
         // account for merged cells with have been hidden via their Style.display property.
         if grammar.clone().style.display == false {
             return html! {<> </>};
         }
+        // ... and for cells inside collapsed outline groups
+        if is_hidden(&m.get_session().outline_groups, &coord) {
+            return html! {<> </>};
+        }
+        // ... and for cells covered by a spanning neighbor
+        if is_covered_by_span(m, &coord) {
+            return html! {<> </>};
+        }
         match grammar.kind.clone() {
             Kind::Text(value) => view_text_grammar(m, &coord, value, is_active),
             Kind::Input(value) => {
@@ -483,12 +3500,13 @@ pub fn view_grammar(m: &Model, coord: Coordinate) -> Html {
                 view_input_grammar(m, coord.clone(), suggestions, value, is_active)
             }
             Kind::Interactive(name, Interactive::Button()) => {
+                let button_coord = coord.clone();
                 html! {
                     <div
                         class=format!{"cell interactive row-{} col-{}", coord.row_to_string(), coord.col_to_string()}
-                        id=format!{"cell-{}", coord.to_string()}
+                        id=cell_id(m, &coord)
                         style={ get_style(m.get_session().grammars.get(&coord).expect("no grammar with this coordinate"), &m.col_widths, &m.row_heights,  &coord) }>
-                        <button>
+                        <button onclick=m.link.callback(move |_| Action::RunButton(button_coord.clone()))>
                             { name }
                         </button>
                     </div>
@@ -499,7 +3517,7 @@ pub fn view_grammar(m: &Model, coord: Coordinate) -> Html {
                     <div
                         onclick=m.link.callback(|_| Action::HideContextMenu)
                         class=format!{"cell interactive row-{} col-{}", coord.row_to_string(), coord.col_to_string()}
-                        id=format!{"cell-{}", coord.to_string()}
+                        id=cell_id(m, &coord)
                         // style={ get_style(&m, &coord) }>
                         style={ get_style(m.get_session().grammars.get(&coord).expect("no grammar with this coordinate"), &m.col_widths, &m.row_heights,  &coord) }>
                         <input type="range" min={min} max={max} value={value}>
@@ -509,19 +3527,251 @@ pub fn view_grammar(m: &Model, coord: Coordinate) -> Html {
                 }
             }
             Kind::Interactive(name, Interactive::Toggle(checked)) => {
+                let toggle_coord = coord.clone();
                 html! {
                     <div
                         onclick=m.link.callback(|_| Action::HideContextMenu)
                         class=format!{"cell interactive row-{} col-{}", coord.row_to_string(), coord.col_to_string()}
-                        id=format!{"cell-{}", coord.to_string()}
+                        id=cell_id(m, &coord)
                         // style={ get_style(&m, &coord) }>
                         style={ get_style(m.get_session().grammars.get(&coord).expect("no grammar with this coordinate"), &m.col_widths, &m.row_heights,  &coord) }>
-                        <input type="checkbox" checked={checked}>
+                        <input type="checkbox" checked={checked}
+                            onclick=m.link.callback(move |_| Action::SetToggle(toggle_coord.clone(), !checked))>
                             { name }
                         </input>
                     </div>
                 }
             }
+            Kind::Interactive(_, Interactive::ColorPicker(color)) => {
+                let color_coord = coord.clone();
+                html! {
+                    <div
+                        onclick=m.link.callback(|_| Action::HideContextMenu)
+                        class=format!{"cell interactive color-picker row-{} col-{}", coord.row_to_string(), coord.col_to_string()}
+                        id=cell_id(m, &coord)
+                        style={ get_style(m.get_session().grammars.get(&coord).expect("no grammar with this coordinate"), &m.col_widths, &m.row_heights,  &coord) }>
+                        <input type="color" value=color.clone()
+                            onchange=m.link.callback(move |e: ChangeData| match e {
+                                ChangeData::Value(color) => Action::SetColor(color_coord.clone(), color),
+                                _ => Action::Noop,
+                            })>
+                        </input>
+                        <span class="color-value">{ color }</span>
+                    </div>
+                }
+            }
+            Kind::Interactive(_, Interactive::Gauge(source, min, max)) => {
+                let (source_coord, min_coord, max_coord) = (coord.clone(), coord.clone(), coord.clone());
+                let (min_source, max_source) = (source.clone(), source.clone());
+                let bar = match gauge_value(m.values.get(&coord)) {
+                    Some(value) => {
+                        let filled = fraction(value, min, max);
+                        html! {
+                            <div class="gauge-track" title=format!{"{} of {} to {}", value, min, max}>
+                                <div class=format!{"gauge-bar {}", GaugeLevel::of(filled).class()}
+                                    style=format!{"width: {}%", filled * 100.0}>
+                                </div>
+                                <span class="gauge-value">{ value }</span>
+                            </div>
+                        }
+                    }
+                    None => html! {
+                        <div class="gauge-track gauge-empty">
+                            { m.values.get(&coord).map(|v| v.to_string()).unwrap_or_default() }
+                        </div>
+                    },
+                };
+                html! {
+                    <div
+                        onclick=m.link.callback(|_| Action::HideContextMenu)
+                        class=format!{"cell interactive gauge-cell row-{} col-{}", coord.row_to_string(), coord.col_to_string()}
+                        id=cell_id(m, &coord)
+                        style={ get_style(m.get_session().grammars.get(&coord).expect("no grammar with this coordinate"), &m.col_widths, &m.row_heights,  &coord) }>
+                        { bar }
+                        <div class="gauge-settings">
+                            <input class="gauge-source" value=source placeholder="root-A1 or =formula"
+                                onchange=m.link.callback(move |e: ChangeData| match e {
+                                    ChangeData::Value(source) => Action::ConfigureGauge(source_coord.clone(), source, min, max),
+                                    _ => Action::Noop,
+                                })>
+                            </input>
+                            <input class="gauge-bound" type="number" value=min.to_string() title="min"
+                                onchange=m.link.callback(move |e: ChangeData| match e {
+                                    ChangeData::Value(v) => match v.parse::<f64>() {
+                                        Ok(min) => Action::ConfigureGauge(min_coord.clone(), min_source.clone(), min, max),
+                                        Err(_) => Action::Noop,
+                                    },
+                                    _ => Action::Noop,
+                                })>
+                            </input>
+                            <input class="gauge-bound" type="number" value=max.to_string() title="max"
+                                onchange=m.link.callback(move |e: ChangeData| match e {
+                                    ChangeData::Value(v) => match v.parse::<f64>() {
+                                        Ok(max) => Action::ConfigureGauge(max_coord.clone(), max_source.clone(), min, max),
+                                        Err(_) => Action::Noop,
+                                    },
+                                    _ => Action::Noop,
+                                })>
+                            </input>
+                        </div>
+                    </div>
+                }
+            }
+            Kind::Interactive(_, Interactive::Rating(stars, max)) => {
+                let key_coord = coord.clone();
+                let mut star_nodes = VList::new();
+                for n in 1..=max {
+                    let star_coord = coord.clone();
+                    let class = if n <= stars { "rating-star filled" } else { "rating-star" };
+                    star_nodes.add_child(html! {
+                        <span class=class onclick=m.link.callback(move |_| Action::SetRating(star_coord.clone(), n))>
+                            { if n <= stars { "\u{2605}" } else { "\u{2606}" } }
+                        </span>
+                    });
+                }
+                html! {
+                    <div
+                        class=format!{"cell interactive rating-cell row-{} col-{}", coord.row_to_string(), coord.col_to_string()}
+                        id=cell_id(m, &coord)
+                        tabindex=0
+                        onkeydown=m.link.callback(move |e: KeyDownEvent| match e.key().deref() {
+                            "ArrowRight" if stars < max => Action::SetRating(key_coord.clone(), stars + 1),
+                            // lowering the rating by one, rather than clearing it at the same count
+                            "ArrowLeft" if stars > 0 => Action::SetRating(key_coord.clone(), stars - 1),
+                            _ => Action::Noop,
+                        })
+                        style={ get_style(m.get_session().grammars.get(&coord).expect("no grammar with this coordinate"), &m.col_widths, &m.row_heights,  &coord) }>
+                        { star_nodes }
+                    </div>
+                }
+            }
+            Kind::Interactive(_, Interactive::Stepper(value, _, min, max)) => {
+                let (down_coord, up_coord, key_coord) = (coord.clone(), coord.clone(), coord.clone());
+                html! {
+                    <div
+                        onclick=m.link.callback(|_| Action::HideContextMenu)
+                        class=format!{"cell interactive stepper-cell row-{} col-{}", coord.row_to_string(), coord.col_to_string()}
+                        id=cell_id(m, &coord)
+                        tabindex=0
+                        onkeydown=m.link.callback(move |e: KeyDownEvent| match e.key().deref() {
+                            "ArrowUp" => { e.prevent_default(); Action::StepStepper(key_coord.clone(), 1) }
+                            "ArrowDown" => { e.prevent_default(); Action::StepStepper(key_coord.clone(), -1) }
+                            _ => Action::Noop,
+                        })
+                        style={ get_style(m.get_session().grammars.get(&coord).expect("no grammar with this coordinate"), &m.col_widths, &m.row_heights,  &coord) }>
+                        <button class="stepper-button" disabled={value <= min}
+                            onclick=m.link.callback(move |_| Action::StepStepper(down_coord.clone(), -1))>{ "-" }</button>
+                        <span class="stepper-value">{ value }</span>
+                        <button class="stepper-button" disabled={value >= max}
+                            onclick=m.link.callback(move |_| Action::StepStepper(up_coord.clone(), 1))>{ "+" }</button>
+                    </div>
+                }
+            }
+            Kind::Interactive(_, Interactive::Timer(elapsed)) => {
+                let running = m.running_timers.contains(&(m.current_session_index, coord.clone()));
+                let (toggle_coord, reset_coord) = (coord.clone(), coord.clone());
+                html! {
+                    <div
+                        onclick=m.link.callback(|_| Action::HideContextMenu)
+                        class=format!{"cell interactive timer-cell row-{} col-{}{}", coord.row_to_string(), coord.col_to_string(), if running { " running" } else { "" }}
+                        id=cell_id(m, &coord)
+                        style={ get_style(m.get_session().grammars.get(&coord).expect("no grammar with this coordinate"), &m.col_widths, &m.row_heights,  &coord) }>
+                        <span class="timer-value">{ format_duration(elapsed) }</span>
+                        <button class="timer-button"
+                            onclick=m.link.callback(move |_| if running {
+                                Action::StopTimer(toggle_coord.clone())
+                            } else {
+                                Action::StartTimer(toggle_coord.clone())
+                            })>
+                            { if running { "Stop" } else { "Start" } }
+                        </button>
+                        <button class="timer-button" onclick=m.link.callback(move |_| Action::ResetTimer(reset_coord.clone()))>
+                            { "Reset" }
+                        </button>
+                    </div>
+                }
+            }
+            Kind::Interactive(message, Interactive::Reminder(trigger, fired)) => {
+                let (message_coord, mode_coord, trigger_coord) = (coord.clone(), coord.clone(), coord.clone());
+                let (message_trigger, mode_message, trigger_message) = (trigger.clone(), message.clone(), message.clone());
+                let (mode, trigger_input) = match &trigger {
+                    ReminderTrigger::At(time) => ("at", html! {
+                        <input class="reminder-trigger" type="datetime-local" value=time
+                            onchange=m.link.callback(move |e: ChangeData| match e {
+                                ChangeData::Value(time) => Action::ConfigureReminder(trigger_coord.clone(), trigger_message.clone(), ReminderTrigger::At(time)),
+                                _ => Action::Noop,
+                            })>
+                        </input>
+                    }),
+                    ReminderTrigger::When(condition) => ("when", html! {
+                        <input class="reminder-trigger" value=condition placeholder="=root-A1 > 100"
+                            onchange=m.link.callback(move |e: ChangeData| match e {
+                                ChangeData::Value(condition) => Action::ConfigureReminder(trigger_coord.clone(), trigger_message.clone(), ReminderTrigger::When(condition)),
+                                _ => Action::Noop,
+                            })>
+                        </input>
+                    }),
+                };
+                let other_mode = match trigger {
+                    ReminderTrigger::At(_) => ReminderTrigger::When(String::new()),
+                    ReminderTrigger::When(_) => ReminderTrigger::At(String::new()),
+                };
+                html! {
+                    <div
+                        onclick=m.link.callback(|_| Action::HideContextMenu)
+                        class=format!{"cell interactive reminder-cell row-{} col-{}{}", coord.row_to_string(), coord.col_to_string(), if fired { " fired" } else { "" }}
+                        id=cell_id(m, &coord)
+                        title={ if fired { "fired, changing the trigger re-arms it" } else { "" } }
+                        style={ get_style(m.get_session().grammars.get(&coord).expect("no grammar with this coordinate"), &m.col_widths, &m.row_heights,  &coord) }>
+                        <input class="reminder-message" value=message placeholder="remind me to..."
+                            onchange=m.link.callback(move |e: ChangeData| match e {
+                                ChangeData::Value(message) => Action::ConfigureReminder(message_coord.clone(), message, message_trigger.clone()),
+                                _ => Action::Noop,
+                            })>
+                        </input>
+                        <button class="reminder-mode" title="fire at a time, or when a condition becomes true"
+                            onclick=m.link.callback(move |_| Action::ConfigureReminder(mode_coord.clone(), mode_message.clone(), other_mode.clone()))>
+                            { mode }
+                        </button>
+                        { trigger_input }
+                    </div>
+                }
+            }
+            Kind::Interactive(name, Interactive::Shell(command, output)) => {
+                let is_running = m.running_shells.contains(&coord);
+                let (command_coord, output_coord, run_coord) = (coord.clone(), coord.clone(), coord.clone());
+                let current_command = command.clone();
+                html! {
+                    <div
+                        onclick=m.link.callback(|_| Action::HideContextMenu)
+                        class=format!{"cell interactive shell-cell row-{} col-{}", coord.row_to_string(), coord.col_to_string()}
+                        id=cell_id(m, &coord)
+                        style={ get_style(m.get_session().grammars.get(&coord).expect("no grammar with this coordinate"), &m.col_widths, &m.row_heights,  &coord) }>
+                        <input class="shell-command" value=command placeholder="command"
+                            onchange=m.link.callback(move |e: ChangeData| match e {
+                                ChangeData::Value(command) => Action::ConfigureShell(command_coord.clone(), command, output),
+                                _ => Action::Noop,
+                            })>
+                        </input>
+                        <label title="parse the output as CSV into a grid">
+                            <input type="checkbox" checked={output == ShellOutput::Csv}
+                                onclick=m.link.callback(move |_: ClickEvent| {
+                                    let output = match output {
+                                        ShellOutput::Text => ShellOutput::Csv,
+                                        ShellOutput::Csv => ShellOutput::Text,
+                                    };
+                                    Action::ConfigureShell(output_coord.clone(), current_command.clone(), output)
+                                })>
+                            </input>
+                            { "CSV" }
+                        </label>
+                        <button disabled=is_running onclick=m.link.callback(move |_| Action::RunShell(run_coord.clone()))>
+                            { if is_running { "Running...".to_string() } else { name } }
+                        </button>
+                    </div>
+                }
+            }
+            Kind::Grid(sub_coords) if m.is_collapsed(&coord) => view_collapsed_grid(m, &coord, &sub_coords),
             Kind::Grid(sub_coords) => view_grid_grammar(
                 m,
                 &coord,
@@ -549,6 +3799,7 @@ pub fn view_grammar(m: &Model, coord: Coordinate) -> Html {
                 view_defn_grammar(m, &coord, &defn_coord, name, sub_grammars)
             }
             Kind::Editor(content) => view_editor_grammar(m, &coord, content),
+            Kind::Transclude(path, source) => view_transclude_grammar(m, &coord, &path, &source),
         }
     } else {
         html! { <></> }
@@ -562,6 +3813,47 @@ pub fn view_editor_grammar(m: &Model, coord: &Coordinate, content: String) -> Ht
     }
 }
 
+// Read-only table of a grid in another session file. A stale view (the file changed but didn't
+// load) still shows the last content that did, a broken one only shows why.
+pub fn view_transclude_grammar(m: &Model, coord: &Coordinate, path: &str, source: &Coordinate) -> Html {
+    let (rows, status, problem) = match m.transclusions.get(path) {
+        None => (Err("loading...".to_string()), "broken", None),
+        Some(transclusion) => match &transclusion.status {
+            TransclusionStatus::Live => (transclusion.rows(source), "live", None),
+            TransclusionStatus::Stale(e) => (transclusion.rows(source), "stale", Some(e.clone())),
+            TransclusionStatus::Broken(e) => (Err(e.clone()), "broken", None),
+        },
+    };
+    let (content, status) = match rows {
+        Ok(rows) => (
+            html! {
+                <table class="transclusion-table">
+                    { for rows.into_iter().map(|row| html! {
+                        <tr>{ for row.into_iter().map(|text| html! { <td>{ text }</td> }) }</tr>
+                    }) }
+                </table>
+            },
+            status,
+        ),
+        Err(e) => (html! { <div class="transclusion-error">{ e }</div> }, "broken"),
+    };
+    let c = coord.clone();
+    html! {
+        <div
+            onclick=m.link.callback(move |_| Action::SetActiveCell(c.clone()))
+            class=format!{"cell transclusion transclusion-{} row-{} col-{}", status, coord.row_to_string(), coord.col_to_string()}
+            id=cell_id(m, &coord)
+            title=problem.unwrap_or_default()
+            style={ get_style(m.get_session().grammars.get(&coord).expect("no grammar with this coordinate"), &m.col_widths, &m.row_heights,  &coord) }>
+            <div class="transclusion-header">
+                <span>{ format!{"{} › {}", path, source.to_string()} }</span>
+                <span class="transclusion-status">{ status }</span>
+            </div>
+            { content }
+        </div>
+    }
+}
+
 pub fn view_defn_grammar(
     m: &Model,
     coord: &Coordinate,
@@ -585,11 +3877,24 @@ pub fn view_defn_grammar(
         index += 1;
     }
     let c = coord.clone();
+    // definitions on the meta sheet offer to propagate their changes to their instances
+    let propagate_button = if coord.truncate(1) == Some(coord!("meta")) {
+        let count = instances(&m.get_session().grammars, coord).len();
+        let defn = coord.clone();
+        html! {
+            <button class="menu-bar-button propagate-button" disabled=count == 0
+                onclick=m.link.callback(move |_| Action::OpenPropagation(defn.clone()))>
+                { format!{"Propagate to {} instances...", count} }
+            </button>
+        }
+    } else {
+        html! { <></> }
+    };
     html! {
         <div
             onclick=m.link.callback(|_| Action::HideContextMenu)
             class=format!{"cell grid row-{} col-{}", coord.row_to_string(), coord.col_to_string()}
-            id=format!{"cell-{}", coord.to_string()}
+            id=cell_id(m, &coord)
             style={ get_style(m.get_session().grammars.get(&coord).expect("no grammar with this coordinate"), &m.col_widths, &m.row_heights,  &coord) }>
             <input
                 class="cell"
@@ -597,6 +3902,7 @@ pub fn view_defn_grammar(
                 // oninput=m.link.callback(move |e : InputData| Action::DefnUpdateName(c.clone(), e.value))>
             </input>
             { nodes }
+            { propagate_button }
         </div>
     }
 }
@@ -616,7 +3922,7 @@ pub fn view_defn_variant_grammar(
         <div
             onclick=m.link.callback(|_| Action::HideContextMenu)
             class=format!{"cell variant row-{} col-{}", coord.row_to_string(), coord.col_to_string()}
-            id=format!{"cell-{}", coord.to_string()}
+            id=cell_id(m, &coord)
             // style={ get_style(&m, &coord) }>
             style={ get_style(m.get_session().grammars.get(&coord).expect("no grammar with this coordinate"), &m.col_widths, &m.row_heights,  &coord) }>
             { nodes }
@@ -664,7 +3970,7 @@ pub fn view_lookup_grammar(
         <div
             onclick=m.link.callback(|_| Action::HideContextMenu)
             class=format!{"cell suggestion lookup row-{} col-{}", coord.row_to_string(), coord.col_to_string()}
-            id=format!{"cell-{}", coord.to_string()}
+            id=cell_id(m, &coord)
             style={ get_style(m.get_session().grammars.get(&coord).expect("no grammar with this coordinate"), &m.col_widths, &m.row_heights,  &coord) }>
             <b style=format!{"font-size: 20px; color: {};", random_color()}>{ "$" }</b>
             <div contenteditable=true
@@ -712,39 +4018,76 @@ pub fn view_input_grammar(
     }
 
     // load the suggestion values, including the completion callbacks
-    // and parse them into DOM nodes
-    let suggestions_len = if value.clone() != "" && is_active {
-        suggestions.len()
-    } else {
-        0
-    };
+    // and parse them into DOM nodes, grouped into sections by category
+    // (with the most used suggestions in a "Recent" section on top)
+    let mut suggestions_len = 0;
     let suggestions = if value.clone() != "" && is_active {
         let mut suggestion_nodes = VList::new();
         let mut suggestion_index = 1;
-        for (s_coord, s_grammar) in suggestions {
-            let s_coord_2 = s_coord.clone();
-            let c = coord.clone();
-            let dest_coord = coord.clone();
+        // values already entered in the same column come first, most frequent first
+        let column_values = match coord.parent() {
+            Some(_) => column_completions(&m.column_index, &coord.full_col(), &value, COLUMN_COMPLETION_LIMIT),
+            None => vec![],
+        };
+        if !column_values.is_empty() {
             suggestion_nodes.add_child(html! {
-                    <a 
-                        id=format!{"cell-{}-suggestion-{}", c.to_string(), suggestion_index}
-                        tabindex=2
-                        onkeydown=m.link.callback(move |e : KeyDownEvent| {
-                            Action::HideContextMenu;
-                            if e.code() == "Tab" {
-                                e.prevent_default();
-                                return Action::NextSuggestion(c.clone(), if e.shift_key() { suggestion_index-1 } else { suggestion_index+1 });
-                            } else if e.code() == "Enter" || e.code() == "Space" {
-                                return Action::DoCompletion(s_coord_2.clone(), c.clone());
-                            }
-                            Action::Noop
-                        })
-                        onclick=m.link.callback(move |_ : ClickEvent| Action::DoCompletion(s_coord.clone(), dest_coord.clone()))>
-                        { &s_grammar.name }
-                    </a>
+                <button class="suggestion-category" tabindex=-1>{ "Column values" }</button>
+            });
+            for column_value in column_values {
+                let c = coord.clone();
+                let label = column_value.clone();
+                suggestion_nodes.add_child(view_suggestion(m, &coord, &label, suggestion_index, None, move || {
+                    Action::ChangeInput(c.clone(), column_value.clone())
+                }));
+                suggestion_index += 1;
+            }
+        }
+        // grammars that don't fit the shape expected in this cell are marked, with the reason why
+        let expected = m
+            .get_session()
+            .grammars
+            .get(&coord)
+            .and_then(|g| g.style.shape.clone())
+            .and_then(|declared| Shape::parse(&declared).ok());
+        let recent = most_used(&m.preferences.suggestion_usage, &suggestions, RECENT_LIMIT);
+        let mut sections = vec![(None, recent)];
+        for (category, section) in group_by_category(&suggestions) {
+            sections.push((Some(category), by_usage(&m.preferences.suggestion_usage, &section)));
+        }
+        for (category, section) in sections {
+            if section.is_empty() {
+                continue;
+            }
+            let collapsed = category
+                .map(|c| m.preferences.collapsed_suggestion_categories.contains(&c))
+                .unwrap_or(false);
+            let label = category.map(|c| c.label()).unwrap_or("Recent");
+            suggestion_nodes.add_child(html! {
+                <button
+                    class="suggestion-category"
+                    tabindex=-1
+                    onclick=m.link.callback(move |_ : ClickEvent| {
+                        category.map_or(Action::Noop, Action::ToggleSuggestionCategory)
+                    })>
+                    { format!{"{} {}", if collapsed { "+" } else { "-" }, label} }
+                </button>
+            });
+            if collapsed {
+                continue;
+            }
+            for (s_coord, s_grammar) in section {
+                let c = coord.clone();
+                let reason = expected.as_ref().and_then(|shape| {
+                    mismatch(&m.get_session().grammars, &s_coord, shape)
+                        .map(|reason| format! {"expects {}: {}", shape.describe(), reason})
                 });
-            suggestion_index += 1;
+                suggestion_nodes.add_child(view_suggestion(m, &coord, &s_grammar.name, suggestion_index, reason, move || {
+                    Action::DoCompletion(s_coord.clone(), c.clone())
+                }));
+                suggestion_index += 1;
+            }
         }
+        suggestions_len = (suggestion_index - 1) as usize;
         html! {
             <div
             onclick=m.link.callback(|_| Action::HideContextMenu)
@@ -759,26 +4102,56 @@ pub fn view_input_grammar(
      * Calculate if a specific cell should be selected based on the top-rightmost
      * and bottom-leftmost cells
      */
-    let is_selected = cell_is_selected(&coord, &m.first_select_cell, &m.last_select_cell);
+    let is_selected = m.selection.contains(&coord);
     let has_lookup_prefix: bool = value.clone() == "$";
     let current_coord = coord.clone();
     let tab_coord = coord.clone();
+    let pick_coord = coord.clone();
+    // while a lookup or formula is being edited, clicking this cell inserts its coordinate there
+    let is_picking = m.reference_target().map_or(false, |c| c != coord);
+    let is_duplicate_row = match &m.duplicates {
+        Some((grid, rows)) => coord.parent().as_ref() == Some(grid) && rows.contains(&coord.row().get()),
+        None => false,
+    };
+    let step_value = value.clone();
+    let step_coord = coord.clone();
+    let numeric_step = m.preferences.numeric_step;
     let focus_coord = coord.clone();
     let drag_coord = coord.clone();
     let is_hovered_on = coord.clone();
     let shift_key_pressed = m.shift_key_pressed;
     let new_selected_cell = coord.clone();
+    let press_selected_cell = coord.clone();
+    let hover_selected_cell = coord.clone();
+    let is_resizing = m.resizing.is_some();
     let cell_classes =
         format! {"cell suggestion row-{} col-{}", coord.row_to_string(), coord.col_to_string()};
+    let placeholder = m
+        .get_session()
+        .grammars
+        .get(&coord)
+        .and_then(|g| g.style.placeholder.clone());
+    let spellcheck = spellcheck_enabled(m, &coord).to_string();
     let cell_data_classes = format! {
-        "cell-data {} {}",
+        "cell-data {} {} {} {} {}",
         if is_active { "cell-active " } else { "cell-inactive" },
-        if is_selected { "selection" } else { "" }
+        if is_selected { "selection" } else { "" },
+        if placeholder.is_some() { "placeholder" } else { "" },
+        if is_picking { "picking-reference" } else { "" },
+        if is_duplicate_row { "duplicate-row" } else { "" }
     };
 
     // relevant coordinates for navigation purposes
+    let outline_groups = m.get_session().outline_groups.clone();
     let neighbor_left = current_coord
         .neighbor_left()
+        .and_then(|mut c| {
+            // skip over columns hidden by collapsed outline groups or spanning cells
+            while is_hidden(&outline_groups, &c) || is_covered_by_span(m, &c) {
+                c = c.neighbor_left()?;
+            }
+            Some(c)
+        })
         .and_then(|c| {
             // check if grammar corresponding to left neighbor coord exists...
             m.get_session().grammars.get(&c).map(|g| {
@@ -793,6 +4166,13 @@ pub fn view_input_grammar(
         .clone();
     let neighbor_right = current_coord
         .neighbor_right()
+        .map(|mut c| {
+            // skip over columns hidden by collapsed outline groups or spanning cells
+            while is_hidden(&outline_groups, &c) || is_covered_by_span(m, &c) {
+                c = c.neighbor_right().unwrap();
+            }
+            c
+        })
         .and_then(|c| {
             // check if grammar corresponding to right neighbor coord exists...
             m.get_session().grammars.get(&c).map(|g| {
@@ -811,6 +4191,10 @@ pub fn view_input_grammar(
         if let Some(t) = temp {
             let col = t.col_mut();
             *col = NonZeroU32::new(1).unwrap();
+            // skip over rows hidden by collapsed outline groups or spanning cells
+            while is_hidden(&outline_groups, &t) || is_covered_by_span(m, &t) {
+                *t = t.neighbor_below().unwrap();
+            }
             if m.get_session().grammars.contains_key(&t) {
                 Some(t.clone())
             } else {
@@ -820,15 +4204,56 @@ pub fn view_input_grammar(
             None
         }
     };
-    let last_col_prev_row = /* TODO: get the correct value of this */ current_coord.neighbor_above();
+    let last_col_prev_row = /* TODO: get the correct value of this */ current_coord
+        .neighbor_above()
+        .and_then(|mut c| {
+            // skip over rows hidden by collapsed outline groups or spanning cells
+            while is_hidden(&outline_groups, &c) || is_covered_by_span(m, &c) {
+                c = c.neighbor_above()?;
+            }
+            Some(c)
+        });
 
+    let in_placeholder_mode = !m.placeholders.is_empty();
+    let formula_result = view_formula_result(m, &coord);
+    // empty cells show what an array formula spilled into them
+    let spilled_value = if value.trim().is_empty() && !is_active {
+        m.values
+            .get(&coord)
+            .map(|v| localize(&v.to_string(), m.preferences.locale))
+    } else {
+        None
+    };
+    // numbers, dates & money are shown in the user's locale, but edited as they're stored
+    let value = if is_active || is_formula(&value) {
+        value
+    } else {
+        localize(&value, m.preferences.locale)
+    };
+    let is_editing_formula = m.formula_edit.as_ref() == Some(&coord);
+    // where Enter & Shift+Enter move to, going through the selection when it's a data entry range
+    let enter_range = m.selection.corners();
+    let enter_range = enter_range.as_ref().map(|(first, last)| (first, last));
+    let enter_behavior = m.preferences.enter_behavior;
+    let enter_next = next_cell(&m.get_session().grammars, &coord, enter_range, enter_behavior, false);
+    let enter_back = next_cell(&m.get_session().grammars, &coord, enter_range, enter_behavior, true);
     let keydownhandler = m.link.callback(move |e: KeyDownEvent| {
         // info! {"suggestion len {}", suggestions_len}
+        if in_placeholder_mode && e.code() == "Escape" {
+            return Action::ExitPlaceholderMode;
+        }
+        if is_editing_formula && e.code() == "Escape" {
+            return Action::EndFormulaEdit;
+        }
         if e.code() == "Tab" {
             e.prevent_default();
             if suggestions_len > 0 {
                 return Action::NextSuggestion(tab_coord.clone(), 1);
             }
+            // after completing a snippet, Tab hops between its placeholders until they're filled
+            if in_placeholder_mode {
+                return Action::NextPlaceholder;
+            }
             let next_active_cell = if e.shift_key() {
                 neighbor_left
                     .clone()
@@ -843,9 +4268,54 @@ pub fn view_input_grammar(
             // info! {"next_active_cell {}", next_active_cell.clone().unwrap().to_string()};
             return next_active_cell.map_or(Action::Noop, |c| Action::SetActiveCell(c));
         } 
+        // the cell's edits are already in the session, so Enter only has to move on (Alt+Enter
+        // still starts a new line)
+        if e.code() == "Enter" && enter_behavior != EnterBehavior::Newline && suggestions_len == 0 && !e.alt_key() {
+            e.prevent_default();
+            let next = if e.shift_key() { enter_back.clone() } else { enter_next.clone() };
+            return next.map_or(Action::Noop, Action::SetActiveCell);
+        }
         if is_selected && (e.code() == "Backspace" || e.code() == "Delete") {       
             return Action::RangeDelete();
         }
+        // Ctrl+D duplicates the row of the cell, Ctrl+Shift+D its column
+        if e.ctrl_key() && e.code() == "KeyD" {
+            e.prevent_default();
+            return if e.shift_key() { Action::DuplicateCol } else { Action::DuplicateRow };
+        }
+        // Alt+Arrow swaps the cell with its neighbor that way, Alt+Shift+Arrow moves its row
+        // (Up/Down) or column (Left/Right) over by one
+        if e.alt_key() && !e.ctrl_key() && e.code().starts_with("Arrow") {
+            let (axis, forward) = match e.code().as_str() {
+                "ArrowUp" => (OutlineAxis::Rows, false),
+                "ArrowDown" => (OutlineAxis::Rows, true),
+                "ArrowLeft" => (OutlineAxis::Cols, false),
+                _ => (OutlineAxis::Cols, true),
+            };
+            e.prevent_default();
+            return if e.shift_key() {
+                Action::MoveActiveLine(axis, forward)
+            } else {
+                Action::SwapActiveCell(axis, forward)
+            };
+        }
+        // Ctrl+Up/Down steps numbers by the step in the settings and dates by a day,
+        // with Shift: numbers by 10 steps and dates by a month
+        if e.ctrl_key() && (e.code() == "ArrowUp" || e.code() == "ArrowDown") {
+            e.prevent_default();
+            let count = if e.code() == "ArrowUp" { 1 } else { -1 };
+            let is_date = match Value::parse(&step_value) {
+                Value::Date(_) => true,
+                _ => false,
+            };
+            let (count, unit) = match (is_date, e.shift_key()) {
+                (true, false) => (count, StepUnit::Days),
+                (true, true) => (count, StepUnit::Months),
+                (false, false) => (count, StepUnit::Step(numeric_step)),
+                (false, true) => (count * 10, StepUnit::Step(numeric_step)),
+            };
+            return Action::StepValue(step_coord.clone(), count, unit);
+        }
         Action::Noop
     });
 <<<<<<< HEAD
@@ -861,7 +4331,7 @@ pub fn view_input_grammar(
         <div
             onclick=m.link.callback(|_| Action::HideContextMenu)
             class=cell_classes
-            id=format!{"cell-{}", coord.to_string()}
+            id=cell_id(m, &coord)
 <<<<<<< HEAD
             style={ get_style(m.get_session().grammars.get(&coord).expect("no grammar with this coordinate"), &m.col_widths, &m.row_heights,  &coord) }>
 =======
@@ -872,6 +4342,8 @@ pub fn view_input_grammar(
             <div contenteditable=true
 
                 class=cell_data_classes
+                spellcheck=spellcheck
+                data-placeholder=placeholder.unwrap_or_default()
                 onkeydown=keydownhandler
                 onkeypress=m.link.callback(move |e : KeyPressEvent| {
                     if e.code() == "Space" && has_lookup_prefix {
@@ -883,8 +4355,13 @@ pub fn view_input_grammar(
                     Action::ChangeInput(coord.clone(), e.value)
                 })
                 onclick=m.link.callback(move |e : ClickEvent| {
-                    if e.shift_key() {
+                    if is_picking {
+                        // the click was used to pick this cell as a reference (see onmousedown)
+                        Action::Noop
+                    } else if e.shift_key() {
                         Action::Select(SelectMsg::End(new_selected_cell.clone()))
+                    } else if e.ctrl_key() {
+                        Action::Select(SelectMsg::Add(new_selected_cell.clone()))
                     } else {
                         Action::Select(SelectMsg::Start(new_selected_cell.clone()))
                     }
@@ -902,6 +4379,10 @@ pub fn view_input_grammar(
                     * - onmousedown/up: handle resize events
                     */
                 onmouseover=m.link.callback(move |e: MouseOverEvent| {
+                    // dragging over cells with the left button down selects up to them
+                    if e.buttons().is_down(MouseButton::Left) && !is_picking && !is_resizing {
+                        return Action::Select(SelectMsg::End(hover_selected_cell.clone()));
+                    }
                     let (offset_x, offset_y) = {
                         // compute the distance from the right & bottom borders that resizing is allowed
                         let target = HtmlElement::try_from(e.target().unwrap()).unwrap();
@@ -926,15 +4407,33 @@ pub fn view_input_grammar(
                     };
                     // info!{"offset: {} {}", offset_x, offset_y};
                     let draggable_area = 4.0;
-                    if offset_x < draggable_area  || offset_y < draggable_area {
+                    if is_picking {
+                        // keep the focus (and caret) in the reference being edited
+                        e.prevent_default();
+                        Action::PickReference(pick_coord.clone())
+                    } else if offset_x < draggable_area  || offset_y < draggable_area {
                         Action::Resize(ResizeMsg::Start(drag_coord.clone()))
-                    } else {
+                    } else if e.shift_key() {
                         Action::Noop
+                    } else if e.ctrl_key() {
+                        Action::Select(SelectMsg::Add(press_selected_cell.clone()))
+                    } else {
+                        // a drag starts where the button is pressed (see onmouseover)
+                        Action::Select(SelectMsg::Start(press_selected_cell.clone()))
                     }
 <<<<<<< HEAD
                 })
                 ondrop=drophandler >
-                { value }
+                {
+                    // formulas are shown highlighted, except while they're being typed into
+                    if is_formula(&value) && !is_active {
+                        html! { <>{ view_formula_text(&value) }{ formula_result }</> }
+                    } else if let Some(spilled) = spilled_value {
+                        html! { <span class="spilled-value">{ spilled }</span> }
+                    } else {
+                        html! { { value } }
+                    }
+                }
 =======
                 })>
                 // { value }
@@ -945,13 +4444,179 @@ pub fn view_input_grammar(
     }
 }
 
+// Text of a formula with its tokens colored by kind. References get the same color as the
+// cells they point to are highlighted in, and a parse error is underlined (the message shows
+// on hover).
+fn view_formula_text(text: &str) -> Html {
+    let chars: Vec<char> = text.chars().collect();
+    let error = parse_formula(text).err();
+    let spans = reference_spans(text);
+    let colors = colored_references(text);
+    let mut nodes = VList::new();
+    nodes.add_child(html! { <span class="formula-token formula-operator">{ "=" }</span> });
+    let mut last_end = 1;
+    for token in tokenize(text) {
+        let (start, end) = token.span;
+        if start > last_end {
+            let gap: String = chars[last_end..start].iter().collect();
+            nodes.add_child(html! { { gap } });
+        }
+        last_end = end;
+        let color = spans
+            .iter()
+            .find(|((s, e), _)| *s <= start && end <= *e)
+            .and_then(|(_, r)| colors.iter().find(|(c, _)| c == r))
+            .map_or("".to_string(), |(_, color)| format! {"color: {};", color});
+        let (error_class, title) = match &error {
+            Some(e) if e.span.0 < end && start < e.span.1.max(e.span.0 + 1) => {
+                ("formula-error", e.message.clone())
+            }
+            _ => ("", "".to_string()),
+        };
+        let class = format! {"formula-token formula-{} {}", format!{"{:?}", token.kind}.to_lowercase(), error_class};
+        nodes.add_child(html! {
+            <span class=class style=color title=title>{ token.text }</span>
+        });
+    }
+    if last_end < chars.len() {
+        let gap: String = chars[last_end..].iter().collect();
+        nodes.add_child(html! { { gap } });
+    }
+    // errors at the very end (like a missing operand) are marked right after the text
+    if let Some(e) = error.filter(|e| e.span.0 >= last_end) {
+        nodes.add_child(html! {
+            <span class="formula-error formula-error-end" title=e.message>{ " " }</span>
+        });
+    }
+    html! { <>{ nodes }</> }
+}
+
+// The computed value of a formula cell, with a badge if it's stale (see `CalcMode::Manual`)
+fn view_formula_result(m: &Model, coord: &Coordinate) -> Html {
+    let value = m
+        .values
+        .get(coord)
+        .map_or("".to_string(), |v| localize(&v.to_string(), m.preferences.locale));
+    let is_error = match m.values.get(coord) {
+        Some(FormulaValue::Error(_)) => "formula-result-error",
+        _ => "",
+    };
+    // formulas in a cycle show the way they read back from themselves
+    let cycle = match m.values.get(coord) {
+        Some(FormulaValue::Error(e)) if e == "#CYCLE!" => m.deps.cycle_through(coord).map(|path| {
+            let path: Vec<String> = path.iter().map(|c| c.to_string()).collect();
+            format! {"circular reference: {}", path.join(" → ")}
+        }),
+        _ => None,
+    };
+    html! {
+        <span class=format!{"formula-result {}", is_error} title=cycle.unwrap_or_default()>
+            { format!{"→ {}", value} }
+            {
+                if m.stale.contains(coord) {
+                    html! { <span class="stale-badge" title="stale: press F9 to recalculate">{ "•" }</span> }
+                } else {
+                    html! { <></> }
+                }
+            }
+        </span>
+    }
+}
+
+// Status bar at the bottom of the window: calculation mode and progress
+pub fn view_status_bar(m: &Model) -> Html {
+    let calc_status = if !m.calc_queue.is_empty() {
+        format! {"Calculating… {}/{}", m.calc_total - m.calc_queue.len(), m.calc_total}
+    } else if !m.stale.is_empty() {
+        format! {"{} stale formulas", m.stale.len()}
+    } else {
+        "Calculated".to_string()
+    };
+    let mode = match m.preferences.calc_mode {
+        CalcMode::Automatic => "Automatic",
+        CalcMode::Manual => "Manual",
+    };
+    html! {
+        <div class="status-bar">
+            <span>{ format!{"Calculation: {}", mode} }</span>
+            <span>{ calc_status }</span>
+            { m.calc_report.clone().map_or(html! { <></> }, |report| html! { <span>{ report }</span> }) }
+            {
+                match &m.duplicates {
+                    Some((_, rows)) => html! {
+                        <span class="duplicates-preview">
+                            { format!{"{} duplicate rows highlighted", rows.len()} }
+                            <button class="menu-bar-button" onclick=m.link.callback(|_| Action::RemoveDuplicates)>{ "Remove" }</button>
+                            <button class="menu-bar-button" onclick=m.link.callback(|_| Action::ClearDuplicates)>{ "Cancel" }</button>
+                        </span>
+                    },
+                    None => html! { <></> },
+                }
+            }
+            { for m.external_conflicts.iter().map(|grid| {
+                let path = m.get_session().external_sources.iter()
+                    .find(|source| source.grid == *grid)
+                    .map_or("".to_string(), |source| source.path.clone());
+                let (overwrite, unbind) = (grid.clone(), grid.clone());
+                html! {
+                    <span class="external-conflict">
+                        { format!{"{} changed, but {} was edited since it was imported", path, grid.to_string()} }
+                        <button class="menu-bar-button" onclick=m.link.callback(move |_| Action::RefreshExternalSource(overwrite.clone(), true))>{ "Overwrite" }</button>
+                        <button class="menu-bar-button" onclick=m.link.callback(move |_| Action::UnbindExternalSource(unbind.clone()))>{ "Unbind" }</button>
+                    </span>
+                }
+            }) }
+            <button class="menu-bar-button" onclick=m.link.callback(|_| Action::Recalculate)>
+                { "Calculate now (F9)" }
+            </button>
+        </div>
+    }
+}
+
+// A single entry of the completion dropdown of `coord`. Entries are numbered from 1 in the order
+// they're shown, which is what Tab/Shift+Tab navigation goes by.
+// `complete` produces the action that's sent when the entry is picked.
+fn view_suggestion<F>(
+    m: &Model,
+    coord: &Coordinate,
+    label: &str,
+    suggestion_index: i32,
+    mismatch: Option<String>,
+    complete: F,
+) -> Html
+where
+    F: Fn() -> Action + Clone + 'static,
+{
+    let c = coord.clone();
+    let complete_on_click = complete.clone();
+    html! {
+        <a
+            id=format!{"{}-suggestion-{}", cell_id(m, &c), suggestion_index}
+            class=if mismatch.is_some() { "suggestion-mismatch" } else { "" }
+            title=mismatch.unwrap_or_default()
+            tabindex=2
+            onkeydown=m.link.callback(move |e : KeyDownEvent| {
+                if e.code() == "Tab" {
+                    e.prevent_default();
+                    return Action::NextSuggestion(c.clone(), if e.shift_key() { suggestion_index-1 } else { suggestion_index+1 });
+                } else if e.code() == "Enter" || e.code() == "Space" {
+                    return complete();
+                }
+                Action::Noop
+            })
+            onclick=m.link.callback(move |_ : ClickEvent| complete_on_click())>
+            { label }
+        </a>
+    }
+}
+
 pub fn view_text_grammar(m: &Model, coord: &Coordinate, value: String, is_active: bool) -> Html {
-    let is_selected = cell_is_selected(coord, &m.first_select_cell, &m.last_select_cell);
+    let is_selected = m.selection.contains(coord);
     html! {
         <div
             onclick=m.link.callback(|_| Action::HideContextMenu)
             class=format!{"cell suggestion row-{} col-{}", coord.row_to_string(), coord.col_to_string(),}
-            id=format!{"cell-{}", coord.to_string()}
+            id=cell_id(m, &coord)
             // style={ get_style(&m, &coord) }>
             style={ get_style(m.get_session().grammars.get(&coord).expect("no grammar with this coordinate"), &m.col_widths, &m.row_heights,  &coord) }>
             <div
@@ -967,28 +4632,179 @@ pub fn view_text_grammar(m: &Model, coord: &Coordinate, value: String, is_active
                         m.focus_node_ref.clone()
                     } else { NodeRef::default() }
                 }>
-                { value }
+                { view_text_value(m, value) }
             </div>
         </div>
     }
 }
 
+// A text cell that's only a reference to an image asset shows the image
+fn view_text_value(m: &Model, value: String) -> Html {
+    let trimmed = value.trim();
+    let image = if trimmed.starts_with(ASSET_PREFIX) {
+        let name = &trimmed[ASSET_PREFIX.len()..];
+        m.get_session().assets.iter().find(|a| a.is_image() && a.name == name)
+    } else {
+        None
+    };
+    match image {
+        Some(asset) => html! { <img class="asset-image" src=asset.data_url() title=value.clone() /> },
+        None => html! { <>{ value }</> },
+    }
+}
+
+// Refresh button of a grid bound to a CSV/JSON file or a remote CSV (see `ExternalSource`)
+fn view_external_source_badge(m: &Model, coord: &Coordinate) -> Html {
+    let source = match m.get_session().external_sources.iter().find(|s| s.grid == *coord) {
+        Some(source) => source,
+        None => return html! { <></> },
+    };
+    let c = coord.clone();
+    html! {
+        <button
+            class=format!{"external-source-refresh{}{}",
+                if m.external_conflicts.contains(coord) { " conflict" } else { "" },
+                if m.fetching_sources.contains(coord) { " fetching" } else { "" }}
+            title=format!{"Refresh from {}", source.path}
+            onclick=m.link.callback(move |e: ClickEvent| {
+                e.stop_propagation();
+                Action::RefreshExternalSource(c.clone(), false)
+            })>
+            { "⟳" }
+        </button>
+    }
+}
+
 pub fn view_grid_grammar(m: &Model, coord: &Coordinate, sub_coords: Vec<Coordinate>) -> Html {
     let mut nodes = VList::new();
     for c in sub_coords {
         nodes.add_child(view_grammar(m, c.clone()));
     }
+    nodes.add_child(view_outline_gutter(m, coord));
+    nodes.add_child(view_row_handles(m, coord));
+    nodes.add_child(view_external_source_badge(m, coord));
     html! {
         <div
             onclick=m.link.callback(|_| Action::HideContextMenu)
             class=format!{"\ncell grid row-{} col-{}", coord.row_to_string(), coord.col_to_string()}
-            id=format!{"cell-{}", coord.to_string()}
+            id=cell_id(m, &coord)
             style={ get_style(m.get_session().grammars.get(&coord).expect("no grammar with this coordinate"), &m.col_widths, &m.row_heights,  &coord) }>
             { nodes }
         </div>
     }
 }
 
+// A collapsed nested grid: a chip with its name and dimensions, and a control expanding it. Its
+// cells aren't rendered at all while it's collapsed.
+fn view_collapsed_grid(
+    m: &Model,
+    coord: &Coordinate,
+    sub_coords: &[(NonZeroU32, NonZeroU32)],
+) -> Html {
+    let grammar = m.get_session().grammars.get(coord).expect("no grammar with this coordinate");
+    let rows = sub_coords.iter().map(|(r, _)| r.get()).max().unwrap_or(0);
+    let cols = sub_coords.iter().map(|(_, c)| c.get()).max().unwrap_or(0);
+    let name = if grammar.name.is_empty() { coord.to_string() } else { grammar.name.clone() };
+    let (select_coord, expand_coord) = (coord.clone(), coord.clone());
+    html! {
+        <div
+            class=format!{"cell collapsed-grid row-{} col-{}", coord.row_to_string(), coord.col_to_string()}
+            id=cell_id(m, &coord)
+            style={ format!{"{}display: flex;", get_style(grammar, &m.col_widths, &m.row_heights, coord)} }
+            onclick=m.link.callback(move |_| Action::SetActiveCell(select_coord.clone()))>
+            <span class="grid-chip" title=coord.to_string()>
+                <span class="grid-chip-name">{ name }</span>
+                <span class="grid-chip-size">{ format!{"{} \u{d7} {}", rows, cols} }</span>
+                <button class="grid-chip-expand" title="Expand"
+                    onclick=m.link.callback(move |_| Action::ToggleCollapsedGrid(expand_coord.clone()))>
+                    { "\u{25b8}" }
+                </button>
+            </span>
+        </div>
+    }
+}
+
+// Handles the rows of a grid are dragged by, to move them to another row of the same grid.
+// Like the outline toggles, they're positioned against their row so they don't take up a slot
+// in the grid layout.
+pub fn view_row_handles(m: &Model, coord: &Coordinate) -> Html {
+    let rows = match m.get_session().grammars.get(coord) {
+        Some(Grammar {
+            kind: Kind::Grid(sub_coords),
+            ..
+        }) => sub_coords.iter().map(|(r, _)| r.get()).max().unwrap_or(0),
+        _ => 0,
+    };
+    if rows < 2 {
+        return html! { <></> };
+    }
+    // rows are only dropped onto rows of the grid they were dragged from
+    let dragged_from = m
+        .dragged_row
+        .clone()
+        .filter(|(grid, _)| grid == coord)
+        .map(|(_, row)| row);
+    let mut handles = VList::new();
+    for row in 1..=rows {
+        let (drag_grid, drop_grid) = (coord.clone(), coord.clone());
+        let class = if dragged_from == Some(row) { "row-handle dragged-row" } else { "row-handle" };
+        handles.add_child(html! {
+            <div
+                class=class
+                style=format!{"grid-row: {} / {};", row, row + 1}
+                title="Drag to move this row"
+                draggable="true"
+                ondragstart=m.link.callback(move |_: DragStartEvent| Action::DragRow(Some((drag_grid.clone(), row))))
+                ondragover=m.link.callback(move |e: DragOverEvent| {
+                    if dragged_from.is_some() {
+                        e.prevent_default();
+                    }
+                    Action::Noop
+                })
+                ondrop=m.link.callback(move |e: DragDropEvent| match dragged_from {
+                    Some(from) => {
+                        e.prevent_default();
+                        Action::MoveRow(drop_grid.clone(), from, row)
+                    }
+                    None => Action::Noop,
+                })
+                ondragend=m.link.callback(|_: DragEndEvent| Action::DragRow(None))>
+                { "\u{2261}" }
+            </div>
+        });
+    }
+    html! { { handles } }
+}
+
+// +/- controls for the outline groups of a grid. These are absolutely positioned against
+// the summary row/col of each group so they don't take up a slot in the grid layout.
+pub fn view_outline_gutter(m: &Model, coord: &Coordinate) -> Html {
+    let mut toggles = VList::new();
+    for (index, group) in groups_of(&m.get_session().outline_groups, coord) {
+        let (class, position) = match group.axis {
+            OutlineAxis::Rows => (
+                "outline-toggle outline-toggle-row",
+                format! {"grid-row: {} / {};", group.start.get(), group.end.get() + 1},
+            ),
+            OutlineAxis::Cols => (
+                "outline-toggle outline-toggle-col",
+                format! {"grid-column: {} / {};", group.start.get(), group.end.get() + 1},
+            ),
+        };
+        toggles.add_child(html! {
+            <button
+                class=class
+                style=position
+                title=format!{"{}-{}", group.start.get(), group.end.get()}
+                onclick=m.link.callback(move |_ : ClickEvent| Action::ToggleOutlineGroup(index))
+                ondoubleclick=m.link.callback(move |_ : DoubleClickEvent| Action::RemoveOutlineGroup(index))>
+                { if group.collapsed { "+" } else { "-" } }
+            </button>
+        });
+    }
+    html! { { toggles } }
+}
+
 pub fn view_context_menu(m: &Model) -> Html {
     let default_options = vec![
         (
@@ -1015,6 +4831,18 @@ pub fn view_context_menu(m: &Model) -> Html {
             true,
             1,
         ),
+        (
+            "Duplicate Row (Ctrl+D)",
+            m.link.callback(|_| Action::DuplicateRow),
+            true,
+            1,
+        ),
+        (
+            "Duplicate Col (Ctrl+Shift+D)",
+            m.link.callback(|_| Action::DuplicateCol),
+            true,
+            1,
+        ),
         (
             "----------",
             m.link.callback(|_| Action::HideContextMenu),
@@ -1034,6 +4862,18 @@ pub fn view_context_menu(m: &Model) -> Html {
             true,
             2,
         ),
+        (
+            "Fit Window (Ctrl+9)",
+            m.link.callback(|_| Action::ZoomToFit),
+            true,
+            2,
+        ),
+        (
+            "Fit Selection (Ctrl+Shift+9)",
+            m.link.callback(|_| Action::ZoomToSelection),
+            false,
+            2,
+        ),
         (
             "----------",
             m.link.callback(|_| Action::HideContextMenu),
@@ -1041,9 +4881,255 @@ pub fn view_context_menu(m: &Model) -> Html {
             0,
         ),
 
+        (
+            "Copy",
+            m.link.callback(|_| Action::CopyCells),
+            true,
+            3,
+        ),
+        (
+            "Paste Table",
+            m.link.callback(|_| Action::PasteCells),
+            true,
+            3,
+        ),
+        (
+            "Copy as Markdown",
+            m.link.callback(|_| Action::CopyAsMarkdown),
+            true,
+            3,
+        ),
+        (
+            "Copy as JSON",
+            m.link.callback(|_| Action::ExportJson(true)),
+            true,
+            3,
+        ),
+        (
+            "Export as JSON...",
+            m.link.callback(|_| Action::ExportJson(false)),
+            true,
+            3,
+        ),
+        (
+            "Copy as Image",
+            m.link.callback(|_| Action::ExportImage(true)),
+            true,
+            3,
+        ),
+        (
+            "Export as PNG",
+            m.link.callback(|_| Action::ExportImage(false)),
+            true,
+            3,
+        ),
+        (
+            "Find Duplicates",
+            m.link.callback(|_| Action::FindDuplicates),
+            true,
+            3,
+        ),
+        (
+            "Goal Seek...",
+            m.link.callback(|_| Action::OpenGoalSeek),
+            true,
+            3,
+        ),
+        (
+            "Import Meta Grammars...",
+            m.link.callback(|_| Action::OpenMetaImport),
+            true,
+            3,
+        ),
+        (
+            "Session Info...",
+            m.link.callback(|_| Action::OpenSessionInfo),
+            true,
+            3,
+        ),
+        (
+            "Calendar View",
+            m.link.callback(|_| Action::OpenCalendar),
+            true,
+            3,
+        ),
+        (
+            "Timeline View",
+            m.link.callback(|_| Action::OpenTimeline),
+            true,
+            3,
+        ),
+        (
+            "Transclude Grid...",
+            m.link.callback(|_| Action::OpenTransclude),
+            true,
+            3,
+        ),
+        (
+            "Bind to File...",
+            m.link.callback(|_| Action::ChooseExternalSource),
+            true,
+            3,
+        ),
+        (
+            "Import from URL...",
+            m.link.callback(|_| Action::OpenImportUrl),
+            true,
+            3,
+        ),
+        (
+            "Export Cell...",
+            m.link.callback(|_| Action::ExportCell),
+            true,
+            3,
+        ),
+        (
+            "Export Dependency Graph...",
+            m.link.callback(|_| Action::ExportDependencyGraph(false)),
+            true,
+            3,
+        ),
+        (
+            "Export Dependency Graph with Nesting...",
+            m.link.callback(|_| Action::ExportDependencyGraph(true)),
+            true,
+            3,
+        ),
+        (
+            "Import Cell...",
+            m.link.callback(|_| Action::ImportCell),
+            true,
+            3,
+        ),
+        (
+            "Import Outline...",
+            m.link.callback(|_| Action::ImportOutline),
+            true,
+            3,
+        ),
+        (
+            "Export JSON Schema...",
+            m.link.callback(|_| Action::ExportSchema),
+            true,
+            3,
+        ),
+        (
+            "New Grid from JSON Schema...",
+            m.link.callback(|_| Action::ImportSchema),
+            true,
+            3,
+        ),
+        (
+            "Expect Shape...",
+            {
+                let active = m.active_cell.clone();
+                m.link.callback(move |_| match active.clone() {
+                    Some(active) => Action::OpenShape(active),
+                    None => Action::Noop,
+                })
+            },
+            true,
+            3,
+        ),
+        (
+            "Visibility Condition...",
+            {
+                let active = m.active_cell.clone();
+                m.link.callback(move |_| match active.clone() {
+                    Some(active) => Action::OpenCondition(active),
+                    None => Action::Noop,
+                })
+            },
+            true,
+            3,
+        ),
+        (
+            "Style Bindings...",
+            {
+                let active = m.active_cell.clone();
+                m.link.callback(move |_| match active.clone() {
+                    Some(active) => Action::OpenBindings(active),
+                    None => Action::Noop,
+                })
+            },
+            true,
+            3,
+        ),
+        (
+            "Button Action...",
+            {
+                let active = m.active_cell.clone();
+                m.link.callback(move |_| match active.clone() {
+                    Some(active) => Action::OpenButtonAction(active),
+                    None => Action::Noop,
+                })
+            },
+            true,
+            3,
+        ),
+        (
+            "Nested Grid Defaults...",
+            {
+                let grid = m.active_cell.clone().and_then(|c| c.parent());
+                m.link.callback(move |_| match grid.clone() {
+                    Some(grid) => Action::OpenNestedDefaults(grid),
+                    None => Action::Noop,
+                })
+            },
+            true,
+            3,
+        ),
+        (
+            "Rename Grammar...",
+            {
+                let active = m.active_cell.clone();
+                m.link.callback(move |_| match active.clone() {
+                    Some(active) => Action::OpenRename(active),
+                    None => Action::Noop,
+                })
+            },
+            false,
+            3,
+        ),
+        (
+            "Start Recording Repro",
+            m.link.callback(|_| Action::StartRecording),
+            false,
+            3,
+        ),
+        (
+            "Export Repro...",
+            m.link.callback(|_| Action::ExportRepro),
+            false,
+            3,
+        ),
+        ("Replay Repro...", m.link.callback(|_| Action::ImportRepro), true, 3),
+        ("Undo", m.link.callback(|_| Action::Undo), false, 3),
+        ("Undo Layout Change", m.link.callback(|_| Action::Undo), false, 3),
+        ("Redo", m.link.callback(|_| Action::Redo), false, 3),
         ("Save", m.link.callback(|_| Action::AskFileName()), true, 3),
+        ("Save a Copy...", m.link.callback(|_| Action::SaveCopy), true, 3),
+        ("Merge Session...", m.link.callback(|_| Action::OpenSessionMerge), true, 3),
+        (
+            "Duplicate Tab",
+            {
+                let current = m.current_session_index;
+                m.link.callback(move |_| Action::DuplicateTab(current))
+            },
+            true,
+            3,
+        ),
         ("Reset", m.link.callback(|_| Action::Recreate), true, 3),
         ("Merge", m.link.callback(|_| Action::MergeCells()), false, 3),
+        (
+            "Create Definition from Selection",
+            {
+                let name = m.default_definition_name.clone();
+                m.link.callback(move |_| Action::DefinitionFromSelection(name.clone()))
+            },
+            false,
+            3,
+        ),
     ];
     /*option Name and action are what their name means
     option_param represents the default or conditionnal render of an option
@@ -1062,9 +5148,35 @@ pub fn view_context_menu(m: &Model) -> Html {
                 //Conditions Manager on the conditional context-menu Option
                 match option_name.clone() {
                     "Merge" => {
-                        if m.last_select_cell != None {
-                            should_render = true;
-                        }
+                        should_render = m.selection.ranges().len() == 1;
+                    }
+                    "Create Definition from Selection" => {
+                        should_render = !m.selection.is_empty();
+                    }
+                    "Fit Selection (Ctrl+Shift+9)" => {
+                        should_render = !m.selection.is_empty();
+                    }
+                    "Undo" => {
+                        should_render = m.history.last().map_or(false, |s| s.kind == EditKind::Content);
+                    }
+                    "Undo Layout Change" => {
+                        should_render = m.history.last().map_or(false, |s| s.kind == EditKind::Layout);
+                    }
+                    "Redo" => {
+                        should_render = m.history.can_redo();
+                    }
+                    "Start Recording Repro" => {
+                        should_render = m.recording.is_none() && m.replay.is_none();
+                    }
+                    "Export Repro..." => {
+                        should_render = m.recording.is_some();
+                    }
+                    "Rename Grammar..." => {
+                        // only the meta grammars suggestions are made from
+                        should_render = m
+                            .active_cell
+                            .as_ref()
+                            .map_or(false, |active| m.meta_suggestions.iter().any(|(_, c)| c == active));
                     }
                     _ => info!("Parameter not managed {:?}", option_name),
                 }
@@ -1081,6 +5193,71 @@ pub fn view_context_menu(m: &Model) -> Html {
         v
     };
 
+    // commands contributed by plugins
+    let plugin_nodes = {
+        let mut v = VList::new();
+        for (index, plugin) in m.plugins.iter().enumerate() {
+            for command in plugin.contribute_commands() {
+                v.add_child(html! {
+                    <li class="context-menu-option" title=plugin.name()
+                        onclick=m.link.callback(move |_| Action::RunPluginCommand(index, command.id))>
+                        { command.label }
+                    </li>
+                });
+            }
+        }
+        v
+    };
+
+    // per-cell spellcheck opt-out
+    let spellcheck_node = match m.active_cell.clone() {
+        Some(active) if m.preferences.spellcheck => {
+            let label = if spellcheck_enabled(m, &active) { "Disable Spellcheck" } else { "Enable Spellcheck" };
+            html! {
+                <li class="context-menu-option" onclick=m.link.callback(move |_| Action::ToggleCellSpellcheck(active.clone()))>
+                    { label }
+                </li>
+            }
+        }
+        _ => html! { <></> },
+    };
+
+    // collapsing the nested grid the active cell is (in) to a summary chip
+    let collapse_node = match m.collapse_target() {
+        Some(grid) => {
+            let label = if m.is_collapsed(&grid) { "Expand Grid" } else { "Collapse Grid" };
+            html! {
+                <li class="context-menu-option" onclick=m.link.callback(move |_| Action::ToggleCollapsedGrid(grid.clone()))>
+                    { label }
+                </li>
+            }
+        }
+        None => html! { <></> },
+    };
+
+    // kind switcher: converts the active cell into any other kind
+    let convert_nodes = {
+        let mut v = VList::new();
+        let current_tag = m
+            .active_cell
+            .clone()
+            .and_then(|c| m.get_session().grammars.get(&c).and_then(|g| g.kind_tag()));
+        if current_tag.is_some() {
+            v.add_child(html! {
+                <li class="context-menu-option">{ "----------" }</li>
+            });
+            for tag in KindTag::ALL.iter().filter(|t| Some(**t) != current_tag) {
+                let tag = *tag;
+                v.add_child(html! {
+                    <li class="context-menu-option" onclick=m.link.callback(move |_| Action::ConvertKind(tag))>
+                        { format!{"Convert to {}", tag.label()} }
+                    </li>
+                });
+            }
+        }
+        v
+    };
+
     let position_style = if let Some((left, top)) = m.context_menu_position {
         format! {"display: block; top: {}px; left: {}px", top, left}
     } else {
@@ -1093,53 +5270,21 @@ pub fn view_context_menu(m: &Model) -> Html {
             class="context-menu" style=position_style>
             <ul class="context-menu-options">
                 {option_nodes}
+                {plugin_nodes}
+                {spellcheck_node}
+                {collapse_node}
+                {convert_nodes}
             </ul>
         </div>
     }
 }
-// util function for determining if one cell's coordinate is within the range of selected cells.
-fn cell_is_selected(
-    coord: &Coordinate,
-    first_select_cell: &Option<Coordinate>,
-    last_select_cell: &Option<Coordinate>,
-) -> bool {
-    let depth = first_select_cell
-        .clone()
-        .map(|c| c.row_cols.len())
-        .unwrap_or(std::usize::MAX);
-    match (
-        first_select_cell
-            .clone()
-            .and_then(|c| c.row_cols.get(depth - 1).cloned()),
-        last_select_cell
-            .clone()
-            .and_then(|c| c.row_cols.get(depth - 1).cloned()),
-    ) {
-        (_, _) if coord.row_cols.len() < depth => false,
-        (Some((first_row, first_col)), Some((last_row, last_col))) => {
-            let current_cell = if coord.row_cols.len() > depth {
-                coord.truncate(depth).unwrap_or(coord.clone())
-            } else {
-                coord.clone()
-            };
-            let row_range = if first_row.get() > last_row.get() {
-                (last_row.get()..=first_row.get())
-            // (a..=b) is shorthand for an integer Range that's inclusive of lower and upper bounds
-            } else {
-                (first_row.get()..=last_row.get())
-            };
-            let col_range = if first_col.get() > last_col.get() {
-                (last_col.get()..=first_col.get())
-            } else {
-                (first_col.get()..=last_col.get())
-            };
-            let parent_cell = current_cell.parent();
-            let parent_check = first_select_cell.clone().unwrap().parent();
-            row_range.contains(&current_cell.row().get())
-                && col_range.contains(&current_cell.col().get()) && parent_cell == parent_check
-        }
-        _ => false,
-    }
+// util function for determining if a cell is hidden underneath a neighbor that spans over it
+fn is_covered_by_span(m: &Model, coord: &Coordinate) -> bool {
+    coord
+        .parent()
+        .and_then(|p| m.get_session().grammars.get(&p))
+        .map(|parent_grammar| parent_grammar.spanned_by(coord.row_col()).is_some())
+        .unwrap_or(false)
 }
 
 fn random_color() -> String {
@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use yew::format::Json;
+use yew::services::storage::StorageService;
+
+// TabView is kept next to the session in ise-core, it's re-exported for the app
+pub use crate::session::TabView;
+use crate::session::Session;
+
+// key under which the workspace is kept in localStorage
+const WORKSPACE_KEY: &str = "ise.workspace";
+
+// Workspace is the set of open tabs, persisted on exit so they can be restored on launch
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Workspace {
+    pub tabs: Vec<WorkspaceTab>,
+    pub active_tab: usize,
+}
+
+// WorkspaceTab is an open tab as it's persisted: how it was viewed, including the file it was
+// saved to, which it's loaded from again. The session itself is only kept when that file
+// doesn't hold it, being new or having unsaved changes.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WorkspaceTab {
+    pub view: TabView,
+    #[serde(default)]
+    pub session: Option<Session>,
+}
+
+impl WorkspaceTab {
+    pub fn of(session: &Session) -> WorkspaceTab {
+        WorkspaceTab {
+            view: session.view.clone(),
+            session: if keeps_session(&session.view) { Some(session.clone()) } else { None },
+        }
+    }
+}
+
+// Whether the session of a tab viewed as `view` has to be kept in the workspace to be restored
+pub fn keeps_session(view: &TabView) -> bool {
+    view.file_path.is_none() || view.dirty
+}
+
+impl Workspace {
+    // `None` if no workspace was stored, or it can't be read anymore
+    pub fn load(storage: &StorageService) -> Option<Workspace> {
+        let Json(workspace) = storage.restore(WORKSPACE_KEY);
+        workspace
+            .ok()
+            .filter(|w: &Workspace| !w.tabs.is_empty() && w.active_tab < w.tabs.len())
+    }
+
+    pub fn store(&self, storage: &mut StorageService) {
+        storage.store(WORKSPACE_KEY, Json(self));
+    }
+}
+
+// Where the tab at `index` ends up once the tab at `from` has been moved to `to`
+pub fn moved_index(index: usize, from: usize, to: usize) -> usize {
+    if index == from {
+        to
+    } else if from < index && index <= to {
+        index - 1
+    } else if to <= index && index < from {
+        index + 1
+    } else {
+        index
+    }
+}
+
+// Where the tab at `index` ends up once the tabs at `closed` have been closed, if it's still open
+pub fn remaining_index(index: usize, closed: &[usize]) -> Option<usize> {
+    if closed.contains(&index) {
+        return None;
+    }
+    Some(index - closed.iter().filter(|c| **c < index).count())
+}
+
+// Title of a new tab that no open tab has: "my session", then "my session 2", ...
+pub fn untitled_title(titles: &[&str]) -> String {
+    let mut title = "my session".to_string();
+    let mut n = 2;
+    while titles.contains(&title.as_str()) {
+        title = format! {"my session {}", n};
+        n += 1;
+    }
+    title
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_session() {
+        let saved = TabView {
+            file_path: Some("/home/me/prices.json".to_string()),
+            ..TabView::default()
+        };
+        assert!(!keeps_session(&saved));
+        assert!(keeps_session(&TabView { dirty: true, ..saved }));
+        assert!(keeps_session(&TabView::default()));
+    }
+
+    #[test]
+    fn test_moved_index() {
+        // moving the first of four tabs to the third place
+        let moved: Vec<usize> = (0..4).map(|i| moved_index(i, 0, 2)).collect();
+        assert_eq!(moved, vec![2, 0, 1, 3]);
+        // and back
+        let moved: Vec<usize> = (0..4).map(|i| moved_index(i, 2, 0)).collect();
+        assert_eq!(moved, vec![1, 2, 0, 3]);
+    }
+
+    #[test]
+    fn test_remaining_index() {
+        let closed = vec![0, 2];
+        let remaining: Vec<Option<usize>> = (0..4).map(|i| remaining_index(i, &closed)).collect();
+        assert_eq!(remaining, vec![None, Some(0), None, Some(1)]);
+    }
+
+    #[test]
+    fn test_untitled_title() {
+        assert_eq!(untitled_title(&["prices"]), "my session");
+        assert_eq!(untitled_title(&["my session", "my session 3"]), "my session 2");
+    }
+}
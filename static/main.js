@@ -1,6 +1,7 @@
-const { app, protocol, BrowserWindow, remote } = require('electron');
-const { readFile } = require("fs");
+const { app, dialog, protocol, shell, BrowserWindow, Notification, remote } = require('electron');
+const { readFile, watch } = require("fs");
 const { extname } = require("path");
+const { exec } = require("child_process");
 const { URL } = require("url");
 
 let win;
@@ -54,16 +55,266 @@ function createProtocol (scheme, normalize = true) {
 }
 
 // IPC: Communication between Electron main.js and Rust src/lib.rs
+// Requests come in as JSON on one versioned channel and are dispatched by their `type` to the
+// handlers below (see src/ipc.rs). Synchronous requests get a reply of
+// { status: "ok", value } or { status: "error", value: message }, and messages for the
+// renderer go out on the matching event channel.
 const { ipcMain } = require('electron');
-ipcMain.on('upload-driver-misc-file', (event, args) => {
-  console.log(args);
+const REQUEST_CHANNEL = 'ise-ipc-v1';
+const EVENT_CHANNEL = 'ise-ipc-v1-event';
+const INVOKE_CHANNEL = 'ise-ipc-v1-invoke';
 
-  driverMiscFiles[args[0]] = args[1];
+function sendEvent(sender, message) {
+  sender.send(EVENT_CHANNEL, JSON.stringify(message));
+}
 
-  // respond with success or failure (true/false)
-  event.returnValue = true;
-});
+// grids bound to CSV/JSON files are reimported when the file changes (see src/external.rs).
+// editors often write a file in several steps, so changes are reported once they settle
+let dataSourceWatchers = {};
+
+// remote CSVs (see `ExternalSource` in core/src/external.rs) are fetched here rather than in the
+// renderer, which CORS would block from most servers. Google Sheets answers its CSV exports with
+// redirects, which are followed up to a limit. Links that need signing in aren't supported: the
+// request is sent without any credentials, and a sign-in page is reported as an error.
+const https = require('https');
+const FETCH_MAX_REDIRECTS = 5;
+const FETCH_TIMEOUT = 30000;
+
+function fetchText(url, redirects = FETCH_MAX_REDIRECTS) {
+  return new Promise((resolve, reject) => {
+    const client = url.startsWith('https:') ? https : http;
+    const req = client.get(url, { timeout: FETCH_TIMEOUT }, res => {
+      const { statusCode, headers } = res;
+      if (statusCode >= 300 && statusCode < 400 && headers.location) {
+        res.resume();
+        if (redirects === 0) {
+          return reject(new Error('too many redirects'));
+        }
+        return resolve(fetchText(new URL(headers.location, url).toString(), redirects - 1));
+      }
+      if (statusCode === 401 || statusCode === 403) {
+        res.resume();
+        return reject(new Error('the link needs signing in, only published or public links can be imported'));
+      }
+      if (statusCode < 200 || statusCode >= 300) {
+        res.resume();
+        return reject(new Error(`HTTP ${statusCode}`));
+      }
+      if ((headers['content-type'] || '').startsWith('text/html')) {
+        res.resume();
+        return reject(new Error('got a web page rather than CSV, the link may need signing in'));
+      }
+      res.setEncoding('utf8');
+      let body = '';
+      res.on('data', chunk => body += chunk);
+      res.on('end', () => resolve(body));
+    });
+    req.on('timeout', () => req.destroy(new Error('timed out')));
+    req.on('error', reject);
+  });
+}
+
+// the automation server (see src/automation.rs): JSON-RPC calls POSTed to localhost with the
+// token from the settings are handed to the renderer, and answered with its 'automation-reply'
+const http = require('http');
+const { timingSafeEqual } = require('crypto');
+let automationServer = null;
+let automationCalls = {};
+let nextAutomationCall = 1;
+// longest request body accepted, and how long the renderer has to answer
+const AUTOMATION_MAX_BODY = 1024 * 1024;
+const AUTOMATION_TIMEOUT = 30000;
+
+function stopAutomation() {
+  if (automationServer) {
+    automationServer.close();
+    automationServer = null;
+  }
+}
+
+function startAutomation(sender, port, token) {
+  const expected = Buffer.from(`Bearer ${token}`);
+  automationServer = http.createServer((req, res) => {
+    const reply = (status, body) => {
+      res.writeHead(status, { 'Content-Type': 'application/json' });
+      res.end(body);
+    };
+    const given = Buffer.from(req.headers['authorization'] || '');
+    if (given.length !== expected.length || !timingSafeEqual(given, expected)) {
+      return reply(401, JSON.stringify({ error: 'invalid token' }));
+    }
+    if (req.method !== 'POST') {
+      return reply(405, JSON.stringify({ error: 'POST a JSON-RPC 2.0 request' }));
+    }
+    let body = '';
+    req.on('data', chunk => {
+      body += chunk;
+      if (body.length > AUTOMATION_MAX_BODY) {
+        reply(413, JSON.stringify({ error: 'request too large' }));
+        req.destroy();
+      }
+    });
+    req.on('end', () => {
+      const id = nextAutomationCall++;
+      const timeout = setTimeout(() => {
+        delete automationCalls[id];
+        reply(504, JSON.stringify({ error: 'the app did not answer' }));
+      }, AUTOMATION_TIMEOUT);
+      automationCalls[id] = response => {
+        clearTimeout(timeout);
+        reply(200, response);
+      };
+      sendEvent(sender, { type: 'automation-call', id, body });
+    });
+  });
+  automationServer.on('error', e => console.log(`automation server: ${e.message}`));
+  automationServer.listen(port, '127.0.0.1');
+}
+
+const handlers = {
+  'upload-driver-misc-file': (event, { name, data }) => {
+    driverMiscFiles[name] = { content: Buffer.from(data, 'base64') };
+    return true;
+  },
+
+  // custom dictionary words from the user's preferences, which the spellchecker shouldn't flag.
+  // adding words to the spellchecker needs electron >= 8, older versions just ignore them
+  'set-spellcheck-dictionary': (event, { words }) => {
+    const spellcheckSession = event.sender.session;
+    if (typeof spellcheckSession.addWordToSpellCheckerDictionary !== 'function') {
+      return false;
+    }
+    words.forEach(word => spellcheckSession.addWordToSpellCheckerDictionary(word));
+    return true;
+  },
+
+  'watch-data-source': (event, { path }) => {
+    if (!dataSourceWatchers[path]) {
+      let timeout = null;
+      dataSourceWatchers[path] = watch(path, { persistent: false }, () => {
+        clearTimeout(timeout);
+        timeout = setTimeout(() => sendEvent(event.sender, { type: 'data-source-changed', path }), 100);
+      });
+    }
+    return true;
+  },
+
+  'unwatch-data-source': (event, { path }) => {
+    if (dataSourceWatchers[path]) {
+      dataSourceWatchers[path].close();
+      delete dataSourceWatchers[path];
+    }
+    return true;
+  },
+
+  // native open/save dialogs, replying with the chosen path (or null if cancelled)
+  'choose-file': (event, { save, filter_name, extensions }) => {
+    const filters = [{ name: filter_name, extensions }];
+    if (save) {
+      return dialog.showSaveDialogSync(win, { filters }) || null;
+    }
+    const paths = dialog.showOpenDialogSync(win, { properties: ['openFile'], filters });
+    return paths ? paths[0] : null;
+  },
+
+  // shell command cells (see src/shell.rs) run their commands here, once the user has allowed
+  // them in the renderer. The output is sent back when the command exits
+  'run-shell': (event, { coord, command }) => {
+    exec(command, { cwd: app.getPath('home'), timeout: 60000 }, (error, stdout, stderr) => {
+      sendEvent(event.sender, {
+        type: 'shell-finished',
+        coord,
+        succeeded: !error,
+        output: error ? (stderr || error.message) : stdout,
+      });
+    });
+    return null;
+  },
+
+  // the renderer is ready to open files, starting with the ones opened before it was
+  'take-opened-files': () => {
+    rendererReady = true;
+    return pendingFiles.splice(0);
+  },
+
+  'reveal-file': (event, { path }) => {
+    shell.showItemInFolder(path);
+    return true;
+  },
+
+  // reminder cells (see core/src/reminders.rs) that fire, clicking the notification brings the
+  // window back
+  'show-notification': (event, { title, body }) => {
+    if (!Notification.isSupported()) {
+      return false;
+    }
+    const notification = new Notification({ title, body });
+    notification.on('click', () => {
+      if (win) {
+        win.show();
+        win.focus();
+      }
+    });
+    notification.show();
+    return true;
+  },
+
+  'configure-automation': (event, { enabled, port, token }) => {
+    stopAutomation();
+    if (enabled) {
+      startAutomation(event.sender, port, token);
+    }
+    return true;
+  },
+
+  'automation-reply': (event, { id, response }) => {
+    const respond = automationCalls[id];
+    delete automationCalls[id];
+    if (respond) {
+      respond(response);
+    }
+    return null;
+  },
+
+  // replies with the text at the URL once it's fetched, so it's only answered to `invoke`
+  'fetch-url': (event, { url }) => {
+    if (!/^https?:\/\//.test(url)) {
+      throw new Error(`${url} isn't an http(s) URL`);
+    }
+    return fetchText(url);
+  },
+
+  'quit-confirmed': () => {
+    quitConfirmed = true;
+    app.quit();
+    return true;
+  },
+};
+
+function dispatch(event, message) {
+  const failed = e => JSON.stringify({ status: 'error', value: String(e && e.message || e) });
+  try {
+    const request = JSON.parse(message);
+    const handler = handlers[request.type];
+    if (!handler) {
+      throw new Error(`unknown request ${request.type}`);
+    }
+    const value = handler(event, request);
+    // handlers that answer later return a promise, which `invoke` waits for
+    if (value instanceof Promise) {
+      return value.then(value => JSON.stringify({ status: 'ok', value }), failed);
+    }
+    return JSON.stringify({ status: 'ok', value });
+  } catch (e) {
+    return failed(e);
+  }
+}
 
+// the reply is ignored for requests that were sent asynchronously
+ipcMain.on(REQUEST_CHANNEL, (event, message) => {
+  event.returnValue = dispatch(event, message);
+});
+ipcMain.handle(INVOKE_CHANNEL, (event, message) => dispatch(event, message));
 
 // standard scheme must be registered before the app is ready
 // https://gist.github.com/dbkr/e898624be6d53590ebf494521d868fec
@@ -72,6 +323,54 @@ protocol.registerSchemesAsPrivileged([{
     privileges: { standard: true, secure: true, supportFetchAPI: true },
 }]);
 
+// Quitting (or closing the window) first asks the renderer, which prompts about unsaved
+// changes and replies with 'quit-confirmed' once it's fine to go
+let quitConfirmed = false;
+
+function requestQuit(event) {
+  if (!quitConfirmed && win !== null) {
+    event.preventDefault();
+    sendEvent(win.webContents, { type: 'confirm-quit' });
+  }
+}
+
+app.on('before-quit', requestQuit);
+
+// Session files opened from the OS: double-clicked (`open-file` on macOS), passed on the command
+// line, or to a second instance, which hands them to this one and exits. They're held until the
+// renderer asks for them once it's ready, and opened in new tabs (see `Event::OpenFile` in src/ipc.rs)
+let pendingFiles = process.argv.slice(1).filter(isSessionFile);
+let rendererReady = false;
+
+function isSessionFile(arg) {
+  return arg.toLowerCase().endsWith('.json') && !arg.startsWith('-');
+}
+
+function openFile(path) {
+  if (rendererReady && win !== null) {
+    sendEvent(win.webContents, { type: 'open-file', path });
+    if (win.isMinimized()) {
+      win.restore();
+    }
+    win.focus();
+  } else {
+    pendingFiles.push(path);
+  }
+}
+
+app.on('open-file', (event, path) => {
+  event.preventDefault();
+  openFile(path);
+});
+
+if (!app.requestSingleInstanceLock()) {
+  app.quit();
+} else {
+  app.on('second-instance', (event, argv) => {
+    argv.slice(1).filter(isSessionFile).forEach(openFile);
+  });
+}
+
 function createWindow () {
   win = new BrowserWindow({
     webPreferences: {
@@ -81,12 +380,17 @@ function createWindow () {
     width: 1600,
   });
   win.loadURL(`file://${__dirname}/index.html`);
+  win.webContents.on('did-start-loading', () => {
+    rendererReady = false;
+  });
   win.webContents.openDevTools(); // TODO: only do this in development mode
   win.once('ready-to-show', () => {
     win.show();
     win.maximize();
   });
+  win.on('close', requestQuit);
   win.on('closed', () => {
+    rendererReady = false;
     win = null; // dereference window object
   });
 }